@@ -0,0 +1,200 @@
+//! Flashcard export for Anki.
+//!
+//! Writes the cards `flashcards::extract_cards` finds as an Anki text import:
+//! a tab-separated file whose header lines tell Anki to treat the fields as
+//! HTML for the Basic note type, and which columns hold the deck, tags and a
+//! stable GUID, so importing again updates cards instead of duplicating them.
+//! Notes in folders go into subdecks named after the folder. Images the cards
+//! embed are copied into a `<name>.media/` folder beside the file, to be copied
+//! into Anki's `collection.media` folder.
+//!
+//! Anki's own `.apkg` package is a zipped SQLite collection, which would take
+//! two more dependencies to write; the text import carries the same cards.
+
+use crate::{export, flashcards, render, tags, ScannedNote};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const DEFAULT_DECK: &str = "Scratch";
+const HEADER: &str =
+    "#separator:tab\n#html:true\n#notetype:Basic\n#deck column:3\n#tags column:4\n#guid column:5\n";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnkiScope {
+    pub folder: Option<String>, // only notes under this folder
+    pub tag: Option<String>,    // only notes carrying this tag
+    pub note_ids: Vec<String>,  // only these notes (empty = all)
+    pub deck: Option<String>,   // top-level deck, default "Scratch"
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnkiExport {
+    pub path: String,
+    pub cards: usize,
+    pub notes: usize, // notes the cards came from
+    pub media_dir: Option<String>,
+    pub media_copied: usize,
+}
+
+impl AnkiScope {
+    fn selects(&self, note: &ScannedNote) -> bool {
+        if !self.note_ids.is_empty() && !self.note_ids.contains(&note.id) {
+            return false;
+        }
+        if let Some(folder) = self.folder.as_deref().map(|f| f.trim_matches('/')) {
+            if !folder.is_empty() && !note.id.starts_with(&format!("{}/", folder)) {
+                return false;
+            }
+        }
+        match self.tag {
+            Some(ref tag) => tags::extract_tags(&note.content).contains(&tags::normalize(tag)),
+            None => true,
+        }
+    }
+}
+
+fn img_src_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"(<img\b[^>]*?\bsrc=")([^"]+)(")"#).unwrap())
+}
+
+/// Deck for a note: the top-level deck, then one subdeck per folder.
+fn deck_for(root_deck: &str, note_id: &str) -> String {
+    match note_id.rsplit_once('/') {
+        Some((folder, _)) => format!("{}::{}", root_deck, folder.replace('/', "::")),
+        None => root_deck.to_string(),
+    }
+}
+
+/// Anki tags can't hold spaces; nested tags use `::`.
+fn anki_tag(tag: &str) -> String {
+    tag.replace('/', "::").replace(char::is_whitespace, "_")
+}
+
+/// One field of the tab-separated file: on one line, quoted if it has quotes.
+fn field(text: &str) -> String {
+    let flat = text.replace(['\t', '\r'], " ").replace('\n', " ");
+    if flat.contains('"') {
+        format!("\"{}\"", flat.replace('"', "\"\""))
+    } else {
+        flat
+    }
+}
+
+/// Vault-relative path of an image a card in `note_id` embeds, if it is a
+/// file in the vault.
+fn image_path(notes_root: &Path, note_id: &str, src: &str) -> Option<String> {
+    let src = urlencoding::decode(src).ok()?;
+    let candidates = match src.rfind("assets/") {
+        Some(pos) => vec![src[pos..].to_string()],
+        None if src.contains("://") => return None,
+        None => {
+            let relative = note_id
+                .rsplit_once('/')
+                .map(|(dir, _)| format!("{}/{}", dir, src));
+            relative.into_iter().chain([src.to_string()]).collect()
+        }
+    };
+    candidates.into_iter().find(|rel| {
+        crate::assets::resolve(notes_root, rel)
+            .map(|path| path.is_file())
+            .unwrap_or(false)
+    })
+}
+
+/// Copies card images into the media folder under flat, unique names.
+struct Media {
+    dir: PathBuf,
+    names: HashMap<String, String>, // vault path -> media file name
+}
+
+impl Media {
+    fn name_for(&mut self, notes_root: &Path, rel: &str) -> Option<String> {
+        if let Some(name) = self.names.get(rel) {
+            return Some(name.clone());
+        }
+        std::fs::create_dir_all(&self.dir).ok()?;
+        let leaf = rel.rsplit('/').next().unwrap_or(rel);
+        let name = crate::assets::unique_name(&self.dir, leaf);
+        std::fs::copy(notes_root.join(rel), self.dir.join(&name)).ok()?;
+        self.names.insert(rel.to_string(), name.clone());
+        Some(name)
+    }
+
+    /// Render card markdown to HTML, pointing images at their media copies.
+    fn render(&mut self, notes_root: &Path, note_id: &str, markdown: &str) -> String {
+        let html = render::render_body(markdown);
+        img_src_re()
+            .replace_all(&html, |cap: &regex::Captures| {
+                let src = image_path(notes_root, note_id, &cap[2])
+                    .and_then(|rel| self.name_for(notes_root, &rel))
+                    .unwrap_or_else(|| cap[2].to_string());
+                format!("{}{}{}", &cap[1], src, &cap[3])
+            })
+            .into_owned()
+    }
+}
+
+/// Write the cards in `notes` that `scope` selects to `path`, an absolute
+/// file path outside the vault. Blocking; call from spawn_blocking.
+pub fn export(
+    notes: &[ScannedNote],
+    scope: &AnkiScope,
+    path: &str,
+    notes_root: &Path,
+) -> Result<AnkiExport, String> {
+    let path = PathBuf::from(path.trim());
+    let (Some(parent), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return Err("Export path must be a file path".to_string());
+    };
+    let out_dir = export::validate_output_dir(&parent.to_string_lossy(), notes_root)?;
+    let path = out_dir.join(path.file_name().unwrap_or(stem));
+    let mut media = Media {
+        dir: out_dir.join(format!("{}.media", stem.to_string_lossy())),
+        names: HashMap::new(),
+    };
+    let root_deck = scope
+        .deck
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .unwrap_or(DEFAULT_DECK);
+
+    let mut out = String::from(HEADER);
+    let (mut cards, mut source_notes) = (0, 0);
+    for note in notes.iter().filter(|n| scope.selects(n)) {
+        let note_cards = flashcards::extract_cards(&note.id, &note.content);
+        if note_cards.is_empty() {
+            continue;
+        }
+        source_notes += 1;
+        let deck = deck_for(root_deck, &note.id);
+        let note_tags: Vec<String> = tags::extract_tags(&note.content)
+            .iter()
+            .map(|t| anki_tag(t))
+            .collect();
+        for card in note_cards {
+            let front = media.render(notes_root, &note.id, &card.question);
+            let back = media.render(notes_root, &note.id, &card.answer);
+            let row = [front, back, deck.clone(), note_tags.join(" "), card.id];
+            let row: Vec<String> = row.iter().map(|f| field(f)).collect();
+            out.push_str(&row.join("\t"));
+            out.push('\n');
+            cards += 1;
+        }
+    }
+
+    std::fs::write(&path, out).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(AnkiExport {
+        path: path.to_string_lossy().into_owned(),
+        cards,
+        notes: source_notes,
+        media_copied: media.names.len(),
+        media_dir: (!media.names.is_empty()).then(|| media.dir.to_string_lossy().into_owned()),
+    })
+}
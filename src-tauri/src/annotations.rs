@@ -0,0 +1,142 @@
+//! Per-note reading positions and highlight annotations.
+//!
+//! Stored in `.scratch/annotations.json`, keyed by note ID, so note files stay
+//! untouched. Ranges are character offsets into the note content; the
+//! highlighted text is kept alongside so the frontend can re-anchor a range
+//! after the note has been edited.
+
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+const STORE_FILE: &str = "annotations.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingPosition {
+    pub offset: usize,     // character offset of the top visible line
+    pub scroll_ratio: f64, // 0.0-1.0, fallback when the offset no longer fits
+    pub updated: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    pub id: String,
+    pub start: usize,
+    pub end: usize,
+    pub quote: String,
+    pub comment: Option<String>,
+    pub created: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteAnnotations {
+    pub reading_position: Option<ReadingPosition>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+impl NoteAnnotations {
+    fn is_empty(&self) -> bool {
+        self.reading_position.is_none() && self.annotations.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnnotationStore {
+    pub notes: HashMap<String, NoteAnnotations>,
+}
+
+impl AnnotationStore {
+    pub fn load(notes_folder: &str) -> Self {
+        store::load(Path::new(notes_folder), STORE_FILE)
+    }
+
+    pub fn save(&self, notes_folder: &str) -> Result<()> {
+        store::save(Path::new(notes_folder), STORE_FILE, self)
+    }
+
+    pub fn get(&self, note_id: &str) -> NoteAnnotations {
+        self.notes.get(note_id).cloned().unwrap_or_default()
+    }
+
+    pub fn set_reading_position(&mut self, note_id: &str, offset: usize, scroll_ratio: f64) {
+        self.notes.entry(note_id.to_string()).or_default().reading_position = Some(ReadingPosition {
+            offset,
+            scroll_ratio: scroll_ratio.clamp(0.0, 1.0),
+            updated: chrono::Utc::now().timestamp(),
+        });
+    }
+
+    /// Add a highlight over `start..end` (character offsets) of `content`.
+    pub fn add(
+        &mut self,
+        note_id: &str,
+        content: &str,
+        start: usize,
+        end: usize,
+        comment: Option<String>,
+    ) -> Result<Annotation, String> {
+        if start >= end {
+            return Err("Annotation range is empty".to_string());
+        }
+        let quote: String = content.chars().skip(start).take(end - start).collect();
+        if quote.chars().count() != end - start {
+            return Err("Annotation range is outside the note".to_string());
+        }
+
+        let entry = self.notes.entry(note_id.to_string()).or_default();
+        let created = chrono::Utc::now().timestamp_millis();
+        let mut id = format!("{:x}", created);
+        let mut counter = 1;
+        while entry.annotations.iter().any(|a| a.id == id) {
+            id = format!("{:x}-{}", created, counter);
+            counter += 1;
+        }
+
+        let annotation = Annotation {
+            id,
+            start,
+            end,
+            quote,
+            comment: comment.filter(|c| !c.trim().is_empty()),
+            created: created / 1000,
+        };
+        entry.annotations.push(annotation.clone());
+        entry.annotations.sort_by_key(|a| (a.start, a.end));
+        Ok(annotation)
+    }
+
+    /// Remove an annotation. Returns false if it did not exist.
+    pub fn remove(&mut self, note_id: &str, annotation_id: &str) -> bool {
+        let Some(entry) = self.notes.get_mut(note_id) else {
+            return false;
+        };
+        let before = entry.annotations.len();
+        entry.annotations.retain(|a| a.id != annotation_id);
+        let removed = entry.annotations.len() != before;
+        if entry.is_empty() {
+            self.notes.remove(note_id);
+        }
+        removed
+    }
+
+    /// Carry a note's data over to its new ID after a rename or move.
+    pub fn rename_note(&mut self, old_id: &str, new_id: &str) -> bool {
+        match self.notes.remove(old_id) {
+            Some(data) => {
+                self.notes.insert(new_id.to_string(), data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_note(&mut self, note_id: &str) -> bool {
+        self.notes.remove(note_id).is_some()
+    }
+}
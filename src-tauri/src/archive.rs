@@ -0,0 +1,346 @@
+//! Archived copies of the web pages notes point to, so references survive
+//! dead links.
+//!
+//! A bookmark or clipped note names its page in a `url` or `source`
+//! frontmatter key. `archive_url` saves the page, its stylesheets and its
+//! images as one MHTML file in `assets/archives/`, which browsers open
+//! offline, and the note records it as `archive: <path>` and `archived:
+//! <date>`. With the `archiveLinks` setting, a note that names a page and has
+//! no archive yet is archived in the background when it is saved or clipped.
+
+use crate::{assets, clipboard, frontmatter, write_note_content, AppState, ScratchError};
+use base64::Engine;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+
+pub const ARCHIVE_FOLDER: &str = "assets/archives";
+const MAX_PAGE_BYTES: usize = 10 * 1024 * 1024;
+const MAX_RESOURCE_BYTES: usize = 5 * 1024 * 1024;
+const MAX_RESOURCES: usize = 60; // stylesheets and images per page
+const TIMEOUT: Duration = Duration::from_secs(30);
+const RETRY_AFTER: Duration = Duration::from_secs(60 * 60); // between background archives of a note
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedPage {
+    pub url: String,
+    pub path: String, // vault-relative
+    pub title: Option<String>,
+    pub resources: usize, // stylesheets and images saved with the page
+    pub bytes: u64,
+}
+
+/// The page a note is about: its `url` or `source` frontmatter, if http(s).
+pub fn page_url(content: &str) -> Option<String> {
+    let meta = frontmatter::Frontmatter::parse(content);
+    ["url", "source"]
+        .into_iter()
+        .filter_map(|key| meta.get(key))
+        .map(str::trim)
+        .find(|value| value.starts_with("https://") || value.starts_with("http://"))
+        .map(String::from)
+}
+
+/// Whether a note names a page and has no archive of it yet.
+pub fn needs_archive(content: &str) -> bool {
+    page_url(content).is_some()
+        && frontmatter::Frontmatter::parse(content)
+            .get("archive")
+            .is_none()
+}
+
+fn parse_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|_| format!("Invalid URL: {}", url.trim()))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(parsed),
+        _ => Err("Only http and https pages can be archived".to_string()),
+    }
+}
+
+struct Fetched {
+    url: Url, // after redirects
+    content_type: String,
+    body: Vec<u8>,
+}
+
+async fn fetch(client: &reqwest::Client, url: &Url, limit: usize) -> Result<Fetched, String> {
+    let response = client
+        .get(url.as_str())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("{} returned {}", url, status));
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len as usize > limit)
+    {
+        return Err(format!("{} is too large to archive", url));
+    }
+    let final_url = response.url().clone();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    if body.len() > limit {
+        return Err(format!("{} is too large to archive", url));
+    }
+    Ok(Fetched {
+        url: final_url,
+        content_type,
+        body: body.to_vec(),
+    })
+}
+
+fn tag_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?is)<(img|link)\b[^>]*>").unwrap())
+}
+
+/// Stylesheets and images the page loads, resolved against `base`.
+fn resource_urls(html: &str, base: &Url) -> Vec<Url> {
+    let mut urls: Vec<Url> = Vec::new();
+    for cap in tag_re().captures_iter(html) {
+        let tag = &cap[0];
+        let href = if cap[1].eq_ignore_ascii_case("img") {
+            clipboard::attr(tag, "src")
+        } else {
+            let rel = clipboard::attr(tag, "rel").unwrap_or_default();
+            if !rel.to_ascii_lowercase().contains("stylesheet") {
+                continue;
+            }
+            clipboard::attr(tag, "href")
+        };
+        let Some(url) = href.and_then(|href| base.join(href.trim()).ok()) else {
+            continue;
+        };
+        if matches!(url.scheme(), "http" | "https") && !urls.contains(&url) {
+            urls.push(url);
+        }
+        if urls.len() == MAX_RESOURCES {
+            break;
+        }
+    }
+    urls
+}
+
+fn page_title(html: &str) -> Option<String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"(?is)<title\b[^>]*>(.*?)</title>").unwrap());
+    let title = clipboard::strip_tags(re.captures(html)?.get(1)?.as_str());
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// A header value, RFC 2047-encoded when it isn't plain ASCII.
+fn header_text(text: &str) -> String {
+    if text.is_ascii() && !text.contains(['\r', '\n']) {
+        text.to_string()
+    } else {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(text.as_bytes());
+        format!("=?utf-8?B?{}?=", encoded)
+    }
+}
+
+/// The page and its resources as a multipart/related MHTML document.
+fn mhtml(page: &Fetched, title: Option<&str>, resources: &[Fetched]) -> Vec<u8> {
+    let boundary = format!(
+        "----MultipartBoundary--scratch-{}----",
+        chrono::Utc::now().timestamp_millis()
+    );
+    let mut out = String::new();
+    out.push_str("From: <Saved by Scratch>\r\n");
+    out.push_str(&format!("Snapshot-Content-Location: {}\r\n", page.url));
+    out.push_str(&format!(
+        "Subject: {}\r\n",
+        header_text(title.unwrap_or(page.url.as_str()))
+    ));
+    out.push_str(&format!("Date: {}\r\n", chrono::Utc::now().to_rfc2822()));
+    out.push_str("MIME-Version: 1.0\r\n");
+    out.push_str(&format!(
+        "Content-Type: multipart/related;\r\n\ttype=\"text/html\";\r\n\tboundary=\"{}\"\r\n\r\n",
+        boundary
+    ));
+    for part in std::iter::once(page).chain(resources) {
+        out.push_str(&format!("--{}\r\n", boundary));
+        out.push_str(&format!("Content-Type: {}\r\n", part.content_type));
+        out.push_str("Content-Transfer-Encoding: base64\r\n");
+        out.push_str(&format!("Content-Location: {}\r\n\r\n", part.url));
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&part.body);
+        for line in encoded.as_bytes().chunks(76) {
+            out.push_str(std::str::from_utf8(line).unwrap_or_default());
+            out.push_str("\r\n");
+        }
+        out.push_str("\r\n");
+    }
+    out.push_str(&format!("--{}--\r\n", boundary));
+    out.into_bytes()
+}
+
+/// File name for an archive of `url`: its host and the time.
+fn archive_name(url: &Url) -> String {
+    let host = url.host_str().unwrap_or("page").trim_start_matches("www.");
+    format!(
+        "{}-{}.mhtml",
+        crate::sanitize_filename(host),
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    )
+}
+
+/// Fetch `url` with its stylesheets and images and save it under
+/// `assets/archives/`. Resources that fail to load are left out.
+pub async fn archive_url(notes_root: &Path, url: &str) -> Result<ArchivedPage, String> {
+    let url = parse_url(url)?;
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .user_agent(concat!("Scratch/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let page = fetch(&client, &url, MAX_PAGE_BYTES).await?;
+    let html = String::from_utf8_lossy(&page.body).into_owned();
+    let title = page_title(&html);
+    let mut resources = Vec::new();
+    if page.content_type.to_ascii_lowercase().contains("html") {
+        for resource in resource_urls(&html, &page.url) {
+            if let Ok(fetched) = fetch(&client, &resource, MAX_RESOURCE_BYTES).await {
+                resources.push(fetched);
+            }
+        }
+    }
+    let archive = mhtml(&page, title.as_deref(), &resources);
+
+    let dir: PathBuf = notes_root.join(ARCHIVE_FOLDER);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", ARCHIVE_FOLDER, e))?;
+    let name = assets::unique_name(&dir, &archive_name(&url));
+    tokio::fs::write(dir.join(&name), &archive)
+        .await
+        .map_err(|e| format!("Failed to write archive: {}", e))?;
+
+    Ok(ArchivedPage {
+        url: url.to_string(),
+        path: assets::join(ARCHIVE_FOLDER, &name),
+        title,
+        resources: resources.len(),
+        bytes: archive.len() as u64,
+    })
+}
+
+/// Record an archive in note `id`'s frontmatter.
+pub async fn record(
+    state: &AppState,
+    notes_root: &Path,
+    id: &str,
+    page: &ArchivedPage,
+) -> Result<(), ScratchError> {
+    let _guard = state.note_locks.lock(notes_root, &[id]).await;
+    let path = crate::abs_path_from_id(notes_root, id)?;
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, id))?;
+    let updated = frontmatter::set(
+        &content,
+        "archive",
+        frontmatter::Value::Scalar(page.path.clone()),
+    );
+    let updated = frontmatter::set(
+        &updated,
+        "archived",
+        frontmatter::Value::Scalar(chrono::Local::now().format("%Y-%m-%d").to_string()),
+    );
+    write_note_content(state, notes_root, id, updated).await?;
+    Ok(())
+}
+
+/// Background archives by note: running (None) or finished at a time. A
+/// note saved again before its new frontmatter reaches the editor would
+/// otherwise be archived on every save.
+fn attempts() -> &'static Mutex<HashMap<String, Option<Instant>>> {
+    static ATTEMPTS: OnceLock<Mutex<HashMap<String, Option<Instant>>>> = OnceLock::new();
+    ATTEMPTS.get_or_init(Default::default)
+}
+
+/// Archive the page of note `id` in the background if the `archiveLinks`
+/// setting is on and `content` names a page without an archive. Emits
+/// `note-archived` or `note-archive-failed`.
+pub fn archive_in_background(app: &AppHandle, id: &str, content: &str) {
+    let state = app.state::<AppState>();
+    let enabled = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .archive_links
+        .unwrap_or(false);
+    if !enabled || !needs_archive(content) {
+        return;
+    }
+    let Some(url) = page_url(content) else {
+        return;
+    };
+    let Some(folder) = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone()
+    else {
+        return;
+    };
+    {
+        let mut attempts = attempts().lock().expect("archive attempts mutex");
+        match attempts.get(id) {
+            Some(None) => return,
+            Some(Some(finished)) if finished.elapsed() < RETRY_AFTER => return,
+            _ => {}
+        }
+        attempts.insert(id.to_string(), None);
+    }
+
+    let app = app.clone();
+    let id = id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let root = PathBuf::from(&folder);
+        let result = match archive_url(&root, &url).await {
+            Ok(page) => record(&state, &root, &id, &page)
+                .await
+                .map(|_| page)
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        attempts()
+            .lock()
+            .expect("archive attempts mutex")
+            .insert(id.clone(), Some(Instant::now()));
+        match result {
+            Ok(page) => {
+                let _ = app.emit(
+                    "note-archived",
+                    serde_json::json!({ "id": id, "archive": page }),
+                );
+            }
+            Err(e) => {
+                let _ = app.emit(
+                    "note-archive-failed",
+                    serde_json::json!({ "id": id, "error": e }),
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,318 @@
+//! Attachments (images and other files notes embed).
+//!
+//! Where new attachments go is set by `assetLayout`: a flat `assets/` folder,
+//! a subfolder per note (`assets/<note-id>/`), or the note's own folder. Asset
+//! paths are vault-relative throughout; notes may spell them vault-relative,
+//! note-relative, or as the absolute (often percent-encoded) path inside an
+//! asset URL.
+//!
+//! An attachment no note references is orphaned, unless a metadata sidecar
+//! describes it (see `sidecars`). Orphans are found by checking every note's
+//! text for the asset's path in any of those spellings, and are moved to the
+//! trash rather than deleted.
+
+use crate::{links, sidecars, ScannedNote};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const ASSETS_DIR: &str = "assets";
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "svg", "bmp", "tiff", "tif", "ico", "avif",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetLayout {
+    #[default]
+    Flat, // assets/
+    PerNote,    // assets/<note-id>/
+    NoteFolder, // next to the note
+}
+
+/// Vault-relative folder new attachments of `note_id` go in ("" for the vault
+/// root). Without a note, attachments go in `assets/`.
+pub fn folder_for(layout: AssetLayout, note_id: Option<&str>) -> String {
+    match (layout, note_id) {
+        (AssetLayout::PerNote, Some(id)) => format!("assets/{}", id),
+        (AssetLayout::NoteFolder, Some(id)) => note_folder(id).unwrap_or_default().to_string(),
+        _ => "assets".to_string(),
+    }
+}
+
+pub fn join(folder: &str, name: &str) -> String {
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", folder, name)
+    }
+}
+
+/// Resolve a vault-relative attachment path, rejecting traversal and the
+/// app's own dot-folders.
+pub fn resolve(notes_root: &Path, rel: &str) -> Result<PathBuf, String> {
+    if rel.is_empty()
+        || rel.contains('\\')
+        || rel.starts_with('/')
+        || rel.split('/').any(|part| part.is_empty() || part.starts_with('.'))
+    {
+        return Err("Invalid asset path".to_string());
+    }
+    Ok(notes_root.join(rel))
+}
+
+fn note_folder(note_id: &str) -> Option<&str> {
+    note_id.rsplit_once('/').map(|(dir, _)| dir)
+}
+
+/// Pick a free file name in `dir` for `name`, appending `-1`, `-2`, ... to the
+/// stem on collision.
+pub fn unique_name(dir: &Path, name: &str) -> String {
+    if !dir.join(name).exists() {
+        return name.to_string();
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], &name[pos..]),
+        _ => (name, ""),
+    };
+    let mut counter = 1;
+    loop {
+        let candidate = format!("{}-{}{}", stem, counter, ext);
+        if !dir.join(&candidate).exists() {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// What may precede a path for a match to count.
+#[derive(Clone, Copy)]
+enum Start {
+    Any,       // absolute paths are specific enough on their own
+    Separator, // standalone, or the tail of a longer path
+    Standalone,
+}
+
+/// Whether the match at `start..end` is a whole path rather than part of a
+/// longer name, so renaming `a.png` leaves `a.png.bak` and `ba.png` alone.
+fn bounded(haystack: &str, start: usize, end: usize, rule: Start) -> bool {
+    let ends = match haystack[end..].chars().next() {
+        Some(c) => c.is_whitespace() || ")]>\"'?#|".contains(c),
+        None => true,
+    };
+    let before = &haystack[..start];
+    let standalone = match before.chars().next_back() {
+        Some(c) => c.is_whitespace() || "([<\"'=".contains(c),
+        None => true,
+    };
+    let starts = match rule {
+        Start::Any => true,
+        Start::Separator => standalone || before.ends_with('/') || before.ends_with("%2F"),
+        Start::Standalone => standalone,
+    };
+    ends && starts
+}
+
+fn replace_bounded(haystack: &str, needle: &str, replacement: &str, rule: Start) -> String {
+    let mut out = String::with_capacity(haystack.len());
+    let mut last = 0;
+    for (start, _) in haystack.match_indices(needle) {
+        let end = start + needle.len();
+        if bounded(haystack, start, end, rule) {
+            out.push_str(&haystack[last..start]);
+            out.push_str(replacement);
+            last = end;
+        }
+    }
+    out.push_str(&haystack[last..]);
+    out
+}
+
+/// A path as written raw, URL-encoded with slashes kept, and fully encoded
+/// (as asset URLs embed absolute paths). Always three entries, so the
+/// spellings of an old and a new path line up.
+fn spellings(path: &str) -> [String; 3] {
+    let encoded = urlencoding::encode(path).into_owned();
+    [path.to_string(), encoded.replace("%2F", "/"), encoded]
+}
+
+/// The relative spellings a note could use for the vault-relative `path`.
+fn relative_forms(note_id: &str, path: &str) -> Vec<String> {
+    let mut forms = vec![path.to_string()];
+    if let Some(rest) = note_folder(note_id)
+        .and_then(|dir| path.strip_prefix(dir))
+        .and_then(|rest| rest.strip_prefix('/'))
+    {
+        forms.push(rest.to_string());
+    }
+    forms
+}
+
+fn absolute(notes_root: &Path, path: &str) -> String {
+    notes_root.join(path).to_string_lossy().into_owned()
+}
+
+/// Whether a note references the vault-relative asset `path`. Errs towards
+/// yes, so garbage collection never takes a file still in use.
+pub fn references(note_id: &str, content: &str, path: &str) -> bool {
+    relative_forms(note_id, path).iter().flat_map(|form| spellings(form)).any(|needle| {
+        content
+            .match_indices(needle.as_str())
+            .any(|(start, _)| bounded(content, start, start + needle.len(), Start::Separator))
+    })
+}
+
+/// Rewrite a note's references to asset `old` as `new` (both vault-relative),
+/// keeping each reference's spelling. Returns None if nothing changed.
+pub fn rewrite_references(
+    notes_root: &Path,
+    note_id: &str,
+    content: &str,
+    old: &str,
+    new: &str,
+) -> Option<String> {
+    let mut updated = content.to_string();
+    let absolute_pair = (absolute(notes_root, old), absolute(notes_root, new));
+    for (from, to) in spellings(&absolute_pair.0).into_iter().zip(spellings(&absolute_pair.1)) {
+        updated = replace_bounded(&updated, &from, &to, Start::Any);
+    }
+    for (old_form, new_form) in relative_forms(note_id, old).into_iter().zip(relative_forms(note_id, new)) {
+        for (from, to) in spellings(&old_form).into_iter().zip(spellings(&new_form)) {
+            updated = replace_bounded(&updated, &from, &to, Start::Standalone);
+        }
+    }
+    (updated != content).then_some(updated)
+}
+
+/// Vault-relative paths of the attachments a note references: anything under
+/// `assets/`, plus files beside the note under the note-folder layout.
+pub fn referenced(notes_root: &Path, note_id: &str, content: &str, layout: AssetLayout) -> Vec<String> {
+    let mut paths: Vec<String> = links::extract_asset_refs(content)
+        .into_iter()
+        .map(|name| format!("assets/{}", name))
+        .collect();
+    if layout == AssetLayout::NoteFolder {
+        let folder = folder_for(layout, Some(note_id));
+        let Ok(entries) = std::fs::read_dir(notes_root.join(&folder)) else {
+            return paths;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() || path.extension().is_some_and(|ext| ext == "md") {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+            let rel = join(&folder, &name);
+            if !paths.contains(&rel) && references(note_id, content, &rel) {
+                paths.push(rel);
+            }
+        }
+    }
+    paths
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetInfo {
+    pub path: String, // vault-relative
+    pub size: u64,
+    pub modified: i64,
+    pub image: bool,
+    pub meta: Option<sidecars::AttachmentMeta>, // from its metadata sidecar
+}
+
+pub fn is_image(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Markdown that embeds an image or links any other file at the
+/// vault-relative `path`.
+pub fn markdown_link(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let target = urlencoding::encode(path).replace("%2F", "/");
+    if is_image(path) {
+        format!("![{}]({})", name, target)
+    } else {
+        format!("[{}]({})", name, target)
+    }
+}
+
+fn asset_info(notes_root: &Path, path: &Path) -> Option<AssetInfo> {
+    let rel = path
+        .strip_prefix(notes_root)
+        .ok()?
+        .to_str()?
+        .replace(std::path::MAIN_SEPARATOR, "/");
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    Some(AssetInfo {
+        image: is_image(&rel),
+        meta: sidecars::read(notes_root, &rel),
+        path: rel,
+        size: metadata.len(),
+        modified,
+    })
+}
+
+/// Every attachment in the vault, by path: the files under `assets/`, and
+/// under the note-folder layout the images beside notes too. Other files
+/// beside notes (a BibTeX library, a script) are not treated as attachments.
+/// Blocking.
+pub fn list(notes_root: &Path, layout: AssetLayout) -> Vec<AssetInfo> {
+    let mut found: Vec<AssetInfo> = walkdir::WalkDir::new(notes_root.join(ASSETS_DIR))
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_none_or(|ext| ext != "md"))
+        .filter(|e| !e.file_name().to_string_lossy().ends_with(sidecars::SUFFIX))
+        .filter_map(|e| asset_info(notes_root, e.path()))
+        .collect();
+    if layout == AssetLayout::NoteFolder {
+        found.extend(
+            walkdir::WalkDir::new(notes_root)
+                .max_depth(10)
+                .into_iter()
+                .filter_entry(crate::is_visible_notes_entry)
+                .flatten()
+                .filter(|e| e.file_type().is_file())
+                .filter_map(|e| asset_info(notes_root, e.path()))
+                .filter(|asset| asset.image),
+        );
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+/// The assets no note references and no sidecar describes. Plain
+/// `assets/...` references are collected in one pass; only the rest are
+/// checked against each note in every spelling. Blocking.
+pub fn orphaned(notes: &[ScannedNote], assets: Vec<AssetInfo>) -> Vec<AssetInfo> {
+    let linked: HashSet<String> = notes
+        .iter()
+        .flat_map(|n| links::extract_asset_refs(&n.content))
+        .map(|name| join(ASSETS_DIR, &name))
+        .collect();
+    assets
+        .into_iter()
+        .filter(|asset| asset.meta.is_none() && !linked.contains(&asset.path))
+        .filter(|asset| {
+            let name = asset.path.rsplit('/').next().unwrap_or(&asset.path);
+            let encoded = urlencoding::encode(name).into_owned();
+            !notes.iter().any(|n| {
+                (n.content.contains(name) || n.content.contains(&encoded))
+                    && references(&n.id, &n.content, &asset.path)
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,177 @@
+//! Append-only audit trail of note changes.
+//!
+//! Every create, save, rename, move, append, delete and restore records which
+//! surface made it (the app window, the CLI, the vault server or sync), the
+//! note, the time and SHA-256 hashes of the content before and after. Entries
+//! are JSON lines in `audit.jsonl` in app data, so the trail covers every vault
+//! opened on this machine and survives switching between them. Writes never fail a
+//! note operation; a change that can't be logged is still made.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const DEFAULT_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Surface {
+    Ui,   // the app window, including autosave and backend features it triggers
+    Cli,  // command-line launches
+    Http, // vault server
+    Sync, // co-editing sessions
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    Create,
+    Save,
+    Rename,
+    Move,
+    Append,
+    Delete,  // moved to the trash
+    Restore, // put back from the trash
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub time: i64, // unix seconds
+    pub surface: Surface,
+    pub action: Action,
+    pub vault: String,
+    pub note_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_id: Option<String>, // after a rename or move
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditFilter {
+    pub note_id: Option<String>, // matches the old or new ID
+    pub surface: Option<Surface>,
+    pub vault: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: Option<usize>, // default 500
+}
+
+pub fn hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// An unset filter field matches everything.
+fn wanted<T>(filter: &Option<T>, test: impl FnOnce(&T) -> bool) -> bool {
+    match filter {
+        Some(value) => test(value),
+        None => true,
+    }
+}
+
+/// One change, built up before it is recorded.
+pub struct Change {
+    entry: AuditEntry,
+}
+
+impl Change {
+    pub fn new(surface: Surface, action: Action, note_id: &str) -> Self {
+        Change {
+            entry: AuditEntry {
+                time: chrono::Utc::now().timestamp(),
+                surface,
+                action,
+                vault: String::new(),
+                note_id: note_id.to_string(),
+                new_id: None,
+                old_hash: None,
+                new_hash: None,
+            },
+        }
+    }
+
+    pub fn new_id(mut self, id: &str) -> Self {
+        if id != self.entry.note_id {
+            self.entry.new_id = Some(id.to_string());
+        }
+        self
+    }
+
+    pub fn before(mut self, content: Option<&[u8]>) -> Self {
+        self.entry.old_hash = content.map(hash);
+        self
+    }
+
+    pub fn after(mut self, content: Option<&[u8]>) -> Self {
+        self.entry.new_hash = content.map(hash);
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct AuditLog {
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl AuditLog {
+    /// Start logging to `audit.jsonl` in `app_data`.
+    pub fn open(&self, app_data: &Path) {
+        let _ = std::fs::create_dir_all(app_data);
+        *self.path.lock().expect("audit log mutex") = Some(app_data.join("audit.jsonl"));
+    }
+
+    /// Append a change made in the vault at `vault`.
+    pub fn record(&self, vault: &Path, change: Change) {
+        let mut entry = change.entry;
+        entry.vault = vault.to_string_lossy().into_owned();
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        // Held while writing so concurrent entries never interleave
+        let path = self.path.lock().expect("audit log mutex");
+        let Some(ref path) = *path else {
+            return;
+        };
+        if let Ok(mut file) = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+        {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    /// Matching entries, newest first. Blocking.
+    pub fn read(&self, filter: &AuditFilter) -> Vec<AuditEntry> {
+        let Some(path) = self.path.lock().expect("audit log mutex").clone() else {
+            return Vec::new();
+        };
+        let Ok(file) = std::fs::File::open(path) else {
+            return Vec::new();
+        };
+        let matches = |entry: &AuditEntry| {
+            wanted(&filter.note_id, |id| {
+                entry.note_id == *id || entry.new_id.as_deref() == Some(id.as_str())
+            }) && wanted(&filter.surface, |s| entry.surface == *s)
+                && wanted(&filter.vault, |v| entry.vault == *v)
+                && wanted(&filter.since, |t| entry.time >= *t)
+                && wanted(&filter.until, |t| entry.time <= *t)
+        };
+        let mut entries: Vec<AuditEntry> = std::io::BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .filter(matches)
+            .collect();
+        entries.reverse();
+        entries.truncate(filter.limit.unwrap_or(DEFAULT_LIMIT));
+        entries
+    }
+}
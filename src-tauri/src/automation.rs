@@ -0,0 +1,302 @@
+//! Scheduled export and backup automations.
+//!
+//! Automations are configured in the vault settings (`automations`) and run on
+//! an interval, at app start or at app quit. The outcome of each automation's
+//! last run is kept in `.scratch/automation.json` so intervals carry across
+//! restarts and `get_automation_status` can report it.
+
+use crate::{disk, export, export_with_profile, store, AppState};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const STORE_FILE: &str = "automation.json";
+const BACKUP_PREFIX: &str = "Scratch Backup ";
+const TICK: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Trigger {
+    Interval { minutes: u64 },
+    AppStart,
+    AppQuit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AutomationAction {
+    Export {
+        profile: String,
+    },
+    Backup {
+        #[serde(rename = "outputDir")]
+        output_dir: String,
+        keep: Option<usize>, // number of backups to retain
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Automation {
+    pub name: String,
+    pub trigger: Trigger,
+    pub action: AutomationAction,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationRun {
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationStatus {
+    pub name: String,
+    pub trigger: Trigger,
+    pub enabled: bool,
+    pub last_run: Option<AutomationRun>,
+    pub next_run: Option<i64>, // interval automations only
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunStore {
+    runs: HashMap<String, AutomationRun>,
+}
+
+impl RunStore {
+    fn load(notes_folder: &str) -> Self {
+        store::load(Path::new(notes_folder), STORE_FILE)
+    }
+
+    fn save(&self, notes_folder: &str) -> Result<()> {
+        store::save(Path::new(notes_folder), STORE_FILE, self)
+    }
+}
+
+/// Copy the vault (minus `.git` and `.trash`) into a timestamped folder under
+/// `output_dir`, then prune old backups beyond `keep`.
+fn backup(notes_root: &Path, output_dir: &str, keep: Option<usize>) -> Result<String, String> {
+    use walkdir::WalkDir;
+
+    let out_dir = export::validate_output_dir(output_dir, notes_root)?;
+    let dest = out_dir.join(format!(
+        "{}{}",
+        BACKUP_PREFIX,
+        chrono::Local::now().format("%Y-%m-%d %H%M%S")
+    ));
+
+    let mut copied = 0;
+    for entry in WalkDir::new(notes_root)
+        .into_iter()
+        .filter_entry(|e| {
+            let skipped = matches!(e.file_name().to_str(), Some(".git" | ".trash" | ".history"));
+            !(e.file_type().is_dir() && skipped)
+        })
+        .flatten()
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(rel) = entry.path().strip_prefix(notes_root) else {
+            continue;
+        };
+        let target = dest.join(rel);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::copy(entry.path(), &target).map_err(|e| format!("Failed to copy {}: {}", rel.display(), e))?;
+        copied += 1;
+    }
+
+    if let Some(keep) = keep.filter(|k| *k > 0) {
+        let mut backups: Vec<PathBuf> = std::fs::read_dir(&out_dir)
+            .map_err(|e| e.to_string())?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                p.is_dir()
+                    && p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(BACKUP_PREFIX))
+            })
+            .collect();
+        // Timestamped names sort chronologically
+        backups.sort();
+        let excess = backups.len().saturating_sub(keep);
+        for old in backups.into_iter().take(excess) {
+            let _ = std::fs::remove_dir_all(old);
+        }
+    }
+
+    Ok(format!("Backed up {} files to {}", copied, dest.display()))
+}
+
+/// Run one automation now and record the outcome. Blocking.
+pub fn run(app: &AppHandle, automation: &Automation) -> Result<AutomationRun, String> {
+    let state = app.state::<AppState>();
+    let folder = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone()
+        .ok_or("Notes folder not set")?;
+
+    let started_at = chrono::Utc::now().timestamp();
+    let outcome = execute(&folder, &state, automation);
+    Ok(record(app, &folder, &automation.name, started_at, outcome))
+}
+
+fn execute(folder: &str, state: &AppState, automation: &Automation) -> Result<String, String> {
+    match automation.action {
+        AutomationAction::Export { ref profile } => export_with_profile(state, profile)
+            .map(|r| format!("Exported {} notes to {}", r.exported, r.output_dir)),
+        AutomationAction::Backup {
+            ref output_dir,
+            keep,
+        } => backup(Path::new(folder), output_dir, keep),
+    }
+}
+
+fn record(
+    app: &AppHandle,
+    folder: &str,
+    name: &str,
+    started_at: i64,
+    outcome: Result<String, String>,
+) -> AutomationRun {
+    let run = AutomationRun {
+        started_at,
+        finished_at: chrono::Utc::now().timestamp(),
+        success: outcome.is_ok(),
+        message: outcome.unwrap_or_else(|e| e),
+    };
+
+    let mut store = RunStore::load(folder);
+    store.runs.insert(name.to_string(), run.clone());
+    let _ = store.save(folder);
+    let _ = app.emit("automation-finished", name);
+    run
+}
+
+/// Run an automation the scheduler picked, unless disk space is critically
+/// low, in which case the skip is recorded as a failed run.
+fn run_scheduled(app: &AppHandle, automation: &Automation) {
+    match disk::allow_background_write(app) {
+        Ok(()) => {
+            let _ = run(app, automation);
+        }
+        Err(reason) => {
+            if let Some(folder) = notes_folder(app) {
+                let now = chrono::Utc::now().timestamp();
+                record(app, &folder, &automation.name, now, Err(reason));
+            }
+        }
+    }
+}
+
+fn configured(app: &AppHandle) -> Vec<Automation> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.read().expect("settings read lock");
+    settings.automations.clone().unwrap_or_default()
+}
+
+fn notes_folder(app: &AppHandle) -> Option<String> {
+    app.state::<AppState>()
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone()
+}
+
+/// Run every enabled automation with the given trigger. Blocking.
+pub fn run_for_trigger(app: &AppHandle, trigger: &Trigger) {
+    for automation in configured(app) {
+        if automation.enabled && automation.trigger == *trigger {
+            run_scheduled(app, &automation);
+        }
+    }
+}
+
+/// Run interval automations whose period has elapsed since their last run.
+fn run_due(app: &AppHandle) {
+    let Some(folder) = notes_folder(app) else {
+        return;
+    };
+    let now = chrono::Utc::now().timestamp();
+    let runs = RunStore::load(&folder).runs;
+    for automation in configured(app) {
+        let Trigger::Interval { minutes } = automation.trigger else {
+            continue;
+        };
+        let due = runs
+            .get(&automation.name)
+            .map(|last| now - last.started_at >= minutes.max(1) as i64 * 60)
+            .unwrap_or(true);
+        if automation.enabled && due {
+            run_scheduled(app, &automation);
+        }
+    }
+}
+
+/// Run start-up automations, then check interval automations every minute.
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let start_app = app.clone();
+        let _ = tauri::async_runtime::spawn_blocking(move || {
+            run_for_trigger(&start_app, &Trigger::AppStart)
+        })
+        .await;
+        loop {
+            if app.state::<AppState>().shutdown.started() {
+                return;
+            }
+            let tick_app = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || run_due(&tick_app)).await;
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
+
+pub fn status(app: &AppHandle) -> Vec<AutomationStatus> {
+    let runs = notes_folder(app)
+        .map(|folder| RunStore::load(&folder).runs)
+        .unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    configured(app)
+        .into_iter()
+        .map(|automation| {
+            let last_run = runs.get(&automation.name).cloned();
+            let next_run = match automation.trigger {
+                Trigger::Interval { minutes } if automation.enabled => Some(
+                    last_run
+                        .as_ref()
+                        .map(|r| r.started_at + minutes.max(1) as i64 * 60)
+                        .unwrap_or(now),
+                ),
+                _ => None,
+            };
+            AutomationStatus {
+                name: automation.name,
+                trigger: automation.trigger,
+                enabled: automation.enabled,
+                last_run,
+                next_run,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,472 @@
+//! Debounced, coalescing autosave.
+//!
+//! The editor sends every change to `autosave_note`; only the latest content
+//! per note is kept, and it is written once edits pause for the configured
+//! interval (`autosaveIntervalMs`), or after a few intervals of continuous
+//! typing. `flush_autosave` writes immediately (on blur), and everything
+//! pending is flushed on quit. Each change gets a revision number so the
+//! frontend can tell which one is on disk. Pending content is also journaled
+//! as a draft (see `recovery`) so a crash loses at most a couple of seconds.
+//! Every write is followed by a `note-stats` event with the counts of what
+//! was written, for the editor's status bar.
+//!
+//! Like `save_note`, a change can carry the hash of the content the editor
+//! last loaded. If the note has changed on disk since then (and since the
+//! last autosave of it), nothing is written: the content stays pending and an
+//! `autosave-conflict` event carries what is on disk, until a checked save
+//! or a change based on the new content resolves it.
+
+use crate::{
+    abs_path_from_id, audit, frontmatter, recovery, report, write_note_content, AppState,
+    SaveConflict,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const MIN_INTERVAL_MS: u64 = 200;
+const MAX_INTERVAL_MS: u64 = 60_000;
+const MAX_WAIT_INTERVALS: u32 = 5; // cap on how long continuous typing defers a write
+const DRAFT_EVERY: Duration = Duration::from_secs(2);
+
+struct Pending {
+    content: String,
+    expected_hash: Option<String>, // of the content the editor based this on
+    revision: u64,
+    first_edit: Instant,
+    last_edit: Instant,
+    timer: bool, // a flush is scheduled
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutosaveStatus {
+    pub id: String,
+    pub revision: u64,                   // latest revision received
+    pub persisted_revision: Option<u64>, // latest revision written to disk
+    pub pending: bool,
+}
+
+/// Counts of the content last written for a note. Cursor-independent, so the
+/// status bar shows the same numbers whatever is selected.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteStats {
+    pub id: String,
+    pub words: usize,      // body only, frontmatter aside
+    pub characters: usize, // body only
+    pub characters_no_spaces: usize,
+    pub lines: usize,
+    pub paragraphs: usize,
+    pub revision: u64, // the revision these counts are for
+    pub pending: bool, // newer edits are waiting to be written
+    pub saved_at: i64, // unix seconds of the write
+}
+
+impl NoteStats {
+    fn new(id: &str, content: &str, revision: u64) -> Self {
+        let body = frontmatter::body(content);
+        NoteStats {
+            id: id.to_string(),
+            words: report::word_count(content),
+            characters: body.chars().count(),
+            characters_no_spaces: body.chars().filter(|c| !c.is_whitespace()).count(),
+            lines: body.lines().count(),
+            paragraphs: body.split("\n\n").filter(|p| !p.trim().is_empty()).count(),
+            revision,
+            pending: false,
+            saved_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+/// Why a flush wrote nothing.
+#[derive(Debug)]
+pub enum FlushError {
+    Conflict(SaveConflict), // the note changed on disk; the content stays pending
+    Write(String),
+}
+
+impl std::fmt::Display for FlushError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FlushError::Conflict(conflict) => {
+                write!(f, "{} changed on disk since it was loaded", conflict.id)
+            }
+            FlushError::Write(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<FlushError> for String {
+    fn from(err: FlushError) -> Self {
+        err.to_string()
+    }
+}
+
+#[derive(Default)]
+pub struct Autosaver {
+    pending: Mutex<HashMap<String, Pending>>,
+    persisted: Mutex<HashMap<String, u64>>,
+    written: Mutex<HashMap<String, String>>, // hash of the last write per note
+    saved: Mutex<HashMap<String, NoteStats>>, // counts of the last write per note
+    drafted: Mutex<HashMap<String, Instant>>, // last draft journaled per note
+    next_revision: AtomicU64,
+    // Serializes writes so an older revision never lands after a newer one
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl Autosaver {
+    /// Record new content for a note. Returns the status and whether a timer
+    /// needs starting (none is scheduled for this note yet).
+    fn queue(
+        &self,
+        id: &str,
+        content: String,
+        expected_hash: Option<String>,
+    ) -> (AutosaveStatus, bool) {
+        let revision = self.next_revision.fetch_add(1, Ordering::SeqCst) + 1;
+        let now = Instant::now();
+        let start_timer = {
+            let mut pending = self.pending.lock().expect("autosave pending mutex");
+            match pending.get_mut(id) {
+                Some(existing) => {
+                    existing.content = content;
+                    existing.expected_hash = expected_hash;
+                    existing.revision = revision;
+                    existing.last_edit = now;
+                    !std::mem::replace(&mut existing.timer, true)
+                }
+                None => {
+                    pending.insert(
+                        id.to_string(),
+                        Pending {
+                            content,
+                            expected_hash,
+                            revision,
+                            first_edit: now,
+                            last_edit: now,
+                            timer: true,
+                        },
+                    );
+                    true
+                }
+            }
+        };
+        (
+            AutosaveStatus {
+                id: id.to_string(),
+                revision,
+                persisted_revision: self.persisted_revision(id),
+                pending: true,
+            },
+            start_timer,
+        )
+    }
+
+    fn persisted_revision(&self, id: &str) -> Option<u64> {
+        self.persisted
+            .lock()
+            .expect("autosave persisted mutex")
+            .get(id)
+            .copied()
+    }
+
+    /// Drop pending content and its draft for a note, e.g. when a full save
+    /// supersedes it or the note is deleted.
+    pub fn discard(&self, notes_root: &Path, id: &str) {
+        self.pending.lock().expect("autosave pending mutex").remove(id);
+        self.written.lock().expect("autosave written mutex").remove(id);
+        self.saved.lock().expect("autosave saved mutex").remove(id);
+        self.drafted.lock().expect("autosave drafted mutex").remove(id);
+        recovery::remove_draft(notes_root, id);
+    }
+
+    /// Forget persisted revisions, counts and draft times, e.g. when another
+    /// vault opens and the same IDs mean other notes.
+    pub fn reset(&self) {
+        self.persisted
+            .lock()
+            .expect("autosave persisted mutex")
+            .clear();
+        self.written.lock().expect("autosave written mutex").clear();
+        self.saved.lock().expect("autosave saved mutex").clear();
+        self.drafted.lock().expect("autosave drafted mutex").clear();
+    }
+
+    /// Whether it is time to journal another draft for a note.
+    fn take_draft_slot(&self, id: &str) -> bool {
+        let mut drafted = self.drafted.lock().expect("autosave drafted mutex");
+        let now = Instant::now();
+        match drafted.get(id) {
+            Some(last) if now.duration_since(*last) < DRAFT_EVERY => false,
+            _ => {
+                drafted.insert(id.to_string(), now);
+                true
+            }
+        }
+    }
+
+    /// Whether a write is under way right now.
+    pub fn is_flushing(&self) -> bool {
+        self.write_lock.try_lock().is_err()
+    }
+
+    pub fn pending_ids(&self) -> Vec<String> {
+        self.pending
+            .lock()
+            .expect("autosave pending mutex")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Time left before a note's pending content is due, or None if nothing
+    /// is pending.
+    fn time_until_due(&self, id: &str, interval: Duration) -> Option<Duration> {
+        let pending = self.pending.lock().expect("autosave pending mutex");
+        let entry = pending.get(id)?;
+        let due = (entry.last_edit + interval).min(entry.first_edit + interval * MAX_WAIT_INTERVALS);
+        Some(due.saturating_duration_since(Instant::now()))
+    }
+}
+
+fn interval(state: &AppState) -> Duration {
+    let settings = state.settings.read().expect("settings read lock");
+    let ms = settings
+        .autosave_interval_ms
+        .unwrap_or(DEFAULT_INTERVAL_MS)
+        .clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS);
+    Duration::from_millis(ms)
+}
+
+/// Queue content for a note and start its timer if needed. `expected_hash`
+/// is the SHA-256 of the content the editor last loaded or saved.
+pub fn queue(
+    app: &AppHandle,
+    id: &str,
+    content: String,
+    expected_hash: Option<String>,
+) -> AutosaveStatus {
+    let state = app.state::<AppState>();
+    if state.autosave.take_draft_slot(id) {
+        let folder = {
+            let app_config = state.app_config.read().expect("app_config read lock");
+            app_config.notes_folder.clone()
+        };
+        if let Some(folder) = folder {
+            let _ = recovery::write_draft(Path::new(&folder), id, &content);
+        }
+    }
+    let (status, start_timer) = state.autosave.queue(id, content, expected_hash);
+    if start_timer {
+        let app = app.clone();
+        let id = id.to_string();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let wait = {
+                    let state = app.state::<AppState>();
+                    state.autosave.time_until_due(&id, interval(&state))
+                };
+                match wait {
+                    None => return,
+                    Some(wait) if wait.is_zero() => break,
+                    Some(wait) => tokio::time::sleep(wait).await,
+                }
+            }
+            let state = app.state::<AppState>();
+            match flush(&state, &id).await {
+                Ok(Some(status)) => {
+                    let _ = app.emit("autosave-persisted", &status);
+                    emit_stats(&app, &id);
+                }
+                Ok(None) => {}
+                Err(FlushError::Conflict(conflict)) => {
+                    let _ = app.emit("autosave-conflict", &conflict);
+                }
+                Err(FlushError::Write(e)) => {
+                    let _ = app.emit("autosave-failed", serde_json::json!({ "id": id, "error": e }));
+                }
+            }
+        });
+    }
+    status
+}
+
+/// What is on disk, if the note changed there since the content it was
+/// based on, or since it was last autosaved. Someone else writing the same
+/// text isn't a conflict.
+async fn conflict(state: &AppState, path: &Path, id: &str, entry: &Pending) -> Option<SaveConflict> {
+    let expected = entry.expected_hash.as_deref()?;
+    let disk = tokio::fs::read(path).await.ok();
+    let disk_hash = disk.as_deref().map(audit::hash);
+    let written = state
+        .autosave
+        .written
+        .lock()
+        .expect("autosave written mutex")
+        .get(id)
+        .cloned();
+    if disk_hash.as_deref() == Some(expected) || (disk_hash.is_some() && disk_hash == written) {
+        return None;
+    }
+    let disk_content = disk.and_then(|bytes| String::from_utf8(bytes).ok());
+    if disk_content.as_deref() == Some(entry.content.as_str()) {
+        return None;
+    }
+    let disk_modified = tokio::fs::metadata(path)
+        .await
+        .ok()
+        .map(|m| crate::file_modified_secs(&m));
+    Some(SaveConflict {
+        id: id.to_string(),
+        disk_content,
+        disk_modified,
+        disk_hash,
+    })
+}
+
+/// Write a note's pending content now. Returns None if nothing was pending.
+/// On failure the content stays pending unless newer content arrived.
+pub async fn flush(state: &AppState, id: &str) -> Result<Option<AutosaveStatus>, FlushError> {
+    let folder_path = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .map(PathBuf::from)
+            .ok_or_else(|| FlushError::Write("Notes folder not set".to_string()))?
+    };
+    let _guard = state.autosave.write_lock.lock().await;
+    // Held from the existence check through the write, so a rename or delete
+    // can't slip in between
+    let _note_guard = state.note_locks.lock(&folder_path, &[id]).await;
+    let Some(entry) = state
+        .autosave
+        .pending
+        .lock()
+        .expect("autosave pending mutex")
+        .remove(id)
+    else {
+        return Ok(None);
+    };
+
+    // Never recreate a note that was deleted or moved meanwhile
+    let result = match abs_path_from_id(&folder_path, id) {
+        Ok(path) if path.exists() => match conflict(state, &path, id, &entry).await {
+            Some(conflict) => Err(FlushError::Conflict(conflict)),
+            None => write_note_content(state, &folder_path, id, entry.content.clone())
+                .await
+                .map(|_| ())
+                .map_err(FlushError::Write),
+        },
+        Ok(_) => {
+            state
+                .autosave
+                .saved
+                .lock()
+                .expect("autosave saved mutex")
+                .remove(id);
+            return Ok(None);
+        }
+        Err(e) => Err(FlushError::Write(e)),
+    };
+
+    if let Err(e) = result {
+        // The next change starts a new timer
+        let mut pending = state.autosave.pending.lock().expect("autosave pending mutex");
+        pending.entry(id.to_string()).or_insert(Pending {
+            timer: false,
+            ..entry
+        });
+        return Err(e);
+    }
+
+    state
+        .autosave
+        .persisted
+        .lock()
+        .expect("autosave persisted mutex")
+        .insert(id.to_string(), entry.revision);
+    state
+        .autosave
+        .written
+        .lock()
+        .expect("autosave written mutex")
+        .insert(id.to_string(), audit::hash(entry.content.as_bytes()));
+    let stats = NoteStats::new(id, &entry.content, entry.revision);
+    state
+        .autosave
+        .saved
+        .lock()
+        .expect("autosave saved mutex")
+        .insert(id.to_string(), stats);
+    let still_pending = state
+        .autosave
+        .pending
+        .lock()
+        .expect("autosave pending mutex")
+        .get(id)
+        .map(|p| p.revision);
+    if still_pending.is_none() {
+        state.autosave.drafted.lock().expect("autosave drafted mutex").remove(id);
+        recovery::remove_draft(&folder_path, id);
+    }
+    Ok(Some(AutosaveStatus {
+        id: id.to_string(),
+        revision: still_pending.unwrap_or(entry.revision),
+        persisted_revision: Some(entry.revision),
+        pending: still_pending.is_some(),
+    }))
+}
+
+/// Send `note-stats` with the counts of a note's last write, and whether
+/// newer edits are pending by now. Nothing is sent if it was never written.
+pub fn emit_stats(app: &AppHandle, id: &str) {
+    let state = app.state::<AppState>();
+    let Some(mut stats) = state
+        .autosave
+        .saved
+        .lock()
+        .expect("autosave saved mutex")
+        .get(id)
+        .cloned()
+    else {
+        return;
+    };
+    stats.pending = status(&state, id).pending;
+    let _ = app.emit("note-stats", &stats);
+}
+
+/// Flush every pending note. Returns the IDs that failed to write.
+pub async fn flush_all(state: &AppState) -> Vec<String> {
+    let mut failed = Vec::new();
+    for id in state.autosave.pending_ids() {
+        if flush(state, &id).await.is_err() {
+            failed.push(id);
+        }
+    }
+    failed
+}
+
+pub fn status(state: &AppState, id: &str) -> AutosaveStatus {
+    let pending = state
+        .autosave
+        .pending
+        .lock()
+        .expect("autosave pending mutex")
+        .get(id)
+        .map(|p| p.revision);
+    let persisted_revision = state.autosave.persisted_revision(id);
+    AutosaveStatus {
+        id: id.to_string(),
+        revision: pending.or(persisted_revision).unwrap_or(0),
+        persisted_revision,
+        pending: pending.is_some(),
+    }
+}
@@ -0,0 +1,166 @@
+//! Index of the links between notes, for backlinks.
+//!
+//! Each note's title and outgoing links are kept in memory so finding the
+//! notes that link to one doesn't mean reading the whole vault. The index is
+//! built on first use, kept current by saves and the file watcher, and dropped
+//! when notes move in bulk or it grows past its memory budget. Targets are
+//! resolved at lookup time, since adding or retitling one note can change what
+//! links elsewhere point at.
+
+use crate::memory::{CacheUsage, MemoryBudgets};
+use crate::{extract_title, links, scan_notes, VAULT_REPORT_ID};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+struct Entry {
+    title: String,
+    links: Vec<links::Link>,
+}
+
+impl Entry {
+    fn new(content: &str) -> Self {
+        Entry {
+            title: extract_title(content),
+            links: links::extract_links(content),
+        }
+    }
+
+    fn weight(&self, id: &str) -> usize {
+        std::mem::size_of::<Self>()
+            + id.len()
+            + self.title.len()
+            + self
+                .links
+                .iter()
+                .map(|l| std::mem::size_of::<links::Link>() + l.target.len())
+                .sum::<usize>()
+    }
+}
+
+type Notes = HashMap<String, Entry>;
+
+struct Inner {
+    root: PathBuf,
+    notes: Notes,
+    bytes: usize,
+}
+
+pub struct LinkIndex {
+    inner: RwLock<Option<Inner>>,
+    budget: AtomicUsize, // bytes
+}
+
+impl Default for LinkIndex {
+    fn default() -> Self {
+        LinkIndex {
+            inner: RwLock::new(None),
+            budget: AtomicUsize::new(MemoryBudgets::default().link_index_bytes()),
+        }
+    }
+}
+
+/// Notes in `notes` with a link that resolves to `target`.
+fn sources_in(notes: &Notes, target: &str) -> (Vec<String>, links::LinkResolver) {
+    let resolver = links::LinkResolver::new(
+        notes
+            .iter()
+            .map(|(id, entry)| (id.as_str(), entry.title.as_str())),
+    );
+    let sources = notes
+        .iter()
+        .filter(|(id, _)| id.as_str() != target)
+        .filter(|(id, entry)| {
+            entry
+                .links
+                .iter()
+                .any(|link| resolver.resolve(link, id).as_deref() == Some(target))
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+    (sources, resolver)
+}
+
+impl LinkIndex {
+    /// Record the current content of note `id`. Ignored until the index has
+    /// been built for `root`.
+    pub fn update(&self, root: &Path, id: &str, content: &str) {
+        if id == VAULT_REPORT_ID {
+            return;
+        }
+        let budget = self.budget.load(Ordering::Relaxed);
+        let mut guard = self.inner.write().expect("link index lock");
+        let Some(inner) = guard.as_mut().filter(|inner| inner.root == root) else {
+            return;
+        };
+        let entry = Entry::new(content);
+        inner.bytes += entry.weight(id);
+        if let Some(old) = inner.notes.insert(id.to_string(), entry) {
+            inner.bytes -= old.weight(id);
+        }
+        if inner.bytes > budget {
+            *guard = None;
+        }
+    }
+
+    /// Forget note `id`.
+    pub fn remove(&self, root: &Path, id: &str) {
+        let mut inner = self.inner.write().expect("link index lock");
+        if let Some(inner) = inner.as_mut().filter(|inner| inner.root == root) {
+            if let Some(old) = inner.notes.remove(id) {
+                inner.bytes -= old.weight(id);
+            }
+        }
+    }
+
+    /// Drop the index; it is rebuilt on next use.
+    pub fn invalidate(&self) {
+        *self.inner.write().expect("link index lock") = None;
+    }
+
+    /// Change the memory budget, dropping the index if it no longer fits.
+    pub fn set_budget(&self, bytes: usize) {
+        self.budget.store(bytes, Ordering::Relaxed);
+        let mut inner = self.inner.write().expect("link index lock");
+        if inner.as_ref().is_some_and(|inner| inner.bytes > bytes) {
+            *inner = None;
+        }
+    }
+
+    pub fn usage(&self) -> CacheUsage {
+        let inner = self.inner.read().expect("link index lock");
+        CacheUsage {
+            entries: inner.as_ref().map_or(0, |inner| inner.notes.len()),
+            bytes: inner.as_ref().map_or(0, |inner| inner.bytes),
+            budget_bytes: self.budget.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Notes with a link that resolves to `target`, and the resolver that
+    /// resolved it. Builds the index for `root` if needed, or just scans the
+    /// vault when it won't fit. Blocking.
+    pub fn sources(&self, root: &Path, target: &str) -> (Vec<String>, links::LinkResolver) {
+        {
+            let inner = self.inner.read().expect("link index lock");
+            if let Some(inner) = inner.as_ref().filter(|inner| inner.root == root) {
+                return sources_in(&inner.notes, target);
+            }
+        }
+        let notes: Notes = scan_notes(root)
+            .into_iter()
+            .filter(|n| n.id != VAULT_REPORT_ID)
+            .map(|n| (n.id, Entry::new(&n.content)))
+            .collect();
+        let found = sources_in(&notes, target);
+        let bytes = notes.iter().map(|(id, entry)| entry.weight(id)).sum();
+        if bytes <= self.budget.load(Ordering::Relaxed) {
+            *self.inner.write().expect("link index lock") = Some(Inner {
+                root: root.to_path_buf(),
+                notes,
+                bytes,
+            });
+        }
+        found
+    }
+}
@@ -0,0 +1,151 @@
+//! Portable zip backups of the whole vault.
+//!
+//! `export_vault_zip` packs every file in the notes folder (notes, `assets/`,
+//! attachments beside notes and their sidecars) into one archive at the
+//! vault-relative paths, so unzipping it anywhere gives a working vault. The
+//! trash and version history go in unless excluded; git data, `.scratch/`
+//! state and other hidden folders never do. The archive is written under a
+//! temporary name and renamed when complete, and `vault-export-progress`
+//! events report each batch of files.
+
+use crate::{history, trash};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+const PROGRESS_EVERY: usize = 25; // files between progress events
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultZipOptions {
+    #[serde(default)]
+    pub exclude_trash: bool,
+    #[serde(default)]
+    pub exclude_history: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultZipProgress {
+    pub current: usize,
+    pub total: usize,
+    pub path: String, // vault-relative, the last file packed
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultZipReport {
+    pub destination: String,
+    pub files: usize,
+    pub bytes: u64, // size of the archive
+}
+
+/// Files to pack, as (vault-relative path, absolute path).
+fn files(notes_root: &Path, options: &VaultZipOptions) -> Vec<(String, PathBuf)> {
+    let included = |name: &str| match name {
+        trash::TRASH_DIR => !options.exclude_trash,
+        history::HISTORY_DIR => !options.exclude_history,
+        _ => !name.starts_with('.'),
+    };
+    let mut files: Vec<(String, PathBuf)> = walkdir::WalkDir::new(notes_root)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0 || !e.file_type().is_dir() || included(&e.file_name().to_string_lossy())
+        })
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let rel = e
+                .path()
+                .strip_prefix(notes_root)
+                .ok()?
+                .to_str()?
+                .replace(std::path::MAIN_SEPARATOR, "/");
+            Some((rel, e.into_path()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// The file's modification time as a zip timestamp, if it fits one.
+fn zip_time(path: &Path) -> Option<zip::DateTime> {
+    use chrono::{Datelike, Timelike};
+
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let local: chrono::DateTime<chrono::Local> = modified.into();
+    zip::DateTime::from_date_and_time(
+        u16::try_from(local.year()).ok()?,
+        local.month() as u8,
+        local.day() as u8,
+        local.hour() as u8,
+        local.minute() as u8,
+        local.second() as u8,
+    )
+    .ok()
+}
+
+/// Pack the vault into a zip at `destination`. Blocking.
+pub fn export(
+    app: &AppHandle,
+    notes_root: &Path,
+    destination: &Path,
+    options: &VaultZipOptions,
+) -> Result<VaultZipReport, String> {
+    if destination.starts_with(notes_root) {
+        return Err("Choose a destination outside the notes folder".to_string());
+    }
+    let files = files(notes_root, options);
+    let total = files.len();
+    let mut temp = destination.as_os_str().to_owned();
+    temp.push(".partial");
+    let temp = PathBuf::from(temp);
+
+    let write = || -> Result<(), String> {
+        let file = std::fs::File::create(&temp).map_err(|e| e.to_string())?;
+        let mut zip = zip::ZipWriter::new(file);
+        for (i, (rel, abs)) in files.iter().enumerate() {
+            let mut source =
+                std::fs::File::open(abs).map_err(|e| format!("Failed to read {}: {}", rel, e))?;
+            let size = source.metadata().map(|m| m.len()).unwrap_or(0);
+            let mut options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .large_file(size >= u32::MAX as u64);
+            if let Some(time) = zip_time(abs) {
+                options = options.last_modified_time(time);
+            }
+            zip.start_file(rel.as_str(), options)
+                .map_err(|e| e.to_string())?;
+            std::io::copy(&mut source, &mut zip)
+                .map_err(|e| format!("Failed to pack {}: {}", rel, e))?;
+
+            let current = i + 1;
+            if current % PROGRESS_EVERY == 0 || current == total {
+                let _ = app.emit(
+                    "vault-export-progress",
+                    VaultZipProgress {
+                        current,
+                        total,
+                        path: rel.clone(),
+                    },
+                );
+            }
+        }
+        zip.finish()
+            .map_err(|e| e.to_string())?
+            .sync_all()
+            .map_err(|e| e.to_string())
+    };
+    write()
+        .and_then(|_| std::fs::rename(&temp, destination).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            format!("Failed to export the vault: {}", e)
+        })?;
+
+    Ok(VaultZipReport {
+        destination: destination.to_string_lossy().into_owned(),
+        files: total,
+        bytes: std::fs::metadata(destination).map(|m| m.len()).unwrap_or(0),
+    })
+}
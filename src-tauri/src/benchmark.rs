@@ -0,0 +1,178 @@
+//! Vault performance benchmark.
+//!
+//! `run` times the work that makes a large vault feel slow: scanning every
+//! note, building a search index from scratch, answering searches and getting
+//! file watcher events. The index is built in memory and the watcher probes use
+//! a scratch folder under `.scratch/`, so the live index, watcher and notes are
+//! left alone. Startup time is measured from process start to the end of app
+//! setup.
+
+use crate::{scan_notes, LockedNotePolicy, SearchIndex};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+const SEARCH_QUERIES: usize = 20;
+const SEARCH_LIMIT: usize = 50;
+const WATCHER_PROBES: usize = 5;
+const WATCHER_TIMEOUT: Duration = Duration::from_secs(3);
+const PROBE_DIR: &str = "benchmark";
+
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+static STARTUP: OnceLock<Duration> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkReport {
+    pub note_count: usize,
+    pub total_bytes: u64,
+    pub startup_ms: Option<f64>, // process start to end of app setup
+    pub scan_ms: f64,
+    pub index_build_ms: f64,
+    pub search: Option<LatencyStats>, // None if no query words were found
+    pub watcher: Option<LatencyStats>, // None if no watcher event arrived
+}
+
+/// Call first thing at process start.
+pub fn mark_start() {
+    PROCESS_START.get_or_init(Instant::now);
+}
+
+/// Call once app setup has finished.
+pub fn mark_ready() {
+    if let Some(start) = PROCESS_START.get() {
+        STARTUP.get_or_init(|| start.elapsed());
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl LatencyStats {
+    fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(f64::total_cmp);
+        let at = |fraction: f64| samples[((samples.len() - 1) as f64 * fraction).round() as usize];
+        Some(LatencyStats {
+            samples: samples.len(),
+            min_ms: samples[0],
+            median_ms: at(0.5),
+            p95_ms: at(0.95),
+            max_ms: samples[samples.len() - 1],
+        })
+    }
+}
+
+/// Words from note titles, spread across the vault, to search for.
+fn sample_queries(titles: &[&str]) -> Vec<String> {
+    let step = (titles.len() / SEARCH_QUERIES).max(1);
+    let mut queries = BTreeSet::new();
+    for title in titles.iter().step_by(step) {
+        let word = title
+            .split_whitespace()
+            .find(|w| w.chars().count() >= 4 && w.chars().all(char::is_alphanumeric));
+        if let Some(word) = word {
+            queries.insert(word.to_lowercase());
+        }
+        if queries.len() == SEARCH_QUERIES {
+            break;
+        }
+    }
+    queries.into_iter().collect()
+}
+
+type WatchEvents = mpsc::Receiver<(Instant, Vec<std::path::PathBuf>)>;
+
+/// Write one probe file and wait for the watcher to report it.
+fn probe(dir: &Path, name: &str, events: &WatchEvents) -> Option<f64> {
+    let written = Instant::now();
+    std::fs::write(dir.join(name), "probe").ok()?;
+    loop {
+        let left = (written + WATCHER_TIMEOUT).checked_duration_since(Instant::now())?;
+        let (at, paths) = events.recv_timeout(left).ok()?;
+        // Paths may come back canonicalized, so match on the file name
+        if paths.iter().any(|p| p.ends_with(name)) {
+            return Some(ms(at.duration_since(written)));
+        }
+    }
+}
+
+/// Time from writing a file to the watcher reporting it, for a few files.
+fn watcher_lag(notes_root: &Path) -> Option<LatencyStats> {
+    let dir = notes_root.join(".scratch").join(PROBE_DIR);
+    std::fs::create_dir_all(&dir).ok()?;
+    let (tx, events) = mpsc::channel();
+    let watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| {
+            if let Ok(event) = res {
+                let _ = tx.send((Instant::now(), event.paths));
+            }
+        },
+        Config::default(),
+    );
+    let probes = 0..WATCHER_PROBES;
+    let lags = match watcher {
+        Ok(mut watcher) => match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(()) => probes
+                .filter_map(|n| probe(&dir, &format!("probe-{}.md", n), &events))
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+        Err(_) => Vec::new(),
+    };
+    let _ = std::fs::remove_dir_all(&dir);
+    LatencyStats::from_samples(lags)
+}
+
+/// Benchmark the vault at `notes_root`. Blocking; takes a few seconds on
+/// large vaults.
+pub fn run(notes_root: &Path, locked_policy: LockedNotePolicy) -> Result<BenchmarkReport, String> {
+    let started = Instant::now();
+    let notes = scan_notes(notes_root);
+    let scan_ms = ms(started.elapsed());
+
+    let index = SearchIndex::new(None, locked_policy, None).map_err(|e| e.to_string())?;
+    let started = Instant::now();
+    index
+        .rebuild_index(&notes_root.to_path_buf())
+        .map_err(|e| e.to_string())?;
+    index.reader.reload().map_err(|e| e.to_string())?;
+    let index_build_ms = ms(started.elapsed());
+
+    let titles: Vec<&str> = notes.iter().map(|n| n.title.as_str()).collect();
+    let search_samples = sample_queries(&titles)
+        .iter()
+        .filter_map(|query| {
+            let started = Instant::now();
+            index.search(query, SEARCH_LIMIT).ok()?;
+            Some(ms(started.elapsed()))
+        })
+        .collect();
+
+    Ok(BenchmarkReport {
+        note_count: notes.len(),
+        total_bytes: notes.iter().map(|n| n.size).sum(),
+        startup_ms: STARTUP.get().copied().map(ms),
+        scan_ms,
+        index_build_ms,
+        search: LatencyStats::from_samples(search_samples),
+        watcher: watcher_lag(notes_root),
+    })
+}
@@ -0,0 +1,179 @@
+//! Scoped access tokens for surfaces outside the app window.
+//!
+//! Each token grants one scope on one surface: `readOnly` may list and read
+//! notes, `appendOnly` may only add text to the end of existing notes or
+//! create new ones, and `full` may also rewrite them. A token is shown once when created; only its
+//! SHA-256 hash is kept, in `.scratch/tokens.json`. Surfaces map every request
+//! to an `Operation` and check it with `authorize` before touching a note.
+
+use crate::store;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+const STORE_FILE: &str = "tokens.json";
+const TOKEN_PREFIX: &str = "scr_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Surface {
+    Http,      // vault server
+    Extension, // browser extension, on the vault server's `/clip`
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Scope {
+    ReadOnly,
+    AppendOnly,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Read,   // list and view notes
+    Append, // add to the end of an existing note
+    Create, // add a new note
+    Write,  // replace a note's content
+}
+
+impl Scope {
+    pub fn allows(self, operation: Operation) -> bool {
+        matches!(
+            (self, operation),
+            (Scope::Full, _)
+                | (Scope::ReadOnly, Operation::Read)
+                | (Scope::AppendOnly, Operation::Append | Operation::Create)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenRecord {
+    id: String,
+    label: String,
+    surface: Surface,
+    scope: Scope,
+    hash: String, // hex SHA-256 of the token
+    created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub id: String,
+    pub label: String,
+    pub surface: Surface,
+    pub scope: Scope,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewToken {
+    pub token: String, // only ever returned here
+    pub info: TokenInfo,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenStore {
+    tokens: Vec<TokenRecord>,
+}
+
+impl TokenStore {
+    fn load(notes_root: &Path) -> Self {
+        store::load(notes_root, STORE_FILE)
+    }
+
+    fn save(&self, notes_root: &Path) -> Result<()> {
+        store::save(notes_root, STORE_FILE, self)
+    }
+}
+
+impl From<&TokenRecord> for TokenInfo {
+    fn from(record: &TokenRecord) -> Self {
+        Self {
+            id: record.id.clone(),
+            label: record.label.clone(),
+            surface: record.surface,
+            scope: record.scope,
+            created_at: record.created_at,
+        }
+    }
+}
+
+fn hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn create(notes_root: &Path, surface: Surface, scope: Scope, label: &str) -> Result<NewToken, String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("Token label cannot be empty".to_string());
+    }
+    let token = format!("{}{}", TOKEN_PREFIX, random_hex(24));
+    let record = TokenRecord {
+        id: random_hex(8),
+        label: label.to_string(),
+        surface,
+        scope,
+        hash: hash(&token),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    let info = TokenInfo::from(&record);
+
+    let mut store = TokenStore::load(notes_root);
+    store.tokens.push(record);
+    store.save(notes_root).map_err(|e| e.to_string())?;
+    Ok(NewToken { token, info })
+}
+
+pub fn list(notes_root: &Path) -> Vec<TokenInfo> {
+    TokenStore::load(notes_root).tokens.iter().map(TokenInfo::from).collect()
+}
+
+pub fn revoke(notes_root: &Path, id: &str) -> Result<(), String> {
+    let mut store = TokenStore::load(notes_root);
+    let before = store.tokens.len();
+    store.tokens.retain(|t| t.id != id);
+    if store.tokens.len() == before {
+        return Err("Token not found".to_string());
+    }
+    store.save(notes_root).map_err(|e| e.to_string())
+}
+
+/// The scope a presented token grants on a surface, or None if it is unknown
+/// or was issued for another surface.
+pub fn scope_for(notes_root: &Path, surface: Surface, token: &str) -> Option<Scope> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return None;
+    }
+    let presented = hash(token);
+    TokenStore::load(notes_root)
+        .tokens
+        .iter()
+        .find(|t| t.surface == surface && t.hash == presented)
+        .map(|t| t.scope)
+}
+
+/// Check an operation against a granted scope.
+pub fn authorize(scope: Scope, operation: Operation) -> Result<(), String> {
+    if scope.allows(operation) {
+        Ok(())
+    } else {
+        Err(format!("{:?} access does not permit {:?}", scope, operation))
+    }
+}
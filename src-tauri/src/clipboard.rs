@@ -0,0 +1,469 @@
+//! Notes made from whatever is on the clipboard.
+//!
+//! A list of copied files becomes a list of links (wikilinks for notes in the
+//! vault, embeds for images, which are copied into the note's attachments
+//! unless over the import limits, `file://` links otherwise). An image is saved as a PNG attachment and
+//! embedded. HTML, passed in by the webview (which can read that flavour) or
+//! found as the clipboard text, is converted to markdown. Anything else is
+//! kept as plain text. The note is titled by its first heading, else
+//! "Clipboard <date> <time>". HTML copied from a browser on Windows names its
+//! page (`SourceURL:`), which the note keeps as `source` in its frontmatter.
+//! Pages and selections a browser extension sends to the vault server's
+//! `/clip` go through the same conversion, titled by the page.
+
+use crate::{
+    archive, assets, attachment_folder, audit, claim_note_file, expand_note_name_template,
+    extract_title, frontmatter, id_from_abs_path, import_limits, is_markdown_extension,
+    rel_path_from_id, sanitize_filename, AppState, Note,
+};
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp"];
+
+enum Clip {
+    Files(Vec<PathBuf>),
+    Image(Vec<u8>), // PNG
+    Html(String),
+    Text(String),
+}
+
+fn read(app: &AppHandle, html: Option<String>) -> Result<Clip, String> {
+    if let Some(html) = html.filter(|h| !h.trim().is_empty()) {
+        return Ok(Clip::Html(html));
+    }
+    let text = app.clipboard().read_text().unwrap_or_default();
+    if let Some(files) = file_list(&text) {
+        return Ok(Clip::Files(files));
+    }
+    if text.trim().is_empty() {
+        let image = app
+            .clipboard()
+            .read_image()
+            .map_err(|_| "The clipboard is empty".to_string())?;
+        return encode_png(image.width(), image.height(), image.rgba()).map(Clip::Image);
+    }
+    if looks_like_html(&text) {
+        return Ok(Clip::Html(text));
+    }
+    Ok(Clip::Text(text))
+}
+
+/// Copied files arrive as one `file://` URI or absolute path per line.
+fn file_list(text: &str) -> Option<Vec<PathBuf>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    lines
+        .into_iter()
+        .map(|line| {
+            let path = if line.starts_with("file://") {
+                url::Url::parse(line).ok()?.to_file_path().ok()?
+            } else {
+                PathBuf::from(line)
+            };
+            (path.is_absolute() && path.exists()).then_some(path)
+        })
+        .collect()
+}
+
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(rgba).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+fn looks_like_html(text: &str) -> bool {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"(?i)</?(html|body|div|p|span|a|h[1-6]|ul|ol|li|table|br|strong|em|b|i)[\s/>]")
+            .unwrap()
+    });
+    text.trim_start().starts_with('<') && re.is_match(text)
+}
+
+/// The page a Windows clipboard HTML fragment (CF_HTML) was copied from.
+fn source_url(html: &str) -> Option<String> {
+    let header = &html[..html.find('<').unwrap_or(html.len())];
+    header
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("SourceURL:"))
+        .map(str::trim)
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+        .map(String::from)
+}
+
+fn re(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+fn decode_entities(text: &str) -> String {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    re(&ENTITY, r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);")
+        .replace_all(text, |cap: &Captures| {
+            let name = &cap[1];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| name.strip_prefix('#').map(|dec| dec.parse()))
+                    .and_then(|n| n.ok())
+                    .and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| cap[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+pub fn strip_tags(html: &str) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    decode_entities(&re(&TAG, r"<[^>]*>").replace_all(html, ""))
+}
+
+pub fn attr(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"(?i)\s{}\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#, name);
+    let cap = Regex::new(&pattern).ok()?.captures(tag)?;
+    let value = cap.get(1).or(cap.get(2)).or(cap.get(3))?.as_str();
+    Some(decode_entities(value))
+}
+
+/// Convert an HTML fragment to markdown: headings, paragraphs, emphasis,
+/// links, images, lists, quotes, code and rules. Other tags are dropped.
+pub fn html_to_markdown(html: &str) -> String {
+    static DROP: OnceLock<Regex> = OnceLock::new();
+    static PRE: OnceLock<Regex> = OnceLock::new();
+    static CODE: OnceLock<Regex> = OnceLock::new();
+    static SPACE: OnceLock<Regex> = OnceLock::new();
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    static STRONG: OnceLock<Regex> = OnceLock::new();
+    static EM: OnceLock<Regex> = OnceLock::new();
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    static IMG: OnceLock<Regex> = OnceLock::new();
+    static OL: OnceLock<Regex> = OnceLock::new();
+    static LI: OnceLock<Regex> = OnceLock::new();
+    static QUOTE: OnceLock<Regex> = OnceLock::new();
+    static BR: OnceLock<Regex> = OnceLock::new();
+    static HR: OnceLock<Regex> = OnceLock::new();
+    static BLOCK: OnceLock<Regex> = OnceLock::new();
+    static TRAILING: OnceLock<Regex> = OnceLock::new();
+    static BLANK: OnceLock<Regex> = OnceLock::new();
+
+    let html = re(
+        &DROP,
+        r"(?is)<!--.*?-->|<head\b.*?</head>|<script\b.*?</script>|<style\b.*?</style>",
+    )
+    .replace_all(html, "");
+
+    // Code keeps its whitespace, so it is set aside until the end
+    let mut stash: Vec<String> = Vec::new();
+    let mut keep = |text: String| {
+        stash.push(text);
+        format!("\u{0}{}\u{0}", stash.len() - 1)
+    };
+    let html = re(&PRE, r"(?is)<pre\b[^>]*>(.*?)</pre>").replace_all(&html, |cap: &Captures| {
+        let code = strip_tags(&cap[1]);
+        format!(
+            "\n\n{}\n\n",
+            keep(format!("```\n{}\n```", code.trim_matches('\n')))
+        )
+    });
+    let html = re(&CODE, r"(?is)<code\b[^>]*>(.*?)</code>").replace_all(&html, |cap: &Captures| {
+        keep(format!("`{}`", strip_tags(&cap[1])))
+    });
+    let html = re(&SPACE, r"\s+").replace_all(&html, " ");
+
+    let html = re(&HEADING, r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]>").replace_all(
+        &html,
+        |cap: &Captures| {
+            let level: usize = cap[1].parse().unwrap_or(1);
+            format!(
+                "\n\n{} {}\n\n",
+                "#".repeat(level),
+                strip_tags(&cap[2]).trim()
+            )
+        },
+    );
+    let html =
+        re(&STRONG, r"(?is)<(?:strong|b)\b[^>]*>(.*?)</(?:strong|b)>").replace_all(&html, "**$1**");
+    let html = re(&EM, r"(?is)<(?:em|i)\b[^>]*>(.*?)</(?:em|i)>").replace_all(&html, "*$1*");
+    let html = re(&LINK, r"(?is)(<a\b[^>]*>)(.*?)</a>").replace_all(&html, |cap: &Captures| {
+        let text = strip_tags(&cap[2]);
+        match attr(&cap[1], "href") {
+            Some(href) => format!("[{}]({})", text.trim(), href),
+            None => text,
+        }
+    });
+    let html = re(&IMG, r"(?is)<img\b[^>]*>").replace_all(&html, |cap: &Captures| {
+        match attr(&cap[0], "src") {
+            Some(src) => format!("![{}]({})", attr(&cap[0], "alt").unwrap_or_default(), src),
+            None => String::new(),
+        }
+    });
+    let html = re(&OL, r"(?is)<ol\b[^>]*>(.*?)</ol>").replace_all(&html, |cap: &Captures| {
+        re(&LI, r"(?i)<li\b[^>]*>")
+            .replace_all(&cap[1], "\n1. ")
+            .into_owned()
+            + "\n\n"
+    });
+    let html = re(&LI, r"(?i)<li\b[^>]*>").replace_all(&html, "\n- ");
+    let html = re(&QUOTE, r"(?is)<blockquote\b[^>]*>(.*?)</blockquote>").replace_all(
+        &html,
+        |cap: &Captures| {
+            let inner = html_to_markdown(&cap[1]);
+            let quoted: Vec<String> = inner
+                .lines()
+                .map(|line| format!("> {}", line).trim_end().to_string())
+                .collect();
+            format!("\n\n{}\n\n", quoted.join("\n"))
+        },
+    );
+    let html = re(&BR, r"(?i)<br\s*/?>").replace_all(&html, "\n");
+    let html = re(&HR, r"(?i)<hr\b[^>]*>").replace_all(&html, "\n\n---\n\n");
+    let html = re(
+        &BLOCK,
+        r"(?i)</?(?:p|div|section|article|header|footer|ul|table|tr)\b[^>]*>",
+    )
+    .replace_all(&html, "\n\n");
+
+    let text = strip_tags(&html);
+    let text = re(&TRAILING, r"(?m)^[ \t]+|[ \t]+$").replace_all(&text, "");
+    let text = re(&BLANK, r"\n{3,}").replace_all(&text, "\n\n");
+
+    let mut markdown = text.trim().to_string();
+    for (i, code) in stash.iter().enumerate() {
+        markdown = markdown.replace(&format!("\u{0}{}\u{0}", i), code);
+    }
+    markdown
+}
+
+/// Markdown for a list of copied files. Images are copied into the note's
+/// attachments. Blocking.
+fn files_body(
+    state: &AppState,
+    notes_root: &Path,
+    note_id: &str,
+    files: &[PathBuf],
+) -> Result<String, String> {
+    let canonical_root = notes_root.canonicalize().map_err(|e| e.to_string())?;
+    let limits = import_limits(state);
+    let mut lines = Vec::new();
+    for path in files {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let in_vault = path
+            .canonicalize()
+            .ok()
+            .filter(|p| is_markdown_extension(p))
+            .and_then(|p| id_from_abs_path(&canonical_root, &p));
+        let is_image = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+        // An image over the import limits is linked where it is instead
+        let within_limits = std::fs::metadata(path)
+            .is_ok_and(|m| m.is_file() && limits.check_attachment(&name, &name, m.len()).is_ok());
+
+        let line = if let Some(id) = in_vault {
+            format!("- [[{}]]", id)
+        } else if is_image && within_limits {
+            let folder = attachment_folder(state, notes_root, Some(note_id))?;
+            let dir = notes_root.join(&folder);
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let target = assets::unique_name(&dir, &name);
+            std::fs::copy(path, dir.join(&target)).map_err(|e| e.to_string())?;
+            format!(
+                "![{}]({})",
+                name,
+                assets::join(&folder, &target).replace(' ', "%20")
+            )
+        } else {
+            let href = url::Url::from_file_path(path)
+                .map(|u| u.to_string())
+                .unwrap_or_else(|_| path.to_string_lossy().into_owned());
+            format!("- [{}]({})", name, href)
+        };
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
+
+fn save_image(
+    state: &AppState,
+    notes_root: &Path,
+    note_id: &str,
+    png: &[u8],
+) -> Result<String, String> {
+    let folder = attachment_folder(state, notes_root, Some(note_id))?;
+    let dir = notes_root.join(&folder);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let name = assets::unique_name(
+        &dir,
+        &format!("clipboard-{}.png", chrono::Utc::now().timestamp()),
+    );
+    std::fs::write(dir.join(&name), png).map_err(|e| e.to_string())?;
+    Ok(format!("![]({})", assets::join(&folder, &name)))
+}
+
+/// Create a note in `target_folder` (the vault root if `None`) from the
+/// clipboard. `html` is the clipboard's HTML flavour when the caller can read
+/// it; `surface` is who asked, for the audit log. Blocking.
+pub fn create_note(
+    app: &AppHandle,
+    html: Option<String>,
+    target_folder: Option<&str>,
+    surface: audit::Surface,
+) -> Result<Note, String> {
+    let clip = read(app, html)?;
+    let source = match &clip {
+        Clip::Html(html) => source_url(html),
+        _ => None,
+    };
+    create(app, clip, source, None, target_folder, surface)
+}
+
+/// Create a note in `target_folder` from HTML a browser extension sent (the
+/// selection, or the whole page). `url` is kept as the note's `source`;
+/// `title` names the note when the HTML has no heading first. Blocking.
+pub fn create_web_note(
+    app: &AppHandle,
+    html: &str,
+    url: Option<&str>,
+    title: Option<&str>,
+    target_folder: Option<&str>,
+    surface: audit::Surface,
+) -> Result<Note, String> {
+    if html.trim().is_empty() {
+        return Err("Nothing to clip".to_string());
+    }
+    let source = url
+        .map(str::trim)
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+        .map(String::from);
+    let title = title
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|t| !t.is_empty());
+    create(
+        app,
+        Clip::Html(html.to_string()),
+        source,
+        title,
+        target_folder,
+        surface,
+    )
+}
+
+fn create(
+    app: &AppHandle,
+    clip: Clip,
+    source: Option<String>,
+    fallback_title: Option<String>,
+    target_folder: Option<&str>,
+    surface: audit::Surface,
+) -> Result<Note, String> {
+    let state = app.state::<AppState>();
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let notes_root = PathBuf::from(&folder);
+
+    let body = match &clip {
+        Clip::Html(html) => html_to_markdown(html),
+        Clip::Text(text) => text.trim_end().to_string(),
+        Clip::Files(_) | Clip::Image(_) => String::new(),
+    };
+    let has_heading = body.trim_start().starts_with("# ");
+    let title = if has_heading {
+        extract_title(&body)
+    } else {
+        fallback_title.unwrap_or_else(|| expand_note_name_template("Clipboard {date} {time}"))
+    };
+
+    let prefix = target_folder
+        .map(|f| f.trim_matches('/'))
+        .filter(|f| !f.is_empty())
+        .map(|f| format!("{}/", f))
+        .unwrap_or_default();
+    let base_id = format!("{}{}", prefix, sanitize_filename(&title));
+    let (id, file_path) = claim_note_file(&notes_root, None, |attempt| match attempt {
+        0 => base_id.clone(),
+        n => format!("{}-{}", base_id, n),
+    })
+    .map_err(|e| e.to_string())?;
+
+    let written = match clip {
+        Clip::Files(files) => files_body(&state, &notes_root, &id, &files),
+        Clip::Image(png) => save_image(&state, &notes_root, &id, &png),
+        Clip::Html(_) | Clip::Text(_) => Ok(body),
+    }
+    .map(|body| {
+        if has_heading {
+            format!("{}\n", body)
+        } else {
+            format!("# {}\n\n{}\n", title, body)
+        }
+    })
+    .map(|content| match source {
+        Some(url) => frontmatter::set(&content, "source", frontmatter::Value::Scalar(url)),
+        None => content,
+    })
+    .and_then(|content| {
+        std::fs::write(&file_path, &content).map_err(|e| e.to_string())?;
+        Ok(content)
+    });
+    let content = match written {
+        Ok(content) => content,
+        Err(e) => {
+            let _ = std::fs::remove_file(&file_path);
+            return Err(e);
+        }
+    };
+
+    let modified = chrono::Utc::now().timestamp();
+    let title = extract_title(&content);
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(&id, &title, &content, modified);
+        }
+    }
+
+    state.audit.record(
+        &notes_root,
+        audit::Change::new(surface, audit::Action::Create, &id).after(Some(content.as_bytes())),
+    );
+    archive::archive_in_background(app, &id, &content);
+
+    Ok(Note {
+        path: rel_path_from_id(&id),
+        id,
+        title,
+        content,
+        modified,
+    })
+}
@@ -0,0 +1,115 @@
+//! TODO, FIXME and HACK markers in code.
+//!
+//! Dev-journal notes keep code in fenced blocks and inline backticks, and a
+//! marker there (`// TODO: handle EOF`, `` `FIXME(ana): retry` ``) is an open
+//! issue. `collect` gathers them across the vault with the note, the 0-based
+//! line and the language of the block; inline code has no language. Markers
+//! in prose are left alone, as are `output` blocks written by `runner` and
+//! locked notes.
+
+use crate::{note_exposure, runner, LockedNotePolicy, ScannedNote};
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+const LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeAnnotation {
+    pub note_id: String,
+    pub note_title: String,
+    pub line: usize,              // 0-based line in the note
+    pub marker: &'static str,     // "TODO", "FIXME" or "HACK"
+    pub text: String,             // after the marker, comment closers removed
+    pub language: Option<String>, // info string of the block
+    pub inline: bool,
+}
+
+fn marker_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b(TODO|FIXME|HACK)\b(?:\([^)]*\))?[:!]?\s*(.*)$").unwrap())
+}
+
+fn inline_code_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"`([^`\n]+)`").unwrap())
+}
+
+/// The first marker in `code` and the text after it.
+fn find_marker(code: &str) -> Option<(&'static str, String)> {
+    let caps = marker_re().captures(code)?;
+    let marker = match &caps[1] {
+        "TODO" => "TODO",
+        "FIXME" => "FIXME",
+        _ => "HACK",
+    };
+    let text = caps[2]
+        .trim()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim_end_matches("#}")
+        .trim_end();
+    Some((marker, text.to_string()))
+}
+
+/// The markers in one note's code, in line order.
+fn extract(note: &ScannedNote) -> Vec<CodeAnnotation> {
+    let annotation = |line: usize,
+                      (marker, text): (&'static str, String),
+                      language: Option<String>,
+                      inline: bool| CodeAnnotation {
+        note_id: note.id.clone(),
+        note_title: note.title.clone(),
+        line,
+        marker,
+        text,
+        language,
+        inline,
+    };
+    let mut found = Vec::new();
+    // Open fence: character, length and language
+    let mut open: Option<(char, usize, String)> = None;
+    for (i, line) in note.content.lines().enumerate() {
+        match open.as_ref() {
+            None => {
+                if let Some((c, len, info)) = runner::fence(line) {
+                    let language = info.split_whitespace().next().unwrap_or_default();
+                    open = Some((c, len, language.to_ascii_lowercase()));
+                    continue;
+                }
+                for span in inline_code_re().captures_iter(line) {
+                    if let Some(found_marker) = find_marker(&span[1]) {
+                        found.push(annotation(i, found_marker, None, true));
+                    }
+                }
+            }
+            Some((c, len, language)) => {
+                let closes = runner::fence(line)
+                    .is_some_and(|(fc, flen, info)| fc == *c && flen >= *len && info.is_empty());
+                if closes {
+                    open = None;
+                } else if language != runner::OUTPUT_LANGUAGE {
+                    if let Some(found_marker) = find_marker(line) {
+                        let language = (!language.is_empty()).then(|| language.clone());
+                        found.push(annotation(i, found_marker, language, false));
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Code annotations across `notes`, by note ID and then line.
+pub fn collect(notes: &[ScannedNote], locked_policy: &LockedNotePolicy) -> Vec<CodeAnnotation> {
+    let mut notes: Vec<&ScannedNote> = notes.iter().collect();
+    notes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    notes
+        .into_iter()
+        .filter(|note| !note_exposure(&note.content, locked_policy).locked)
+        .flat_map(extract)
+        .take(LIMIT)
+        .collect()
+}
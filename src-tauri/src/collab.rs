@@ -0,0 +1,437 @@
+//! Experimental live co-editing of a single note over the local network.
+//!
+//! The host keeps the note in a CRDT text document (yrs, the Rust port of Yjs)
+//! and accepts TCP connections from guests presenting the secret from the join
+//! code (`ip:port-SECRET`) within `HANDSHAKE_TIMEOUT`. Every message after
+//! that is a length-prefixed yrs v1 update: the host first sends its full
+//! document state, then both sides exchange incremental updates and the host
+//! relays each guest's updates to the others. The host saves remote edits to
+//! the note the way the app saves one, under the note's lock.
+
+use crate::{abs_path_from_id, audit, snapshot_history, write_note_content_from, AppState};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use serde::Serialize;
+use std::net::{IpAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, watch};
+use yrs::updates::decoder::Decode;
+use yrs::{Doc, GetString, OffsetKind, Options, ReadTxn, StateVector, Text, Transact, Update};
+
+const TEXT_NAME: &str = "content";
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const SECRET_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// Origin used for edits made in this instance; connected peers count from 1.
+const LOCAL_ORIGIN: u64 = 0;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub code: String,
+    pub is_host: bool,
+    pub note_id: Option<String>, // set for the host only
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ContentEvent {
+    note_id: Option<String>,
+    content: String,
+}
+
+/// State shared between the session handle and its connection tasks.
+struct Shared {
+    doc: Mutex<Doc>,
+    note_id: Option<String>, // set for the host, which saves remote edits to the note
+    outgoing: broadcast::Sender<(u64, Vec<u8>)>,
+    app: AppHandle,
+}
+
+impl Shared {
+    fn new(app: AppHandle, note_id: Option<String>) -> Arc<Self> {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Bytes,
+            ..Options::default()
+        });
+        let (outgoing, _) = broadcast::channel(256);
+        Arc::new(Self {
+            doc: Mutex::new(doc),
+            note_id,
+            outgoing,
+            app,
+        })
+    }
+
+    fn content(&self) -> String {
+        let doc = self.doc.lock().expect("collab doc mutex");
+        let text = doc.get_or_insert_text(TEXT_NAME);
+        let txn = doc.transact();
+        text.get_string(&txn)
+    }
+
+    fn full_state(&self) -> Vec<u8> {
+        let doc = self.doc.lock().expect("collab doc mutex");
+        let txn = doc.transact();
+        txn.encode_state_as_update_v1(&StateVector::default())
+    }
+
+    /// Replace the document text with `new`, expressed as a single splice so
+    /// concurrent edits elsewhere in the note merge cleanly.
+    fn apply_local(&self, new: &str) -> Option<Vec<u8>> {
+        let doc = self.doc.lock().expect("collab doc mutex");
+        let text = doc.get_or_insert_text(TEXT_NAME);
+        let mut txn = doc.transact_mut();
+        let old = text.get_string(&txn);
+        if old == new {
+            return None;
+        }
+
+        let prefix: usize = old
+            .chars()
+            .zip(new.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        let suffix: usize = old[prefix..]
+            .chars()
+            .rev()
+            .zip(new[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+
+        let removed = old.len() - prefix - suffix;
+        if removed > 0 {
+            text.remove_range(&mut txn, prefix as u32, removed as u32);
+        }
+        let inserted = &new[prefix..new.len() - suffix];
+        if !inserted.is_empty() {
+            text.insert(&mut txn, prefix as u32, inserted);
+        }
+        Some(txn.encode_update_v1())
+    }
+
+    fn apply_remote(&self, update: &[u8]) -> Result<String, String> {
+        let update = Update::decode_v1(update).map_err(|e| e.to_string())?;
+        let doc = self.doc.lock().expect("collab doc mutex");
+        let text = doc.get_or_insert_text(TEXT_NAME);
+        let mut txn = doc.transact_mut();
+        txn.apply_update(update).map_err(|e| e.to_string())?;
+        Ok(text.get_string(&txn))
+    }
+
+    fn notes_folder(&self) -> Option<PathBuf> {
+        let state = self.app.state::<AppState>();
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.as_ref().map(PathBuf::from)
+    }
+
+    /// Save the hosted note under its lock: atomically, audited, indexed and
+    /// snapshotted in its history.
+    async fn save(&self, id: &str, content: String) {
+        let Some(folder) = self.notes_folder() else {
+            return;
+        };
+        let Ok(path) = abs_path_from_id(&folder, id) else {
+            return;
+        };
+        let state = self.app.state::<AppState>();
+        let _guard = state.note_locks.lock(&folder, &[id]).await;
+        let previous = tokio::fs::read_to_string(&path).await.ok();
+        let saved = write_note_content_from(
+            &state,
+            audit::Surface::Sync,
+            Some(audit::Action::Save),
+            &folder,
+            id,
+            content.clone(),
+        )
+        .await;
+        if saved.is_err() {
+            return;
+        }
+        state
+            .notes_cache
+            .write()
+            .expect("cache write lock")
+            .remove(id);
+        snapshot_history(&self.app, &folder, id, previous, content).await;
+    }
+
+    /// Handle an update from a peer: merge it, relay it and surface the result.
+    async fn receive(&self, origin: u64, update: Vec<u8>) {
+        let Ok(content) = self.apply_remote(&update) else {
+            return;
+        };
+        let _ = self.outgoing.send((origin, update));
+        if let Some(id) = &self.note_id {
+            self.save(id, content.clone()).await;
+        }
+        let _ = self.app.emit(
+            "collab-content",
+            ContentEvent {
+                note_id: self.note_id.clone(),
+                content,
+            },
+        );
+    }
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, data: &[u8]) -> std::io::Result<()> {
+    writer.write_u32(data.len() as u32).await?;
+    writer.write_all(data).await?;
+    writer.flush().await
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_len: usize,
+) -> std::io::Result<Vec<u8>> {
+    let len = reader.read_u32().await? as usize;
+    if len > max_len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Pump updates between one peer connection and the shared document until the
+/// peer disconnects or the session ends.
+async fn run_connection(
+    shared: Arc<Shared>,
+    mut reader: OwnedReadHalf,
+    mut writer: OwnedWriteHalf,
+    mut outgoing: broadcast::Receiver<(u64, Vec<u8>)>,
+    peer_id: u64,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    // Reads run in their own task: read_frame is not cancel-safe, so it must not
+    // race against the outgoing queue in a select!
+    let reader_shared = shared.clone();
+    let mut reader_shutdown = shutdown.clone();
+    let mut reader_task = tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                frame = read_frame(&mut reader, MAX_FRAME_LEN) => match frame {
+                    Ok(update) => reader_shared.receive(peer_id, update).await,
+                    Err(_) => break,
+                },
+                _ = reader_shutdown.changed() => break,
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => {
+                let sent = match message {
+                    Ok((origin, _)) if origin == peer_id => Ok(()),
+                    Ok((_, update)) => write_frame(&mut writer, &update).await,
+                    // Fell behind: resend the whole document, the CRDT dedupes it
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        write_frame(&mut writer, &shared.full_state()).await
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if sent.is_err() {
+                    break;
+                }
+            }
+            _ = &mut reader_task => break,
+            _ = shutdown.changed() => break,
+        }
+    }
+    reader_task.abort();
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 8];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|b| SECRET_ALPHABET[*b as usize % SECRET_ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Best-effort LAN address of this machine. Connecting a UDP socket sends no
+/// packets; it only selects the outbound interface.
+pub fn local_ip() -> IpAddr {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("8.8.8.8:80")?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]))
+}
+
+/// A running host or guest session. Dropping it ends the session.
+pub struct Session {
+    info: SessionInfo,
+    shared: Arc<Shared>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+impl Session {
+    /// Start hosting `content` of note `note_id` on an ephemeral port.
+    pub async fn host(app: AppHandle, note_id: String, content: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(("0.0.0.0", 0))
+            .await
+            .map_err(|e| format!("Failed to start session: {}", e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let secret = generate_secret();
+        let code = format!("{}:{}-{}", local_ip(), port, secret);
+
+        let shared = Shared::new(app, Some(note_id.clone()));
+        shared.apply_local(content);
+        let (shutdown, shutdown_rx) = watch::channel(false);
+
+        let accept_shared = shared.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut shutdown = shutdown_rx;
+            let mut next_peer = LOCAL_ORIGIN + 1;
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => {
+                        let Ok((stream, _)) = accepted else { continue };
+                        let peer_id = next_peer;
+                        next_peer += 1;
+                        tauri::async_runtime::spawn(handle_guest(
+                            accept_shared.clone(),
+                            stream,
+                            peer_id,
+                            secret.clone(),
+                            shutdown.clone(),
+                        ));
+                    }
+                    _ = shutdown.changed() => break,
+                }
+            }
+        });
+
+        Ok(Self {
+            info: SessionInfo {
+                code,
+                is_host: true,
+                note_id: Some(note_id),
+                content: content.to_string(),
+            },
+            shared,
+            shutdown,
+        })
+    }
+
+    /// Join a hosted session using its join code.
+    pub async fn join(app: AppHandle, code: &str) -> Result<Self, String> {
+        let (addr, secret) = code
+            .trim()
+            .rsplit_once('-')
+            .ok_or("Invalid join code")?;
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| format!("Failed to connect to session: {}", e))?;
+        let (mut reader, mut writer) = stream.into_split();
+
+        write_frame(&mut writer, secret.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        let state = read_frame(&mut reader, MAX_FRAME_LEN)
+            .await
+            .map_err(|_| "Session rejected the join code".to_string())?;
+
+        let shared = Shared::new(app.clone(), None);
+        let content = shared.apply_remote(&state)?;
+        let outgoing = shared.outgoing.subscribe();
+        let (shutdown, shutdown_rx) = watch::channel(false);
+
+        let connection_shared = shared.clone();
+        tauri::async_runtime::spawn(async move {
+            run_connection(
+                connection_shared,
+                reader,
+                writer,
+                outgoing,
+                LOCAL_ORIGIN + 1,
+                shutdown_rx.clone(),
+            )
+            .await;
+            // Host went away (as opposed to us leaving)
+            if !*shutdown_rx.borrow() {
+                let _ = app.emit("collab-ended", ());
+            }
+        });
+
+        Ok(Self {
+            info: SessionInfo {
+                code: code.trim().to_string(),
+                is_host: false,
+                note_id: None,
+                content,
+            },
+            shared,
+            shutdown,
+        })
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            content: self.shared.content(),
+            ..self.info.clone()
+        }
+    }
+
+    /// Push a local edit (the editor's full content) to the other participants.
+    pub fn update_content(&self, content: &str) {
+        if let Some(update) = self.shared.apply_local(content) {
+            let _ = self.shared.outgoing.send((LOCAL_ORIGIN, update));
+        }
+    }
+}
+
+async fn handle_guest(
+    shared: Arc<Shared>,
+    stream: TcpStream,
+    peer_id: u64,
+    secret: String,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let (mut reader, mut writer) = stream.into_split();
+    // Nothing bigger than the secret is read before it checks out
+    let handshake = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_frame(&mut reader, secret.len()));
+    let presented = tokio::select! {
+        frame = handshake => frame,
+        _ = shutdown.changed() => return,
+    };
+    let Ok(Ok(presented)) = presented else {
+        return;
+    };
+    if !bool::from(presented.ct_eq(secret.as_bytes())) {
+        return;
+    }
+
+    // Subscribe before sending the snapshot so no update falls in between
+    let outgoing = shared.outgoing.subscribe();
+    if write_frame(&mut writer, &shared.full_state()).await.is_err() {
+        return;
+    }
+    run_connection(shared, reader, writer, outgoing, peer_id, shutdown).await;
+}
@@ -0,0 +1,34 @@
+//! Locale-aware ordering for note titles and tags.
+//!
+//! Uses ICU collation for the `collationLocale` setting (a BCP 47 tag such as
+//! `de` or `sv-SE`), falling back to the root collation when it is unset or
+//! invalid, so "Ärger" sorts next to "Arger" and case only breaks ties.
+//! Numbers compare by value ("Note 2" before "Note 10").
+
+use icu_collator::{Collator, CollatorOptions, Numeric};
+use icu_locid::Locale;
+use std::cmp::Ordering;
+
+pub struct Collation {
+    collator: Option<Collator>,
+}
+
+impl Collation {
+    pub fn new(locale: Option<&str>) -> Self {
+        let locale = locale
+            .and_then(|tag| tag.trim().parse::<Locale>().ok())
+            .unwrap_or(Locale::UND);
+        let mut options = CollatorOptions::new();
+        options.numeric = Some(Numeric::On);
+        Self {
+            collator: Collator::try_new(&(&locale).into(), options).ok(),
+        }
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self.collator {
+            Some(ref collator) => collator.compare(a, b),
+            None => a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b)),
+        }
+    }
+}
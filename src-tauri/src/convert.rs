@@ -0,0 +1,469 @@
+//! Plain-text formats converted to markdown for `import_files`.
+//!
+//! - `.txt` and markdown are kept as written.
+//! - `.org`: headings, TODO/DONE keywords, emphasis, links, lists, source,
+//!   example and quote blocks; `#+TITLE` names the note, heading tags and
+//!   `#+FILETAGS` become the note's tags, drawers and other `#+` lines are
+//!   dropped.
+//! - `.textile`: headings, quotes, code, emphasis, links and lists.
+//! - `.rtf`: the text, paragraph breaks kept, formatting dropped.
+//!
+//! Files that are not UTF-8 are read as Windows-1252, which covers most old
+//! text files; line endings become `\n`.
+
+use regex::{Captures, Regex};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// A converted file: its title if the format names one, tags, and the body.
+pub struct Converted {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub body: String,
+}
+
+const EXTENSIONS: &[&str] = &["txt", "text", "org", "textile", "rtf", "md", "markdown"];
+
+fn re(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+/// Windows-1252 characters for bytes 0x80-0x9F; the rest match Latin-1.
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+fn cp1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => CP1252_HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+/// File bytes as text: UTF-8 (without a BOM) if valid, else Windows-1252.
+pub fn decode(bytes: Vec<u8>) -> String {
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().into_iter().map(cp1252).collect(),
+    };
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(&text);
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Whether `import_files` can convert the file at `path`.
+pub fn is_supported(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        EXTENSIONS
+            .iter()
+            .any(|known| ext.eq_ignore_ascii_case(known))
+    })
+}
+
+/// Convert a file's text by its extension. None for unsupported types.
+pub fn to_markdown(extension: &str, text: &str) -> Option<Converted> {
+    let plain = |body: String| Converted {
+        title: None,
+        tags: Vec::new(),
+        body,
+    };
+    match extension.to_ascii_lowercase().as_str() {
+        "txt" | "text" | "md" | "markdown" => Some(plain(text.trim_end().to_string())),
+        "org" => Some(org(text)),
+        "textile" => Some(plain(textile(text))),
+        "rtf" => Some(plain(rtf(text))),
+        _ => None,
+    }
+}
+
+/// Org and textile inline markup: emphasis and code between markers that
+/// stand at word boundaries. Fenced code is left alone.
+fn inline(text: &str, rules: &[(&'static OnceLock<Regex>, char, &str)]) -> String {
+    let convert = |chunk: &str| {
+        let mut chunk = chunk.to_string();
+        for (cell, marker, replacement) in rules {
+            let m = regex::escape(&marker.to_string());
+            let pattern =
+                format!(r"(^|[\s(\[]){m}([^\s{m}](?:[^{m}\n]*?[^\s{m}])?){m}($|[\s.,;:!?)\]])");
+            chunk = re(cell, &pattern)
+                .replace_all(&chunk, |cap: &Captures| {
+                    format!(
+                        "{}{}{}{}{}",
+                        &cap[1], replacement, &cap[2], replacement, &cap[3]
+                    )
+                })
+                .into_owned();
+        }
+        chunk
+    };
+
+    let mut out = Vec::new();
+    let mut prose = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            if !in_fence {
+                out.push(convert(&prose.join("\n")));
+                prose.clear();
+            }
+            in_fence = !in_fence;
+            out.push(line.to_string());
+        } else if in_fence {
+            out.push(line.to_string());
+        } else {
+            prose.push(line);
+        }
+    }
+    out.push(convert(&prose.join("\n")));
+    out.join("\n")
+}
+
+/// Add the tags of an org `:a:b:` list that are not there yet.
+fn add_tags(list: &str, tags: &mut Vec<String>) {
+    for tag in list.split(':').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+}
+
+fn org(text: &str) -> Converted {
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    static KEYWORD: OnceLock<Regex> = OnceLock::new();
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    static ORDERED: OnceLock<Regex> = OnceLock::new();
+    static BOLD: OnceLock<Regex> = OnceLock::new();
+    static ITALIC: OnceLock<Regex> = OnceLock::new();
+    static VERBATIM: OnceLock<Regex> = OnceLock::new();
+    static CODE: OnceLock<Regex> = OnceLock::new();
+    static STRIKE: OnceLock<Regex> = OnceLock::new();
+
+    let heading = re(
+        &HEADING,
+        r"^(\*+)\s+(?:(TODO|DONE)\s+)?(.*?)(?:\s+(:[\w@#%:]+:))?\s*$",
+    );
+    let keyword = re(&KEYWORD, r"(?i)^#\+([A-Z_]+):?\s*(.*)$");
+    let mut title = None;
+    let mut tags: Vec<String> = Vec::new();
+
+    let mut lines = Vec::new();
+    let mut in_drawer = false;
+    let mut block: Option<String> = None; // "src", "example" or "quote"
+    let all: Vec<&str> = text.lines().collect();
+    for (i, line) in all.iter().copied().enumerate() {
+        let trimmed = line.trim();
+        if let Some(kind) = &block {
+            if trimmed.eq_ignore_ascii_case(&format!("#+end_{}", kind)) {
+                if kind != "quote" {
+                    lines.push("```".to_string());
+                }
+                block = None;
+            } else if kind == "quote" {
+                lines.push(format!("> {}", line).trim_end().to_string());
+            } else {
+                lines.push(line.to_string());
+            }
+            continue;
+        }
+        if in_drawer {
+            in_drawer = !trimmed.eq_ignore_ascii_case(":end:");
+            continue;
+        }
+        // A drawer (:PROPERTIES: ... :END:) is dropped, if it is closed
+        let is_drawer = trimmed.len() > 2
+            && trimmed.starts_with(':')
+            && trimmed.ends_with(':')
+            && trimmed[1..trimmed.len() - 1]
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if is_drawer {
+            if trimmed.eq_ignore_ascii_case(":end:") {
+                continue;
+            }
+            if all[i + 1..]
+                .iter()
+                .any(|l| l.trim().eq_ignore_ascii_case(":end:"))
+            {
+                in_drawer = true;
+                continue;
+            }
+        }
+        if let Some(cap) = keyword.captures(trimmed) {
+            let value = cap[2].trim();
+            match cap[1].to_ascii_lowercase().as_str() {
+                "title" if !value.is_empty() => title = Some(value.to_string()),
+                "filetags" => add_tags(value, &mut tags),
+                "begin_src" | "begin_example" | "begin_quote" => {
+                    let kind = cap[1].to_ascii_lowercase()["begin_".len()..].to_string();
+                    if kind == "src" {
+                        let language = value.split_whitespace().next().unwrap_or_default();
+                        lines.push(format!("```{}", language));
+                    } else if kind == "example" {
+                        lines.push("```".to_string());
+                    }
+                    block = Some(kind);
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if trimmed.starts_with("# ") || trimmed == "#" {
+            continue; // comment
+        }
+        if let Some(cap) = heading.captures(line) {
+            // The note's title is the H1, so org levels start at H2
+            let level = (cap[1].len() + 1).min(6);
+            let todo = cap
+                .get(2)
+                .map(|m| format!("{} ", m.as_str()))
+                .unwrap_or_default();
+            if let Some(list) = cap.get(4) {
+                add_tags(list.as_str(), &mut tags);
+            }
+            lines.push(format!("{} {}{}", "#".repeat(level), todo, &cap[3]));
+            continue;
+        }
+
+        let line = re(&ORDERED, r"^(\s*)(\d+)\)\s").replace(line, "$1$2. ");
+        let line = if let Some(rest) = line.trim_start().strip_prefix("+ ") {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            format!("{}- {}", indent, rest)
+        } else {
+            line.into_owned()
+        };
+        lines.push(line);
+    }
+    if block.is_some_and(|kind| kind != "quote") {
+        lines.push("```".to_string());
+    }
+
+    let body = lines.join("\n");
+    let body =
+        re(&LINK, r"\[\[([^\]]+)\](?:\[([^\]]+)\])?\]").replace_all(&body, |cap: &Captures| {
+            let target = &cap[1];
+            let description = cap.get(2).map(|m| m.as_str());
+            if target.starts_with("http://")
+                || target.starts_with("https://")
+                || target.starts_with("mailto:")
+            {
+                match description {
+                    Some(text) => format!("[{}]({})", text, target),
+                    None => format!("<{}>", target),
+                }
+            } else {
+                let note = target.strip_prefix("file:").unwrap_or(target);
+                let note = note.strip_suffix(".org").unwrap_or(note);
+                match description {
+                    Some(text) if text != note => format!("[[{}|{}]]", note, text),
+                    _ => format!("[[{}]]", note),
+                }
+            }
+        });
+    let body = inline(
+        &body,
+        &[
+            (&BOLD, '*', "**"),
+            (&ITALIC, '/', "*"),
+            (&VERBATIM, '=', "`"),
+            (&CODE, '~', "`"),
+            (&STRIKE, '+', "~~"),
+        ],
+    );
+
+    Converted {
+        title,
+        tags,
+        body: body.trim().to_string(),
+    }
+}
+
+fn textile(text: &str) -> String {
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    static QUOTE: OnceLock<Regex> = OnceLock::new();
+    static CODE_BLOCK: OnceLock<Regex> = OnceLock::new();
+    static BULLET: OnceLock<Regex> = OnceLock::new();
+    static NUMBERED: OnceLock<Regex> = OnceLock::new();
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    static IMAGE: OnceLock<Regex> = OnceLock::new();
+    static BOLD: OnceLock<Regex> = OnceLock::new();
+    static ITALIC: OnceLock<Regex> = OnceLock::new();
+    static CODE: OnceLock<Regex> = OnceLock::new();
+    static STRIKE: OnceLock<Regex> = OnceLock::new();
+
+    let text = re(&BULLET, r"(?m)^(\*+)\s+").replace_all(text, |cap: &Captures| {
+        format!("{}- ", "  ".repeat(cap[1].len() - 1))
+    });
+    // Lists before headings, whose markdown form starts with #
+    let text = re(&NUMBERED, r"(?m)^(#+)\s+").replace_all(&text, |cap: &Captures| {
+        format!("{}1. ", "   ".repeat(cap[1].len() - 1))
+    });
+    let text =
+        re(&HEADING, r"(?m)^h([1-6])(?:\([^)]*\))?\.\s+").replace_all(&text, |cap: &Captures| {
+            let level: usize = cap[1].parse().unwrap_or(1);
+            format!("{} ", "#".repeat((level + 1).min(6)))
+        });
+    let text = re(&QUOTE, r"(?m)^bq\.\s+").replace_all(&text, "> ");
+    let text = re(&CODE_BLOCK, r"(?ms)^bc\.\s+(.*?)(\n\n|\z)").replace_all(&text, "```\n$1\n```$2");
+    let text = re(&IMAGE, r"!([^\s!()]+)(?:\(([^)]*)\))?!").replace_all(&text, |cap: &Captures| {
+        format!("![{}]({})", cap.get(2).map_or("", |m| m.as_str()), &cap[1])
+    });
+    let text = re(&LINK, r#""([^"]+)":(\S+[^\s.,;:!?)])"#).replace_all(&text, "[$1]($2)");
+    inline(
+        &text,
+        &[
+            (&BOLD, '*', "**"),
+            (&ITALIC, '_', "*"),
+            (&CODE, '@', "`"),
+            (&STRIKE, '-', "~~"),
+        ],
+    )
+    .trim()
+    .to_string()
+}
+
+/// Groups whose text is not part of the document.
+const RTF_SKIPPED: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "header",
+    "footer",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "generator",
+    "xmlnstbl",
+    "themedata",
+    "latentstyles",
+    "datastore",
+    "object",
+];
+
+fn rtf(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut skip_depth: Option<usize> = None; // depth of the group being skipped
+    let mut depth = 0usize;
+    let mut unicode_skip = 1usize; // \ucN: fallback characters after \u
+    let mut pending_skip = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '{' => {
+                depth += 1;
+                i += 1;
+                // {\*\dest ...} marks an optional destination to ignore
+                if skip_depth.is_none()
+                    && chars.get(i) == Some(&'\\')
+                    && chars.get(i + 1) == Some(&'*')
+                {
+                    skip_depth = Some(depth);
+                }
+            }
+            '}' => {
+                if skip_depth == Some(depth) {
+                    skip_depth = None;
+                }
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            '\\' => {
+                i += 1;
+                let Some(&next) = chars.get(i) else {
+                    break;
+                };
+                if !next.is_ascii_alphabetic() {
+                    i += 1;
+                    let emit = match next {
+                        '\\' | '{' | '}' => Some(next.to_string()),
+                        '~' => Some("\u{A0}".to_string()),
+                        '\'' => {
+                            let hex: String = chars.iter().skip(i).take(2).collect();
+                            i += 2;
+                            u8::from_str_radix(&hex, 16)
+                                .ok()
+                                .map(|b| cp1252(b).to_string())
+                        }
+                        '\n' => Some("\n".to_string()),
+                        _ => None,
+                    };
+                    if let Some(text) = emit {
+                        if pending_skip > 0 {
+                            pending_skip -= 1;
+                        } else if skip_depth.is_none() {
+                            out.push_str(&text);
+                        }
+                    }
+                    continue;
+                }
+                let start = i;
+                while chars.get(i).is_some_and(|c| c.is_ascii_alphabetic()) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let num_start = i;
+                if chars.get(i) == Some(&'-') {
+                    i += 1;
+                }
+                while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+                    i += 1;
+                }
+                let param: Option<i32> =
+                    chars[num_start..i].iter().collect::<String>().parse().ok();
+                if chars.get(i) == Some(&' ') {
+                    i += 1; // the delimiter belongs to the control word
+                }
+
+                if RTF_SKIPPED.contains(&word.as_str()) && skip_depth.is_none() {
+                    skip_depth = Some(depth);
+                }
+                if skip_depth.is_some() {
+                    continue;
+                }
+                match word.as_str() {
+                    "par" | "line" | "sect" | "page" => out.push('\n'),
+                    "tab" => out.push('\t'),
+                    "emdash" => out.push('—'),
+                    "endash" => out.push('–'),
+                    "bullet" => out.push('•'),
+                    "lquote" => out.push('‘'),
+                    "rquote" => out.push('’'),
+                    "ldblquote" => out.push('“'),
+                    "rdblquote" => out.push('”'),
+                    "uc" => unicode_skip = param.unwrap_or(1).max(0) as usize,
+                    "u" => {
+                        if let Some(code) = param {
+                            let code = if code < 0 { code + 65536 } else { code };
+                            if let Some(ch) = char::from_u32(code as u32) {
+                                out.push(ch);
+                            }
+                            pending_skip = unicode_skip;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            '\n' | '\r' => i += 1,
+            _ => {
+                if pending_skip > 0 {
+                    pending_skip -= 1;
+                } else if skip_depth.is_none() {
+                    out.push(c);
+                }
+                i += 1;
+            }
+        }
+    }
+
+    static TRAILING: OnceLock<Regex> = OnceLock::new();
+    static BLANK: OnceLock<Regex> = OnceLock::new();
+    let out = re(&TRAILING, r"(?m)[ \t]+$").replace_all(&out, "");
+    re(&BLANK, r"\n{3,}")
+        .replace_all(&out, "\n\n")
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,152 @@
+//! Daily notes: one note per day at `<dailyNotesFolder>/YYYY-MM-DD.md`
+//! (folder `Daily` unless configured), titled with the date. The
+//! `dailyNoteFormat` setting changes the file name under the folder, as a
+//! chrono format that may add subfolders (`%Y/%m/%Y-%m-%d`); it has to
+//! spell out the whole date so a name can be read back as its day. With
+//! `dailyTemplate`, `open_daily_note` starts new notes from that template
+//! note, its `{{date}}` and the like filled in for the note's day.
+//!
+//! With the `dailyContext` setting, `open_daily_note` stamps today's note as
+//! it creates it with `weather:` (the `weatherUrl` provider's line, asked for
+//! the location when the URL has `{location}`), `device:` (this machine's
+//! name) and `location:` (the position the app passes in, else a fixed
+//! place) frontmatter, so a journal carries where and how it was written.
+//! Notes created for other days, or by routines, are left unstamped.
+
+use crate::frontmatter::{self, Value};
+use crate::Settings;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+const DEFAULT_FOLDER: &str = "Daily";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The configured daily-notes folder, without surrounding slashes.
+pub fn folder(configured: Option<&str>) -> String {
+    match configured.map(|f| f.trim().trim_matches('/')) {
+        Some(f) if !f.is_empty() => f.to_string(),
+        _ => DEFAULT_FOLDER.to_string(),
+    }
+}
+
+/// Whether a file name format names each day by a distinct, valid path that
+/// reads back as the day.
+fn valid_format(format: &str) -> bool {
+    let sample = NaiveDate::from_ymd_opt(2024, 5, 12).expect("valid date");
+    let mut name = String::new();
+    write!(name, "{}", sample.format(format)).is_ok()
+        && !name.contains('\\')
+        && name
+            .split('/')
+            .all(|part| !part.trim().is_empty() && part != "." && part != "..")
+        && NaiveDate::parse_from_str(&name, format) == Ok(sample)
+}
+
+/// Where daily notes live: the folder and the file name format under it.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    folder: String,
+    format: String,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl Layout {
+    /// An invalid format falls back to `YYYY-MM-DD`.
+    pub fn new(folder: Option<&str>, format: Option<&str>) -> Self {
+        let format = format
+            .map(|f| f.trim().trim_matches('/').trim_end_matches(".md"))
+            .filter(|f| !f.is_empty());
+        let format = match format {
+            Some(f) if valid_format(f) => f.to_string(),
+            Some(f) => {
+                eprintln!("Ignoring invalid daily note format \"{}\"", f);
+                DATE_FORMAT.to_string()
+            }
+            None => DATE_FORMAT.to_string(),
+        };
+        Self {
+            folder: self::folder(folder),
+            format,
+        }
+    }
+
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self::new(
+            settings.daily_notes_folder.as_deref(),
+            settings.daily_note_format.as_deref(),
+        )
+    }
+
+    pub fn note_id(&self, date: NaiveDate) -> String {
+        format!("{}/{}", self.folder, date.format(&self.format))
+    }
+}
+
+pub fn new_content(date: NaiveDate) -> String {
+    format!("# {}\n\n", date.format(DATE_FORMAT))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ContextStamp {
+    pub weather: bool,
+    pub device: bool,
+    pub location: bool,
+    pub place: Option<String>,       // location when the app passes none
+    pub device_name: Option<String>, // instead of the machine's name
+}
+
+/// What a new daily note is stamped with.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub weather: Option<String>,
+    pub device: Option<String>,
+    pub location: Option<String>,
+}
+
+#[cfg(not(target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// This machine's name: the computer name on Windows and macOS, the host
+/// name elsewhere. Blocking.
+pub fn device_name() -> Option<String> {
+    #[cfg(target_os = "windows")]
+    let name = std::env::var("COMPUTERNAME").ok();
+    #[cfg(target_os = "macos")]
+    let name = command_output("scutil", &["--get", "ComputerName"]);
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let name = std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| command_output("hostname", &[]));
+    name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty())
+}
+
+/// Add the context's values to a new note's frontmatter.
+pub fn stamp(content: &str, context: &Context) -> String {
+    let mut content = content.to_string();
+    for (key, value) in [
+        ("weather", &context.weather),
+        ("device", &context.device),
+        ("location", &context.location),
+    ] {
+        if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+            content = frontmatter::set(&content, key, Value::Scalar(value.to_string()));
+        }
+    }
+    content
+}
@@ -0,0 +1,227 @@
+//! Project dashboards.
+//!
+//! A project is either a tag (`#project` or `project`) or a folder; a folder
+//! wins when one exists with that path. The dashboard gathers, in one pass
+//! over the vault, the project's open tasks, recently edited notes, upcoming
+//! dates (task `@due` markers and `due:`/`date:` frontmatter) and the people
+//! its notes link to or are linked from. People are notes in the `People`
+//! folder or with `type: person` frontmatter. Locked notes only show up among
+//! the recent notes, under their listing title.
+
+use crate::frontmatter::Frontmatter;
+use crate::links::{self, LinkResolver};
+use crate::{
+    listing_metadata, listing_title_and_preview, note_exposure, tags, tasks, LockedNotePolicy,
+    NoteMetadata, ScannedNote,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+const PEOPLE_FOLDER: &str = "People";
+const RECENT_LIMIT: usize = 10;
+const TASK_LIMIT: usize = 200;
+const UPCOMING_DAYS: i64 = 60;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardTask {
+    pub note_id: String,
+    pub note_title: String,
+    pub line: usize,
+    pub text: String,
+    pub due: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingDate {
+    pub date: String, // YYYY-MM-DD
+    pub label: String,
+    pub note_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedPerson {
+    pub id: String,
+    pub name: String,
+    pub mentions: usize, // project notes linking to or from the person
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectDashboard {
+    pub project: String,
+    pub kind: &'static str, // "folder" or "tag"
+    pub note_count: usize,
+    pub open_tasks: Vec<DashboardTask>,
+    pub recent_notes: Vec<NoteMetadata>,
+    pub upcoming: Vec<UpcomingDate>,
+    pub people: Vec<LinkedPerson>,
+}
+
+fn is_person(note: &ScannedNote) -> bool {
+    note.id.starts_with(&format!("{}/", PEOPLE_FOLDER))
+        || Frontmatter::parse(&note.content)
+            .get("type")
+            .is_some_and(|t| t.eq_ignore_ascii_case("person"))
+}
+
+/// Build the dashboard for a tag or folder. Blocking.
+pub fn build(
+    notes: &[ScannedNote],
+    project: &str,
+    is_folder: bool,
+    locked_policy: &LockedNotePolicy,
+    pinned: &HashSet<String>,
+    today: NaiveDate,
+) -> ProjectDashboard {
+    let project = project.trim();
+    let (kind, members): (&'static str, Vec<&ScannedNote>) = if is_folder {
+        let prefix = format!("{}/", project.trim_matches('/'));
+        (
+            "folder",
+            notes.iter().filter(|n| n.id.starts_with(&prefix)).collect(),
+        )
+    } else {
+        let tag = tags::normalize(project);
+        (
+            "tag",
+            notes
+                .iter()
+                .filter(|n| tags::extract_tags(&n.content).contains(&tag))
+                .collect(),
+        )
+    };
+    let horizon = today + chrono::Duration::days(UPCOMING_DAYS);
+    let in_window = |date: NaiveDate| date >= today && date <= horizon;
+
+    let mut open_tasks = Vec::new();
+    let mut upcoming = Vec::new();
+    for note in &members {
+        if note_exposure(&note.content, locked_policy).locked {
+            continue;
+        }
+        let (title, _) = listing_title_and_preview(&note.content, locked_policy);
+        for task in tasks::extract_tasks(&note.content) {
+            if task.done {
+                continue;
+            }
+            if let Some(due) = task
+                .due
+                .as_deref()
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .filter(|d| in_window(*d))
+            {
+                upcoming.push(UpcomingDate {
+                    date: due.format("%Y-%m-%d").to_string(),
+                    label: task.text.clone(),
+                    note_id: note.id.clone(),
+                });
+            }
+            open_tasks.push(DashboardTask {
+                note_id: note.id.clone(),
+                note_title: title.clone(),
+                line: task.line,
+                text: task.text,
+                due: task.due,
+            });
+        }
+
+        let meta = Frontmatter::parse(&note.content);
+        for key in ["due", "date"] {
+            if let Some(date) = meta
+                .get(key)
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .filter(|d| in_window(*d))
+            {
+                upcoming.push(UpcomingDate {
+                    date: date.format("%Y-%m-%d").to_string(),
+                    label: title.clone(),
+                    note_id: note.id.clone(),
+                });
+            }
+        }
+    }
+    // Dated tasks first, soonest first
+    open_tasks.sort_by(|a, b| match (&a.due, &b.due) {
+        (Some(x), Some(y)) => x.cmp(y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    open_tasks.truncate(TASK_LIMIT);
+    upcoming.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.label.cmp(&b.label)));
+
+    let mut recent: Vec<&ScannedNote> = members.clone();
+    recent.sort_by_key(|n| Reverse(n.modified));
+    let recent_notes = recent
+        .into_iter()
+        .take(RECENT_LIMIT)
+        .map(|note| {
+            listing_metadata(
+                note.id.clone(),
+                &note.content,
+                note.modified,
+                note.created,
+                locked_policy,
+                pinned,
+            )
+        })
+        .collect();
+
+    // People linked from project notes, or linking to them
+    let resolver = LinkResolver::new(notes.iter().map(|n| (n.id.as_str(), n.title.as_str())));
+    let by_id: HashMap<&str, &ScannedNote> = notes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let member_ids: HashSet<&str> = members.iter().map(|n| n.id.as_str()).collect();
+    let people_ids: HashSet<&str> = notes
+        .iter()
+        .filter(|n| is_person(n))
+        .map(|n| n.id.as_str())
+        .collect();
+    let mut mentions: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for note in notes {
+        let person = people_ids.contains(note.id.as_str());
+        for target in links::extract_links(&note.content)
+            .iter()
+            .filter_map(|link| resolver.resolve(link, &note.id))
+        {
+            let Some(target) = by_id.get(target.as_str()).copied() else {
+                continue;
+            };
+            if member_ids.contains(note.id.as_str()) && people_ids.contains(target.id.as_str()) {
+                mentions.entry(&target.id).or_default().insert(&note.id);
+            } else if person && member_ids.contains(target.id.as_str()) {
+                mentions.entry(&note.id).or_default().insert(&target.id);
+            }
+        }
+    }
+    let mut people: Vec<LinkedPerson> = mentions
+        .into_iter()
+        .filter_map(|(id, from)| {
+            let note = by_id.get(id)?;
+            Some(LinkedPerson {
+                id: note.id.clone(),
+                name: listing_title_and_preview(&note.content, locked_policy).0,
+                mentions: from.len(),
+            })
+        })
+        .collect();
+    people.sort_by(|a, b| {
+        b.mentions
+            .cmp(&a.mentions)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    ProjectDashboard {
+        project: project.to_string(),
+        kind,
+        note_count: members.len(),
+        open_tasks,
+        recent_notes,
+        upcoming,
+        people,
+    }
+}
@@ -0,0 +1,183 @@
+//! Natural-language date parsing.
+//!
+//! Resolves what people type for a date ("tomorrow", "next tuesday", "in 3
+//! weeks", "2 days ago", "march 5", "end of month") to a calendar date
+//! relative to a given day. A bare weekday or month-day means the next one to
+//! come (today counts); "next <weekday>" means that day in the following
+//! Monday-based week. ISO dates (`YYYY-MM-DD`) pass through.
+
+use chrono::{Datelike, Days, Month, Months, NaiveDate, Weekday};
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+fn unit(word: &str) -> Option<Unit> {
+    match word {
+        "day" | "days" | "d" => Some(Unit::Day),
+        "week" | "weeks" | "wk" | "wks" | "w" => Some(Unit::Week),
+        "month" | "months" | "mo" | "mos" => Some(Unit::Month),
+        "year" | "years" | "yr" | "yrs" | "y" => Some(Unit::Year),
+        _ => None,
+    }
+}
+
+fn number(word: &str) -> Option<i64> {
+    let n = match word {
+        "a" | "an" | "one" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        "ten" => 10,
+        "eleven" => 11,
+        "twelve" => 12,
+        _ => word.parse::<u32>().ok()?,
+    };
+    Some(i64::from(n))
+}
+
+pub fn weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "tues" => Some(Weekday::Tue),
+        "thur" | "thurs" => Some(Weekday::Thu),
+        _ => word.parse().ok(),
+    }
+}
+
+fn month(word: &str) -> Option<u32> {
+    match word {
+        "sept" => Some(9),
+        _ => word.parse::<Month>().ok().map(|m| m.number_from_month()),
+    }
+}
+
+/// A day of the month, with or without an ordinal suffix ("5", "5th").
+pub fn day_of_month(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    digits.parse().ok().filter(|d| (1..=31).contains(d))
+}
+
+fn add_days(date: NaiveDate, n: i64) -> Option<NaiveDate> {
+    if n >= 0 {
+        date.checked_add_days(Days::new(n.unsigned_abs()))
+    } else {
+        date.checked_sub_days(Days::new(n.unsigned_abs()))
+    }
+}
+
+fn add_months(date: NaiveDate, n: i64) -> Option<NaiveDate> {
+    let months = Months::new(u32::try_from(n.unsigned_abs()).ok()?);
+    if n >= 0 {
+        date.checked_add_months(months)
+    } else {
+        date.checked_sub_months(months)
+    }
+}
+
+fn shift(date: NaiveDate, n: i64, unit: Unit) -> Option<NaiveDate> {
+    match unit {
+        Unit::Day => add_days(date, n),
+        Unit::Week => add_days(date, n * 7),
+        Unit::Month => add_months(date, n),
+        Unit::Year => add_months(date, n * 12),
+    }
+}
+
+/// The next `weekday` on or after `today`.
+fn upcoming(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    add_days(today, i64::from(ahead))
+}
+
+/// `weekday` in the week after this one.
+fn in_next_week(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let next_monday = add_days(today, i64::from(7 - today.weekday().num_days_from_monday()))?;
+    add_days(next_monday, i64::from(weekday.num_days_from_monday()))
+}
+
+/// The most recent `weekday` before `today`.
+fn previous(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let back = match (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7 {
+        0 => 7,
+        n => n,
+    };
+    add_days(today, -i64::from(back))
+}
+
+fn end_of(today: NaiveDate, unit: Unit) -> Option<NaiveDate> {
+    match unit {
+        Unit::Day => Some(today),
+        Unit::Week => upcoming(today, Weekday::Sun),
+        Unit::Month => add_months(today.with_day(1)?, 1)?.pred_opt(),
+        Unit::Year => NaiveDate::from_ymd_opt(today.year(), 12, 31),
+    }
+}
+
+/// "march 5", "5 march", "march 5th 2027", "5 mar 2027".
+fn month_day(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    let (m, d, year) = match *words {
+        [a, b] => (a, b, None),
+        [a, b, y] => (a, b, Some(y.parse::<i32>().ok()?)),
+        _ => return None,
+    };
+    let (month, day) = match (month(m), day_of_month(d)) {
+        (Some(month), Some(day)) => (month, day),
+        _ => (month(d)?, day_of_month(m)?),
+    };
+    if let Some(year) = year {
+        return NaiveDate::from_ymd_opt(year, month, day);
+    }
+    match NaiveDate::from_ymd_opt(today.year(), month, day) {
+        Some(date) if date >= today => Some(date),
+        _ => NaiveDate::from_ymd_opt(today.year() + 1, month, day),
+    }
+}
+
+/// Resolve a typed date relative to `today`, or None if it is not understood.
+pub fn parse_natural_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let text = text.trim().trim_end_matches('.').to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    match words.as_slice() {
+        ["today"] | ["now"] => Some(today),
+        ["tomorrow"] | ["tmrw"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        ["day", "after", "tomorrow"] => add_days(today, 2),
+        ["day", "before", "yesterday"] => add_days(today, -2),
+        ["in", n, u] => shift(today, number(n)?, unit(u)?),
+        [n, u, "ago"] => shift(today, -number(n)?, unit(u)?),
+        [n, u, "from", "now"] => shift(today, number(n)?, unit(u)?),
+        ["end", "of", "the", u] | ["end", "of", u] => end_of(today, unit(u)?),
+        ["end", "of", "next", u] => end_of(shift(today, 1, unit(u)?)?, unit(u)?),
+        ["next", w] => match unit(w) {
+            Some(u) => shift(today, 1, u),
+            None => in_next_week(today, weekday(w)?),
+        },
+        ["last", w] => match unit(w) {
+            Some(u) => shift(today, -1, u),
+            None => previous(today, weekday(w)?),
+        },
+        ["this", w] => upcoming(today, weekday(w)?),
+        [w] if weekday(w).is_some() => upcoming(today, weekday(w)?),
+        words => month_day(words, today),
+    }
+}
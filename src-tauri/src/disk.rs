@@ -0,0 +1,125 @@
+//! Free-space monitoring for the vault and app-data volumes.
+//!
+//! A background task checks both volumes every few minutes and emits
+//! `disk-space-warning` when one drops to a lower level than last seen.
+//! Writes the app makes on its own (scheduled backups and exports) are
+//! skipped while either volume is critically low; saving notes never is.
+
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_WARN_MB: u64 = 1024;
+const DEFAULT_CRITICAL_MB: u64 = 200;
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskThresholds {
+    pub warn_mb: Option<u64>,     // default 1024
+    pub critical_mb: Option<u64>, // default 200
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpaceLevel {
+    Ok,
+    Low,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeSpace {
+    pub volume: &'static str, // "vault" or "appData"
+    pub path: String,
+    pub available_bytes: u64,
+    pub level: SpaceLevel,
+}
+
+impl DiskThresholds {
+    fn level(&self, available_bytes: u64) -> SpaceLevel {
+        let critical = self.critical_mb.unwrap_or(DEFAULT_CRITICAL_MB) * MB;
+        let warn = self.warn_mb.unwrap_or(DEFAULT_WARN_MB) * MB;
+        if available_bytes < critical {
+            SpaceLevel::Critical
+        } else if available_bytes < warn {
+            SpaceLevel::Low
+        } else {
+            SpaceLevel::Ok
+        }
+    }
+}
+
+fn measure(volume: &'static str, path: &Path, thresholds: &DiskThresholds) -> Option<VolumeSpace> {
+    let available_bytes = fs2::available_space(path).ok()?;
+    Some(VolumeSpace {
+        volume,
+        path: path.to_string_lossy().into_owned(),
+        available_bytes,
+        level: thresholds.level(available_bytes),
+    })
+}
+
+/// Current free space on the vault and app-data volumes. Volumes that cannot
+/// be measured are left out.
+pub fn check(app: &AppHandle) -> Vec<VolumeSpace> {
+    let state = app.state::<AppState>();
+    let thresholds = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.disk_space.clone().unwrap_or_default()
+    };
+    let notes_folder = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone();
+
+    let mut volumes = Vec::new();
+    if let Some(folder) = notes_folder {
+        volumes.extend(measure("vault", Path::new(&folder), &thresholds));
+    }
+    if let Ok(app_data) = app.path().app_data_dir() {
+        volumes.extend(measure("appData", &app_data, &thresholds));
+    }
+    volumes
+}
+
+/// Refuse a write the app makes on its own while space is critically low.
+pub fn allow_background_write(app: &AppHandle) -> Result<(), String> {
+    match check(app).into_iter().find(|v| v.level == SpaceLevel::Critical) {
+        Some(volume) => Err(format!(
+            "Skipped: only {} MB free on {}",
+            volume.available_bytes / MB,
+            volume.path
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Check free space periodically, warning once each time a volume gets worse.
+pub fn start_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_levels: HashMap<&'static str, SpaceLevel> = HashMap::new();
+        loop {
+            if app.state::<AppState>().shutdown.started() {
+                return;
+            }
+            let check_app = app.clone();
+            if let Ok(volumes) = tauri::async_runtime::spawn_blocking(move || check(&check_app)).await {
+                for volume in volumes {
+                    let previous = last_levels.insert(volume.volume, volume.level);
+                    if volume.level > previous.unwrap_or(SpaceLevel::Ok) {
+                        let _ = app.emit("disk-space-warning", &volume);
+                    }
+                }
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
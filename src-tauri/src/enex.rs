@@ -0,0 +1,308 @@
+//! Evernote `.enex` import.
+//!
+//! An ENEX export is one XML file holding notes whose bodies are ENML (XHTML
+//! with a few Evernote elements) and whose attachments are embedded as
+//! base64 `<resource>`s. Each note becomes an `ImportItem` titled by its
+//! Evernote title, with its tags, creation and update times and source URL in
+//! the frontmatter; the update time is also kept as the file's modification
+//! time. Resources are written to the note's attachment folder and linked
+//! where the ENML embeds them (`<en-media hash>`, the MD5 of the data), or
+//! listed at the end when nothing does. Checkboxes become tasks; encrypted
+//! text cannot be decrypted and is left out with a marker. Images the note
+//! only links to on the web stay links.
+//!
+//! A resource that does not decode is reported as a failure of the note, by
+//! `"<title> / <file name>"`, and the note is imported without it.
+
+use crate::{
+    assets, attachment_folder, clipboard, frontmatter, import, sanitize_filename, AppState,
+};
+use base64::Engine;
+use md5::{Digest, Md5};
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const MARKER: char = '\u{E000}'; // private use, brackets resource placeholders
+
+struct Resource {
+    name: String,
+    data: Vec<u8>,
+}
+
+fn re(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+/// The text of the first `<tag>` element in `xml`, entities decoded.
+fn field(xml: &str, tag: &str) -> Option<String> {
+    let pattern = format!(r"(?s)<{}(?:\s[^>]*)?>(.*?)</{}>", tag, tag);
+    let cap = Regex::new(&pattern).ok()?.captures(xml)?;
+    let text = clipboard::strip_tags(&cap[1]).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn fields(xml: &str, tag: &str) -> Vec<String> {
+    let pattern = format!(r"(?s)<{}(?:\s[^>]*)?>(.*?)</{}>", tag, tag);
+    let Ok(regex) = Regex::new(&pattern) else {
+        return Vec::new();
+    };
+    regex
+        .captures_iter(xml)
+        .map(|cap| clipboard::strip_tags(&cap[1]).trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+/// An ENEX timestamp (`20240131T094500Z`) as unix seconds.
+fn timestamp(value: &str) -> Option<i64> {
+    chrono::NaiveDateTime::parse_from_str(value.trim(), TIME_FORMAT)
+        .ok()
+        .map(|t| t.and_utc().timestamp())
+}
+
+fn extension_for(mime: &str) -> &'static str {
+    match mime.trim().to_ascii_lowercase().as_str() {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "audio/mp4" | "audio/m4a" => "m4a",
+        "video/mp4" => "mp4",
+        "video/quicktime" => "mov",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "application/zip" => "zip",
+        _ => "bin",
+    }
+}
+
+/// A file name for a resource that is safe as one vault path segment.
+fn resource_name(file_name: Option<&str>, mime: Option<&str>, index: usize) -> String {
+    let name: String = file_name
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_start_matches('.').trim();
+    let extension = extension_for(mime.unwrap_or_default());
+    if name.is_empty() {
+        format!("attachment-{}.{}", index + 1, extension)
+    } else if Path::new(name).extension().is_none() {
+        format!("{}.{}", name, extension)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Decode a note's resources, keyed by the hex MD5 `<en-media>` refers to
+/// them by. Resources that fail are returned as failures.
+fn resources(title: &str, note: &str) -> (Vec<(String, Resource)>, Vec<import::ImportFailure>) {
+    static RESOURCE: OnceLock<Regex> = OnceLock::new();
+    static DATA: OnceLock<Regex> = OnceLock::new();
+    let mut decoded = Vec::new();
+    let mut failed = Vec::new();
+    for (i, cap) in re(&RESOURCE, r"(?s)<resource>(.*?)</resource>")
+        .captures_iter(note)
+        .enumerate()
+    {
+        let block = &cap[1];
+        let name = resource_name(
+            field(block, "file-name").as_deref(),
+            field(block, "mime").as_deref(),
+            i,
+        );
+        let data = re(&DATA, r"(?s)<data(?:\s[^>]*)?>(.*?)</data>")
+            .captures(block)
+            .map(|cap| cap[1].split_whitespace().collect::<String>())
+            .ok_or_else(|| "Attachment has no data".to_string())
+            .and_then(|encoded| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|_| "Attachment data is not valid base64".to_string())
+            });
+        match data {
+            Ok(data) => {
+                let hash = Md5::digest(&data)
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                decoded.push((hash, Resource { name, data }));
+            }
+            Err(error) => failed.push(import::ImportFailure {
+                source: format!("{} / {}", title, name),
+                error,
+            }),
+        }
+    }
+    (decoded, failed)
+}
+
+/// Convert ENML to markdown. `<en-media>` elements become placeholders for
+/// the resource with that hash, filled in by the caller.
+fn enml_to_markdown(enml: &str) -> String {
+    static BODY: OnceLock<Regex> = OnceLock::new();
+    static MEDIA: OnceLock<Regex> = OnceLock::new();
+    static TODO: OnceLock<Regex> = OnceLock::new();
+    static CRYPT: OnceLock<Regex> = OnceLock::new();
+    static TASK: OnceLock<Regex> = OnceLock::new();
+
+    let enml = match re(&BODY, r"(?s)<en-note\b[^>]*>(.*)</en-note>").captures(enml) {
+        Some(cap) => cap[1].to_string(),
+        None => enml.to_string(),
+    };
+    let enml = re(&MEDIA, r"(?is)<en-media\b[^>]*?/?>(?:\s*</en-media>)?").replace_all(
+        &enml,
+        |cap: &Captures| match clipboard::attr(&cap[0], "hash") {
+            Some(hash) => format!(" {}{}{} ", MARKER, hash.to_ascii_lowercase(), MARKER),
+            None => String::new(),
+        },
+    );
+    let enml = re(&TODO, r"(?is)<en-todo\b[^>]*?/?>(?:\s*</en-todo>)?").replace_all(
+        &enml,
+        |cap: &Captures| match clipboard::attr(&cap[0], "checked") {
+            Some(checked) if checked.eq_ignore_ascii_case("true") => "[x] ",
+            _ => "[ ] ",
+        },
+    );
+    let enml = re(&CRYPT, r"(?is)<en-crypt\b[^>]*>.*?</en-crypt>")
+        .replace_all(&enml, "*(encrypted text not imported)*");
+
+    let markdown = clipboard::html_to_markdown(&enml);
+    re(&TASK, r"(?m)^\[( |x)\] ")
+        .replace_all(&markdown, "- [$1] ")
+        .into_owned()
+}
+
+/// Convert an ENEX export to import items, writing nothing. Attachments get
+/// paths in the attachment folder of the note they belong to, free on disk
+/// and among each other. Blocking.
+pub fn items(
+    state: &AppState,
+    notes_root: &Path,
+    xml: &str,
+    options: &import::ImportOptions,
+) -> (Vec<import::ImportItem>, Vec<import::ImportFailure>) {
+    static NOTE: OnceLock<Regex> = OnceLock::new();
+    static CONTENT: OnceLock<Regex> = OnceLock::new();
+    static CDATA: OnceLock<Regex> = OnceLock::new();
+    static RESOURCES: OnceLock<Regex> = OnceLock::new();
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+
+    let mut items = Vec::new();
+    let mut failed = Vec::new();
+    let mut claimed: HashSet<String> = HashSet::new();
+
+    for cap in re(&NOTE, r"(?s)<note>(.*?)</note>").captures_iter(xml) {
+        let note = &cap[1];
+        // The body can hold anything; read the other fields without it
+        let content = re(&CONTENT, r"(?s)<content>(.*?)</content>").captures(note);
+        let enml = match &content {
+            Some(content) => match re(&CDATA, r"(?s)<!\[CDATA\[(.*?)\]\]>").captures(&content[1]) {
+                Some(cdata) => cdata[1].to_string(),
+                None => clipboard::strip_tags(&content[1]),
+            },
+            None => String::new(),
+        };
+        let note = match &content {
+            Some(content) => note.replace(&content[0], ""),
+            None => note.to_string(),
+        };
+        let title = field(&note, "title").unwrap_or_else(|| "Untitled".to_string());
+        let (decoded, resource_failures) = resources(&title, &note);
+        failed.extend(resource_failures);
+        let note = re(&RESOURCES, r"(?s)<resource>.*?</resource>").replace_all(&note, "");
+
+        let id = import::in_target_folder(options, &sanitize_filename(&title));
+        let folder = match attachment_folder(state, notes_root, Some(&id)) {
+            Ok(folder) => folder,
+            Err(error) => {
+                failed.push(import::ImportFailure {
+                    source: title,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        // Pick a free path for each attachment
+        let mut links: HashMap<String, String> = HashMap::new();
+        let mut order: Vec<String> = Vec::new();
+        let mut note_assets = Vec::new();
+        for (hash, resource) in decoded {
+            if links.contains_key(&hash) {
+                continue;
+            }
+            let path = import::claim_asset_path(notes_root, &mut claimed, &folder, &resource.name);
+            links.insert(hash.clone(), assets::markdown_link(&path));
+            order.push(hash);
+            note_assets.push(import::ImportAsset {
+                path,
+                data: resource.data,
+            });
+        }
+
+        let mut used: HashSet<String> = HashSet::new();
+        let body = enml_to_markdown(&enml);
+        let pattern = format!("{}([0-9a-f]+){}", MARKER, MARKER);
+        let body = re(&PLACEHOLDER, &pattern).replace_all(&body, |cap: &Captures| {
+            match links.get(&cap[1]) {
+                Some(link) => {
+                    used.insert(cap[1].to_string());
+                    link.clone()
+                }
+                None => String::new(),
+            }
+        });
+        let mut body = body.trim().to_string();
+        let unused: Vec<&str> = order
+            .iter()
+            .filter(|hash| !used.contains(*hash))
+            .map(|hash| links[hash].as_str())
+            .collect();
+        if !unused.is_empty() {
+            if !body.is_empty() {
+                body.push_str("\n\n");
+            }
+            body.push_str(&unused.join("\n"));
+        }
+
+        let mut content = format!("# {}\n\n{}\n", title, body);
+        let tags = fields(&note, "tag");
+        if !tags.is_empty() {
+            content = frontmatter::set(&content, "tags", frontmatter::Value::List(tags));
+        }
+        let created = field(&note, "created").and_then(|t| timestamp(&t));
+        let updated = field(&note, "updated").and_then(|t| timestamp(&t));
+        for (key, time) in [("created", created), ("updated", updated)] {
+            if let Some(time) = time.and_then(import::frontmatter_time) {
+                content = frontmatter::set(&content, key, frontmatter::Value::Scalar(time));
+            }
+        }
+        if let Some(url) = field(&note, "source-url") {
+            content = frontmatter::set(&content, "source", frontmatter::Value::Scalar(url));
+        }
+
+        items.push(import::ImportItem {
+            source: title,
+            id,
+            content,
+            assets: note_assets,
+            modified: updated.or(created),
+        });
+    }
+
+    (items, failed)
+}
@@ -0,0 +1,269 @@
+//! Errors returned by Tauri commands.
+//!
+//! Every command fails with a `ScratchError`, which reaches the frontend as
+//! `{ code, message, ... }`: `code` tells the cases apart ("folderNotSet",
+//! "noteNotFound", "permissionDenied", ...), `message` is the text to show,
+//! and some codes carry the note ID or path involved. `tryAgain` marks
+//! failures that an immediate retry may get past. Helpers that still report
+//! errors as text convert through `From<String>`, landing in `other` unless
+//! the text is one the frontend needs to recognise.
+
+use crate::autosave::FlushError;
+use crate::recovery::WriteError;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::io::ErrorKind;
+use std::path::Path;
+
+const FOLDER_NOT_SET: &str = "Notes folder not set";
+const NAME_CONTENDED: &str = "Could not claim a free file name for the note, please try again";
+
+#[derive(Debug)]
+pub enum ScratchError {
+    FolderNotSet,
+    NoteNotFound {
+        id: String,
+    },
+    NotFound {
+        path: Option<String>,
+        message: String,
+    },
+    PermissionDenied {
+        path: Option<String>,
+        message: String,
+    },
+    AlreadyExists {
+        path: Option<String>,
+        message: String,
+    },
+    DiskFull {
+        path: Option<String>,
+        message: String,
+    },
+    InvalidInput {
+        message: String,
+    },
+    Io {
+        path: Option<String>,
+        message: String,
+    },
+    TryAgain {
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl ScratchError {
+    pub fn invalid(message: impl Into<String>) -> Self {
+        ScratchError::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ScratchError::NotFound {
+            path: None,
+            message: message.into(),
+        }
+    }
+
+    /// Every candidate file name for a new note was taken, most likely by
+    /// other saves racing this one.
+    pub fn name_contended() -> Self {
+        ScratchError::TryAgain {
+            message: NAME_CONTENDED.to_string(),
+        }
+    }
+
+    pub fn already_exists(message: impl Into<String>) -> Self {
+        ScratchError::AlreadyExists {
+            path: None,
+            message: message.into(),
+        }
+    }
+
+    /// An I/O error on `path`, by kind.
+    pub fn io(err: std::io::Error, path: &Path) -> Self {
+        Self::from_io(err, Some(path.to_string_lossy().into_owned()))
+    }
+
+    /// An I/O error reading or writing note `id`; a missing file is a missing
+    /// note.
+    pub fn note_io(err: std::io::Error, id: &str) -> Self {
+        match err.kind() {
+            ErrorKind::NotFound => ScratchError::NoteNotFound { id: id.to_string() },
+            _ => Self::from_io(err, Some(id.to_string())),
+        }
+    }
+
+    fn from_io(err: std::io::Error, path: Option<String>) -> Self {
+        let message = err.to_string();
+        match err.kind() {
+            ErrorKind::NotFound => ScratchError::NotFound { path, message },
+            ErrorKind::PermissionDenied => ScratchError::PermissionDenied { path, message },
+            ErrorKind::AlreadyExists => ScratchError::AlreadyExists { path, message },
+            ErrorKind::InvalidInput | ErrorKind::InvalidData => {
+                ScratchError::InvalidInput { message }
+            }
+            _ if is_disk_full(&err) => ScratchError::DiskFull { path, message },
+            _ => ScratchError::Io { path, message },
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ScratchError::FolderNotSet => "folderNotSet",
+            ScratchError::NoteNotFound { .. } => "noteNotFound",
+            ScratchError::NotFound { .. } => "notFound",
+            ScratchError::PermissionDenied { .. } => "permissionDenied",
+            ScratchError::AlreadyExists { .. } => "alreadyExists",
+            ScratchError::DiskFull { .. } => "diskFull",
+            ScratchError::InvalidInput { .. } => "invalidInput",
+            ScratchError::Io { .. } => "io",
+            ScratchError::TryAgain { .. } => "tryAgain",
+            ScratchError::Other { .. } => "other",
+        }
+    }
+
+    fn path(&self) -> Option<&str> {
+        match self {
+            ScratchError::NotFound { path, .. }
+            | ScratchError::PermissionDenied { path, .. }
+            | ScratchError::AlreadyExists { path, .. }
+            | ScratchError::DiskFull { path, .. }
+            | ScratchError::Io { path, .. } => path.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// ENOSPC on Unix, ERROR_DISK_FULL / ERROR_HANDLE_DISK_FULL on Windows.
+pub fn is_disk_full(err: &std::io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(code) if cfg!(windows) => code == 112 || code == 39,
+        Some(code) => code == 28,
+        None => false,
+    }
+}
+
+impl std::fmt::Display for ScratchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScratchError::FolderNotSet => f.write_str(FOLDER_NOT_SET),
+            ScratchError::NoteNotFound { id } => write!(f, "Note not found: {}", id),
+            ScratchError::NotFound { message, .. }
+            | ScratchError::PermissionDenied { message, .. }
+            | ScratchError::AlreadyExists { message, .. }
+            | ScratchError::DiskFull { message, .. }
+            | ScratchError::InvalidInput { message }
+            | ScratchError::Io { message, .. }
+            | ScratchError::TryAgain { message }
+            | ScratchError::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ScratchError {}
+
+impl Serialize for ScratchError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut out = serializer.serialize_struct("ScratchError", 3)?;
+        out.serialize_field("code", self.code())?;
+        out.serialize_field("message", &self.to_string())?;
+        if let ScratchError::NoteNotFound { id } = self {
+            out.serialize_field("id", id)?;
+        }
+        if let Some(path) = self.path() {
+            out.serialize_field("path", path)?;
+        }
+        out.end()
+    }
+}
+
+impl From<String> for ScratchError {
+    fn from(message: String) -> Self {
+        if message == FOLDER_NOT_SET {
+            ScratchError::FolderNotSet
+        } else if message == NAME_CONTENDED {
+            ScratchError::TryAgain { message }
+        } else {
+            ScratchError::Other { message }
+        }
+    }
+}
+
+impl From<&str> for ScratchError {
+    fn from(message: &str) -> Self {
+        ScratchError::from(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for ScratchError {
+    fn from(err: std::io::Error) -> Self {
+        Self::from_io(err, None)
+    }
+}
+
+impl From<WriteError> for ScratchError {
+    fn from(err: WriteError) -> Self {
+        let path = Some(err.path.to_string_lossy().into_owned());
+        let message = err.to_string();
+        if err.is_disk_full() {
+            return ScratchError::DiskFull { path, message };
+        }
+        match err.source.kind() {
+            ErrorKind::PermissionDenied => ScratchError::PermissionDenied { path, message },
+            _ => ScratchError::Io { path, message },
+        }
+    }
+}
+
+impl From<FlushError> for ScratchError {
+    fn from(err: FlushError) -> Self {
+        match err {
+            FlushError::Conflict(_) => ScratchError::Other {
+                message: err.to_string(),
+            },
+            FlushError::Write(message) => message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ScratchError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<std::io::Error>() {
+            Ok(err) => err.into(),
+            Err(err) => ScratchError::Other {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for ScratchError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        ScratchError::Other {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<tauri::Error> for ScratchError {
+    fn from(err: tauri::Error) -> Self {
+        match err {
+            tauri::Error::Io(err) => err.into(),
+            err => ScratchError::Other {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl From<serde_json::Error> for ScratchError {
+    fn from(err: serde_json::Error) -> Self {
+        ScratchError::InvalidInput {
+            message: err.to_string(),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Named export profiles.
+//!
+//! A profile selects notes by folder and tags, writes them to an output
+//! directory as markdown or standalone HTML, and optionally copies the assets
+//! they reference. Profiles are stored in the vault settings
+//! (`exportProfiles`) and run by name.
+
+use crate::{links, render, tags, ScannedNote};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetHandling {
+    #[default]
+    Copy,
+    Skip,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProfile {
+    pub name: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub folder: Option<String>, // only notes under this folder
+    #[serde(default)]
+    pub include_tags: Vec<String>, // notes must carry one of these (empty = all)
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    #[serde(default)]
+    pub assets: AssetHandling,
+    pub css: Option<String>, // extra CSS for HTML exports
+    pub output_dir: String,  // absolute path outside the vault
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub profile: String,
+    pub output_dir: String,
+    pub exported: usize,
+    pub assets_copied: usize,
+    pub finished_at: i64,
+}
+
+impl ExportProfile {
+    fn selects(&self, note: &ScannedNote) -> bool {
+        if let Some(folder) = self.folder.as_deref().map(|f| f.trim_matches('/')) {
+            if !folder.is_empty() && !note.id.starts_with(&format!("{}/", folder)) {
+                return false;
+            }
+        }
+        if self.include_tags.is_empty() && self.exclude_tags.is_empty() {
+            return true;
+        }
+        let note_tags = tags::extract_tags(&note.content);
+        let has = |wanted: &[String]| wanted.iter().any(|t| note_tags.contains(&tags::normalize(t)));
+        (self.include_tags.is_empty() || has(&self.include_tags)) && !has(&self.exclude_tags)
+    }
+}
+
+/// Check an output directory is absolute and outside the vault, so written
+/// files are never picked up as notes. Creates it if needed.
+pub fn validate_output_dir(dir: &str, notes_root: &Path) -> Result<PathBuf, String> {
+    let path = PathBuf::from(dir.trim());
+    if dir.trim().is_empty() || !path.is_absolute() {
+        return Err("Output directory must be an absolute path".to_string());
+    }
+    std::fs::create_dir_all(&path).map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let canonical = path.canonicalize().map_err(|e| e.to_string())?;
+    let root = notes_root.canonicalize().map_err(|e| e.to_string())?;
+    if canonical.starts_with(&root) {
+        return Err("Output directory cannot be inside the notes folder".to_string());
+    }
+    Ok(canonical)
+}
+
+/// Run a profile over the given notes. Blocking; call from spawn_blocking.
+/// `class_styles` are the vault's `exportClassStyles`, for HTML pages.
+pub fn run(
+    profile: &ExportProfile,
+    notes: &[ScannedNote],
+    notes_root: &Path,
+    class_styles: &BTreeMap<String, String>,
+) -> Result<ExportResult, String> {
+    let out_dir = validate_output_dir(&profile.output_dir, notes_root)?;
+
+    let mut exported = 0;
+    let mut assets: Vec<String> = Vec::new();
+    for note in notes.iter().filter(|n| profile.selects(n)) {
+        let (extension, body) = match profile.format {
+            ExportFormat::Markdown => ("md", note.content.clone()),
+            ExportFormat::Html => (
+                "html",
+                render::render_page(
+                    &note.title,
+                    &note.content,
+                    "",
+                    profile.css.as_deref(),
+                    Some(class_styles),
+                ),
+            ),
+        };
+        let target = out_dir.join(format!("{}.{}", note.id, extension));
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&target, body).map_err(|e| format!("Failed to write {}: {}", note.id, e))?;
+        exported += 1;
+
+        if profile.assets == AssetHandling::Copy {
+            for name in links::extract_asset_refs(&note.content) {
+                if !assets.contains(&name) {
+                    assets.push(name);
+                }
+            }
+        }
+    }
+
+    let mut assets_copied = 0;
+    for name in assets {
+        let source = notes_root.join("assets").join(&name);
+        if !source.is_file() {
+            continue;
+        }
+        let target = out_dir.join("assets").join(&name);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        if std::fs::copy(&source, &target).is_ok() {
+            assets_copied += 1;
+        }
+    }
+
+    Ok(ExportResult {
+        profile: profile.name.clone(),
+        output_dir: out_dir.to_string_lossy().into_owned(),
+        exported,
+        assets_copied,
+        finished_at: chrono::Utc::now().timestamp(),
+    })
+}
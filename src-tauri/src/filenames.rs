@@ -0,0 +1,271 @@
+//! How new notes are named on disk.
+//!
+//! By default a note's file name is its sanitized title and follows it on
+//! every retitle. The other schemes keep names stable: a timestamp or UUID
+//! never changes, and a date-prefixed slug keeps its date while the slug
+//! follows the title. Either way the title itself lives in the note's first
+//! heading, not in the file name.
+//!
+//! Title-based names can be transliterated to ASCII ("Über μλ" → "Uber ml")
+//! and written in kebab or snake case ("uber-ml", "uber_ml") for vaults synced
+//! to filesystems or tools that choke on anything else.
+
+use crate::{sanitize_filename, Settings};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilenameScheme {
+    #[default]
+    TitleSlug, // "Meeting Notes"
+    Timestamp,      // "20261015143000"
+    Uuid,           // "3f2b8c1e-..."
+    DatePrefixSlug, // "2026-10-15-meeting-notes"
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SlugStyle {
+    #[default]
+    PreserveCase, // "Meeting Notes"
+    Kebab, // "meeting-notes"
+    Snake, // "meeting_notes"
+}
+
+/// The naming settings in effect.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Naming {
+    pub scheme: FilenameScheme,
+    pub style: SlugStyle,
+    pub transliterate: bool,
+}
+
+/// ASCII spelling of a lowercase letter, if there is one.
+fn ascii_for(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => "s",
+        'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'þ' => "th",
+        // Greek
+        'α' | 'ά' => "a",
+        'β' => "b",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' | 'έ' => "e",
+        'ζ' => "z",
+        'η' | 'ή' => "i",
+        'θ' => "th",
+        'ι' | 'ί' | 'ϊ' | 'ΐ' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ό' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' | 'ύ' | 'ϋ' | 'ΰ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' | 'ώ' => "o",
+        // Cyrillic
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// Spell `text` in ASCII, keeping case. Characters with no known spelling
+/// are dropped.
+pub fn transliterate(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            out.push(c);
+            continue;
+        }
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let Some(ascii) = ascii_for(lower) else {
+            if c.is_whitespace() {
+                out.push(' ');
+            }
+            continue;
+        };
+        if c.is_uppercase() {
+            let mut chars = ascii.chars();
+            if let Some(first) = chars.next() {
+                out.push(first.to_ascii_uppercase());
+                out.push_str(chars.as_str());
+            }
+        } else {
+            out.push_str(ascii);
+        }
+    }
+    out
+}
+
+/// The title lowercased, with each run of anything but letters and digits
+/// turned into one `separator`.
+fn slugify(title: &str, separator: char) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with(separator) {
+            slug.push(separator);
+        }
+    }
+    let slug = slug.trim_end_matches(separator);
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+fn uuid_v4() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// The `YYYY-MM-DD-` prefix of a date-prefixed file name.
+fn date_prefix(leaf: &str) -> Option<&str> {
+    let prefix = leaf.get(..11)?;
+    let valid = prefix.bytes().enumerate().all(|(i, b)| match i {
+        4 | 7 | 10 => b == b'-',
+        _ => b.is_ascii_digit(),
+    });
+    valid.then_some(prefix)
+}
+
+impl Naming {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Naming {
+            scheme: settings.filename_scheme.unwrap_or_default(),
+            style: settings.slug_style.unwrap_or_default(),
+            transliterate: settings.transliterate_filenames.unwrap_or(false),
+        }
+    }
+
+    /// Whether file names are exactly the sanitized title (the default).
+    pub fn is_plain_title(&self) -> bool {
+        self.scheme == FilenameScheme::TitleSlug
+            && self.style == SlugStyle::PreserveCase
+            && !self.transliterate
+    }
+
+    fn prepare<'a>(&self, title: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.transliterate {
+            std::borrow::Cow::Owned(transliterate(title))
+        } else {
+            std::borrow::Cow::Borrowed(title)
+        }
+    }
+
+    /// File name for a title under the slug style.
+    fn title_leaf(&self, title: &str) -> String {
+        let title = self.prepare(title);
+        match self.style {
+            SlugStyle::PreserveCase => sanitize_filename(&title),
+            SlugStyle::Kebab => slugify(&title, '-'),
+            SlugStyle::Snake => slugify(&title, '_'),
+        }
+    }
+
+    /// File name (without folder or extension) for a new note.
+    pub fn new_leaf(&self, title: &str, now: DateTime<Local>) -> String {
+        match self.scheme {
+            FilenameScheme::TitleSlug => self.title_leaf(title),
+            FilenameScheme::Timestamp => now.format("%Y%m%d%H%M%S").to_string(),
+            FilenameScheme::Uuid => uuid_v4(),
+            FilenameScheme::DatePrefixSlug => {
+                format!(
+                    "{}-{}",
+                    now.format("%Y-%m-%d"),
+                    slugify(&self.prepare(title), '-')
+                )
+            }
+        }
+    }
+
+    /// File name for note `leaf` after it is retitled to `title`. Stable
+    /// schemes keep `leaf`; a date-prefixed name keeps its date, and a name
+    /// without one (made before the scheme was chosen) is left alone.
+    pub fn retitled_leaf(&self, leaf: &str, title: &str) -> String {
+        match self.scheme {
+            FilenameScheme::TitleSlug => self.title_leaf(title),
+            FilenameScheme::Timestamp | FilenameScheme::Uuid => leaf.to_string(),
+            FilenameScheme::DatePrefixSlug => match date_prefix(leaf) {
+                Some(prefix) => format!("{}{}", prefix, slugify(&self.prepare(title), '-')),
+                None => leaf.to_string(),
+            },
+        }
+    }
+}
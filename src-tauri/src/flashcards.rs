@@ -0,0 +1,199 @@
+//! Flashcard extraction and SM-2 scheduling.
+//!
+//! Cards are written inline in notes, either as `Q:` / `A:` line pairs or as a
+//! paragraph tagged `#flashcard` (first line is the question, the rest of the
+//! paragraph is the answer). Review state lives in `.scratch/flashcards.json`,
+//! keyed by a card ID derived from the note ID and question text.
+
+use crate::{frontmatter, store};
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+const STORE_FILE: &str = "flashcards.json";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+/// A question/answer pair found in a note.
+#[derive(Debug, Clone)]
+pub struct Card {
+    pub id: String,
+    pub question: String,
+    pub answer: String,
+}
+
+/// SM-2 review state for a single card.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardSchedule {
+    pub ease: f64,
+    pub interval_days: u32,
+    pub repetitions: u32,
+    pub due: String,                   // YYYY-MM-DD
+    pub last_reviewed: Option<String>, // YYYY-MM-DD
+}
+
+impl CardSchedule {
+    fn new(today: NaiveDate) -> Self {
+        Self {
+            ease: DEFAULT_EASE,
+            interval_days: 0,
+            repetitions: 0,
+            due: today.format(DATE_FORMAT).to_string(),
+            last_reviewed: None,
+        }
+    }
+
+    /// Whether the card is due on `today`. Unparseable dates count as due.
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        NaiveDate::parse_from_str(&self.due, DATE_FORMAT)
+            .map(|due| due <= today)
+            .unwrap_or(true)
+    }
+
+    /// Apply an SM-2 grade (0 = blackout, 5 = perfect recall).
+    fn grade(&mut self, grade: u8, today: NaiveDate) {
+        let q = grade.min(5) as f64;
+        if grade < 3 {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        } else {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval_days as f64 * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        }
+        self.ease = (self.ease + (0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02))).max(MIN_EASE);
+        self.last_reviewed = Some(today.format(DATE_FORMAT).to_string());
+        self.due = (today + chrono::Duration::days(self.interval_days as i64))
+            .format(DATE_FORMAT)
+            .to_string();
+    }
+}
+
+/// Per-vault review state, persisted as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlashcardStore {
+    pub cards: HashMap<String, CardSchedule>,
+}
+
+impl FlashcardStore {
+    pub fn load(notes_folder: &str) -> Self {
+        store::load(Path::new(notes_folder), STORE_FILE)
+    }
+
+    pub fn save(&self, notes_folder: &str) -> Result<()> {
+        store::save(Path::new(notes_folder), STORE_FILE, self)
+    }
+
+    /// Schedule for a card, treating unseen cards as new and due today.
+    pub fn schedule(&self, card_id: &str, today: NaiveDate) -> CardSchedule {
+        self.cards
+            .get(card_id)
+            .cloned()
+            .unwrap_or_else(|| CardSchedule::new(today))
+    }
+
+    /// Record a review and return the updated schedule.
+    pub fn grade(&mut self, card_id: &str, grade: u8, today: NaiveDate) -> CardSchedule {
+        let schedule = self
+            .cards
+            .entry(card_id.to_string())
+            .or_insert_with(|| CardSchedule::new(today));
+        schedule.grade(grade, today);
+        schedule.clone()
+    }
+}
+
+/// Stable card ID from the owning note and the question text.
+fn card_id(note_id: &str, question: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(format!("{}\n{}", note_id, question.trim()).as_bytes());
+    digest.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+fn push_card(cards: &mut Vec<Card>, note_id: &str, question: &str, answer: &[&str]) {
+    let question = question.trim();
+    let answer = answer.join("\n").trim().to_string();
+    if question.is_empty() || answer.is_empty() {
+        return;
+    }
+    let id = card_id(note_id, question);
+    if cards.iter().any(|c| c.id == id) {
+        return;
+    }
+    cards.push(Card {
+        id,
+        question: question.to_string(),
+        answer,
+    });
+}
+
+/// Extract flashcards from a note, skipping fenced code blocks.
+pub fn extract_cards(note_id: &str, content: &str) -> Vec<Card> {
+    let mut cards = Vec::new();
+
+    // Split the body into paragraphs outside of code fences
+    let mut paragraphs: Vec<Vec<&str>> = vec![Vec::new()];
+    let mut in_fence = false;
+    for line in frontmatter::body(content).lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            paragraphs.push(Vec::new());
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if trimmed.is_empty() {
+            paragraphs.push(Vec::new());
+        } else if let Some(current) = paragraphs.last_mut() {
+            current.push(trimmed);
+        }
+    }
+
+    for paragraph in paragraphs.iter().filter(|p| !p.is_empty()) {
+        // `#flashcard` paragraph: first line is the question
+        if paragraph[0].split_whitespace().any(|w| w == "#flashcard") {
+            let question: Vec<&str> = paragraph[0]
+                .split_whitespace()
+                .filter(|w| *w != "#flashcard")
+                .collect();
+            push_card(&mut cards, note_id, &question.join(" "), &paragraph[1..]);
+            continue;
+        }
+
+        // `Q:` / `A:` pairs; an answer runs until the next `Q:` or paragraph end
+        let mut question: Option<&str> = None;
+        let mut answer: Vec<&str> = Vec::new();
+        let mut in_answer = false;
+        for line in paragraph {
+            if let Some(q) = line.strip_prefix("Q:") {
+                if let Some(prev) = question {
+                    push_card(&mut cards, note_id, prev, &answer);
+                }
+                question = Some(q);
+                answer.clear();
+                in_answer = false;
+            } else if let Some(a) = line.strip_prefix("A:") {
+                if question.is_some() {
+                    answer.push(a);
+                    in_answer = true;
+                }
+            } else if in_answer {
+                answer.push(line);
+            }
+        }
+        if let Some(prev) = question {
+            push_card(&mut cards, note_id, prev, &answer);
+        }
+    }
+
+    cards
+}
@@ -0,0 +1,224 @@
+//! Minimal YAML frontmatter support for note metadata.
+//!
+//! Notes may start with a `---` delimited block of `key: value` pairs. Only the
+//! subset of YAML that notes actually use is understood: scalars, inline lists
+//! (`tags: [a, b]`) and block lists (`- item` lines under a key).
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Frontmatter {
+    entries: Vec<(String, Value)>,
+}
+
+/// Split content into the raw frontmatter block (without delimiters) and the body.
+/// Returns None if the content has no frontmatter.
+fn split(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\r\n")
+        .or_else(|| content.strip_prefix("---\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Strip a value's quotes. In double quotes, `\"` and `\\` are unescaped;
+/// other escapes are left as written.
+fn unquote(value: &str) -> String {
+    let v = value.trim();
+    if v.len() >= 2 && v.starts_with('"') && v.ends_with('"') {
+        let mut out = String::new();
+        let mut chars = v[1..v.len() - 1].chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('\\', Some(&next @ ('"' | '\\'))) => {
+                    out.push(next);
+                    chars.next();
+                }
+                _ => out.push(c),
+            }
+        }
+        out
+    } else if v.len() >= 2 && v.starts_with('\'') && v.ends_with('\'') {
+        v[1..v.len() - 1].to_string()
+    } else {
+        v.to_string()
+    }
+}
+
+fn quote_if_needed(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.starts_with([' ', '[', '{', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@'])
+        || value.ends_with(' ')
+        || value.contains(": ")
+        || value.contains(" #");
+    if needs_quotes {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl Frontmatter {
+    /// Parse the frontmatter block of a note. Missing or malformed frontmatter
+    /// yields an empty set of entries.
+    pub fn parse(content: &str) -> Self {
+        let Some((block, _)) = split(content) else {
+            return Self::default();
+        };
+
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        for line in block.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            // Block list item belonging to the previous key
+            if trimmed == "-" || trimmed.starts_with("- ") {
+                if let Some((_, value)) = entries.last_mut() {
+                    if matches!(value, Value::Scalar(s) if s.is_empty()) {
+                        *value = Value::List(Vec::new());
+                    }
+                    if let Value::List(items) = value {
+                        items.push(unquote(&trimmed[1..]));
+                    }
+                }
+                continue;
+            }
+
+            // Nested mappings are not supported; skip indented lines
+            if line.starts_with([' ', '\t']) {
+                continue;
+            }
+
+            let Some((key, raw)) = trimmed.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_string();
+            let raw = raw.trim();
+
+            let value = if raw.starts_with('[') && raw.ends_with(']') {
+                Value::List(
+                    raw[1..raw.len() - 1]
+                        .split(',')
+                        .map(unquote)
+                        .filter(|s| !s.is_empty())
+                        .collect(),
+                )
+            } else {
+                Value::Scalar(unquote(raw))
+            };
+            entries.push((key, value));
+        }
+
+        Self { entries }
+    }
+
+    /// Keys and values in the order they are written.
+    pub fn entries(&self) -> &[(String, Value)] {
+        &self.entries
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find(|(k, _)| k == key).and_then(|(_, v)| match v {
+            Value::Scalar(s) if !s.is_empty() => Some(s.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)?.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" => Some(true),
+            "false" | "no" | "off" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns a list value. A scalar is treated as a single-item list and a
+    /// comma-separated scalar (`tags: a, b`) is split.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        match self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v) {
+            Some(Value::List(items)) => items.clone(),
+            Some(Value::Scalar(s)) if !s.is_empty() => s
+                .split(',')
+                .map(|part| part.trim().to_string())
+                .filter(|part| !part.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Return the note body without its frontmatter block.
+pub fn body(content: &str) -> &str {
+    split(content).map(|(_, body)| body).unwrap_or(content)
+}
+
+fn render_entry(key: &str, value: &Value, newline: &str) -> String {
+    match value {
+        Value::Scalar(s) => format!("{}: {}{}", key, quote_if_needed(s), newline),
+        Value::List(items) => {
+            let rendered: Vec<String> = items.iter().map(|i| quote_if_needed(i)).collect();
+            format!("{}: [{}]{}", key, rendered.join(", "), newline)
+        }
+    }
+}
+
+/// Set (or replace) a key in the note's frontmatter, creating the block if needed.
+/// Other lines in the block are preserved as written, and new lines end the
+/// way the note's lines do (CRLF or LF).
+pub fn set(content: &str, key: &str, value: Value) -> String {
+    let newline = match content.find('\n') {
+        Some(i) if content[..i].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let rendered = render_entry(key, &value, newline);
+
+    let Some((block, body)) = split(content) else {
+        return format!("---{nl}{}---{nl}{}", rendered, content, nl = newline);
+    };
+
+    let mut out = format!("---{}", newline);
+    let mut replaced = false;
+    let mut skipping_list = false;
+    for line in block.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if skipping_list {
+            if trimmed.starts_with('-') || line.starts_with([' ', '\t']) {
+                continue;
+            }
+            skipping_list = false;
+        }
+        let is_key = !line.starts_with([' ', '\t'])
+            && trimmed.split_once(':').map(|(k, _)| k.trim() == key).unwrap_or(false);
+        if is_key {
+            if !replaced {
+                out.push_str(&rendered);
+                replaced = true;
+            }
+            skipping_list = true;
+            continue;
+        }
+        out.push_str(line);
+        if !line.ends_with('\n') {
+            out.push_str(newline);
+        }
+    }
+    if !replaced {
+        out.push_str(&rendered);
+    }
+    out.push_str("---");
+    out.push_str(newline);
+    out.push_str(body);
+    out
+}
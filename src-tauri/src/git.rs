@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Output};
+use std::sync::Mutex;
+
+/// Auto-commits run off the save path; one at a time so they don't trip over
+/// each other's `index.lock`.
+static AUTO_COMMIT: Mutex<()> = Mutex::new(());
 
 /// Create a `Command` for git that hides the console window on Windows.
 fn git_cmd() -> Command {
@@ -161,14 +166,17 @@ pub fn get_status(path: &Path) -> GitStatus {
     status
 }
 
-/// Stage all changes and commit
-pub fn commit_all(path: &Path, message: &str) -> GitResult {
+/// Stage all changes except those under `excluded` (vault-relative files or
+/// folders) and commit
+pub fn commit_all(path: &Path, message: &str, excluded: &[String]) -> GitResult {
     // Stage all changes
-    let stage_output = match git_cmd()
-        .args(["add", "-A"])
-        .current_dir(path)
-        .output()
-    {
+    let mut args = vec!["add".to_string(), "-A".to_string()];
+    if !excluded.is_empty() {
+        args.push("--".to_string());
+        args.push(".".to_string());
+        args.extend(excluded.iter().map(|p| format!(":(exclude,literal){}", p)));
+    }
+    let stage_output = match git_cmd().args(&args).current_dir(path).output() {
         Ok(output) => output,
         Err(e) => {
             return GitResult {
@@ -210,8 +218,14 @@ pub fn commit_all(path: &Path, message: &str) -> GitResult {
                 }
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                // "nothing to commit" is not really an error
-                if stderr.contains("nothing to commit") {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                // "nothing to commit" is not really an error, nor is having
+                // only excluded changes
+                if stderr.contains("nothing to commit")
+                    || stdout.contains("nothing to commit")
+                    || stdout.contains("no changes added to commit")
+                    || stdout.contains("nothing added to commit")
+                {
                     GitResult {
                         success: true,
                         message: Some("Nothing to commit".to_string()),
@@ -436,6 +450,182 @@ pub fn push_with_upstream(path: &Path, branch: &str) -> GitResult {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommit {
+    pub rev: String,
+    pub short_rev: String,
+    pub author: String,
+    pub timestamp: i64, // unix seconds
+    pub message: String,
+    pub path: String, // the note's vault-relative path in this commit
+}
+
+/// Run git in `path` with pathspecs taken literally, so note names with `*`
+/// or `:` aren't read as patterns.
+fn run_literal(path: &Path, args: &[&str]) -> Result<Output, String> {
+    git_cmd()
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .env("GIT_LITERAL_PATHSPECS", "1")
+        .current_dir(path)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))
+}
+
+fn stdout_or_err(output: Output) -> Result<String, String> {
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Commit just `files` (vault-relative, existing or deleted), leaving
+/// anything else staged or changed alone.
+pub fn commit_paths(path: &Path, files: &[String], message: &str) -> GitResult {
+    if files.is_empty() {
+        return GitResult {
+            success: true,
+            message: Some("Nothing to commit".to_string()),
+            error: None,
+        };
+    }
+    let _serial = AUTO_COMMIT.lock().unwrap_or_else(|e| e.into_inner());
+    let result = (|| -> Result<&str, String> {
+        for file in files {
+            let args = if path.join(file).exists() {
+                vec!["add", "--", file.as_str()]
+            } else {
+                vec![
+                    "rm",
+                    "--cached",
+                    "--quiet",
+                    "--ignore-unmatch",
+                    "--",
+                    file.as_str(),
+                ]
+            };
+            stdout_or_err(run_literal(path, &args)?)?;
+        }
+        // Only paths with staged changes; git refuses to commit the others.
+        // Without renames, so both sides of a rename are listed.
+        let mut args = vec!["diff", "--cached", "--no-renames", "--name-only", "--"];
+        args.extend(files.iter().map(String::as_str));
+        let staged = stdout_or_err(run_literal(path, &args)?)?;
+        let staged: Vec<&str> = staged.lines().filter(|l| !l.is_empty()).collect();
+        if staged.is_empty() {
+            return Ok("Nothing to commit");
+        }
+        let mut args = vec!["commit", "--quiet", "-m", message, "--"];
+        args.extend(staged);
+        stdout_or_err(run_literal(path, &args)?)?;
+        Ok("Changes committed")
+    })();
+    match result {
+        Ok(message) => GitResult {
+            success: true,
+            message: Some(message.to_string()),
+            error: None,
+        },
+        Err(error) => GitResult {
+            success: false,
+            message: None,
+            error: Some(error),
+        },
+    }
+}
+
+/// Commits that touched the note at `file`, newest first, following it
+/// through renames.
+pub fn file_history(path: &Path, file: &str) -> Result<Vec<GitCommit>, String> {
+    if !is_git_repo(path) {
+        return Err("Notes folder is not a git repository".to_string());
+    }
+    let output = run_literal(
+        path,
+        &[
+            "log",
+            "--follow",
+            "--name-only",
+            "--format=%x1e%H%x1f%h%x1f%an%x1f%at%x1f%s",
+            "--",
+            file,
+        ],
+    )?;
+    // A repository without commits has no history rather than an error
+    if !output.status.success()
+        && String::from_utf8_lossy(&output.stderr).contains("does not have any commits")
+    {
+        return Ok(Vec::new());
+    }
+    let log = stdout_or_err(output)?;
+    let commits = log
+        .split('\u{1e}')
+        .filter_map(|entry| {
+            let mut lines = entry.lines();
+            let header = lines.next()?;
+            let fields: Vec<&str> = header.split('\u{1f}').collect();
+            let [rev, short_rev, author, timestamp, message] = fields[..] else {
+                return None;
+            };
+            let path = lines.find(|l| !l.is_empty()).unwrap_or(file);
+            Some(GitCommit {
+                rev: rev.to_string(),
+                short_rev: short_rev.to_string(),
+                author: author.to_string(),
+                timestamp: timestamp.parse().unwrap_or(0),
+                message: message.to_string(),
+                path: path.to_string(),
+            })
+        })
+        .collect();
+    Ok(commits)
+}
+
+/// The commit `rev` names in the history of `file`. Only revisions from that
+/// history are accepted, so `rev` never reaches git as an option or a path.
+fn commit_in_history(path: &Path, file: &str, rev: &str) -> Result<GitCommit, String> {
+    let rev = rev.trim();
+    if rev.len() < 4 {
+        return Err("Revision not found in this note's history".to_string());
+    }
+    file_history(path, file)?
+        .into_iter()
+        .find(|c| c.rev.starts_with(rev))
+        .ok_or_else(|| "Revision not found in this note's history".to_string())
+}
+
+/// The changes commit `rev` made to the note at `file`, as a unified diff.
+pub fn file_diff(path: &Path, file: &str, rev: &str) -> Result<String, String> {
+    let commit = commit_in_history(path, file, rev)?;
+    let output = run_literal(
+        path,
+        &[
+            "show",
+            "--no-color",
+            "--format=",
+            commit.rev.as_str(),
+            "--",
+            commit.path.as_str(),
+        ],
+    )?;
+    stdout_or_err(output)
+}
+
+/// The note at `file` as it was in commit `rev`.
+pub fn file_at(path: &Path, file: &str, rev: &str) -> Result<(GitCommit, String), String> {
+    let commit = commit_in_history(path, file, rev)?;
+    let object = format!("{}:{}", commit.rev, commit.path);
+    let output = run_literal(path, &["show", object.as_str()])?;
+    if !output.status.success() {
+        return Err("The note doesn't exist in this revision".to_string());
+    }
+    let content = String::from_utf8(output.stdout)
+        .map_err(|_| "The note isn't valid UTF-8 in this revision".to_string())?;
+    Ok((commit, content))
+}
+
 /// Basic validation for git remote URLs
 fn is_valid_remote_url(url: &str) -> bool {
     let url = url.trim();
@@ -0,0 +1,204 @@
+//! Goals and OKR rollup from plain notes.
+//!
+//! A note with a `goal:` frontmatter key works toward that goal (`goal: true`
+//! uses the note's title); several notes may share a goal. `quarter:`
+//! (`2026-Q4`, or derived from a `due:` date) and `area:` group goals in the
+//! rollup. A goal's progress is the average of its measurements: a note's
+//! `progress:` frontmatter value (`40%`, `0.4`, `2/5`) stands for the whole
+//! note; otherwise each task counts as done or not, and any line may carry an
+//! explicit `@progress(3/5)` marker.
+
+use crate::frontmatter::{self, Frontmatter};
+use crate::{tasks, ScannedNote};
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+    pub name: String,
+    pub area: Option<String>,
+    pub quarter: Option<String>, // e.g. "2026-Q4"
+    pub progress: f64,           // 0.0..=1.0
+    pub measurements: usize,     // key results and progress values averaged
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollupGroup {
+    pub key: String, // quarter or area; "" for goals without one
+    pub goals: usize,
+    pub progress: f64, // mean of the group's goals
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalRollup {
+    pub goals: Vec<GoalProgress>,
+    pub by_quarter: Vec<RollupGroup>,
+    pub by_area: Vec<RollupGroup>,
+    pub overall: f64,
+}
+
+struct Goal {
+    progress: GoalProgress,
+    values: Vec<f64>,
+}
+
+/// Normalize "2026-Q4", "Q4 2026" or "2026 q4" to "2026-Q4".
+fn normalize_quarter(value: &str) -> String {
+    let parts: Vec<String> = value
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '/')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_uppercase())
+        .collect();
+    let year = parts
+        .iter()
+        .find(|p| p.len() == 4 && p.chars().all(|c| c.is_ascii_digit()));
+    let quarter = parts
+        .iter()
+        .find(|p| matches!(p.as_str(), "Q1" | "Q2" | "Q3" | "Q4"));
+    match (year, quarter) {
+        (Some(year), Some(quarter)) => format!("{}-{}", year, quarter),
+        _ => value.trim().to_string(),
+    }
+}
+
+fn quarter_of(date: NaiveDate) -> String {
+    format!("{}-Q{}", date.year(), (date.month() - 1) / 3 + 1)
+}
+
+/// Progress values a note contributes to its goal.
+fn measurements(content: &str, meta: &Frontmatter) -> Vec<f64> {
+    if let Some(progress) = meta.get("progress").and_then(tasks::parse_progress) {
+        return vec![progress];
+    }
+    let note_tasks = tasks::extract_tasks(content);
+    let task_lines: HashSet<usize> = note_tasks.iter().map(|t| t.line).collect();
+    let mut values: Vec<f64> = note_tasks
+        .iter()
+        .map(|t| match (t.done, t.progress) {
+            (true, _) => 1.0,
+            (false, Some(progress)) => progress,
+            (false, None) => 0.0,
+        })
+        .collect();
+
+    // Key results written as plain lines with a marker
+    let mut in_fence = false;
+    for (line_no, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || task_lines.contains(&line_no) {
+            continue;
+        }
+        values.extend(tasks::progress_marker(line));
+    }
+    values
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+fn group_by(
+    goals: &[GoalProgress],
+    key: impl Fn(&GoalProgress) -> Option<&String>,
+) -> Vec<RollupGroup> {
+    let mut keys: Vec<String> = goals
+        .iter()
+        .map(|g| key(g).cloned().unwrap_or_default())
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .map(|k| {
+            let members: Vec<&GoalProgress> = goals
+                .iter()
+                .filter(|&g| key(g).map(String::as_str).unwrap_or_default() == k)
+                .collect();
+            RollupGroup {
+                goals: members.len(),
+                progress: mean(members.iter().map(|g| g.progress)),
+                key: k,
+            }
+        })
+        .collect()
+}
+
+/// Collect every goal in the vault and roll progress up by quarter and area.
+pub fn rollup(notes: &[ScannedNote]) -> GoalRollup {
+    let mut goals: Vec<Goal> = Vec::new();
+    for note in notes {
+        let meta = Frontmatter::parse(&note.content);
+        let name = match meta.get("goal") {
+            Some(value) if value.eq_ignore_ascii_case("true") => note.title.clone(),
+            Some(value) if !value.eq_ignore_ascii_case("false") => value.trim().to_string(),
+            _ => continue,
+        };
+        let quarter = meta.get("quarter").map(normalize_quarter).or_else(|| {
+            meta.get("due")
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                .map(quarter_of)
+        });
+        let area = meta.get("area").map(|a| a.trim().to_string());
+        let values = measurements(frontmatter::body(&note.content), &meta);
+
+        let index = match goals
+            .iter()
+            .position(|g| g.progress.name.eq_ignore_ascii_case(&name))
+        {
+            Some(index) => index,
+            None => {
+                goals.push(Goal {
+                    progress: GoalProgress {
+                        name,
+                        area: None,
+                        quarter: None,
+                        progress: 0.0,
+                        measurements: 0,
+                        notes: Vec::new(),
+                    },
+                    values: Vec::new(),
+                });
+                goals.len() - 1
+            }
+        };
+        let goal = &mut goals[index];
+        goal.progress.area = goal.progress.area.take().or(area);
+        goal.progress.quarter = goal.progress.quarter.take().or(quarter);
+        goal.progress.notes.push(note.id.clone());
+        goal.values.extend(values);
+    }
+
+    let mut goals: Vec<GoalProgress> = goals
+        .into_iter()
+        .map(|goal| GoalProgress {
+            progress: mean(goal.values.iter().copied()),
+            measurements: goal.values.len(),
+            ..goal.progress
+        })
+        .collect();
+    goals.sort_by(|a, b| {
+        a.quarter
+            .cmp(&b.quarter)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+
+    GoalRollup {
+        by_quarter: group_by(&goals, |g| g.quarter.as_ref()),
+        by_area: group_by(&goals, |g| g.area.as_ref()),
+        overall: mean(goals.iter().map(|g| g.progress)),
+        goals,
+    }
+}
@@ -0,0 +1,142 @@
+//! Habit tracking from daily-note checkboxes.
+//!
+//! A habit is a task in the daily notes whose text is the habit's name
+//! (case-insensitive), optionally followed by more detail ("Run 5k" counts for
+//! "Run"). Each day in a range is done (a checked box), missed (an unchecked
+//! box) or untracked (no daily note, or no box for the habit).
+
+use crate::{abs_path_from_id, daily, is_private_note, tasks};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const MAX_RANGE_DAYS: i64 = 3660;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HabitRange {
+    pub from: String, // YYYY-MM-DD or a natural date ("30 days ago")
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HabitStatus {
+    Done,
+    Missed,
+    Untracked,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HabitDay {
+    pub date: String,
+    pub status: HabitStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HabitData {
+    pub habit: String,
+    pub from: String,
+    pub to: String,
+    pub days: Vec<HabitDay>,
+    pub done: usize,
+    pub missed: usize,
+    pub current_streak: usize, // done days ending at `to` (or the day before, if `to` is still open)
+    pub longest_streak: usize,
+}
+
+fn matches_habit(text: &str, habit: &str) -> bool {
+    let text = text.to_lowercase();
+    match text.strip_prefix(habit) {
+        Some(rest) => !rest.starts_with(char::is_alphanumeric),
+        None => false,
+    }
+}
+
+fn status_on(
+    notes_root: &Path,
+    daily: &daily::Layout,
+    private_folders: Option<&[String]>,
+    habit: &str,
+    date: NaiveDate,
+) -> HabitStatus {
+    let id = daily.note_id(date);
+    let Some(content) = abs_path_from_id(notes_root, &id)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+    else {
+        return HabitStatus::Untracked;
+    };
+    if private_folders.is_some_and(|private| is_private_note(&id, &content, private)) {
+        return HabitStatus::Untracked;
+    }
+    let boxes: Vec<bool> = tasks::extract_tasks(&content)
+        .into_iter()
+        .filter(|t| matches_habit(&t.text, habit))
+        .map(|t| t.done)
+        .collect();
+    if boxes.iter().any(|done| *done) {
+        HabitStatus::Done
+    } else if boxes.is_empty() {
+        HabitStatus::Untracked
+    } else {
+        HabitStatus::Missed
+    }
+}
+
+/// Completion series for a habit over `from..=to`. Blocking.
+pub fn habit_data(
+    notes_root: &Path,
+    daily: &daily::Layout,
+    private_folders: Option<&[String]>,
+    habit: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    today: NaiveDate,
+) -> HabitData {
+    let needle = habit.trim().to_lowercase();
+    let days: Vec<HabitDay> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| HabitDay {
+            date: date.format("%Y-%m-%d").to_string(),
+            status: status_on(notes_root, daily, private_folders, &needle, date),
+        })
+        .collect();
+
+    let mut longest_streak = 0;
+    let mut run = 0;
+    for day in &days {
+        run = if day.status == HabitStatus::Done {
+            run + 1
+        } else {
+            0
+        };
+        longest_streak = longest_streak.max(run);
+    }
+    // Today does not break the streak until it is over
+    let mut open = days.iter().rev().peekable();
+    if to == today && open.peek().is_some_and(|d| d.status != HabitStatus::Done) {
+        open.next();
+    }
+    let current_streak = open.take_while(|d| d.status == HabitStatus::Done).count();
+
+    HabitData {
+        habit: habit.trim().to_string(),
+        from: from.format("%Y-%m-%d").to_string(),
+        to: to.format("%Y-%m-%d").to_string(),
+        done: days
+            .iter()
+            .filter(|d| d.status == HabitStatus::Done)
+            .count(),
+        missed: days
+            .iter()
+            .filter(|d| d.status == HabitStatus::Missed)
+            .count(),
+        days,
+        current_streak,
+        longest_streak,
+    }
+}
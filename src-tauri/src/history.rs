@@ -0,0 +1,306 @@
+//! Note version history (`.history/` inside the notes folder).
+//!
+//! Every full save leaves a snapshot of the note in `.history/<id>/`, named by
+//! the time it was taken in milliseconds (e.g. `.history/Work/Plan/1760538600000.md`).
+//! When the file held something the history hasn't seen (an edit made outside
+//! the app, or the first save after history started), that is kept first, so
+//! an overwrite can always be undone. A snapshot matching the newest one is
+//! skipped, and only the newest `MAX_VERSIONS` are kept per note.
+//!
+//! Most snapshots are stored as a delta against the version before them
+//! (`<ts>.delta`): the lines the two share at the start and end, and the text
+//! in between. Every `KEYFRAME_EVERY`th version, and any whose delta wouldn't
+//! be much smaller, is stored whole (`<ts>.md`), so reading one never replays
+//! a long chain and the oldest kept version is always whole.
+
+use crate::{abs_path_from_id, recovery};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+pub const HISTORY_DIR: &str = ".history";
+const MAX_VERSIONS: usize = 100;
+const KEYFRAME_EVERY: usize = 20;
+const FULL_EXT: &str = "md";
+const DELTA_EXT: &str = "delta";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteVersion {
+    pub ts: i64, // unix milliseconds, passed back to read_note_version
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStats {
+    pub notes: usize,
+    pub versions: usize,
+    pub full_versions: usize,
+    pub stored_bytes: u64,
+    pub content_bytes: u64, // what the versions would take stored whole
+    pub drafts: usize,
+    pub draft_bytes: u64,
+}
+
+struct Version {
+    ts: i64,
+    path: PathBuf,
+    full: bool,
+}
+
+/// Folder holding the snapshots of note `id`.
+fn note_dir(notes_root: &Path, id: &str) -> Result<PathBuf, String> {
+    // Validates the ID the same way as note paths
+    abs_path_from_id(notes_root, id)?;
+    Ok(id
+        .split('/')
+        .fold(notes_root.join(HISTORY_DIR), |dir, part| dir.join(part)))
+}
+
+/// Snapshot files in `dir`, oldest first.
+fn versions(dir: &Path) -> Vec<Version> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<Version> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let full = match path.extension()?.to_str()? {
+                FULL_EXT => true,
+                DELTA_EXT => false,
+                _ => return None,
+            };
+            let ts = path.file_stem()?.to_str()?.parse().ok()?;
+            Some(Version { ts, path, full })
+        })
+        .collect();
+    versions.sort_by_key(|v| v.ts);
+    versions
+}
+
+/// Delta turning `old` into `new`: a header with the number of lines kept
+/// from the start and end of `old`, then the text that replaces the rest.
+fn diff(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let max_suffix = old_lines.len().min(new_lines.len()) - prefix;
+    let suffix = old_lines
+        .iter()
+        .rev()
+        .zip(new_lines.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let middle: String = new_lines[prefix..new_lines.len() - suffix].concat();
+    format!("{} {}\n{}", prefix, suffix, middle)
+}
+
+/// Apply a delta from `diff` to `old`.
+fn patch(old: &str, delta: &str) -> Option<String> {
+    let (header, middle) = delta.split_once('\n')?;
+    let (prefix, suffix) = header.split_once(' ')?;
+    let (prefix, suffix): (usize, usize) = (prefix.parse().ok()?, suffix.parse().ok()?);
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    if prefix + suffix > old_lines.len() {
+        return None;
+    }
+    let mut new = old_lines[..prefix].concat();
+    new.push_str(middle);
+    new.push_str(&old_lines[old_lines.len() - suffix..].concat());
+    Some(new)
+}
+
+/// Content of `versions[index]`, replayed from the whole version before it.
+fn content_at(versions: &[Version], index: usize) -> Option<String> {
+    let start = versions[..=index].iter().rposition(|v| v.full)?;
+    let mut content = std::fs::read_to_string(&versions[start].path).ok()?;
+    for version in &versions[start + 1..=index] {
+        let delta = std::fs::read_to_string(&version.path).ok()?;
+        content = patch(&content, &delta)?;
+    }
+    Some(content)
+}
+
+/// Record `content` as the newest version, unless it already is.
+fn push(dir: &Path, content: &str) -> std::io::Result<()> {
+    let existing = versions(dir);
+    let newest = existing
+        .len()
+        .checked_sub(1)
+        .and_then(|last| content_at(&existing, last));
+    if newest.as_deref() == Some(content) {
+        return Ok(());
+    }
+    // Never reuse or go behind the newest name, even if the clock did
+    let now = chrono::Utc::now().timestamp_millis();
+    let ts = existing.last().map_or(now, |v| now.max(v.ts + 1));
+    let since_full = existing.iter().rev().take_while(|v| !v.full).count();
+    let delta = newest
+        .filter(|_| since_full + 1 < KEYFRAME_EVERY)
+        .map(|newest| diff(&newest, content))
+        .filter(|delta| delta.len() < content.len() / 2);
+    std::fs::create_dir_all(dir)?;
+    match delta {
+        Some(delta) => std::fs::write(dir.join(format!("{}.{}", ts, DELTA_EXT)), delta),
+        None => std::fs::write(dir.join(format!("{}.{}", ts, FULL_EXT)), content),
+    }
+}
+
+/// Drop all but the newest `MAX_VERSIONS`, first storing the oldest kept
+/// version whole if it was a delta.
+fn prune(dir: &Path) {
+    let existing = versions(dir);
+    let excess = existing.len().saturating_sub(MAX_VERSIONS);
+    if excess == 0 {
+        return;
+    }
+    let first_kept = &existing[excess];
+    if !first_kept.full {
+        let Some(content) = content_at(&existing, excess) else {
+            return;
+        };
+        let full_path = first_kept.path.with_extension(FULL_EXT);
+        if std::fs::write(&full_path, content).is_err() {
+            return;
+        }
+        let _ = std::fs::remove_file(&first_kept.path);
+    }
+    for version in &existing[..excess] {
+        let _ = std::fs::remove_file(&version.path);
+    }
+}
+
+/// Snapshot a save of note `id`: `previous` is what the file held before, if
+/// anything, and `content` what was written. Blocking.
+pub fn snapshot(
+    notes_root: &Path,
+    id: &str,
+    previous: Option<&str>,
+    content: &str,
+) -> Result<(), String> {
+    let dir = note_dir(notes_root, id)?;
+    for version in previous.into_iter().chain(std::iter::once(content)) {
+        push(&dir, version).map_err(|e| e.to_string())?;
+    }
+    prune(&dir);
+    Ok(())
+}
+
+/// Each readable version's size, oldest first, replaying the chain once.
+fn sizes(existing: &[Version]) -> Vec<NoteVersion> {
+    let mut sizes = Vec::with_capacity(existing.len());
+    let mut content: Option<String> = None;
+    for version in existing {
+        let Ok(stored) = std::fs::read_to_string(&version.path) else {
+            content = None;
+            continue;
+        };
+        content = if version.full {
+            Some(stored)
+        } else {
+            content.and_then(|content| patch(&content, &stored))
+        };
+        if let Some(ref content) = content {
+            sizes.push(NoteVersion {
+                ts: version.ts,
+                size: content.len() as u64,
+            });
+        }
+    }
+    sizes
+}
+
+/// Versions of note `id`, newest first.
+pub fn list(notes_root: &Path, id: &str) -> Result<Vec<NoteVersion>, String> {
+    let mut list = sizes(&versions(&note_dir(notes_root, id)?));
+    list.reverse();
+    Ok(list)
+}
+
+/// Content of note `id` as of version `ts`.
+pub fn read(notes_root: &Path, id: &str, ts: i64) -> Result<String, String> {
+    let existing = versions(&note_dir(notes_root, id)?);
+    existing
+        .iter()
+        .position(|v| v.ts == ts)
+        .and_then(|index| content_at(&existing, index))
+        .ok_or_else(|| "Version not found".to_string())
+}
+
+/// Carry the history of `old_id` over to `new_id` after a rename or move.
+/// If `new_id` already has history (from an earlier note of that name), it
+/// is replaced, since the two chains can't be interleaved.
+pub fn rename(notes_root: &Path, old_id: &str, new_id: &str) -> Result<(), String> {
+    let old_dir = note_dir(notes_root, old_id)?;
+    let new_dir = note_dir(notes_root, new_id)?;
+    let old_versions = versions(&old_dir);
+    if old_versions.is_empty() || old_dir == new_dir {
+        return Ok(());
+    }
+    for version in versions(&new_dir) {
+        let _ = std::fs::remove_file(&version.path);
+    }
+    std::fs::create_dir_all(&new_dir).map_err(|e| e.to_string())?;
+    for version in old_versions {
+        if let Some(name) = version.path.file_name() {
+            let _ = std::fs::rename(&version.path, new_dir.join(name));
+        }
+    }
+    // Only goes if nothing else (like a subfolder's history) is left in it
+    let _ = std::fs::remove_dir(&old_dir);
+    Ok(())
+}
+
+/// Carry the history of the notes in folder `old_path` over to `new_path`.
+/// Only subfolders move: files directly in `old_path` are the versions of a
+/// note with the folder's name, which stays put.
+pub fn rename_folder(notes_root: &Path, old_path: &str, new_path: &str) -> Result<(), String> {
+    let old_dir = note_dir(notes_root, old_path)?;
+    let new_dir = note_dir(notes_root, new_path)?;
+    let Ok(entries) = std::fs::read_dir(&old_dir) else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&new_dir).map_err(|e| e.to_string())?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            let _ = std::fs::rename(&path, new_dir.join(entry.file_name()));
+        }
+    }
+    let _ = std::fs::remove_dir(&old_dir);
+    Ok(())
+}
+
+/// Disk used by every note's history and by crash-recovery drafts.
+/// Blocking; reads the whole history.
+pub fn storage_stats(notes_root: &Path) -> HistoryStats {
+    let mut stats = HistoryStats::default();
+    let dirs = walkdir::WalkDir::new(notes_root.join(HISTORY_DIR))
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_dir());
+    for dir in dirs {
+        let existing = versions(dir.path());
+        if existing.is_empty() {
+            continue;
+        }
+        stats.notes += 1;
+        stats.versions += existing.len();
+        stats.full_versions += existing.iter().filter(|v| v.full).count();
+        stats.stored_bytes += existing
+            .iter()
+            .filter_map(|v| std::fs::metadata(&v.path).ok())
+            .map(|m| m.len())
+            .sum::<u64>();
+        stats.content_bytes += sizes(&existing).iter().map(|v| v.size).sum::<u64>();
+    }
+    (stats.drafts, stats.draft_bytes) = recovery::draft_usage(notes_root);
+    stats
+}
@@ -0,0 +1,421 @@
+//! Shared import framework used by every format importer.
+//!
+//! An importer only converts its source into `ImportItem`s (a desired note ID
+//! plus markdown content, and optionally attachments and a modification time
+//! to keep). `plan` resolves each item against the vault under a
+//! collision policy, which is exactly what a dry run reports; `execute` writes
+//! the plan, emits `import-progress` events as it goes and returns the final
+//! `ImportReport`. `run` first drops what the import limits (`importlimits.rs`)
+//! leave out, reporting it as warnings.
+
+use crate::importlimits::{ImportLimits, ImportWarning};
+use crate::{
+    abs_path_from_id, audit, extract_title, recovery, snapshot_history_blocking, AppState,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// What to do when an imported note's ID is already taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionPolicy {
+    Skip,
+    #[default]
+    Rename,
+    Overwrite,
+}
+
+// Options accepted by every import command
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub collision: CollisionPolicy,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub target_folder: Option<String>,
+}
+
+/// A note produced by an importer, before collision handling.
+pub struct ImportItem {
+    pub source: String, // shown in progress and reports (file path, page name, ...)
+    pub id: String,     // desired note ID
+    pub content: String,
+    pub assets: Vec<ImportAsset>, // written with the note, and skipped with it
+    pub modified: Option<i64>,    // unix seconds to keep as the file's modification time
+}
+
+/// An attachment that comes with an imported note.
+pub struct ImportAsset {
+    pub path: String, // vault-relative, already free when the importer chose it
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportAction {
+    Create,
+    Rename,
+    Overwrite,
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedNote {
+    pub source: String,
+    pub requested_id: String,
+    pub id: String,
+    pub action: ImportAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFailure {
+    pub source: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub current: usize,
+    pub total: usize,
+    pub source: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub notes: Vec<PlannedNote>,
+    pub created: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    pub failed: Vec<ImportFailure>,
+    pub warnings: Vec<ImportWarning>, // files left out by the import limits, vault size
+}
+
+/// What an entry writes once planned.
+struct Pending {
+    content: String,
+    assets: Vec<ImportAsset>,
+    modified: Option<i64>,
+}
+
+/// A resolved import, ready to preview or execute.
+pub struct ImportPlan {
+    entries: Vec<(PlannedNote, Pending)>,
+    failed: Vec<ImportFailure>,
+}
+
+impl ImportPlan {
+    fn report(&self, dry_run: bool) -> ImportReport {
+        let notes: Vec<PlannedNote> = self.entries.iter().map(|(note, _)| note.clone()).collect();
+        let count = |action: ImportAction| notes.iter().filter(|n| n.action == action).count();
+        ImportReport {
+            dry_run,
+            created: count(ImportAction::Create) + count(ImportAction::Rename),
+            overwritten: count(ImportAction::Overwrite),
+            skipped: count(ImportAction::Skip),
+            failed: self.failed.clone(),
+            warnings: Vec::new(),
+            notes,
+        }
+    }
+}
+
+/// Prefix an importer's note ID with the chosen target folder.
+pub fn in_target_folder(options: &ImportOptions, id: &str) -> String {
+    match options.target_folder.as_deref().map(|f| f.trim_matches('/')) {
+        Some(folder) if !folder.is_empty() => format!("{}/{}", folder, id),
+        _ => id.to_string(),
+    }
+}
+
+/// A vault-relative path for attachment `name` in `folder`, free on disk and
+/// not claimed by an earlier attachment of the same import, appending `-1`,
+/// `-2`, ... to the stem on collision. Claims it.
+pub fn claim_asset_path(
+    notes_root: &Path,
+    claimed: &mut HashSet<String>,
+    folder: &str,
+    name: &str,
+) -> String {
+    let (stem, ext) = match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], &name[pos..]),
+        _ => (name, ""),
+    };
+    let free =
+        |path: &str| !claimed.contains(&path.to_lowercase()) && !notes_root.join(path).exists();
+    let mut path = crate::assets::join(folder, name);
+    let mut counter = 1;
+    while !free(&path) {
+        path = crate::assets::join(folder, &format!("{}-{}{}", stem, counter, ext));
+        counter += 1;
+    }
+    claimed.insert(path.to_lowercase());
+    path
+}
+
+/// Unix seconds as a frontmatter timestamp (RFC 3339, UTC).
+pub fn frontmatter_time(secs: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
+
+/// Resolve every item against the vault. IDs claimed earlier in the same
+/// import count as taken, so two items never land on the same note.
+pub fn plan(notes_root: &Path, items: Vec<ImportItem>, policy: CollisionPolicy) -> ImportPlan {
+    let mut claimed: HashSet<String> = HashSet::new();
+    let mut entries = Vec::new();
+    let mut failed = Vec::new();
+
+    let taken = |id: &str, claimed: &HashSet<String>| {
+        claimed.contains(&id.to_lowercase())
+            || abs_path_from_id(notes_root, id)
+                .map(|p| p.exists())
+                .unwrap_or(false)
+    };
+
+    for item in items {
+        if let Err(error) = abs_path_from_id(notes_root, &item.id) {
+            failed.push(ImportFailure {
+                source: item.source,
+                error,
+            });
+            continue;
+        }
+
+        let (id, action) = if !taken(&item.id, &claimed) {
+            (item.id.clone(), ImportAction::Create)
+        } else {
+            match policy {
+                CollisionPolicy::Skip => (item.id.clone(), ImportAction::Skip),
+                CollisionPolicy::Overwrite if !claimed.contains(&item.id.to_lowercase()) => {
+                    (item.id.clone(), ImportAction::Overwrite)
+                }
+                // Overwriting a note created by this same import would lose data; rename instead
+                CollisionPolicy::Rename | CollisionPolicy::Overwrite => {
+                    let mut counter = 1;
+                    let mut candidate = format!("{}-{}", item.id, counter);
+                    while taken(&candidate, &claimed) {
+                        counter += 1;
+                        candidate = format!("{}-{}", item.id, counter);
+                    }
+                    (candidate, ImportAction::Rename)
+                }
+            }
+        };
+
+        if action != ImportAction::Skip {
+            claimed.insert(id.to_lowercase());
+        }
+        entries.push((
+            PlannedNote {
+                source: item.source,
+                requested_id: item.id,
+                id,
+                action,
+            },
+            Pending {
+                content: item.content,
+                assets: item.assets,
+                modified: item.modified,
+            },
+        ));
+    }
+
+    ImportPlan { entries, failed }
+}
+
+/// Write a note's attachments. Each must not exist yet; an importer picks free
+/// paths, so one appearing since means something else wrote there.
+fn write_assets(notes_root: &Path, assets: &[ImportAsset]) -> Result<(), String> {
+    use std::io::Write;
+    for asset in assets {
+        let path = crate::assets::resolve(notes_root, &asset.path)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&asset.data))
+            .map_err(|e| format!("Failed to write {}: {}", asset.path, e))?;
+    }
+    Ok(())
+}
+
+/// Best effort: keep the source's modification time on an imported note.
+fn set_modified(path: &Path, secs: i64) {
+    let Ok(secs) = u64::try_from(secs) else {
+        return;
+    };
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+    if let Ok(file) = std::fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(time);
+    }
+}
+
+/// Write a plan to disk, indexing each note and emitting `import-progress`.
+/// Blocking; call from spawn_blocking.
+pub fn execute(app: &AppHandle, notes_root: &Path, plan: ImportPlan) -> ImportReport {
+    let mut report = plan.report(false);
+    report.created = 0;
+    report.overwritten = 0;
+
+    let state = app.state::<AppState>();
+    let total = plan.entries.len();
+    for (i, (note, pending)) in plan.entries.into_iter().enumerate() {
+        let content = pending.content;
+        let _ = app.emit(
+            "import-progress",
+            ImportProgress {
+                current: i + 1,
+                total,
+                source: note.source.clone(),
+                id: note.id.clone(),
+            },
+        );
+        if note.action == ImportAction::Skip {
+            continue;
+        }
+
+        // Attachments go first, so a note never points at a missing file
+        let result = write_assets(notes_root, &pending.assets);
+        let result = result
+            .and_then(|_| abs_path_from_id(notes_root, &note.id))
+            .and_then(|path| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                if note.action == ImportAction::Overwrite {
+                    overwrite(app, &state, notes_root, &note.id, &path, &content)?;
+                } else {
+                    // create_new guards against a note appearing since planning
+                    use std::io::Write;
+                    std::fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)
+                        .and_then(|mut file| file.write_all(content.as_bytes()))
+                        .map_err(|e| e.to_string())?;
+                }
+                if let Some(modified) = pending.modified {
+                    set_modified(&path, modified);
+                }
+                Ok(())
+            });
+
+        match result {
+            Ok(()) => {
+                if note.action == ImportAction::Overwrite {
+                    report.overwritten += 1;
+                } else {
+                    report.created += 1;
+                }
+                let modified = pending
+                    .modified
+                    .unwrap_or_else(|| chrono::Utc::now().timestamp());
+                let index = state.search_index.lock().expect("search index mutex");
+                if let Some(ref search_index) = *index {
+                    let _ = search_index.index_note(&note.id, &extract_title(&content), &content, modified);
+                }
+            }
+            Err(error) => report.failed.push(ImportFailure {
+                source: note.source,
+                error,
+            }),
+        }
+    }
+
+    report
+}
+
+/// Replace an existing note the way the app saves one: under its lock,
+/// atomically, audited, with the old content kept in its history.
+fn overwrite(
+    app: &AppHandle,
+    state: &AppState,
+    notes_root: &Path,
+    id: &str,
+    path: &Path,
+    content: &str,
+) -> Result<(), String> {
+    let _guard = state.note_locks.blocking_lock(notes_root, &[id]);
+    let previous = std::fs::read(path).ok();
+    recovery::write_atomic_sync(path, content.as_bytes()).map_err(|e| e.to_string())?;
+    state.audit.record(
+        notes_root,
+        audit::Change::new(audit::Surface::Ui, audit::Action::Save, id)
+            .before(previous.as_deref())
+            .after(Some(content.as_bytes())),
+    );
+    state
+        .notes_cache
+        .write()
+        .expect("cache write lock")
+        .remove(id);
+    let previous = previous.map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    snapshot_history_blocking(app, notes_root, id, previous.as_deref(), content);
+    Ok(())
+}
+
+/// Drop notes and attachments the import limits leave out, with a warning
+/// for each. A note keeps its other attachments when one is dropped.
+fn within_limits(
+    limits: &ImportLimits,
+    items: Vec<ImportItem>,
+    warnings: &mut Vec<ImportWarning>,
+) -> Vec<ImportItem> {
+    let mut kept = Vec::new();
+    for mut item in items {
+        if let Err(warning) = limits.check_size(&item.source, item.content.len() as u64) {
+            warnings.push(warning);
+            continue;
+        }
+        item.assets.retain(|asset| {
+            let name = asset.path.rsplit('/').next().unwrap_or(&asset.path);
+            let source = format!("{} / {}", item.source, name);
+            match limits.check_attachment(&source, name, asset.data.len() as u64) {
+                Ok(()) => true,
+                Err(warning) => {
+                    warnings.push(warning);
+                    false
+                }
+            }
+        });
+        kept.push(item);
+    }
+    kept
+}
+
+/// Plan and, unless this is a dry run, execute an import. Both report what
+/// the import limits left out.
+pub fn run(app: &AppHandle, notes_root: &Path, items: Vec<ImportItem>, options: &ImportOptions) -> ImportReport {
+    let limits = crate::import_limits(&app.state::<AppState>());
+    let mut warnings = Vec::new();
+    let items = within_limits(&limits, items, &mut warnings);
+    let adding: u64 = items
+        .iter()
+        .map(|item| {
+            let assets: usize = item.assets.iter().map(|a| a.data.len()).sum();
+            (item.content.len() + assets) as u64
+        })
+        .sum();
+    warnings.extend(limits.check_vault(notes_root, adding));
+
+    let plan = plan(notes_root, items, options.collision);
+    let mut report = if options.dry_run {
+        plan.report(true)
+    } else {
+        execute(app, notes_root, plan)
+    };
+    report.warnings = warnings;
+    report
+}
@@ -0,0 +1,147 @@
+//! Size and type limits on what importers bring into the vault.
+//!
+//! Every file in the vault is also in every backup, sync and zip export, so
+//! one stray video can cost gigabytes many times over. The `importLimits`
+//! setting caps each imported note or attachment at `maxFileMb` (default
+//! 100 MB) and can restrict attachments to `allowedExtensions` (any by
+//! default). A file over the cap or of another type is left out: the note
+//! importers list it as an `ImportWarning` in their report, and the asset
+//! commands refuse it. Crossing `vaultWarningMb` (default 2 GB) never blocks
+//! an import; it is reported as a warning, or for a single attachment sent
+//! as an `import-warning` event.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const MB: u64 = 1024 * 1024;
+const DEFAULT_MAX_FILE_MB: u64 = 100;
+const DEFAULT_VAULT_WARNING_MB: u64 = 2048;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportLimits {
+    pub max_file_mb: Option<u64>,                // default 100
+    pub allowed_extensions: Option<Vec<String>>, // attachments only; None = any
+    pub vault_warning_mb: Option<u64>,           // default 2048
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ImportWarning {
+    TooLarge {
+        source: String,
+        bytes: u64,
+        limit: u64,
+    },
+    ExtensionNotAllowed {
+        source: String,
+        extension: String,
+    },
+    VaultSize {
+        bytes: u64, // the vault's size once the import is done
+        limit: u64,
+    },
+}
+
+impl std::fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportWarning::TooLarge {
+                source,
+                bytes,
+                limit,
+            } => write!(
+                f,
+                "{} is {} MB, over the {} MB import limit",
+                source,
+                bytes.div_ceil(MB),
+                limit / MB
+            ),
+            ImportWarning::ExtensionNotAllowed { source, extension } => {
+                write!(
+                    f,
+                    "{}: .{} files are not allowed in the vault",
+                    source, extension
+                )
+            }
+            ImportWarning::VaultSize { bytes, limit } => write!(
+                f,
+                "The vault will be {} MB, over the {} MB warning size",
+                bytes.div_ceil(MB),
+                limit / MB
+            ),
+        }
+    }
+}
+
+impl ImportLimits {
+    fn max_file_bytes(&self) -> u64 {
+        self.max_file_mb.unwrap_or(DEFAULT_MAX_FILE_MB).max(1) * MB
+    }
+
+    fn vault_warning_bytes(&self) -> u64 {
+        self.vault_warning_mb.unwrap_or(DEFAULT_VAULT_WARNING_MB) * MB
+    }
+
+    /// Whether a note or attachment of `bytes` may be imported.
+    pub fn check_size(&self, source: &str, bytes: u64) -> Result<(), ImportWarning> {
+        let limit = self.max_file_bytes();
+        if bytes > limit {
+            return Err(ImportWarning::TooLarge {
+                source: source.to_string(),
+                bytes,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether attachment `name` of `bytes` may be imported.
+    pub fn check_attachment(
+        &self,
+        source: &str,
+        name: &str,
+        bytes: u64,
+    ) -> Result<(), ImportWarning> {
+        self.check_size(source, bytes)?;
+        let Some(allowed) = &self.allowed_extensions else {
+            return Ok(());
+        };
+        let extension = Path::new(name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let permitted = allowed.iter().any(|ext| {
+            ext.trim()
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(&extension)
+        });
+        if !permitted {
+            return Err(ImportWarning::ExtensionNotAllowed {
+                source: source.to_string(),
+                extension,
+            });
+        }
+        Ok(())
+    }
+
+    /// A warning if adding `adding` bytes takes the vault past the warning
+    /// size. Walks the vault; blocking.
+    pub fn check_vault(&self, notes_root: &Path, adding: u64) -> Option<ImportWarning> {
+        let limit = self.vault_warning_bytes();
+        let bytes = vault_size(notes_root) + adding;
+        (bytes > limit).then_some(ImportWarning::VaultSize { bytes, limit })
+    }
+}
+
+/// Bytes of every file in the vault, git data aside.
+fn vault_size(notes_root: &Path) -> u64 {
+    walkdir::WalkDir::new(notes_root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || e.file_name() != ".git")
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
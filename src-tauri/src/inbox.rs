@@ -0,0 +1,220 @@
+//! Inbox rules.
+//!
+//! Notes that land in the inbox folder (`inbox` unless the `inbox` setting
+//! names another) are sorted by rules kept in the settings. A rule matches on
+//! the note's title, content and `source`/`url` with case-insensitive regular
+//! expressions (all that are given must match; none matches every note) and
+//! runs its actions in order: add tags, apply a template, move the note to a
+//! folder, or merge it into a target note and trash it. Every matching rule
+//! runs, in the order written, until one moves or merges the note, which ends
+//! its processing. Rules run on captured notes as they are created and on the
+//! whole inbox with `run_inbox_rules`.
+
+use crate::frontmatter::{self, Frontmatter, Value};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_FOLDER: &str = "inbox";
+const CONTENT_PLACEHOLDER: &str = "{{content}}";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxSettings {
+    pub folder: Option<String>, // default "inbox"
+    #[serde(default)]
+    pub rules: Vec<InboxRule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxMatch {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub source: Option<String>, // the `source` or `url` frontmatter
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum InboxAction {
+    Tag { tags: Vec<String> },
+    Template { template: String }, // note ID; `{{content}}` takes the note's body
+    Move { folder: String },
+    Merge { target: String }, // note ID
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxRule {
+    pub name: String,
+    #[serde(rename = "match", default)]
+    pub matches: InboxMatch,
+    pub actions: Vec<InboxAction>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxOutcome {
+    pub id: String,              // as it was in the inbox
+    pub rules: Vec<String>,      // the rules that matched
+    pub note_id: Option<String>, // where the note is now; None once merged
+    pub merged_into: Option<String>,
+    pub error: Option<String>, // the action that failed ends the note's processing
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxRun {
+    pub processed: Vec<InboxOutcome>,
+    pub unmatched: usize,
+}
+
+pub fn folder(settings: &InboxSettings) -> String {
+    match settings
+        .folder
+        .as_deref()
+        .map(|f| f.trim().trim_matches('/'))
+    {
+        Some(f) if !f.is_empty() => f.to_string(),
+        _ => DEFAULT_FOLDER.to_string(),
+    }
+}
+
+/// Whether note `id` is in the inbox folder or a folder under it.
+pub fn contains(folder: &str, id: &str) -> bool {
+    id.strip_prefix(folder)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+pub struct CompiledRule<'a> {
+    rule: &'a InboxRule,
+    title: Option<Regex>,
+    content: Option<Regex>,
+    source: Option<Regex>,
+}
+
+impl CompiledRule<'_> {
+    pub fn name(&self) -> &str {
+        &self.rule.name
+    }
+
+    pub fn actions(&self) -> &[InboxAction] {
+        &self.rule.actions
+    }
+
+    fn matches(&self, title: &str, content: &str) -> bool {
+        let fm = Frontmatter::parse(content);
+        let source = fm.get("source").or_else(|| fm.get("url"));
+        self.title
+            .as_ref()
+            .map(|re| re.is_match(title))
+            .unwrap_or(true)
+            && self
+                .content
+                .as_ref()
+                .map(|re| re.is_match(content))
+                .unwrap_or(true)
+            && match &self.source {
+                Some(re) => source.is_some_and(|s| re.is_match(s)),
+                None => true,
+            }
+    }
+}
+
+fn pattern(rule: &str, pattern: Option<&str>) -> Result<Option<Regex>, String> {
+    pattern
+        .map(|p| {
+            RegexBuilder::new(p)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Inbox rule \"{}\" has an invalid pattern: {}", rule, e))
+        })
+        .transpose()
+}
+
+/// The enabled rules, ready to match. Fails on the first invalid pattern.
+pub fn compile(settings: &InboxSettings) -> Result<Vec<CompiledRule<'_>>, String> {
+    settings
+        .rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .map(|rule| {
+            Ok(CompiledRule {
+                rule,
+                title: pattern(&rule.name, rule.matches.title.as_deref())?,
+                content: pattern(&rule.name, rule.matches.content.as_deref())?,
+                source: pattern(&rule.name, rule.matches.source.as_deref())?,
+            })
+        })
+        .collect()
+}
+
+/// The rules that match a note, in order.
+pub fn matching<'r, 'a>(
+    rules: &'r [CompiledRule<'a>],
+    title: &str,
+    content: &str,
+) -> Vec<&'r CompiledRule<'a>> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(title, content))
+        .collect()
+}
+
+/// Add tags to the note's `tags` frontmatter, skipping ones it has.
+pub fn add_tags(content: &str, tags: &[String]) -> String {
+    let mut list = Frontmatter::parse(content).get_list("tags");
+    let before = list.len();
+    for tag in tags.iter().map(|t| t.trim().trim_start_matches('#')) {
+        if !tag.is_empty() && !list.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            list.push(tag.to_string());
+        }
+    }
+    if list.len() == before {
+        return content.to_string();
+    }
+    frontmatter::set(content, "tags", Value::List(list))
+}
+
+/// Rewrite a note from an expanded template: the template's body with the
+/// note's body at `{{content}}` (or after it), and the note's frontmatter
+/// plus the template's keys the note does not set.
+pub fn apply_template(template: &str, content: &str) -> String {
+    let body = frontmatter::body(content);
+    let frontmatter_block = &content[..content.len() - body.len()];
+    let template_body = frontmatter::body(template);
+    let body = if template_body.contains(CONTENT_PLACEHOLDER) {
+        template_body.replace(CONTENT_PLACEHOLDER, body.trim())
+    } else {
+        format!("{}\n\n{}", template_body.trim_end(), body.trim_start())
+    };
+    let mut out = format!("{}{}", frontmatter_block, body);
+    let note_keys = Frontmatter::parse(content);
+    for (key, value) in Frontmatter::parse(template).entries() {
+        if !note_keys.entries().iter().any(|(k, _)| k == key) {
+            out = frontmatter::set(&out, key, value.clone());
+        }
+    }
+    out
+}
+
+/// Append a note to a target note as a section: its heading one level down
+/// and its source, if it has one, under the body.
+pub fn merge(target: &str, content: &str) -> String {
+    let fm = Frontmatter::parse(content);
+    let source = fm.get("source").or_else(|| fm.get("url"));
+    let body = frontmatter::body(content).trim();
+    let mut section = match body.strip_prefix("# ") {
+        Some(rest) => format!("## {}", rest),
+        None => body.to_string(),
+    };
+    if let Some(source) = source {
+        section.push_str(&format!("\n\nSource: {}", source));
+    }
+    format!("{}\n\n{}\n", target.trim_end(), section)
+}
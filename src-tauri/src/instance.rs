@@ -0,0 +1,211 @@
+//! Vault lock shared between app instances.
+//!
+//! The running instance keeps `.scratch/instance.lock` (host name, PID, start
+//! time and a heartbeat refreshed every 30 seconds) and removes it on a clean
+//! shutdown. A lock whose heartbeat has gone quiet means the previous run
+//! crashed, so recovery runs before taking it over; a live lock held by
+//! another instance is reported with `vault-in-use` and left alone. A lock
+//! written on another host (a synced vault) belongs to a remote instance: it
+//! is never ours whatever its PID, and once stale it is taken over without
+//! recovery, since the crash left nothing behind on this machine. Each
+//! heartbeat re-reads the lock first, and an instance that finds another one
+//! has taken it over stops refreshing it and reports `vault-in-use`.
+
+use crate::{recovery, AppState};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const LOCK_FILE: &str = "instance.lock";
+const HEARTBEAT: Duration = Duration::from_secs(30);
+const STALE_AFTER_SECS: i64 = 120;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockInfo {
+    #[serde(default)]
+    pub hostname: String, // empty in locks from before host names were kept
+    pub pid: u32,
+    pub started_at: i64,
+    pub heartbeat: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceStatus {
+    pub notes_folder: String,
+    pub owned: bool,
+    pub other_instance: Option<LockInfo>, // live instance holding the vault
+    pub remote_instance: bool,            // whether that instance runs on another host
+    pub recovered_from_crash: bool,
+    pub recovery: Option<recovery::RecoveryReport>,
+}
+
+/// This process's identity in lock files.
+fn own_lock() -> LockInfo {
+    static STARTED_AT: OnceLock<i64> = OnceLock::new();
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    LockInfo {
+        hostname: HOSTNAME
+            .get_or_init(|| crate::daily::device_name().unwrap_or_default())
+            .clone(),
+        pid: std::process::id(),
+        started_at: *STARTED_AT.get_or_init(|| chrono::Utc::now().timestamp()),
+        heartbeat: chrono::Utc::now().timestamp(),
+    }
+}
+
+fn is_ours(lock: &LockInfo) -> bool {
+    let own = own_lock();
+    !is_remote(lock) && lock.pid == own.pid && lock.started_at == own.started_at
+}
+
+/// Whether the lock was written on another host. Locks without a host name
+/// count as local.
+fn is_remote(lock: &LockInfo) -> bool {
+    !lock.hostname.is_empty() && lock.hostname != own_lock().hostname
+}
+
+fn lock_path(notes_root: &Path) -> PathBuf {
+    notes_root.join(".scratch").join(LOCK_FILE)
+}
+
+fn read_lock(notes_root: &Path) -> Option<LockInfo> {
+    let content = std::fs::read_to_string(lock_path(notes_root)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_lock(notes_root: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(notes_root.join(".scratch"))?;
+    let content = serde_json::to_string_pretty(&own_lock())?;
+    std::fs::write(lock_path(notes_root), content)
+}
+
+/// Remove the lock if this instance holds it.
+pub fn release(notes_root: &Path) {
+    if read_lock(notes_root).is_some_and(|lock| is_ours(&lock)) {
+        let _ = std::fs::remove_file(lock_path(notes_root));
+    }
+}
+
+/// Try to take the vault lock, recovering first if the last holder crashed.
+/// Blocking.
+fn open(notes_root: &Path) -> InstanceStatus {
+    let mut status = InstanceStatus {
+        notes_folder: notes_root.to_string_lossy().into_owned(),
+        owned: false,
+        other_instance: None,
+        remote_instance: false,
+        recovered_from_crash: false,
+        recovery: None,
+    };
+    match read_lock(notes_root) {
+        Some(lock) if is_ours(&lock) => {}
+        Some(lock) if chrono::Utc::now().timestamp() - lock.heartbeat < STALE_AFTER_SECS => {
+            status.remote_instance = is_remote(&lock);
+            status.other_instance = Some(lock);
+            return status;
+        }
+        Some(lock) if is_remote(&lock) => {}
+        Some(_) => {
+            status.recovered_from_crash = true;
+            status.recovery = Some(recovery::recover(notes_root));
+        }
+        None => {}
+    }
+    status.owned = write_lock(notes_root).is_ok();
+    status
+}
+
+/// What a heartbeat found in the lock file.
+enum Beat {
+    Held,                 // still ours, and refreshed
+    Lost(InstanceStatus), // another instance has taken it over
+    Gone,                 // removed; taken again on the next pass
+}
+
+/// Refresh the lock if this instance still holds it. Blocking.
+fn heartbeat(notes_root: &Path) -> Beat {
+    match read_lock(notes_root) {
+        Some(lock) if is_ours(&lock) => {
+            let _ = write_lock(notes_root);
+            Beat::Held
+        }
+        Some(lock) => Beat::Lost(InstanceStatus {
+            notes_folder: notes_root.to_string_lossy().into_owned(),
+            owned: false,
+            remote_instance: is_remote(&lock),
+            other_instance: Some(lock),
+            recovered_from_crash: false,
+            recovery: None,
+        }),
+        None => Beat::Gone,
+    }
+}
+
+/// Hold the lock for the current notes folder for the life of the app,
+/// following folder changes.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // Folder we last tried to lock, and whether we hold it
+        let mut current: Option<(String, bool)> = None;
+        loop {
+            let state = app.state::<AppState>();
+            if state.shutdown.started() {
+                return;
+            }
+            let folder = state
+                .app_config
+                .read()
+                .expect("app_config read lock")
+                .notes_folder
+                .clone();
+
+            match (current.take(), folder) {
+                (Some((locked, true)), Some(folder)) if locked == folder => {
+                    let root = PathBuf::from(&folder);
+                    let beat = tauri::async_runtime::spawn_blocking(move || heartbeat(&root)).await;
+                    let owned = match beat {
+                        Ok(Beat::Held) | Err(_) => true,
+                        Ok(Beat::Gone) => false,
+                        Ok(Beat::Lost(status)) => {
+                            let _ = app.emit("vault-in-use", &status);
+                            *state.instance.lock().expect("instance status mutex") = Some(status);
+                            false
+                        }
+                    };
+                    current = Some((folder, owned));
+                }
+                (previous, Some(folder)) => {
+                    let retrying = previous.as_ref().is_some_and(|(f, _)| *f == folder);
+                    if let Some((old, true)) = previous {
+                        release(Path::new(&old));
+                    }
+                    let root = PathBuf::from(&folder);
+                    let opened = tauri::async_runtime::spawn_blocking(move || open(&root)).await;
+                    let owned = opened.as_ref().is_ok_and(|status| status.owned);
+                    if let Ok(status) = opened {
+                        if status.other_instance.is_some() && !retrying {
+                            let _ = app.emit("vault-in-use", &status);
+                        }
+                        if status.recovered_from_crash {
+                            let _ = app.emit("vault-recovered", &status);
+                        }
+                        if !retrying || status.owned {
+                            *state.instance.lock().expect("instance status mutex") = Some(status);
+                        }
+                    }
+                    current = Some((folder, owned));
+                }
+                (previous, None) => {
+                    if let Some((old, true)) = previous {
+                        release(Path::new(&old));
+                    }
+                }
+            }
+            tokio::time::sleep(HEARTBEAT).await;
+        }
+    });
+}
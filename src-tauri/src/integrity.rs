@@ -0,0 +1,209 @@
+//! Vault integrity checks.
+//!
+//! `verify_vault` walks the notes folder for files that cannot be read or are
+//! empty, checks `.trash/` entries and the `.scratch/` sidecar stores, and
+//! compares the search index and notes cache against disk. Repair only ever
+//! rebuilds derived state: the index is regenerated, the cache pruned, broken
+//! sidecars are set aside (not deleted) so their loaders start fresh, and
+//! empty trash entries are removed. Note files are never modified.
+
+use crate::{
+    abs_path_from_id, annotations, automation, capabilities, flashcards, id_from_abs_path,
+    is_visible_notes_entry, redirects, routines, share, trash, Settings,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    Unreadable,
+    EmptyNote,
+    MissingFromIndex,
+    StaleIndexEntry,
+    IndexUnavailable,
+    StaleCacheEntry,
+    DanglingTrashEntry,
+    BrokenSidecar,
+    OrphanedAnnotations,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub path: String, // note ID or vault-relative path
+    pub detail: String,
+    pub repaired: bool,
+}
+
+impl Issue {
+    pub fn new(kind: IssueKind, path: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            path: path.into(),
+            detail: detail.into(),
+            repaired: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultReport {
+    pub checked_notes: usize,
+    pub issues: Vec<Issue>,
+    pub repaired: bool,
+}
+
+/// A readable note found on disk.
+pub struct DiskNote {
+    pub id: String,
+    pub content: String,
+}
+
+/// Find notes that cannot be read or are empty. Blocking.
+pub fn check_notes(notes_root: &Path, issues: &mut Vec<Issue>) -> Vec<DiskNote> {
+    use walkdir::WalkDir;
+
+    let mut notes = Vec::new();
+    for entry in WalkDir::new(notes_root)
+        .max_depth(10)
+        .into_iter()
+        .filter_entry(is_visible_notes_entry)
+        .flatten()
+    {
+        let file_path = entry.path();
+        if !file_path.is_file() {
+            continue;
+        }
+        let Some(id) = id_from_abs_path(notes_root, file_path) else {
+            continue;
+        };
+        if entry.metadata().is_ok_and(|m| m.len() == 0) {
+            issues.push(Issue::new(IssueKind::EmptyNote, &id, "Note file is empty"));
+        }
+        let content = match std::fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                issues.push(Issue::new(IssueKind::Unreadable, &id, e.to_string()));
+                continue;
+            }
+        };
+        notes.push(DiskNote { id, content });
+    }
+    notes
+}
+
+/// Trash entries with no readable manifest, or with nothing left to restore.
+/// Repair removes only the latter.
+pub fn check_trash(notes_root: &Path, repair: bool, issues: &mut Vec<Issue>) {
+    let Ok(entries) = std::fs::read_dir(notes_root.join(trash::TRASH_DIR)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let rel = format!("{}/{}", trash::TRASH_DIR, entry.file_name().to_string_lossy());
+        if !path.is_dir() {
+            issues.push(Issue::new(IssueKind::DanglingTrashEntry, rel, "Stray file in trash"));
+            continue;
+        }
+        let Some(manifest) = trash::read_manifest(&path) else {
+            issues.push(Issue::new(
+                IssueKind::DanglingTrashEntry,
+                rel,
+                "Missing or unreadable manifest",
+            ));
+            continue;
+        };
+        let holds_files = trash::entry_files(&path).next().is_some();
+        if !holds_files {
+            let mut issue = Issue::new(
+                IssueKind::DanglingTrashEntry,
+                rel,
+                format!("Nothing left to restore for {}", manifest.note_id),
+            );
+            issue.repaired = repair && std::fs::remove_dir_all(&path).is_ok();
+            issues.push(issue);
+        }
+    }
+}
+
+fn parses<T: DeserializeOwned>(content: &str) -> bool {
+    serde_json::from_str::<T>(content).is_ok()
+}
+
+/// A sidecar's file name in `.scratch/` and whether content parses as it.
+type Sidecar = (&'static str, fn(&str) -> bool);
+
+/// Sidecar stores in `.scratch/` that exist but no longer parse. Their loaders
+/// fall back to defaults, so the next save would silently drop the old data;
+/// repair moves them aside instead.
+pub fn check_sidecars(notes_root: &Path, repair: bool, issues: &mut Vec<Issue>) {
+    let sidecars: &[Sidecar] = &[
+        ("settings.json", parses::<Settings>),
+        ("annotations.json", parses::<annotations::AnnotationStore>),
+        ("flashcards.json", parses::<flashcards::FlashcardStore>),
+        ("shares.json", parses::<share::ShareStore>),
+        ("automation.json", parses::<automation::RunStore>),
+        ("tokens.json", parses::<capabilities::TokenStore>),
+        ("routines.json", parses::<routines::RoutineStore>),
+        ("redirects.json", parses::<redirects::RedirectStore>),
+    ];
+    let scratch_dir = notes_root.join(".scratch");
+    for &(name, valid) in sidecars {
+        let path = scratch_dir.join(name);
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        let detail = match String::from_utf8(bytes) {
+            Ok(content) if valid(&content) => continue,
+            Ok(_) => "Could not parse store",
+            Err(_) => "Store is not valid UTF-8",
+        };
+        let mut issue = Issue::new(IssueKind::BrokenSidecar, format!(".scratch/{}", name), detail);
+        if repair {
+            let aside = scratch_dir.join(format!(
+                "{}.broken-{}",
+                name,
+                chrono::Utc::now().timestamp()
+            ));
+            issue.repaired = std::fs::rename(&path, aside).is_ok();
+        }
+        issues.push(issue);
+    }
+}
+
+/// Annotations kept for notes that no longer exist. Skipped when the store
+/// itself is broken, which `check_sidecars` reports.
+pub fn check_annotations(notes_folder: &str, repair: bool, issues: &mut Vec<Issue>) {
+    let root = Path::new(notes_folder);
+    let path = root.join(".scratch").join("annotations.json");
+    let Some(mut store) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<annotations::AnnotationStore>(&content).ok())
+    else {
+        return;
+    };
+    let orphaned: Vec<String> = store
+        .notes
+        .keys()
+        .filter(|id| !abs_path_from_id(root, id).is_ok_and(|p| p.exists()))
+        .cloned()
+        .collect();
+    if orphaned.is_empty() {
+        return;
+    }
+    for id in &orphaned {
+        if repair {
+            store.remove_note(id);
+        }
+    }
+    let saved = repair && store.save(notes_folder).is_ok();
+    for id in orphaned {
+        let mut issue = Issue::new(IssueKind::OrphanedAnnotations, id, "Annotations for a missing note");
+        issue.repaired = saved;
+        issues.push(issue);
+    }
+}
@@ -0,0 +1,381 @@
+//! Joplin import, from a JEX archive or a RAW export folder.
+//!
+//! Both hold one `<id>.md` file per item (note, notebook, resource, tag or
+//! note-tag link): the title, a blank line, the body, a blank line, then
+//! `key: value` properties ending in `type_`. Resource files sit in
+//! `resources/<id>.<ext>`; a JEX file is the same layout in a tar archive.
+//!
+//! Notebooks become folders (nested as in Joplin) under the target folder.
+//! Each note keeps its tags, its creation and update times and its source URL
+//! in the frontmatter, and the update time as the file's modification time.
+//! `:/<id>` references become links to the resource, written to the
+//! attachment folder of the first note that uses it, or wikilinks to the
+//! note. HTML notes are converted to markdown. Encrypted items cannot be
+//! read and are reported as failures.
+
+use crate::{attachment_folder, clipboard, frontmatter, import, sanitize_filename, AppState};
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const TYPE_NOTE: &str = "1";
+const TYPE_FOLDER: &str = "2";
+const TYPE_RESOURCE: &str = "4";
+const TYPE_TAG: &str = "5";
+const TYPE_NOTE_TAG: &str = "6";
+const MAX_FOLDER_DEPTH: usize = 32; // guards against parent cycles
+const HTML_MARKUP: &str = "2";
+
+struct Item {
+    title: String,
+    body: String,
+    props: HashMap<String, String>,
+}
+
+impl Item {
+    fn prop(&self, key: &str) -> Option<&str> {
+        self.props
+            .get(key)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn time(&self, key: &str) -> Option<i64> {
+        let value = self
+            .prop(&format!("user_{}", key))
+            .or_else(|| self.prop(key))?;
+        chrono::DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|t| t.timestamp())
+    }
+}
+
+fn re(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+/// Split an item file into title, body and the trailing properties.
+fn parse_item(text: &str) -> Option<Item> {
+    static PROP: OnceLock<Regex> = OnceLock::new();
+    let prop = re(&PROP, r"^([a-z_]+): ?(.*)$");
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut end = lines.len();
+    while end > 0 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    let mut start = end;
+    while start > 0 && prop.is_match(lines[start - 1]) {
+        start -= 1;
+    }
+    let props: HashMap<String, String> = lines[start..end]
+        .iter()
+        .filter_map(|line| prop.captures(line))
+        .map(|cap| (cap[1].to_string(), cap[2].to_string()))
+        .collect();
+    if !props.contains_key("type_") || !props.contains_key("id") {
+        return None;
+    }
+
+    let head = lines[..start].join("\n");
+    let head = head.trim_end();
+    let (title, body) = head.split_once('\n').unwrap_or((head, ""));
+    Some(Item {
+        title: title.trim().to_string(),
+        body: body.trim_matches('\n').to_string(),
+        props,
+    })
+}
+
+/// The export's files by relative path (`<id>.md`, `resources/<id>.<ext>`).
+/// Blocking.
+fn read_export(path: &Path) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut files = BTreeMap::new();
+    if path.is_dir() {
+        let entries = walkdir::WalkDir::new(path)
+            .max_depth(2)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file());
+        for entry in entries {
+            let Some(rel) = entry
+                .path()
+                .strip_prefix(path)
+                .ok()
+                .and_then(|p| p.to_str())
+            else {
+                continue;
+            };
+            let rel = rel.replace(std::path::MAIN_SEPARATOR, "/");
+            let data = std::fs::read(entry.path())
+                .map_err(|e| format!("Failed to read {}: {}", rel, e))?;
+            files.insert(rel, data);
+        }
+        return Ok(files);
+    }
+
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = tar::Archive::new(file);
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Not a Joplin export: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Not a Joplin export: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let rel = entry
+            .path()
+            .map_err(|e| e.to_string())?
+            .to_string_lossy()
+            .trim_start_matches("./")
+            .to_string();
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {}: {}", rel, e))?;
+        files.insert(rel, data);
+    }
+    Ok(files)
+}
+
+/// Notes in a JEX archive or RAW export folder, without importing anything.
+/// Blocking.
+pub fn count_notes(path: &Path) -> Option<usize> {
+    let files = read_export(path).ok()?;
+    let count = files
+        .iter()
+        .filter(|(rel, _)| !rel.contains('/') && rel.ends_with(".md"))
+        .filter_map(|(_, data)| parse_item(&String::from_utf8_lossy(data)))
+        .filter(|item| item.prop("type_") == Some(TYPE_NOTE))
+        .count();
+    Some(count)
+}
+
+/// Whether a folder looks like a RAW export: item files and a `resources`
+/// folder, or item files alone.
+pub fn is_raw_export(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    let mut items = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_item = name.len() == 35
+            && name.ends_with(".md")
+            && name.as_bytes()[..32].iter().all(u8::is_ascii_hexdigit);
+        if is_item {
+            items += 1;
+        } else if name != "resources" && !name.starts_with('.') {
+            return false;
+        }
+    }
+    items > 0
+}
+
+/// The folder path of notebook `id`, from the root notebook down.
+fn folder_path(folders: &HashMap<String, Item>, id: &str) -> String {
+    let mut parts = Vec::new();
+    let mut current = Some(id);
+    while let Some(id) = current.filter(|_| parts.len() < MAX_FOLDER_DEPTH) {
+        let Some(folder) = folders.get(id) else {
+            break;
+        };
+        parts.push(sanitize_filename(&folder.title));
+        current = folder.prop("parent_id");
+    }
+    parts.reverse();
+    parts.join("/")
+}
+
+/// Convert a Joplin export to import items, writing nothing. Blocking.
+pub fn items(
+    state: &AppState,
+    notes_root: &Path,
+    path: &Path,
+    options: &import::ImportOptions,
+) -> Result<(Vec<import::ImportItem>, Vec<import::ImportFailure>), String> {
+    static REFERENCE: OnceLock<Regex> = OnceLock::new();
+
+    let files = read_export(path)?;
+    let mut notes = Vec::new();
+    let mut folders = HashMap::new();
+    let mut resources = HashMap::new();
+    let mut tags = HashMap::new();
+    let mut note_tags: Vec<(String, String)> = Vec::new();
+    let mut failed = Vec::new();
+
+    for (rel, data) in &files {
+        if rel.contains('/') || !rel.ends_with(".md") {
+            continue;
+        }
+        let Some(item) = parse_item(&String::from_utf8_lossy(data)) else {
+            continue;
+        };
+        if item.prop("encryption_applied") == Some("1") {
+            failed.push(import::ImportFailure {
+                source: rel.clone(),
+                error: "Encrypted in Joplin; turn off encryption before exporting".to_string(),
+            });
+            continue;
+        }
+        let id = item.prop("id").unwrap_or_default().to_string();
+        let kind = item.prop("type_").unwrap_or_default().to_string();
+        match kind.as_str() {
+            TYPE_NOTE => notes.push(item),
+            TYPE_FOLDER => {
+                folders.insert(id, item);
+            }
+            TYPE_RESOURCE => {
+                resources.insert(id, item);
+            }
+            TYPE_TAG => {
+                tags.insert(id, item.title);
+            }
+            TYPE_NOTE_TAG => {
+                if let (Some(note), Some(tag)) = (item.prop("note_id"), item.prop("tag_id")) {
+                    note_tags.push((note.to_string(), tag.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    if notes.is_empty() && failed.is_empty() {
+        return Err("The export contains no notes".to_string());
+    }
+
+    // Every note's ID first, so links between notes can be rewritten
+    let title_of = |note: &Item| {
+        if note.title.is_empty() {
+            "Untitled".to_string()
+        } else {
+            note.title.clone()
+        }
+    };
+    let note_ids: HashMap<String, String> = notes
+        .iter()
+        .map(|note| {
+            let folder = note
+                .prop("parent_id")
+                .map(|parent| folder_path(&folders, parent))
+                .unwrap_or_default();
+            let name = sanitize_filename(&title_of(note));
+            let id = if folder.is_empty() {
+                name
+            } else {
+                format!("{}/{}", folder, name)
+            };
+            let joplin_id = note.prop("id").unwrap_or_default().to_string();
+            (joplin_id, import::in_target_folder(options, &id))
+        })
+        .collect();
+
+    let mut claimed: HashSet<String> = HashSet::new();
+    let mut written: HashMap<String, String> = HashMap::new(); // resource ID -> vault path
+    let mut items = Vec::new();
+    for note in &notes {
+        let title = title_of(note);
+        let joplin_id = note.prop("id").unwrap_or_default();
+        let id = note_ids[joplin_id].clone();
+        let folder = match attachment_folder(state, notes_root, Some(&id)) {
+            Ok(folder) => folder,
+            Err(error) => {
+                failed.push(import::ImportFailure {
+                    source: title,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        let body = match note.prop("markup_language") {
+            Some(HTML_MARKUP) => clipboard::html_to_markdown(&note.body),
+            _ => note.body.clone(),
+        };
+        let mut note_assets = Vec::new();
+        let pattern = r"(!?)\[([^\]]*)\]\(:/([0-9a-fA-F]{32})(?:#[^)]*)?\)";
+        let body = re(&REFERENCE, pattern).replace_all(&body, |cap: &Captures| {
+            let (bang, text, target) = (&cap[1], &cap[2], cap[3].to_ascii_lowercase());
+            if let Some(linked) = note_ids.get(&target) {
+                return match text.trim() {
+                    "" => format!("[[{}]]", linked),
+                    text => format!("[[{}|{}]]", linked, text),
+                };
+            }
+            let Some(resource) = resources.get(&target) else {
+                return cap[0].to_string();
+            };
+            let path = match written.get(&target) {
+                Some(path) => path.clone(),
+                None => {
+                    let extension = resource.prop("file_extension");
+                    let data = match extension {
+                        Some(ext) => files.get(&format!("resources/{}.{}", target, ext)),
+                        None => files.get(&format!("resources/{}", target)),
+                    };
+                    let Some(data) = data else {
+                        failed.push(import::ImportFailure {
+                            source: format!("{} / {}", title, resource.title),
+                            error: "Resource file missing from the export".to_string(),
+                        });
+                        return cap[0].to_string();
+                    };
+                    let name = resource
+                        .prop("filename")
+                        .or(Some(resource.title.as_str()).filter(|t| !t.is_empty()))
+                        .map(|name| sanitize_filename(name.trim_start_matches('.')))
+                        .unwrap_or_else(|| target.clone());
+                    let name = match (Path::new(&name).extension(), extension) {
+                        (None, Some(ext)) => format!("{}.{}", name, ext),
+                        _ => name,
+                    };
+                    let path = import::claim_asset_path(notes_root, &mut claimed, &folder, &name);
+                    note_assets.push(import::ImportAsset {
+                        path: path.clone(),
+                        data: data.clone(),
+                    });
+                    written.insert(target.clone(), path.clone());
+                    path
+                }
+            };
+            let href = urlencoding::encode(&path).replace("%2F", "/");
+            format!("{}[{}]({})", bang, text, href)
+        });
+
+        let mut content = format!("# {}\n\n{}\n", title, body.trim());
+        let note_tag_names: Vec<String> = note_tags
+            .iter()
+            .filter(|(note_id, _)| note_id == joplin_id)
+            .filter_map(|(_, tag_id)| tags.get(tag_id).cloned())
+            .collect();
+        if !note_tag_names.is_empty() {
+            content = frontmatter::set(&content, "tags", frontmatter::Value::List(note_tag_names));
+        }
+        let created = note.time("created_time");
+        let updated = note.time("updated_time");
+        for (key, time) in [("created", created), ("updated", updated)] {
+            if let Some(time) = time.and_then(import::frontmatter_time) {
+                content = frontmatter::set(&content, key, frontmatter::Value::Scalar(time));
+            }
+        }
+        if let Some(url) = note.prop("source_url") {
+            content = frontmatter::set(
+                &content,
+                "source",
+                frontmatter::Value::Scalar(url.to_string()),
+            );
+        }
+
+        items.push(import::ImportItem {
+            source: title,
+            id,
+            content,
+            assets: note_assets,
+            modified: updated.or(created),
+        });
+    }
+
+    Ok((items, failed))
+}
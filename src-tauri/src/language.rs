@@ -0,0 +1,269 @@
+//! Note and vault language detection.
+//!
+//! A note's language is its `lang` frontmatter key when it has one, otherwise
+//! whatlang's guess from the start of its prose (code blocks and frontmatter
+//! left out), kept only when whatlang calls it reliable. Detected languages
+//! are ISO 639-1 codes where one exists ("de"), else ISO 639-3 ("yid").
+//!
+//! The vault's dominant language picks the stemmer and stop-word list of the
+//! search index, unless the `searchLanguage` setting names one ("none" keeps
+//! the plain tokenizer). Notes carry their language on `NoteMetadata` for
+//! filtering and for the editor's spellcheck dictionary.
+
+use crate::{frontmatter, id_from_abs_path, is_visible_notes_entry};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tantivy::tokenizer::{
+    Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, StopWordFilter, TextAnalyzer,
+};
+
+const SAMPLE_CHARS: usize = 1000;
+const MIN_SAMPLE_CHARS: usize = 40; // shorter text gives no reliable guess
+const VAULT_SAMPLE_NOTES: usize = 300;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageShare {
+    pub code: String,
+    pub notes: usize,
+    pub share: f32, // of the notes with a known language
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultLanguages {
+    pub dominant: Option<String>,
+    pub languages: Vec<LanguageShare>, // most notes first
+    pub undetected: usize,
+    pub search_language: Option<String>, // stemmer the search index uses
+}
+
+/// ISO 639-1 code for a whatlang (ISO 639-3) code, where there is one.
+fn iso639_1(code: &'static str) -> &'static str {
+    match code {
+        "afr" => "af",
+        "aka" => "ak",
+        "amh" => "am",
+        "ara" => "ar",
+        "aze" => "az",
+        "bel" => "be",
+        "ben" => "bn",
+        "bul" => "bg",
+        "cat" => "ca",
+        "ces" => "cs",
+        "cmn" => "zh",
+        "dan" => "da",
+        "deu" => "de",
+        "ell" => "el",
+        "eng" => "en",
+        "epo" => "eo",
+        "est" => "et",
+        "fin" => "fi",
+        "fra" => "fr",
+        "guj" => "gu",
+        "heb" => "he",
+        "hin" => "hi",
+        "hrv" => "hr",
+        "hun" => "hu",
+        "hye" => "hy",
+        "ind" => "id",
+        "ita" => "it",
+        "jav" => "jv",
+        "jpn" => "ja",
+        "kan" => "kn",
+        "kat" => "ka",
+        "khm" => "km",
+        "kor" => "ko",
+        "lat" => "la",
+        "lav" => "lv",
+        "lit" => "lt",
+        "mal" => "ml",
+        "mar" => "mr",
+        "mkd" => "mk",
+        "mya" => "my",
+        "nep" => "ne",
+        "nld" => "nl",
+        "nob" => "nb",
+        "ori" => "or",
+        "pan" => "pa",
+        "pes" => "fa",
+        "pol" => "pl",
+        "por" => "pt",
+        "ron" => "ro",
+        "rus" => "ru",
+        "sin" => "si",
+        "slk" => "sk",
+        "slv" => "sl",
+        "sna" => "sn",
+        "spa" => "es",
+        "srp" => "sr",
+        "swe" => "sv",
+        "tam" => "ta",
+        "tel" => "te",
+        "tgl" => "tl",
+        "tha" => "th",
+        "tuk" => "tk",
+        "tur" => "tr",
+        "ukr" => "uk",
+        "urd" => "ur",
+        "uzb" => "uz",
+        "vie" => "vi",
+        "yid" => "yi",
+        "zul" => "zu",
+        other => other,
+    }
+}
+
+/// The first `SAMPLE_CHARS` of a note's prose, without frontmatter or
+/// fenced code.
+fn prose_sample(content: &str) -> String {
+    let mut sample = String::new();
+    let mut in_fence = false;
+    for line in frontmatter::body(content).lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        sample.push_str(trimmed.trim_start_matches(['#', '>', '-', '*', ' ']));
+        sample.push('\n');
+        if sample.len() >= SAMPLE_CHARS {
+            break;
+        }
+    }
+    sample
+}
+
+/// Language of a note: its `lang` frontmatter key, or a reliable guess from
+/// its text.
+pub fn detect(content: &str) -> Option<String> {
+    let meta = frontmatter::Frontmatter::parse(content);
+    if let Some(lang) = meta.get("lang").or_else(|| meta.get("language")) {
+        return Some(lang.trim().to_string());
+    }
+    let sample = prose_sample(content);
+    if sample.chars().filter(|c| c.is_alphabetic()).count() < MIN_SAMPLE_CHARS {
+        return None;
+    }
+    let info = whatlang::detect(&sample)?;
+    info.is_reliable()
+        .then(|| iso639_1(info.lang().code()).to_string())
+}
+
+/// Primary subtag of a language code: "pt" for "pt-BR".
+fn primary(code: &str) -> String {
+    code.split(['-', '_'])
+        .next()
+        .unwrap_or(code)
+        .to_ascii_lowercase()
+}
+
+/// The stemmer tantivy has for `code`, if any.
+pub fn tantivy_language(code: &str) -> Option<Language> {
+    Some(match primary(code).as_str() {
+        "ar" => Language::Arabic,
+        "da" => Language::Danish,
+        "de" => Language::German,
+        "el" => Language::Greek,
+        "en" => Language::English,
+        "es" => Language::Spanish,
+        "fi" => Language::Finnish,
+        "fr" => Language::French,
+        "hu" => Language::Hungarian,
+        "it" => Language::Italian,
+        "nb" | "nn" | "no" => Language::Norwegian,
+        "nl" => Language::Dutch,
+        "pt" => Language::Portuguese,
+        "ro" => Language::Romanian,
+        "ru" => Language::Russian,
+        "sv" => Language::Swedish,
+        "ta" => Language::Tamil,
+        "tr" => Language::Turkish,
+        _ => return None,
+    })
+}
+
+/// Name the analyzer for `code` is registered under.
+pub fn analyzer_name(code: &str) -> String {
+    format!("scratch_{}", primary(code))
+}
+
+/// The default tokenizer followed by stop-word removal (where tantivy has a
+/// list for the language) and stemming.
+pub fn analyzer(language: Language) -> TextAnalyzer {
+    let base = || {
+        TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+    };
+    match StopWordFilter::new(language) {
+        Some(stop_words) => base()
+            .filter(stop_words)
+            .filter(Stemmer::new(language))
+            .build(),
+        None => base().filter(Stemmer::new(language)).build(),
+    }
+}
+
+/// Language counts over `languages`, one entry per note.
+pub fn summarize(
+    languages: impl IntoIterator<Item = Option<String>>,
+    search_language: Option<String>,
+) -> VaultLanguages {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut undetected = 0;
+    for language in languages {
+        match language {
+            Some(code) => *counts.entry(code).or_default() += 1,
+            None => undetected += 1,
+        }
+    }
+    let known: usize = counts.values().sum();
+    let mut languages: Vec<LanguageShare> = counts
+        .into_iter()
+        .map(|(code, notes)| LanguageShare {
+            code,
+            notes,
+            share: notes as f32 / known as f32,
+        })
+        .collect();
+    languages.sort_by(|a, b| b.notes.cmp(&a.notes).then_with(|| a.code.cmp(&b.code)));
+    VaultLanguages {
+        dominant: languages.first().map(|l| l.code.clone()),
+        languages,
+        undetected,
+        search_language,
+    }
+}
+
+/// Language the search index should stem for: the `searchLanguage` setting,
+/// or the dominant language of up to `VAULT_SAMPLE_NOTES` notes spread over
+/// the vault. None when it has no stemmer. Blocking.
+pub fn search_language(setting: Option<&str>, notes_root: &Path) -> Option<String> {
+    let code = match setting.map(str::trim).filter(|s| !s.is_empty()) {
+        Some("none") => return None,
+        Some(code) if code != "auto" => code.to_string(),
+        _ => {
+            let paths: Vec<PathBuf> = walkdir::WalkDir::new(notes_root)
+                .max_depth(10)
+                .into_iter()
+                .filter_entry(is_visible_notes_entry)
+                .flatten()
+                .map(|entry| entry.into_path())
+                .filter(|path| path.is_file() && id_from_abs_path(notes_root, path).is_some())
+                .collect();
+            let step = paths.len().div_ceil(VAULT_SAMPLE_NOTES).max(1);
+            let sample = paths
+                .iter()
+                .step_by(step)
+                .filter_map(|path| std::fs::read_to_string(path).ok())
+                .map(|content| detect(&content));
+            summarize(sample, None).dominant?
+        }
+    };
+    tantivy_language(&code).map(|_| primary(&code))
+}
@@ -2,21 +2,101 @@ use anyhow::Result;
 use base64::Engine;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
-use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use tantivy::collector::{DocSetCollector, TopDocs};
+use tantivy::query::{AllQuery, QueryParser};
 use tantivy::schema::*;
 use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy};
 use tauri::{AppHandle, Emitter, Manager, State, WebviewUrl};
 use tauri::webview::WebviewWindowBuilder;
 use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_deep_link::DeepLinkExt;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
+mod anki;
+mod annotations;
+mod archive;
+mod assets;
+mod audit;
+mod autosave;
+mod automation;
+mod backlinks;
+mod backup;
+mod benchmark;
+mod capabilities;
+mod clipboard;
+mod codemarks;
+mod collation;
+mod daily;
+mod dashboard;
+mod dates;
+mod collab;
+mod convert;
+mod disk;
+mod enex;
+mod error;
+mod export;
+mod filenames;
+mod flashcards;
+mod frontmatter;
 mod git;
+mod goals;
+mod habits;
+mod history;
+mod import;
+mod importlimits;
+mod inbox;
+mod instance;
+mod integrity;
+mod joplin;
+mod language;
+mod links;
+mod locations;
+mod locks;
+mod logs;
+mod memory;
+mod metadata;
+mod migration;
+mod passphrase;
+mod policies;
+mod reading;
+mod readlimits;
+mod recovery;
+mod redirects;
+mod references;
+mod render;
+mod report;
+mod restore;
+mod retention;
+mod routines;
+mod runner;
+mod safemode;
+mod scaffold;
+mod schemas;
+mod screenshot;
+mod serve;
+mod share;
+mod shutdown;
+mod sidecars;
+mod slides;
+mod sources;
+mod stars;
+mod store;
+mod tags;
+mod tasks;
+mod templates;
+mod translate;
+mod trash;
+mod tts;
+mod vaultconfig;
+mod vaults;
+
+use error::ScratchError;
 
 // Note metadata for list display
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +105,40 @@ pub struct NoteMetadata {
     pub title: String,
     pub preview: String,
     pub modified: i64,
+    #[serde(default)]
+    pub created: i64, // `created:` frontmatter, else the file's creation time, else `modified`
+    #[serde(default)]
+    pub tags: Vec<String>, // empty for locked notes
+    #[serde(default)]
+    pub language: Option<String>, // None for locked notes and when undetected
+    #[serde(default)]
+    pub pinned: bool, // in pinnedNoteIds; set by listing_metadata
+}
+
+// Order for list_notes; pinned notes always come first
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteSort {
+    #[default]
+    Modified, // newest first
+    Title,
+    Created, // newest first
+    Size,    // largest first
+}
+
+// One page of list_notes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotePage {
+    pub notes: Vec<NoteMetadata>,
+    pub total: usize, // notes in the whole listing
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +180,7 @@ pub struct ThemeSettings {
     pub mode: String, // "light" | "dark" | "system"
     pub custom_light_colors: Option<ThemeColors>,
     pub custom_dark_colors: Option<ThemeColors>,
+    pub custom_theme: Option<String>, // name of a theme file in {APP_DATA}/themes
 }
 
 impl Default for ThemeSettings {
@@ -74,10 +189,30 @@ impl Default for ThemeSettings {
             mode: "system".to_string(),
             custom_light_colors: None,
             custom_dark_colors: None,
+            custom_theme: None,
         }
     }
 }
 
+// Theme file discovered in {APP_DATA}/themes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeInfo {
+    pub name: String,
+    pub format: String, // "json" | "css"
+}
+
+// Contents of a theme file. JSON themes provide color overrides, CSS themes raw CSS.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeFile {
+    #[serde(default)]
+    pub name: String,
+    pub light_colors: Option<ThemeColors>,
+    pub dark_colors: Option<ThemeColors>,
+    pub css: Option<String>,
+}
+
 // Editor font settings (simplified)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -96,10 +231,39 @@ pub enum TextDirection {
     Rtl,
 }
 
-// App config (stored in app data directory - just the notes folder path)
+// Where the search index lives. `memory` never writes note text to app data and
+// rebuilds the index on each launch.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchIndexMode {
+    #[default]
+    Disk,
+    Memory,
+}
+
+// Exposure policy for notes marked `locked: true` or `encrypted: true` in frontmatter.
+// Per-note `show_title:` / `searchable:` frontmatter flags override these defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LockedNotePolicy {
+    #[serde(default)]
+    pub show_titles: bool, // show real titles in lists and search results
+    #[serde(default)]
+    pub index_content: bool, // allow content into the search index and previews
+}
+
+// App config (stored in app data directory - the notes folder and other
+// machine-specific paths)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
     pub notes_folder: Option<String>,
+    #[serde(default)]
+    pub source_folders: Vec<sources::SourceFolder>, // read-only, indexed alongside the vault
+    #[serde(default)]
+    pub vaults: Vec<vaults::Vault>, // known vaults, the open one included
+    #[serde(default)]
+    pub code_blocks: runner::CodeBlockSettings, // languages run_code_block may run; off by default
+    pub tts_command: Option<String>, // replaces the built-in speech engine; {input}, {output} and {voice} are filled in
 }
 
 // Per-folder settings (stored in .scratch/settings.json within notes folder)
@@ -110,6 +274,8 @@ pub struct Settings {
     pub editor_font: Option<EditorFontSettings>,
     #[serde(rename = "gitEnabled")]
     pub git_enabled: Option<bool>,
+    #[serde(rename = "gitAutoCommit")]
+    pub git_auto_commit: Option<bool>, // commit each note save, rename and delete
     #[serde(rename = "pinnedNoteIds")]
     pub pinned_note_ids: Option<Vec<String>>,
     #[serde(rename = "textDirection")]
@@ -118,6 +284,12 @@ pub struct Settings {
     pub editor_width: Option<String>,
     #[serde(rename = "defaultNoteName")]
     pub default_note_name: Option<String>,
+    #[serde(rename = "filenameScheme")]
+    pub filename_scheme: Option<filenames::FilenameScheme>, // file names of new notes; default follows the title
+    #[serde(rename = "slugStyle")]
+    pub slug_style: Option<filenames::SlugStyle>, // case and separators of title-based file names
+    #[serde(rename = "transliterateFilenames")]
+    pub transliterate_filenames: Option<bool>, // spell title-based file names in ASCII
     #[serde(rename = "interfaceZoom")]
     pub interface_zoom: Option<f32>,
     #[serde(rename = "customEditorWidthPx")]
@@ -126,6 +298,63 @@ pub struct Settings {
     pub ollama_model: Option<String>,
     #[serde(rename = "foldersEnabled")]
     pub folders_enabled: Option<bool>,
+    #[serde(rename = "lockedNotes")]
+    pub locked_notes: Option<LockedNotePolicy>,
+    #[serde(rename = "privateFolders")]
+    pub private_folders: Option<Vec<String>>,
+    #[serde(rename = "privacyPassphraseHash")]
+    pub privacy_passphrase_hash: Option<String>, // see passphrase.rs; never sent to the webview
+    #[serde(rename = "shareEndpoint")]
+    pub share_endpoint: Option<String>,
+    #[serde(rename = "exportProfiles")]
+    pub export_profiles: Option<Vec<export::ExportProfile>>,
+    #[serde(rename = "exportClassStyles")]
+    pub export_class_styles: Option<BTreeMap<String, String>>, // CSS per `cssclasses` class in HTML/PDF exports
+    pub automations: Option<Vec<automation::Automation>>,
+    #[serde(rename = "trashOrphanedAssets")]
+    pub trash_orphaned_assets: Option<bool>, // move assets only the deleted note used into .trash
+    #[serde(rename = "assetLayout")]
+    pub asset_layout: Option<assets::AssetLayout>,
+    #[serde(rename = "diskSpace")]
+    pub disk_space: Option<disk::DiskThresholds>,
+    #[serde(rename = "autosaveIntervalMs")]
+    pub autosave_interval_ms: Option<u64>, // quiet period before an autosave is written
+    #[serde(rename = "searchIndexMode")]
+    pub search_index_mode: Option<SearchIndexMode>,
+    #[serde(rename = "searchLanguage")]
+    pub search_language: Option<String>, // ISO 639-1 code for search stemming; "auto" (default) or "none"
+    #[serde(rename = "collationLocale")]
+    pub collation_locale: Option<String>, // BCP 47 tag for title and tag ordering
+    #[serde(rename = "noteSort")]
+    pub note_sort: Option<NoteSort>, // list_notes order when the caller gives none
+    #[serde(rename = "dailyNotesFolder")]
+    pub daily_notes_folder: Option<String>, // default "Daily"
+    #[serde(rename = "dailyNoteFormat")]
+    pub daily_note_format: Option<String>, // file name under the folder, e.g. "%Y/%m/%Y-%m-%d"; default "%Y-%m-%d"
+    #[serde(rename = "dailyTemplate")]
+    pub daily_template: Option<String>, // note ID of the template new daily notes start from
+    pub habits: Option<Vec<String>>, // daily-note checkboxes tracked by get_habit_data
+    #[serde(rename = "weatherUrl")]
+    pub weather_url: Option<String>, // plain-text provider for {{weather}}, e.g. https://wttr.in/{location}?format=3
+    #[serde(rename = "dailyContext")]
+    pub daily_context: Option<daily::ContextStamp>, // weather, device and location stamped into new daily notes
+    #[serde(rename = "memoryBudgets")]
+    pub memory_budgets: Option<memory::MemoryBudgets>,
+    pub translation: Option<translate::TranslationSettings>, // provider URLs and model; keys are in the keychain
+    pub geocoding: Option<locations::GeocodingSettings>, // looks up `location:` place names; off by default
+    #[serde(rename = "archiveLinks")]
+    pub archive_links: Option<bool>, // archive the `url`/`source` page of notes when saved or clipped
+    pub references: Option<references::ReferenceSettings>, // BibTeX or CSL JSON library for citations
+    #[serde(rename = "watcherIgnore")]
+    pub watcher_ignore: Option<Vec<String>>, // directory names the file watcher skips (`*` wildcards)
+    #[serde(rename = "notePolicies")]
+    pub note_policies: Option<Vec<policies::NotePolicy>>, // folders and tags never exported, synced or indexed
+    #[serde(rename = "readLimits")]
+    pub read_limits: Option<readlimits::ReadLimits>, // bytes of each note read for the note list and the index
+    #[serde(rename = "importLimits")]
+    pub import_limits: Option<importlimits::ImportLimits>, // size and type caps on imported notes and attachments
+    pub inbox: Option<inbox::InboxSettings>, // rules for notes captured into the inbox folder
+    pub retention: Option<retention::RetentionSettings>, // folder rules that archive or trash old notes
 }
 
 // Search result
@@ -153,6 +382,17 @@ pub struct FileWatcherState {
     watcher: RecommendedWatcher,
 }
 
+// Tokenizer the content field of an index was built with
+fn content_tokenizer(schema: &Schema) -> Option<String> {
+    let field = schema.get_field("content").ok()?;
+    match schema.get_field_entry(field).field_type() {
+        FieldType::Str(options) => options
+            .get_indexing_options()
+            .map(|indexing| indexing.tokenizer().to_string()),
+        _ => None,
+    }
+}
+
 // Tantivy search index state
 pub struct SearchIndex {
     index: Index,
@@ -164,22 +404,66 @@ pub struct SearchIndex {
     title_field: Field,
     content_field: Field,
     modified_field: Field,
+    locked_policy: RwLock<LockedNotePolicy>,
+    note_policies: RwLock<Vec<policies::NotePolicy>>,
+    read_limit: RwLock<readlimits::ReadLimit>, // how much of each note is indexed
+    sources: RwLock<Vec<sources::SourceFolder>>,
+    language: Option<String>, // stemming and stop words; None for the plain tokenizer
 }
 
 impl SearchIndex {
-    fn new(index_path: &PathBuf) -> Result<Self> {
+    /// Open the index at `index_path`, or in memory when it is None, stemming
+    /// title and content for `language` (an ISO 639-1 code) if tantivy can.
+    fn new(
+        index_path: Option<&Path>,
+        locked_policy: LockedNotePolicy,
+        language: Option<&str>,
+    ) -> Result<Self> {
+        let stemmer = language.and_then(language::tantivy_language);
+        let tokenizer = match (language, stemmer) {
+            (Some(code), Some(_)) => language::analyzer_name(code),
+            _ => "default".to_string(),
+        };
+        let text = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(&tokenizer)
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+            .set_stored();
+
         // Build schema
         let mut schema_builder = Schema::builder();
         let id_field = schema_builder.add_text_field("id", STRING | STORED);
-        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
-        let content_field = schema_builder.add_text_field("content", TEXT | STORED);
+        let title_field = schema_builder.add_text_field("title", text.clone());
+        let content_field = schema_builder.add_text_field("content", text);
         let modified_field = schema_builder.add_i64_field("modified", INDEXED | STORED);
         let schema = schema_builder.build();
 
         // Create or open index
-        std::fs::create_dir_all(index_path)?;
-        let index = Index::create_in_dir(index_path, schema.clone())
-            .or_else(|_| Index::open_in_dir(index_path))?;
+        let index = match index_path {
+            Some(index_path) => {
+                std::fs::create_dir_all(index_path)?;
+                let index = Index::create_in_dir(index_path, schema.clone())
+                    .or_else(|_| Index::open_in_dir(index_path))?;
+                // An index built for another language is started over; the
+                // caller rebuilds it anyway
+                if content_tokenizer(&index.schema()).as_deref() != Some(tokenizer.as_str()) {
+                    drop(index);
+                    std::fs::remove_dir_all(index_path)?;
+                    std::fs::create_dir_all(index_path)?;
+                    Index::create_in_dir(index_path, schema.clone())?
+                } else {
+                    index
+                }
+            }
+            None => Index::create_in_ram(schema.clone()),
+        };
+        if let Some(stemmer) = stemmer {
+            index
+                .tokenizers()
+                .register(&tokenizer, language::analyzer(stemmer));
+        }
 
         let reader = index
             .reader_builder()
@@ -197,9 +481,67 @@ impl SearchIndex {
             title_field,
             content_field,
             modified_field,
+            locked_policy: RwLock::new(locked_policy),
+            note_policies: RwLock::new(Vec::new()),
+            read_limit: RwLock::new(readlimits::ReadLimits::default().index()),
+            sources: RwLock::new(Vec::new()),
+            language: stemmer.and(language).map(str::to_string),
         })
     }
 
+    fn set_locked_policy(&self, policy: LockedNotePolicy) {
+        *self.locked_policy.write().expect("locked policy write lock") = policy;
+    }
+
+    /// Folder and tag policies; notes they keep out of the index are left out.
+    fn set_note_policies(&self, note_policies: Vec<policies::NotePolicy>) {
+        *self.note_policies.write().expect("note policies lock") = note_policies;
+    }
+
+    /// Bytes of each note that enter the index.
+    fn set_read_limit(&self, read_limit: readlimits::ReadLimit) {
+        *self.read_limit.write().expect("read limit lock") = read_limit;
+    }
+
+    fn read_limit(&self) -> readlimits::ReadLimit {
+        *self.read_limit.read().expect("read limit lock")
+    }
+
+    /// Source folders indexed by `rebuild_index` alongside the vault.
+    fn set_sources(&self, sources: Vec<sources::SourceFolder>) {
+        *self.sources.write().expect("sources write lock") = sources;
+    }
+
+    /// Apply the note policies and the locked-note policy: returns the title
+    /// and content that may be indexed, cut to the read limit, or None if
+    /// nothing about the note may enter the index.
+    fn indexable<'a>(
+        &self,
+        id: &str,
+        title: &'a str,
+        content: &'a str,
+    ) -> Option<(&'a str, &'a str)> {
+        {
+            // Attachments fall under the policies of the folder they are in
+            let path = sidecars::path_from_id(id).unwrap_or(id);
+            let note_policies = self.note_policies.read().expect("note policies read lock");
+            if policies::forbids(&note_policies, policies::Restriction::Index, path, content) {
+                return None;
+            }
+        }
+        let policy = self.locked_policy.read().expect("locked policy read lock");
+        let exposure = note_exposure(content, &policy);
+        let indexed = self.read_limit().truncate(content);
+        if !exposure.locked {
+            return Some((title, indexed));
+        }
+        match (exposure.show_title, exposure.index_content) {
+            (_, true) => Some((title, indexed)),
+            (true, false) => Some((title, "")),
+            (false, false) => None,
+        }
+    }
+
     fn index_note(&self, id: &str, title: &str, content: &str, modified: i64) -> Result<()> {
         let mut writer = self.writer.lock().expect("search writer mutex");
 
@@ -207,18 +549,26 @@ impl SearchIndex {
         let id_term = tantivy::Term::from_field_text(self.id_field, id);
         writer.delete_term(id_term);
 
-        // Add new document
-        writer.add_document(doc!(
-            self.id_field => id,
-            self.title_field => title,
-            self.content_field => content,
-            self.modified_field => modified,
-        ))?;
+        // Add new document (locked notes may be excluded entirely)
+        if let Some((title, content)) = self.indexable(id, title, content) {
+            writer.add_document(doc!(
+                self.id_field => id,
+                self.title_field => title,
+                self.content_field => content,
+                self.modified_field => modified,
+            ))?;
+        }
 
         writer.commit()?;
         Ok(())
     }
 
+    /// Commit anything still buffered in the writer.
+    fn commit(&self) -> Result<()> {
+        self.writer.lock().expect("search writer mutex").commit()?;
+        Ok(())
+    }
+
     fn delete_note(&self, id: &str) -> Result<()> {
         let mut writer = self.writer.lock().expect("search writer mutex");
         let id_term = tantivy::Term::from_field_text(self.id_field, id);
@@ -279,9 +629,24 @@ impl SearchIndex {
         Ok(results)
     }
 
+    /// IDs of every note currently in the index.
+    fn indexed_ids(&self) -> Result<HashSet<String>> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let mut ids = HashSet::new();
+        for doc_address in searcher.search(&AllQuery, &DocSetCollector)? {
+            let doc: TantivyDocument = searcher.doc(doc_address)?;
+            if let Some(id) = doc.get_first(self.id_field).and_then(|v| v.as_str()) {
+                ids.insert(id.to_string());
+            }
+        }
+        Ok(ids)
+    }
+
     fn rebuild_index(&self, notes_folder: &PathBuf) -> Result<()> {
         let mut writer = self.writer.lock().expect("search writer mutex");
         writer.delete_all_documents()?;
+        let read_limit = self.read_limit();
 
         if notes_folder.exists() {
             use walkdir::WalkDir;
@@ -296,7 +661,7 @@ impl SearchIndex {
                     continue;
                 }
                 if let Some(id) = id_from_abs_path(notes_folder, file_path) {
-                    if let Ok(content) = std::fs::read_to_string(file_path) {
+                    if let Ok(content) = read_limit.read(file_path) {
                         let modified = entry
                             .metadata()
                             .ok()
@@ -307,17 +672,36 @@ impl SearchIndex {
 
                         let title = extract_title(&content);
 
-                        writer.add_document(doc!(
-                            self.id_field => id.as_str(),
-                            self.title_field => title,
-                            self.content_field => content.as_str(),
-                            self.modified_field => modified,
-                        ))?;
+                        if let Some((title, content)) = self.indexable(&id, &title, &content) {
+                            writer.add_document(doc!(
+                                self.id_field => id.as_str(),
+                                self.title_field => title,
+                                self.content_field => content,
+                                self.modified_field => modified,
+                            ))?;
+                        }
                     }
                 }
             }
         }
 
+        let sources = self.sources.read().expect("sources read lock").clone();
+        let attachments = if notes_folder.exists() {
+            sidecars::scan(notes_folder)
+        } else {
+            Vec::new()
+        };
+        for note in sources::scan(&sources).into_iter().chain(attachments) {
+            if let Some((title, content)) = self.indexable(&note.id, &note.title, &note.content) {
+                writer.add_document(doc!(
+                    self.id_field => note.id.as_str(),
+                    self.title_field => title,
+                    self.content_field => content,
+                    self.modified_field => note.modified,
+                ))?;
+            }
+        }
+
         writer.commit()?;
         Ok(())
     }
@@ -327,10 +711,22 @@ impl SearchIndex {
 pub struct AppState {
     pub app_config: RwLock<AppConfig>,  // notes_folder path (stored in app data)
     pub settings: RwLock<Settings>,      // per-folder settings (stored in .scratch/)
-    pub notes_cache: RwLock<HashMap<String, NoteMetadata>>,
+    pub notes_cache: RwLock<memory::LruCache<NoteMetadata>>,
     pub file_watcher: Mutex<Option<FileWatcherState>>,
+    pub theme_watcher: Mutex<Option<FileWatcherState>>,
+    pub source_watcher: Mutex<Option<FileWatcherState>>,
     pub search_index: Mutex<Option<SearchIndex>>,
     pub debounce_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+    pub privacy_unlocked_until: Mutex<Option<Instant>>, // privacy mode temporarily disabled
+    pub collab_session: Mutex<Option<collab::Session>>,
+    pub vault_server: Mutex<Option<serve::VaultServer>>,
+    pub autosave: autosave::Autosaver,
+    pub shutdown: shutdown::Shutdown,
+    pub instance: Mutex<Option<instance::InstanceStatus>>, // vault lock and crash recovery outcome
+    pub template_variables: templates::Registry,
+    pub audit: audit::AuditLog,
+    pub note_locks: locks::NoteLocks, // serializes writes to each note
+    pub link_index: backlinks::LinkIndex,
 }
 
 impl Default for AppState {
@@ -338,10 +734,24 @@ impl Default for AppState {
         Self {
             app_config: RwLock::new(AppConfig::default()),
             settings: RwLock::new(Settings::default()),
-            notes_cache: RwLock::new(HashMap::new()),
+            notes_cache: RwLock::new(memory::LruCache::new(
+                memory::MemoryBudgets::default().notes_cache_bytes(),
+            )),
             file_watcher: Mutex::new(None),
+            theme_watcher: Mutex::new(None),
+            source_watcher: Mutex::new(None),
             search_index: Mutex::new(None),
             debounce_map: Arc::new(Mutex::new(HashMap::new())),
+            privacy_unlocked_until: Mutex::new(None),
+            collab_session: Mutex::new(None),
+            vault_server: Mutex::new(None),
+            autosave: autosave::Autosaver::default(),
+            shutdown: shutdown::Shutdown::default(),
+            instance: Mutex::new(None),
+            template_variables: templates::Registry::default(),
+            audit: audit::AuditLog::default(),
+            note_locks: locks::NoteLocks::default(),
+            link_index: backlinks::LinkIndex::default(),
         }
     }
 }
@@ -569,8 +979,202 @@ fn strip_markdown(text: &str) -> String {
     result.trim().to_string()
 }
 
+/// Placeholder title shown for locked notes whose titles are hidden.
+const LOCKED_NOTE_TITLE: &str = "Locked note";
+
+/// What may be surfaced about a note outside the editor.
+struct NoteExposure {
+    locked: bool,
+    show_title: bool,
+    index_content: bool,
+}
+
+/// Resolve a note's exposure from its frontmatter flags and the vault-wide policy.
+fn note_exposure(content: &str, policy: &LockedNotePolicy) -> NoteExposure {
+    let fm = frontmatter::Frontmatter::parse(content);
+    let locked = fm.get_bool("locked").unwrap_or(false) || fm.get_bool("encrypted").unwrap_or(false);
+    if !locked {
+        return NoteExposure {
+            locked: false,
+            show_title: true,
+            index_content: true,
+        };
+    }
+    NoteExposure {
+        locked: true,
+        show_title: fm.get_bool("show_title").unwrap_or(policy.show_titles),
+        index_content: fm.get_bool("searchable").unwrap_or(policy.index_content),
+    }
+}
+
+/// Title and preview for list display, masked according to the locked-note policy.
+fn listing_title_and_preview(content: &str, policy: &LockedNotePolicy) -> (String, String) {
+    let exposure = note_exposure(content, policy);
+    let title = if exposure.show_title {
+        extract_title(content)
+    } else {
+        LOCKED_NOTE_TITLE.to_string()
+    };
+    let preview = if exposure.index_content {
+        generate_preview(content)
+    } else {
+        String::new()
+    };
+    (title, preview)
+}
+
+/// Tags for list display; none for notes whose content is locked.
+fn listing_tags(content: &str, policy: &LockedNotePolicy) -> Vec<String> {
+    if note_exposure(content, policy).index_content {
+        tags::extract_tags(content)
+    } else {
+        Vec::new()
+    }
+}
+
+/// Language shown in listings: None for notes whose content is hidden.
+fn listing_language(content: &str, policy: &LockedNotePolicy) -> Option<String> {
+    if note_exposure(content, policy).index_content {
+        language::detect(content)
+    } else {
+        None
+    }
+}
+
+/// IDs of the notes pinned in settings, for `listing_metadata`.
+fn pinned_note_ids(state: &AppState) -> HashSet<String> {
+    let settings = state.settings.read().expect("settings read lock");
+    settings.pinned_note_ids.iter().flatten().cloned().collect()
+}
+
+/// A note's entry in listings and the notes cache.
+fn listing_metadata(
+    id: String,
+    content: &str,
+    modified: i64,
+    created: i64,
+    policy: &LockedNotePolicy,
+    pinned: &HashSet<String>,
+) -> NoteMetadata {
+    let (title, preview) = listing_title_and_preview(content, policy);
+    NoteMetadata {
+        pinned: pinned.contains(&id),
+        id,
+        title,
+        preview,
+        modified,
+        created,
+        tags: listing_tags(content, policy),
+        language: listing_language(content, policy),
+    }
+}
+
+/// Whether a note is private: flagged `private: true` in frontmatter or stored
+/// under one of the vault's private folders.
+fn is_private_note(id: &str, content: &str, private_folders: &[String]) -> bool {
+    let in_private_folder = private_folders.iter().any(|folder| {
+        let folder = folder.trim_matches('/');
+        !folder.is_empty() && id.starts_with(&format!("{}/", folder))
+    });
+    in_private_folder
+        || frontmatter::Frontmatter::parse(content)
+            .get_bool("private")
+            .unwrap_or(false)
+}
+
+/// Private folders to hide, or None when privacy mode is temporarily disabled.
+fn active_private_folders(state: &AppState) -> Option<Vec<String>> {
+    {
+        let mut unlocked = state
+            .privacy_unlocked_until
+            .lock()
+            .expect("privacy unlock mutex");
+        match *unlocked {
+            Some(until) if Instant::now() < until => return None,
+            Some(_) => *unlocked = None,
+            None => {}
+        }
+    }
+    let settings = state.settings.read().expect("settings read lock");
+    Some(settings.private_folders.clone().unwrap_or_default())
+}
+
+/// The folder and tag policies of the `notePolicies` setting.
+fn note_policies(state: &AppState) -> Vec<policies::NotePolicy> {
+    let settings = state.settings.read().expect("settings read lock");
+    settings.note_policies.clone().unwrap_or_default()
+}
+
+/// Hash a privacy passphrase as "salt$sha256(salt:passphrase)".
+/// Drop a `.metadata_never_index` marker into each private folder so Spotlight
+/// skips it.
+fn mark_private_folders_unindexed(notes_folder: &Path, private_folders: &[String]) {
+    for folder in private_folders {
+        if validate_folder_path(folder.trim_matches('/')).is_err() {
+            continue;
+        }
+        let dir = notes_folder.join(folder.trim_matches('/').replace('/', std::path::MAIN_SEPARATOR_STR));
+        if dir.is_dir() {
+            let _ = std::fs::write(dir.join(".metadata_never_index"), b"");
+        }
+    }
+}
+
 /// Directories to exclude from note discovery and ID resolution.
-const EXCLUDED_DIRS: &[&str] = &[".git", ".scratch", ".obsidian", ".trash", "assets"];
+const EXCLUDED_DIRS: &[&str] = &[
+    ".git",
+    ".scratch",
+    ".obsidian",
+    ".trash",
+    history::HISTORY_DIR,
+    "assets",
+    sources::ID_PREFIX,
+    sidecars::ID_PREFIX,
+];
+
+/// Directories the file watcher skips unless the `watcherIgnore` setting
+/// says otherwise; `.*` covers hidden directories.
+const DEFAULT_WATCHER_IGNORE: &[&str] = &[".trash", ".obsidian", "node_modules", ".*"];
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Whether a change at `path` is inside a directory the file watcher
+/// ignores. Only directories count, so a dot-prefixed note like ".foo.md" is
+/// still watched.
+fn watcher_ignores(notes_root: &Path, path: &Path, patterns: &[String]) -> bool {
+    let Ok(rel) = path.strip_prefix(notes_root) else {
+        return false;
+    };
+    rel.parent()
+        .unwrap_or(Path::new(""))
+        .components()
+        .any(|component| match component {
+            std::path::Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                patterns.iter().any(|p| wildcard_match(p, &name))
+            }
+            _ => false,
+        })
+}
 
 /// Filter for WalkDir: skips excluded directories.
 fn is_visible_notes_entry(entry: &walkdir::DirEntry) -> bool {
@@ -581,6 +1185,56 @@ fn is_visible_notes_entry(entry: &walkdir::DirEntry) -> bool {
     true
 }
 
+/// A note read from disk during a full vault scan.
+pub struct ScannedNote {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub modified: i64,
+    pub created: i64, // as for NoteMetadata
+    pub size: u64,
+}
+
+/// Read every visible note in the vault. Blocking; call from spawn_blocking.
+fn scan_notes(notes_root: &Path) -> Vec<ScannedNote> {
+    use walkdir::WalkDir;
+
+    let mut notes = Vec::new();
+    for entry in WalkDir::new(notes_root)
+        .max_depth(10)
+        .into_iter()
+        .filter_entry(is_visible_notes_entry)
+        .flatten()
+    {
+        let file_path = entry.path();
+        if !file_path.is_file() {
+            continue;
+        }
+        let Some(id) = id_from_abs_path(notes_root, file_path) else {
+            continue;
+        };
+        let Ok(content) = std::fs::read_to_string(file_path) else {
+            continue;
+        };
+        let metadata = entry.metadata().ok();
+        let modified = metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        notes.push(ScannedNote {
+            id,
+            title: extract_title(&content),
+            created: note_created_secs(&content, metadata.as_ref(), modified),
+            size: metadata.map(|m| m.len()).unwrap_or(content.len() as u64),
+            content,
+            modified,
+        });
+    }
+    notes
+}
+
 /// Convert an absolute file path to a note ID (relative path from notes root, no .md extension, POSIX separators).
 /// Returns None if the path is outside the root, not a .md file, or in an excluded directory.
 fn id_from_abs_path(notes_root: &Path, file_path: &Path) -> Option<String> {
@@ -615,8 +1269,47 @@ fn id_from_abs_path(notes_root: &Path, file_path: &Path) -> Option<String> {
     }
 }
 
+/// The vault-relative path of a note's file, e.g. "Projects/plan.md". Events and
+/// `Note.path` carry these rather than absolute paths so they survive the vault
+/// moving; `resolve_vault_path` turns one back into an absolute path.
+fn rel_path_from_id(id: &str) -> String {
+    format!("{}.md", id)
+}
+
+/// Convert an absolute path inside the vault to a vault-relative one with
+/// POSIX separators. Paths outside the vault are returned unchanged.
+fn rel_path_from_abs(notes_root: &Path, path: &Path) -> String {
+    match path.strip_prefix(notes_root) {
+        Ok(rel) => rel.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/"),
+        Err(_) => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Convert a vault-relative path to an absolute one. Absolute paths are
+/// accepted as-is for callers that still hold one.
+fn abs_path_from_rel(notes_root: &Path, path: &str) -> Result<PathBuf, String> {
+    let rel = Path::new(path);
+    if rel.is_absolute() {
+        return Ok(rel.to_path_buf());
+    }
+    if path.contains('\\')
+        || !rel
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err("Invalid path: must stay inside the notes folder".to_string());
+    }
+    Ok(notes_root.join(rel))
+}
+
 /// Convert a note ID to an absolute file path. Validates against path traversal.
 fn abs_path_from_id(notes_root: &Path, id: &str) -> Result<PathBuf, String> {
+    if sources::is_source_id(id) {
+        return Err("Notes in source folders are read-only".to_string());
+    }
+    if sidecars::is_attachment_id(id) {
+        return Err("Attachments are not notes".to_string());
+    }
     if id.contains('\\') {
         return Err("Invalid note ID: backslashes not allowed".to_string());
     }
@@ -661,9 +1354,14 @@ fn get_app_config_path(app: &AppHandle) -> Result<PathBuf> {
 
 // Get per-folder settings file path (in .scratch/ within notes folder)
 fn get_settings_path(notes_folder: &str) -> PathBuf {
-    let scratch_dir = PathBuf::from(notes_folder).join(".scratch");
-    std::fs::create_dir_all(&scratch_dir).ok();
-    scratch_dir.join("settings.json")
+    store::path(Path::new(notes_folder), "settings.json")
+}
+
+// Get custom themes directory (in app data directory)
+fn get_themes_dir(app: &AppHandle) -> Result<PathBuf> {
+    let themes_dir = app.path().app_data_dir()?.join("themes");
+    std::fs::create_dir_all(&themes_dir)?;
+    Ok(themes_dir)
 }
 
 // Get search index path
@@ -673,6 +1371,93 @@ fn get_search_index_path(app: &AppHandle) -> Result<PathBuf> {
     Ok(app_data.join("search_index"))
 }
 
+// Open the search index as the `searchIndexMode` setting says. Memory mode also
+// deletes any index left on disk, since it holds note text. The index stems
+// for the `searchLanguage` setting, or the language most of the vault is in.
+fn open_search_index(
+    app: &AppHandle,
+    settings: &Settings,
+    sources: &[sources::SourceFolder],
+    notes_root: &Path,
+) -> Result<SearchIndex> {
+    let index_path = get_search_index_path(app)?;
+    let locked_policy = settings.locked_notes.clone().unwrap_or_default();
+    let language = language::search_language(settings.search_language.as_deref(), notes_root);
+    let index = match settings.search_index_mode.unwrap_or_default() {
+        SearchIndexMode::Disk => {
+            SearchIndex::new(Some(&index_path), locked_policy, language.as_deref())?
+        }
+        SearchIndexMode::Memory => {
+            if index_path.exists() {
+                let _ = std::fs::remove_dir_all(&index_path);
+            }
+            SearchIndex::new(None, locked_policy, language.as_deref())?
+        }
+    };
+    index.set_note_policies(settings.note_policies.clone().unwrap_or_default());
+    index.set_read_limit(settings.read_limits.clone().unwrap_or_default().index());
+    index.set_sources(sources.to_vec());
+    Ok(index)
+}
+
+// Get notes cache path (in app data directory)
+fn get_notes_cache_path(app: &AppHandle) -> Result<PathBuf> {
+    let app_data = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&app_data)?;
+    Ok(app_data.join("notes_cache.json"))
+}
+
+// Notes cache as persisted on shutdown, tied to the folder it describes
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotesCacheFile {
+    notes_folder: String,
+    notes: Vec<NoteMetadata>,
+}
+
+// Load the notes cache saved by the last run, if it is for this folder
+fn load_notes_cache(app: &AppHandle, notes_folder: &str) -> HashMap<String, NoteMetadata> {
+    get_notes_cache_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<NotesCacheFile>(&content).ok())
+        .filter(|file| file.notes_folder == notes_folder)
+        .map(|file| file.notes.into_iter().map(|n| (n.id.clone(), n)).collect())
+        .unwrap_or_default()
+}
+
+// Save the notes cache to disk
+fn save_notes_cache(app: &AppHandle, state: &AppState) -> Result<()> {
+    let Some(notes_folder) = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone()
+    else {
+        return Ok(());
+    };
+    // The cache holds note previews, which memory mode keeps off disk too
+    let memory_only = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.search_index_mode == Some(SearchIndexMode::Memory)
+    };
+    if memory_only {
+        let _ = std::fs::remove_file(get_notes_cache_path(app)?);
+        return Ok(());
+    }
+    let notes = {
+        let cache = state.notes_cache.read().expect("cache read lock");
+        cache.values().cloned().collect()
+    };
+    let content = serde_json::to_string(&NotesCacheFile {
+        notes_folder,
+        notes,
+    })?;
+    std::fs::write(get_notes_cache_path(app)?, content)?;
+    Ok(())
+}
+
 // Load app config from disk (notes folder path)
 fn load_app_config(app: &AppHandle) -> AppConfig {
     let path = match get_app_config_path(app) {
@@ -694,12 +1479,12 @@ fn load_app_config(app: &AppHandle) -> AppConfig {
 fn save_app_config(app: &AppHandle, config: &AppConfig) -> Result<()> {
     let path = get_app_config_path(app)?;
     let content = serde_json::to_string_pretty(config)?;
-    std::fs::write(path, content)?;
+    recovery::write_atomic_sync(&path, content.as_bytes())?;
     Ok(())
 }
 
-// Load per-folder settings from disk
-fn load_settings(notes_folder: &str) -> Settings {
+// Load per-folder settings from disk, without the vault config
+fn load_own_settings(notes_folder: &str) -> Settings {
     let path = get_settings_path(notes_folder);
 
     if path.exists() {
@@ -712,11 +1497,25 @@ fn load_settings(notes_folder: &str) -> Settings {
     }
 }
 
-// Save per-folder settings to disk
+// Merge the vault config over settings, leaving them as they are if it is broken
+fn with_vault_config(notes_folder: &str, mut settings: Settings) -> Settings {
+    if let Err(e) = vaultconfig::apply(Path::new(notes_folder), &mut settings) {
+        eprintln!("Ignoring vault config: {}", e);
+    }
+    settings
+}
+
+// Load per-folder settings from disk, with the vault config merged over them
+fn load_settings(notes_folder: &str) -> Settings {
+    with_vault_config(notes_folder, load_own_settings(notes_folder))
+}
+
+// Save per-folder settings to disk; values from the vault config stay out
 fn save_settings(notes_folder: &str, settings: &Settings) -> Result<()> {
     let path = get_settings_path(notes_folder);
-    let content = serde_json::to_string_pretty(settings)?;
-    std::fs::write(path, content)?;
+    let value = vaultconfig::settings_file_value(Path::new(notes_folder), settings, &path)?;
+    let content = serde_json::to_string_pretty(&value)?;
+    recovery::write_atomic_sync(&path, content.as_bytes())?;
     Ok(())
 }
 
@@ -747,7 +1546,8 @@ fn normalize_notes_folder_path(path: &str) -> Result<PathBuf, String> {
 
 /// Shared initialization logic for setting a notes folder.
 /// Creates required directories, verifies write access, updates config/settings,
-/// adds asset protocol scope, and rebuilds the search index.
+/// adds asset protocol scope, rebuilds the search index and moves the file
+/// watcher to the new folder.
 fn initialize_notes_folder(app: &AppHandle, path_buf: &PathBuf, state: &AppState) -> Result<String, String> {
     let normalized_path = path_buf.to_string_lossy().into_owned();
 
@@ -772,11 +1572,13 @@ fn initialize_notes_folder(app: &AppHandle, path_buf: &PathBuf, state: &AppState
 
     // Load per-folder settings (starts fresh with defaults if none exist)
     let settings = load_settings(&normalized_path);
+    memory::apply(state, &settings.memory_budgets.clone().unwrap_or_default());
 
     // Update app config
     {
         let mut app_config = state.app_config.write().expect("app_config write lock");
         app_config.notes_folder = Some(normalized_path.clone());
+        vaults::remember(&mut app_config, &normalized_path);
     }
 
     // Update settings in memory
@@ -795,12 +1597,25 @@ fn initialize_notes_folder(app: &AppHandle, path_buf: &PathBuf, state: &AppState
     let _ = app.asset_protocol_scope().allow_directory(path_buf, true);
 
     // Initialize search index
-    if let Ok(index_path) = get_search_index_path(app) {
-        if let Ok(search_index) = SearchIndex::new(&index_path) {
-            let _ = search_index.rebuild_index(path_buf);
-            let mut index = state.search_index.lock().expect("search index mutex");
-            *index = Some(search_index);
-        }
+    let opened = {
+        let sources = state
+            .app_config
+            .read()
+            .expect("app_config read lock")
+            .source_folders
+            .clone();
+        let settings = state.settings.read().expect("settings read lock");
+        open_search_index(app, &settings, &sources, path_buf)
+    };
+    if let Ok(search_index) = opened {
+        let _ = search_index.rebuild_index(path_buf);
+        let mut index = state.search_index.lock().expect("search index mutex");
+        *index = Some(search_index);
+    }
+
+    // The old watcher would keep reporting the previous folder
+    if let Err(e) = restart_file_watcher(app, state, &normalized_path) {
+        eprintln!("Failed to watch notes folder {}: {}", normalized_path, e);
     }
 
     Ok(normalized_path)
@@ -818,128 +1633,624 @@ fn get_notes_folder(state: State<AppState>) -> Option<String> {
         .clone()
 }
 
+/// The current ID of a note that may have been renamed, moved or deleted with
+/// a redirect since `id` was stored. Also accepts a wikilink target. None if
+/// no live note is found. Targets that only exist in a source folder resolve
+/// to the source note.
 #[tauri::command]
-fn set_notes_folder(app: AppHandle, path: String, state: State<AppState>) -> Result<(), String> {
-    let path_buf = normalize_notes_folder_path(&path)?;
-    initialize_notes_folder(&app, &path_buf, &state)?;
-    Ok(())
+async fn resolve_id(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, ScratchError> {
+    let (folder, sources) = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        (
+            app_config
+                .notes_folder
+                .clone()
+                .ok_or(ScratchError::FolderNotSet)?,
+            app_config.source_folders.clone(),
+        )
+    };
+
+    tokio::task::spawn_blocking(move || {
+        redirects::resolve(Path::new(&folder), &id).or_else(|| sources::find(&sources, &id))
+    })
+    .await
+    .map_err(ScratchError::from)
 }
 
+/// Absolute path for a vault-relative path such as `Note.path`, for the
+/// places that need a real file (opening in another app, reveal in folder).
 #[tauri::command]
-async fn list_notes(state: State<'_, AppState>) -> Result<Vec<NoteMetadata>, String> {
-    let folder = {
+fn resolve_vault_path(path: String, state: State<AppState>) -> Result<String, ScratchError> {
+    let (folder, sources) = {
         let app_config = state.app_config.read().expect("app_config read lock");
-        app_config
-            .notes_folder
-            .clone()
-            .ok_or("Notes folder not set")?
+        (
+            app_config
+                .notes_folder
+                .clone()
+                .ok_or(ScratchError::FolderNotSet)?,
+            app_config.source_folders.clone(),
+        )
     };
+    let resolved = match path.strip_suffix(".md") {
+        Some(id) if sources::is_source_id(id) => sources::resolve(&sources, id)?,
+        _ => abs_path_from_rel(Path::new(&folder), &path)?,
+    };
+    Ok(resolved.to_string_lossy().into_owned())
+}
 
-    let path = PathBuf::from(&folder);
-    if !path.exists() {
-        return Ok(vec![]);
+/// Open `path_buf` as the notes folder. A different vault that is open is
+/// closed first (`vaults::close`).
+async fn open_vault(app: &AppHandle, path_buf: PathBuf) -> Result<String, ScratchError> {
+    let current = app
+        .state::<AppState>()
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone();
+    if current.is_some_and(|folder| Path::new(&folder) != path_buf) {
+        vaults::close(app).await?;
     }
+    let app = app.clone();
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        initialize_notes_folder(&app, &path_buf, &state)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
 
-    let path_clone = path.clone();
-    let discovered = tokio::task::spawn_blocking(move || {
-        use walkdir::WalkDir;
-        let mut results: Vec<(String, String, String, i64)> = Vec::new();
-        for entry in WalkDir::new(&path_clone)
-            .max_depth(10)
-            .into_iter()
-            .filter_entry(is_visible_notes_entry)
-            .flatten()
-        {
-            let file_path = entry.path();
-            if !file_path.is_file() {
-                continue;
-            }
-            if let Some(id) = id_from_abs_path(&path_clone, file_path) {
-                if let Ok(content) = std::fs::read_to_string(file_path) {
-                    let modified = entry
-                        .metadata()
-                        .ok()
-                        .and_then(|m| m.modified().ok())
-                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                        .map(|d| d.as_secs() as i64)
-                        .unwrap_or(0);
-                    let title = extract_title(&content);
-                    let preview = generate_preview(&content);
-                    results.push((id, title, preview, modified));
-                }
-            }
+#[tauri::command]
+async fn set_notes_folder(app: AppHandle, path: String) -> Result<(), ScratchError> {
+    let path_buf = normalize_notes_folder_path(&path)?;
+    open_vault(&app, path_buf).await?;
+    Ok(())
+}
+
+#[tauri::command]
+fn list_vault_templates() -> Vec<scaffold::VaultTemplateInfo> {
+    scaffold::templates()
+}
+
+/// Create a new vault at `path` (missing or empty) laid out from `template`,
+/// with its starter notes and settings, and open it.
+#[tauri::command]
+async fn create_vault(
+    app: AppHandle,
+    path: String,
+    template: scaffold::VaultTemplate,
+) -> Result<scaffold::CreatedVault, ScratchError> {
+    let path_buf = normalize_notes_folder_path(&path)?;
+    scaffold::check_empty(&path_buf).map_err(ScratchError::invalid)?;
+    std::fs::create_dir_all(&path_buf).map_err(|e| ScratchError::io(e, &path_buf))?;
+
+    let mut settings = Settings::default();
+    let (notes, home) = scaffold::scaffold(&path_buf, template, &mut settings)?;
+    save_settings(&path_buf.to_string_lossy(), &settings)?;
+    let path = open_vault(&app, path_buf).await?;
+    Ok(scaffold::CreatedVault { path, notes, home })
+}
+
+/// Vaults known on this machine, the open one marked active.
+#[tauri::command]
+fn list_vaults(state: State<AppState>) -> Vec<vaults::VaultInfo> {
+    vaults::list(&state.app_config.read().expect("app_config read lock"))
+}
+
+/// Register an existing folder as a vault without opening it. `name`
+/// defaults to the folder name and is made unique.
+#[tauri::command]
+fn add_vault(
+    app: AppHandle,
+    path: String,
+    name: Option<String>,
+    state: State<AppState>,
+) -> Result<vaults::VaultInfo, ScratchError> {
+    let path_buf = normalize_notes_folder_path(&path)?;
+    if !path_buf.is_dir() {
+        return Err(ScratchError::not_found("Vault folder not found"));
+    }
+    let path = path_buf.to_string_lossy().into_owned();
+    let mut app_config = state.app_config.write().expect("app_config write lock");
+    if app_config.vaults.iter().any(|v| v.path == path) {
+        return Err(ScratchError::already_exists(
+            "This folder is already a vault",
+        ));
+    }
+    let vault = vaults::add(&mut app_config, &path, name.as_deref())?;
+    save_app_config(&app, &app_config)?;
+    Ok(vaults::VaultInfo {
+        active: app_config.notes_folder.as_deref() == Some(vault.path.as_str()),
+        available: true,
+        name: vault.name,
+        path: vault.path,
+    })
+}
+
+/// Forget a vault (not the open one). Its folder is not touched.
+#[tauri::command]
+fn remove_vault(app: AppHandle, name: String, state: State<AppState>) -> Result<(), ScratchError> {
+    let mut app_config = state.app_config.write().expect("app_config write lock");
+    vaults::remove(&mut app_config, &name)?;
+    save_app_config(&app, &app_config)?;
+    Ok(())
+}
+
+/// Open a known vault, by name or path, in place of the current one: pending
+/// autosaves are written and the old vault's caches, index, watcher and
+/// sessions let go before the new one loads. Windows follow through
+/// `set-notes-folder`.
+#[tauri::command]
+async fn switch_vault(
+    app: AppHandle,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<vaults::VaultInfo>, ScratchError> {
+    let vault = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        vaults::find(&app_config, &name)
+            .cloned()
+            .ok_or_else(|| ScratchError::not_found("Vault not found"))?
+    };
+    let path_buf = PathBuf::from(&vault.path);
+    if !path_buf.is_dir() {
+        return Err(ScratchError::not_found("Vault folder not found"));
+    }
+    let path = open_vault(&app, path_buf).await?;
+    let _ = app.emit("set-notes-folder", path);
+    Ok(vaults::list(
+        &state.app_config.read().expect("app_config read lock"),
+    ))
+}
+
+/// Which code blocks may run on this machine. Kept in the app config, not in
+/// the vault, so opening a vault cannot turn running code on.
+#[tauri::command]
+fn get_code_block_settings(state: State<AppState>) -> runner::CodeBlockSettings {
+    state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .code_blocks
+        .clone()
+}
+
+#[tauri::command]
+fn set_code_block_settings(
+    app: AppHandle,
+    settings: runner::CodeBlockSettings,
+    state: State<AppState>,
+) -> Result<(), ScratchError> {
+    let mut app_config = state.app_config.write().expect("app_config write lock");
+    app_config.code_blocks = settings;
+    save_app_config(&app, &app_config)?;
+    Ok(())
+}
+
+/// The command line that replaces the built-in speech engine. Kept in the app
+/// config, not in the vault, so opening a vault cannot pick a program to run.
+#[tauri::command]
+fn get_tts_command(state: State<AppState>) -> Option<String> {
+    state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .tts_command
+        .clone()
+}
+
+#[tauri::command]
+fn set_tts_command(
+    app: AppHandle,
+    command: Option<String>,
+    state: State<AppState>,
+) -> Result<(), ScratchError> {
+    let mut app_config = state.app_config.write().expect("app_config write lock");
+    app_config.tts_command = command.filter(|c| !c.trim().is_empty());
+    save_app_config(&app, &app_config)?;
+    Ok(())
+}
+
+#[tauri::command]
+fn list_source_folders(state: State<AppState>) -> Vec<sources::SourceFolder> {
+    state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .source_folders
+        .clone()
+}
+
+// Save the source list, then re-index and re-watch so the change takes effect
+async fn apply_source_folders(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    source_folders: Vec<sources::SourceFolder>,
+) -> Result<(), String> {
+    let folder = {
+        let mut app_config = state.app_config.write().expect("app_config write lock");
+        app_config.source_folders = source_folders.clone();
+        save_app_config(app, &app_config).map_err(|e| e.to_string())?;
+        app_config.notes_folder.clone()
+    };
+    sources::restart_watcher(app);
+
+    if let Some(folder) = folder {
+        let app = app.clone();
+        tokio::task::spawn_blocking(move || {
+            let state = app.state::<AppState>();
+            let index = state.search_index.lock().expect("search index mutex");
+            if let Some(ref search_index) = *index {
+                search_index.set_sources(source_folders);
+                let _ = search_index.rebuild_index(&PathBuf::from(&folder));
+            }
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Register a folder outside the vault as a read-only source. `name` defaults
+/// to the folder's name and is made unique.
+#[tauri::command]
+async fn add_source_folder(
+    app: AppHandle,
+    path: String,
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<sources::SourceFolder, ScratchError> {
+    let source_path = normalize_notes_folder_path(&path)?
+        .canonicalize()
+        .map_err(|_| ScratchError::not_found("Source folder not found"))?;
+    if !source_path.is_dir() {
+        return Err(ScratchError::invalid("Source folder is not a directory"));
+    }
+
+    let mut source_folders = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        if let Some(vault) = app_config
+            .notes_folder
+            .as_deref()
+            .and_then(|folder| Path::new(folder).canonicalize().ok())
+        {
+            if source_path.starts_with(&vault) || vault.starts_with(&source_path) {
+                return Err(ScratchError::invalid(
+                    "Source folders cannot overlap the notes folder",
+                ));
+            }
+        }
+        app_config.source_folders.clone()
+    };
+    let path_str = source_path.to_string_lossy().into_owned();
+    if source_folders.iter().any(|s| s.path == path_str) {
+        return Err(ScratchError::already_exists(
+            "Source folder is already registered",
+        ));
+    }
+
+    let requested = name.unwrap_or_else(|| {
+        source_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    let source = sources::SourceFolder {
+        name: sources::unique_name(&source_folders, &requested),
+        path: path_str,
+    };
+    source_folders.push(source.clone());
+    apply_source_folders(&app, &state, source_folders).await?;
+    Ok(source)
+}
+
+/// Stop indexing a source folder. Its files are left untouched.
+#[tauri::command]
+async fn remove_source_folder(
+    app: AppHandle,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), ScratchError> {
+    let mut source_folders = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .source_folders
+        .clone();
+    let before = source_folders.len();
+    source_folders.retain(|s| s.name != name);
+    if source_folders.len() == before {
+        return Err(ScratchError::not_found("Source folder not found"));
+    }
+    Ok(apply_source_folders(&app, &state, source_folders).await?)
+}
+
+/// The notes in the vault, sorted by `sort_by` (default: the `noteSort`
+/// setting) with pinned notes first.
+/// `offset` and `limit` select a page of the sorted list; without a limit the
+/// rest of the list is returned.
+#[tauri::command]
+async fn list_notes(
+    sort_by: Option<NoteSort>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<NotePage, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    let path = PathBuf::from(&folder);
+    let offset = offset.unwrap_or(0);
+    if !path.exists() {
+        return Ok(NotePage {
+            notes: Vec::new(),
+            total: 0,
+            offset,
+        });
+    }
+
+    let (locked_policy, read_limit) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            settings.locked_notes.clone().unwrap_or_default(),
+            settings.read_limits.clone().unwrap_or_default().listing(),
+        )
+    };
+    let private_folders = active_private_folders(&state);
+    let pinned = pinned_note_ids(&state);
+
+    let path_clone = path.clone();
+    // Each note with its size, for sorting
+    let mut notes: Vec<(NoteMetadata, u64)> = tokio::task::spawn_blocking(move || {
+        use walkdir::WalkDir;
+        let mut results = Vec::new();
+        for entry in WalkDir::new(&path_clone)
+            .max_depth(10)
+            .into_iter()
+            .filter_entry(is_visible_notes_entry)
+            .flatten()
+        {
+            let file_path = entry.path();
+            if !file_path.is_file() {
+                continue;
+            }
+            if let Some(id) = id_from_abs_path(&path_clone, file_path) {
+                // Giant files are listed from their first bytes only
+                if let Ok(content) = read_limit.read(file_path) {
+                    if let Some(ref private) = private_folders {
+                        if is_private_note(&id, &content, private) {
+                            continue;
+                        }
+                    }
+                    let metadata = entry.metadata().ok();
+                    let modified = metadata.as_ref().map(file_modified_secs).unwrap_or(0);
+                    let created = note_created_secs(&content, metadata.as_ref(), modified);
+                    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+                    results.push((
+                        listing_metadata(id, &content, modified, created, &locked_policy, &pinned),
+                        size,
+                    ));
+                }
+            }
         }
         results
     })
     .await
     .map_err(|e| e.to_string())?;
 
-    let mut notes: Vec<NoteMetadata> = discovered
-        .into_iter()
-        .map(|(id, title, preview, modified)| NoteMetadata {
-            id,
-            title,
-            preview,
-            modified,
-        })
-        .collect();
-
-    // Load pinned note IDs from settings
-    let pinned_ids: HashSet<String> = {
+    // Load the collation locale and the default order from settings
+    let (locale, default_sort) = {
         let settings = state.settings.read().expect("settings read lock");
-        settings
-            .pinned_note_ids
-            .as_ref()
-            .map(|ids| ids.iter().cloned().collect())
-            .unwrap_or_default()
+        (settings.collation_locale.clone(), settings.note_sort)
     };
+    let sort = sort_by.or(default_sort).unwrap_or_default();
+    let collation = (sort == NoteSort::Title).then(|| collation::Collation::new(locale.as_deref()));
 
-    // Sort: pinned notes first (by date), then unpinned notes (by date)
-    notes.sort_by(|a, b| {
-        let a_pinned = pinned_ids.contains(&a.id);
-        let b_pinned = pinned_ids.contains(&b.id);
-
-        match (a_pinned, b_pinned) {
+    // Sort: pinned notes first, then unpinned notes, each by the chosen key
+    notes.sort_by(|(a, a_size), (b, b_size)| {
+        match (a.pinned, b.pinned) {
             (true, false) => std::cmp::Ordering::Less,    // a pinned, b not -> a first
             (false, true) => std::cmp::Ordering::Greater, // b pinned, a not -> b first
-            _ => b.modified.cmp(&a.modified),             // both same status -> sort by date (newest first)
+            _ => match sort {
+                NoteSort::Title => collation
+                    .as_ref()
+                    .map(|collation| collation.compare(&a.title, &b.title))
+                    .unwrap_or_else(|| a.title.cmp(&b.title))
+                    .then_with(|| a.id.cmp(&b.id)),
+                NoteSort::Modified => b.modified.cmp(&a.modified),
+                NoteSort::Created => b
+                    .created
+                    .cmp(&a.created)
+                    .then_with(|| b.modified.cmp(&a.modified)),
+                NoteSort::Size => b_size.cmp(a_size).then_with(|| a.id.cmp(&b.id)),
+            },
         }
     });
+    let notes: Vec<NoteMetadata> = notes.into_iter().map(|(note, _)| note).collect();
 
-    // Update cache efficiently
+    // Update cache efficiently. Insert from the bottom of the list up, so if
+    // the cache fills it keeps the notes at the top.
     {
         let mut cache = state.notes_cache.write().expect("cache write lock");
         cache.clear();
-        for note in &notes {
+        for note in notes.iter().rev() {
             cache.insert(note.id.clone(), note.clone());
         }
     }
 
-    Ok(notes)
+    let total = notes.len();
+    let page = notes
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+    Ok(NotePage {
+        notes: page,
+        total,
+        offset,
+    })
 }
 
+/// Pin a note to the top of the note list, or unpin it. Returns whether it
+/// is pinned now.
 #[tauri::command]
-async fn read_note(id: String, state: State<'_, AppState>) -> Result<Note, String> {
+fn toggle_pin(id: String, state: State<AppState>) -> Result<bool, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    if !abs_path_from_id(Path::new(&folder), &id)?.is_file() {
+        return Err(ScratchError::not_found("Note not found"));
+    }
+
+    let mut settings = state.settings.write().expect("settings write lock");
+    let pinned_ids = settings.pinned_note_ids.get_or_insert_with(Vec::new);
+    let pinned = match pinned_ids.iter().position(|pin_id| *pin_id == id) {
+        Some(index) => {
+            pinned_ids.remove(index);
+            false
+        }
+        None => {
+            pinned_ids.push(id);
+            true
+        }
+    };
+    save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+    Ok(pinned)
+}
+
+/// Star a note, adding it to the favorites `list_starred` returns.
+#[tauri::command]
+fn star_note(id: String, state: State<AppState>) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    if !abs_path_from_id(Path::new(&folder), &id)?.is_file() {
+        return Err(ScratchError::not_found("Note not found"));
+    }
+    let mut stars = stars::StarStore::load(&folder);
+    if stars.star(&id) {
+        stars.save(&folder).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn unstar_note(id: String, state: State<AppState>) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let mut stars = stars::StarStore::load(&folder);
+    if stars.unstar(&id) {
+        stars.save(&folder).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Starred notes, most recently starred first. Stars of notes that are gone
+/// or hidden as private are left out.
+#[tauri::command]
+async fn list_starred(state: State<'_, AppState>) -> Result<Vec<NoteMetadata>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let (locked_policy, read_limit) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            settings.locked_notes.clone().unwrap_or_default(),
+            settings.read_limits.clone().unwrap_or_default().listing(),
+        )
+    };
+    let private_folders = active_private_folders(&state);
+    let pinned = pinned_note_ids(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let root = Path::new(&folder);
+        stars::StarStore::load(&folder)
+            .stars
+            .into_iter()
+            .rev()
+            .filter_map(|star| {
+                let path = abs_path_from_id(root, &star.id).ok()?;
+                let content = read_limit.read(&path).ok()?;
+                if let Some(ref private) = private_folders {
+                    if is_private_note(&star.id, &content, private) {
+                        return None;
+                    }
+                }
+                let metadata = std::fs::metadata(&path).ok();
+                let modified = metadata.as_ref().map(file_modified_secs).unwrap_or(0);
+                let created = note_created_secs(&content, metadata.as_ref(), modified);
+                Some(listing_metadata(
+                    star.id,
+                    &content,
+                    modified,
+                    created,
+                    &locked_policy,
+                    &pinned,
+                ))
+            })
+            .collect()
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+#[tauri::command]
+async fn read_note(id: String, state: State<'_, AppState>) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
 
     let folder_path = PathBuf::from(&folder);
-    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let file_path = if sources::is_source_id(&id) {
+        let sources = state
+            .app_config
+            .read()
+            .expect("app_config read lock")
+            .source_folders
+            .clone();
+        sources::resolve(&sources, &id)?
+    } else {
+        abs_path_from_id(&folder_path, &id)?
+    };
     if !file_path.exists() {
-        return Err("Note not found".to_string());
+        return Err(ScratchError::NoteNotFound { id });
     }
 
     let content = fs::read_to_string(&file_path)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| ScratchError::note_io(e, &id))?;
     let metadata = fs::metadata(&file_path)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| ScratchError::note_io(e, &id))?;
 
     let modified = metadata
         .modified()
@@ -949,34 +2260,201 @@ async fn read_note(id: String, state: State<'_, AppState>) -> Result<Note, Strin
         .unwrap_or(0);
 
     Ok(Note {
+        path: rel_path_from_id(&id),
         id,
         title: extract_title(&content),
         content,
-        path: file_path.to_string_lossy().into_owned(),
         modified,
     })
 }
 
-#[tauri::command]
-async fn save_note(
-    id: Option<String>,
-    content: String,
-    state: State<'_, AppState>,
-) -> Result<Note, String> {
-    let folder = {
-        let app_config = state.app_config.read().expect("app_config read lock");
-        app_config
-            .notes_folder
-            .clone()
-            .ok_or("Notes folder not set")?
-    };
-    let folder_path = PathBuf::from(&folder);
-
-    let title = extract_title(&content);
-    let sanitized_leaf = sanitize_filename(&title);
+const MAX_NAME_ATTEMPTS: usize = 1000;
+
+/// Atomically create the empty file of a new note, trying `candidate(0)`,
+/// `candidate(1)`, ... until one is free. `keep` (the note being renamed) is
+/// taken as is. Creating with `create_new` instead of checking `exists()`
+/// first means two quick saves can never pick, and overwrite, the same file.
+/// Running out of candidates fails with `tryAgain`.
+fn claim_note_file(
+    notes_root: &Path,
+    keep: Option<&str>,
+    candidate: impl Fn(usize) -> String,
+) -> Result<(String, PathBuf), ScratchError> {
+    for attempt in 0..MAX_NAME_ATTEMPTS {
+        let id = candidate(attempt);
+        let path = abs_path_from_id(notes_root, &id)?;
+        if keep == Some(id.as_str()) {
+            return Ok((id, path));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| ScratchError::io(e, parent))?;
+        }
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => return Ok((id, path)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(ScratchError::io(e, &path)),
+        }
+    }
+    Err(ScratchError::name_contended())
+}
+
+// On-disk state of a note that changed since the editor last loaded it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveConflict {
+    pub id: String,
+    pub disk_content: Option<String>, // None if the file was deleted
+    pub disk_modified: Option<i64>,
+    pub disk_hash: Option<String>, // to overwrite it with a checked save
+}
+
+// Result of save_note: the saved note, with its fields at the top level so a
+// plain save still reads as a Note, or the conflict that stopped the save
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum SaveResult {
+    Saved(Note),
+    Conflict(SaveConflict),
+}
+
+fn file_modified_secs(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// When the filesystem recorded the file's creation, where it does.
+fn file_created_secs(metadata: &std::fs::Metadata) -> Option<i64> {
+    metadata
+        .created()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+/// A note's `created:` frontmatter as Unix seconds: an RFC 3339 time, or a
+/// local `YYYY-MM-DD HH:MM[:SS]` time or `YYYY-MM-DD` date.
+fn frontmatter_created_secs(content: &str) -> Option<i64> {
+    let fm = frontmatter::Frontmatter::parse(content);
+    let value = fm.get("created")?.trim();
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(time.timestamp());
+    }
+    const FORMATS: [&str; 4] = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ];
+    let local = FORMATS
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+    local
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|t| t.timestamp())
+}
+
+/// When a note was created: its `created:` frontmatter, else the file's
+/// creation time, else `modified`. Frontmatter comes first since imported and
+/// cloned notes carry their real date there while their files are new.
+fn note_created_secs(content: &str, metadata: Option<&std::fs::Metadata>, modified: i64) -> i64 {
+    frontmatter_created_secs(content)
+        .or_else(|| metadata.and_then(file_created_secs))
+        .unwrap_or(modified)
+}
+
+/// Give a new note `created:` frontmatter when the filesystem at `file_path`
+/// keeps no creation times, so the note still knows when it was made.
+fn stamp_created(content: String, file_path: &Path) -> String {
+    let has_birthtime = std::fs::metadata(file_path).is_ok_and(|m| file_created_secs(&m).is_some());
+    if has_birthtime || frontmatter_created_secs(&content).is_some() {
+        return content;
+    }
+    match import::frontmatter_time(chrono::Utc::now().timestamp()) {
+        Some(now) => frontmatter::set(&content, "created", frontmatter::Value::Scalar(now)),
+        None => content,
+    }
+}
+
+/// Save a note, renaming its file when the title changed. With
+/// `expected_hash` (the SHA-256 of the content the editor last loaded or
+/// saved), a note changed or deleted on disk since then isn't overwritten; a
+/// `Conflict` comes back with what is on disk instead. Content is compared
+/// rather than modification times, which may only count whole seconds.
+#[tauri::command]
+async fn save_note(
+    app: AppHandle,
+    id: Option<String>,
+    content: String,
+    expected_hash: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SaveResult, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    // A full save supersedes any autosave still waiting
+    let _guard = match id {
+        Some(ref existing_id) => {
+            state.autosave.discard(&folder_path, existing_id);
+            Some(state.note_locks.lock(&folder_path, &[existing_id.as_str()]).await)
+        }
+        None => None,
+    };
+
+    if let (Some(existing_id), Some(expected)) = (id.as_deref(), expected_hash.as_deref()) {
+        let path = abs_path_from_id(&folder_path, existing_id)?;
+        let disk = fs::read(&path).await.ok();
+        let disk_hash = disk.as_deref().map(audit::hash);
+        if disk_hash.as_deref() != Some(expected) {
+            let disk_content = disk.and_then(|bytes| String::from_utf8(bytes).ok());
+            // Someone else writing the same text isn't a conflict
+            if disk_content.as_deref() != Some(content.as_str()) {
+                let disk_modified = fs::metadata(&path)
+                    .await
+                    .ok()
+                    .map(|m| file_modified_secs(&m));
+                return Ok(SaveResult::Conflict(SaveConflict {
+                    id: existing_id.to_string(),
+                    disk_content,
+                    disk_modified,
+                    disk_hash,
+                }));
+            }
+        }
+    }
+
+    let title = extract_title(&content);
+    let naming = {
+        let settings = state.settings.read().expect("settings read lock");
+        filenames::Naming::from_settings(&settings)
+    };
+
+    // Determine the file ID and path, handling renames. New files are claimed
+    // up front so concurrent saves can't pick the same one.
+    let new_note = id.is_none();
+    let (final_id, file_path, old_id, claimed) = if let Some(existing_id) = id {
+        let existing_leaf = existing_id.rsplit('/').next().unwrap_or(&existing_id);
+        let sanitized_leaf = naming.retitled_leaf(existing_leaf, &title);
 
-    // Determine the file ID and path, handling renames
-    let (final_id, file_path, old_id) = if let Some(existing_id) = id {
         // Preserve directory prefix for notes in subfolders
         let (dir_prefix, desired_id) = if let Some(pos) = existing_id.rfind('/') {
             let prefix = &existing_id[..pos];
@@ -988,48 +2466,51 @@ async fn save_note(
         let old_file_path = abs_path_from_id(&folder_path, &existing_id)?;
 
         if existing_id != desired_id {
-            let mut new_id = desired_id.clone();
-            let mut counter = 1;
-
-            while new_id != existing_id
-                && abs_path_from_id(&folder_path, &new_id)
-                    .map(|p| p.exists())
-                    .unwrap_or(false)
-            {
-                new_id = if let Some(ref prefix) = dir_prefix {
-                    format!("{}/{}-{}", prefix, sanitized_leaf, counter)
-                } else {
-                    format!("{}-{}", sanitized_leaf, counter)
-                };
-                counter += 1;
-            }
-
-            let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
-            (new_id, new_file_path, Some((existing_id, old_file_path)))
+            let (new_id, new_file_path) = claim_note_file(&folder_path, Some(existing_id.as_str()), |attempt| {
+                match (attempt, &dir_prefix) {
+                    (0, _) => desired_id.clone(),
+                    (n, Some(prefix)) => format!("{}/{}-{}", prefix, sanitized_leaf, n),
+                    (n, None) => format!("{}-{}", sanitized_leaf, n),
+                }
+            })?;
+            let claimed = new_id != existing_id;
+            (new_id, new_file_path, Some((existing_id, old_file_path)), claimed)
         } else {
-            (existing_id, old_file_path, None)
+            (existing_id, old_file_path, None, false)
         }
     } else {
         // New notes go in root
-        let mut new_id = sanitized_leaf.clone();
-        let mut counter = 1;
-
-        while abs_path_from_id(&folder_path, &new_id)
-            .map(|p| p.exists())
-            .unwrap_or(false)
-        {
-            new_id = format!("{}-{}", sanitized_leaf, counter);
-            counter += 1;
-        }
+        let sanitized_leaf = naming.new_leaf(&title, chrono::Local::now());
+        let (new_id, new_file_path) = claim_note_file(&folder_path, None, |attempt| match attempt {
+            0 => sanitized_leaf.clone(),
+            n => format!("{}-{}", sanitized_leaf, n),
+        })?;
+        (new_id, new_file_path, None, true)
+    };
+    let content = if new_note {
+        stamp_created(content, &file_path)
+    } else {
+        content
+    };
 
-        let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
-        (new_id, new_file_path, None)
+    // What the note held before, for the audit log
+    let previous_path = match old_id {
+        Some((_, ref old_file_path)) => Some(old_file_path),
+        None if !claimed => Some(&file_path),
+        None => None,
+    };
+    let previous = match previous_path {
+        Some(path) => fs::read(path).await.ok(),
+        None => None,
     };
 
     // Write the file to the new path
-    fs::write(&file_path, &content)
-        .await
-        .map_err(|e| e.to_string())?;
+    if let Err(e) = recovery::write_atomic(&file_path, &content).await {
+        if claimed {
+            let _ = fs::remove_file(&file_path).await;
+        }
+        return Err(e.into());
+    }
 
     // Delete old file AFTER successful write (to prevent data loss)
     if let Some((_, ref old_file_path)) = old_id {
@@ -1038,9 +2519,7 @@ async fn save_note(
         }
     }
 
-    let metadata = fs::metadata(&file_path)
-        .await
-        .map_err(|e| e.to_string())?;
+    let metadata = fs::metadata(&file_path).await?;
     let modified = metadata
         .modified()
         .ok()
@@ -1058,6 +2537,10 @@ async fn save_note(
             let _ = search_index.index_note(&final_id, &title, &content, modified);
         }
     }
+    if let Some((ref old_id_str, _)) = old_id {
+        state.link_index.remove(&folder_path, old_id_str);
+    }
+    state.link_index.update(&folder_path, &final_id, &content);
 
     // Update cache (remove old entry if renamed)
     if let Some((ref old_id_str, _)) = old_id {
@@ -1065,566 +2548,1317 @@ async fn save_note(
         cache.remove(old_id_str);
     }
 
-    Ok(Note {
+    // Keep reading position and annotations with the renamed note
+    if let Some((ref old_id_str, _)) = old_id {
+        let mut store = annotations::AnnotationStore::load(&folder);
+        if store.rename_note(old_id_str, &final_id) {
+            let _ = store.save(&folder);
+        }
+        let mut stars = stars::StarStore::load(&folder);
+        if stars.rename_note(old_id_str, &final_id) {
+            let _ = stars.save(&folder);
+        }
+        let _ = redirects::record_note(&folder_path, old_id_str, &final_id);
+        let _ = history::rename(&folder_path, old_id_str, &final_id);
+    }
+
+    let change = match old_id {
+        Some((ref old_id_str, _)) => {
+            audit::Change::new(audit::Surface::Ui, audit::Action::Rename, old_id_str)
+                .new_id(&final_id)
+        }
+        None if claimed => audit::Change::new(audit::Surface::Ui, audit::Action::Create, &final_id),
+        None => audit::Change::new(audit::Surface::Ui, audit::Action::Save, &final_id),
+    };
+    state.audit.record(
+        &folder_path,
+        change
+            .before(previous.as_deref())
+            .after(Some(content.as_bytes())),
+    );
+    let previous_text = previous.and_then(|p| String::from_utf8(p).ok());
+    snapshot_history(
+        &app,
+        &folder_path,
+        &final_id,
+        previous_text,
+        content.clone(),
+    )
+    .await;
+    let (message, mut files) = match old_id {
+        Some((ref old_id_str, _)) => (
+            format!("Rename {} to {}", old_id_str, final_id),
+            vec![rel_path_from_id(old_id_str)],
+        ),
+        None if claimed => (format!("Create {}", final_id), Vec::new()),
+        None => (format!("Update {}", final_id), Vec::new()),
+    };
+    files.push(rel_path_from_id(&final_id));
+    git_auto_commit(&state, &folder_path, files, message);
+    archive::archive_in_background(&app, &final_id, &content);
+
+    Ok(SaveResult::Saved(Note {
+        path: rel_path_from_id(&final_id),
         id: final_id,
         title,
         content,
-        path: file_path.to_string_lossy().into_owned(),
         modified,
-    })
+    }))
+}
+
+const BACKLINK_CONTEXT_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BacklinkLine {
+    line: usize, // 1-based
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Backlink {
+    id: String,
+    title: String,
+    lines: Vec<BacklinkLine>, // empty for locked notes whose content is hidden
 }
 
+/// Notes that link to `id` (for a described attachment, that reference its
+/// file), with the lines the links are on, sorted by title.
 #[tauri::command]
-async fn delete_note(id: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn get_backlinks(
+    id: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<Backlink>, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
     };
+    let private_folders = active_private_folders(&state);
 
-    let folder_path = PathBuf::from(&folder);
-    let file_path = abs_path_from_id(&folder_path, &id)?;
-    if file_path.exists() {
-        fs::remove_file(&file_path)
-            .await
-            .map_err(|e| e.to_string())?;
-    }
+    tokio::task::spawn_blocking(move || {
+        let root = PathBuf::from(&folder);
+        let backlink = |source: String, content: &str, lines: Vec<(usize, &str)>| {
+            let lines = if note_exposure(content, &locked_policy).index_content {
+                lines
+                    .into_iter()
+                    .map(|(line, text)| BacklinkLine {
+                        line,
+                        text: text.trim().chars().take(BACKLINK_CONTEXT_CHARS).collect(),
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            Backlink {
+                title: listing_title_and_preview(content, &locked_policy).0,
+                id: source,
+                lines,
+            }
+        };
 
-    // Update search index
-    {
-        let index = state.search_index.lock().expect("search index mutex");
-        if let Some(ref search_index) = *index {
-            let _ = search_index.delete_note(&id);
+        // Attachments are linked to by path, which the link index doesn't track
+        if let Some(attachment) = sidecars::path_from_id(&id) {
+            let mut backlinks: Vec<Backlink> =
+                scan_analyzable_notes(&root, private_folders.as_deref())
+                    .into_iter()
+                    .filter_map(|note| {
+                        let lines =
+                            sidecars::lines_referencing(&note.id, &note.content, attachment);
+                        (!lines.is_empty()).then(|| backlink(note.id.clone(), &note.content, lines))
+                    })
+                    .collect();
+            backlinks.sort_by(|a, b| a.title.cmp(&b.title));
+            return backlinks;
         }
-    }
 
-    // Remove from cache
-    {
-        let mut cache = state.notes_cache.write().expect("cache write lock");
-        cache.remove(&id);
-    }
+        let (sources, resolver) = app.state::<AppState>().link_index.sources(&root, &id);
+        let mut backlinks: Vec<Backlink> = sources
+            .into_iter()
+            .filter_map(|source| {
+                // Read fresh so the context matches what's on disk now
+                let path = abs_path_from_id(&root, &source).ok()?;
+                let content = std::fs::read_to_string(path).ok()?;
+                if let Some(ref private) = private_folders {
+                    if is_private_note(&source, &content, private) {
+                        return None;
+                    }
+                }
+                let lines = links::lines_linking_to(&content, &source, &id, &resolver);
+                if lines.is_empty() {
+                    return None;
+                }
+                Some(backlink(source, &content, lines))
+            })
+            .collect();
+        backlinks.sort_by(|a, b| a.title.cmp(&b.title));
+        backlinks
+    })
+    .await
+    .map_err(ScratchError::from)
+}
 
-    Ok(())
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkingNote {
+    id: String,
+    title: String,
+    links: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteImpact {
+    id: String,
+    link_count: usize,
+    notes: Vec<LinkingNote>, // most links first
 }
 
+/// Notes that link to `id`, so the UI can offer to redirect or strip those
+/// links before deleting it.
 #[tauri::command]
-async fn create_note(target_folder: Option<String>, state: State<'_, AppState>) -> Result<Note, String> {
+async fn get_delete_impact(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<DeleteImpact, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
-    let folder_path = PathBuf::from(&folder);
-
-    // Get template from settings (default "Untitled")
-    let template = {
+    let locked_policy = {
         let settings = state.settings.read().expect("settings read lock");
-        settings
-            .default_note_name
-            .clone()
-            .unwrap_or_else(|| "Untitled".to_string())
+        settings.locked_notes.clone().unwrap_or_default()
     };
+    let private_folders = active_private_folders(&state);
 
-    // Expand template tags
-    let expanded = expand_note_name_template(&template);
-
-    // Sanitize filename
-    let sanitized = sanitize_filename(&expanded);
-
-    // Prepend folder prefix if specified
-    let sanitized = if let Some(ref folder_prefix) = target_folder {
-        if folder_prefix.is_empty() {
-            sanitized
-        } else {
-            format!("{}/{}", folder_prefix.trim_end_matches('/'), sanitized)
+    tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref());
+        let resolver =
+            links::LinkResolver::new(notes.iter().map(|n| (n.id.as_str(), n.title.as_str())));
+        let mut linking: Vec<LinkingNote> = notes
+            .iter()
+            .filter(|note| note.id != id)
+            .filter_map(|note| {
+                let count = links::count_links_to(&note.content, &note.id, &id, &resolver);
+                (count > 0).then(|| LinkingNote {
+                    id: note.id.clone(),
+                    title: listing_title_and_preview(&note.content, &locked_policy).0,
+                    links: count,
+                })
+            })
+            .collect();
+        linking.sort_by(|a, b| b.links.cmp(&a.links).then_with(|| a.title.cmp(&b.title)));
+        DeleteImpact {
+            link_count: linking.iter().map(|n| n.links).sum(),
+            notes: linking,
+            id,
         }
-    } else {
-        sanitized
-    };
+    })
+    .await
+    .map_err(ScratchError::from)
+}
 
-    // Handle {counter} tag
-    let has_counter = template.contains("{counter}");
-    let base_id = if has_counter {
-        sanitized.replace("{counter}", "1")
-    } else {
-        sanitized.clone()
+/// Move a note to the trash (see `restore_note`). `rewrite_links` optionally
+/// strips or redirects the links other notes have to it; without it they are
+/// left dangling.
+#[tauri::command]
+async fn delete_note(
+    id: String,
+    rewrite_links: Option<links::LinkRewrite>,
+    state: State<'_, AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
 
-    let mut final_id = base_id.clone();
-    let mut counter = if has_counter { 2 } else { 1 };
-
-    // Ensure filename uniqueness
-    while abs_path_from_id(&folder_path, &final_id)
-        .map(|p| p.exists())
-        .unwrap_or(false)
-    {
-        if has_counter {
-            final_id = sanitized.replace("{counter}", &counter.to_string());
-        } else {
-            final_id = format!("{}-{}", base_id, counter);
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if let Some(links::LinkRewrite::Redirect { to }) = &rewrite_links {
+        if to == &id || !abs_path_from_id(&folder_path, to)?.is_file() {
+            return Err(ScratchError::not_found("Redirect target not found"));
         }
-        counter += 1;
     }
+    state.autosave.discard(&folder_path, &id);
+    let guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
 
-    // Extract display title from filename
-    let display_title = extract_title_from_id(&final_id);
-
-    let content = format!("# {}\n\n", display_title);
-    let file_path = abs_path_from_id(&folder_path, &final_id)?;
+    // Keep the content around for the audit log and to find assets that
+    // only this note used
+    let (collect_assets, layout) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            settings.trash_orphaned_assets.unwrap_or(false),
+            settings.asset_layout.unwrap_or_default(),
+        )
+    };
+    let deleted_content = fs::read_to_string(&file_path).await.ok();
+
+    // Move the note into the trash rather than deleting it
+    let entry = if file_path.exists() {
+        let root = folder_path.clone();
+        let note_id = id.clone();
+        let entry = tokio::task::spawn_blocking(move || {
+            let deleted_at = chrono::Utc::now().timestamp();
+            let entry =
+                trash::create_entry(&root, &note_id, deleted_at).map_err(|e| e.to_string())?;
+            let note = rel_path_from_id(&note_id);
+            let moved = trash::move_into(&root, &entry, &note).and_then(|_| {
+                trash::write_manifest(
+                    &entry,
+                    &trash::TrashManifest {
+                        note_id,
+                        deleted_at,
+                        note: Some(note.clone()),
+                        assets: Vec::new(),
+                    },
+                )
+            });
+            if let Err(e) = moved {
+                // Put the note back if it got as far as the trash
+                let _ = std::fs::rename(entry.join(&note), root.join(&note));
+                let _ = std::fs::remove_dir_all(&entry);
+                return Err(format!("Failed to move the note to the trash: {}", e));
+            }
+            Ok(entry)
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        Some(entry)
+    } else {
+        None
+    };
+    state.audit.record(
+        &folder_path,
+        audit::Change::new(audit::Surface::Ui, audit::Action::Delete, &id)
+            .before(deleted_content.as_deref().map(str::as_bytes)),
+    );
+    drop(guard);
+    git_auto_commit(
+        &state,
+        &folder_path,
+        vec![rel_path_from_id(&id)],
+        format!("Delete {}", id),
+    );
 
-    // Create parent directories (for templates like {year}/{month}/{day})
-    if let Some(parent) = file_path.parent() {
-        fs::create_dir_all(parent)
-            .await
-            .map_err(|e| e.to_string())?;
+    if let Some(links::LinkRewrite::Redirect { to }) = &rewrite_links {
+        let _ = redirects::record_note(&folder_path, &id, to);
     }
-
-    fs::write(&file_path, &content)
+    if let Some(rewrite) = rewrite_links {
+        let root = folder_path.clone();
+        let note_id = id.clone();
+        let title = deleted_content
+            .as_deref()
+            .map(extract_title)
+            .unwrap_or_default();
+        let note_locks = state.note_locks.clone();
+        let rewritten = tokio::task::spawn_blocking(move || {
+            let notes = scan_notes(&root);
+            // The deleted note goes first so title and leaf matches still find it
+            let resolver = links::LinkResolver::new(
+                std::iter::once((note_id.as_str(), title.as_str()))
+                    .chain(notes.iter().map(|n| (n.id.as_str(), n.title.as_str()))),
+            );
+            rewrite_links_in_notes(&root, &note_locks, &notes, &note_id, &resolver, &rewrite)
+        })
         .await
         .map_err(|e| e.to_string())?;
 
-    let modified = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+        let modified = chrono::Utc::now().timestamp();
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            for (id, content) in &rewritten {
+                let _ = search_index.index_note(id, &extract_title(content), content, modified);
+            }
+        }
+        for (id, content) in &rewritten {
+            state.link_index.update(&folder_path, id, content);
+        }
+        git_auto_commit(
+            &state,
+            &folder_path,
+            rewritten.iter().map(|(id, _)| rel_path_from_id(id)).collect(),
+            format!("Update links to {}", id),
+        );
+    }
+
+    // Assets only this note used go into its trash entry
+    if let (Some(content), Some(entry)) = (deleted_content.filter(|_| collect_assets), entry) {
+        let note_id = id.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            let notes = scan_notes(&folder_path);
+            let orphaned = trash::orphaned_assets(&folder_path, &note_id, &content, &notes, layout);
+            let Some(mut manifest) = trash::read_manifest(&entry) else {
+                return;
+            };
+            manifest.assets = trash::move_assets(&folder_path, &entry, &orphaned);
+            if !manifest.assets.is_empty() {
+                let _ = trash::write_manifest(&entry, &manifest);
+            }
+        })
+        .await;
+    }
 
     // Update search index
     {
         let index = state.search_index.lock().expect("search index mutex");
         if let Some(ref search_index) = *index {
-            let _ = search_index.index_note(&final_id, &display_title, &content, modified);
+            let _ = search_index.delete_note(&id);
         }
     }
+    state.link_index.remove(Path::new(&folder), &id);
 
-    Ok(Note {
-        id: final_id,
-        title: display_title,
-        content,
-        path: file_path.to_string_lossy().into_owned(),
-        modified,
-    })
-}
-
-/// Validate a relative folder path against traversal attacks
-const RESERVED_FOLDER_NAMES: &[&str] = &[".git", ".scratch", ".obsidian", ".trash", "assets"];
+    // Remove from cache
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        cache.remove(&id);
+    }
 
-fn validate_folder_path(path: &str) -> Result<(), String> {
-    if path.contains('\\') {
-        return Err("Invalid path: backslashes not allowed".to_string());
+    // Drop reading position, annotations and the star
+    let mut store = annotations::AnnotationStore::load(&folder);
+    if store.remove_note(&id) {
+        let _ = store.save(&folder);
     }
-    if path.is_empty() {
-        return Err("Path cannot be empty".to_string());
+    let mut stars = stars::StarStore::load(&folder);
+    if stars.remove_note(&id) {
+        let _ = stars.save(&folder);
     }
-    let rel = Path::new(path);
-    for component in rel.components() {
-        match component {
-            std::path::Component::ParentDir => {
-                return Err("Path traversal not allowed".to_string());
-            }
-            std::path::Component::CurDir => {
-                return Err("Invalid path: current directory references not allowed".to_string());
-            }
-            std::path::Component::RootDir | std::path::Component::Prefix(_) => {
-                return Err("Invalid path: absolute paths not allowed".to_string());
-            }
-            std::path::Component::Normal(name) => {
-                if let Some(name_str) = name.to_str() {
-                    if RESERVED_FOLDER_NAMES.contains(&name_str) {
-                        return Err(format!("'{}' is a reserved folder name", name_str));
-                    }
-                }
-            }
+
+    Ok(())
+}
+
+/// Rewrite the links to `target_id` in `notes` (a scan of the vault at
+/// `root`). Each note is rewritten under its lock from what is on disk then,
+/// not the scanned copy. Returns the rewritten notes' IDs and content.
+/// Blocking.
+fn rewrite_links_in_notes(
+    root: &Path,
+    note_locks: &locks::NoteLocks,
+    notes: &[ScannedNote],
+    target_id: &str,
+    resolver: &links::LinkResolver,
+    rewrite: &links::LinkRewrite,
+) -> Vec<(String, String)> {
+    let mut rewritten = Vec::new();
+    for note in notes {
+        if links::rewrite_links(&note.content, &note.id, target_id, resolver, rewrite).is_none() {
+            continue;
+        }
+        let Ok(path) = abs_path_from_id(root, &note.id) else {
+            continue;
+        };
+        let _guard = note_locks.blocking_lock(root, &[note.id.as_str()]);
+        let Some(updated) = std::fs::read_to_string(&path).ok().and_then(|content| {
+            links::rewrite_links(&content, &note.id, target_id, resolver, rewrite)
+        }) else {
+            continue;
+        };
+        if std::fs::write(&path, &updated).is_ok() {
+            rewritten.push((note.id.clone(), updated));
         }
     }
-    Ok(())
+    rewritten
+}
+
+/// Replace a note's title heading with `title`, adding one if the note
+/// starts with something else.
+fn retitle_content(content: &str, title: &str) -> String {
+    let body = frontmatter::body(content);
+    let head = &content[..content.len() - body.len()];
+    let leading = body.len() - body.trim_start().len();
+    let rest = &body[leading..];
+    let first_line = rest.split_inclusive('\n').next().unwrap_or_default();
+    if first_line.starts_with("# ") {
+        let newline = &first_line[first_line.trim_end_matches(['\r', '\n']).len()..];
+        format!(
+            "{}{}# {}{}{}",
+            head,
+            &body[..leading],
+            title,
+            newline,
+            &rest[first_line.len()..]
+        )
+    } else {
+        format!("{}# {}\n\n{}", head, title, body)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NoteRename {
+    note: Note,
+    updated_notes: Vec<String>, // notes whose links were rewritten
 }
 
+/// Retitle a note: set its heading to `new_title`, rename the file to match
+/// (with a `-n` suffix if the name is taken; stable filename schemes keep the
+/// file) and point every link to it at the new name.
 #[tauri::command]
-async fn list_folders(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+async fn rename_note(
+    app: AppHandle,
+    id: String,
+    new_title: String,
+    state: State<'_, AppState>,
+) -> Result<NoteRename, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
     let folder_path = PathBuf::from(&folder);
+    let new_title = new_title.trim().to_string();
+    if is_effectively_empty(&new_title) {
+        return Err(ScratchError::invalid("Title cannot be empty"));
+    }
+    let naming = {
+        let settings = state.settings.read().expect("settings read lock");
+        filenames::Naming::from_settings(&settings)
+    };
 
-    let fp = folder_path.clone();
-    tokio::task::spawn_blocking(move || {
-        let mut folders = Vec::new();
-        use walkdir::WalkDir;
-        for entry in WalkDir::new(&fp)
-            .max_depth(10)
-            .into_iter()
-            .filter_entry(is_visible_notes_entry)
-            .flatten()
+    // Write pending edits before the file is renamed
+    autosave::flush(&state, &id).await?;
+    let guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+
+    let old_path = abs_path_from_id(&folder_path, &id)?;
+    let content = fs::read_to_string(&old_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    let old_title = extract_title(&content);
+    let updated = retitle_content(&content, &new_title);
+
+    let leaf = naming.retitled_leaf(id.rsplit('/').next().unwrap_or(&id), &new_title);
+    let dir_prefix = id.rsplit_once('/').map(|(dir, _)| format!("{}/", dir));
+    let base_id = format!("{}{}", dir_prefix.unwrap_or_default(), leaf);
+    let (new_id, new_path) = claim_note_file(&folder_path, Some(id.as_str()), |attempt| {
+        match attempt {
+            0 => base_id.clone(),
+            n => format!("{}-{}", base_id, n),
+        }
+    })?;
+
+    if let Err(e) = recovery::write_atomic(&new_path, &updated).await {
+        if new_id != id {
+            let _ = fs::remove_file(&new_path).await;
+        }
+        return Err(e.into());
+    }
+    if new_id != id {
+        let _ = fs::remove_file(&old_path).await;
+    }
+
+    let modified = fs::metadata(&new_path)
+        .await
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.delete_note(&id);
+            let _ = search_index.index_note(&new_id, &new_title, &updated, modified);
+        }
+    }
+    state.link_index.remove(&folder_path, &id);
+    state.link_index.update(&folder_path, &new_id, &updated);
+    state.audit.record(
+        &folder_path,
+        audit::Change::new(audit::Surface::Ui, audit::Action::Rename, &id)
+            .new_id(&new_id)
+            .before(Some(content.as_bytes()))
+            .after(Some(updated.as_bytes())),
+    );
+
+    if new_id != id {
+        state.notes_cache.write().expect("cache write lock").remove(&id);
         {
-            if entry.file_type().is_dir() && entry.path() != fp {
-                if let Ok(rel) = entry.path().strip_prefix(&fp) {
-                    let rel_str = rel.to_string_lossy().replace('\\', "/");
-                    if !rel_str.is_empty() {
-                        folders.push(rel_str);
-                    }
+            let mut settings = state.settings.write().expect("settings write lock");
+            if let Some(ref mut pinned) = settings.pinned_note_ids {
+                for pin_id in pinned.iter_mut().filter(|pin_id| **pin_id == id) {
+                    *pin_id = new_id.clone();
                 }
             }
+            let _ = save_settings(&folder, &settings);
         }
-        folders.sort();
-        folders
+        let mut store = annotations::AnnotationStore::load(&folder);
+        if store.rename_note(&id, &new_id) {
+            let _ = store.save(&folder);
+        }
+        let mut stars = stars::StarStore::load(&folder);
+        if stars.rename_note(&id, &new_id) {
+            let _ = stars.save(&folder);
+        }
+        let _ = redirects::record_note(&folder_path, &id, &new_id);
+        let _ = history::rename(&folder_path, &id, &new_id);
+    }
+    snapshot_history(&app, &folder_path, &new_id, Some(content), updated.clone()).await;
+    let (message, files) = if new_id == id {
+        (format!("Update {}", id), vec![rel_path_from_id(&id)])
+    } else {
+        (
+            format!("Rename {} to {}", id, new_id),
+            vec![rel_path_from_id(&id), rel_path_from_id(&new_id)],
+        )
+    };
+    git_auto_commit(&state, &folder_path, files, message);
+    drop(guard);
+
+    let note = Note {
+        path: rel_path_from_id(&new_id),
+        id: new_id.clone(),
+        title: new_title.clone(),
+        content: updated,
+        modified,
+    };
+    if new_id == id && old_title == new_title {
+        return Ok(NoteRename {
+            note,
+            updated_notes: Vec::new(),
+        });
+    }
+
+    // Point links at the new name. The old ID and title go first so links
+    // written against them still resolve to this note.
+    let root = folder_path.clone();
+    let (old_id, target_id, title) = (id.clone(), new_id.clone(), new_title.clone());
+    let note_locks = state.note_locks.clone();
+    let rewritten = tokio::task::spawn_blocking(move || {
+        let notes = scan_notes(&root);
+        let current =
+            links::LinkResolver::new(notes.iter().map(|n| (n.id.as_str(), n.title.as_str())));
+        let by_title = links::Link {
+            target: title.clone(),
+            kind: links::LinkKind::Wiki,
+        };
+        // Wikilinks use the new title unless it would resolve to another note
+        let wiki = if current.resolve(&by_title, "").as_deref() == Some(target_id.as_str()) {
+            title
+        } else {
+            target_id.clone()
+        };
+        let resolver = links::LinkResolver::new(
+            std::iter::once((old_id.as_str(), old_title.as_str()))
+                .chain(notes.iter().map(|n| (n.id.as_str(), n.title.as_str()))),
+        );
+        let rewrite = links::LinkRewrite::Rename {
+            to: target_id,
+            wiki,
+        };
+        rewrite_links_in_notes(&root, &note_locks, &notes, &old_id, &resolver, &rewrite)
     })
     .await
-    .map_err(|e| format!("Failed to list folders: {}", e))
+    .map_err(|e| e.to_string())?;
+
+    {
+        let modified = chrono::Utc::now().timestamp();
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            for (id, content) in &rewritten {
+                let _ = search_index.index_note(id, &extract_title(content), content, modified);
+            }
+        }
+    }
+    for (id, content) in &rewritten {
+        state.link_index.update(&folder_path, id, content);
+    }
+    git_auto_commit(
+        &state,
+        &folder_path,
+        rewritten.iter().map(|(id, _)| rel_path_from_id(id)).collect(),
+        format!("Update links to {}", new_id),
+    );
+
+    // The note's own links to itself may have been rewritten too
+    let mut note = note;
+    if let Some((_, content)) = rewritten.iter().find(|(id, _)| *id == note.id) {
+        note.content = content.clone();
+    }
+    Ok(NoteRename {
+        note,
+        updated_notes: rewritten.into_iter().map(|(id, _)| id).collect(),
+    })
 }
 
+/// Notes and assets in the trash, most recently deleted first.
 #[tauri::command]
-async fn create_folder(path: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn list_trash(state: State<'_, AppState>) -> Result<Vec<trash::TrashItem>, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
-
-    validate_folder_path(&path)?;
-
-    let target = PathBuf::from(&folder).join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
-
-    if !target.starts_with(&folder) {
-        return Err("Invalid path: escapes notes folder".to_string());
-    }
-
-    fs::create_dir_all(&target)
+    tokio::task::spawn_blocking(move || trash::list(Path::new(&folder)))
         .await
-        .map_err(|e| e.to_string())?;
-
-    Ok(())
+        .map_err(ScratchError::from)
 }
 
+/// Put a trash entry back. Returns the restored note, or None for an entry
+/// that held only assets.
 #[tauri::command]
-async fn delete_folder(path: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn restore_note(
+    entry: String,
+    state: State<'_, AppState>,
+) -> Result<Option<Note>, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
+    let folder_path = PathBuf::from(&folder);
 
-    validate_folder_path(&path)?;
-
-    let target = PathBuf::from(&folder).join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
-
-    if !target.starts_with(&folder) {
-        return Err("Invalid path: escapes notes folder".to_string());
-    }
+    let root = folder_path.clone();
+    let restored = tokio::task::spawn_blocking(move || trash::restore(&root, &entry))
+        .await
+        .map_err(|e| e.to_string())??;
+    let Some(id) = restored else {
+        return Ok(None);
+    };
 
-    if !target.is_dir() {
-        return Err("Path is not a directory".to_string());
-    }
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    let title = extract_title(&content);
+    let modified = fs::metadata(&file_path)
+        .await
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
 
-    // Remove notes from search index
     {
         let index = state.search_index.lock().expect("search index mutex");
         if let Some(ref search_index) = *index {
-            let cache = state.notes_cache.read().expect("cache read lock");
-            let prefix = format!("{}/", path);
-            for note_id in cache.keys() {
-                if note_id.starts_with(&prefix) {
-                    let _ = search_index.delete_note(note_id);
-                }
-            }
+            let _ = search_index.index_note(&id, &title, &content, modified);
         }
     }
+    state.link_index.update(&folder_path, &id, &content);
+    state.audit.record(
+        &folder_path,
+        audit::Change::new(audit::Surface::Ui, audit::Action::Restore, &id)
+            .after(Some(content.as_bytes())),
+    );
 
-    // Remove notes from cache
-    {
-        let mut cache = state.notes_cache.write().expect("cache write lock");
-        let prefix = format!("{}/", path);
-        cache.retain(|id, _| !id.starts_with(&prefix));
-    }
+    Ok(Some(Note {
+        path: rel_path_from_id(&id),
+        id,
+        title,
+        content,
+        modified,
+    }))
+}
 
-    fs::remove_dir_all(&target)
-        .await
-        .map_err(|e| e.to_string())?;
+/// Permanently delete everything in the trash. Returns the number of entries
+/// removed.
+#[tauri::command]
+async fn empty_trash(state: State<'_, AppState>) -> Result<usize, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    tokio::task::spawn_blocking(move || trash::empty(Path::new(&folder)))
+        .await?
+        .map_err(ScratchError::from)
+}
 
-    Ok(())
+/// Where a vault's restore points are kept: a directory of its own in app
+/// data, so they are never synced or committed with the vault. Points older
+/// versions left in the vault are moved there.
+fn restore_points_dir(app: &AppHandle, folder: &str) -> Result<PathBuf, ScratchError> {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(folder.as_bytes());
+    let name: String = digest
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let dir = app.path().app_data_dir()?.join("restore-points").join(name);
+    restore::adopt_legacy(Path::new(folder), &dir);
+    Ok(dir)
+}
+
+/// Refuse to take a restore point while disk space is critically low.
+fn allow_restore_point(app: &AppHandle) -> Result<(), ScratchError> {
+    disk::allow_background_write(app).map_err(|message| ScratchError::DiskFull {
+        path: None,
+        message,
+    })
+}
+
+/// Snapshot every note into a restore point named `name`.
+async fn new_restore_point(
+    app: &AppHandle,
+    folder: &str,
+    name: String,
+) -> Result<restore::RestorePoint, ScratchError> {
+    allow_restore_point(app)?;
+    let root = PathBuf::from(folder);
+    let dir = restore_points_dir(app, folder)?;
+    tokio::task::spawn_blocking(move || restore::create(&root, &dir, &name))
+        .await?
+        .map_err(ScratchError::from)
+}
+
+/// Take a restore point before an operation that changes many notes, so
+/// `rollback_to` can undo it. The operation should not go ahead if this fails.
+async fn take_restore_point(
+    app: &AppHandle,
+    folder: &str,
+    name: String,
+) -> Result<(), ScratchError> {
+    new_restore_point(app, folder, name).await.map(|_| ())
 }
 
+/// Snapshot every note into a named restore point.
 #[tauri::command]
-async fn rename_folder(
-    old_path: String,
-    new_name: String,
+async fn create_restore_point(
+    app: AppHandle,
+    name: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<restore::RestorePoint, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
-
-    validate_folder_path(&old_path)?;
-
-    // Sanitize new name (no slashes allowed in the name itself)
-    let sanitized_name = new_name
-        .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "-")
-        .trim()
-        .to_string();
-    if sanitized_name.is_empty() {
-        return Err("Folder name cannot be empty".to_string());
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ScratchError::invalid("Restore point name cannot be empty"));
     }
+    new_restore_point(&app, &folder, name).await
+}
 
-    let folder_root = PathBuf::from(&folder);
-    let old_target = folder_root.join(old_path.replace('/', std::path::MAIN_SEPARATOR_STR));
+/// Restore points in the vault, newest first.
+#[tauri::command]
+async fn list_restore_points(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<restore::RestorePoint>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let dir = restore_points_dir(&app, &folder)?;
+    tokio::task::spawn_blocking(move || restore::list(&dir))
+        .await
+        .map_err(ScratchError::from)
+}
 
-    if !old_target.starts_with(&folder_root) {
-        return Err("Invalid path: escapes notes folder".to_string());
+/// Put every note back as it was in restore point `point`. Notes created
+/// since go to the trash, and the current state is kept as a new restore
+/// point first. Each rewritten note is audited and kept in version history.
+#[tauri::command]
+async fn rollback_to(
+    app: AppHandle,
+    point: String,
+    state: State<'_, AppState>,
+) -> Result<restore::RollbackReport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    allow_restore_point(&app)?;
+    let dir = restore_points_dir(&app, &folder)?;
+    let _guard = state.note_locks.lock_vault().await;
+    let root = PathBuf::from(&folder);
+    let message = format!("Roll back to {}", point);
+    let report = tokio::task::spawn_blocking(move || restore::rollback(&root, &dir, &point))
+        .await?
+        .map_err(ScratchError::from)?;
+
+    let folder_path = PathBuf::from(&folder);
+    let mut files = Vec::new();
+    for note in &report.rewritten {
+        let Some(id) = note.rel.strip_suffix(".md") else {
+            continue;
+        };
+        let action = if note.before.is_some() {
+            audit::Action::Save
+        } else {
+            audit::Action::Create
+        };
+        state.audit.record(
+            &folder_path,
+            audit::Change::new(audit::Surface::Ui, action, id)
+                .before(note.before.as_deref())
+                .after(Some(note.after.as_slice())),
+        );
+        let previous = note
+            .before
+            .as_ref()
+            .and_then(|b| String::from_utf8(b.clone()).ok());
+        if let Ok(content) = String::from_utf8(note.after.clone()) {
+            snapshot_history(&app, &folder_path, id, previous, content).await;
+        }
+        files.push(note.rel.clone());
     }
-    if !old_target.is_dir() {
-        return Err("Path is not a directory".to_string());
+    for id in &report.trashed {
+        state.audit.record(
+            &folder_path,
+            audit::Change::new(audit::Surface::Ui, audit::Action::Delete, id),
+        );
+        files.push(rel_path_from_id(id));
     }
+    git_auto_commit(&state, &folder_path, files, message);
 
-    // Build new path: same parent, new name
-    let new_target = old_target
-        .parent()
-        .ok_or("Cannot determine parent directory")?
-        .join(&sanitized_name);
-
-    if new_target.exists() {
-        return Err("A folder with that name already exists".to_string());
+    state.notes_cache.write().expect("cache write lock").clear();
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.rebuild_index(&PathBuf::from(&folder));
+        }
     }
+    state.link_index.invalidate();
 
-    // Compute old and new path prefixes for updating IDs
-    let old_prefix = format!("{}/", old_path);
-    let new_path = if old_path.contains('/') {
-        let parent = &old_path[..old_path.rfind('/').unwrap()];
-        format!("{}/{}", parent, sanitized_name)
-    } else {
-        sanitized_name.clone()
+    Ok(report)
+}
+
+/// Saved versions of a note, newest first.
+#[tauri::command]
+async fn list_note_versions(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<history::NoteVersion>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
-    let new_prefix = format!("{}/", new_path);
+    tokio::task::spawn_blocking(move || history::list(Path::new(&folder), &id))
+        .await?
+        .map_err(ScratchError::from)
+}
 
-    // Rename on disk
-    tokio::fs::rename(&old_target, &new_target)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Update pinned note IDs in settings
-    {
-        let mut settings = state.settings.write().expect("settings write lock");
-        if let Some(ref mut pinned) = settings.pinned_note_ids {
-            for id in pinned.iter_mut() {
-                if id.starts_with(&old_prefix) {
-                    *id = format!("{}{}", new_prefix, &id[old_prefix.len()..]);
-                } else if *id == old_path {
-                    *id = new_path.clone();
-                }
-            }
-        }
-        // Save settings
-        let _ = save_settings(&folder, &settings);
-    }
-
-    // Update cache
-    {
-        let mut cache = state.notes_cache.write().expect("cache write lock");
-        let updates: Vec<(String, String)> = cache
-            .keys()
-            .filter(|id| id.starts_with(&old_prefix))
-            .map(|id| {
-                let new_id = format!("{}{}", new_prefix, &id[old_prefix.len()..]);
-                (id.clone(), new_id)
-            })
-            .collect();
-        for (old_id, new_id) in updates {
-            if let Some(mut meta) = cache.remove(&old_id) {
-                meta.id = new_id.clone();
-                cache.insert(new_id, meta);
-            }
-        }
-    }
-
-    // Rebuild search index for affected notes
-    {
-        let index = state.search_index.lock().expect("search index mutex");
-        if let Some(ref search_index) = *index {
-            let _ = search_index.rebuild_index(&folder_root);
-        }
-    }
+/// Content of a note as saved at version `ts` (from `list_note_versions`).
+#[tauri::command]
+async fn read_note_version(
+    id: String,
+    ts: i64,
+    state: State<'_, AppState>,
+) -> Result<String, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    tokio::task::spawn_blocking(move || history::read(Path::new(&folder), &id, ts))
+        .await?
+        .map_err(ScratchError::from)
+}
 
-    Ok(())
+/// Disk used by version history (stored whole vs. as deltas) and drafts.
+#[tauri::command]
+async fn get_history_storage_stats(
+    state: State<'_, AppState>,
+) -> Result<history::HistoryStats, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    tokio::task::spawn_blocking(move || history::storage_stats(Path::new(&folder)))
+        .await
+        .map_err(ScratchError::from)
 }
 
 #[tauri::command]
-async fn move_note(
-    id: String,
-    target_folder: String,
+async fn create_note(
+    target_folder: Option<String>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<Note, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
-    let folder_root = PathBuf::from(&folder);
-    let source_path = abs_path_from_id(&folder_root, &id)?;
+    let folder_path = PathBuf::from(&folder);
 
-    if !source_path.exists() {
-        return Err("Note not found".to_string());
-    }
+    // Get template from settings (default "Untitled")
+    let (template, naming) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            settings
+                .default_note_name
+                .clone()
+                .unwrap_or_else(|| "Untitled".to_string()),
+            filenames::Naming::from_settings(&settings),
+        )
+    };
 
-    // Extract the filename (leaf) from the note ID
-    let leaf = id.rsplit('/').next().unwrap_or(&id);
+    // Expand template tags
+    let expanded = expand_note_name_template(&template);
 
-    // Build new ID
-    let new_id = if target_folder.is_empty() {
-        leaf.to_string()
+    // Sanitize filename
+    let sanitized = sanitize_filename(&expanded);
+
+    // Prepend folder prefix if specified
+    let sanitized = if let Some(ref folder_prefix) = target_folder {
+        if folder_prefix.is_empty() {
+            sanitized
+        } else {
+            format!("{}/{}", folder_prefix.trim_end_matches('/'), sanitized)
+        }
     } else {
-        validate_folder_path(&target_folder)?;
-        format!("{}/{}", target_folder, leaf)
+        sanitized
     };
 
-    if new_id == id {
-        return Ok(id);
-    }
+    // Handle {counter} tag
+    let has_counter = template.contains("{counter}");
+    let title_id = |attempt: usize| {
+        if has_counter {
+            sanitized.replace("{counter}", &(attempt + 1).to_string())
+        } else if attempt == 0 {
+            sanitized.clone()
+        } else {
+            format!("{}-{}", sanitized, attempt)
+        }
+    };
 
-    let dest_path = abs_path_from_id(&folder_root, &new_id)?;
+    // Claim a unique file atomically (also creates parent directories for
+    // templates like {year}/{month}/{day})
+    let (final_id, file_path, display_title) = if naming.is_plain_title() {
+        let (id, path) = claim_note_file(&folder_path, None, title_id)?;
+        // Extract display title from filename
+        let title = extract_title_from_id(&id);
+        (id, path, title)
+    } else {
+        // Other naming settings name the file independently; the title stays in the heading
+        let first_id = title_id(0);
+        let display_title = extract_title_from_id(&first_id);
+        let dir_prefix = first_id.rsplit_once('/').map(|(dir, _)| format!("{}/", dir));
+        let base_id = format!(
+            "{}{}",
+            dir_prefix.unwrap_or_default(),
+            naming.new_leaf(&display_title, chrono::Local::now())
+        );
+        let (id, path) = claim_note_file(&folder_path, None, |attempt| match attempt {
+            0 => base_id.clone(),
+            n => format!("{}-{}", base_id, n),
+        })?;
+        (id, path, display_title)
+    };
 
-    // Ensure target directory exists
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    let content = stamp_created(format!("# {}\n\n", display_title), &file_path);
+    if let Err(e) = fs::write(&file_path, &content).await {
+        let _ = fs::remove_file(&file_path).await;
+        return Err(ScratchError::io(e, &file_path));
     }
 
-    // Handle collision
-    if dest_path.exists() {
-        return Err("A note with that name already exists in the target folder".to_string());
+    let modified = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    // Update search index
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(&final_id, &display_title, &content, modified);
+        }
     }
+    state.link_index.update(&folder_path, &final_id, &content);
 
-    tokio::fs::rename(&source_path, &dest_path)
-        .await
-        .map_err(|e| e.to_string())?;
+    state.audit.record(
+        &folder_path,
+        audit::Change::new(audit::Surface::Ui, audit::Action::Create, &final_id)
+            .after(Some(content.as_bytes())),
+    );
 
-    // Update pinned note IDs
-    {
-        let mut settings = state.settings.write().expect("settings write lock");
-        if let Some(ref mut pinned) = settings.pinned_note_ids {
-            for pin_id in pinned.iter_mut() {
-                if *pin_id == id {
-                    *pin_id = new_id.clone();
+    Ok(Note {
+        path: rel_path_from_id(&final_id),
+        id: final_id,
+        title: display_title,
+        content,
+        modified,
+    })
+}
+
+/// Validate a relative folder path against traversal attacks
+const RESERVED_FOLDER_NAMES: &[&str] = &[
+    ".git",
+    ".scratch",
+    ".obsidian",
+    ".trash",
+    history::HISTORY_DIR,
+    "assets",
+];
+
+fn validate_folder_path(path: &str) -> Result<(), String> {
+    if path.contains('\\') {
+        return Err("Invalid path: backslashes not allowed".to_string());
+    }
+    if path.is_empty() {
+        return Err("Path cannot be empty".to_string());
+    }
+    let rel = Path::new(path);
+    for component in rel.components() {
+        match component {
+            std::path::Component::ParentDir => {
+                return Err("Path traversal not allowed".to_string());
+            }
+            std::path::Component::CurDir => {
+                return Err("Invalid path: current directory references not allowed".to_string());
+            }
+            std::path::Component::RootDir | std::path::Component::Prefix(_) => {
+                return Err("Invalid path: absolute paths not allowed".to_string());
+            }
+            std::path::Component::Normal(name) => {
+                if let Some(name_str) = name.to_str() {
+                    if RESERVED_FOLDER_NAMES.contains(&name_str) {
+                        return Err(format!("'{}' is a reserved folder name", name_str));
+                    }
                 }
             }
         }
-        let _ = save_settings(&folder, &settings);
     }
+    Ok(())
+}
 
-    // Update cache
-    {
-        let mut cache = state.notes_cache.write().expect("cache write lock");
-        if let Some(mut meta) = cache.remove(&id) {
-            meta.id = new_id.clone();
-            cache.insert(new_id.clone(), meta);
-        }
-    }
+#[tauri::command]
+async fn list_folders(state: State<'_, AppState>) -> Result<Vec<String>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
 
-    // Rebuild search index
-    {
-        let index = state.search_index.lock().expect("search index mutex");
-        if let Some(ref search_index) = *index {
-            let _ = search_index.rebuild_index(&folder_root);
+    let fp = folder_path.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut folders = Vec::new();
+        use walkdir::WalkDir;
+        for entry in WalkDir::new(&fp)
+            .max_depth(10)
+            .into_iter()
+            .filter_entry(is_visible_notes_entry)
+            .flatten()
+        {
+            if entry.file_type().is_dir() && entry.path() != fp {
+                if let Ok(rel) = entry.path().strip_prefix(&fp) {
+                    let rel_str = rel.to_string_lossy().replace('\\', "/");
+                    if !rel_str.is_empty() {
+                        folders.push(rel_str);
+                    }
+                }
+            }
         }
+        folders.sort();
+        folders
+    })
+    .await
+    .map_err(|e| format!("Failed to list folders: {}", e).into())
+}
+
+#[tauri::command]
+async fn create_folder(path: String, state: State<'_, AppState>) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    validate_folder_path(&path)?;
+
+    let target = PathBuf::from(&folder).join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
+
+    if !target.starts_with(&folder) {
+        return Err(ScratchError::invalid("Invalid path: escapes notes folder"));
     }
 
-    Ok(new_id)
+    fs::create_dir_all(&target)
+        .await
+        .map_err(|e| ScratchError::io(e, &target))?;
+
+    Ok(())
 }
 
 #[tauri::command]
-async fn move_folder(
+async fn delete_folder(
+    app: AppHandle,
     path: String,
-    target_parent: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
 
     validate_folder_path(&path)?;
-    if !target_parent.is_empty() {
-        validate_folder_path(&target_parent)?;
-    }
 
-    let folder_root = PathBuf::from(&folder);
-    let source = folder_root.join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
+    let target = PathBuf::from(&folder).join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
 
-    if !source.is_dir() {
-        return Err("Source is not a directory".to_string());
+    if !target.starts_with(&folder) {
+        return Err(ScratchError::invalid("Invalid path: escapes notes folder"));
     }
 
-    // Get folder name
-    let name = source
-        .file_name()
-        .ok_or("Cannot determine folder name")?
-        .to_string_lossy()
-        .to_string();
+    if !target.is_dir() {
+        return Err(ScratchError::not_found("Path is not a directory"));
+    }
+    let _guard = state.note_locks.lock_vault().await;
+    take_restore_point(&app, &folder, format!("Before deleting folder {}", path)).await?;
 
-    let dest = if target_parent.is_empty() {
-        folder_root.join(&name)
-    } else {
-        folder_root
-            .join(target_parent.replace('/', std::path::MAIN_SEPARATOR_STR))
-            .join(&name)
+    // Remove notes from search index. Walk the folder rather than trusting the
+    // notes cache, which may have evicted some of them.
+    {
+        let root = PathBuf::from(&folder);
+        let note_ids: Vec<String> = walkdir::WalkDir::new(&target)
+            .into_iter()
+            .filter_entry(is_visible_notes_entry)
+            .flatten()
+            .filter_map(|entry| id_from_abs_path(&root, entry.path()))
+            .collect();
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            for note_id in &note_ids {
+                let _ = search_index.delete_note(note_id);
+            }
+        }
+    }
+
+    // Remove notes from cache
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        let prefix = format!("{}/", path);
+        cache.retain(|id, _| !id.starts_with(&prefix));
+    }
+
+    fs::remove_dir_all(&target)
+        .await
+        .map_err(|e| ScratchError::io(e, &target))?;
+    state.link_index.invalidate();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn rename_folder(
+    old_path: String,
+    new_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
 
-    // Prevent moving into itself
-    if dest.starts_with(&source) {
-        return Err("Cannot move a folder into itself".to_string());
+    validate_folder_path(&old_path)?;
+
+    // Sanitize new name (no slashes allowed in the name itself)
+    let sanitized_name = new_name
+        .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "-")
+        .trim()
+        .to_string();
+    if sanitized_name.is_empty() {
+        return Err(ScratchError::invalid("Folder name cannot be empty"));
     }
 
-    if dest.exists() {
-        return Err("A folder with that name already exists in the target".to_string());
+    let folder_root = PathBuf::from(&folder);
+    let old_target = folder_root.join(old_path.replace('/', std::path::MAIN_SEPARATOR_STR));
+
+    if !old_target.starts_with(&folder_root) {
+        return Err(ScratchError::invalid("Invalid path: escapes notes folder"));
+    }
+    if !old_target.is_dir() {
+        return Err(ScratchError::not_found("Path is not a directory"));
     }
 
-    // Ensure target parent exists
-    if let Some(parent) = dest.parent() {
-        fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+    // Build new path: same parent, new name
+    let new_target = old_target
+        .parent()
+        .ok_or("Cannot determine parent directory")?
+        .join(&sanitized_name);
+
+    if new_target.exists() {
+        return Err(ScratchError::already_exists(
+            "A folder with that name already exists",
+        ));
     }
 
     // Compute old and new path prefixes for updating IDs
-    let old_prefix = format!("{}/", path);
-    let new_path = if target_parent.is_empty() {
-        name.clone()
+    let old_prefix = format!("{}/", old_path);
+    let new_path = if old_path.contains('/') {
+        let parent = &old_path[..old_path.rfind('/').unwrap()];
+        format!("{}/{}", parent, sanitized_name)
     } else {
-        format!("{}/{}", target_parent, name)
+        sanitized_name.clone()
     };
     let new_prefix = format!("{}/", new_path);
 
-    tokio::fs::rename(&source, &dest)
+    // Rename on disk
+    let _guard = state.note_locks.lock_vault().await;
+    tokio::fs::rename(&old_target, &new_target)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| ScratchError::io(e, &old_target))?;
 
-    // Update pinned note IDs
+    // Update pinned note IDs in settings
     {
         let mut settings = state.settings.write().expect("settings write lock");
         if let Some(ref mut pinned) = settings.pinned_note_ids {
-            for pin_id in pinned.iter_mut() {
-                if pin_id.starts_with(&old_prefix) {
-                    *pin_id = format!("{}{}", new_prefix, &pin_id[old_prefix.len()..]);
+            for id in pinned.iter_mut() {
+                if id.starts_with(&old_prefix) {
+                    *id = format!("{}{}", new_prefix, &id[old_prefix.len()..]);
+                } else if *id == old_path {
+                    *id = new_path.clone();
                 }
             }
         }
+        // Save settings
         let _ = save_settings(&folder, &settings);
     }
+    let mut stars = stars::StarStore::load(&folder);
+    if stars.rename_folder(&old_path, &new_path) {
+        let _ = stars.save(&folder);
+    }
 
     // Update cache
     {
@@ -1645,696 +3879,5208 @@ async fn move_folder(
         }
     }
 
-    // Rebuild search index
+    let _ = redirects::record_folder(&folder_root, &old_path, &new_path);
+    let _ = history::rename_folder(&folder_root, &old_path, &new_path);
+
+    // Rebuild search index for affected notes
     {
         let index = state.search_index.lock().expect("search index mutex");
         if let Some(ref search_index) = *index {
             let _ = search_index.rebuild_index(&folder_root);
         }
     }
+    state.link_index.invalidate();
 
     Ok(())
 }
 
 #[tauri::command]
-fn get_settings(state: State<AppState>) -> Settings {
-    state.settings.read().expect("settings read lock").clone()
-}
-
-#[tauri::command]
-fn update_settings(
-    new_settings: Settings,
-    state: State<AppState>,
-) -> Result<(), String> {
+async fn move_note(
+    id: String,
+    target_folder: String,
+    state: State<'_, AppState>,
+) -> Result<String, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
-        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
+    let folder_root = PathBuf::from(&folder);
+    let source_path = abs_path_from_id(&folder_root, &id)?;
 
-    {
-        let mut settings = state.settings.write().expect("settings write lock");
-        *settings = new_settings;
+    if !source_path.exists() {
+        return Err(ScratchError::NoteNotFound { id });
     }
 
-    let settings = state.settings.read().expect("settings read lock");
-    save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+    // Write pending edits before the file moves
+    autosave::flush(&state, &id).await?;
 
-    Ok(())
-}
+    // Extract the filename (leaf) from the note ID
+    let leaf = id.rsplit('/').next().unwrap_or(&id);
 
-#[tauri::command]
-fn update_git_enabled(
-    enabled: Option<bool>,
-    expected_folder: String,
-    state: State<AppState>,
-) -> Result<(), String> {
-    let folder = {
-        let app_config = state.app_config.read().expect("app_config read lock");
-        let folder = app_config.notes_folder.clone().ok_or("Notes folder not set")?;
+    // Build new ID
+    let new_id = if target_folder.is_empty() {
+        leaf.to_string()
+    } else {
+        validate_folder_path(&target_folder)?;
+        format!("{}/{}", target_folder, leaf)
+    };
 
-        if folder != expected_folder {
-            return Err("Notes folder changed".to_string());
-        }
+    if new_id == id {
+        return Ok(id);
+    }
 
-        folder
-    };
+    let dest_path = abs_path_from_id(&folder_root, &new_id)?;
+    let _guard = state.note_locks.lock(&folder_root, &[id.as_str(), new_id.as_str()]).await;
+    if !source_path.exists() {
+        return Err(ScratchError::NoteNotFound { id });
+    }
+
+    // Ensure target directory exists
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| ScratchError::io(e, parent))?;
+    }
+
+    // Handle collision
+    if dest_path.exists() {
+        return Err(ScratchError::already_exists(
+            "A note with that name already exists in the target folder",
+        ));
+    }
+
+    tokio::fs::rename(&source_path, &dest_path)
+        .await
+        .map_err(|e| ScratchError::io(e, &source_path))?;
+
+    let moved = fs::read(&dest_path).await.ok();
+    state.audit.record(
+        &folder_root,
+        audit::Change::new(audit::Surface::Ui, audit::Action::Move, &id)
+            .new_id(&new_id)
+            .before(moved.as_deref())
+            .after(moved.as_deref()),
+    );
 
+    // Update pinned note IDs
     {
         let mut settings = state.settings.write().expect("settings write lock");
-        settings.git_enabled = enabled;
+        if let Some(ref mut pinned) = settings.pinned_note_ids {
+            for pin_id in pinned.iter_mut() {
+                if *pin_id == id {
+                    *pin_id = new_id.clone();
+                }
+            }
+        }
+        let _ = save_settings(&folder, &settings);
     }
 
-    let settings = state.settings.read().expect("settings read lock");
-    save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+    // Update cache
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        if let Some(mut meta) = cache.remove(&id) {
+            meta.id = new_id.clone();
+            cache.insert(new_id.clone(), meta);
+        }
+    }
 
-    Ok(())
+    // Keep reading position, annotations and the star with the moved note
+    let mut store = annotations::AnnotationStore::load(&folder);
+    if store.rename_note(&id, &new_id) {
+        let _ = store.save(&folder);
+    }
+    let mut stars = stars::StarStore::load(&folder);
+    if stars.rename_note(&id, &new_id) {
+        let _ = stars.save(&folder);
+    }
+    let _ = redirects::record_note(&folder_root, &id, &new_id);
+    let _ = history::rename(&folder_root, &id, &new_id);
+
+    // Rebuild search index
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.rebuild_index(&folder_root);
+        }
+    }
+    state.link_index.invalidate();
+
+    Ok(new_id)
 }
 
+/// Recorded note changes matching `filter`, newest first.
 #[tauri::command]
-async fn write_file(path: String, contents: Vec<u8>) -> Result<(), String> {
-    fs::write(&path, contents)
-        .await
-        .map_err(|_| "Failed to write file".to_string())
+async fn get_audit_log(
+    filter: Option<audit::AuditFilter>,
+    app: AppHandle,
+) -> Result<Vec<audit::AuditEntry>, ScratchError> {
+    tokio::task::spawn_blocking(move || {
+        app.state::<AppState>()
+            .audit
+            .read(&filter.unwrap_or_default())
+    })
+    .await
+    .map_err(ScratchError::from)
 }
 
 #[tauri::command]
-fn preview_note_name(template: String) -> Result<String, String> {
-    let expanded = expand_note_name_template(&template);
-    let sanitized = sanitize_filename(&expanded);
-
-    // Show first note name (with counter as 1 if present)
-    let preview = if template.contains("{counter}") {
-        sanitized.replace("{counter}", "1")
-    } else {
-        sanitized
+async fn move_folder(
+    path: String,
+    target_parent: String,
+    state: State<'_, AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
 
-    Ok(preview)
-}
-
-// Preview mode: file content returned by read_file_direct / save_file_direct
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FileContent {
-    pub path: String,
-    pub content: String,
-    pub title: String,
-    pub modified: i64,
-}
+    validate_folder_path(&path)?;
+    if !target_parent.is_empty() {
+        validate_folder_path(&target_parent)?;
+    }
 
-/// Validate a file path for preview mode direct file operations.
-/// Ensures the path is a markdown file and resolves symlinks.
-fn validate_preview_path(path: &str) -> Result<PathBuf, String> {
-    let file_path = PathBuf::from(path);
+    let folder_root = PathBuf::from(&folder);
+    let source = folder_root.join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
 
-    // Must have a markdown extension
-    match file_path.extension().and_then(|e| e.to_str()) {
-        Some(ext) if ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown") => {}
-        _ => return Err("Only .md and .markdown files are allowed".to_string()),
+    if !source.is_dir() {
+        return Err(ScratchError::not_found("Source is not a directory"));
     }
 
-    // Resolve symlinks to get the real path
-    let canonical = file_path
-        .canonicalize()
-        .map_err(|e| format!("Cannot resolve file path: {}", e))?;
+    // Get folder name
+    let name = source
+        .file_name()
+        .ok_or("Cannot determine folder name")?
+        .to_string_lossy()
+        .to_string();
 
-    Ok(canonical)
-}
+    let dest = if target_parent.is_empty() {
+        folder_root.join(&name)
+    } else {
+        folder_root
+            .join(target_parent.replace('/', std::path::MAIN_SEPARATOR_STR))
+            .join(&name)
+    };
 
-#[tauri::command]
-async fn read_file_direct(path: String) -> Result<FileContent, String> {
-    let canonical = validate_preview_path(&path)?;
+    // Prevent moving into itself
+    if dest.starts_with(&source) {
+        return Err(ScratchError::invalid("Cannot move a folder into itself"));
+    }
 
-    if !canonical.is_file() {
-        return Err(format!("Not a file: {}", path));
+    if dest.exists() {
+        return Err(ScratchError::already_exists(
+            "A folder with that name already exists in the target",
+        ));
     }
 
-    let content = fs::read_to_string(&canonical)
-        .await
-        .map_err(|_| "Failed to read file".to_string())?;
-    let metadata = fs::metadata(&canonical)
-        .await
-        .map_err(|_| "Failed to read metadata".to_string())?;
+    // Ensure target parent exists
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| ScratchError::io(e, parent))?;
+    }
 
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+    // Compute old and new path prefixes for updating IDs
+    let old_prefix = format!("{}/", path);
+    let new_path = if target_parent.is_empty() {
+        name.clone()
+    } else {
+        format!("{}/{}", target_parent, name)
+    };
+    let new_prefix = format!("{}/", new_path);
 
-    let title = extract_title(&content);
+    let _guard = state.note_locks.lock_vault().await;
+    tokio::fs::rename(&source, &dest)
+        .await
+        .map_err(|e| ScratchError::io(e, &source))?;
 
-    Ok(FileContent {
-        path,
-        content,
-        title,
-        modified,
-    })
-}
+    // Update pinned note IDs
+    {
+        let mut settings = state.settings.write().expect("settings write lock");
+        if let Some(ref mut pinned) = settings.pinned_note_ids {
+            for pin_id in pinned.iter_mut() {
+                if pin_id.starts_with(&old_prefix) {
+                    *pin_id = format!("{}{}", new_prefix, &pin_id[old_prefix.len()..]);
+                }
+            }
+        }
+        let _ = save_settings(&folder, &settings);
+    }
+    let mut stars = stars::StarStore::load(&folder);
+    if stars.rename_folder(&path, &new_path) {
+        let _ = stars.save(&folder);
+    }
 
-#[tauri::command]
-async fn save_file_direct(path: String, content: String) -> Result<FileContent, String> {
-    // For save, the file must already exist (we validate extension + path security)
-    let canonical = validate_preview_path(&path)?;
+    // Update cache
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        let updates: Vec<(String, String)> = cache
+            .keys()
+            .filter(|id| id.starts_with(&old_prefix))
+            .map(|id| {
+                let new_id = format!("{}{}", new_prefix, &id[old_prefix.len()..]);
+                (id.clone(), new_id)
+            })
+            .collect();
+        for (old_id, new_id) in updates {
+            if let Some(mut meta) = cache.remove(&old_id) {
+                meta.id = new_id.clone();
+                cache.insert(new_id, meta);
+            }
+        }
+    }
 
-    if !canonical.is_file() {
-        return Err(format!("Not a file: {}", path));
+    let _ = redirects::record_folder(&folder_root, &path, &new_path);
+    let _ = history::rename_folder(&folder_root, &path, &new_path);
+
+    // Rebuild search index
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.rebuild_index(&folder_root);
+        }
     }
+    state.link_index.invalidate();
 
-    fs::write(&canonical, &content)
-        .await
-        .map_err(|_| "Failed to write file".to_string())?;
+    Ok(())
+}
 
-    let metadata = fs::metadata(&canonical)
-        .await
-        .map_err(|_| "Failed to read metadata".to_string())?;
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+/// Settings as the webview sees them, without the passphrase hash.
+fn settings_for_webview(settings: &Settings) -> Settings {
+    Settings {
+        privacy_passphrase_hash: None,
+        ..settings.clone()
+    }
+}
 
-    let title = extract_title(&content);
+/// `settings` with the passphrase hash and private folders in use kept:
+/// those change only through `set_privacy_passphrase`, which checks the
+/// passphrase.
+fn keep_privacy(state: &AppState, settings: Settings) -> Settings {
+    let current = state.settings.read().expect("settings read lock");
+    Settings {
+        privacy_passphrase_hash: current.privacy_passphrase_hash.clone(),
+        private_folders: current.private_folders.clone(),
+        ..settings
+    }
+}
 
-    Ok(FileContent {
-        path,
-        content,
-        title,
-        modified,
-    })
+#[tauri::command]
+fn get_settings(state: State<AppState>) -> Settings {
+    settings_for_webview(&state.settings.read().expect("settings read lock"))
 }
 
 #[tauri::command]
-async fn import_file_to_folder(
+fn update_settings(
     app: AppHandle,
-    path: String,
-    state: State<'_, AppState>,
-) -> Result<NoteMetadata, String> {
-    let source = validate_preview_path(&path)?;
-    if !source.is_file() {
-        return Err(format!("Not a file: {}", path));
-    }
-
+    new_settings: Settings,
+    state: State<AppState>,
+) -> Result<(), ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
-    let folder_path = PathBuf::from(&folder);
 
-    // Read the source file content
-    let content = fs::read_to_string(&source)
-        .await
-        .map_err(|_| "Failed to read source file".to_string())?;
+    let new_settings = keep_privacy(&state, new_settings);
+    save_settings(&folder, &new_settings).map_err(|e| e.to_string())?;
+    let new_settings = with_vault_config(&folder, new_settings);
+    apply_settings(&app, &state, &folder, new_settings)
+}
 
-    // Derive the note ID from the title (H1 heading), falling back to filename
-    let extracted_title = extract_title(&content);
-    let base_name = if extracted_title.trim().is_empty() {
-        source
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Untitled")
-            .to_string()
-    } else {
-        extracted_title.trim().to_string()
+/// Make `new_settings` the vault's settings: swap them in and bring the
+/// memory budgets, private folders and search index in line. Does not save.
+fn apply_settings(
+    app: &AppHandle,
+    state: &AppState,
+    folder: &str,
+    new_settings: Settings,
+) -> Result<(), ScratchError> {
+    let new_policy = new_settings.locked_notes.clone().unwrap_or_default();
+    let new_note_policies = new_settings.note_policies.clone().unwrap_or_default();
+    let new_read_limit = new_settings.read_limits.clone().unwrap_or_default().index();
+    memory::apply(state, &new_settings.memory_budgets.clone().unwrap_or_default());
+    let (policy_changed, mode_changed) = {
+        let mut settings = state.settings.write().expect("settings write lock");
+        let changed = settings.locked_notes.clone().unwrap_or_default() != new_policy
+            || settings.note_policies.clone().unwrap_or_default() != new_note_policies
+            || settings.read_limits.clone().unwrap_or_default().index() != new_read_limit;
+        let mode_changed = settings.search_index_mode.unwrap_or_default()
+            != new_settings.search_index_mode.unwrap_or_default()
+            || settings.search_language != new_settings.search_language;
+        *settings = new_settings;
+        (changed, mode_changed)
     };
-    let base_id = sanitize_filename(&base_name);
 
-    // Atomically create the file and write content via the handle
-    let mut final_id = base_id.clone();
-    let mut counter = 1;
-    loop {
-        let candidate = abs_path_from_id(&folder_path, &final_id)?;
-        match fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&candidate)
-            .await
-        {
-            Ok(mut file) => {
-                if file.write_all(content.as_bytes()).await.is_err() {
-                    // Clean up the empty file on write failure
-                    let _ = fs::remove_file(&candidate).await;
-                    return Err("Failed to write file".to_string());
-                }
-                break;
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
-                final_id = format!("{}-{}", base_id, counter);
-                counter += 1;
-            }
-            Err(_) => return Err("Failed to create file".to_string()),
+    {
+        let settings = state.settings.read().expect("settings read lock");
+        if let Some(ref private_folders) = settings.private_folders {
+            mark_private_folders_unindexed(Path::new(folder), private_folders);
         }
-    };
+    }
 
-    let modified = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+    // Move the index between disk and memory or rebuild it for another
+    // language, dropping the old one first so its files can be deleted
+    if mode_changed {
+        state.search_index.lock().expect("search index mutex").take();
+        let search_index = {
+            let sources = state
+                .app_config
+                .read()
+                .expect("app_config read lock")
+                .source_folders
+                .clone();
+            let settings = state.settings.read().expect("settings read lock");
+            open_search_index(app, &settings, &sources, Path::new(folder))
+                .map_err(|e| e.to_string())?
+        };
+        search_index
+            .rebuild_index(&PathBuf::from(folder))
+            .map_err(|e| e.to_string())?;
+        *state.search_index.lock().expect("search index mutex") = Some(search_index);
+        return Ok(());
+    }
 
-    // Update search index
-    {
+    // Re-apply the locked-note and note policies and the read limit to the
+    // index so hidden notes drop out
+    if policy_changed {
         let index = state.search_index.lock().expect("search index mutex");
         if let Some(ref search_index) = *index {
-            let _ = search_index.index_note(&final_id, &extracted_title, &content, modified);
+            search_index.set_locked_policy(new_policy);
+            search_index.set_note_policies(new_note_policies);
+            search_index.set_read_limit(new_read_limit);
+            let _ = search_index.rebuild_index(&PathBuf::from(folder));
         }
     }
 
-    let preview = content
-        .lines()
-        .skip(1)
-        .filter(|l| !l.trim().is_empty())
-        .take(3)
-        .collect::<Vec<_>>()
-        .join(" ");
+    Ok(())
+}
 
-    let metadata = NoteMetadata {
-        id: final_id,
-        title: extracted_title,
-        preview,
-        modified,
+#[tauri::command]
+fn update_git_enabled(
+    enabled: Option<bool>,
+    expected_folder: String,
+    state: State<AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        let folder = app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?;
+
+        if folder != expected_folder {
+            return Err("Notes folder changed".into());
+        }
+
+        folder
     };
 
-    // Update notes cache so fallback search sees the imported note immediately
     {
-        let mut cache = state.notes_cache.write().expect("cache write lock");
-        cache.insert(metadata.id.clone(), metadata.clone());
+        let mut settings = state.settings.write().expect("settings write lock");
+        settings.git_enabled = enabled;
     }
 
-    // Tell the main window to select the imported note and focus it
-    let _ = app.emit_to("main", "select-note", &metadata.id);
-    if let Some(main_window) = app.get_webview_window("main") {
-        let _ = main_window.set_focus();
-    }
+    let settings = state.settings.read().expect("settings read lock");
+    save_settings(&folder, &settings).map_err(|e| e.to_string())?;
 
-    Ok(metadata)
+    Ok(())
 }
 
-#[tauri::command]
-async fn search_notes(query: String, state: State<'_, AppState>) -> Result<Vec<SearchResult>, String> {
-    let trimmed_query = query.trim().to_string();
-    if trimmed_query.is_empty() {
-        return Ok(vec![]);
-    }
+// Settings bundle written by export_settings / read by import_settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsExport {
+    pub version: u32,
+    pub app_config: AppConfig,
+    pub settings: Settings,
+}
 
-    // Check if search index is available and use it (scoped to drop lock before await)
-    let indexed_result = {
-        let index = state.search_index.lock().expect("search index mutex");
-        (*index).as_ref().map(|search_index| {
-            search_index.search(&trimmed_query, 20).map_err(|e| e.to_string())
-        })
+const SETTINGS_EXPORT_VERSION: u32 = 1;
+
+#[tauri::command]
+async fn export_settings(path: String, state: State<'_, AppState>) -> Result<(), ScratchError> {
+    let bundle = SettingsExport {
+        version: SETTINGS_EXPORT_VERSION,
+        app_config: state.app_config.read().expect("app_config read lock").clone(),
+        settings: settings_for_webview(&state.settings.read().expect("settings read lock")),
     };
 
-    match indexed_result {
-        Some(Ok(results)) if !results.is_empty() => Ok(results),
-        Some(Ok(_)) => {
-            // Tantivy can miss partial/fuzzy matches; fall back to substring search.
-            fallback_search(&trimmed_query, &state).await
-        }
-        Some(Err(e)) => {
-            eprintln!("Tantivy search error, falling back to substring search: {}", e);
-            fallback_search(&trimmed_query, &state).await
-        }
-        None => {
-            // Fallback to simple search if index not available
-            fallback_search(&trimmed_query, &state).await
-        }
-    }
+    let content = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to write settings export: {}", e).into())
 }
 
-// Fallback search when Tantivy index isn't available - searches title and full content
-async fn fallback_search(query: &str, state: &State<'_, AppState>) -> Result<Vec<SearchResult>, String> {
-    let folder = {
-        let app_config = state.app_config.read().expect("app_config read lock");
-        app_config.notes_folder.clone()
-    };
+#[tauri::command]
+async fn import_settings(
+    app: AppHandle,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Settings, ScratchError> {
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read settings export: {}", e))?;
+    let bundle: SettingsExport = serde_json::from_str(&content)
+        .map_err(|e| ScratchError::invalid(format!("Invalid settings export: {}", e)))?;
 
-    let folder = match folder {
-        Some(f) => f,
-        None => return Ok(vec![]),
+    if bundle.version > SETTINGS_EXPORT_VERSION {
+        return Err(ScratchError::invalid(
+            "Settings export was created by a newer version of Scratch",
+        ));
+    }
+
+    let current_folder = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone();
+
+    // Adopt the exported notes folder only when none is configured yet and the
+    // path exists on this machine; otherwise keep the current vault.
+    let folder = match current_folder {
+        Some(folder) => folder,
+        None => {
+            let exported = bundle
+                .app_config
+                .notes_folder
+                .as_deref()
+                .map(normalize_notes_folder_path)
+                .transpose()?
+                .filter(|p| p.is_dir())
+                .ok_or(ScratchError::FolderNotSet)?;
+            initialize_notes_folder(&app, &exported, &state)?
+        }
     };
 
-    // Collect cache data upfront to avoid holding lock during async operations
-    let cache_data: Vec<(String, String, String, i64)> = {
-        let cache = state.notes_cache.read().expect("cache read lock");
-        cache
-            .values()
-            .map(|note| {
-                (
-                    note.id.clone(),
-                    note.title.clone(),
-                    note.preview.clone(),
-                    note.modified,
-                )
-            })
-            .collect()
+    // The vault's own config still wins over an imported bundle, and the
+    // privacy settings are never imported
+    let imported = with_vault_config(&folder, keep_privacy(&state, bundle.settings));
+    let locked_policy = imported.locked_notes.clone().unwrap_or_default();
+    let note_policies = imported.note_policies.clone().unwrap_or_default();
+    let read_limits = imported.read_limits.clone().unwrap_or_default();
+    memory::apply(&state, &imported.memory_budgets.clone().unwrap_or_default());
+    {
+        let mut settings = state.settings.write().expect("settings write lock");
+        *settings = imported;
+    }
+
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            search_index.set_locked_policy(locked_policy);
+            search_index.set_note_policies(note_policies);
+            search_index.set_read_limit(read_limits.index());
+            let _ = search_index.rebuild_index(&PathBuf::from(&folder));
+        }
+    }
+
+    let settings = state.settings.read().expect("settings read lock");
+    save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+
+    Ok(settings_for_webview(&settings))
+}
+
+#[tauri::command]
+async fn write_file(path: String, contents: Vec<u8>) -> Result<(), ScratchError> {
+    fs::write(&path, contents)
+        .await
+        .map_err(|e| ScratchError::io(e, Path::new(&path)))
+}
+
+#[tauri::command]
+fn preview_note_name(template: String) -> Result<String, ScratchError> {
+    let expanded = expand_note_name_template(&template);
+    let sanitized = sanitize_filename(&expanded);
+
+    // Show first note name (with counter as 1 if present)
+    let preview = if template.contains("{counter}") {
+        sanitized.replace("{counter}", "1")
+    } else {
+        sanitized
     };
 
-    let folder_path = PathBuf::from(&folder);
-    let query_lower = query.to_lowercase();
-    let mut results: Vec<SearchResult> = Vec::new();
+    Ok(preview)
+}
 
-    for (id, title, preview, modified) in cache_data {
-        let title_lower = title.to_lowercase();
+/// Validate a theme name: a plain file stem inside the themes directory.
+fn validate_theme_name(name: &str) -> Result<(), String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err("Theme name cannot be empty".to_string());
+    }
+    if trimmed.contains(['/', '\\']) || trimmed.starts_with('.') {
+        return Err("Invalid theme name".to_string());
+    }
+    Ok(())
+}
 
-        let mut score = 0.0f32;
-        if title_lower.contains(&query_lower) {
-            score += 50.0;
+#[tauri::command]
+fn list_themes(app: AppHandle) -> Result<Vec<ThemeInfo>, ScratchError> {
+    let themes_dir = get_themes_dir(&app).map_err(|e| e.to_string())?;
+
+    let mut themes: Vec<ThemeInfo> = std::fs::read_dir(&themes_dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let format = match path.extension()?.to_str()? {
+                "json" => "json",
+                "css" => "css",
+                _ => return None,
+            };
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some(ThemeInfo {
+                name,
+                format: format.to_string(),
+            })
+        })
+        .collect();
+
+    themes.sort_by_key(|t| t.name.to_lowercase());
+    Ok(themes)
+}
+
+#[tauri::command]
+fn get_theme(app: AppHandle, name: String) -> Result<ThemeFile, ScratchError> {
+    validate_theme_name(&name)?;
+    let themes_dir = get_themes_dir(&app).map_err(|e| e.to_string())?;
+
+    let json_path = themes_dir.join(format!("{}.json", name));
+    if json_path.is_file() {
+        let content = std::fs::read_to_string(&json_path).map_err(|e| e.to_string())?;
+        let mut theme: ThemeFile = serde_json::from_str(&content)
+            .map_err(|e| ScratchError::invalid(format!("Invalid theme file {}: {}", name, e)))?;
+        if theme.name.is_empty() {
+            theme.name = name;
         }
+        return Ok(theme);
+    }
 
-        // Read file content asynchronously and search in it
-        let file_path = match abs_path_from_id(&folder_path, &id) {
-            Ok(p) => p,
-            Err(_) => continue,
-        };
-        if let Ok(content) = tokio::fs::read_to_string(&file_path).await {
-            let content_lower = content.to_lowercase();
-            if content_lower.contains(&query_lower) {
-                // Higher score if in title, lower if only in content
-                if score == 0.0 {
-                    score += 10.0;
-                } else {
-                    score += 5.0;
+    let css_path = themes_dir.join(format!("{}.css", name));
+    if css_path.is_file() {
+        let css = std::fs::read_to_string(&css_path).map_err(|e| e.to_string())?;
+        return Ok(ThemeFile {
+            name,
+            css: Some(css),
+            ..Default::default()
+        });
+    }
+
+    Err(ScratchError::not_found(format!(
+        "Theme not found: {}",
+        name
+    )))
+}
+
+// Theme watcher event payload
+#[derive(Clone, Serialize)]
+struct ThemeChangeEvent {
+    name: String,
+}
+
+/// Watch the themes directory and emit "theme-changed" so the frontend can
+/// hot-reload the active theme when its file is edited.
+fn setup_theme_watcher(app: AppHandle, themes_dir: &Path) -> Result<FileWatcherState, String> {
+    let app_handle = app.clone();
+
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| {
+            if let Ok(event) = res {
+                if !matches!(
+                    event.kind,
+                    notify::EventKind::Create(_)
+                        | notify::EventKind::Modify(_)
+                        | notify::EventKind::Remove(_)
+                ) {
+                    return;
+                }
+                for path in event.paths.iter() {
+                    let is_theme = matches!(
+                        path.extension().and_then(|e| e.to_str()),
+                        Some("json") | Some("css")
+                    );
+                    if !is_theme {
+                        continue;
+                    }
+                    if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                        let _ = app_handle.emit(
+                            "theme-changed",
+                            ThemeChangeEvent {
+                                name: name.to_string(),
+                            },
+                        );
+                    }
                 }
             }
-        }
+        },
+        Config::default(),
+    )
+    .map_err(|e| e.to_string())?;
 
-        if score > 0.0 {
-            results.push(SearchResult {
-                id,
-                title,
-                preview,
-                modified,
-                score,
-            });
-        }
+    watcher
+        .watch(themes_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    Ok(FileWatcherState { watcher })
+}
+
+// Preview mode: file content returned by read_file_direct / save_file_direct
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileContent {
+    pub path: String,
+    pub content: String,
+    pub title: String,
+    pub modified: i64,
+}
+
+/// Validate a file path for preview mode direct file operations.
+/// Ensures the path is a markdown file and resolves symlinks.
+fn validate_preview_path(path: &str) -> Result<PathBuf, String> {
+    let file_path = PathBuf::from(path);
+
+    // Must have a markdown extension
+    match file_path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown") => {}
+        _ => return Err("Only .md and .markdown files are allowed".to_string()),
     }
 
-    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-    results.truncate(20);
+    // Resolve symlinks to get the real path
+    let canonical = file_path
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve file path: {}", e))?;
 
-    Ok(results)
+    Ok(canonical)
 }
 
-// File watcher event payload
-#[derive(Clone, Serialize)]
-struct FileChangeEvent {
-    kind: String,
+#[tauri::command]
+async fn read_file_direct(path: String) -> Result<FileContent, ScratchError> {
+    let canonical = validate_preview_path(&path)?;
+
+    if !canonical.is_file() {
+        return Err(ScratchError::not_found(format!("Not a file: {}", path)));
+    }
+
+    let content = fs::read_to_string(&canonical)
+        .await
+        .map_err(|e| ScratchError::io(e, &canonical))?;
+    let metadata = fs::metadata(&canonical)
+        .await
+        .map_err(|e| ScratchError::io(e, &canonical))?;
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let title = extract_title(&content);
+
+    Ok(FileContent {
+        path,
+        content,
+        title,
+        modified,
+    })
+}
+
+#[tauri::command]
+async fn save_file_direct(path: String, content: String) -> Result<FileContent, ScratchError> {
+    // For save, the file must already exist (we validate extension + path security)
+    let canonical = validate_preview_path(&path)?;
+
+    if !canonical.is_file() {
+        return Err(ScratchError::not_found(format!("Not a file: {}", path)));
+    }
+
+    fs::write(&canonical, &content)
+        .await
+        .map_err(|e| ScratchError::io(e, &canonical))?;
+
+    let metadata = fs::metadata(&canonical)
+        .await
+        .map_err(|e| ScratchError::io(e, &canonical))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let title = extract_title(&content);
+
+    Ok(FileContent {
+        path,
+        content,
+        title,
+        modified,
+    })
+}
+
+#[tauri::command]
+async fn import_file_to_folder(
+    app: AppHandle,
     path: String,
-    changed_ids: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<NoteMetadata, ScratchError> {
+    let source = validate_preview_path(&path)?;
+    if !source.is_file() {
+        return Err(ScratchError::not_found(format!("Not a file: {}", path)));
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    let size = fs::metadata(&source)
+        .await
+        .map_err(|e| ScratchError::io(e, &source))?
+        .len();
+    import_limits(&state)
+        .check_size(&path, size)
+        .map_err(|warning| ScratchError::invalid(warning.to_string()))?;
+
+    // Read the source file content
+    let content = fs::read_to_string(&source)
+        .await
+        .map_err(|e| ScratchError::io(e, &source))?;
+
+    // Derive the note ID from the title (H1 heading), falling back to filename
+    let extracted_title = extract_title(&content);
+    let base_name = if extracted_title.trim().is_empty() {
+        source
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Untitled")
+            .to_string()
+    } else {
+        extracted_title.trim().to_string()
+    };
+    let base_id = sanitize_filename(&base_name);
+
+    // Atomically create the file and write content via the handle
+    let mut final_id = base_id.clone();
+    let mut counter = 1;
+    loop {
+        let candidate = abs_path_from_id(&folder_path, &final_id)?;
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+            .await
+        {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(content.as_bytes()).await {
+                    // Clean up the empty file on write failure
+                    let _ = fs::remove_file(&candidate).await;
+                    return Err(ScratchError::io(e, &candidate));
+                }
+                break;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                final_id = format!("{}-{}", base_id, counter);
+                counter += 1;
+            }
+            Err(e) => return Err(ScratchError::io(e, &candidate)),
+        }
+    };
+
+    let modified = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    // Update search index
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(&final_id, &extracted_title, &content, modified);
+        }
+    }
+    state.link_index.update(&folder_path, &final_id, &content);
+
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let preview = if note_exposure(&content, &locked_policy).index_content {
+        content
+            .lines()
+            .skip(1)
+            .filter(|l| !l.trim().is_empty())
+            .take(3)
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        String::new()
+    };
+    let metadata = NoteMetadata {
+        preview,
+        ..listing_metadata(
+            final_id,
+            &content,
+            modified,
+            note_created_secs(&content, None, modified),
+            &locked_policy,
+            &pinned_note_ids(&state),
+        )
+    };
+
+    // Update notes cache so fallback search sees the imported note immediately
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        cache.insert(metadata.id.clone(), metadata.clone());
+    }
+
+    // Tell the main window to select the imported note and focus it
+    let _ = app.emit_to("main", "select-note", &metadata.id);
+    if let Some(main_window) = app.get_webview_window("main") {
+        let _ = main_window.set_focus();
+    }
+
+    Ok(metadata)
+}
+
+/// Expand the folders among `paths` into the files under them that `accept`
+/// takes, hidden folders skipped, each with the note ID that keeps its place
+/// in the folder (relative path without extension, segments sanitized).
+/// Plain files come back without an ID.
+fn import_sources(paths: Vec<String>, accept: fn(&Path) -> bool) -> Vec<(String, Option<String>)> {
+    let mut files = Vec::new();
+    for path in paths {
+        let root = Path::new(&path);
+        if !root.is_dir() {
+            files.push((path, None));
+            continue;
+        }
+        let entries = walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .flatten()
+            .filter(|e| e.file_type().is_file() && accept(e.path()));
+        for entry in entries {
+            let Some(rel) = entry
+                .path()
+                .strip_prefix(root)
+                .ok()
+                .and_then(|rel| rel.with_extension("").to_str().map(String::from))
+            else {
+                continue;
+            };
+            let id = rel
+                .split(std::path::MAIN_SEPARATOR)
+                .map(sanitize_filename)
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((entry.path().to_string_lossy().into_owned(), Some(id)));
+        }
+    }
+    files
+}
+
+/// Import markdown files into the vault through the shared import framework
+/// (dry run, collision policy, `import-progress` events, final report). A
+/// folder among `paths` brings every markdown file under it, keeping its
+/// subfolders and file names (links between its notes rely on those); hidden
+/// folders such as `.obsidian` stay behind.
+#[tauri::command]
+async fn import_markdown_files(
+    app: AppHandle,
+    paths: Vec<String>,
+    options: Option<import::ImportOptions>,
+    state: State<'_, AppState>,
+) -> Result<import::ImportReport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let options = options.unwrap_or_default();
+    if let Some(ref target) = options.target_folder {
+        if !target.trim_matches('/').is_empty() {
+            validate_folder_path(target.trim_matches('/'))?;
+        }
+    }
+    if !options.dry_run {
+        let point = match paths.len() {
+            1 => "Before importing 1 file".to_string(),
+            n => format!("Before importing {} files", n),
+        };
+        take_restore_point(&app, &folder, point).await?;
+    }
+    let limits = import_limits(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let mut items = Vec::new();
+        let mut unreadable = Vec::new();
+        let mut too_large = Vec::new();
+        for (path, id) in import_sources(paths, is_markdown_extension) {
+            // Check the size before reading a file that may be huge
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if let Err(warning) = limits.check_size(&path, size) {
+                too_large.push(warning);
+                continue;
+            }
+            let content = validate_preview_path(&path).and_then(|source| {
+                std::fs::read_to_string(&source).map_err(|_| "Failed to read source file".to_string())
+            });
+            let content = match content {
+                Ok(content) => content,
+                Err(error) => {
+                    unreadable.push(import::ImportFailure { source: path, error });
+                    continue;
+                }
+            };
+
+            // Name the note after its H1 title, falling back to the file name
+            let id = id.unwrap_or_else(|| {
+                let title = extract_title(&content);
+                let base_name = if title.trim().is_empty() || title == "Untitled" {
+                    Path::new(&path)
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or("Untitled")
+                        .to_string()
+                } else {
+                    title
+                };
+                sanitize_filename(&base_name)
+            });
+            items.push(import::ImportItem {
+                id: import::in_target_folder(&options, &id),
+                source: path,
+                content,
+                assets: Vec::new(),
+                modified: None,
+            });
+        }
+
+        let mut report = import::run(&app, Path::new(&folder), items, &options);
+        report.failed.extend(unreadable);
+        report.warnings.extend(too_large);
+        report
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Import plain text (`.txt`), org-mode and textile files, RTF documents and
+/// markdown as notes, converted by `convert.rs`. Folders among `paths` bring
+/// every such file under them, keeping their layout; other files are reported
+/// as failed. Each file's modification time is kept. Same options and report
+/// as `import_markdown_files`.
+#[tauri::command]
+async fn import_files(
+    app: AppHandle,
+    paths: Vec<String>,
+    options: Option<import::ImportOptions>,
+    state: State<'_, AppState>,
+) -> Result<import::ImportReport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let options = options.unwrap_or_default();
+    if let Some(ref target) = options.target_folder {
+        if !target.trim_matches('/').is_empty() {
+            validate_folder_path(target.trim_matches('/'))?;
+        }
+    }
+    if !options.dry_run {
+        let point = match paths.len() {
+            1 => "Before importing 1 file".to_string(),
+            n => format!("Before importing {} files", n),
+        };
+        take_restore_point(&app, &folder, point).await?;
+    }
+    let limits = import_limits(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let mut items = Vec::new();
+        let mut unreadable = Vec::new();
+        let mut too_large = Vec::new();
+        for (path, id) in import_sources(paths, convert::is_supported) {
+            let source = Path::new(&path);
+            let metadata = std::fs::metadata(source).ok();
+            let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+            if let Err(warning) = limits.check_size(&path, size) {
+                too_large.push(warning);
+                continue;
+            }
+            let text = validate_preview_path(&path).and_then(|source| {
+                std::fs::read(&source).map_err(|_| "Failed to read source file".to_string())
+            });
+            let text = match text {
+                Ok(bytes) => convert::decode(bytes),
+                Err(error) => {
+                    unreadable.push(import::ImportFailure {
+                        source: path,
+                        error,
+                    });
+                    continue;
+                }
+            };
+            let extension = source
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let Some(converted) = convert::to_markdown(&extension, &text) else {
+                let error = format!("Unsupported file type (.{})", extension);
+                unreadable.push(import::ImportFailure {
+                    source: path,
+                    error,
+                });
+                continue;
+            };
+
+            let stem = source
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("Untitled")
+                .to_string();
+            let mut content = if is_markdown_extension(source) {
+                converted.body
+            } else {
+                let title = converted.title.unwrap_or_else(|| stem.clone());
+                format!("# {}\n\n{}\n", title, converted.body)
+            };
+            if !converted.tags.is_empty() {
+                let tags = frontmatter::Value::List(converted.tags);
+                content = frontmatter::set(&content, "tags", tags);
+            }
+            // Named like import_markdown_files: the title, else the file name
+            let id = id.unwrap_or_else(|| {
+                let title = extract_title(&content);
+                if title.trim().is_empty() || title == "Untitled" {
+                    sanitize_filename(&stem)
+                } else {
+                    sanitize_filename(&title)
+                }
+            });
+            let modified = metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64);
+            items.push(import::ImportItem {
+                id: import::in_target_folder(&options, &id),
+                source: path,
+                content,
+                assets: Vec::new(),
+                modified,
+            });
+        }
+
+        let mut report = import::run(&app, Path::new(&folder), items, &options);
+        report.failed.extend(unreadable);
+        report.warnings.extend(too_large);
+        report
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Import an Evernote `.enex` export: every note in it, with its attachments,
+/// tags and timestamps. Same options and report as `import_markdown_files`.
+#[tauri::command]
+async fn import_enex(
+    app: AppHandle,
+    path: String,
+    options: Option<import::ImportOptions>,
+    state: State<'_, AppState>,
+) -> Result<import::ImportReport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let options = options.unwrap_or_default();
+    if let Some(ref target) = options.target_folder {
+        if !target.trim_matches('/').is_empty() {
+            validate_folder_path(target.trim_matches('/'))?;
+        }
+    }
+    let source = PathBuf::from(&path);
+    if !source
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("enex"))
+    {
+        return Err(ScratchError::invalid("Choose an Evernote export (.enex)"));
+    }
+    if !options.dry_run {
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(path);
+        take_restore_point(&app, &folder, format!("Before importing {}", name)).await?;
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let xml = std::fs::read_to_string(&source).map_err(|e| ScratchError::io(e, &source))?;
+        let notes_root = Path::new(&folder);
+        let state = app.state::<AppState>();
+        let (items, failed) = enex::items(&state, notes_root, &xml, &options);
+        if items.is_empty() && failed.is_empty() {
+            return Err(ScratchError::invalid("The export contains no notes"));
+        }
+        let mut report = import::run(&app, notes_root, items, &options);
+        report.failed.extend(failed);
+        Ok(report)
+    })
+    .await?
+}
+
+/// Import a Joplin export: a `.jex` archive or a RAW export folder. Notebooks
+/// become folders; resources, tags and timestamps come along. Same options
+/// and report as `import_markdown_files`.
+#[tauri::command]
+async fn import_joplin(
+    app: AppHandle,
+    path: String,
+    options: Option<import::ImportOptions>,
+    state: State<'_, AppState>,
+) -> Result<import::ImportReport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let options = options.unwrap_or_default();
+    if let Some(ref target) = options.target_folder {
+        if !target.trim_matches('/').is_empty() {
+            validate_folder_path(target.trim_matches('/'))?;
+        }
+    }
+    let source = PathBuf::from(&path);
+    let is_jex = source.is_file()
+        && source
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("jex"));
+    if !is_jex && !joplin::is_raw_export(&source) {
+        return Err(ScratchError::invalid(
+            "Choose a Joplin export (.jex file or RAW export folder)",
+        ));
+    }
+    if !options.dry_run {
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(path);
+        take_restore_point(&app, &folder, format!("Before importing {}", name)).await?;
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let notes_root = Path::new(&folder);
+        let state = app.state::<AppState>();
+        let (items, failed) = joplin::items(&state, notes_root, &source, &options)?;
+        let mut report = import::run(&app, notes_root, items, &options);
+        report.failed.extend(failed);
+        Ok(report)
+    })
+    .await?
+}
+
+/// Notes from other apps found on this computer (Obsidian vaults, Apple Notes
+/// and Evernote exports, Joplin), with counts and how to import each.
+#[tauri::command]
+async fn detect_importable_sources(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<migration::ImportableSource>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    tokio::task::spawn_blocking(move || migration::detect(&app, folder.as_deref().map(Path::new)))
+        .await
+        .map_err(ScratchError::from)
+}
+
+#[tauri::command]
+async fn search_notes(
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchResult>, ScratchError> {
+    let trimmed_query = query.trim().to_string();
+    if trimmed_query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Check if search index is available and use it (scoped to drop lock before await)
+    let indexed_result = {
+        let index = state.search_index.lock().expect("search index mutex");
+        (*index).as_ref().map(|search_index| {
+            search_index.search(&trimmed_query, 20).map_err(|e| e.to_string())
+        })
+    };
+
+    let results = match indexed_result {
+        Some(Ok(results)) if !results.is_empty() => results,
+        Some(Ok(_)) => {
+            // Tantivy can miss partial/fuzzy matches; fall back to substring search.
+            fallback_search(&trimmed_query, &state).await?
+        }
+        Some(Err(e)) => {
+            eprintln!("Tantivy search error, falling back to substring search: {}", e);
+            fallback_search(&trimmed_query, &state).await?
+        }
+        None => {
+            // Fallback to simple search if index not available
+            fallback_search(&trimmed_query, &state).await?
+        }
+    };
+
+    // Hide private notes unless privacy mode is temporarily disabled
+    let private_folders = match active_private_folders(&state) {
+        Some(folders) => folders,
+        None => return Ok(results),
+    };
+    let folder = match state.app_config.read().expect("app_config read lock").notes_folder.clone() {
+        Some(f) => PathBuf::from(f),
+        None => return Ok(results),
+    };
+
+    let mut visible = Vec::with_capacity(results.len());
+    for result in results {
+        if let Some(path) = sidecars::path_from_id(&result.id) {
+            if !is_private_note(path, "", &private_folders) {
+                visible.push(result);
+            }
+            continue;
+        }
+        let content = match abs_path_from_id(&folder, &result.id) {
+            Ok(path) => fs::read_to_string(&path).await.unwrap_or_default(),
+            Err(_) => continue,
+        };
+        if !is_private_note(&result.id, &content, &private_folders) {
+            visible.push(result);
+        }
+    }
+    Ok(visible)
+}
+
+const VAULT_REPORT_ID: &str = "Vault Stats";
+
+/// Scan notes for vault-wide analysis, leaving out the generated report note
+/// and (while privacy mode is active) private notes.
+fn scan_analyzable_notes(notes_root: &Path, private_folders: Option<&[String]>) -> Vec<ScannedNote> {
+    scan_notes(notes_root)
+        .into_iter()
+        .filter(|n| n.id != VAULT_REPORT_ID)
+        .filter(|n| match private_folders {
+            Some(private) => !is_private_note(&n.id, &n.content, private),
+            None => true,
+        })
+        .collect()
+}
+
+/// Attachments described by a metadata sidecar, as pseudo-notes, leaving out
+/// (while privacy mode is active) those in private folders.
+fn scan_attachments(notes_root: &Path, private_folders: Option<&[String]>) -> Vec<ScannedNote> {
+    sidecars::scan(notes_root)
+        .into_iter()
+        .filter(|n| match private_folders {
+            Some(private) => {
+                !is_private_note(sidecars::path_from_id(&n.id).unwrap_or(&n.id), "", private)
+            }
+            None => true,
+        })
+        .collect()
+}
+
+/// Every tag in the vault with the number of notes and described attachments
+/// using it, in the order of the `collationLocale` setting.
+#[tauri::command]
+async fn list_tags(state: State<'_, AppState>) -> Result<Vec<TagCount>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locale = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.collation_locale.clone()
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let root = Path::new(&folder);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let notes = scan_analyzable_notes(root, private_folders.as_deref());
+        for note in notes
+            .into_iter()
+            .chain(scan_attachments(root, private_folders.as_deref()))
+        {
+            for tag in tags::extract_tags(&note.content) {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        let collation = collation::Collation::new(locale.as_deref());
+        let mut tags: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        tags.sort_by(|a, b| collation.compare(&a.tag, &b.tag));
+        tags
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Notes and described attachments tagged `tag` or one of its nested tags
+/// (`project` also matches `project/alpha`), newest first. Locked notes are
+/// left out.
+#[tauri::command]
+async fn list_notes_by_tag(
+    tag: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteMetadata>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+    let pinned = pinned_note_ids(&state);
+    let tag = tags::normalize(&tag);
+    if tag.is_empty() {
+        return Err(ScratchError::invalid("Tag cannot be empty"));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let nested = format!("{}/", tag);
+        let root = Path::new(&folder);
+        let mut notes: Vec<NoteMetadata> = scan_analyzable_notes(root, private_folders.as_deref())
+            .into_iter()
+            .chain(scan_attachments(root, private_folders.as_deref()))
+            .filter_map(|note| {
+                let metadata = listing_metadata(
+                    note.id,
+                    &note.content,
+                    note.modified,
+                    note.created,
+                    &locked_policy,
+                    &pinned,
+                );
+                let tagged = metadata
+                    .tags
+                    .iter()
+                    .any(|t| *t == tag || t.starts_with(&nested));
+                tagged.then_some(metadata)
+            })
+            .collect();
+        notes.sort_by_key(|n| Reverse(n.modified));
+        notes
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Write notes, tags, links, tasks and totals as one JSON document to `path`.
+#[tauri::command]
+async fn export_vault_metadata(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+
+    let dump = tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref());
+        metadata::build(&notes, &locked_policy)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let content = serde_json::to_string_pretty(&dump).map_err(|e| e.to_string())?;
+    fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to write vault metadata: {}", e).into())
+}
+
+/// Pack every file in the vault into a zip at `destination`, emitting
+/// `vault-export-progress` as it goes.
+#[tauri::command]
+async fn export_vault_zip(
+    app: AppHandle,
+    destination: String,
+    options: Option<backup::VaultZipOptions>,
+    state: State<'_, AppState>,
+) -> Result<backup::VaultZipReport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let destination = PathBuf::from(destination.trim());
+    if destination.as_os_str().is_empty() || destination.is_dir() {
+        return Err(ScratchError::invalid(
+            "Choose a file to export the vault to",
+        ));
+    }
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || {
+        backup::export(&app, Path::new(&folder), &destination, &options)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+/// Read a dump written by `export_vault_metadata` and seed the notes cache
+/// with the notes that are unchanged since. Returns how many were seeded.
+#[tauri::command]
+async fn import_vault_metadata(
+    app: AppHandle,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read vault metadata: {}", e))?;
+    let dump: metadata::VaultMetadata = serde_json::from_str(&content)
+        .map_err(|e| ScratchError::invalid(format!("Invalid vault metadata: {}", e)))?;
+    if dump.version > metadata::VERSION {
+        return Err(ScratchError::invalid(
+            "Vault metadata was created by a newer version of Scratch",
+        ));
+    }
+    let private_folders = active_private_folders(&state);
+    let pinned = pinned_note_ids(&state);
+    take_restore_point(&app, &folder, "Before importing vault metadata".to_string()).await?;
+
+    let seeded = tokio::task::spawn_blocking(move || {
+        metadata::seed(
+            Path::new(&folder),
+            &dump,
+            private_folders.as_deref(),
+            &pinned,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let count = seeded.len();
+    let mut cache = state.notes_cache.write().expect("cache write lock");
+    for note in seeded {
+        cache.insert(note.id.clone(), note);
+    }
+    Ok(count)
+}
+
+/// Notes with no incoming or outgoing links and no tags, oldest first.
+#[tauri::command]
+async fn find_orphan_notes(state: State<'_, AppState>) -> Result<Vec<NoteMetadata>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+    let pinned = pinned_note_ids(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref());
+        let by_id: HashMap<&str, &ScannedNote> = notes.iter().map(|n| (n.id.as_str(), n)).collect();
+        let stats = report::analyze(&notes);
+
+        report::find_orphans(&stats)
+            .into_iter()
+            .filter_map(|s| by_id.get(s.id.as_str()))
+            .map(|note| {
+                listing_metadata(
+                    note.id.clone(),
+                    &note.content,
+                    note.modified,
+                    note.created,
+                    &locked_policy,
+                    &pinned,
+                )
+            })
+            .collect()
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Create or refresh the "Vault Stats" note with counts, monthly word totals,
+/// top tags, orphan notes and the largest notes.
+#[tauri::command]
+async fn generate_vault_report(state: State<'_, AppState>) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    let private_folders = active_private_folders(&state);
+
+    let root = folder_path.clone();
+    let content = tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(&root, private_folders.as_deref());
+        let stats = report::analyze(&notes);
+        let generated_at = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+        report::render_report(&stats, VAULT_REPORT_ID, &generated_at)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let file_path = abs_path_from_id(&folder_path, VAULT_REPORT_ID)?;
+    let _guard = state.note_locks.lock(&folder_path, &[VAULT_REPORT_ID]).await;
+    fs::write(&file_path, &content)
+        .await
+        .map_err(|e| ScratchError::io(e, &file_path))?;
+
+    let modified = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(VAULT_REPORT_ID, VAULT_REPORT_ID, &content, modified);
+        }
+    }
+
+    Ok(Note {
+        id: VAULT_REPORT_ID.to_string(),
+        title: VAULT_REPORT_ID.to_string(),
+        content,
+        path: rel_path_from_id(VAULT_REPORT_ID),
+        modified,
+    })
+}
+
+/// Write updated content for an existing note in place and refresh the search
+/// index. Used by backend features that edit notes (e.g. frontmatter updates).
+/// Callers hold the note's lock.
+async fn write_note_content(
+    state: &AppState,
+    folder_path: &Path,
+    id: &str,
+    content: String,
+) -> Result<Note, String> {
+    write_note_content_from(state, audit::Surface::Ui, None, folder_path, id, content).await
+}
+
+/// `write_note_content` for writes from another surface, audited as `action`
+/// when given rather than as a save or create.
+async fn write_note_content_from(
+    state: &AppState,
+    surface: audit::Surface,
+    action: Option<audit::Action>,
+    folder_path: &Path,
+    id: &str,
+    content: String,
+) -> Result<Note, String> {
+    let file_path = abs_path_from_id(folder_path, id)?;
+    let previous = fs::read(&file_path).await.ok();
+    recovery::write_atomic(&file_path, &content)
+        .await
+        .map_err(|e| e.to_string())?;
+    let action = action.unwrap_or(if previous.is_some() {
+        audit::Action::Save
+    } else {
+        audit::Action::Create
+    });
+    state.audit.record(
+        folder_path,
+        audit::Change::new(surface, action, id)
+            .before(previous.as_deref())
+            .after(Some(content.as_bytes())),
+    );
+
+    let modified = fs::metadata(&file_path)
+        .await
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let title = extract_title(&content);
+
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(id, &title, &content, modified);
+        }
+    }
+    state.link_index.update(folder_path, id, &content);
+
+    Ok(Note {
+        id: id.to_string(),
+        title,
+        content,
+        path: rel_path_from_id(id),
+        modified,
+    })
+}
+
+/// Queue a note's content for a debounced write. Call on every change; only
+/// the latest content is written once edits pause. `expected_hash` works as
+/// in `save_note`, except that a conflict comes back as `autosave-conflict`.
+#[tauri::command]
+fn autosave_note(
+    app: AppHandle,
+    id: String,
+    content: String,
+    expected_hash: Option<String>,
+    state: State<AppState>,
+) -> Result<autosave::AutosaveStatus, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    if !abs_path_from_id(Path::new(&folder), &id)?.exists() {
+        return Err(ScratchError::NoteNotFound { id });
+    }
+    Ok(autosave::queue(&app, &id, content, expected_hash))
+}
+
+/// Write pending autosaves now: one note (on blur) or all of them.
+#[tauri::command]
+async fn flush_autosave(
+    app: AppHandle,
+    id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<autosave::AutosaveStatus>, ScratchError> {
+    let ids = match id {
+        Some(id) => {
+            match autosave::flush(&state, &id).await {
+                Ok(Some(_)) => autosave::emit_stats(&app, &id),
+                Ok(None) => {}
+                Err(autosave::FlushError::Conflict(conflict)) => {
+                    let _ = app.emit("autosave-conflict", &conflict);
+                }
+                Err(e) => return Err(e.into()),
+            }
+            vec![id]
+        }
+        None => {
+            let ids = state.autosave.pending_ids();
+            let failed = autosave::flush_all(&state).await;
+            if !failed.is_empty() {
+                return Err(format!("Failed to save: {}", failed.join(", ")).into());
+            }
+            for id in &ids {
+                autosave::emit_stats(&app, id);
+            }
+            ids
+        }
+    };
+    Ok(ids.iter().map(|id| autosave::status(&state, id)).collect())
+}
+
+/// Whether this instance holds the vault lock, and what crash recovery found.
+#[tauri::command]
+fn get_instance_status(state: State<AppState>) -> Option<instance::InstanceStatus> {
+    state.instance.lock().expect("instance status mutex").clone()
+}
+
+#[tauri::command]
+async fn list_drafts(state: State<'_, AppState>) -> Result<Vec<recovery::DraftInfo>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    Ok(recovery::list_drafts(Path::new(&folder)))
+}
+
+/// Write a recovered draft back into its note.
+#[tauri::command]
+async fn restore_draft(id: String, state: State<'_, AppState>) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    let draft = recovery::read_draft(&folder_path, &id).ok_or("Draft not found")?;
+    let _guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+    let note = write_note_content(&state, &folder_path, &id, draft.content).await?;
+    recovery::remove_draft(&folder_path, &id);
+    Ok(note)
+}
+
+#[tauri::command]
+async fn discard_draft(id: String, state: State<'_, AppState>) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    recovery::remove_draft(Path::new(&folder), &id);
+    Ok(())
+}
+
+/// Pick a pseudo-random index below `len` (not for security purposes).
+fn random_index(len: usize) -> usize {
+    use std::hash::{BuildHasher, Hasher};
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    (hasher.finish() % len.max(1) as u64) as usize
+}
+
+// Filter for get_random_note
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RandomNoteFilter {
+    pub folder: Option<String>,
+    pub tag: Option<String>,
+    pub older_than_days: Option<i64>, // only notes not modified in this many days
+}
+
+#[tauri::command]
+async fn get_random_note(
+    filter: Option<RandomNoteFilter>,
+    state: State<'_, AppState>,
+) -> Result<Option<NoteMetadata>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+    let pinned = pinned_note_ids(&state);
+    let filter = filter.unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || {
+        let now = chrono::Utc::now().timestamp();
+        let folder_prefix = filter
+            .folder
+            .as_ref()
+            .map(|f| format!("{}/", f.trim_matches('/')))
+            .filter(|f| f != "/");
+        let tag = filter.tag.as_deref().map(tags::normalize);
+
+        let candidates: Vec<ScannedNote> = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref())
+            .into_iter()
+            .filter(|n| match folder_prefix {
+                Some(ref prefix) => n.id.starts_with(prefix.as_str()),
+                None => true,
+            })
+            .filter(|n| match tag {
+                Some(ref tag) => tags::extract_tags(&n.content).contains(tag),
+                None => true,
+            })
+            .filter(|n| match filter.older_than_days {
+                Some(days) => now - n.modified >= days * 86_400,
+                None => true,
+            })
+            .collect();
+
+        if candidates.is_empty() {
+            return None;
+        }
+        let note = &candidates[random_index(candidates.len())];
+        Some(listing_metadata(
+            note.id.clone(),
+            &note.content,
+            note.modified,
+            note.created,
+            &locked_policy,
+            &pinned,
+        ))
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Resolve a typed date ("next tuesday", "in 3 weeks") to `YYYY-MM-DD`,
+/// relative to `reference` (`YYYY-MM-DD`) or today.
+#[tauri::command]
+fn parse_natural_date(text: String, reference: Option<String>) -> Result<String, ScratchError> {
+    let today = match reference {
+        Some(reference) => chrono::NaiveDate::parse_from_str(&reference, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid reference date: {}", reference))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    dates::parse_natural_date(&text, today)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .ok_or_else(|| {
+            ScratchError::invalid(format!("Could not understand the date \"{}\"", text.trim()))
+        })
+}
+
+/// Open the daily note for a day, creating it if needed, from the
+/// `dailyTemplate` note when one is set. `date` may be typed naturally
+/// ("yesterday", "next monday"); defaults to today. `location` is the
+/// device's position, for the `dailyContext` stamp on a new note.
+#[tauri::command]
+async fn open_daily_note(
+    date: Option<String>,
+    location: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let (daily, template_id, stamp, weather_url) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            daily::Layout::from_settings(&settings),
+            settings
+                .daily_template
+                .clone()
+                .filter(|t| !t.trim().is_empty()),
+            settings.daily_context.clone().unwrap_or_default(),
+            settings.weather_url.clone(),
+        )
+    };
+    let today = chrono::Local::now().date_naive();
+    let date = match date {
+        Some(text) => dates::parse_natural_date(&text, today).ok_or_else(|| {
+            ScratchError::invalid(format!("Could not understand the date \"{}\"", text.trim()))
+        })?,
+        None => today,
+    };
+
+    let id = daily.note_id(date);
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let _guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+    if file_path.exists() {
+        return read_note(id, state).await;
+    }
+    let template = match template_id {
+        Some(template_id) => {
+            let template_path = abs_path_from_id(&folder_path, &template_id)?;
+            let template = fs::read_to_string(&template_path).await.map_err(|_| {
+                ScratchError::not_found(format!("Daily template not found: {}", template_id))
+            })?;
+            Some(template)
+        }
+        None => None,
+    };
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| ScratchError::io(e, parent))?;
+    }
+
+    let place = location.filter(|l| !l.trim().is_empty()).or(stamp.place);
+    let wants_weather = (date == today && stamp.weather)
+        || template
+            .as_deref()
+            .is_some_and(|t| templates::uses(t, "weather"));
+    let weather = match weather_url {
+        Some(url) if wants_weather => templates::fetch_weather(&url, place.as_deref()).await,
+        _ => None,
+    };
+    let mut content = match template {
+        Some(template) => {
+            // Date variables are for the note's day, at the current time
+            let now = chrono::Local::now();
+            let ctx = templates::TemplateContext {
+                now: date
+                    .and_time(now.time())
+                    .and_local_timezone(chrono::Local)
+                    .earliest()
+                    .unwrap_or(now),
+                vault_name: folder_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                selection: None,
+                clipboard: None,
+                weather: weather.clone(),
+            };
+            state.template_variables.expand(&template, &ctx)
+        }
+        None => daily::new_content(date),
+    };
+    if date == today {
+        let device = match (stamp.device, stamp.device_name) {
+            (false, _) => None,
+            (true, Some(name)) => Some(name),
+            (true, None) => tokio::task::spawn_blocking(daily::device_name).await?,
+        };
+        let context = daily::Context {
+            weather: weather.filter(|_| stamp.weather),
+            device,
+            location: place.filter(|_| stamp.location),
+        };
+        content = daily::stamp(&content, &context);
+    }
+    Ok(write_note_content(&state, &folder_path, &id, content).await?)
+}
+
+/// Append a timestamped entry to a log (see `logs.rs`), in this month's note
+/// of the series, creating the note and linking it from the log's index
+/// note when a month starts.
+#[tauri::command]
+async fn append_log_entry(
+    log: String,
+    entry: String,
+    state: State<'_, AppState>,
+) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let log = logs::normalize(&log);
+    if log.is_empty() {
+        return Err(ScratchError::invalid("Log name cannot be empty"));
+    }
+    if entry.trim().is_empty() {
+        return Err(ScratchError::invalid("Log entry cannot be empty"));
+    }
+    let now = chrono::Local::now().naive_local();
+    let month_id = logs::month_id(&log, now.date());
+    let folder_path = PathBuf::from(&folder);
+    let index_path = abs_path_from_id(&folder_path, &log)?;
+    let month_path = abs_path_from_id(&folder_path, &month_id)?;
+    let _guard = state
+        .note_locks
+        .lock(&folder_path, &[log.as_str(), month_id.as_str()])
+        .await;
+    if let Some(parent) = month_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| ScratchError::io(e, parent))?;
+    }
+
+    let (content, new_month) = match fs::read_to_string(&month_path).await {
+        Ok(content) => (content, false),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            (logs::new_month(&log, now.date()), true)
+        }
+        Err(e) => return Err(ScratchError::note_io(e, &month_id)),
+    };
+    let note = write_note_content(
+        &state,
+        &folder_path,
+        &month_id,
+        logs::append(&content, &entry, now),
+    )
+    .await?;
+
+    if new_month || !index_path.exists() {
+        let index = match fs::read_to_string(&index_path).await {
+            Ok(index) => index,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => logs::new_index(&log),
+            Err(e) => return Err(ScratchError::note_io(e, &log)),
+        };
+        if let Some(index) = logs::link_month(&index, &log, &month_id) {
+            write_note_content(&state, &folder_path, &log, index).await?;
+        }
+    }
+    Ok(note)
+}
+
+/// Add the recurring tasks due on a day (default today) to its daily note.
+#[tauri::command]
+async fn materialize_routines(
+    date: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<routines::RoutineRun, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let daily = {
+        let settings = state.settings.read().expect("settings read lock");
+        daily::Layout::from_settings(&settings)
+    };
+    let date = match date {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {}", date))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let root = Path::new(&folder);
+        let notes = scan_analyzable_notes(root, private_folders.as_deref());
+        routines::materialize(root, &notes, &daily, date)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+/// Every recurring task with the days it was generated and completed.
+#[tauri::command]
+async fn get_routines(
+    state: State<'_, AppState>,
+) -> Result<Vec<routines::RoutineStatus>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let daily = {
+        let settings = state.settings.read().expect("settings read lock");
+        daily::Layout::from_settings(&settings)
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let root = Path::new(&folder);
+        let notes = scan_analyzable_notes(root, private_folders.as_deref());
+        let today = chrono::Local::now().date_naive();
+        routines::statuses(root, &notes, &daily, today)
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Done/missed series for a habit checkbox across the daily notes, over
+/// `range` (natural dates allowed; default the last 30 days).
+#[tauri::command]
+async fn get_habit_data(
+    habit: String,
+    range: Option<habits::HabitRange>,
+    state: State<'_, AppState>,
+) -> Result<habits::HabitData, ScratchError> {
+    if habit.trim().is_empty() {
+        return Err(ScratchError::invalid("Habit name cannot be empty"));
+    }
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let daily = {
+        let settings = state.settings.read().expect("settings read lock");
+        daily::Layout::from_settings(&settings)
+    };
+    let private_folders = active_private_folders(&state);
+
+    let today = chrono::Local::now().date_naive();
+    let parse = |text: &str| {
+        dates::parse_natural_date(text, today).ok_or_else(|| {
+            ScratchError::invalid(format!("Could not understand the date \"{}\"", text.trim()))
+        })
+    };
+    let (from, to) = match range {
+        Some(range) => (parse(&range.from)?, parse(&range.to)?),
+        None => (today - chrono::Duration::days(29), today),
+    };
+    if from > to {
+        return Err(ScratchError::invalid("Range starts after it ends"));
+    }
+    if (to - from).num_days() > habits::MAX_RANGE_DAYS {
+        return Err(ScratchError::invalid(format!(
+            "Range is limited to {} days",
+            habits::MAX_RANGE_DAYS
+        )));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        habits::habit_data(
+            Path::new(&folder),
+            &daily,
+            private_folders.as_deref(),
+            &habit,
+            from,
+            to,
+            today,
+        )
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Goals declared with `goal:` frontmatter, with progress rolled up by
+/// quarter and area.
+#[tauri::command]
+async fn get_goal_progress(state: State<'_, AppState>) -> Result<goals::GoalRollup, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref());
+        goals::rollup(&notes)
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Open tasks, recent notes, upcoming dates and linked people for a project,
+/// given as a tag (`#project`) or a folder path.
+#[tauri::command]
+async fn get_project_dashboard(
+    project: String,
+    state: State<'_, AppState>,
+) -> Result<dashboard::ProjectDashboard, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let project = project.trim().to_string();
+    if project.trim_start_matches('#').is_empty() {
+        return Err(ScratchError::invalid("Project cannot be empty"));
+    }
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+    let pinned = pinned_note_ids(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let root = Path::new(&folder);
+        let is_folder = !project.starts_with('#')
+            && !project.starts_with('.')
+            && root.join(project.trim_matches('/')).is_dir();
+        let notes = scan_analyzable_notes(root, private_folders.as_deref());
+        dashboard::build(
+            &notes,
+            project.trim_start_matches('#'),
+            is_folder,
+            &locked_policy,
+            &pinned,
+            chrono::Local::now().date_naive(),
+        )
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// A note read as structured content: a recipe, a book, or whatever schema it
+/// names with `type:` or a tag when `schema` is not given.
+#[tauri::command]
+async fn get_structured_content(
+    id: String,
+    schema: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<schemas::StructuredContent, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let path = abs_path_from_id(Path::new(&folder), &id)?;
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    schemas::extract(&extract_title(&content), &content, schema.as_deref())
+        .map_err(ScratchError::invalid)
+}
+
+/// Notes with `location:` frontmatter, for a map view. Place names are looked
+/// up with the configured geocoding provider; the ones it cannot place, or
+/// that are still waiting their turn, come back without coordinates.
+#[tauri::command]
+async fn get_notes_with_locations(
+    state: State<'_, AppState>,
+) -> Result<Vec<locations::NoteLocation>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let (locked_policy, geocoding) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            settings.locked_notes.clone().unwrap_or_default(),
+            settings.geocoding.clone().unwrap_or_default(),
+        )
+    };
+    let private_folders = active_private_folders(&state);
+
+    let scan_folder = folder.clone();
+    let (mut located, mut cache) = tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&scan_folder), private_folders.as_deref());
+        (
+            locations::collect(&notes, &locked_policy),
+            locations::GeocodeCache::load(&scan_folder),
+        )
+    })
+    .await?;
+
+    let missing = cache.missing(&located);
+    if !missing.is_empty() {
+        match locations::geocode(&geocoding, &missing, &mut cache).await {
+            Ok(true) => {
+                let save_cache = cache.clone();
+                let _ = tokio::task::spawn_blocking(move || save_cache.save(&folder)).await?;
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Geocoding failed: {}", e),
+        }
+    }
+    cache.fill(&mut located);
+    Ok(located)
+}
+
+/// Set a note's `location:` frontmatter to coordinates or a place name.
+#[tauri::command]
+async fn set_note_location(
+    id: String,
+    location: String,
+    state: State<'_, AppState>,
+) -> Result<Note, ScratchError> {
+    let location = location.trim().to_string();
+    if location.is_empty() {
+        return Err(ScratchError::invalid("Location cannot be empty"));
+    }
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let _guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    let updated = frontmatter::set(&content, "location", frontmatter::Value::Scalar(location));
+    Ok(write_note_content(&state, &folder_path, &id, updated).await?)
+}
+
+/// Book notes grouped into reading, to-read, finished and abandoned, with
+/// their progress.
+#[tauri::command]
+async fn get_reading_list(
+    state: State<'_, AppState>,
+) -> Result<reading::ReadingList, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref());
+        reading::build(&notes, &locked_policy, chrono::Local::now().date_naive())
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Rewrite a book note's frontmatter with `update` and return it as it now
+/// shows on the reading list.
+async fn update_book_note(
+    state: &AppState,
+    id: &str,
+    update: impl FnOnce(&str, chrono::NaiveDate) -> Result<String, String>,
+) -> Result<reading::ReadingItem, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, id)?;
+    let _guard = state.note_locks.lock(&folder_path, &[id]).await;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, id))?;
+    if schemas::declared(&content) != Some("book") {
+        return Err(ScratchError::invalid(
+            "The note is not a book; set `type: book` or tag it #book",
+        ));
+    }
+    let today = chrono::Local::now().date_naive();
+    let updated = update(&content, today).map_err(ScratchError::invalid)?;
+    let note = write_note_content(state, &folder_path, id, updated).await?;
+    Ok(reading::item(
+        &note.id,
+        &note.title,
+        &note.content,
+        note.modified,
+    ))
+}
+
+/// Set a book's `progress` (`120`, `120/350` or `35%`), moving it to reading,
+/// or to finished when read to the end.
+#[tauri::command]
+async fn update_reading_progress(
+    id: String,
+    progress: String,
+    state: State<'_, AppState>,
+) -> Result<reading::ReadingItem, ScratchError> {
+    update_book_note(&state, &id, |content, today| {
+        reading::update_progress(content, &progress, today)
+    })
+    .await
+}
+
+/// Set a book's reading status, stamping `started` or `finished` when unset.
+#[tauri::command]
+async fn set_reading_status(
+    id: String,
+    status: reading::ReadingStatus,
+    state: State<'_, AppState>,
+) -> Result<reading::ReadingItem, ScratchError> {
+    update_book_note(&state, &id, |content, today| {
+        Ok(reading::set_status(content, status, today))
+    })
+    .await
+}
+
+/// TODO, FIXME and HACK markers in fenced code blocks and inline code across
+/// the vault, with their note, line and language.
+#[tauri::command]
+async fn list_code_annotations(
+    state: State<'_, AppState>,
+) -> Result<Vec<codemarks::CodeAnnotation>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref());
+        codemarks::collect(&notes, &locked_policy)
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Frontmatter keys driving the review queue.
+const REVIEW_LAST_KEY: &str = "last_reviewed";
+const REVIEW_INTERVAL_KEY: &str = "review_interval";
+const REVIEW_MAX_INTERVAL_DAYS: i64 = 365;
+
+// A note due for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewItem {
+    pub id: String,
+    pub title: String,
+    pub last_reviewed: Option<String>, // YYYY-MM-DD
+    pub interval_days: i64,
+    pub due: String, // YYYY-MM-DD
+    pub overdue_days: i64,
+}
+
+/// Review schedule of a note, if it participates in the review queue
+/// (`review: true` or a `last_reviewed:` date in frontmatter).
+fn review_schedule(content: &str) -> Option<(Option<chrono::NaiveDate>, i64)> {
+    let fm = frontmatter::Frontmatter::parse(content);
+    let last = fm
+        .get(REVIEW_LAST_KEY)
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    if last.is_none() && !fm.get_bool("review").unwrap_or(false) {
+        return None;
+    }
+    let interval = fm
+        .get(REVIEW_INTERVAL_KEY)
+        .and_then(|i| i.parse::<i64>().ok())
+        .unwrap_or(1)
+        .clamp(1, REVIEW_MAX_INTERVAL_DAYS);
+    Some((last, interval))
+}
+
+/// Notes whose review date has passed, most overdue first.
+#[tauri::command]
+async fn get_review_queue(state: State<'_, AppState>) -> Result<Vec<ReviewItem>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let today = chrono::Local::now().date_naive();
+        let mut items: Vec<ReviewItem> = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref())
+            .into_iter()
+            .filter_map(|note| {
+                let (last, interval) = review_schedule(&note.content)?;
+                let due = last
+                    .map(|d| d + chrono::Duration::days(interval))
+                    .unwrap_or(today);
+                if due > today {
+                    return None;
+                }
+                let (title, _) = listing_title_and_preview(&note.content, &locked_policy);
+                Some(ReviewItem {
+                    id: note.id,
+                    title,
+                    last_reviewed: last.map(|d| d.format("%Y-%m-%d").to_string()),
+                    interval_days: interval,
+                    due: due.format("%Y-%m-%d").to_string(),
+                    overdue_days: (today - due).num_days(),
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| b.overdue_days.cmp(&a.overdue_days).then(a.id.cmp(&b.id)));
+        items
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Record a review: stamps today's date and doubles the review interval.
+#[tauri::command]
+async fn mark_reviewed(id: String, state: State<'_, AppState>) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let _guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+
+    let next_interval = match review_schedule(&content) {
+        Some((Some(_), interval)) => (interval * 2).min(REVIEW_MAX_INTERVAL_DAYS),
+        _ => 1,
+    };
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let updated = frontmatter::set(&content, REVIEW_LAST_KEY, frontmatter::Value::Scalar(today));
+    let updated = frontmatter::set(
+        &updated,
+        REVIEW_INTERVAL_KEY,
+        frontmatter::Value::Scalar(next_interval.to_string()),
+    );
+
+    Ok(write_note_content(&state, &folder_path, &id, updated).await?)
+}
+
+// A flashcard due for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DueCard {
+    pub id: String,
+    pub note_id: String,
+    pub question: String,
+    pub answer: String,
+    pub schedule: flashcards::CardSchedule,
+}
+
+/// Flashcards across the vault that are new or due today.
+#[tauri::command]
+async fn get_due_cards(state: State<'_, AppState>) -> Result<Vec<DueCard>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let store = flashcards::FlashcardStore::load(&folder);
+        let today = chrono::Local::now().date_naive();
+
+        let mut due: Vec<DueCard> = Vec::new();
+        for note in scan_analyzable_notes(Path::new(&folder), private_folders.as_deref()) {
+            // Locked notes only contribute cards when their content is searchable
+            if !note_exposure(&note.content, &locked_policy).index_content {
+                continue;
+            }
+            for card in flashcards::extract_cards(&note.id, &note.content) {
+                let schedule = store.schedule(&card.id, today);
+                if schedule.is_due(today) {
+                    due.push(DueCard {
+                        id: card.id,
+                        note_id: note.id.clone(),
+                        question: card.question,
+                        answer: card.answer,
+                        schedule,
+                    });
+                }
+            }
+        }
+        due.sort_by(|a, b| a.schedule.due.cmp(&b.schedule.due).then(a.note_id.cmp(&b.note_id)));
+        due
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Grade a flashcard review (SM-2, 0-5) and return its new schedule.
+#[tauri::command]
+async fn grade_card(
+    id: String,
+    grade: u8,
+    state: State<'_, AppState>,
+) -> Result<flashcards::CardSchedule, ScratchError> {
+    if grade > 5 {
+        return Err(ScratchError::invalid("Grade must be between 0 and 5"));
+    }
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let mut store = flashcards::FlashcardStore::load(&folder);
+        let schedule = store.grade(&id, grade, chrono::Local::now().date_naive());
+        store.save(&folder).map_err(|e| e.to_string())?;
+        Ok::<_, String>(schedule)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+/// Export the flashcards in the notes `scope` selects (all by default) as an
+/// Anki import file at `path`, with the images they use beside it.
+#[tauri::command]
+async fn export_anki(
+    path: String,
+    scope: Option<anki::AnkiScope>,
+    state: State<'_, AppState>,
+) -> Result<anki::AnkiExport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+    let note_policies = note_policies(&state);
+
+    tokio::task::spawn_blocking(move || {
+        let notes_root = Path::new(&folder);
+        // Locked notes only contribute cards when their content is searchable
+        let notes: Vec<ScannedNote> = scan_analyzable_notes(notes_root, private_folders.as_deref())
+            .into_iter()
+            .filter(|note| note_exposure(&note.content, &locked_policy).index_content)
+            .filter(|note| {
+                let export = policies::Restriction::Export;
+                !policies::forbids(&note_policies, export, &note.id, &note.content)
+            })
+            .collect();
+        anki::export(&notes, &scope.unwrap_or_default(), &path, notes_root)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NoteTranslation {
+    content: String,
+    note: Option<Note>, // the saved copy, when `save` was set
+}
+
+/// Translate a note into `target_lang` (e.g. "de", "pt-BR"). The result links
+/// back to the original in its frontmatter; with `save` it is also written as
+/// a new note next to it, named with a `-<lang>` suffix.
+#[tauri::command]
+async fn translate_note(
+    id: String,
+    target_lang: String,
+    provider: translate::Provider,
+    save: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<NoteTranslation, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let settings = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.translation.clone().unwrap_or_default()
+    };
+    let folder_path = PathBuf::from(&folder);
+    let content = fs::read_to_string(abs_path_from_id(&folder_path, &id)?)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+
+    let key = tokio::task::spawn_blocking(move || translate::get_key(provider)).await??;
+    let translated =
+        translate::translate(&id, &content, &target_lang, provider, key, &settings).await?;
+    if !save.unwrap_or(false) {
+        return Ok(NoteTranslation {
+            content: translated,
+            note: None,
+        });
+    }
+
+    let base_id = format!("{}-{}", id, sanitize_filename(&target_lang.trim().to_lowercase()));
+    let (new_id, new_path) = claim_note_file(&folder_path, None, |attempt| match attempt {
+        0 => base_id.clone(),
+        n => format!("{}-{}", base_id, n),
+    })?;
+    let _guard = state.note_locks.lock(&folder_path, &[new_id.as_str()]).await;
+    let note = match write_note_content(&state, &folder_path, &new_id, translated.clone()).await {
+        Ok(note) => note,
+        Err(e) => {
+            let _ = fs::remove_file(&new_path).await;
+            return Err(e.into());
+        }
+    };
+    Ok(NoteTranslation {
+        content: translated,
+        note: Some(note),
+    })
+}
+
+/// Store the API key for a translation provider in the OS keychain, or
+/// remove it when `key` is empty.
+#[tauri::command]
+async fn set_translation_key(
+    provider: translate::Provider,
+    key: Option<String>,
+) -> Result<(), ScratchError> {
+    tokio::task::spawn_blocking(move || translate::set_key(provider, key.as_deref()))
+        .await?
+        .map_err(ScratchError::from)
+}
+
+/// Save a web page with its stylesheets and images as MHTML in
+/// `assets/archives/`. With `note_id`, the note records it as `archive`.
+#[tauri::command]
+async fn archive_url(
+    url: String,
+    note_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<archive::ArchivedPage, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    if let Some(ref id) = note_id {
+        if !abs_path_from_id(&folder_path, id)?.exists() {
+            return Err(ScratchError::NoteNotFound { id: id.clone() });
+        }
+    }
+
+    let page = archive::archive_url(&folder_path, &url).await?;
+    if let Some(id) = note_id {
+        archive::record(&state, &folder_path, &id, &page).await?;
+    }
+    Ok(page)
+}
+
+/// References in the vault's library matching `query`, with the literature
+/// note each one has, if any.
+#[tauri::command]
+async fn search_references(
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<references::Reference>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let settings = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.references.clone().unwrap_or_default()
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let folder_path = PathBuf::from(&folder);
+        let library = references::load(&settings.library_path(&folder_path)?)?;
+        let mut matches = references::search(&library, &query);
+        for reference in &mut matches {
+            let note_id = references::literature_note_id(&settings, &reference.key);
+            if abs_path_from_id(&folder_path, &note_id)?.exists() {
+                reference.literature_note = Some(note_id);
+            }
+        }
+        Ok::<_, String>(matches)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+/// Citation text for reference `key`, rendered with the `citationFormat`
+/// setting, for the editor to insert.
+#[tauri::command]
+async fn insert_citation(key: String, state: State<'_, AppState>) -> Result<String, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let settings = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.references.clone().unwrap_or_default()
+    };
+
+    let library_path = settings.library_path(Path::new(&folder))?;
+    let library = tokio::task::spawn_blocking(move || references::load(&library_path)).await??;
+    if !library.iter().any(|reference| reference.key == key) {
+        return Err(ScratchError::not_found(format!(
+            "No reference with key {}",
+            key
+        )));
+    }
+    Ok(settings.citation(&key))
+}
+
+/// Write a literature note for reference `key` into the `literatureFolder`,
+/// or return the one it already has.
+#[tauri::command]
+async fn create_literature_note(
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let settings = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.references.clone().unwrap_or_default()
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let library_path = settings.library_path(&folder_path)?;
+    let library = tokio::task::spawn_blocking(move || references::load(&library_path)).await??;
+    let reference = library
+        .iter()
+        .find(|reference| reference.key == key)
+        .ok_or_else(|| ScratchError::not_found(format!("No reference with key {}", key)))?;
+
+    let id = references::literature_note_id(&settings, &key);
+    let path = abs_path_from_id(&folder_path, &id)?;
+    let _guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+    if let Ok(content) = fs::read_to_string(&path).await {
+        let modified = fs::metadata(&path)
+            .await
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        return Ok(Note {
+            title: extract_title(&content),
+            path: rel_path_from_id(&id),
+            id,
+            content,
+            modified,
+        });
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| ScratchError::io(e, parent))?;
+    }
+    let content = references::literature_note(reference);
+    Ok(write_note_content(&state, &folder_path, &id, content).await?)
+}
+
+/// Run fenced code block `block_index` of note `id` in a sandbox, streaming
+/// `code-block-output` events. With `write_result`, the output is written
+/// under the block.
+#[tauri::command]
+async fn run_code_block(
+    app: AppHandle,
+    id: String,
+    block_index: usize,
+    write_result: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<runner::CodeRun, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let settings = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .code_blocks
+        .clone();
+    if settings.languages.is_empty() {
+        return Err(ScratchError::PermissionDenied {
+            path: None,
+            message: "Running code blocks is turned off".to_string(),
+        });
+    }
+    let folder_path = PathBuf::from(&folder);
+    let content = fs::read_to_string(abs_path_from_id(&folder_path, &id)?)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    let block = runner::code_block(&content, block_index)
+        .ok_or_else(|| ScratchError::invalid(format!("Note has no code block {}", block_index)))?;
+
+    let run = {
+        let id = id.clone();
+        tokio::task::spawn_blocking(move || runner::run(&app, &settings, &id, block_index, &block))
+            .await??
+    };
+
+    if write_result.unwrap_or(false) {
+        let _guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+        let current = fs::read_to_string(abs_path_from_id(&folder_path, &id)?)
+            .await
+            .map_err(|e| ScratchError::note_io(e, &id))?;
+        let updated = runner::with_output(&current, block_index, &run.output).ok_or_else(|| {
+            ScratchError::invalid(format!("Note has no code block {}", block_index))
+        })?;
+        write_note_content(&state, &folder_path, &id, updated).await?;
+    }
+    Ok(run)
+}
+
+/// Read a note aloud into an audio file at `path`, outside the vault, with
+/// the platform's speech engine or this machine's speech command.
+#[tauri::command]
+async fn export_note_audio(
+    id: String,
+    path: String,
+    voice: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<tts::AudioExport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let command = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .tts_command
+        .clone();
+
+    let file_path = abs_path_from_id(Path::new(&folder), &id)?;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+
+    tokio::task::spawn_blocking(move || {
+        tts::synthesize(
+            &content,
+            &path,
+            voice.as_deref(),
+            command.as_deref(),
+            Path::new(&folder),
+        )
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+/// Write a note as a Reveal.js slide deck at `path`, outside the vault, with
+/// its images embedded. Slides break at `---` lines and H2 headings.
+#[tauri::command]
+async fn export_slides(
+    id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<slides::SlideExport, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let class_styles = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.export_class_styles.clone().unwrap_or_default()
+    };
+
+    let file_path = abs_path_from_id(Path::new(&folder), &id)?;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    let export = policies::Restriction::Export;
+    if policies::forbids(&note_policies(&state), export, &id, &content) {
+        return Err(ScratchError::PermissionDenied {
+            path: Some(id),
+            message: "This note is never exported".to_string(),
+        });
+    }
+
+    tokio::task::spawn_blocking(move || {
+        slides::export(
+            &extract_title(&content),
+            &content,
+            &id,
+            &path,
+            Path::new(&folder),
+            &class_styles,
+        )
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+#[tauri::command]
+async fn get_annotations(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<annotations::NoteAnnotations, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    abs_path_from_id(Path::new(&folder), &id)?;
+
+    Ok(annotations::AnnotationStore::load(&folder).get(&id))
+}
+
+/// Highlight a character range of a note, optionally with a margin comment.
+#[tauri::command]
+async fn add_annotation(
+    id: String,
+    start: usize,
+    end: usize,
+    comment: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<annotations::Annotation, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let file_path = abs_path_from_id(Path::new(&folder), &id)?;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+
+    let mut store = annotations::AnnotationStore::load(&folder);
+    let annotation = store.add(&id, &content, start, end, comment)?;
+    store.save(&folder)?;
+    Ok(annotation)
+}
+
+#[tauri::command]
+async fn remove_annotation(
+    id: String,
+    annotation_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    let mut store = annotations::AnnotationStore::load(&folder);
+    if !store.remove(&id, &annotation_id) {
+        return Err(ScratchError::not_found("Annotation not found"));
+    }
+    Ok(store.save(&folder)?)
+}
+
+#[tauri::command]
+async fn set_reading_position(
+    id: String,
+    offset: usize,
+    scroll_ratio: f64,
+    state: State<'_, AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    abs_path_from_id(Path::new(&folder), &id)?;
+
+    let mut store = annotations::AnnotationStore::load(&folder);
+    store.set_reading_position(&id, offset, scroll_ratio);
+    Ok(store.save(&folder)?)
+}
+
+/// Run a saved export profile by name. Blocking; shared by the command and
+/// scheduled automations.
+fn export_with_profile(state: &AppState, name: &str) -> Result<export::ExportResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let (profile, locked_policy, class_styles) = {
+        let settings = state.settings.read().expect("settings read lock");
+        let profile = settings
+            .export_profiles
+            .as_ref()
+            .and_then(|profiles| profiles.iter().find(|p| p.name == name))
+            .cloned()
+            .ok_or_else(|| format!("Export profile not found: {}", name))?;
+        (
+            profile,
+            settings.locked_notes.clone().unwrap_or_default(),
+            settings.export_class_styles.clone().unwrap_or_default(),
+        )
+    };
+    let private_folders = active_private_folders(state);
+    let note_policies = note_policies(state);
+
+    let root = Path::new(&folder);
+    // Locked notes and notes marked never to export stay in the vault
+    let notes: Vec<ScannedNote> = scan_analyzable_notes(root, private_folders.as_deref())
+        .into_iter()
+        .filter(|n| !note_exposure(&n.content, &locked_policy).locked)
+        .filter(|n| {
+            let export = policies::Restriction::Export;
+            !policies::forbids(&note_policies, export, &n.id, &n.content)
+        })
+        .collect();
+    export::run(&profile, &notes, root, &class_styles)
+}
+
+/// The classes and `exportClassStyles` snippets a note is exported with, for
+/// printing it to PDF from the editor.
+#[tauri::command]
+async fn get_note_export_style(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<render::ExportStyle, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let path = abs_path_from_id(Path::new(&folder), &id)?;
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    let class_styles = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.export_class_styles.clone().unwrap_or_default()
+    };
+    Ok(render::export_style(&content, &class_styles))
+}
+
+#[tauri::command]
+async fn run_export_profile(
+    app: AppHandle,
+    name: String,
+) -> Result<export::ExportResult, ScratchError> {
+    tokio::task::spawn_blocking(move || export_with_profile(&app.state::<AppState>(), &name))
+        .await?
+        .map_err(ScratchError::from)
+}
+
+#[tauri::command]
+fn get_automation_status(app: AppHandle) -> Vec<automation::AutomationStatus> {
+    automation::status(&app)
+}
+
+/// Run a configured automation immediately, regardless of its trigger.
+#[tauri::command]
+async fn run_automation(
+    app: AppHandle,
+    name: String,
+) -> Result<automation::AutomationRun, ScratchError> {
+    let configured = {
+        let state = app.state::<AppState>();
+        let settings = state.settings.read().expect("settings read lock");
+        settings
+            .automations
+            .as_ref()
+            .and_then(|automations| automations.iter().find(|a| a.name == name))
+            .cloned()
+    };
+    let automation = configured
+        .ok_or_else(|| ScratchError::not_found(format!("Automation not found: {}", name)))?;
+
+    tokio::task::spawn_blocking(move || automation::run(&app, &automation))
+        .await?
+        .map_err(ScratchError::from)
+}
+
+fn inbox_settings(state: &AppState) -> inbox::InboxSettings {
+    let settings = state.settings.read().expect("settings read lock");
+    settings.inbox.clone().unwrap_or_default()
+}
+
+/// Edit an inbox note in place under its lock; unchanged content is not
+/// written.
+async fn update_inbox_note(
+    state: &AppState,
+    folder_path: &Path,
+    id: &str,
+    update: impl FnOnce(&str) -> String,
+) -> Result<(), ScratchError> {
+    let file_path = abs_path_from_id(folder_path, id)?;
+    let _guard = state.note_locks.lock(folder_path, &[id]).await;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, id))?;
+    let updated = update(&content);
+    if updated != content {
+        write_note_content(state, folder_path, id, updated).await?;
+    }
+    Ok(())
+}
+
+/// Run a matched note's actions in order, recording where it ends up in
+/// `outcome`. A move or a merge ends the run.
+async fn apply_inbox_actions(
+    state: &State<'_, AppState>,
+    folder_path: &Path,
+    actions: impl Iterator<Item = &inbox::InboxAction>,
+    outcome: &mut inbox::InboxOutcome,
+) -> Result<(), ScratchError> {
+    let id = outcome.id.clone();
+    for action in actions {
+        match action {
+            inbox::InboxAction::Tag { tags } => {
+                update_inbox_note(state, folder_path, &id, |content| {
+                    inbox::add_tags(content, tags)
+                })
+                .await?;
+            }
+            inbox::InboxAction::Template { template } => {
+                let template_path = abs_path_from_id(folder_path, template)?;
+                let template = fs::read_to_string(&template_path)
+                    .await
+                    .map_err(|e| ScratchError::note_io(e, template))?;
+                let ctx = templates::TemplateContext {
+                    now: chrono::Local::now(),
+                    vault_name: folder_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    selection: None,
+                    clipboard: None,
+                    weather: None,
+                };
+                let expanded = state.template_variables.expand(&template, &ctx);
+                update_inbox_note(state, folder_path, &id, |content| {
+                    inbox::apply_template(&expanded, content)
+                })
+                .await?;
+            }
+            inbox::InboxAction::Move { folder } => {
+                let new_id = move_note(id.clone(), folder.clone(), state.clone()).await?;
+                outcome.note_id = Some(new_id);
+                return Ok(());
+            }
+            inbox::InboxAction::Merge { target } => {
+                if target == &id {
+                    return Err(ScratchError::invalid("A note cannot be merged into itself"));
+                }
+                let target_path = abs_path_from_id(folder_path, target)?;
+                if !target_path.is_file() {
+                    return Err(ScratchError::NoteNotFound { id: target.clone() });
+                }
+                autosave::flush(state, &id).await?;
+                {
+                    let _guard = state
+                        .note_locks
+                        .lock(folder_path, &[id.as_str(), target.as_str()])
+                        .await;
+                    let content = fs::read_to_string(abs_path_from_id(folder_path, &id)?)
+                        .await
+                        .map_err(|e| ScratchError::note_io(e, &id))?;
+                    let target_content = fs::read_to_string(&target_path)
+                        .await
+                        .map_err(|e| ScratchError::note_io(e, target))?;
+                    let merged = inbox::merge(&target_content, &content);
+                    write_note_content(state, folder_path, target, merged).await?;
+                }
+                delete_note(id.clone(), None, state.clone()).await?;
+                outcome.note_id = None;
+                outcome.merged_into = Some(target.clone());
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run the inbox rules over one note. None when no rule matches it; a
+/// failed action is reported in the outcome.
+async fn process_inbox_note(
+    state: &State<'_, AppState>,
+    folder_path: &Path,
+    rules: &[inbox::CompiledRule<'_>],
+    id: &str,
+) -> Option<inbox::InboxOutcome> {
+    let content = fs::read_to_string(abs_path_from_id(folder_path, id).ok()?)
+        .await
+        .ok()?;
+    let matched = inbox::matching(rules, &extract_title(&content), &content);
+    if matched.is_empty() {
+        return None;
+    }
+    let mut outcome = inbox::InboxOutcome {
+        id: id.to_string(),
+        rules: matched.iter().map(|rule| rule.name().to_string()).collect(),
+        note_id: Some(id.to_string()),
+        merged_into: None,
+        error: None,
+    };
+    let actions = matched.iter().flat_map(|rule| rule.actions());
+    if let Err(e) = apply_inbox_actions(state, folder_path, actions, &mut outcome).await {
+        outcome.error = Some(e.to_string());
+    }
+    Some(outcome)
+}
+
+/// Run the inbox rules over a note just captured, if it landed in the inbox.
+async fn process_captured_note(
+    state: &State<'_, AppState>,
+    id: &str,
+) -> Option<inbox::InboxOutcome> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()?
+    };
+    let settings = inbox_settings(state);
+    if settings.rules.is_empty() || !inbox::contains(&inbox::folder(&settings), id) {
+        return None;
+    }
+    let rules = match inbox::compile(&settings) {
+        Ok(rules) => rules,
+        Err(e) => {
+            eprintln!("Inbox rules skipped: {}", e);
+            return None;
+        }
+    };
+    process_inbox_note(state, Path::new(&folder), &rules, id).await
+}
+
+/// The ID a captured note has after the inbox rules ran: where it was moved
+/// or merged, or its own. Blocking, for capture outside a command.
+fn sort_captured_note(app: &AppHandle, id: String) -> String {
+    let state = app.state::<AppState>();
+    tauri::async_runtime::block_on(process_captured_note(&state, &id))
+        .and_then(|outcome| outcome.note_id.or(outcome.merged_into))
+        .unwrap_or(id)
+}
+
+/// Archive or trash the notes that `expires:` frontmatter and the
+/// `retention` folder rules make due, or with `dry_run` only list them.
+/// Runs that acted are added to the retention report.
+async fn enforce_retention(
+    state: &State<'_, AppState>,
+    dry_run: bool,
+) -> Result<retention::RetentionRun, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let settings = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.retention.clone().unwrap_or_default()
+    };
+    let archive_folder = retention::archive_folder(&settings);
+    validate_folder_path(&archive_folder)?;
+
+    let root = PathBuf::from(&folder);
+    let today = chrono::Local::now().date_naive();
+    let notes = tokio::task::spawn_blocking(move || scan_notes(&root)).await?;
+    let due = retention::due(&settings, &notes, today);
+
+    let mut run = retention::RetentionRun {
+        ran_at: chrono::Utc::now().timestamp(),
+        dry_run,
+        notes: Vec::with_capacity(due.len()),
+    };
+    for due in due {
+        let mut note = retention::RetainedNote {
+            id: due.id.clone(),
+            action: due.action,
+            reason: due.reason,
+            new_id: None,
+            error: None,
+        };
+        if !dry_run {
+            let result = match due.action {
+                retention::RetentionAction::Archive => {
+                    let target = retention::archive_target(&archive_folder, &due.id);
+                    move_note(due.id, target, state.clone()).await.map(Some)
+                }
+                retention::RetentionAction::Trash => {
+                    delete_note(due.id, None, state.clone()).await.map(|_| None)
+                }
+            };
+            match result {
+                Ok(new_id) => note.new_id = new_id,
+                Err(e) => note.error = Some(e.to_string()),
+            }
+        }
+        run.notes.push(note);
+    }
+
+    if !dry_run && !run.notes.is_empty() {
+        let mut report = retention::RetentionReport::load(&folder);
+        report.record(run.clone());
+        report.save(&folder)?;
+    }
+    Ok(run)
+}
+
+/// Apply note expiry and retention now rather than at the next hourly run.
+/// `dry_run` lists what is due without archiving or trashing anything.
+#[tauri::command]
+async fn apply_retention(
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<retention::RetentionRun, ScratchError> {
+    enforce_retention(&state, dry_run.unwrap_or(false)).await
+}
+
+/// Past retention runs that archived or trashed notes, newest first.
+#[tauri::command]
+fn get_retention_report(
+    state: State<AppState>,
+) -> Result<Vec<retention::RetentionRun>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    Ok(retention::RetentionReport::load(&folder).runs)
+}
+
+/// Run the inbox rules over every note in the inbox folder.
+#[tauri::command]
+async fn run_inbox_rules(state: State<'_, AppState>) -> Result<inbox::InboxRun, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let settings = inbox_settings(&state);
+    let rules = inbox::compile(&settings).map_err(ScratchError::invalid)?;
+    let inbox_folder = inbox::folder(&settings);
+    validate_folder_path(&inbox_folder)?;
+
+    let folder_path = PathBuf::from(&folder);
+    let inbox_root = folder_path.join(&inbox_folder);
+    let mut ids: Vec<String> = tokio::task::spawn_blocking(move || scan_notes(&inbox_root))
+        .await?
+        .into_iter()
+        .map(|note| format!("{}/{}", inbox_folder, note.id))
+        .collect();
+    ids.sort();
+
+    let mut run = inbox::InboxRun::default();
+    for id in ids {
+        match process_inbox_note(&state, &folder_path, &rules, &id).await {
+            Some(outcome) => run.processed.push(outcome),
+            None => run.unmatched += 1,
+        }
+    }
+    Ok(run)
+}
+
+/// Render a note, encrypt it and upload it to the configured share endpoint.
+/// `ttl` is the link lifetime in seconds (default one day).
+#[tauri::command]
+async fn share_note_link(
+    id: String,
+    ttl: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<share::ShareLink, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let (endpoint, class_styles, locked_policy) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            settings
+                .share_endpoint
+                .clone()
+                .filter(|e| !e.trim().is_empty())
+                .ok_or("Share endpoint not configured")?,
+            settings.export_class_styles.clone().unwrap_or_default(),
+            settings.locked_notes.clone().unwrap_or_default(),
+        )
+    };
+
+    let file_path = abs_path_from_id(Path::new(&folder), &id)?;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &id))?;
+    let export = policies::Restriction::Export;
+    if policies::forbids(&note_policies(&state), export, &id, &content) {
+        return Err(ScratchError::PermissionDenied {
+            path: Some(id),
+            message: "This note is never exported".to_string(),
+        });
+    }
+    // Locked and private notes only leave the machine while privacy is unlocked
+    if let Some(private_folders) = active_private_folders(&state) {
+        if note_exposure(&content, &locked_policy).locked
+            || is_private_note(&id, &content, &private_folders)
+        {
+            return Err(ScratchError::PermissionDenied {
+                path: Some(id),
+                message: "Unlock private notes to share this note".to_string(),
+            });
+        }
+    }
+    let token = tokio::task::spawn_blocking(share::get_token).await??;
+    let html = render::render_page(
+        &extract_title(&content),
+        &content,
+        "",
+        None,
+        Some(&class_styles),
+    );
+
+    let (link, record) = share::create(
+        &endpoint,
+        token.as_deref(),
+        &id,
+        &html,
+        share::clamp_ttl(ttl),
+    )
+    .await?;
+
+    let mut store = share::ShareStore::load(&folder);
+    store.shares.push(record);
+    store.save(&folder)?;
+
+    Ok(link)
+}
+
+/// Store the share endpoint's bearer token in the OS keychain, or remove it
+/// when `token` is empty.
+#[tauri::command]
+async fn set_share_token(token: Option<String>) -> Result<(), ScratchError> {
+    tokio::task::spawn_blocking(move || share::set_token(token.as_deref()))
+        .await?
+        .map_err(ScratchError::from)
+}
+
+/// Revoke every active share of a note. Returns how many were revoked.
+#[tauri::command]
+async fn revoke_share(id: String, state: State<'_, AppState>) -> Result<usize, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let token = tokio::task::spawn_blocking(share::get_token).await??;
+
+    let mut store = share::ShareStore::load(&folder);
+    let (targets, remaining): (Vec<_>, Vec<_>) =
+        store.shares.drain(..).partition(|s| s.note_id == id);
+    store.shares = remaining;
+
+    let mut revoked = 0;
+    let mut first_error = None;
+    for record in targets {
+        match share::revoke(&record, token.as_deref()).await {
+            Ok(()) => revoked += 1,
+            Err(e) => {
+                // Keep failed shares so revoking can be retried
+                first_error.get_or_insert(e);
+                store.shares.push(record);
+            }
+        }
+    }
+    store.save(&folder)?;
+
+    match first_error {
+        Some(e) => Err(e.into()),
+        None => Ok(revoked),
+    }
+}
+
+/// Start hosting a live co-editing session for a note (experimental).
+#[tauri::command]
+async fn host_session(
+    app: AppHandle,
+    note_id: String,
+    state: State<'_, AppState>,
+) -> Result<collab::SessionInfo, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    if state
+        .collab_session
+        .lock()
+        .expect("collab session mutex")
+        .is_some()
+    {
+        return Err(ScratchError::already_exists(
+            "A collaboration session is already running",
+        ));
+    }
+
+    let file_path = abs_path_from_id(Path::new(&folder), &note_id)?;
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ScratchError::note_io(e, &note_id))?;
+
+    let session = collab::Session::host(app, note_id, &content).await?;
+    let info = session.info();
+    let mut current = state.collab_session.lock().expect("collab session mutex");
+    if current.is_some() {
+        return Err(ScratchError::already_exists(
+            "A collaboration session is already running",
+        ));
+    }
+    *current = Some(session);
+    Ok(info)
+}
+
+/// Join a session hosted by another instance using its join code (experimental).
+#[tauri::command]
+async fn join_session(
+    app: AppHandle,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<collab::SessionInfo, ScratchError> {
+    if state
+        .collab_session
+        .lock()
+        .expect("collab session mutex")
+        .is_some()
+    {
+        return Err(ScratchError::already_exists(
+            "A collaboration session is already running",
+        ));
+    }
+
+    let session = collab::Session::join(app, &code).await?;
+    let info = session.info();
+    let mut current = state.collab_session.lock().expect("collab session mutex");
+    if current.is_some() {
+        return Err(ScratchError::already_exists(
+            "A collaboration session is already running",
+        ));
+    }
+    *current = Some(session);
+    Ok(info)
+}
+
+/// Send the editor's current content to the other session participants.
+#[tauri::command]
+fn update_session_content(content: String, state: State<AppState>) -> Result<(), ScratchError> {
+    let session = state.collab_session.lock().expect("collab session mutex");
+    match *session {
+        Some(ref session) => {
+            session.update_content(&content);
+            Ok(())
+        }
+        None => Err(ScratchError::not_found(
+            "No collaboration session is running",
+        )),
+    }
+}
+
+#[tauri::command]
+fn get_session(state: State<AppState>) -> Option<collab::SessionInfo> {
+    let session = state.collab_session.lock().expect("collab session mutex");
+    session.as_ref().map(|s| s.info())
+}
+
+#[tauri::command]
+fn leave_session(state: State<AppState>) {
+    state.collab_session.lock().expect("collab session mutex").take();
+}
+
+/// Serve rendered notes over HTTP, optionally behind basic auth. Only this
+/// machine can connect unless `lan` is true. Read-only unless `readonly` is
+/// false, which also enables a simple editor and requires basic auth.
+#[tauri::command]
+async fn serve_vault(
+    app: AppHandle,
+    port: u16,
+    readonly: Option<bool>,
+    username: Option<String>,
+    password: Option<String>,
+    lan: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<serve::ServeInfo, ScratchError> {
+    if state
+        .vault_server
+        .lock()
+        .expect("vault server mutex")
+        .is_some()
+    {
+        return Err(ScratchError::already_exists(
+            "The vault is already being served",
+        ));
+    }
+    let credentials = match (username, password) {
+        (Some(user), Some(pass)) if !user.is_empty() && !pass.is_empty() => {
+            Some(format!("{}:{}", user, pass))
+        }
+        (None, None) => None,
+        _ => {
+            return Err(ScratchError::invalid(
+                "Both username and password are required for authentication",
+            ))
+        }
+    };
+    let options = serve::ServeOptions {
+        readonly: readonly.unwrap_or(true),
+        credentials,
+        lan: lan.unwrap_or(false),
+    };
+
+    let server = serve::start(app, port, options).await?;
+    let info = server.info.clone();
+    let mut current = state.vault_server.lock().expect("vault server mutex");
+    if current.is_some() {
+        return Err(ScratchError::already_exists(
+            "The vault is already being served",
+        ));
+    }
+    *current = Some(server);
+    Ok(info)
+}
+
+#[tauri::command]
+fn stop_serving_vault(state: State<AppState>) {
+    state.vault_server.lock().expect("vault server mutex").take();
+}
+
+/// Issue an access token for an external surface. The token itself is only
+/// returned here.
+#[tauri::command]
+fn create_access_token(
+    surface: capabilities::Surface,
+    scope: capabilities::Scope,
+    label: String,
+    state: State<AppState>,
+) -> Result<capabilities::NewToken, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let token = capabilities::create(Path::new(&folder), surface, scope, &label)?;
+    Ok(token)
+}
+
+#[tauri::command]
+fn list_access_tokens(
+    state: State<AppState>,
+) -> Result<Vec<capabilities::TokenInfo>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    Ok(capabilities::list(Path::new(&folder)))
+}
+
+#[tauri::command]
+fn revoke_access_token(id: String, state: State<AppState>) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    Ok(capabilities::revoke(Path::new(&folder), &id)?)
+}
+
+// Privacy mode status for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyStatus {
+    pub has_passphrase: bool,
+    pub unlocked: bool,
+    pub unlocked_seconds_remaining: u64,
+}
+
+#[tauri::command]
+fn get_privacy_status(state: State<AppState>) -> PrivacyStatus {
+    let has_passphrase = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .privacy_passphrase_hash
+        .is_some();
+    let remaining = state
+        .privacy_unlocked_until
+        .lock()
+        .expect("privacy unlock mutex")
+        .map(|until| until.saturating_duration_since(Instant::now()).as_secs())
+        .unwrap_or(0);
+    PrivacyStatus {
+        has_passphrase,
+        unlocked: remaining > 0,
+        unlocked_seconds_remaining: remaining,
+    }
+}
+
+/// Set a new privacy passphrase, the private folders, or both; `None` keeps
+/// what is set. Once a passphrase is set, `current` must match it.
+#[tauri::command]
+fn set_privacy_passphrase(
+    current: Option<String>,
+    passphrase: Option<String>,
+    private_folders: Option<Vec<String>>,
+    state: State<AppState>,
+) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    if passphrase.as_ref().is_some_and(|p| p.len() < 4) {
+        return Err(ScratchError::invalid(
+            "Passphrase must be at least 4 characters",
+        ));
+    }
+
+    let mut settings = state.settings.write().expect("settings write lock");
+    if let Some(ref stored) = settings.privacy_passphrase_hash {
+        let current = current.ok_or(ScratchError::invalid("Current passphrase required"))?;
+        if !passphrase::verify(&current, stored) {
+            return Err(ScratchError::PermissionDenied {
+                path: None,
+                message: "Incorrect passphrase".to_string(),
+            });
+        }
+    }
+
+    if let Some(passphrase) = passphrase {
+        settings.privacy_passphrase_hash = Some(passphrase::hash(&passphrase));
+    }
+    if let Some(private_folders) = private_folders {
+        mark_private_folders_unindexed(Path::new(&folder), &private_folders);
+        settings.private_folders = Some(private_folders).filter(|f| !f.is_empty());
+    }
+    Ok(save_settings(&folder, &settings)?)
+}
+
+/// Temporarily disable privacy mode so private notes show up in lists and search.
+#[tauri::command]
+fn unlock_private_notes(
+    passphrase: String,
+    minutes: Option<u64>,
+    state: State<AppState>,
+) -> Result<(), ScratchError> {
+    let stored = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .privacy_passphrase_hash
+        .clone()
+        .ok_or("No privacy passphrase set")?;
+
+    if !passphrase::verify(&passphrase, &stored) {
+        return Err(ScratchError::PermissionDenied {
+            path: None,
+            message: "Incorrect passphrase".to_string(),
+        });
+    }
+    if passphrase::needs_rehash(&stored) {
+        let folder = state
+            .app_config
+            .read()
+            .expect("app_config read lock")
+            .notes_folder
+            .clone();
+        let mut settings = state.settings.write().expect("settings write lock");
+        settings.privacy_passphrase_hash = Some(passphrase::hash(&passphrase));
+        if let Some(folder) = folder {
+            let _ = save_settings(&folder, &settings);
+        }
+    }
+
+    let minutes = minutes.unwrap_or(15).clamp(1, 24 * 60);
+    let mut unlocked = state
+        .privacy_unlocked_until
+        .lock()
+        .expect("privacy unlock mutex");
+    *unlocked = Some(Instant::now() + Duration::from_secs(minutes * 60));
+    Ok(())
+}
+
+#[tauri::command]
+fn lock_private_notes(state: State<AppState>) {
+    *state
+        .privacy_unlocked_until
+        .lock()
+        .expect("privacy unlock mutex") = None;
+}
+
+// Fallback search when Tantivy index isn't available - searches title and full content
+async fn fallback_search(query: &str, state: &State<'_, AppState>) -> Result<Vec<SearchResult>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    let folder = match folder {
+        Some(f) => f,
+        None => return Ok(vec![]),
+    };
+
+    // Collect cache data upfront to avoid holding lock during async operations
+    let cache_data: Vec<(String, String, String, i64)> = {
+        let cache = state.notes_cache.read().expect("cache read lock");
+        cache
+            .values()
+            .map(|note| {
+                (
+                    note.id.clone(),
+                    note.title.clone(),
+                    note.preview.clone(),
+                    note.modified,
+                )
+            })
+            .collect()
+    };
+
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let query_lower = query.to_lowercase();
+    let mut results: Vec<SearchResult> = Vec::new();
+
+    for (id, title, preview, modified) in cache_data {
+        let title_lower = title.to_lowercase();
+
+        let mut score = 0.0f32;
+        if title_lower.contains(&query_lower) {
+            score += 50.0;
+        }
+
+        // Read file content asynchronously and search in it
+        let file_path = match abs_path_from_id(&folder_path, &id) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+        if let Ok(content) = tokio::fs::read_to_string(&file_path).await {
+            // Locked notes only match on content when the policy allows indexing it
+            let searchable = note_exposure(&content, &locked_policy).index_content;
+            if searchable && content.to_lowercase().contains(&query_lower) {
+                // Higher score if in title, lower if only in content
+                if score == 0.0 {
+                    score += 10.0;
+                } else {
+                    score += 5.0;
+                }
+            }
+        }
+
+        if score > 0.0 {
+            results.push(SearchResult {
+                id,
+                title,
+                preview,
+                modified,
+                score,
+            });
+        }
+    }
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(20);
+
+    Ok(results)
+}
+
+// File watcher event payload
+#[derive(Clone, Serialize)]
+struct FileChangeEvent {
+    kind: String,
+    path: String, // vault-relative
+    changed_ids: Vec<String>,
+}
+
+// Payload of `notes-updated`: the notes cache entries a file change touched
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NotesDelta {
+    upserted: Vec<NoteMetadata>,
+    removed: Vec<String>,
+}
+
+/// Bring note `id`'s notes cache entry in line with its file at `path`.
+/// Private notes (while privacy mode is active) leave the cache like
+/// deleted ones.
+fn update_notes_cache(state: &AppState, id: &str, path: &Path) -> NotesDelta {
+    let mut delta = NotesDelta {
+        upserted: Vec::new(),
+        removed: Vec::new(),
+    };
+    let read_limit = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.read_limits.clone().unwrap_or_default().listing()
+    };
+    let content = match read_limit.read(path) {
+        Ok(content) => Some(content),
+        Err(_) if !path.exists() => None,
+        // Mid-write or unreadable for now; a later event brings it in
+        Err(_) => return delta,
+    };
+    let private_folders = active_private_folders(state);
+    let listed = content.filter(|content| {
+        !private_folders
+            .as_deref()
+            .is_some_and(|private| is_private_note(id, content, private))
+    });
+
+    match listed {
+        Some(content) => {
+            let locked_policy = {
+                let settings = state.settings.read().expect("settings read lock");
+                settings.locked_notes.clone().unwrap_or_default()
+            };
+            let file_metadata = std::fs::metadata(path).ok();
+            let modified = file_metadata.as_ref().map(file_modified_secs).unwrap_or(0);
+            let created = note_created_secs(&content, file_metadata.as_ref(), modified);
+            let metadata = listing_metadata(
+                id.to_string(),
+                &content,
+                modified,
+                created,
+                &locked_policy,
+                &pinned_note_ids(state),
+            );
+            let mut cache = state.notes_cache.write().expect("cache write lock");
+            cache.insert(id.to_string(), metadata.clone());
+            delta.upserted.push(metadata);
+        }
+        None => {
+            let mut cache = state.notes_cache.write().expect("cache write lock");
+            cache.remove(id);
+            delta.removed.push(id.to_string());
+        }
+    }
+    delta
+}
+
+fn setup_file_watcher(
+    app: AppHandle,
+    notes_folder: &str,
+    debounce_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+) -> Result<FileWatcherState, String> {
+    let folder_path = PathBuf::from(notes_folder);
+    let notes_root = folder_path.clone();
+    let app_handle = app.clone();
+
+    let watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| {
+            if let Ok(event) = res {
+                let ignore = app_handle
+                    .try_state::<AppState>()
+                    .and_then(|state| {
+                        let settings = state.settings.read().expect("settings read lock");
+                        settings.watcher_ignore.clone()
+                    })
+                    .unwrap_or_else(|| {
+                        DEFAULT_WATCHER_IGNORE
+                            .iter()
+                            .map(|p| p.to_string())
+                            .collect()
+                    });
+                for path in event.paths.iter() {
+                    // The vault config lives in the ignored `.scratch` folder
+                    if vaultconfig::is_config_file(&notes_root, path) {
+                        vaultconfig::reload(&app_handle, &notes_root);
+                        continue;
+                    }
+                    // Skip noisy folders before they reach the debounce map
+                    if watcher_ignores(&notes_root, path, &ignore) {
+                        continue;
+                    }
+                    // A sidecar change updates its attachment's index entry
+                    if let Some(attachment) = sidecars::attachment_for(&notes_root, path) {
+                        if let Some(state) = app_handle.try_state::<AppState>() {
+                            let index = state.search_index.lock().expect("search index mutex");
+                            if let Some(ref search_index) = *index {
+                                let _ = match sidecars::load(&notes_root, &attachment) {
+                                    Some(note) => search_index.index_note(
+                                        &note.id,
+                                        &note.title,
+                                        &note.content,
+                                        note.modified,
+                                    ),
+                                    None => {
+                                        search_index.delete_note(&sidecars::note_id(&attachment))
+                                    }
+                                };
+                            }
+                        }
+                        continue;
+                    }
+                    let note_id = match id_from_abs_path(&notes_root, path) {
+                        Some(id) => id,
+                        None => continue,
+                    };
+
+                    // Debounce with cleanup
+                    {
+                        let limit = app_handle
+                            .try_state::<AppState>()
+                            .map(|state| memory::budgets(&state))
+                            .unwrap_or_default()
+                            .debounce_entries();
+                        let mut map = debounce_map.lock().expect("debounce map mutex");
+                        let now = Instant::now();
+
+                        if map.len() >= limit {
+                            map.retain(|_, last| now.duration_since(*last) < Duration::from_secs(5));
+                            memory::trim_debounce(&mut map, limit);
+                        }
+
+                        if let Some(last) = map.get(path) {
+                            if now.duration_since(*last) < Duration::from_millis(500) {
+                                continue;
+                            }
+                        }
+                        map.insert(path.clone(), now);
+                    }
+
+                    let kind = match event.kind {
+                        notify::EventKind::Create(_) => "created",
+                        notify::EventKind::Modify(_) => "modified",
+                        notify::EventKind::Remove(_) => "deleted",
+                        // Some backends emit Any for renames or unclassified changes
+                        notify::EventKind::Any => "modified",
+                        _ => continue,
+                    };
+
+                    // Update search index for external file changes
+                    if let Some(state) = app_handle.try_state::<AppState>() {
+                        let index = state.search_index.lock().expect("search index mutex");
+                        if let Some(ref search_index) = *index {
+                            match kind {
+                                "created" | "modified" => {
+                                    match search_index.read_limit().read(path) {
+                                        Ok(content) => {
+                                            let title = extract_title(&content);
+                                            let modified = std::fs::metadata(path)
+                                                .ok()
+                                                .and_then(|m| m.modified().ok())
+                                                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                                                .map(|d| d.as_secs() as i64)
+                                                .unwrap_or(0);
+                                            let _ = search_index.index_note(&note_id, &title, &content, modified);
+                                        }
+                                        Err(_) => {
+                                            // File gone between event and read — treat as deletion
+                                            if !path.exists() {
+                                                let _ = search_index.delete_note(&note_id);
+                                            }
+                                        }
+                                    }
+                                }
+                                "deleted" => {
+                                    let _ = search_index.delete_note(&note_id);
+                                }
+                                _ => {}
+                            }
+                        }
+                        drop(index);
+
+                        match std::fs::read_to_string(path) {
+                            Ok(content) => state.link_index.update(&notes_root, &note_id, &content),
+                            Err(_) if !path.exists() => state.link_index.remove(&notes_root, &note_id),
+                            Err(_) => {}
+                        }
+                    }
+
+                    // Determine the actual kind for the frontend event
+                    // (a "modified" event on a non-existent file is really a delete)
+                    let effective_kind = if kind == "modified" && !path.exists() {
+                        "deleted"
+                    } else {
+                        kind
+                    };
+
+                    let _ = app_handle.emit(
+                        "file-change",
+                        FileChangeEvent {
+                            kind: effective_kind.to_string(),
+                            path: rel_path_from_abs(&notes_root, path),
+                            changed_ids: vec![note_id.clone()],
+                        },
+                    );
+
+                    // Update the notes cache in place and send the frontend the delta,
+                    // so the note list never needs a full relist
+                    if let Some(state) = app_handle.try_state::<AppState>() {
+                        let delta = update_notes_cache(&state, &note_id, path);
+                        if !delta.upserted.is_empty() || !delta.removed.is_empty() {
+                            let _ = app_handle.emit("notes-updated", delta);
+                        }
+                    }
+                }
+            }
+        },
+        Config::default(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut watcher = watcher;
+
+    // Watch the notes folder recursively for .md files in subfolders
+    watcher
+        .watch(&folder_path, RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+
+    Ok(FileWatcherState { watcher })
+}
+
+/// Watch `folder`, replacing the watcher on whatever folder came before.
+/// Nothing is watched in safe mode.
+fn restart_file_watcher(app: &AppHandle, state: &AppState, folder: &str) -> Result<(), String> {
+    if safemode::is_active() {
+        return Ok(());
+    }
+    let mut file_watcher = state.file_watcher.lock().expect("file watcher mutex");
+    // Stop the old watcher before the new one starts reporting
+    file_watcher.take();
+
+    // Clean up debounce map before starting
+    cleanup_debounce_map(&state.debounce_map);
+
+    let watcher_state = setup_file_watcher(app.clone(), folder, Arc::clone(&state.debounce_map))?;
+    *file_watcher = Some(watcher_state);
+    Ok(())
+}
+
+#[tauri::command]
+fn start_file_watcher(app: AppHandle, state: State<AppState>) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    restart_file_watcher(&app, &state, &folder)?;
+    Ok(())
+}
+
+/// Stop watching the notes folder; `start_file_watcher` or a folder change
+/// starts it again.
+#[tauri::command]
+fn stop_file_watcher(state: State<AppState>) {
+    state
+        .file_watcher
+        .lock()
+        .expect("file watcher mutex")
+        .take();
+}
+
+/// Create a note from the clipboard and select it in the main window.
+/// `html` is the clipboard's HTML flavour, which only the webview can read.
+#[tauri::command]
+async fn create_note_from_clipboard(
+    app: AppHandle,
+    html: Option<String>,
+    target_folder: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Note, ScratchError> {
+    let handle = app.clone();
+    let note = tokio::task::spawn_blocking(move || {
+        clipboard::create_note(&handle, html, target_folder.as_deref(), audit::Surface::Ui)
+    })
+    .await??;
+    // Inbox rules may have tagged, moved or merged it
+    let note = match process_captured_note(&state, &note.id).await {
+        Some(outcome) => {
+            let id = outcome.note_id.or(outcome.merged_into).unwrap_or(note.id);
+            read_note(id, state).await?
+        }
+        None => note,
+    };
+    let _ = app.emit_to("main", "select-note", note.id.clone());
+    Ok(note)
+}
+
+#[tauri::command]
+fn copy_to_clipboard(app: AppHandle, text: String) -> Result<(), ScratchError> {
+    app.clipboard()
+        .write_text(text)
+        .map_err(|e| ScratchError::from(e.to_string()))
+}
+
+/// Fill `{{variable}}` placeholders in a note template. The clipboard and
+/// weather are only read when the template uses them.
+#[tauri::command]
+async fn expand_template(
+    app: AppHandle,
+    template: String,
+    selection: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, ScratchError> {
+    let vault_name = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .as_deref()
+            .and_then(|folder| Path::new(folder).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let weather_url = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.weather_url.clone()
+    };
+    let clipboard = if templates::uses(&template, "clipboard") {
+        app.clipboard().read_text().ok()
+    } else {
+        None
+    };
+    let weather = match weather_url {
+        Some(url) if templates::uses(&template, "weather") => {
+            templates::fetch_weather(&url, None).await
+        }
+        _ => None,
+    };
+    let ctx = templates::TemplateContext {
+        now: chrono::Local::now(),
+        vault_name,
+        selection,
+        clipboard,
+        weather,
+    };
+    Ok(state.template_variables.expand(&template, &ctx))
+}
+
+#[tauri::command]
+fn register_template_variable(
+    name: String,
+    value: String,
+    state: State<AppState>,
+) -> Result<(), ScratchError> {
+    Ok(state.template_variables.register_value(&name, value)?)
+}
+
+#[tauri::command]
+fn unregister_template_variable(name: String, state: State<AppState>) -> bool {
+    state.template_variables.unregister(&name)
+}
+
+#[tauri::command]
+fn list_template_variables(state: State<AppState>) -> Vec<String> {
+    state.template_variables.names()
+}
+
+/// Vault-relative folder for a note's new attachments under the configured
+/// asset layout.
+fn import_limits(state: &AppState) -> importlimits::ImportLimits {
+    let settings = state.settings.read().expect("settings read lock");
+    settings.import_limits.clone().unwrap_or_default()
+}
+
+/// Emit `import-warning` in the background if an attachment just added took
+/// the vault past the warning size.
+fn warn_vault_size(app: AppHandle, notes_root: PathBuf, limits: importlimits::ImportLimits) {
+    tokio::task::spawn_blocking(move || {
+        if let Some(warning) = limits.check_vault(&notes_root, 0) {
+            let _ = app.emit("import-warning", warning);
+        }
+    });
+}
+
+fn attachment_folder(state: &AppState, notes_root: &Path, note_id: Option<&str>) -> Result<String, String> {
+    if let Some(id) = note_id {
+        abs_path_from_id(notes_root, id)?;
+    }
+    let layout = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.asset_layout.unwrap_or_default()
+    };
+    Ok(assets::folder_for(layout, note_id))
+}
+
+/// Save an image from the clipboard as an attachment of `note_id` and return
+/// the markdown that embeds it. The image is read from the system clipboard
+/// unless the frontend passes what was pasted as `base64_data`.
+#[tauri::command]
+async fn save_clipboard_image(
+    app: AppHandle,
+    note_id: Option<String>,
+    base64_data: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    let image_data = match base64_data {
+        // Guard against empty clipboard payload
+        Some(data) if data.trim().is_empty() => {
+            return Err(ScratchError::invalid("Clipboard data is empty"));
+        }
+        Some(data) => base64::engine::general_purpose::STANDARD
+            .decode(&data)
+            .map_err(|_| ScratchError::invalid("Failed to decode base64 image data"))?,
+        None => {
+            tokio::task::spawn_blocking(move || {
+                let image = app
+                    .clipboard()
+                    .read_image()
+                    .map_err(|_| "The clipboard has no image".to_string())?;
+                clipboard::encode_png(image.width(), image.height(), image.rgba())
+            })
+            .await??
+        }
+    };
+
+    // Guard against zero-byte files
+    if image_data.is_empty() {
+        return Err(ScratchError::invalid("Decoded image data is empty"));
+    }
+
+    // Create the attachment folder for this note's layout
+    let assets_rel = attachment_folder(&state, Path::new(&folder), note_id.as_deref())?;
+    let assets_dir = PathBuf::from(&folder).join(&assets_rel);
+    fs::create_dir_all(&assets_dir)
+        .await
+        .map_err(|e| ScratchError::io(e, &assets_dir))?;
+
+    // Generate unique filename with timestamp
+    let timestamp = chrono::Utc::now().timestamp();
+    let target_name = assets::unique_name(&assets_dir, &format!("screenshot-{}.png", timestamp));
+    let target_path = assets_dir.join(&target_name);
+
+    // Write the file
+    fs::write(&target_path, &image_data)
+        .await
+        .map_err(|e| ScratchError::io(e, &target_path))?;
+
+    let rel = assets::join(&assets_rel, &target_name);
+    Ok(assets::markdown_link(&rel))
+}
+
+/// Run the OS screenshot tool and save the capture as an attachment of
+/// `note_id`. Returns the markdown embed, or `None` when the user cancelled.
+#[tauri::command]
+async fn capture_screenshot(
+    app: AppHandle,
+    note_id: Option<String>,
+    mode: screenshot::CaptureMode,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let assets_rel = attachment_folder(&state, Path::new(&folder), note_id.as_deref())?;
+    let assets_dir = PathBuf::from(&folder).join(&assets_rel);
+    fs::create_dir_all(&assets_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let target_name = assets::unique_name(&assets_dir, &format!("screenshot-{}.png", timestamp));
+    let target_path = assets_dir.join(&target_name);
+
+    let captured = tokio::task::spawn_blocking(move || screenshot::capture(&app, mode, &target_path))
+        .await
+        .map_err(|e| e.to_string())??;
+    if !captured {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "![]({})",
+        assets::join(&assets_rel, &target_name)
+    )))
+}
+
+// Result of renaming an asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetRename {
+    pub path: String,               // new vault-relative path, e.g. "assets/diagram.png"
+    pub updated_notes: Vec<String>, // IDs of notes whose references were rewritten
+}
+
+/// Rename an attachment in place and rewrite every note that references it.
+/// `old_path` is vault-relative; the original extension is kept when
+/// `new_name` has none.
+#[tauri::command]
+async fn rename_asset(
+    old_path: String,
+    new_name: String,
+    state: State<'_, AppState>,
+) -> Result<AssetRename, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let root = PathBuf::from(&folder);
+
+    // Attachments can live anywhere the asset layout puts them, but notes are
+    // renamed through save/move instead
+    let old_rel = old_path.trim().to_string();
+    let old_abs = assets::resolve(&root, &old_rel)?;
+    if old_abs.extension().is_some_and(|ext| ext == "md") {
+        return Err(ScratchError::invalid("Invalid asset path"));
+    }
+    if !old_abs.is_file() {
+        return Err(ScratchError::not_found("Asset not found"));
+    }
+
+    let mut leaf = sanitize_filename(new_name.trim());
+    let (dir, old_leaf) = old_rel.rsplit_once('/').unwrap_or(("", &old_rel));
+    if let Some(ext) = Path::new(old_leaf).extension().and_then(|e| e.to_str()) {
+        if Path::new(&leaf).extension().is_none() {
+            leaf = format!("{}.{}", leaf, ext);
+        }
+    }
+    if Path::new(&leaf).extension().is_some_and(|ext| ext == "md") {
+        return Err(ScratchError::invalid("Assets cannot be renamed to .md"));
+    }
+    if leaf == old_leaf {
+        return Ok(AssetRename {
+            path: old_rel,
+            updated_notes: Vec::new(),
+        });
+    }
+    // Stay in the asset's current folder
+    let target_dir = old_abs.parent().unwrap_or(&root).to_path_buf();
+    let new_rel = assets::join(dir, &assets::unique_name(&target_dir, &leaf));
+
+    fs::rename(&old_abs, root.join(&new_rel))
+        .await
+        .map_err(|e| format!("Failed to rename asset: {}", e))?;
+    let described = sidecars::rename(&root, &old_rel, &new_rel);
+
+    let (old, new) = (old_rel.clone(), new_rel.clone());
+    let note_locks = state.note_locks.clone();
+    let rewritten = tokio::task::spawn_blocking(move || {
+        let mut rewritten = Vec::new();
+        for note in scan_notes(&root) {
+            if assets::rewrite_references(&root, &note.id, &note.content, &old, &new).is_none() {
+                continue;
+            }
+            let Ok(path) = abs_path_from_id(&root, &note.id) else {
+                continue;
+            };
+            // Rewrite what is on disk now, not the scanned copy
+            let _guard = note_locks.blocking_lock(&root, &[note.id.as_str()]);
+            let Some(updated) = std::fs::read_to_string(&path).ok().and_then(|content| {
+                assets::rewrite_references(&root, &note.id, &content, &old, &new)
+            }) else {
+                continue;
+            };
+            if std::fs::write(&path, &updated).is_ok() {
+                rewritten.push((note.id, updated));
+            }
+        }
+        rewritten
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    {
+        let modified = chrono::Utc::now().timestamp();
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            for (id, content) in &rewritten {
+                let _ = search_index.index_note(id, &extract_title(content), content, modified);
+            }
+            if described {
+                let _ = search_index.delete_note(&sidecars::note_id(&old_rel));
+                if let Some(note) = sidecars::load(Path::new(&folder), &new_rel) {
+                    let _ = search_index.index_note(
+                        &note.id,
+                        &note.title,
+                        &note.content,
+                        note.modified,
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(AssetRename {
+        path: new_rel,
+        updated_notes: rewritten.into_iter().map(|(id, _)| id).collect(),
+    })
 }
 
-fn setup_file_watcher(
+#[tauri::command]
+async fn copy_image_to_assets(
     app: AppHandle,
-    notes_folder: &str,
-    debounce_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
-) -> Result<FileWatcherState, String> {
-    let folder_path = PathBuf::from(notes_folder);
-    let notes_root = folder_path.clone();
-    let app_handle = app.clone();
-
-    let watcher = RecommendedWatcher::new(
-        move |res: Result<notify::Event, notify::Error>| {
-            if let Ok(event) = res {
-                for path in event.paths.iter() {
-                    let note_id = match id_from_abs_path(&notes_root, path) {
-                        Some(id) => id,
-                        None => continue,
-                    };
+    source_path: String,
+    note_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
 
-                    // Debounce with cleanup
-                    {
-                        let mut map = debounce_map.lock().expect("debounce map mutex");
-                        let now = Instant::now();
+    let source = PathBuf::from(&source_path);
+    if !source.exists() {
+        return Err(ScratchError::not_found("Source image file does not exist"));
+    }
 
-                        if map.len() > 100 {
-                            map.retain(|_, last| now.duration_since(*last) < Duration::from_secs(5));
-                        }
+    // Get file extension
+    let extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .ok_or(ScratchError::invalid("Invalid file extension"))?;
 
-                        if let Some(last) = map.get(path) {
-                            if now.duration_since(*last) < Duration::from_millis(500) {
-                                continue;
-                            }
-                        }
-                        map.insert(path.clone(), now);
-                    }
+    const ALLOWED_IMAGE_EXTENSIONS: &[&str] = &[
+        "jpg", "jpeg", "png", "gif", "webp", "svg", "bmp", "tiff", "tif", "ico", "avif",
+    ];
+    let ext_lower = extension.to_lowercase();
+    if !ALLOWED_IMAGE_EXTENSIONS.contains(&ext_lower.as_str()) {
+        return Err(ScratchError::invalid(
+            "Only image files can be copied to assets",
+        ));
+    }
 
-                    let kind = match event.kind {
-                        notify::EventKind::Create(_) => "created",
-                        notify::EventKind::Modify(_) => "modified",
-                        notify::EventKind::Remove(_) => "deleted",
-                        // Some backends emit Any for renames or unclassified changes
-                        notify::EventKind::Any => "modified",
-                        _ => continue,
-                    };
+    let limits = import_limits(&state);
+    let size = fs::metadata(&source)
+        .await
+        .map_err(|e| ScratchError::io(e, &source))?
+        .len();
+    let name = source.file_name().unwrap_or_default().to_string_lossy();
+    limits
+        .check_attachment(&source_path, &name, size)
+        .map_err(|warning| ScratchError::invalid(warning.to_string()))?;
 
-                    // Update search index for external file changes
-                    if let Some(state) = app_handle.try_state::<AppState>() {
-                        let index = state.search_index.lock().expect("search index mutex");
-                        if let Some(ref search_index) = *index {
-                            match kind {
-                                "created" | "modified" => {
-                                    match std::fs::read_to_string(path) {
-                                        Ok(content) => {
-                                            let title = extract_title(&content);
-                                            let modified = std::fs::metadata(path)
-                                                .ok()
-                                                .and_then(|m| m.modified().ok())
-                                                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                                                .map(|d| d.as_secs() as i64)
-                                                .unwrap_or(0);
-                                            let _ = search_index.index_note(&note_id, &title, &content, modified);
-                                        }
-                                        Err(_) => {
-                                            // File gone between event and read — treat as deletion
-                                            if !path.exists() {
-                                                let _ = search_index.delete_note(&note_id);
-                                            }
-                                        }
-                                    }
-                                }
-                                "deleted" => {
-                                    let _ = search_index.delete_note(&note_id);
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
+    // Get original filename (without extension)
+    let original_name = source
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .unwrap_or("image");
 
-                    // Determine the actual kind for the frontend event
-                    // (a "modified" event on a non-existent file is really a delete)
-                    let effective_kind = if kind == "modified" && !path.exists() {
-                        "deleted"
-                    } else {
-                        kind
-                    };
+    // Sanitize the filename
+    let sanitized_name = sanitize_filename(original_name);
 
-                    let _ = app_handle.emit(
-                        "file-change",
-                        FileChangeEvent {
-                            kind: effective_kind.to_string(),
-                            path: path.to_string_lossy().into_owned(),
-                            changed_ids: vec![note_id.clone()],
-                        },
-                    );
-                }
-            }
-        },
-        Config::default(),
-    )
-    .map_err(|e| e.to_string())?;
+    // Create the attachment folder for this note's layout
+    let assets_rel = attachment_folder(&state, Path::new(&folder), note_id.as_deref())?;
+    let assets_dir = PathBuf::from(&folder).join(&assets_rel);
+    fs::create_dir_all(&assets_dir)
+        .await
+        .map_err(|e| ScratchError::io(e, &assets_dir))?;
 
-    let mut watcher = watcher;
+    // Generate unique filename
+    let target_name =
+        assets::unique_name(&assets_dir, &format!("{}.{}", sanitized_name, extension));
+    let target_path = assets_dir.join(&target_name);
 
-    // Watch the notes folder recursively for .md files in subfolders
-    watcher
-        .watch(&folder_path, RecursiveMode::Recursive)
-        .map_err(|e| e.to_string())?;
+    // Copy the file
+    fs::copy(&source, &target_path)
+        .await
+        .map_err(|e| ScratchError::io(e, &target_path))?;
+    warn_vault_size(app, PathBuf::from(&folder), limits);
 
-    Ok(FileWatcherState { watcher })
+    // Return both relative path and filename for frontend to construct the URL
+    Ok(assets::join(&assets_rel, &target_name))
 }
 
+/// Save `bytes` as a new attachment of `note_id`, named after
+/// `suggested_name`. Returns the markdown that embeds or links it. Refused
+/// when over the import limits.
 #[tauri::command]
-fn start_file_watcher(app: AppHandle, state: State<AppState>) -> Result<(), String> {
+async fn import_asset(
+    app: AppHandle,
+    bytes: Vec<u8>,
+    suggested_name: String,
+    note_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
+    if bytes.is_empty() {
+        return Err(ScratchError::invalid("Asset data is empty"));
+    }
 
-    // Clean up debounce map before starting
-    cleanup_debounce_map(&state.debounce_map);
+    let suggested = Path::new(suggested_name.trim());
+    let stem = suggested
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .unwrap_or("attachment");
+    let name = match suggested.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("md") => {
+            return Err(ScratchError::invalid("Notes cannot be imported as assets"));
+        }
+        Some(ext) => format!("{}.{}", sanitize_filename(stem), sanitize_filename(ext)),
+        None => sanitize_filename(stem),
+    };
+    let limits = import_limits(&state);
+    limits
+        .check_attachment(suggested_name.trim(), &name, bytes.len() as u64)
+        .map_err(|warning| ScratchError::invalid(warning.to_string()))?;
 
-    let watcher_state = setup_file_watcher(
-        app,
-        &folder,
-        Arc::clone(&state.debounce_map),
-    )?;
+    let assets_rel = attachment_folder(&state, Path::new(&folder), note_id.as_deref())?;
+    let assets_dir = PathBuf::from(&folder).join(&assets_rel);
+    fs::create_dir_all(&assets_dir)
+        .await
+        .map_err(|e| ScratchError::io(e, &assets_dir))?;
+    let target_name = assets::unique_name(&assets_dir, &name);
+    let target_path = assets_dir.join(&target_name);
+    fs::write(&target_path, &bytes)
+        .await
+        .map_err(|e| ScratchError::io(e, &target_path))?;
+    warn_vault_size(app, PathBuf::from(&folder), limits);
 
-    let mut file_watcher = state.file_watcher.lock().expect("file watcher mutex");
-    *file_watcher = Some(watcher_state);
+    let rel = assets::join(&assets_rel, &target_name);
+    Ok(assets::markdown_link(&rel))
+}
 
-    Ok(())
+/// Every attachment in the vault, by path.
+#[tauri::command]
+async fn list_assets(state: State<'_, AppState>) -> Result<Vec<assets::AssetInfo>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let layout = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.asset_layout.unwrap_or_default()
+    };
+    tokio::task::spawn_blocking(move || assets::list(Path::new(&folder), layout))
+        .await
+        .map_err(ScratchError::from)
 }
 
+/// Attachments no note references, private notes included.
 #[tauri::command]
-fn copy_to_clipboard(app: AppHandle, text: String) -> Result<(), String> {
-    app.clipboard().write_text(text).map_err(|e| e.to_string())
+async fn find_orphaned_assets(
+    state: State<'_, AppState>,
+) -> Result<Vec<assets::AssetInfo>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let layout = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.asset_layout.unwrap_or_default()
+    };
+    tokio::task::spawn_blocking(move || {
+        let root = Path::new(&folder);
+        assets::orphaned(&scan_notes(root), assets::list(root, layout))
+    })
+    .await
+    .map_err(ScratchError::from)
 }
 
+const ORPHANED_ASSETS_LABEL: &str = "Orphaned assets";
+
+/// Move the attachments among `paths` that are still orphaned into one trash
+/// entry. Returns the paths moved; one a note has started using since it was
+/// listed stays put.
 #[tauri::command]
-async fn save_clipboard_image(
-    base64_data: String,
+async fn delete_orphaned_assets(
+    paths: Vec<String>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    // Guard against empty clipboard payload
-    if base64_data.trim().is_empty() {
-        return Err("Clipboard data is empty".to_string());
-    }
+) -> Result<Vec<String>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let layout = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.asset_layout.unwrap_or_default()
+    };
+    tokio::task::spawn_blocking(move || {
+        let root = Path::new(&folder);
+        let orphaned: Vec<String> = assets::orphaned(&scan_notes(root), assets::list(root, layout))
+            .into_iter()
+            .map(|asset| asset.path)
+            .filter(|path| paths.contains(path))
+            .collect();
+        if orphaned.is_empty() {
+            return Ok(Vec::new());
+        }
+        trash::trash_assets(root, ORPHANED_ASSETS_LABEL, &orphaned)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
 
+/// The metadata sidecar of attachment `path` (vault-relative), if it has one.
+#[tauri::command]
+async fn get_attachment_meta(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Option<sidecars::AttachmentMeta>, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
+    assets::resolve(Path::new(&folder), &path)?;
+    tokio::task::spawn_blocking(move || sidecars::read(Path::new(&folder), &path))
+        .await
+        .map_err(ScratchError::from)
+}
 
-    // Decode base64
-    let image_data = base64::engine::general_purpose::STANDARD
-        .decode(&base64_data)
-        .map_err(|_| "Failed to decode base64 image data".to_string())?;
+/// Describe attachment `path` with a title, tags and notes, making it
+/// searchable and listed under its tags. Empty metadata removes the sidecar.
+/// Returns what was stored.
+#[tauri::command]
+async fn set_attachment_meta(
+    path: String,
+    meta: sidecars::AttachmentMeta,
+    state: State<'_, AppState>,
+) -> Result<Option<sidecars::AttachmentMeta>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let root = PathBuf::from(&folder);
+    let id = sidecars::note_id(&path);
+    let (stored, note) = tokio::task::spawn_blocking(move || {
+        sidecars::write(&root, &path, meta).map(|stored| (stored, sidecars::load(&root, &path)))
+    })
+    .await??;
 
-    // Guard against zero-byte files
-    if image_data.is_empty() {
-        return Err("Decoded image data is empty".to_string());
+    let index = state.search_index.lock().expect("search index mutex");
+    if let Some(ref search_index) = *index {
+        let _ = match note {
+            Some(note) => {
+                search_index.index_note(&note.id, &note.title, &note.content, note.modified)
+            }
+            None => search_index.delete_note(&id),
+        };
     }
+    Ok(stored)
+}
 
-    // Create assets folder path
-    let assets_dir = PathBuf::from(&folder).join("assets");
-    fs::create_dir_all(&assets_dir)
-        .await
+#[tauri::command]
+fn rebuild_search_index(app: AppHandle, state: State<AppState>) -> Result<(), ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+
+    // Create new index
+    let search_index = {
+        let sources = state
+            .app_config
+            .read()
+            .expect("app_config read lock")
+            .source_folders
+            .clone();
+        let settings = state.settings.read().expect("settings read lock");
+        open_search_index(&app, &settings, &sources, Path::new(&folder))
+            .map_err(|e| e.to_string())?
+    };
+    search_index
+        .rebuild_index(&PathBuf::from(&folder))
         .map_err(|e| e.to_string())?;
 
-    // Generate unique filename with timestamp
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
+    let mut index = state.search_index.lock().expect("search index mutex");
+    *index = Some(search_index);
+    state.link_index.invalidate();
 
-    let mut target_name = format!("screenshot-{}.png", timestamp);
-    let mut counter = 1;
-    let mut target_path = assets_dir.join(&target_name);
+    Ok(())
+}
 
-    while target_path.exists() {
-        target_name = format!("screenshot-{}-{}.png", timestamp, counter);
-        target_path = assets_dir.join(&target_name);
-        counter += 1;
-    }
+/// Languages of the visible notes, the dominant one first, and the language
+/// the search index stems for.
+#[tauri::command]
+async fn get_vault_languages(
+    state: State<'_, AppState>,
+) -> Result<language::VaultLanguages, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    let private_folders = active_private_folders(&state);
+    let search_language = state
+        .search_index
+        .lock()
+        .expect("search index mutex")
+        .as_ref()
+        .and_then(|index| index.language.clone());
 
-    // Write the file
-    fs::write(&target_path, &image_data)
+    tokio::task::spawn_blocking(move || {
+        let notes = scan_analyzable_notes(Path::new(&folder), private_folders.as_deref());
+        language::summarize(
+            notes
+                .iter()
+                .map(|note| listing_language(&note.content, &locked_policy)),
+            search_language,
+        )
+    })
+    .await
+    .map_err(ScratchError::from)
+}
+
+/// Free space on the vault and app-data volumes, with warning levels from the
+/// `diskSpace` thresholds.
+#[tauri::command]
+async fn get_disk_space(app: AppHandle) -> Result<Vec<disk::VolumeSpace>, ScratchError> {
+    tauri::async_runtime::spawn_blocking(move || disk::check(&app))
         .await
-        .map_err(|_| "Failed to write image".to_string())?;
+        .map_err(ScratchError::from)
+}
+
+/// Whether the app was started in read-only safe mode.
+#[tauri::command]
+fn get_safe_mode() -> bool {
+    safemode::is_active()
+}
 
-    // Return relative path
-    Ok(format!("assets/{}", target_name))
+/// Estimated size of the in-memory caches against their `memoryBudgets`.
+#[tauri::command]
+fn get_memory_usage(state: State<AppState>) -> memory::MemoryUsage {
+    memory::usage(&state)
 }
 
+/// Time scanning, index building, search and file watcher events for the
+/// current vault, plus app startup, to diagnose slowness on large vaults.
 #[tauri::command]
-async fn copy_image_to_assets(
-    source_path: String,
+async fn benchmark_vault(
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<benchmark::BenchmarkReport, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
-
-    let source = PathBuf::from(&source_path);
-    if !source.exists() {
-        return Err("Source image file does not exist".to_string());
-    }
-
-    // Get file extension
-    let extension = source
-        .extension()
-        .and_then(|e| e.to_str())
-        .ok_or("Invalid file extension")?;
-
-    const ALLOWED_IMAGE_EXTENSIONS: &[&str] = &[
-        "jpg", "jpeg", "png", "gif", "webp", "svg", "bmp", "tiff", "tif", "ico", "avif",
-    ];
-    let ext_lower = extension.to_lowercase();
-    if !ALLOWED_IMAGE_EXTENSIONS.contains(&ext_lower.as_str()) {
-        return Err("Only image files can be copied to assets".to_string());
-    }
-
-    // Get original filename (without extension)
-    let original_name = source
-        .file_stem()
-        .and_then(|n| n.to_str())
-        .unwrap_or("image");
-
-    // Sanitize the filename
-    let sanitized_name = sanitize_filename(original_name);
-
-    // Create assets folder path
-    let assets_dir = PathBuf::from(&folder).join("assets");
-    fs::create_dir_all(&assets_dir)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Generate unique filename
-    let mut target_name = format!("{}.{}", sanitized_name, extension);
-    let mut counter = 1;
-    let mut target_path = assets_dir.join(&target_name);
-
-    while target_path.exists() {
-        target_name = format!("{}-{}.{}", sanitized_name, counter, extension);
-        target_path = assets_dir.join(&target_name);
-        counter += 1;
-    }
-
-    // Copy the file
-    fs::copy(&source, &target_path)
-        .await
-        .map_err(|_| "Failed to copy image".to_string())?;
-
-    // Return both relative path and filename for frontend to construct the URL
-    Ok(format!("assets/{}", target_name))
+    let locked_policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    tokio::task::spawn_blocking(move || benchmark::run(Path::new(&folder), locked_policy))
+        .await?
+        .map_err(ScratchError::from)
 }
 
+/// Check the vault for unreadable or empty notes, search index and cache
+/// drift, dangling trash entries and broken sidecar stores. With `repair`,
+/// derived state is rebuilt; note files are never touched.
 #[tauri::command]
-fn rebuild_search_index(app: AppHandle, state: State<AppState>) -> Result<(), String> {
+async fn verify_vault(
+    app: AppHandle,
+    repair: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<integrity::VaultReport, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
             .notes_folder
             .clone()
-            .ok_or("Notes folder not set")?
+            .ok_or(ScratchError::FolderNotSet)?
     };
+    let repair = repair.unwrap_or(false);
+
+    let scan_folder = folder.clone();
+    let (notes, mut issues) = tokio::task::spawn_blocking(move || {
+        let root = PathBuf::from(&scan_folder);
+        let mut issues = Vec::new();
+        let notes = integrity::check_notes(&root, &mut issues);
+        integrity::check_trash(&root, repair, &mut issues);
+        integrity::check_sidecars(&root, repair, &mut issues);
+        integrity::check_annotations(&scan_folder, repair, &mut issues);
+        (notes, issues)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
 
-    let index_path = get_search_index_path(&app).map_err(|e| e.to_string())?;
+    // Search index: every indexable note present, nothing else
+    let mut index_issues = Vec::new();
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        match *index {
+            Some(ref search_index) => {
+                let indexed = search_index.indexed_ids().map_err(|e| e.to_string())?;
+                let expected: HashSet<&str> = notes
+                    .iter()
+                    .filter(|n| {
+                        search_index
+                            .indexable(&n.id, &extract_title(&n.content), &n.content)
+                            .is_some()
+                    })
+                    .map(|n| n.id.as_str())
+                    .collect();
+                for id in expected.iter().filter(|id| !indexed.contains(**id)) {
+                    index_issues.push(integrity::Issue::new(
+                        integrity::IssueKind::MissingFromIndex,
+                        *id,
+                        "Note is not in the search index",
+                    ));
+                }
+                for id in indexed.iter().filter(|id| !expected.contains(id.as_str())) {
+                    index_issues.push(integrity::Issue::new(
+                        integrity::IssueKind::StaleIndexEntry,
+                        id,
+                        "Search index entry has no matching note",
+                    ));
+                }
+            }
+            None => index_issues.push(integrity::Issue::new(
+                integrity::IssueKind::IndexUnavailable,
+                "",
+                "Search index is not loaded",
+            )),
+        }
+    }
+    if repair && !index_issues.is_empty() {
+        let rebuilt = rebuild_search_index(app, state.clone()).is_ok();
+        for issue in &mut index_issues {
+            issue.repaired = rebuilt;
+        }
+    }
+    issues.append(&mut index_issues);
 
-    // Create new index
-    let search_index = SearchIndex::new(&index_path).map_err(|e| e.to_string())?;
-    search_index
-        .rebuild_index(&PathBuf::from(&folder))
-        .map_err(|e| e.to_string())?;
+    // Notes cache: drop entries for notes no longer on disk
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        let on_disk: HashSet<&str> = notes.iter().map(|n| n.id.as_str()).collect();
+        let stale: Vec<String> = cache
+            .keys()
+            .filter(|id| !on_disk.contains(id.as_str()))
+            .cloned()
+            .collect();
+        for id in stale {
+            let mut issue = integrity::Issue::new(
+                integrity::IssueKind::StaleCacheEntry,
+                &id,
+                "Cached note is missing from disk",
+            );
+            if repair {
+                issue.repaired = cache.remove(&id).is_some();
+            }
+            issues.push(issue);
+        }
+    }
 
-    let mut index = state.search_index.lock().expect("search index mutex");
-    *index = Some(search_index);
+    // Keep private notes out of the report while privacy mode is active
+    if let Some(private) = active_private_folders(&state) {
+        let hidden: HashSet<&str> = notes
+            .iter()
+            .filter(|n| is_private_note(&n.id, &n.content, &private))
+            .map(|n| n.id.as_str())
+            .collect();
+        issues.retain(|issue| {
+            !hidden.contains(issue.path.as_str()) && !is_private_note(&issue.path, "", &private)
+        });
+    }
 
-    Ok(())
+    Ok(integrity::VaultReport {
+        checked_notes: notes.len(),
+        issues,
+        repaired: repair,
+    })
 }
 
 // UI helper commands - wrap Tauri plugins for consistent invoke-based API
@@ -2343,7 +9089,7 @@ fn rebuild_search_index(app: AppHandle, state: State<AppState>) -> Result<(), St
 async fn open_folder_dialog(
     app: AppHandle,
     default_path: Option<String>,
-) -> Result<Option<String>, String> {
+) -> Result<Option<String>, ScratchError> {
     use tauri_plugin_dialog::DialogExt;
 
     // Run blocking dialog on a separate thread to avoid blocking the async runtime
@@ -2363,10 +9109,12 @@ async fn open_folder_dialog(
 }
 
 #[tauri::command]
-async fn open_in_file_manager(path: String) -> Result<(), String> {
+async fn open_in_file_manager(path: String) -> Result<(), ScratchError> {
     let path_buf = PathBuf::from(&path);
     if !path_buf.exists() || !path_buf.is_dir() {
-        return Err("Path does not exist or is not a directory".to_string());
+        return Err(ScratchError::not_found(
+            "Path does not exist or is not a directory",
+        ));
     }
 
     #[cfg(target_os = "macos")]
@@ -2396,29 +9144,30 @@ async fn open_in_file_manager(path: String) -> Result<(), String> {
 
     #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
     {
-        return Err("Unsupported platform".to_string());
+        return Err("Unsupported platform".into());
     }
 
     Ok(())
 }
 
 #[tauri::command]
-async fn open_url_safe(url: String) -> Result<(), String> {
+async fn open_url_safe(url: String) -> Result<(), ScratchError> {
     // Validate URL scheme - only allow http, https, mailto
-    let parsed = url::Url::parse(&url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let parsed =
+        url::Url::parse(&url).map_err(|e| ScratchError::invalid(format!("Invalid URL: {}", e)))?;
 
     match parsed.scheme() {
         "http" | "https" | "mailto" => {}
         scheme => {
-            return Err(format!(
+            return Err(ScratchError::invalid(format!(
                 "URL scheme '{}' is not allowed. Only http, https, and mailto are permitted.",
                 scheme
-            ))
+            )))
         }
     }
 
     // Use system opener
-    open::that(&url).map_err(|e| format!("Failed to open URL: {}", e))
+    open::that(&url).map_err(|e| format!("Failed to open URL: {}", e).into())
 }
 
 // Git commands - run blocking git operations off the main thread
@@ -2431,7 +9180,7 @@ async fn git_is_available() -> bool {
 }
 
 #[tauri::command]
-async fn git_get_status(state: State<'_, AppState>) -> Result<git::GitStatus, String> {
+async fn git_get_status(state: State<'_, AppState>) -> Result<git::GitStatus, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -2439,32 +9188,34 @@ async fn git_get_status(state: State<'_, AppState>) -> Result<git::GitStatus, St
 
     match folder {
         Some(path) => {
-            tauri::async_runtime::spawn_blocking(move || {
-                git::get_status(&PathBuf::from(path))
-            })
-            .await
-            .map_err(|e| e.to_string())
+            tauri::async_runtime::spawn_blocking(move || git::get_status(&PathBuf::from(path)))
+                .await
+                .map_err(ScratchError::from)
         }
         None => Ok(git::GitStatus::default()),
     }
 }
 
 #[tauri::command]
-async fn git_init_repo(state: State<'_, AppState>) -> Result<(), String> {
+async fn git_init_repo(state: State<'_, AppState>) -> Result<(), ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
-        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
 
-    tauri::async_runtime::spawn_blocking(move || {
-        git::git_init(&PathBuf::from(folder))
-    })
-    .await
-    .map_err(|e| e.to_string())?
+    tauri::async_runtime::spawn_blocking(move || git::git_init(&PathBuf::from(folder)))
+        .await?
+        .map_err(ScratchError::from)
 }
 
 #[tauri::command]
-async fn git_commit(message: String, state: State<'_, AppState>) -> Result<git::GitResult, String> {
+async fn git_commit(
+    message: String,
+    state: State<'_, AppState>,
+) -> Result<git::GitResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -2472,11 +9223,14 @@ async fn git_commit(message: String, state: State<'_, AppState>) -> Result<git::
 
     match folder {
         Some(path) => {
+            let note_policies = note_policies(&state);
             tauri::async_runtime::spawn_blocking(move || {
-                git::commit_all(&PathBuf::from(path), &message)
+                let root = PathBuf::from(path);
+                let excluded = never_synced_paths(&root, &note_policies);
+                git::commit_all(&root, &message, &excluded)
             })
             .await
-            .map_err(|e| e.to_string())
+            .map_err(ScratchError::from)
         }
         None => Ok(git::GitResult {
             success: false,
@@ -2487,7 +9241,7 @@ async fn git_commit(message: String, state: State<'_, AppState>) -> Result<git::
 }
 
 #[tauri::command]
-async fn git_push(state: State<'_, AppState>) -> Result<git::GitResult, String> {
+async fn git_push(state: State<'_, AppState>) -> Result<git::GitResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -2499,7 +9253,7 @@ async fn git_push(state: State<'_, AppState>) -> Result<git::GitResult, String>
                 git::push(&PathBuf::from(path))
             })
             .await
-            .map_err(|e| e.to_string())
+            .map_err(ScratchError::from)
         }
         None => Ok(git::GitResult {
             success: false,
@@ -2510,7 +9264,7 @@ async fn git_push(state: State<'_, AppState>) -> Result<git::GitResult, String>
 }
 
 #[tauri::command]
-async fn git_fetch(state: State<'_, AppState>) -> Result<git::GitResult, String> {
+async fn git_fetch(state: State<'_, AppState>) -> Result<git::GitResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -2522,7 +9276,7 @@ async fn git_fetch(state: State<'_, AppState>) -> Result<git::GitResult, String>
                 git::fetch(&PathBuf::from(path))
             })
             .await
-            .map_err(|e| e.to_string())
+            .map_err(ScratchError::from)
         }
         None => Ok(git::GitResult {
             success: false,
@@ -2533,7 +9287,7 @@ async fn git_fetch(state: State<'_, AppState>) -> Result<git::GitResult, String>
 }
 
 #[tauri::command]
-async fn git_pull(state: State<'_, AppState>) -> Result<git::GitResult, String> {
+async fn git_pull(state: State<'_, AppState>) -> Result<git::GitResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -2541,11 +9295,13 @@ async fn git_pull(state: State<'_, AppState>) -> Result<git::GitResult, String>
 
     match folder {
         Some(path) => {
+            // A pull rewrites notes underneath every other writer
+            let _guard = state.note_locks.lock_vault().await;
             tauri::async_runtime::spawn_blocking(move || {
                 git::pull(&PathBuf::from(path))
             })
             .await
-            .map_err(|e| e.to_string())
+            .map_err(ScratchError::from)
         }
         None => Ok(git::GitResult {
             success: false,
@@ -2556,7 +9312,10 @@ async fn git_pull(state: State<'_, AppState>) -> Result<git::GitResult, String>
 }
 
 #[tauri::command]
-async fn git_add_remote(url: String, state: State<'_, AppState>) -> Result<git::GitResult, String> {
+async fn git_add_remote(
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<git::GitResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -2568,7 +9327,7 @@ async fn git_add_remote(url: String, state: State<'_, AppState>) -> Result<git::
                 git::add_remote(&PathBuf::from(path), &url)
             })
             .await
-            .map_err(|e| e.to_string())
+            .map_err(ScratchError::from)
         }
         None => Ok(git::GitResult {
             success: false,
@@ -2579,7 +9338,9 @@ async fn git_add_remote(url: String, state: State<'_, AppState>) -> Result<git::
 }
 
 #[tauri::command]
-async fn git_push_with_upstream(state: State<'_, AppState>) -> Result<git::GitResult, String> {
+async fn git_push_with_upstream(
+    state: State<'_, AppState>,
+) -> Result<git::GitResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -2612,7 +9373,7 @@ async fn git_push_with_upstream(state: State<'_, AppState>) -> Result<git::GitRe
                 }
             })
             .await
-            .map_err(|e| e.to_string())
+            .map_err(ScratchError::from)
         }
         None => Ok(git::GitResult {
             success: false,
@@ -2622,6 +9383,179 @@ async fn git_push_with_upstream(state: State<'_, AppState>) -> Result<git::GitRe
     }
 }
 
+/// Snapshot a save into version history, unless disk space is critically
+/// low. Blocking; async callers use `snapshot_history`.
+fn snapshot_history_blocking(
+    app: &AppHandle,
+    folder_path: &Path,
+    id: &str,
+    previous: Option<&str>,
+    content: &str,
+) {
+    if disk::allow_background_write(app).is_ok() {
+        let _ = history::snapshot(folder_path, id, previous, content);
+    }
+}
+
+/// Snapshot a save into version history off the async runtime.
+async fn snapshot_history(
+    app: &AppHandle,
+    folder_path: &Path,
+    id: &str,
+    previous: Option<String>,
+    content: String,
+) {
+    let app = app.clone();
+    let root = folder_path.to_path_buf();
+    let id = id.to_string();
+    let _ = tauri::async_runtime::spawn_blocking(move || {
+        snapshot_history_blocking(&app, &root, &id, previous.as_deref(), &content)
+    })
+    .await;
+}
+
+/// Commit `files` (vault-relative) in the background when auto-commit is on
+/// and the vault is a git repository. A failed commit leaves the changes for
+/// the next manual commit.
+fn git_auto_commit(state: &AppState, folder_path: &Path, files: Vec<String>, message: String) {
+    let enabled = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.git_auto_commit.unwrap_or(false)
+    };
+    if !enabled || safemode::is_active() || files.is_empty() || !git::is_git_repo(folder_path) {
+        return;
+    }
+    let root = folder_path.to_path_buf();
+    let note_policies = note_policies(state);
+    tauri::async_runtime::spawn_blocking(move || {
+        let files: Vec<String> = files
+            .into_iter()
+            .filter(|file| !never_synced(&root, &note_policies, file))
+            .collect();
+        git::commit_paths(&root, &files, &message)
+    });
+}
+
+/// Whether the vault-relative `file` is kept out of git by a note policy.
+/// Notes that are gone can only be matched by folder.
+fn never_synced(notes_root: &Path, note_policies: &[policies::NotePolicy], file: &str) -> bool {
+    let sync = policies::Restriction::Sync;
+    if policies::forbids_path(note_policies, sync, file) {
+        return true;
+    }
+    let Some(id) = file.strip_suffix(".md") else {
+        return false;
+    };
+    policies::has_tags(note_policies, sync)
+        && std::fs::read_to_string(notes_root.join(file))
+            .is_ok_and(|content| policies::forbids(note_policies, sync, id, &content))
+}
+
+/// Vault-relative paths a full commit leaves out: folders and tagged notes
+/// kept out of git by a note policy. Blocking.
+fn never_synced_paths(notes_root: &Path, note_policies: &[policies::NotePolicy]) -> Vec<String> {
+    let sync = policies::Restriction::Sync;
+    let mut paths: Vec<String> = policies::folders(note_policies, sync)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    if policies::has_tags(note_policies, sync) {
+        paths.extend(
+            scan_notes(notes_root)
+                .into_iter()
+                .filter(|n| policies::forbids(note_policies, sync, &n.id, &n.content))
+                .map(|n| rel_path_from_id(&n.id)),
+        );
+    }
+    paths
+}
+
+/// Commits that changed a note, newest first, following it through renames.
+#[tauri::command]
+async fn git_history(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<git::GitCommit>, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    abs_path_from_id(Path::new(&folder), &id)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        git::file_history(&PathBuf::from(folder), &rel_path_from_id(&id))
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+/// The changes commit `rev` made to a note, as a unified diff.
+#[tauri::command]
+async fn git_diff(
+    id: String,
+    rev: String,
+    state: State<'_, AppState>,
+) -> Result<String, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    abs_path_from_id(Path::new(&folder), &id)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        git::file_diff(&PathBuf::from(folder), &rel_path_from_id(&id), &rev)
+    })
+    .await?
+    .map_err(ScratchError::from)
+}
+
+/// Put a note back to how it was in commit `rev`. The restore is a new save,
+/// so it lands in version history and, with auto-commit on, in git.
+#[tauri::command]
+async fn git_restore(
+    app: AppHandle,
+    id: String,
+    rev: String,
+    state: State<'_, AppState>,
+) -> Result<Note, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+
+    let root = folder_path.clone();
+    let file = rel_path_from_id(&id);
+    let (commit, content) =
+        tauri::async_runtime::spawn_blocking(move || git::file_at(&root, &file, &rev))
+            .await??;
+
+    // Write pending edits first so they are kept in version history
+    autosave::flush(&state, &id).await?;
+    let guard = state.note_locks.lock(&folder_path, &[id.as_str()]).await;
+    let previous = fs::read_to_string(&file_path).await.ok();
+    let note = write_note_content(&state, &folder_path, &id, content).await?;
+    snapshot_history(&app, &folder_path, &id, previous, note.content.clone()).await;
+    git_auto_commit(
+        &state,
+        &folder_path,
+        vec![note.path.clone()],
+        format!("Restore {} from {}", id, commit.short_rev),
+    );
+    drop(guard);
+    Ok(note)
+}
+
 // Check if Claude CLI is installed
 fn get_expanded_path() -> String {
     let system_path = std::env::var("PATH").unwrap_or_default();
@@ -2725,7 +9659,7 @@ fn cli_target_path() -> PathBuf {
 }
 
 #[tauri::command]
-fn get_cli_status() -> Result<CliStatus, String> {
+fn get_cli_status() -> Result<CliStatus, ScratchError> {
     #[cfg(not(target_os = "macos"))]
     return Ok(CliStatus { supported: false, installed: false, path: None });
 
@@ -2757,9 +9691,9 @@ fn get_cli_status() -> Result<CliStatus, String> {
 }
 
 #[tauri::command]
-fn install_cli() -> Result<String, String> {
+fn install_cli() -> Result<String, ScratchError> {
     #[cfg(not(target_os = "macos"))]
-    return Err("CLI install is only supported on macOS".to_string());
+    return Err("CLI install is only supported on macOS".into());
 
     #[cfg(target_os = "macos")]
     {
@@ -2776,10 +9710,13 @@ fn install_cli() -> Result<String, String> {
             // Only remove if it's our wrapper (contains marker)
             let content = std::fs::read_to_string(&target).unwrap_or_default();
             if !content.contains(SCRATCH_CLI_MARKER) {
-                return Err(format!(
-                    "A different 'scratch' command already exists at {}. Remove it manually to install the Scratch CLI.",
-                    target.display()
-                ));
+                return Err(ScratchError::AlreadyExists {
+                    path: Some(target.to_string_lossy().into_owned()),
+                    message: format!(
+                        "A different 'scratch' command already exists at {}. Remove it manually to install the Scratch CLI.",
+                        target.display()
+                    ),
+                });
             }
             std::fs::remove_file(&target)
                 .map_err(|e| format!("Failed to remove existing file: {}", e))?;
@@ -2815,7 +9752,7 @@ fn install_cli() -> Result<String, String> {
 }
 
 #[tauri::command]
-fn uninstall_cli() -> Result<(), String> {
+fn uninstall_cli() -> Result<(), ScratchError> {
     #[cfg(not(target_os = "macos"))]
     return Ok(());
 
@@ -2828,7 +9765,8 @@ fn uninstall_cli() -> Result<(), String> {
                 return Err(format!(
                     "File at {} was not installed by Scratch. Refusing to remove.",
                     target.display()
-                ));
+                )
+                .into());
             }
             std::fs::remove_file(&target)
                 .map_err(|e| format!("Failed to remove CLI script: {}", e))?;
@@ -2838,33 +9776,36 @@ fn uninstall_cli() -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn ai_check_claude_cli() -> Result<bool, String> {
+async fn ai_check_claude_cli() -> Result<bool, ScratchError> {
     tauri::async_runtime::spawn_blocking(|| {
         let path = get_expanded_path();
         check_cli_exists("claude", &path)
     })
     .await
     .map_err(|e| format!("Failed to check Claude CLI: {}", e))?
+    .map_err(ScratchError::from)
 }
 
 #[tauri::command]
-async fn ai_check_codex_cli() -> Result<bool, String> {
+async fn ai_check_codex_cli() -> Result<bool, ScratchError> {
     tauri::async_runtime::spawn_blocking(|| {
         let path = get_expanded_path();
         check_cli_exists("codex", &path)
     })
     .await
     .map_err(|e| format!("Failed to check Codex CLI: {}", e))?
+    .map_err(ScratchError::from)
 }
 
 #[tauri::command]
-async fn ai_check_opencode_cli() -> Result<bool, String> {
+async fn ai_check_opencode_cli() -> Result<bool, ScratchError> {
     tauri::async_runtime::spawn_blocking(|| {
         let path = get_expanded_path();
         check_cli_exists("opencode", &path)
     })
     .await
     .map_err(|e| format!("Failed to check OpenCode CLI: {}", e))?
+    .map_err(ScratchError::from)
 }
 
 /// Shared AI CLI execution: spawns `command` with `args`, writes `stdin_input` to stdin,
@@ -3083,24 +10024,29 @@ async fn ai_execute_claude(
     file_path: String,
     prompt: String,
     state: State<'_, AppState>,
-) -> Result<AiExecutionResult, String> {
+) -> Result<AiExecutionResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
-        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
-    let path = PathBuf::from(&file_path);
+    let path = abs_path_from_rel(Path::new(&folder), &file_path)?;
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
     if !ext.eq_ignore_ascii_case("md") && !ext.eq_ignore_ascii_case("markdown") {
-        return Err("AI editing is only supported for markdown files".to_string());
+        return Err(ScratchError::invalid(
+            "AI editing is only supported for markdown files",
+        ));
     }
     let canonical = path
         .canonicalize()
-        .map_err(|_| "Invalid file path".to_string())?;
+        .map_err(|_| ScratchError::invalid("Invalid file path"))?;
     let notes_root = PathBuf::from(&folder)
         .canonicalize()
         .map_err(|_| "Invalid notes folder".to_string())?;
     if !canonical.starts_with(&notes_root) {
-        return Err("File must be within notes folder".to_string());
+        return Err(ScratchError::invalid("File must be within notes folder"));
     }
 
     execute_ai_cli(
@@ -3117,10 +10063,24 @@ async fn ai_execute_claude(
         None,
     )
     .await
+    .map_err(ScratchError::from)
 }
 
 #[tauri::command]
-async fn ai_execute_codex(file_path: String, prompt: String) -> Result<AiExecutionResult, String> {
+async fn ai_execute_codex(
+    file_path: String,
+    prompt: String,
+    state: State<'_, AppState>,
+) -> Result<AiExecutionResult, ScratchError> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
+    };
+    let file_path = abs_path_from_rel(Path::new(&folder), &file_path)?;
+    let file_path = file_path.display();
     let stdin_input = format!(
         "Edit only this markdown file: {file_path}\n\
          Apply the user's instructions below directly to that file.\n\
@@ -3144,6 +10104,7 @@ async fn ai_execute_codex(file_path: String, prompt: String) -> Result<AiExecuti
         None,
     )
     .await
+    .map_err(ScratchError::from)
 }
 
 #[tauri::command]
@@ -3151,24 +10112,29 @@ async fn ai_execute_opencode(
     file_path: String,
     prompt: String,
     state: State<'_, AppState>,
-) -> Result<AiExecutionResult, String> {
+) -> Result<AiExecutionResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
-        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
-    let path = PathBuf::from(&file_path);
+    let path = abs_path_from_rel(Path::new(&folder), &file_path)?;
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
     if !ext.eq_ignore_ascii_case("md") && !ext.eq_ignore_ascii_case("markdown") {
-        return Err("AI editing is only supported for markdown files".to_string());
+        return Err(ScratchError::invalid(
+            "AI editing is only supported for markdown files",
+        ));
     }
     let canonical = path
         .canonicalize()
-        .map_err(|_| "Invalid file path".to_string())?;
+        .map_err(|_| ScratchError::invalid("Invalid file path"))?;
     let notes_root = PathBuf::from(&folder)
         .canonicalize()
         .map_err(|_| "Invalid notes folder".to_string())?;
     if !canonical.starts_with(&notes_root) {
-        return Err("File must be within notes folder".to_string());
+        return Err(ScratchError::invalid("File must be within notes folder"));
     }
 
     let run_prompt = format!(
@@ -3200,16 +10166,18 @@ async fn ai_execute_opencode(
         ]),
     )
     .await
+    .map_err(ScratchError::from)
 }
 
 #[tauri::command]
-async fn ai_check_ollama_cli() -> Result<bool, String> {
+async fn ai_check_ollama_cli() -> Result<bool, ScratchError> {
     tauri::async_runtime::spawn_blocking(|| {
         let path = get_expanded_path();
         check_cli_exists("ollama", &path)
     })
     .await
     .map_err(|e| format!("Failed to check Ollama CLI: {}", e))?
+    .map_err(ScratchError::from)
 }
 
 #[tauri::command]
@@ -3218,24 +10186,29 @@ async fn ai_execute_ollama(
     prompt: String,
     model: String,
     state: State<'_, AppState>,
-) -> Result<AiExecutionResult, String> {
+) -> Result<AiExecutionResult, ScratchError> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
-        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or(ScratchError::FolderNotSet)?
     };
-    let path = PathBuf::from(&file_path);
+    let path = abs_path_from_rel(Path::new(&folder), &file_path)?;
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
     if !ext.eq_ignore_ascii_case("md") && !ext.eq_ignore_ascii_case("markdown") {
-        return Err("AI editing is only supported for markdown files".to_string());
+        return Err(ScratchError::invalid(
+            "AI editing is only supported for markdown files",
+        ));
     }
     let canonical = path
         .canonicalize()
-        .map_err(|_| "Invalid file path".to_string())?;
+        .map_err(|_| ScratchError::invalid("Invalid file path"))?;
     let notes_root = PathBuf::from(&folder)
         .canonicalize()
         .map_err(|_| "Invalid notes folder".to_string())?;
     if !canonical.starts_with(&notes_root) {
-        return Err("File must be within notes folder".to_string());
+        return Err(ScratchError::invalid("File must be within notes folder"));
     }
 
     // Read the current file content
@@ -3462,10 +10435,13 @@ fn create_preview_window(app: &AppHandle, file_path: &str) -> Result<(), String>
 }
 
 #[tauri::command]
-fn open_file_preview(app: AppHandle, path: String) -> Result<(), String> {
+fn open_file_preview(app: AppHandle, path: String) -> Result<(), ScratchError> {
     let file_path = PathBuf::from(&path);
     if !file_path.exists() {
-        return Err(format!("File not found: {}", path));
+        return Err(ScratchError::NotFound {
+            message: format!("File not found: {}", path),
+            path: Some(path),
+        });
     }
 
     if !try_select_in_notes_folder(&app, &file_path) {
@@ -3474,6 +10450,38 @@ fn open_file_preview(app: AppHandle, path: String) -> Result<(), String> {
     Ok(())
 }
 
+/// `scratch://note/<id>`, which opens note `id` in the main window.
+fn note_deep_link(id: &str) -> String {
+    format!("scratch://note/{}", urlencoding::encode(id))
+}
+
+fn deep_link_note_id(url: &str) -> Option<String> {
+    let encoded = url.strip_prefix("scratch://note/")?.trim_end_matches('/');
+    urlencoding::decode(encoded)
+        .ok()
+        .map(|id| id.into_owned())
+        .filter(|id| !id.is_empty())
+}
+
+/// Show the note a deep link names, wherever it has moved since.
+fn open_deep_link(app: &AppHandle, id: String) {
+    let folder = app
+        .state::<AppState>()
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone();
+    let id = folder
+        .and_then(|folder| redirects::resolve(Path::new(&folder), &id))
+        .unwrap_or(id);
+    let _ = app.emit_to("main", "select-note", id);
+    if let Some(main_window) = app.get_webview_window("main") {
+        let _ = main_window.show();
+        let _ = main_window.set_focus();
+    }
+}
+
 // Handle CLI arguments: open .md files in preview mode.
 // Returns true if a standalone preview window was created (file outside notes folder).
 fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) -> bool {
@@ -3481,6 +10489,33 @@ fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) -> bool {
     let mut opened_preview = false;
 
     for arg in args.iter().skip(1) {
+        // `--from-clipboard` makes a note from the clipboard and opens it
+        if arg == "--from-clipboard" && !safemode::is_active() {
+            opened_file = true;
+            let app = app.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                match clipboard::create_note(&app, None, None, audit::Surface::Cli) {
+                    Ok(note) => {
+                        let id = sort_captured_note(&app, note.id);
+                        let _ = app.emit_to("main", "select-note", id);
+                    }
+                    Err(e) => eprintln!("Failed to create note from clipboard: {}", e),
+                }
+                if let Some(main_window) = app.get_webview_window("main") {
+                    let _ = main_window.show();
+                    let _ = main_window.set_focus();
+                }
+            });
+            continue;
+        }
+
+        // `scratch://note/<id>`, passed as an argument on Windows and Linux
+        if let Some(id) = deep_link_note_id(arg) {
+            opened_file = true;
+            open_deep_link(app, id);
+            continue;
+        }
+
         // Skip flags
         if arg.starts_with('-') {
             continue;
@@ -3499,12 +10534,27 @@ fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) -> bool {
             {
                 opened_preview = true;
             }
+        } else if path.is_dir() && safemode::is_active() {
+            // Opening a folder writes the app config and the vault's settings
+            eprintln!("Safe mode: not opening {:?} as the notes folder", path);
         } else if path.is_dir() {
             let canonical = path.canonicalize().unwrap_or(path.clone());
             let state = app.state::<AppState>();
-            // Full initialization: directory creation, write-access check,
+            // Close the open vault if this is another one, then full
+            // initialization: directory creation, write-access check,
             // asset-scope update, config/settings persist, and search-index rebuild
-            match initialize_notes_folder(app, &canonical, &state) {
+            let current = state
+                .app_config
+                .read()
+                .expect("app_config read lock")
+                .notes_folder
+                .clone();
+            let closed = if current.is_some_and(|folder| Path::new(&folder) != canonical) {
+                tauri::async_runtime::block_on(vaults::close(app))
+            } else {
+                Ok(())
+            };
+            match closed.and_then(|()| initialize_notes_folder(app, &canonical, &state)) {
                 Ok(normalized_path) => {
                     // Emit event for when app is already running (single-instance)
                     let _ = app.emit("set-notes-folder", normalized_path);
@@ -3534,11 +10584,14 @@ fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) -> bool {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    benchmark::mark_start();
+    safemode::init(&std::env::args().collect::<Vec<_>>());
     let app = tauri::Builder::default()
         // Single-instance: forward CLI args from subsequent launches to the running instance
         .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
             handle_cli_args(app, &args, &cwd);
         }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
@@ -3570,6 +10623,14 @@ pub fn run() {
                 }
             }
 
+            // Folders opened before vaults were tracked join the list
+            if let Some(folder) = app_config.notes_folder.clone() {
+                if !app_config.vaults.iter().any(|v| v.path == folder) {
+                    vaults::remember(&mut app_config, &folder);
+                    let _ = save_app_config(app.handle(), &app_config);
+                }
+            }
+
             // Load per-folder settings if notes folder is set
             let settings = if let Some(ref folder) = app_config.notes_folder {
                 load_settings(folder)
@@ -3579,32 +10640,99 @@ pub fn run() {
 
             // Initialize search index if notes folder is set
             let search_index = if let Some(ref folder) = app_config.notes_folder {
-                if let Ok(index_path) = get_search_index_path(app.handle()) {
-                    SearchIndex::new(&index_path).ok().inspect(|idx| {
+                open_search_index(
+                    app.handle(),
+                    &settings,
+                    &app_config.source_folders,
+                    Path::new(folder),
+                )
+                    .ok()
+                    .inspect(|idx| {
                         let _ = idx.rebuild_index(&PathBuf::from(folder));
                     })
-                } else {
-                    None
-                }
             } else {
                 None
             };
 
+            // Restore the notes cache saved on the last shutdown
+            let notes_cache = app_config
+                .notes_folder
+                .as_deref()
+                .map(|folder| load_notes_cache(app.handle(), folder))
+                .unwrap_or_default();
+            let budgets = settings.memory_budgets.clone().unwrap_or_default();
+
             let state = AppState {
                 app_config: RwLock::new(app_config),
                 settings: RwLock::new(settings),
-                notes_cache: RwLock::new(HashMap::new()),
-                file_watcher: Mutex::new(None),
+                notes_cache: RwLock::new(memory::LruCache::from_entries(
+                    budgets.notes_cache_bytes(),
+                    notes_cache,
+                )),
                 search_index: Mutex::new(search_index),
-                debounce_map: Arc::new(Mutex::new(HashMap::new())),
+                ..Default::default()
             };
+            state.link_index.set_budget(budgets.link_index_bytes());
             app.manage(state);
 
+            // Record note changes in the audit log
+            if let Ok(app_data) = app.path().app_data_dir() {
+                app.state::<AppState>().audit.open(&app_data);
+            }
+
+            // Safe mode leaves the vault alone: no automations, routines,
+            // vault lock or source watching
+            if !safemode::is_active() {
+                // Start scheduled exports/backups (runs app-start automations first)
+                automation::start_scheduler(app.handle().clone());
+
+                // Lock the vault against other instances, recovering from a crash
+                instance::start(app.handle().clone());
+
+                // Add today's recurring tasks to the daily note
+                routines::start(app.handle().clone());
+
+                // Archive or trash expired notes
+                retention::start(app.handle().clone());
+
+                // Keep read-only source folders in the search index
+                sources::restart_watcher(app.handle());
+            }
+
+            // Warn when the vault or app-data volume runs low on space
+            disk::start_monitor(app.handle().clone());
+
+            // Watch custom themes for hot-reload
+            if let Ok(themes_dir) = get_themes_dir(app.handle()) {
+                match setup_theme_watcher(app.handle().clone(), &themes_dir) {
+                    Ok(watcher) => {
+                        *app.state::<AppState>()
+                            .theme_watcher
+                            .lock()
+                            .expect("theme watcher mutex") = Some(watcher);
+                    }
+                    Err(e) => eprintln!("Failed to watch themes directory: {}", e),
+                }
+            }
+
             // Add notes folder to asset protocol scope so images can be served
             if let Some(ref folder) = app.state::<AppState>().app_config.read().expect("app_config read lock").notes_folder.clone() {
                 let _ = app.asset_protocol_scope().allow_directory(folder, true);
             }
 
+            // scratch:// links. macOS delivers them as events; Windows and Linux
+            // launch the app with the link as an argument (see handle_cli_args).
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            let _ = app.deep_link().register_all();
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    if let Some(id) = deep_link_note_id(url.as_str()) {
+                        open_deep_link(&handle, id);
+                    }
+                }
+            });
+
             // Handle CLI args on first launch; determine whether to show the main window.
             // When a standalone preview is opened (file outside the notes folder) and the
             // notes folder is already configured, the main window is closed so users only
@@ -3643,9 +10771,14 @@ pub fn run() {
                 }
             }
 
+            benchmark::mark_ready();
             Ok(())
         })
         .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                shutdown::on_close_requested(window, api);
+            }
+
             // Handle drag-and-drop of .md files onto any window
             if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
                 let app = window.app_handle();
@@ -3659,31 +10792,158 @@ pub fn run() {
                 }
             }
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(safemode::guard(tauri::generate_handler![
             get_notes_folder,
+            resolve_vault_path,
+            resolve_id,
             set_notes_folder,
+            list_vault_templates,
+            create_vault,
+            list_vaults,
+            add_vault,
+            remove_vault,
+            switch_vault,
+            list_source_folders,
+            add_source_folder,
+            remove_source_folder,
             list_notes,
+            toggle_pin,
+            star_note,
+            unstar_note,
+            list_starred,
             read_note,
             save_note,
+            get_backlinks,
+            get_delete_impact,
             delete_note,
+            rename_note,
+            list_trash,
+            restore_note,
+            empty_trash,
+            create_restore_point,
+            list_restore_points,
+            rollback_to,
+            list_note_versions,
+            read_note_version,
+            get_history_storage_stats,
             create_note,
             list_folders,
             create_folder,
             delete_folder,
             rename_folder,
             move_note,
+            get_audit_log,
             move_folder,
             get_settings,
             update_settings,
             update_git_enabled,
+            export_settings,
+            import_settings,
+            list_themes,
+            get_theme,
+            generate_vault_report,
+            find_orphan_notes,
+            list_tags,
+            list_notes_by_tag,
+            export_vault_metadata,
+            import_vault_metadata,
+            export_vault_zip,
+            parse_natural_date,
+            open_daily_note,
+            append_log_entry,
+            materialize_routines,
+            get_routines,
+            get_habit_data,
+            get_goal_progress,
+            get_project_dashboard,
+            get_structured_content,
+            get_notes_with_locations,
+            set_note_location,
+            get_reading_list,
+            update_reading_progress,
+            set_reading_status,
+            list_code_annotations,
+            get_random_note,
+            get_review_queue,
+            mark_reviewed,
+            get_due_cards,
+            grade_card,
+            export_anki,
+            export_note_audio,
+            get_tts_command,
+            set_tts_command,
+            export_slides,
+            translate_note,
+            set_translation_key,
+            archive_url,
+            search_references,
+            insert_citation,
+            create_literature_note,
+            run_code_block,
+            get_code_block_settings,
+            set_code_block_settings,
+            get_annotations,
+            add_annotation,
+            remove_annotation,
+            set_reading_position,
+            run_export_profile,
+            get_note_export_style,
+            get_automation_status,
+            run_automation,
+            run_inbox_rules,
+            apply_retention,
+            get_retention_report,
+            share_note_link,
+            revoke_share,
+            set_share_token,
+            host_session,
+            join_session,
+            update_session_content,
+            get_session,
+            leave_session,
+            serve_vault,
+            stop_serving_vault,
+            create_access_token,
+            list_access_tokens,
+            revoke_access_token,
+            get_privacy_status,
+            set_privacy_passphrase,
+            unlock_private_notes,
+            lock_private_notes,
             preview_note_name,
             write_file,
             search_notes,
             start_file_watcher,
+            stop_file_watcher,
             rebuild_search_index,
+            get_vault_languages,
+            autosave_note,
+            flush_autosave,
+            get_instance_status,
+            list_drafts,
+            restore_draft,
+            discard_draft,
+            verify_vault,
+            benchmark_vault,
+            get_disk_space,
+            get_memory_usage,
+            get_safe_mode,
             copy_to_clipboard,
+            create_note_from_clipboard,
+            expand_template,
+            register_template_variable,
+            unregister_template_variable,
+            list_template_variables,
             copy_image_to_assets,
+            rename_asset,
+            import_asset,
+            list_assets,
+            find_orphaned_assets,
+            delete_orphaned_assets,
+            get_attachment_meta,
+            set_attachment_meta,
             save_clipboard_image,
+            capture_screenshot,
             open_folder_dialog,
             open_in_file_manager,
             open_url_safe,
@@ -3696,6 +10956,9 @@ pub fn run() {
             git_pull,
             git_add_remote,
             git_push_with_upstream,
+            git_history,
+            git_diff,
+            git_restore,
             ai_check_claude_cli,
             ai_check_codex_cli,
             ai_check_opencode_cli,
@@ -3707,17 +10970,31 @@ pub fn run() {
             read_file_direct,
             save_file_direct,
             import_file_to_folder,
+            import_markdown_files,
+            import_files,
+            import_enex,
+            import_joplin,
+            detect_importable_sources,
             open_file_preview,
             install_cli,
             uninstall_cli,
             get_cli_status,
-        ])
+        ]))
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
 
     // Use .run() callback to handle macOS "Open With" file events
     // RunEvent::Opened is macOS-only in Tauri v2
     app.run(|_app_handle, _event| {
+        // Flush and persist state before quitting
+        match &_event {
+            tauri::RunEvent::ExitRequested { code, api, .. } => {
+                shutdown::on_exit_requested(_app_handle, *code, api);
+            }
+            tauri::RunEvent::Exit => shutdown::on_exit(_app_handle),
+            _ => {}
+        }
+
         #[cfg(target_os = "macos")]
         if let tauri::RunEvent::Opened { urls } = _event {
             for url in urls {
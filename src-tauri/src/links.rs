@@ -0,0 +1,344 @@
+//! Link extraction and resolution between notes.
+//!
+//! Two link styles are recognized: `[[wikilinks]]` (with optional `|alias` and
+//! `#heading` suffixes) and relative markdown links to `.md` files
+//! (`[text](other-note.md)`). External URLs and asset links are ignored.
+
+use crate::frontmatter;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+fn wikilink_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\[\[([^\[\]\n]+?)\]\]").unwrap())
+}
+
+fn asset_ref_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"(?:^|[(\s"'/])assets/([^)\s"'?#]+)"#).unwrap())
+}
+
+fn markdown_link_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:^|[^!])\[[^\]]*\]\(([^)\s]+)(?:\s+\x22[^\x22]*\x22)?\)").unwrap())
+}
+
+fn markdown_link_parts_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(^|[^!])\[([^\]]*)\]\(([^)\s]+)((?:\s+\x22[^\x22]*\x22)?)\)").unwrap()
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkKind {
+    Wiki,
+    Markdown,
+}
+
+/// A raw link found in a note, before resolution against the vault.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub target: String,
+    pub kind: LinkKind,
+}
+
+/// Lines of the note body outside fenced code blocks, with their 1-based line
+/// numbers in the whole note.
+fn linkable_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    let body = frontmatter::body(content);
+    let offset = content[..content.len() - body.len()].lines().count();
+    let mut in_fence = false;
+    body.lines().enumerate().filter_map(move |(n, line)| {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            return None;
+        }
+        (!in_fence).then_some((offset + n + 1, line))
+    })
+}
+
+/// Links on one line of a note.
+fn line_links(line: &str, links: &mut Vec<Link>) {
+    for cap in wikilink_re().captures_iter(line) {
+        let inner = &cap[1];
+        let target = inner.split('|').next().unwrap_or(inner);
+        let target = target.split('#').next().unwrap_or(target).trim();
+        if !target.is_empty() {
+            links.push(Link {
+                target: target.to_string(),
+                kind: LinkKind::Wiki,
+            });
+        }
+    }
+
+    for cap in markdown_link_re().captures_iter(line) {
+        let href = &cap[1];
+        if href.contains("://") || href.starts_with("mailto:") || href.starts_with('#') {
+            continue;
+        }
+        let path = href.split('#').next().unwrap_or(href);
+        let decoded = urlencoding::decode(path)
+            .map(|p| p.into_owned())
+            .unwrap_or_else(|_| path.to_string());
+        if let Some(stripped) = decoded.strip_suffix(".md") {
+            links.push(Link {
+                target: stripped.to_string(),
+                kind: LinkKind::Markdown,
+            });
+        }
+    }
+}
+
+/// Extract outgoing note links, skipping fenced code blocks.
+pub fn extract_links(content: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    for (_, line) in linkable_lines(content) {
+        line_links(line, &mut links);
+    }
+    links
+}
+
+/// Lines of `content` (written in note `from_id`) with a link that resolves
+/// to `target_id`, as 1-based line numbers and the line's text.
+pub fn lines_linking_to<'a>(
+    content: &'a str,
+    from_id: &str,
+    target_id: &str,
+    resolver: &LinkResolver,
+) -> Vec<(usize, &'a str)> {
+    let mut links = Vec::new();
+    linkable_lines(content)
+        .filter(|(_, line)| {
+            links.clear();
+            line_links(line, &mut links);
+            links
+                .iter()
+                .any(|link| resolver.resolve(link, from_id).as_deref() == Some(target_id))
+        })
+        .collect()
+}
+
+/// Lookup table mapping link targets to note IDs.
+pub struct LinkResolver {
+    by_id: HashMap<String, String>,
+    by_leaf: HashMap<String, String>,
+    by_title: HashMap<String, String>,
+}
+
+impl LinkResolver {
+    /// Build a resolver from (id, title) pairs.
+    pub fn new<'a>(notes: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut by_id = HashMap::new();
+        let mut by_leaf = HashMap::new();
+        let mut by_title = HashMap::new();
+        for (id, title) in notes {
+            by_id.insert(id.to_lowercase(), id.to_string());
+            let leaf = id.rsplit('/').next().unwrap_or(id).to_lowercase();
+            by_leaf.entry(leaf).or_insert_with(|| id.to_string());
+            by_title
+                .entry(title.to_lowercase())
+                .or_insert_with(|| id.to_string());
+        }
+        Self {
+            by_id,
+            by_leaf,
+            by_title,
+        }
+    }
+
+    /// Resolve a link found in note `from_id` to a note ID, if it points at one.
+    pub fn resolve(&self, link: &Link, from_id: &str) -> Option<String> {
+        match link.kind {
+            LinkKind::Wiki => {
+                let key = link.target.trim_end_matches(".md").to_lowercase();
+                self.by_id
+                    .get(&key)
+                    .or_else(|| self.by_leaf.get(&key))
+                    .or_else(|| self.by_title.get(&key))
+                    .cloned()
+            }
+            LinkKind::Markdown => {
+                let joined = match from_id.rfind('/') {
+                    Some(pos) if !link.target.starts_with('/') => {
+                        format!("{}/{}", &from_id[..pos], link.target)
+                    }
+                    _ => link.target.trim_start_matches('/').to_string(),
+                };
+                let normalized = normalize_relative(&joined)?;
+                self.by_id.get(&normalized.to_lowercase()).cloned()
+            }
+        }
+    }
+}
+
+/// Collapse `.` and `..` segments of a relative POSIX path.
+fn normalize_relative(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            s => parts.push(s),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// File names under the vault's `assets/` folder referenced by a note
+/// (image embeds, links or raw HTML `src` attributes), de-duplicated.
+pub fn extract_asset_refs(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for cap in asset_ref_re().captures_iter(content) {
+        let name = urlencoding::decode(&cap[1])
+            .map(|n| n.into_owned())
+            .unwrap_or_else(|_| cap[1].to_string());
+        if !name.split('/').any(|part| part == "..") && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// What to do with links to a note that is going away or being renamed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum LinkRewrite {
+    /// Replace each link with its visible text
+    Strip,
+    /// Point each link at another note
+    Redirect { to: String },
+    /// Point each link at the note's new ID; wikilinks name it as `wiki`
+    /// (usually the new title). Only used by `rename_note`.
+    #[serde(skip_deserializing)]
+    Rename { to: String, wiki: String },
+}
+
+/// Relative markdown href from note `from_id` to note `to_id`.
+fn relative_href(from_id: &str, to_id: &str) -> String {
+    let (from_dir, _) = from_id.rsplit_once('/').unwrap_or(("", from_id));
+    let from_dir: Vec<&str> = from_dir.split('/').filter(|p| !p.is_empty()).collect();
+    let to: Vec<&str> = to_id.split('/').collect();
+    let (to_dir, _) = to.split_at(to.len() - 1);
+    let common = from_dir
+        .iter()
+        .zip(to_dir)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from_dir.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|p| urlencoding::encode(p).into_owned()),
+    );
+    format!("{}.md", parts.join("/"))
+}
+
+/// Rewrite the links in note `from_id` that resolve to `target_id`, outside
+/// code fences and frontmatter. Returns None when nothing changed.
+pub fn rewrite_links(
+    content: &str,
+    from_id: &str,
+    target_id: &str,
+    resolver: &LinkResolver,
+    rewrite: &LinkRewrite,
+) -> Option<String> {
+    let body = frontmatter::body(content);
+    let mut updated = content[..content.len() - body.len()].to_string();
+    let points_at_target = |target: &str, kind: LinkKind| {
+        let link = Link {
+            target: target.to_string(),
+            kind,
+        };
+        resolver.resolve(&link, from_id).as_deref() == Some(target_id)
+    };
+
+    let mut in_fence = false;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        if is_fence {
+            in_fence = !in_fence;
+        }
+        if is_fence || in_fence {
+            updated.push_str(line);
+            continue;
+        }
+
+        let line = wikilink_re().replace_all(line, |cap: &Captures| {
+            let inner = &cap[1];
+            let (target, alias) = match inner.split_once('|') {
+                Some((target, alias)) => (target, Some(alias)),
+                None => (inner, None),
+            };
+            let (name, heading) = match target.split_once('#') {
+                Some((name, heading)) => (name, Some(heading)),
+                None => (target, None),
+            };
+            if !points_at_target(name.trim(), LinkKind::Wiki) {
+                return cap[0].to_string();
+            }
+            match rewrite {
+                LinkRewrite::Strip => alias.unwrap_or(name).trim().to_string(),
+                LinkRewrite::Redirect { to: target } | LinkRewrite::Rename { wiki: target, .. } => {
+                    let mut inner = target.clone();
+                    if let Some(heading) = heading {
+                        inner = format!("{}#{}", inner, heading);
+                    }
+                    if let Some(alias) = alias {
+                        inner = format!("{}|{}", inner, alias);
+                    }
+                    format!("[[{}]]", inner)
+                }
+            }
+        });
+        let line = markdown_link_parts_re().replace_all(&line, |cap: &Captures| {
+            let href = &cap[3];
+            let (path, anchor) = match href.split_once('#') {
+                Some((path, anchor)) => (path, Some(anchor)),
+                None => (href, None),
+            };
+            let decoded = urlencoding::decode(path)
+                .map(|p| p.into_owned())
+                .unwrap_or_else(|_| path.to_string());
+            let is_target = !href.contains("://")
+                && decoded
+                    .strip_suffix(".md")
+                    .is_some_and(|stem| points_at_target(stem, LinkKind::Markdown));
+            if !is_target {
+                return cap[0].to_string();
+            }
+            match rewrite {
+                LinkRewrite::Strip => format!("{}{}", &cap[1], &cap[2]),
+                LinkRewrite::Redirect { to } | LinkRewrite::Rename { to, .. } => {
+                    let mut href = relative_href(from_id, to);
+                    if let Some(anchor) = anchor {
+                        href = format!("{}#{}", href, anchor);
+                    }
+                    format!("{}[{}]({}{})", &cap[1], &cap[2], href, &cap[4])
+                }
+            }
+        });
+        updated.push_str(&line);
+    }
+
+    (updated != content).then_some(updated)
+}
+
+/// How many links in `content` (written in note `from_id`) resolve to `target_id`.
+pub fn count_links_to(
+    content: &str,
+    from_id: &str,
+    target_id: &str,
+    resolver: &LinkResolver,
+) -> usize {
+    extract_links(content)
+        .iter()
+        .filter(|link| resolver.resolve(link, from_id).as_deref() == Some(target_id))
+        .count()
+}
@@ -0,0 +1,248 @@
+//! Note locations, for a map view.
+//!
+//! `location:` frontmatter puts a note on the map, as coordinates
+//! (`48.8584, 2.2945` or `geo:48.8584,2.2945`) or a place name. Place names
+//! are looked up with the geocoding provider from settings, Nominatim or
+//! Photon (none by default, so nothing leaves the machine unasked), one
+//! request a second as Nominatim's usage policy asks and at most
+//! `MAX_LOOKUPS` per listing. Answers, misses included, are cached in
+//! `.scratch/geocode.json`, so each place is looked up once.
+
+use crate::frontmatter::Frontmatter;
+use crate::{listing_title_and_preview, note_exposure, store, LockedNotePolicy, ScannedNote};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+const STORE_FILE: &str = "geocode.json";
+const MAX_LOOKUPS: usize = 10;
+const LOOKUP_INTERVAL: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_NOMINATIM_URL: &str = "https://nominatim.openstreetmap.org";
+const DEFAULT_PHOTON_URL: &str = "https://photon.komoot.io";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GeocodingProvider {
+    Nominatim,
+    Photon,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeocodingSettings {
+    pub provider: Option<GeocodingProvider>, // none: place names stay off the map
+    pub url: Option<String>,                 // default: the provider's public instance
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteLocation {
+    pub id: String,
+    pub title: String,
+    pub location: String, // as written
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>, // both None for a place not (yet) found
+    pub geocoded: bool,         // from a place name rather than coordinates
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeocodeCache {
+    pub places: BTreeMap<String, Option<[f64; 2]>>, // [lat, lon] by place; None: not found
+}
+
+fn place_key(place: &str) -> String {
+    place
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+impl GeocodeCache {
+    pub fn load(notes_folder: &str) -> Self {
+        store::load(Path::new(notes_folder), STORE_FILE)
+    }
+
+    pub fn save(&self, notes_folder: &str) -> Result<()> {
+        store::save(Path::new(notes_folder), STORE_FILE, self)
+    }
+
+    /// Fill in the coordinates of place names already looked up.
+    pub fn fill(&self, locations: &mut [NoteLocation]) {
+        for location in locations.iter_mut().filter(|l| l.geocoded) {
+            if let Some(Some([lat, lon])) = self.places.get(&place_key(&location.location)) {
+                location.latitude = Some(*lat);
+                location.longitude = Some(*lon);
+            }
+        }
+    }
+
+    /// Place names in `locations` that were never looked up.
+    pub fn missing(&self, locations: &[NoteLocation]) -> Vec<String> {
+        let mut places: Vec<String> = locations
+            .iter()
+            .filter(|l| l.geocoded && !self.places.contains_key(&place_key(&l.location)))
+            .map(|l| l.location.clone())
+            .collect();
+        places.sort_by_cached_key(|place| place_key(place));
+        places.dedup_by(|a, b| place_key(a) == place_key(b));
+        places
+    }
+}
+
+/// Coordinates written as `lat, lon`, `lat lon` or a `geo:` URI.
+pub fn parse_coordinates(text: &str) -> Option<(f64, f64)> {
+    let text = text.trim();
+    let text = text.strip_prefix("geo:").unwrap_or(text);
+    let text = text.split(';').next()?; // `geo:` URIs may carry `;u=<uncertainty>`
+    let mut parts = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|p| !p.is_empty());
+    let lat: f64 = parts.next()?.parse().ok()?;
+    let lon: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon)
+    {
+        return None;
+    }
+    Some((lat, lon))
+}
+
+/// Notes with a `location:`, by title, with the coordinates they give.
+/// Locked notes that are kept out of search are left out. Blocking.
+pub fn collect(notes: &[ScannedNote], locked_policy: &LockedNotePolicy) -> Vec<NoteLocation> {
+    let mut locations: Vec<NoteLocation> = notes
+        .iter()
+        .filter_map(|note| {
+            let location = Frontmatter::parse(&note.content)
+                .get("location")?
+                .trim()
+                .to_string();
+            let exposure = note_exposure(&note.content, locked_policy);
+            if location.is_empty() || (exposure.locked && !exposure.index_content) {
+                return None;
+            }
+            let coordinates = parse_coordinates(&location);
+            let (title, _) = listing_title_and_preview(&note.content, locked_policy);
+            Some(NoteLocation {
+                id: note.id.clone(),
+                title,
+                location,
+                latitude: coordinates.map(|(lat, _)| lat),
+                longitude: coordinates.map(|(_, lon)| lon),
+                geocoded: coordinates.is_none(),
+            })
+        })
+        .collect();
+    locations.sort_by_cached_key(|l| l.title.to_lowercase());
+    locations
+}
+
+/// A provider URL from settings: https, or http on this machine.
+fn base_url(configured: Option<&str>, default: &str) -> Result<url::Url, String> {
+    let url = configured
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .unwrap_or(default);
+    let parsed = url::Url::parse(url).map_err(|_| format!("Invalid URL: {}", url))?;
+    match parsed.scheme() {
+        "https" => Ok(parsed),
+        "http" if matches!(parsed.host_str(), Some("localhost" | "127.0.0.1")) => Ok(parsed),
+        _ => Err("Geocoding URLs must use https".to_string()),
+    }
+}
+
+fn number(value: Option<&serde_json::Value>) -> Option<f64> {
+    match value? {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+async fn lookup(
+    client: &reqwest::Client,
+    provider: GeocodingProvider,
+    base: &url::Url,
+    place: &str,
+) -> Result<Option<[f64; 2]>, String> {
+    let base = base.as_str().trim_end_matches('/');
+    let url = match provider {
+        GeocodingProvider::Nominatim => url::Url::parse_with_params(
+            &format!("{}/search", base),
+            &[("q", place), ("format", "jsonv2"), ("limit", "1")],
+        ),
+        GeocodingProvider::Photon => {
+            url::Url::parse_with_params(&format!("{}/api/", base), &[("q", place), ("limit", "1")])
+        }
+    }
+    .map_err(|e| e.to_string())?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Geocoding request failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Geocoding service returned {}", status));
+    }
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("Geocoding request failed: {}", e))?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|_| "Unexpected response from the geocoding service".to_string())?;
+    let found = match provider {
+        GeocodingProvider::Nominatim => value
+            .get(0)
+            .and_then(|hit| Some([number(hit.get("lat"))?, number(hit.get("lon"))?])),
+        GeocodingProvider::Photon => value
+            .pointer("/features/0/geometry/coordinates")
+            .and_then(|c| Some([number(c.get(1))?, number(c.get(0))?])),
+    };
+    Ok(found)
+}
+
+/// Look up `places` and record the answers in `cache`. Stops at the first
+/// failed request, leaving the rest for next time; returns whether anything
+/// was recorded.
+pub async fn geocode(
+    settings: &GeocodingSettings,
+    places: &[String],
+    cache: &mut GeocodeCache,
+) -> Result<bool, String> {
+    let Some(provider) = settings.provider else {
+        return Ok(false);
+    };
+    let default = match provider {
+        GeocodingProvider::Nominatim => DEFAULT_NOMINATIM_URL,
+        GeocodingProvider::Photon => DEFAULT_PHOTON_URL,
+    };
+    let base = base_url(settings.url.as_deref(), default)?;
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .user_agent(concat!("Scratch/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut recorded = false;
+    for (i, place) in places.iter().take(MAX_LOOKUPS).enumerate() {
+        if i > 0 {
+            tokio::time::sleep(LOOKUP_INTERVAL).await;
+        }
+        match lookup(&client, provider, &base, place).await {
+            Ok(found) => {
+                cache.places.insert(place_key(place), found);
+                recorded = true;
+            }
+            Err(e) if recorded => {
+                eprintln!("Geocoding stopped: {}", e);
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(recorded)
+}
@@ -0,0 +1,84 @@
+//! Write locks that keep concurrent changes to a note from interleaving.
+//!
+//! Autosave, full saves, renames, moves, deletes, the vault server and
+//! co-editing all write note files from their own tasks. Each takes the lock
+//! for the notes it touches for the whole read-modify-write, so a rename can't
+//! land between an autosave's existence check and its write, and the file and
+//! the search index always agree. Operations on whole folders (and git pulls)
+//! take the vault lock instead, which waits for every note lock and holds
+//! them all off.
+//!
+//! Locks are not reentrant: take them once, in the outermost operation, and
+//! never while holding another.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedMutexGuard, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+type NoteMutex = Arc<tokio::sync::Mutex<()>>;
+
+/// Clones share the same locks, so one can be moved into a blocking task.
+#[derive(Clone, Default)]
+pub struct NoteLocks {
+    vault: Arc<RwLock<()>>,
+    notes: Arc<Mutex<HashMap<String, NoteMutex>>>,
+}
+
+/// Held while changing notes; released on drop.
+pub struct NoteGuard {
+    _notes: Vec<OwnedMutexGuard<()>>,
+    _vault: OwnedRwLockReadGuard<()>,
+}
+
+/// Held while changing a whole vault; released on drop.
+pub struct VaultGuard {
+    _vault: OwnedRwLockWriteGuard<()>,
+}
+
+/// Lowercased so two spellings of a note on a case-insensitive filesystem
+/// share a lock.
+fn key(folder: &Path, id: &str) -> String {
+    format!("{}\0{}", folder.display(), id.to_lowercase())
+}
+
+impl NoteLocks {
+    /// The mutexes for `ids`, deduplicated and in a fixed order so two
+    /// operations locking overlapping notes can't deadlock.
+    fn mutexes(&self, folder: &Path, ids: &[&str]) -> Vec<NoteMutex> {
+        let mut keys: Vec<String> = ids.iter().map(|id| key(folder, id)).collect();
+        keys.sort();
+        keys.dedup();
+        let mut notes = self.notes.lock().expect("note locks mutex");
+        // Forget locks nobody holds or waits for
+        notes.retain(|_, lock| Arc::strong_count(lock) > 1);
+        keys.into_iter()
+            .map(|key| notes.entry(key).or_default().clone())
+            .collect()
+    }
+
+    /// Lock the notes `ids` in the vault at `folder`.
+    pub async fn lock(&self, folder: &Path, ids: &[&str]) -> NoteGuard {
+        let vault = self.vault.clone().read_owned().await;
+        let mut notes = Vec::with_capacity(ids.len());
+        for mutex in self.mutexes(folder, ids) {
+            notes.push(mutex.lock_owned().await);
+        }
+        NoteGuard {
+            _notes: notes,
+            _vault: vault,
+        }
+    }
+
+    /// `lock` for code running on a blocking thread.
+    pub fn blocking_lock(&self, folder: &Path, ids: &[&str]) -> NoteGuard {
+        tauri::async_runtime::block_on(self.lock(folder, ids))
+    }
+
+    /// Lock every note in the vault.
+    pub async fn lock_vault(&self) -> VaultGuard {
+        VaultGuard {
+            _vault: self.vault.clone().write_owned().await,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Append-only log notes that rotate monthly.
+//!
+//! A log is named by a note ID, such as `log` or `Work/standup`. Entries go
+//! into that month's note of the series, `<log>-YYYY-MM`, as timestamped
+//! bullets under a heading per day, so no single note grows without end.
+//! The month notes carry `log: <log>` frontmatter and link back to the log's
+//! index note, `<log>` itself, which lists the series newest first and gets
+//! each new month as it starts.
+
+use crate::frontmatter::{self, Value};
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// A log's index note ID from what the user typed.
+pub fn normalize(log: &str) -> String {
+    let log = log.trim().trim_matches('/');
+    log.strip_suffix(".md").unwrap_or(log).to_string()
+}
+
+fn leaf(log: &str) -> &str {
+    log.rsplit('/').next().unwrap_or(log)
+}
+
+pub fn month_id(log: &str, date: NaiveDate) -> String {
+    format!("{}-{}", log, date.format("%Y-%m"))
+}
+
+pub fn new_month(log: &str, date: NaiveDate) -> String {
+    let content = format!(
+        "# {} {}\n\nPart of [[{}]].\n",
+        leaf(log),
+        date.format("%Y-%m"),
+        log
+    );
+    frontmatter::set(&content, "log", Value::Scalar(log.to_string()))
+}
+
+pub fn new_index(log: &str) -> String {
+    let content = format!("# {}\n", leaf(log));
+    frontmatter::set(&content, "type", Value::Scalar("log".to_string()))
+}
+
+/// Append an entry under today's heading, adding the heading if the last one
+/// is for another day. Lines after the first are indented under the bullet.
+pub fn append(content: &str, entry: &str, now: NaiveDateTime) -> String {
+    let day = format!("## {}", now.format("%Y-%m-%d"));
+    let last_day = content.lines().rev().find(|line| line.starts_with("## "));
+    let mut out = content.trim_end().to_string();
+    if last_day.map(str::trim_end) != Some(day.as_str()) {
+        out.push_str(&format!("\n\n{}\n", day));
+    }
+    let lines: Vec<&str> = entry.trim().lines().map(str::trim_end).collect();
+    out.push_str(&format!(
+        "\n- {} {}\n",
+        now.format("%H:%M"),
+        lines.join("\n  ")
+    ));
+    out
+}
+
+/// Link a month note from the index, above the months already listed.
+/// Returns None when it is linked already.
+pub fn link_month(index: &str, log: &str, month_id: &str) -> Option<String> {
+    let link = format!("[[{}]]", month_id);
+    if index.contains(&link) {
+        return None;
+    }
+    let series = format!("- [[{}-", log);
+    let item = format!("- {}\n", link);
+    Some(match index.find(&series) {
+        Some(at) => format!("{}{}{}", &index[..at], item, &index[at..]),
+        None => format!("{}\n\n{}", index.trim_end(), item),
+    })
+}
@@ -0,0 +1,246 @@
+//! Memory budgets for the in-memory caches.
+//!
+//! The notes cache evicts the least recently used notes once it is over its
+//! budget, favouring pinned and recently modified notes when a listing fills
+//! it; it only backs the fallback search, which reads from disk anyway. The
+//! link index can't answer lookups with notes missing, so it is kept whole or
+//! not at all: over budget, backlinks are found by scanning the vault. The
+//! file watcher's debounce map keeps at most a fixed number of paths.
+
+use crate::{AppState, NoteMetadata};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+const MB: usize = 1024 * 1024;
+const DEFAULT_NOTES_CACHE_MB: usize = 32;
+const DEFAULT_LINK_INDEX_MB: usize = 64;
+const DEFAULT_DEBOUNCE_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryBudgets {
+    pub notes_cache_mb: Option<usize>,   // default 32
+    pub link_index_mb: Option<usize>,    // default 64
+    pub debounce_entries: Option<usize>, // default 100
+}
+
+impl MemoryBudgets {
+    pub fn notes_cache_bytes(&self) -> usize {
+        self.notes_cache_mb.unwrap_or(DEFAULT_NOTES_CACHE_MB) * MB
+    }
+
+    pub fn link_index_bytes(&self) -> usize {
+        self.link_index_mb.unwrap_or(DEFAULT_LINK_INDEX_MB) * MB
+    }
+
+    pub fn debounce_entries(&self) -> usize {
+        self.debounce_entries
+            .unwrap_or(DEFAULT_DEBOUNCE_ENTRIES)
+            .max(1)
+    }
+}
+
+/// Approximate heap and inline size of a cached value.
+pub trait Weigh {
+    fn weight(&self) -> usize;
+}
+
+impl Weigh for NoteMetadata {
+    fn weight(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.id.len()
+            + self.title.len()
+            + self.preview.len()
+            + self.tags.iter().map(|t| t.len()).sum::<usize>()
+            + self.language.as_ref().map_or(0, |l| l.len())
+    }
+}
+
+struct Slot<V> {
+    value: V,
+    used: u64,
+    weight: usize,
+}
+
+/// String-keyed map that evicts its least recently used entries when their
+/// total weight goes over the budget.
+pub struct LruCache<V> {
+    entries: HashMap<String, Slot<V>>,
+    clock: u64,
+    bytes: usize,
+    budget: usize,
+}
+
+impl<V: Weigh> LruCache<V> {
+    pub fn new(budget: usize) -> Self {
+        LruCache {
+            entries: HashMap::new(),
+            clock: 0,
+            bytes: 0,
+            budget,
+        }
+    }
+
+    pub fn from_entries(budget: usize, entries: impl IntoIterator<Item = (String, V)>) -> Self {
+        let mut cache = Self::new(budget);
+        for (key, value) in entries {
+            cache.insert(key, value);
+        }
+        cache
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    /// Insert or replace `key`, marking it most recently used.
+    pub fn insert(&mut self, key: String, value: V) {
+        self.clock += 1;
+        let weight = key.len() + value.weight();
+        let slot = Slot {
+            value,
+            used: self.clock,
+            weight,
+        };
+        self.bytes += weight;
+        if let Some(old) = self.entries.insert(key, slot) {
+            self.bytes -= old.weight;
+        }
+        self.evict();
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let slot = self.entries.remove(key)?;
+        self.bytes -= slot.weight;
+        Some(slot.value)
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&String, &V) -> bool) {
+        let mut freed = 0;
+        self.entries.retain(|key, slot| {
+            let kept = keep(key, &slot.value);
+            if !kept {
+                freed += slot.weight;
+            }
+            kept
+        });
+        self.bytes -= freed;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.values().map(|slot| &slot.value)
+    }
+
+    pub fn usage(&self) -> CacheUsage {
+        CacheUsage {
+            entries: self.entries.len(),
+            bytes: self.bytes,
+            budget_bytes: self.budget,
+        }
+    }
+
+    /// Drop least recently used entries until the cache is at 90% of its
+    /// budget, so a full cache doesn't evict on every insert.
+    fn evict(&mut self) {
+        if self.bytes <= self.budget {
+            return;
+        }
+        let target = self.budget / 10 * 9;
+        let mut by_age: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(key, slot)| (slot.used, key.clone()))
+            .collect();
+        by_age.sort_unstable();
+        for (_, key) in by_age {
+            if self.bytes <= target {
+                break;
+            }
+            self.remove(&key);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheUsage {
+    pub entries: usize,
+    pub bytes: usize, // estimated
+    pub budget_bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    pub notes_cache: CacheUsage,
+    pub link_index: CacheUsage, // zero entries when not built or over budget
+    pub debounce_entries: usize,
+    pub debounce_limit: usize,
+    pub resident_bytes: Option<u64>, // whole process; Linux only
+}
+
+/// The budgets in the current settings.
+pub fn budgets(state: &AppState) -> MemoryBudgets {
+    let settings = state.settings.read().expect("settings read lock");
+    settings.memory_budgets.clone().unwrap_or_default()
+}
+
+/// Apply `budgets` to the caches, evicting what no longer fits.
+pub fn apply(state: &AppState, budgets: &MemoryBudgets) {
+    state
+        .notes_cache
+        .write()
+        .expect("cache write lock")
+        .set_budget(budgets.notes_cache_bytes());
+    state.link_index.set_budget(budgets.link_index_bytes());
+}
+
+/// Make room in the debounce map for one more path, dropping paths that
+/// haven't changed for longest.
+pub fn trim_debounce(map: &mut HashMap<PathBuf, Instant>, limit: usize) {
+    while map.len() >= limit {
+        let Some(oldest) = map
+            .iter()
+            .min_by_key(|(_, at)| **at)
+            .map(|(k, _)| k.clone())
+        else {
+            break;
+        };
+        map.remove(&oldest);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_bytes() -> Option<u64> {
+    None
+}
+
+pub fn usage(state: &AppState) -> MemoryUsage {
+    MemoryUsage {
+        notes_cache: state.notes_cache.read().expect("cache read lock").usage(),
+        link_index: state.link_index.usage(),
+        debounce_entries: state.debounce_map.lock().expect("debounce map mutex").len(),
+        debounce_limit: budgets(state).debounce_entries(),
+        resident_bytes: resident_bytes(),
+    }
+}
@@ -0,0 +1,198 @@
+//! Machine-readable dump of the vault's structure.
+//!
+//! `export_vault_metadata` writes every visible note's title, preview, tags,
+//! resolved links and tasks, plus tag counts, the link graph and totals, as
+//! one JSON document, so external tools never have to parse markdown. Reading
+//! a dump back seeds the notes cache (persisted across restarts and used by
+//! the fallback search) with the notes whose files have not changed since.
+
+use crate::{
+    abs_path_from_id, is_private_note, listing_language, listing_title_and_preview, note_exposure,
+    report, tasks, LockedNotePolicy, NoteMetadata, ScannedNote,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRecord {
+    pub line: usize,
+    pub text: String,
+    pub done: bool,
+    pub due: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRecord {
+    pub id: String,
+    pub title: String,
+    pub preview: String,
+    pub modified: i64,
+    #[serde(default)]
+    pub created: i64, // as for NoteMetadata
+    pub size: u64,
+    pub words: usize,
+    pub tags: Vec<String>,
+    pub links: Vec<String>, // resolved note IDs
+    #[serde(default)]
+    pub tasks: Vec<TaskRecord>, // left out for locked notes
+    #[serde(default)]
+    pub language: Option<String>, // left out for locked notes
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRecord {
+    pub tag: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkRecord {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultTotals {
+    pub notes: usize,
+    pub words: usize,
+    pub links: usize,
+    pub tags: usize,
+    pub open_tasks: usize,
+    pub done_tasks: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultMetadata {
+    pub version: u32,
+    pub generated_at: i64,
+    pub notes: Vec<NoteRecord>,
+    pub tags: Vec<TagRecord>,
+    pub links: Vec<LinkRecord>,
+    pub stats: VaultTotals,
+}
+
+/// Build the dump from scanned notes. Blocking.
+pub fn build(notes: &[ScannedNote], locked_policy: &LockedNotePolicy) -> VaultMetadata {
+    let stats = report::analyze(notes);
+    let mut records = Vec::with_capacity(notes.len());
+    let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+    let mut links = Vec::new();
+    let mut totals = VaultTotals::default();
+
+    for (note, note_stats) in notes.iter().zip(&stats) {
+        let (title, preview) = listing_title_and_preview(&note.content, locked_policy);
+        let tasks: Vec<TaskRecord> = if note_exposure(&note.content, locked_policy).locked {
+            Vec::new()
+        } else {
+            tasks::extract_tasks(&note.content)
+                .into_iter()
+                .map(|t| TaskRecord {
+                    line: t.line,
+                    text: t.text,
+                    done: t.done,
+                    due: t.due,
+                })
+                .collect()
+        };
+
+        for tag in &note_stats.tags {
+            *tag_counts.entry(tag.as_str()).or_default() += 1;
+        }
+        links.extend(note_stats.outgoing.iter().map(|to| LinkRecord {
+            from: note.id.clone(),
+            to: to.clone(),
+        }));
+        totals.words += note_stats.words;
+        totals.done_tasks += tasks.iter().filter(|t| t.done).count();
+        totals.open_tasks += tasks.iter().filter(|t| !t.done).count();
+
+        records.push(NoteRecord {
+            id: note.id.clone(),
+            title,
+            preview,
+            modified: note.modified,
+            created: note.created,
+            size: note.size,
+            words: note_stats.words,
+            tags: note_stats.tags.clone(),
+            links: note_stats.outgoing.clone(),
+            tasks,
+            language: listing_language(&note.content, locked_policy),
+        });
+    }
+
+    let mut tags: Vec<TagRecord> = tag_counts
+        .into_iter()
+        .map(|(tag, count)| TagRecord {
+            tag: tag.to_string(),
+            count,
+        })
+        .collect();
+    tags.sort_by(|a, b| a.tag.cmp(&b.tag));
+    records.sort_by(|a, b| a.id.cmp(&b.id));
+
+    totals.notes = records.len();
+    totals.links = links.len();
+    totals.tags = tags.len();
+    VaultMetadata {
+        version: VERSION,
+        generated_at: chrono::Utc::now().timestamp(),
+        notes: records,
+        tags,
+        links,
+        stats: totals,
+    }
+}
+
+/// Cache entries for the dumped notes whose files still have the recorded
+/// modification time, marked pinned as `pinned` says. Private notes are
+/// skipped while they are locked. Blocking.
+pub fn seed(
+    notes_root: &Path,
+    metadata: &VaultMetadata,
+    private_folders: Option<&[String]>,
+    pinned: &HashSet<String>,
+) -> Vec<NoteMetadata> {
+    metadata
+        .notes
+        .iter()
+        .filter(|record| {
+            let Ok(path) = abs_path_from_id(notes_root, &record.id) else {
+                return false;
+            };
+            let unchanged = std::fs::metadata(&path)
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .is_some_and(|d| d.as_secs() as i64 == record.modified);
+            unchanged
+                && match private_folders {
+                    Some(private) => std::fs::read_to_string(&path)
+                        .is_ok_and(|content| !is_private_note(&record.id, &content, private)),
+                    None => true,
+                }
+        })
+        .map(|record| NoteMetadata {
+            id: record.id.clone(),
+            title: record.title.clone(),
+            preview: record.preview.clone(),
+            modified: record.modified,
+            created: match record.created {
+                0 => record.modified, // dumps from before creation times were recorded
+                created => created,
+            },
+            tags: record.tags.clone(),
+            language: record.language.clone(),
+            pinned: pinned.contains(&record.id),
+        })
+        .collect()
+}
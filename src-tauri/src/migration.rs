@@ -0,0 +1,217 @@
+//! Finding notes from other apps to bring into the vault.
+//!
+//! `detect_importable_sources` looks where other apps keep their notes or
+//! where their exports usually land, and lists what it finds with a note
+//! count and the way to import it:
+//!
+//! - Obsidian vaults, from Obsidian's own list of vaults (`obsidian.json` in
+//!   the OS config folder); imported as a folder by `import_markdown_files`.
+//! - Apple Notes exports: folders named like one ("Apple Notes", "Notes
+//!   Export") on the desktop and in the documents and downloads folders,
+//!   imported the same way. The Apple Notes library itself is listed, uncounted,
+//!   so the user can be told to export it first.
+//! - Evernote `.enex` files in those folders, for `import_enex`.
+//! - Joplin `.jex` files and RAW export folders there, for `import_joplin`,
+//!   and Joplin's profile (`~/.config/joplin-desktop`), whose notes live in a
+//!   database only Joplin reads; listed, uncounted, with a hint to export.
+//!
+//! Folders are searched two levels deep; nothing is read beyond what counting
+//! needs.
+
+use crate::joplin;
+use serde::Serialize;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const SEARCH_DEPTH: usize = 2;
+const APPLE_NOTES_EXPORT_NAMES: &[&str] = &["apple notes", "notes export", "icloud notes"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceApp {
+    Obsidian,
+    AppleNotes,
+    Evernote,
+    Joplin,
+}
+
+/// How a candidate is imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Importer {
+    MarkdownFiles, // import_markdown_files with the folder
+    Enex,          // import_enex with the file
+    Joplin,        // import_joplin with the file or folder
+    ExportFirst,   // the app has to export its notes first; see the hint
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportableSource {
+    pub app: SourceApp,
+    pub name: String,
+    pub path: String,
+    pub notes: Option<usize>, // None when only the app can count them
+    pub importer: Importer,
+    pub hint: Option<String>,
+}
+
+fn candidate(
+    app: SourceApp,
+    path: &Path,
+    notes: Option<usize>,
+    importer: Importer,
+) -> ImportableSource {
+    ImportableSource {
+        app,
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned()),
+        path: path.to_string_lossy().into_owned(),
+        notes,
+        importer,
+        hint: None,
+    }
+}
+
+/// Markdown files under `dir`, hidden folders aside.
+fn count_markdown(dir: &Path) -> usize {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|e| e.file_type().is_file() && crate::is_markdown_extension(e.path()))
+        .count()
+}
+
+/// `<note>` elements in an ENEX file, read a line at a time.
+fn count_enex_notes(path: &Path) -> Option<usize> {
+    let file = std::fs::File::open(path).ok()?;
+    let count = std::io::BufReader::new(file)
+        .split(b'\n')
+        .map_while(Result::ok)
+        .map(|line| {
+            line.windows(b"<note>".len())
+                .filter(|w| *w == b"<note>")
+                .count()
+        })
+        .sum();
+    Some(count)
+}
+
+/// Vault folders listed in Obsidian's `obsidian.json`.
+fn obsidian_vaults(config_dir: &Path) -> Vec<PathBuf> {
+    let Ok(content) = std::fs::read_to_string(config_dir.join("obsidian").join("obsidian.json"))
+    else {
+        return Vec::new();
+    };
+    let Ok(config) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return Vec::new();
+    };
+    let Some(vaults) = config.get("vaults").and_then(|v| v.as_object()) else {
+        return Vec::new();
+    };
+    vaults
+        .values()
+        .filter_map(|vault| vault.get("path")?.as_str())
+        .map(PathBuf::from)
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+/// Look for importable notes. `notes_root` (the open vault) is never offered.
+/// Blocking.
+pub fn detect(app: &AppHandle, notes_root: Option<&Path>) -> Vec<ImportableSource> {
+    let paths = app.path();
+    let home = paths.home_dir().ok();
+    let in_vault = |path: &Path| {
+        notes_root.is_some_and(|root| path.starts_with(root) || root.starts_with(path))
+    };
+    let mut found = Vec::new();
+
+    if let Ok(config_dir) = paths.config_dir() {
+        for vault in obsidian_vaults(&config_dir) {
+            if !in_vault(&vault) {
+                let notes = count_markdown(&vault);
+                found.push(candidate(
+                    SourceApp::Obsidian,
+                    &vault,
+                    Some(notes),
+                    Importer::MarkdownFiles,
+                ));
+            }
+        }
+    }
+
+    let search_dirs: Vec<PathBuf> = [
+        paths.desktop_dir(),
+        paths.document_dir(),
+        paths.download_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    for dir in &search_dirs {
+        let entries = walkdir::WalkDir::new(dir)
+            .max_depth(SEARCH_DEPTH)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .flatten();
+        for entry in entries {
+            let path = entry.path();
+            if in_vault(path) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if entry.file_type().is_file() && name.ends_with(".enex") {
+                let notes = count_enex_notes(path);
+                found.push(candidate(SourceApp::Evernote, path, notes, Importer::Enex));
+            } else if (entry.file_type().is_file() && name.ends_with(".jex"))
+                || (entry.file_type().is_dir() && joplin::is_raw_export(path))
+            {
+                let notes = joplin::count_notes(path);
+                found.push(candidate(SourceApp::Joplin, path, notes, Importer::Joplin));
+            } else if entry.file_type().is_dir()
+                && APPLE_NOTES_EXPORT_NAMES.iter().any(|n| name.contains(n))
+            {
+                let notes = count_markdown(path);
+                if notes > 0 {
+                    found.push(candidate(
+                        SourceApp::AppleNotes,
+                        path,
+                        Some(notes),
+                        Importer::MarkdownFiles,
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(home) = &home {
+        let library = home.join("Library/Group Containers/group.com.apple.notes");
+        if library.join("NoteStore.sqlite").is_file() {
+            let mut source =
+                candidate(SourceApp::AppleNotes, &library, None, Importer::ExportFirst);
+            source.name = "Apple Notes".to_string();
+            source.hint = Some(
+                "Export your notes from Apple Notes as markdown, then import the export"
+                    .to_string(),
+            );
+            found.push(source);
+        }
+
+        let joplin = home.join(".config").join("joplin-desktop");
+        if joplin.join("database.sqlite").is_file() {
+            let mut source = candidate(SourceApp::Joplin, &joplin, None, Importer::ExportFirst);
+            source.name = "Joplin".to_string();
+            source.hint = Some(
+                "In Joplin, choose File > Export all > JEX, then import the export".to_string(),
+            );
+            found.push(source);
+        }
+    }
+
+    found
+}
@@ -0,0 +1,83 @@
+//! Privacy passphrase hashes.
+//!
+//! A passphrase is stored as `pbkdf2-sha256$<rounds>$<salt>$<key>`: PBKDF2
+//! with HMAC-SHA256 over a random salt, both hex. Hashes from earlier
+//! versions, `<salt>$<sha256>` (one salted SHA-256), still verify so an
+//! existing passphrase keeps working, and are replaced on the next unlock.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+const SCHEME: &str = "pbkdf2-sha256";
+const ROUNDS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// PBKDF2-HMAC-SHA256 for a single 32-byte block.
+fn pbkdf2(passphrase: &[u8], salt: &[u8], rounds: u32) -> [u8; 32] {
+    let prf = Hmac::<Sha256>::new_from_slice(passphrase).expect("HMAC takes keys of any length");
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut block: [u8; 32] = mac.finalize().into_bytes().into();
+    let mut key = block;
+    for _ in 1..rounds {
+        let mut mac = prf.clone();
+        mac.update(&block);
+        block = mac.finalize().into_bytes().into();
+        for (k, b) in key.iter_mut().zip(block) {
+            *k ^= b;
+        }
+    }
+    key
+}
+
+pub fn hash(passphrase: &str) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = pbkdf2(passphrase.as_bytes(), &salt, ROUNDS);
+    format!("{}${}${}${}", SCHEME, ROUNDS, hex(&salt), hex(&key))
+}
+
+pub fn verify(passphrase: &str, stored: &str) -> bool {
+    let parts: Vec<&str> = stored.split('$').collect();
+    match parts.as_slice() {
+        [SCHEME, rounds, salt, key] => {
+            let (Ok(rounds), Some(salt), Some(key)) =
+                (rounds.parse::<u32>(), unhex(salt), unhex(key))
+            else {
+                return false;
+            };
+            rounds > 0 && bool::from(pbkdf2(passphrase.as_bytes(), &salt, rounds)[..].ct_eq(&key))
+        }
+        [salt, digest] => {
+            let expected = hex(&Sha256::digest(
+                format!("{}:{}", salt, passphrase).as_bytes(),
+            ));
+            bool::from(expected.as_bytes().ct_eq(digest.as_bytes()))
+        }
+        _ => false,
+    }
+}
+
+/// Whether `stored` is a hash from before PBKDF2, to replace once the
+/// passphrase is known.
+pub fn needs_rehash(stored: &str) -> bool {
+    !stored.starts_with(&format!("{}$", SCHEME))
+}
@@ -0,0 +1,87 @@
+//! Folder and tag policies that hold notes back from leaving the app.
+//!
+//! The `notePolicies` setting lists targets and what they are never part of,
+//! e.g. `{ "target": "inbox/", "never": ["export"] }` or `{ "target":
+//! "#private", "never": ["sync", "index"] }`. A folder covers the notes
+//! beneath it, and for sync every other file there too; a tag matches
+//! frontmatter and inline tags.
+//! The backend enforces them itself: `export` in export profiles, Anki decks
+//! and share links, `sync` in git commits, `index` in the search index.
+
+use crate::tags;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Restriction {
+    Export,
+    Sync,
+    Index,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotePolicy {
+    pub target: String, // a folder ("inbox/") or a tag ("#private")
+    #[serde(default)]
+    pub never: Vec<Restriction>,
+}
+
+impl NotePolicy {
+    fn tag(&self) -> Option<String> {
+        let tag = self.target.trim().strip_prefix('#')?;
+        (!tag.is_empty()).then(|| tags::normalize(tag))
+    }
+
+    fn folder(&self) -> Option<&str> {
+        let target = self.target.trim();
+        let folder = target.trim_matches('/');
+        (!target.starts_with('#') && !folder.is_empty()).then_some(folder)
+    }
+}
+
+fn restricting(
+    policies: &[NotePolicy],
+    restriction: Restriction,
+) -> impl Iterator<Item = &NotePolicy> {
+    policies
+        .iter()
+        .filter(move |policy| policy.never.contains(&restriction))
+}
+
+/// Folders (vault-relative, no slashes at the ends) `restriction` applies to.
+pub fn folders(policies: &[NotePolicy], restriction: Restriction) -> Vec<&str> {
+    restricting(policies, restriction)
+        .filter_map(NotePolicy::folder)
+        .collect()
+}
+
+/// Whether `restriction` applies to any tag, so notes have to be read to
+/// tell.
+pub fn has_tags(policies: &[NotePolicy], restriction: Restriction) -> bool {
+    restricting(policies, restriction).any(|policy| policy.tag().is_some())
+}
+
+/// Whether the vault-relative `path` is under a folder `restriction` applies
+/// to.
+pub fn forbids_path(policies: &[NotePolicy], restriction: Restriction, path: &str) -> bool {
+    folders(policies, restriction)
+        .iter()
+        .any(|folder| path.starts_with(&format!("{}/", folder)))
+}
+
+/// Whether `restriction` applies to note `id` with `content`, by folder or
+/// tag.
+pub fn forbids(policies: &[NotePolicy], restriction: Restriction, id: &str, content: &str) -> bool {
+    if forbids_path(policies, restriction, id) {
+        return true;
+    }
+    let wanted: Vec<String> = restricting(policies, restriction)
+        .filter_map(NotePolicy::tag)
+        .collect();
+    if wanted.is_empty() {
+        return false;
+    }
+    let note_tags = tags::extract_tags(content);
+    wanted.iter().any(|tag| note_tags.contains(tag))
+}
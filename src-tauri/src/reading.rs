@@ -0,0 +1,260 @@
+//! Reading list from book notes.
+//!
+//! Book notes are the ones the `book` content schema claims: `type: book` or
+//! a `#book`, `#books` or `#reading` tag. Their frontmatter drives the list:
+//! `status` (to-read, reading, finished or abandoned, plus a few common
+//! spellings), `progress` as a page (`120`), a page of a total (`120/350`)
+//! or a percentage (`35%`), and `pages`, `author`, `started` and `finished`.
+//! A book with progress but no status is being read. Updating progress moves
+//! a book to reading, or to finished on the last page or at 100%, and stamps
+//! `started` and `finished` with today's date when they are unset.
+
+use crate::frontmatter::{self, Frontmatter, Value};
+use crate::{note_exposure, schemas, LockedNotePolicy, ScannedNote};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadingStatus {
+    ToRead,
+    Reading,
+    Finished,
+    Abandoned,
+}
+
+impl ReadingStatus {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase().replace(['_', ' '], "-");
+        Some(match text.as_str() {
+            "to-read" | "want-to-read" | "tbr" | "queued" | "unread" => Self::ToRead,
+            "reading" | "currently-reading" | "in-progress" | "started" => Self::Reading,
+            "finished" | "read" | "done" | "completed" => Self::Finished,
+            "abandoned" | "dnf" | "dropped" => Self::Abandoned,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::ToRead => "to-read",
+            Self::Reading => "reading",
+            Self::Finished => "finished",
+            Self::Abandoned => "abandoned",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingItem {
+    pub id: String,
+    pub title: String,
+    pub author: Option<String>,
+    pub status: ReadingStatus,
+    pub page: Option<u32>,
+    pub pages: Option<u32>,
+    pub percent: Option<f64>, // 0-100; from the page when progress is a page of known pages
+    pub started: Option<String>,
+    pub finished: Option<String>,
+    pub modified: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingList {
+    pub reading: Vec<ReadingItem>,  // most recently updated first
+    pub to_read: Vec<ReadingItem>,  // by title
+    pub finished: Vec<ReadingItem>, // most recently finished first
+    pub abandoned: Vec<ReadingItem>,
+    pub finished_this_year: usize,
+}
+
+struct Progress {
+    page: Option<u32>,
+    pages: Option<u32>,
+    percent: Option<f64>,
+}
+
+/// `120`, `p. 120`, `120/350`, `120 of 350` or `35%`.
+fn parse_progress(text: &str) -> Option<Progress> {
+    let text = text.trim();
+    if let Some(percent) = text.strip_suffix('%') {
+        let percent: f64 = percent.trim().replace(',', ".").parse().ok()?;
+        return (0.0..=100.0).contains(&percent).then_some(Progress {
+            page: None,
+            pages: None,
+            percent: Some(percent),
+        });
+    }
+    let text = text
+        .trim_start_matches(|c: char| c.is_alphabetic() || c == '.')
+        .replace(" of ", "/");
+    let (page, pages) = match text.split_once('/') {
+        Some((page, pages)) => (page, Some(pages.trim().parse().ok()?)),
+        None => (text.as_str(), None),
+    };
+    Some(Progress {
+        page: Some(page.trim().parse().ok()?),
+        pages,
+        percent: None,
+    })
+}
+
+fn page_count(fm: &Frontmatter) -> Option<u32> {
+    fm.get("pages").and_then(|p| p.trim().parse().ok())
+}
+
+/// A book note as it shows on the reading list.
+pub fn item(id: &str, title: &str, content: &str, modified: i64) -> ReadingItem {
+    let fm = Frontmatter::parse(content);
+    let progress = fm.get("progress").and_then(parse_progress);
+    let page = progress.as_ref().and_then(|p| p.page);
+    let pages = progress
+        .as_ref()
+        .and_then(|p| p.pages)
+        .or_else(|| page_count(&fm));
+    let status = fm
+        .get("status")
+        .and_then(ReadingStatus::parse)
+        .unwrap_or(if progress.is_some() {
+            ReadingStatus::Reading
+        } else {
+            ReadingStatus::ToRead
+        });
+    let percent = match (
+        status,
+        progress.as_ref().and_then(|p| p.percent),
+        page,
+        pages,
+    ) {
+        (ReadingStatus::Finished, ..) => Some(100.0),
+        (_, Some(percent), ..) => Some(percent),
+        (_, None, Some(page), Some(pages)) if pages > 0 => {
+            Some((page as f64 * 100.0 / pages as f64).min(100.0))
+        }
+        _ => None,
+    };
+    ReadingItem {
+        id: id.to_string(),
+        title: fm
+            .get("title")
+            .map(String::from)
+            .unwrap_or_else(|| title.to_string()),
+        author: fm
+            .get("author")
+            .or_else(|| fm.get("authors"))
+            .map(String::from),
+        status,
+        page,
+        pages,
+        percent,
+        started: fm.get("started").map(String::from),
+        finished: fm.get("finished").map(String::from),
+        modified,
+    }
+}
+
+fn finished_in(item: &ReadingItem, year: i32) -> bool {
+    match item.finished.as_deref() {
+        Some(date) => date.get(..4).and_then(|y| y.parse().ok()) == Some(year),
+        None => chrono::DateTime::from_timestamp(item.modified, 0).map(|d| d.year()) == Some(year),
+    }
+}
+
+/// Gather the book notes into a reading list. Locked notes that are kept out
+/// of search are left out. Blocking.
+pub fn build(
+    notes: &[ScannedNote],
+    locked_policy: &LockedNotePolicy,
+    today: NaiveDate,
+) -> ReadingList {
+    let mut list = ReadingList {
+        reading: Vec::new(),
+        to_read: Vec::new(),
+        finished: Vec::new(),
+        abandoned: Vec::new(),
+        finished_this_year: 0,
+    };
+    for note in notes {
+        if schemas::declared(&note.content) != Some("book") {
+            continue;
+        }
+        let exposure = note_exposure(&note.content, locked_policy);
+        if exposure.locked && !exposure.index_content {
+            continue;
+        }
+        let item = item(&note.id, &note.title, &note.content, note.modified);
+        match item.status {
+            ReadingStatus::Reading => list.reading.push(item),
+            ReadingStatus::ToRead => list.to_read.push(item),
+            ReadingStatus::Finished => list.finished.push(item),
+            ReadingStatus::Abandoned => list.abandoned.push(item),
+        }
+    }
+    list.reading.sort_by_key(|item| Reverse(item.modified));
+    list.to_read
+        .sort_by_cached_key(|item| item.title.to_lowercase());
+    list.finished.sort_by(|a, b| {
+        b.finished
+            .cmp(&a.finished)
+            .then(b.modified.cmp(&a.modified))
+    });
+    list.abandoned.sort_by_key(|item| Reverse(item.modified));
+    list.finished_this_year = list
+        .finished
+        .iter()
+        .filter(|item| finished_in(item, today.year()))
+        .count();
+    list
+}
+
+/// Set a book's status, stamping `started` when it moves to reading and
+/// `finished` when it is finished, unless they are set.
+pub fn set_status(content: &str, status: ReadingStatus, today: NaiveDate) -> String {
+    let fm = Frontmatter::parse(content);
+    let today = today.format("%Y-%m-%d").to_string();
+    let mut content = frontmatter::set(
+        content,
+        "status",
+        Value::Scalar(status.as_str().to_string()),
+    );
+    if status == ReadingStatus::Reading && fm.get("started").is_none() {
+        content = frontmatter::set(&content, "started", Value::Scalar(today.clone()));
+    }
+    if status == ReadingStatus::Finished && fm.get("finished").is_none() {
+        content = frontmatter::set(&content, "finished", Value::Scalar(today));
+    }
+    content
+}
+
+/// Set a book's progress and move it to reading, or to finished when it is
+/// read to the end.
+pub fn update_progress(content: &str, progress: &str, today: NaiveDate) -> Result<String, String> {
+    let progress = progress.trim();
+    let parsed = parse_progress(progress).ok_or_else(|| {
+        format!(
+            "Progress \"{}\" is not a page, a page of a total or a percentage",
+            progress
+        )
+    })?;
+    let pages = parsed
+        .pages
+        .or_else(|| page_count(&Frontmatter::parse(content)));
+    let to_the_end = match (parsed.page, pages, parsed.percent) {
+        (Some(page), Some(pages), _) if page > pages => {
+            return Err(format!("Page {} is past the book's {} pages", page, pages));
+        }
+        (Some(page), Some(pages), _) => page == pages,
+        (_, _, Some(percent)) => percent >= 100.0,
+        _ => false,
+    };
+    let content = frontmatter::set(content, "progress", Value::Scalar(progress.to_string()));
+    let status = if to_the_end {
+        ReadingStatus::Finished
+    } else {
+        ReadingStatus::Reading
+    };
+    Ok(set_status(&content, status, today))
+}
@@ -0,0 +1,124 @@
+//! How much of each note is read for the note list and the search index.
+//!
+//! A vault holding giant generated markdown files (logs, data dumps) would
+//! otherwise read every one of them whole each time the list is built or the
+//! index rebuilt. The `readLimits` setting caps the bytes read from the start
+//! of each note: `listingBytes` for the title, preview, tags and language in
+//! the note list (default 1 MB), and `indexBytes` for what enters the search
+//! index (default 10 MB). Either can be `"full"` to read whole files. Opening,
+//! saving and exporting a note always use all of it.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+const KB: usize = 1024;
+const MB: usize = 1024 * KB;
+const DEFAULT_LISTING_BYTES: usize = MB;
+const DEFAULT_INDEX_BYTES: usize = 10 * MB;
+const MIN_BYTES: usize = 4 * KB; // enough for the title and preview
+
+/// A cap in bytes, or `"full"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawLimit", into = "RawLimit")]
+pub enum ReadLimit {
+    Bytes(usize),
+    Full,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawLimit {
+    Bytes(usize),
+    Word(String),
+}
+
+impl TryFrom<RawLimit> for ReadLimit {
+    type Error = String;
+
+    fn try_from(raw: RawLimit) -> Result<Self, Self::Error> {
+        match raw {
+            RawLimit::Bytes(bytes) => Ok(ReadLimit::Bytes(bytes)),
+            RawLimit::Word(word) if word.eq_ignore_ascii_case("full") => Ok(ReadLimit::Full),
+            RawLimit::Word(word) => Err(format!(
+                "Invalid read limit \"{}\": expected a number of bytes or \"full\"",
+                word
+            )),
+        }
+    }
+}
+
+impl From<ReadLimit> for RawLimit {
+    fn from(limit: ReadLimit) -> Self {
+        match limit {
+            ReadLimit::Bytes(bytes) => RawLimit::Bytes(bytes),
+            ReadLimit::Full => RawLimit::Word("full".to_string()),
+        }
+    }
+}
+
+impl ReadLimit {
+    fn max_bytes(self) -> Option<usize> {
+        match self {
+            ReadLimit::Bytes(bytes) => Some(bytes.max(MIN_BYTES)),
+            ReadLimit::Full => None,
+        }
+    }
+
+    /// `content` cut to the limit, on a character boundary.
+    pub fn truncate(self, content: &str) -> &str {
+        match self.max_bytes() {
+            Some(max) if content.len() > max => {
+                let mut end = max;
+                while !content.is_char_boundary(end) {
+                    end -= 1;
+                }
+                &content[..end]
+            }
+            _ => content,
+        }
+    }
+
+    /// Read the start of the file at `path`, up to the limit. A character cut
+    /// off by the limit is dropped; invalid UTF-8 anywhere else fails like
+    /// `read_to_string`.
+    pub fn read(self, path: &Path) -> std::io::Result<String> {
+        let Some(max) = self.max_bytes() else {
+            return std::fs::read_to_string(path);
+        };
+        let mut bytes = Vec::new();
+        std::fs::File::open(path)?
+            .take(max as u64)
+            .read_to_end(&mut bytes)?;
+        let invalid = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        match String::from_utf8(bytes) {
+            Ok(content) => Ok(content),
+            Err(e) if e.utf8_error().error_len().is_none() => {
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                bytes.truncate(valid);
+                String::from_utf8(bytes).map_err(invalid)
+            }
+            Err(e) => Err(invalid(e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadLimits {
+    pub listing_bytes: Option<ReadLimit>, // default 1 MB
+    pub index_bytes: Option<ReadLimit>,   // default 10 MB
+}
+
+impl ReadLimits {
+    pub fn listing(&self) -> ReadLimit {
+        self.listing_bytes
+            .unwrap_or(ReadLimit::Bytes(DEFAULT_LISTING_BYTES))
+    }
+
+    pub fn index(&self) -> ReadLimit {
+        self.index_bytes
+            .unwrap_or(ReadLimit::Bytes(DEFAULT_INDEX_BYTES))
+    }
+}
@@ -0,0 +1,277 @@
+//! Crash recovery: autosave drafts and interrupted writes.
+//!
+//! While an autosave is pending, its content is also journaled (rate-limited)
+//! to `.scratch/drafts/`, and removed once written. Note and settings writes
+//! go through a synced temp file that is renamed into place. After a crash,
+//! leftover drafts that differ from the note on disk are offered back to the
+//! user, and leftover temp files are cleaned up, or promoted when the note
+//! itself is missing.
+
+use crate::{abs_path_from_id, extract_title};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+const DRAFTS_DIR: &str = "drafts";
+const TEMP_SUFFIX: &str = ".scratch-tmp";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub id: String,
+    pub content: String,
+    pub saved_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftInfo {
+    pub id: String,
+    pub title: String,
+    pub saved_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryReport {
+    pub drafts: Vec<DraftInfo>,
+    pub temp_files_removed: usize,
+    pub temp_files_restored: Vec<String>, // vault-relative paths of notes put back from temp files
+}
+
+fn drafts_dir(notes_root: &Path) -> PathBuf {
+    notes_root.join(".scratch").join(DRAFTS_DIR)
+}
+
+fn draft_path(notes_root: &Path, id: &str) -> PathBuf {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(id.as_bytes());
+    let name: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    drafts_dir(notes_root).join(format!("{}.json", name))
+}
+
+pub fn write_draft(notes_root: &Path, id: &str, content: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(drafts_dir(notes_root))?;
+    let draft = Draft {
+        id: id.to_string(),
+        content: content.to_string(),
+        saved_at: chrono::Utc::now().timestamp(),
+    };
+    std::fs::write(draft_path(notes_root, id), serde_json::to_string(&draft)?)
+}
+
+pub fn read_draft(notes_root: &Path, id: &str) -> Option<Draft> {
+    let content = std::fs::read_to_string(draft_path(notes_root, id)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn remove_draft(notes_root: &Path, id: &str) {
+    let _ = std::fs::remove_file(draft_path(notes_root, id));
+}
+
+/// Number of drafts on disk and the bytes they take.
+pub fn draft_usage(notes_root: &Path) -> (usize, u64) {
+    let Ok(entries) = std::fs::read_dir(drafts_dir(notes_root)) else {
+        return (0, 0);
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|m| m.is_file())
+        .fold((0, 0), |(count, bytes), m| (count + 1, bytes + m.len()))
+}
+
+/// Drafts waiting to be restored or discarded, newest first.
+pub fn list_drafts(notes_root: &Path) -> Vec<DraftInfo> {
+    let Ok(entries) = std::fs::read_dir(drafts_dir(notes_root)) else {
+        return Vec::new();
+    };
+    let mut drafts: Vec<DraftInfo> = entries
+        .flatten()
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str::<Draft>(&content).ok())
+        .map(|draft| DraftInfo {
+            title: extract_title(&draft.content),
+            id: draft.id,
+            saved_at: draft.saved_at,
+        })
+        .collect();
+    drafts.sort_by_key(|d| Reverse(d.saved_at));
+    drafts
+}
+
+/// Step of an atomic write that failed. Whichever it was, the target file
+/// is left as it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteStep {
+    Write,
+    Sync,
+    Rename,
+}
+
+#[derive(Debug)]
+pub struct WriteError {
+    pub step: WriteStep,
+    pub path: PathBuf,
+    pub source: std::io::Error,
+}
+
+impl WriteError {
+    pub fn is_disk_full(&self) -> bool {
+        crate::error::is_disk_full(&self.source)
+    }
+}
+
+impl std::fmt::Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_else(|| self.path.to_string_lossy());
+        let reason = if self.is_disk_full() {
+            "the disk is full".to_string()
+        } else {
+            self.source.to_string()
+        };
+        match self.step {
+            WriteStep::Write => write!(f, "Couldn't write {}: {}", name, reason)?,
+            WriteStep::Sync => write!(f, "Couldn't flush {} to disk: {}", name, reason)?,
+            WriteStep::Rename => write!(f, "Couldn't replace {}: {}", name, reason)?,
+        }
+        write!(f, ". The file on disk is unchanged.")
+    }
+}
+
+impl std::error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(TEMP_SUFFIX);
+    PathBuf::from(temp)
+}
+
+/// Flush a rename to disk by syncing its directory. Best effort: not every
+/// platform lets a directory be opened for this.
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::File::open(dir).and_then(|d| d.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Write a file by writing and syncing a sibling temp file and renaming it
+/// into place, so a crash or a full disk never leaves a half-written note.
+pub async fn write_atomic(path: &Path, content: &str) -> Result<(), WriteError> {
+    use tokio::io::AsyncWriteExt;
+
+    let temp = temp_path(path);
+    let fail = |step, source| {
+        let path = path.to_path_buf();
+        let temp = temp.clone();
+        async move {
+            let _ = tokio::fs::remove_file(&temp).await;
+            WriteError { step, path, source }
+        }
+    };
+    let mut file = match tokio::fs::File::create(&temp).await {
+        Ok(file) => file,
+        Err(e) => return Err(fail(WriteStep::Write, e).await),
+    };
+    if let Err(e) = file.write_all(content.as_bytes()).await {
+        return Err(fail(WriteStep::Write, e).await);
+    }
+    if let Err(e) = file.sync_all().await {
+        return Err(fail(WriteStep::Sync, e).await);
+    }
+    drop(file);
+    if let Err(e) = tokio::fs::rename(&temp, path).await {
+        return Err(fail(WriteStep::Rename, e).await);
+    }
+    sync_dir(path);
+    Ok(())
+}
+
+/// Blocking `write_atomic`, for settings and other files written off the
+/// async runtime.
+pub fn write_atomic_sync(path: &Path, content: &[u8]) -> Result<(), WriteError> {
+    use std::io::Write;
+
+    let temp = temp_path(path);
+    let fail = |step, source| {
+        let _ = std::fs::remove_file(&temp);
+        WriteError {
+            step,
+            path: path.to_path_buf(),
+            source,
+        }
+    };
+    let mut file = std::fs::File::create(&temp).map_err(|e| fail(WriteStep::Write, e))?;
+    file.write_all(content).map_err(|e| fail(WriteStep::Write, e))?;
+    file.sync_all().map_err(|e| fail(WriteStep::Sync, e))?;
+    drop(file);
+    std::fs::rename(&temp, path).map_err(|e| fail(WriteStep::Rename, e))?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// Clean up after a crash. Blocking.
+pub fn recover(notes_root: &Path) -> RecoveryReport {
+    use walkdir::WalkDir;
+
+    let mut report = RecoveryReport::default();
+    for entry in WalkDir::new(notes_root)
+        .max_depth(10)
+        .into_iter()
+        .filter_entry(|e| {
+            !(e.file_type().is_dir() && matches!(e.file_name().to_str(), Some(".git" | ".trash")))
+        })
+        .flatten()
+    {
+        let path = entry.path();
+        let Some(target) = path
+            .to_str()
+            .and_then(|p| p.strip_suffix(TEMP_SUFFIX))
+            .map(PathBuf::from)
+        else {
+            continue;
+        };
+        if target.exists() {
+            if std::fs::remove_file(path).is_ok() {
+                report.temp_files_removed += 1;
+            }
+        } else if std::fs::rename(path, &target).is_ok() {
+            if let Ok(rel) = target.strip_prefix(notes_root) {
+                report
+                    .temp_files_restored
+                    .push(rel.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/"));
+            }
+        }
+    }
+
+    // Drafts that match the note on disk were written after all
+    if let Ok(entries) = std::fs::read_dir(drafts_dir(notes_root)) {
+        for entry in entries.flatten() {
+            let Some(draft) = std::fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|content| serde_json::from_str::<Draft>(&content).ok())
+            else {
+                continue;
+            };
+            let on_disk = abs_path_from_id(notes_root, &draft.id)
+                .ok()
+                .and_then(|path| std::fs::read_to_string(path).ok());
+            if on_disk.as_deref() == Some(draft.content.as_str()) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+    report.drafts = list_drafts(notes_root);
+    report
+}
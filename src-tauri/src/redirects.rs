@@ -0,0 +1,119 @@
+//! Tombstones for renamed, moved and redirected notes.
+//!
+//! When a note's ID changes (a title rename, a move, a folder rename or move,
+//! or a delete that redirects its links), the old ID is kept in
+//! `.scratch/redirects.json` pointing at the new one. Folder moves are kept as
+//! a single prefix entry. `resolve` follows these so stale wikilinks, stored
+//! IDs and deep links still find the note.
+
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+const STORE_FILE: &str = "redirects.json";
+const MAX_HOPS: usize = 32;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedirectStore {
+    #[serde(default)]
+    notes: HashMap<String, String>, // old note ID -> new note ID
+    #[serde(default)]
+    folders: HashMap<String, String>, // old folder path -> new folder path
+}
+
+impl RedirectStore {
+    fn load(notes_root: &Path) -> Self {
+        store::load(notes_root, STORE_FILE)
+    }
+
+    fn save(&self, notes_root: &Path) -> Result<()> {
+        store::save(notes_root, STORE_FILE, self)
+    }
+
+    /// One step from `id`: an exact note entry, else the longest folder prefix.
+    fn step(&self, id: &str) -> Option<String> {
+        if let Some(to) = self.notes.get(id) {
+            return Some(to.clone());
+        }
+        self.folders
+            .iter()
+            .filter(|(from, _)| id.starts_with(&format!("{}/", from)))
+            .max_by_key(|(from, _)| from.len())
+            .map(|(from, to)| format!("{}{}", to, &id[from.len()..]))
+    }
+
+    /// An exact key, or one matching case-insensitively by ID or file name,
+    /// since wikilinks are usually written by title.
+    fn find_key(&self, target: &str) -> Option<String> {
+        if self.notes.contains_key(target) {
+            return Some(target.to_string());
+        }
+        let target = target.to_lowercase();
+        let mut keys: Vec<&String> = self.notes.keys().collect();
+        keys.sort();
+        keys.into_iter()
+            .find(|key| {
+                let key = key.to_lowercase();
+                key == target || key.rsplit('/').next() == Some(target.as_str())
+            })
+            .cloned()
+    }
+}
+
+fn exists(notes_root: &Path, id: &str) -> bool {
+    crate::abs_path_from_id(notes_root, id).is_ok_and(|path| path.is_file())
+}
+
+/// Record that note `old` is now `new`.
+pub fn record_note(notes_root: &Path, old: &str, new: &str) -> Result<()> {
+    if old == new {
+        return Ok(());
+    }
+    let mut store = RedirectStore::load(notes_root);
+    // A live note is never a tombstone
+    store.notes.remove(new);
+    for to in store.notes.values_mut() {
+        if to == old {
+            *to = new.to_string();
+        }
+    }
+    store.notes.insert(old.to_string(), new.to_string());
+    store.save(notes_root)
+}
+
+/// Record that every note under folder `old` is now under `new`.
+pub fn record_folder(notes_root: &Path, old: &str, new: &str) -> Result<()> {
+    let (old, new) = (old.trim_matches('/'), new.trim_matches('/'));
+    if old == new || old.is_empty() {
+        return Ok(());
+    }
+    let mut store = RedirectStore::load(notes_root);
+    store.folders.remove(new);
+    for to in store.folders.values_mut() {
+        if to == old {
+            *to = new.to_string();
+        }
+    }
+    store.folders.insert(old.to_string(), new.to_string());
+    store.save(notes_root)
+}
+
+/// The current ID for `id`: itself if the note exists, else wherever its
+/// tombstones lead, if that note exists. Blocking.
+pub fn resolve(notes_root: &Path, id: &str) -> Option<String> {
+    let id = id.trim().trim_end_matches(".md");
+    if exists(notes_root, id) {
+        return Some(id.to_string());
+    }
+    let store = RedirectStore::load(notes_root);
+    let mut current = store.find_key(id).unwrap_or_else(|| id.to_string());
+    for _ in 0..MAX_HOPS {
+        current = store.step(&current)?;
+        if exists(notes_root, &current) {
+            return Some(current);
+        }
+    }
+    None
+}
@@ -0,0 +1,520 @@
+//! Citations from a BibTeX file or a Zotero export.
+//!
+//! The vault's `references.library` setting names the bibliography: a `.bib`
+//! file (BibTeX or Better BibTeX) or a `.json` CSL JSON export, absolute or
+//! relative to the vault. It is parsed on first use and again whenever the
+//! file changes. `search_references` matches words against each entry's key,
+//! title, authors, year and journal; `insert_citation` renders a key with the
+//! `citationFormat` template (`[@{key}]` by default, Pandoc style); and
+//! `create_literature_note` writes a stub note for a reference into the
+//! `literatureFolder` (default "References"), named after its key.
+
+use crate::frontmatter::{self, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+const DEFAULT_CITATION_FORMAT: &str = "[@{key}]";
+const DEFAULT_LITERATURE_FOLDER: &str = "References";
+const SEARCH_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceSettings {
+    pub library: Option<String>, // .bib or CSL .json file; absolute or vault-relative
+    pub citation_format: Option<String>, // `{key}` is replaced; default "[@{key}]"
+    pub literature_folder: Option<String>, // default "References"
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reference {
+    pub key: String,
+    pub kind: String, // entry type: article, book, ...
+    pub title: Option<String>,
+    pub authors: Vec<String>, // "First Last"
+    pub year: Option<String>,
+    pub container: Option<String>, // journal, book or proceedings
+    pub doi: Option<String>,
+    pub url: Option<String>,
+    pub literature_note: Option<String>, // ID of the note for it, if there is one
+}
+
+impl ReferenceSettings {
+    /// The library file, resolved against the vault.
+    pub fn library_path(&self, notes_root: &Path) -> Result<PathBuf, String> {
+        let library = self
+            .library
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .ok_or("No reference library set")?;
+        Ok(notes_root.join(library))
+    }
+
+    pub fn citation(&self, key: &str) -> String {
+        self.citation_format
+            .as_deref()
+            .filter(|f| f.contains("{key}"))
+            .unwrap_or(DEFAULT_CITATION_FORMAT)
+            .replace("{key}", key)
+    }
+
+    pub fn literature_folder(&self) -> String {
+        self.literature_folder
+            .as_deref()
+            .map(|f| f.trim().trim_matches('/'))
+            .filter(|f| !f.is_empty())
+            .unwrap_or(DEFAULT_LITERATURE_FOLDER)
+            .to_string()
+    }
+}
+
+/// Note ID of the literature note for `key`.
+pub fn literature_note_id(settings: &ReferenceSettings, key: &str) -> String {
+    format!(
+        "{}/@{}",
+        settings.literature_folder(),
+        crate::sanitize_filename(key)
+    )
+}
+
+/// Plain text for a BibTeX value: braces dropped, common accents and escapes
+/// spelled out, whitespace collapsed.
+fn latex_to_text(value: &str) -> String {
+    const ACCENTS: &[(&str, &str)] = &[
+        ("\\\"a", "ä"),
+        ("\\\"o", "ö"),
+        ("\\\"u", "ü"),
+        ("\\\"A", "Ä"),
+        ("\\\"O", "Ö"),
+        ("\\\"U", "Ü"),
+        ("\\'a", "á"),
+        ("\\'e", "é"),
+        ("\\'i", "í"),
+        ("\\'o", "ó"),
+        ("\\'u", "ú"),
+        ("\\'E", "É"),
+        ("\\`a", "à"),
+        ("\\`e", "è"),
+        ("\\^o", "ô"),
+        ("\\^e", "ê"),
+        ("\\~n", "ñ"),
+        ("\\c c", "ç"),
+        ("\\c{c}", "ç"),
+        ("\\ss", "ß"),
+        ("\\o", "ø"),
+        ("\\&", "&"),
+        ("\\%", "%"),
+        ("\\_", "_"),
+        ("\\$", "$"),
+        ("---", "—"),
+        ("--", "–"),
+        ("~", " "),
+    ];
+    let mut text = value.to_string();
+    for (latex, plain) in ACCENTS {
+        text = text.replace(latex, plain);
+    }
+    text.retain(|c| c != '{' && c != '}');
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// "Last, First" or "First Last" as "First Last".
+fn display_name(name: &str) -> String {
+    match name.split_once(',') {
+        Some((last, first)) if !first.trim().is_empty() => {
+            format!("{} {}", first.trim(), last.trim())
+        }
+        _ => name.trim().trim_end_matches(',').to_string(),
+    }
+}
+
+/// BibTeX author lists are joined with " and " at brace depth 0.
+fn split_authors(value: &str) -> Vec<String> {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    let lower = value.to_ascii_lowercase(); // same byte offsets
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ' ' if i >= start && depth == 0 && lower[i..].starts_with(" and ") => {
+                names.push(&value[start..i]);
+                start = i + " and ".len();
+            }
+            _ => {}
+        }
+    }
+    names.push(&value[start..]);
+    names
+        .into_iter()
+        .map(|name| display_name(&latex_to_text(name)))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        self.skip_whitespace();
+        let rest = &self.text[self.pos..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || "{}(),=#\"".contains(c))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        rest[..len].to_string()
+    }
+
+    /// Text up to the brace that closes the one just consumed.
+    fn braced(&mut self) -> String {
+        let start = self.pos;
+        let mut depth = 1usize;
+        for (offset, c) in self.text[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos = start + offset + 1;
+                        return self.text[start..start + offset].to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.pos = self.text.len();
+        self.text[start..].to_string()
+    }
+
+    /// Text up to the closing quote; quotes inside braces don't count.
+    fn quoted(&mut self) -> String {
+        let start = self.pos;
+        let mut depth = 0usize;
+        for (offset, c) in self.text[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                '"' if depth == 0 => {
+                    self.pos = start + offset + 1;
+                    return self.text[start..start + offset].to_string();
+                }
+                _ => {}
+            }
+        }
+        self.pos = self.text.len();
+        self.text[start..].to_string()
+    }
+
+    /// A field value: braced, quoted, a number or a `@string` name, joined
+    /// with `#`.
+    fn value(&mut self, strings: &HashMap<String, String>) -> String {
+        let mut out = String::new();
+        loop {
+            if self.eat('{') {
+                out.push_str(&self.braced());
+            } else if self.eat('"') {
+                out.push_str(&self.quoted());
+            } else {
+                let word = self.ident();
+                match strings.get(&word.to_ascii_lowercase()) {
+                    Some(expanded) => out.push_str(expanded),
+                    None => out.push_str(&word),
+                }
+            }
+            if !self.eat('#') {
+                return out;
+            }
+        }
+    }
+
+    /// `name = value` pairs up to the entry's closing delimiter.
+    fn fields(&mut self, close: char, strings: &HashMap<String, String>) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        loop {
+            if self.eat(close) || self.pos >= self.text.len() {
+                return fields;
+            }
+            if self.eat(',') {
+                continue;
+            }
+            let name = self.ident().to_ascii_lowercase();
+            if name.is_empty() || !self.eat('=') {
+                // Malformed; give up on the rest of this entry
+                self.skip_to(close);
+                return fields;
+            }
+            let value = self.value(strings);
+            fields.push((name, value));
+        }
+    }
+
+    fn skip_to(&mut self, close: char) {
+        match self.text[self.pos..].find(close) {
+            Some(offset) => self.pos += offset + 1,
+            None => self.pos = self.text.len(),
+        }
+    }
+}
+
+/// Entries of a BibTeX file, with `@string` abbreviations expanded.
+fn parse_bibtex(text: &str) -> Vec<Reference> {
+    let mut parser = Parser { text, pos: 0 };
+    let mut strings: HashMap<String, String> = HashMap::new();
+    let mut references = Vec::new();
+    while let Some(at) = parser.text[parser.pos..].find('@') {
+        parser.pos += at + 1;
+        let kind = parser.ident().to_ascii_lowercase();
+        let close = if parser.eat('{') {
+            '}'
+        } else if parser.eat('(') {
+            ')'
+        } else {
+            continue;
+        };
+        match kind.as_str() {
+            "comment" | "preamble" => {
+                if close == '}' {
+                    parser.braced();
+                } else {
+                    parser.skip_to(close);
+                }
+            }
+            "string" => {
+                for (name, value) in parser.fields(close, &strings) {
+                    strings.insert(name, value);
+                }
+            }
+            _ => {
+                let key = parser.ident();
+                if key.is_empty() || !parser.eat(',') {
+                    parser.skip_to(close);
+                    continue;
+                }
+                let fields: HashMap<String, String> =
+                    parser.fields(close, &strings).into_iter().collect();
+                let text_of = |name: &str| {
+                    fields
+                        .get(name)
+                        .map(|v| latex_to_text(v))
+                        .filter(|v| !v.is_empty())
+                };
+                let year = text_of("year")
+                    .or_else(|| text_of("date").map(|date| date.chars().take(4).collect()));
+                references.push(Reference {
+                    key,
+                    kind,
+                    title: text_of("title"),
+                    authors: fields
+                        .get("author")
+                        .or_else(|| fields.get("editor"))
+                        .map(|v| split_authors(v))
+                        .unwrap_or_default(),
+                    year,
+                    container: text_of("journal")
+                        .or_else(|| text_of("journaltitle"))
+                        .or_else(|| text_of("booktitle")),
+                    doi: text_of("doi"),
+                    url: text_of("url"),
+                    literature_note: None,
+                });
+            }
+        }
+    }
+    references
+}
+
+/// Items of a CSL JSON export (Zotero's "CSL JSON" format).
+fn parse_csl_json(text: &str) -> Result<Vec<Reference>, String> {
+    let items: Vec<serde_json::Value> =
+        serde_json::from_str(text).map_err(|e| format!("Invalid CSL JSON: {}", e))?;
+    let string = |item: &serde_json::Value, name: &str| {
+        item.get(name)
+            .and_then(|v| match v {
+                serde_json::Value::String(s) => Some(s.trim().to_string()),
+                serde_json::Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .filter(|s| !s.is_empty())
+    };
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let key = string(item, "citation-key").or_else(|| string(item, "id"))?;
+            let authors = item
+                .get("author")
+                .or_else(|| item.get("editor"))
+                .and_then(|a| a.as_array())
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(|name| {
+                            let given = string(name, "given").unwrap_or_default();
+                            match (string(name, "literal"), string(name, "family")) {
+                                (Some(literal), _) => Some(literal),
+                                (None, Some(family)) => {
+                                    Some(format!("{} {}", given, family).trim().to_string())
+                                }
+                                (None, None) => None,
+                            }
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let year = item
+                .pointer("/issued/date-parts/0/0")
+                .map(|y| y.to_string().trim_matches('"').to_string());
+            Some(Reference {
+                key,
+                kind: string(item, "type").unwrap_or_else(|| "document".to_string()),
+                title: string(item, "title"),
+                authors,
+                year,
+                container: string(item, "container-title"),
+                doi: string(item, "DOI"),
+                url: string(item, "URL"),
+                literature_note: None,
+            })
+        })
+        .collect())
+}
+
+type Library = (PathBuf, SystemTime, Arc<Vec<Reference>>);
+
+fn cache() -> &'static Mutex<Option<Library>> {
+    static CACHE: OnceLock<Mutex<Option<Library>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// The references in the library at `path`, parsed again only when the file
+/// has changed. Blocking.
+pub fn load(path: &Path) -> Result<Arc<Vec<Reference>>, String> {
+    let modified = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    if let Some((cached_path, cached_modified, references)) =
+        &*cache().lock().expect("references cache mutex")
+    {
+        if cached_path == path && *cached_modified == modified {
+            return Ok(Arc::clone(references));
+        }
+    }
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let is_json = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let references = Arc::new(if is_json {
+        parse_csl_json(&text)?
+    } else {
+        parse_bibtex(&text)
+    });
+    *cache().lock().expect("references cache mutex") =
+        Some((path.to_path_buf(), modified, Arc::clone(&references)));
+    Ok(references)
+}
+
+/// References matching every word of `query`, keys that start with it first.
+/// An empty query lists the library from the top.
+pub fn search(references: &[Reference], query: &str) -> Vec<Reference> {
+    let query = query.trim().to_lowercase();
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut matches: Vec<(bool, &Reference)> = references
+        .iter()
+        .filter_map(|reference| {
+            let haystack = [
+                Some(reference.key.as_str()),
+                reference.title.as_deref(),
+                reference.year.as_deref(),
+                reference.container.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .chain(reference.authors.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+            words
+                .iter()
+                .all(|word| haystack.contains(word))
+                .then(|| (reference.key.to_lowercase().starts_with(&query), reference))
+        })
+        .collect();
+    // Stable, so the library's order holds within each group
+    matches.sort_by_key(|(key_match, _)| !key_match);
+    matches
+        .into_iter()
+        .take(SEARCH_LIMIT)
+        .map(|(_, reference)| reference.clone())
+        .collect()
+}
+
+/// A literature note for `reference`: its details as frontmatter, then
+/// headings to fill in.
+pub fn literature_note(reference: &Reference) -> String {
+    let title = reference.title.as_deref().unwrap_or(&reference.key);
+    let mut body = format!("# {}\n\n", title);
+    if !reference.authors.is_empty() {
+        body.push_str(&format!("**Authors:** {}\n", reference.authors.join(", ")));
+    }
+    let published: Vec<&str> = [reference.container.as_deref(), reference.year.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect();
+    if !published.is_empty() {
+        body.push_str(&format!("**Published:** {}\n", published.join(", ")));
+    }
+    body.push_str("\n## Summary\n\n## Notes\n");
+
+    let mut entries = vec![
+        ("citekey", Value::Scalar(reference.key.clone())),
+        ("title", Value::Scalar(title.to_string())),
+    ];
+    if !reference.authors.is_empty() {
+        entries.push(("authors", Value::List(reference.authors.clone())));
+    }
+    let optional = [
+        ("year", &reference.year),
+        ("doi", &reference.doi),
+        ("url", &reference.url),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            entries.push((key, Value::Scalar(value.clone())));
+        }
+    }
+    entries.push(("tags", Value::List(vec!["literature".to_string()])));
+    entries.into_iter().fold(body, |content, (key, value)| {
+        frontmatter::set(&content, key, value)
+    })
+}
@@ -0,0 +1,122 @@
+//! Markdown to HTML rendering for notes viewed outside the editor.
+//!
+//! A note can ask for CSS classes with `cssclasses:` (or `cssclass:`) in its
+//! frontmatter; exported pages wrap its body in an `<article>` with those
+//! classes and add the vault's `exportClassStyles` snippet for each, so
+//! slides, recipes or a CV can look different from ordinary notes.
+
+use crate::frontmatter;
+use pulldown_cmark::{html, Options, Parser};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const CLASS_KEYS: &[&str] = &["cssclasses", "cssclass"];
+
+/// The classes and stylesheet an exported note is given, for exporters
+/// outside the backend (printing to PDF).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStyle {
+    pub classes: Vec<String>,
+    pub css: String, // exportClassStyles snippets for those classes
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render a note body (frontmatter stripped) to an HTML fragment.
+pub fn render_body(content: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let parser = Parser::new_ext(frontmatter::body(content), options);
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+/// CSS classes a note asks for in its frontmatter, as a list or separated by
+/// commas or spaces; characters not allowed in a class name are dropped.
+pub fn note_classes(content: &str) -> Vec<String> {
+    let fm = frontmatter::Frontmatter::parse(content);
+    let mut classes: Vec<String> = Vec::new();
+    for key in CLASS_KEYS {
+        for item in fm.get_list(key) {
+            for class in item.split_whitespace() {
+                let class: String = class
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                    .collect();
+                if !class.is_empty() && !classes.contains(&class) {
+                    classes.push(class);
+                }
+            }
+        }
+    }
+    classes
+}
+
+/// The configured snippets for `classes`, in class order. A snippet cannot
+/// close the page's `<style>` element.
+pub fn class_css(classes: &[String], styles: &BTreeMap<String, String>) -> String {
+    classes
+        .iter()
+        .filter_map(|class| styles.get(class))
+        .map(|css| css.replace("</", "<\\/"))
+        .collect()
+}
+
+pub fn export_style(content: &str, styles: &BTreeMap<String, String>) -> ExportStyle {
+    let classes = note_classes(content);
+    let css = class_css(&classes, styles);
+    ExportStyle { classes, css }
+}
+
+const PAGE_STYLE: &str = "body{max-width:720px;margin:2rem auto;padding:0 1rem;\
+font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif;line-height:1.6;color:#1f1f1f}\
+pre{background:#f4f4f4;padding:.75rem;overflow-x:auto;border-radius:6px}\
+code{font-family:ui-monospace,Menlo,monospace;font-size:.9em}\
+table{border-collapse:collapse}td,th{border:1px solid #ddd;padding:.25rem .5rem}\
+img{max-width:100%}blockquote{margin-left:0;padding-left:1rem;border-left:3px solid #ddd;color:#555}\
+@media (prefers-color-scheme:dark){body{background:#1a1a1a;color:#e6e6e6}pre{background:#262626}a{color:#8ab4f8}}";
+
+/// Render a standalone HTML page for a note. `nav` is inserted verbatim above
+/// the note body, which is wrapped in an `<article>` carrying the note's
+/// classes if it has any; `extra_css` is appended after the default styles,
+/// then the `class_styles` snippets for those classes.
+pub fn render_page(
+    title: &str,
+    content: &str,
+    nav: &str,
+    extra_css: Option<&str>,
+    class_styles: Option<&BTreeMap<String, String>>,
+) -> String {
+    let classes = note_classes(content);
+    let body = if classes.is_empty() {
+        render_body(content)
+    } else {
+        format!(
+            "<article class=\"{}\">\n{}</article>\n",
+            classes.join(" "),
+            render_body(content)
+        )
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+<title>{}</title>\n<style>{}{}{}</style>\n</head>\n<body>\n{}{}</body>\n</html>\n",
+        escape_html(title),
+        PAGE_STYLE,
+        extra_css.unwrap_or(""),
+        class_styles
+            .map(|styles| class_css(&classes, styles))
+            .unwrap_or_default(),
+        nav,
+        body
+    )
+}
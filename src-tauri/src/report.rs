@@ -0,0 +1,156 @@
+//! Vault-wide analysis: per-note statistics, orphan detection and the
+//! "Vault Stats" report note.
+
+use crate::links::{self, LinkResolver};
+use crate::{frontmatter, tags, ScannedNote};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Derived statistics for a single note.
+pub struct NoteStats {
+    pub id: String,
+    pub words: usize,
+    pub size: u64,
+    pub modified: i64,
+    pub tags: Vec<String>,
+    pub outgoing: Vec<String>, // resolved note IDs this note links to
+}
+
+pub fn word_count(content: &str) -> usize {
+    frontmatter::body(content).split_whitespace().count()
+}
+
+/// Compute statistics for every scanned note, resolving links against the set.
+pub fn analyze(notes: &[ScannedNote]) -> Vec<NoteStats> {
+    let resolver = LinkResolver::new(notes.iter().map(|n| (n.id.as_str(), n.title.as_str())));
+
+    notes
+        .iter()
+        .map(|note| {
+            let mut outgoing: Vec<String> = links::extract_links(&note.content)
+                .iter()
+                .filter_map(|link| resolver.resolve(link, &note.id))
+                .filter(|target| *target != note.id)
+                .collect();
+            outgoing.sort();
+            outgoing.dedup();
+
+            NoteStats {
+                id: note.id.clone(),
+                words: word_count(&note.content),
+                size: note.size,
+                modified: note.modified,
+                tags: tags::extract_tags(&note.content),
+                outgoing,
+            }
+        })
+        .collect()
+}
+
+/// Notes with no incoming links, no outgoing links and no tags, oldest first.
+pub fn find_orphans(stats: &[NoteStats]) -> Vec<&NoteStats> {
+    let linked: HashSet<&str> = stats
+        .iter()
+        .flat_map(|s| s.outgoing.iter().map(|id| id.as_str()))
+        .collect();
+
+    let mut orphans: Vec<&NoteStats> = stats
+        .iter()
+        .filter(|s| s.outgoing.is_empty() && s.tags.is_empty() && !linked.contains(s.id.as_str()))
+        .collect();
+    orphans.sort_by_key(|s| s.modified);
+    orphans
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Render the "Vault Stats" markdown note.
+pub fn render_report(stats: &[NoteStats], title: &str, generated_at: &str) -> String {
+    use chrono::{Local, TimeZone};
+
+    let total_words: usize = stats.iter().map(|s| s.words).sum();
+    let folders: HashSet<&str> = stats
+        .iter()
+        .filter_map(|s| s.id.rfind('/').map(|pos| &s.id[..pos]))
+        .collect();
+
+    let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+    for s in stats {
+        for tag in &s.tags {
+            *tag_counts.entry(tag.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    // Words per month, keyed by last-modified month
+    let mut months: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for s in stats {
+        if let Some(dt) = Local.timestamp_opt(s.modified, 0).single() {
+            let entry = months.entry(dt.format("%Y-%m").to_string()).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += s.words;
+        }
+    }
+
+    let mut out = format!("# {}\n\n_Generated {}_\n\n", title, generated_at);
+
+    out.push_str("## Overview\n\n");
+    out.push_str(&format!("- Notes: {}\n", stats.len()));
+    out.push_str(&format!("- Words: {}\n", total_words));
+    out.push_str(&format!("- Tags: {}\n", tag_counts.len()));
+    out.push_str(&format!("- Folders: {}\n\n", folders.len()));
+
+    out.push_str("## Words Written per Month\n\n| Month | Notes | Words |\n| --- | --- | --- |\n");
+    for (month, (count, words)) in months.iter().rev().take(12) {
+        out.push_str(&format!("| {} | {} | {} |\n", month, count, words));
+    }
+    out.push('\n');
+
+    let mut top_tags: Vec<(&str, usize)> = tag_counts.into_iter().collect();
+    top_tags.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    out.push_str("## Top Tags\n\n");
+    if top_tags.is_empty() {
+        out.push_str("No tags yet.\n\n");
+    } else {
+        out.push_str("| Tag | Notes |\n| --- | --- |\n");
+        for (tag, count) in top_tags.iter().take(15) {
+            out.push_str(&format!("| `#{}` | {} |\n", tag, count));
+        }
+        out.push('\n');
+    }
+
+    let orphans = find_orphans(stats);
+    out.push_str(&format!("## Orphan Notes ({})\n\n", orphans.len()));
+    if orphans.is_empty() {
+        out.push_str("Every note is linked or tagged.\n\n");
+    } else {
+        for s in orphans.iter().take(25) {
+            out.push_str(&format!("- [[{}]]\n", s.id));
+        }
+        out.push('\n');
+    }
+
+    let mut largest: Vec<&NoteStats> = stats.iter().collect();
+    largest.sort_by(|a, b| b.words.cmp(&a.words).then(b.size.cmp(&a.size)));
+    out.push_str("## Largest Notes\n\n| Note | Words | Size |\n| --- | --- | --- |\n");
+    for s in largest.iter().take(10) {
+        out.push_str(&format!(
+            "| [[{}]] | {} | {} |\n",
+            escape_cell(&s.id),
+            s.words,
+            format_size(s.size)
+        ));
+    }
+
+    out
+}
@@ -0,0 +1,283 @@
+//! Restore points: zip snapshots of every note, taken before operations that
+//! change many notes at once so they can be undone.
+//!
+//! Markdown imports, vault metadata imports and folder deletes take one
+//! automatically; `create_restore_point` takes one on request. Each point
+//! is an archive holding the notes at their vault-relative paths and a
+//! `restore-point.json` manifest, kept in a directory of the vault's own in
+//! app data so points are never synced or committed with the vault. Rolling
+//! back first takes a point of the current state, then rewrites every note
+//! from the archive and moves notes created since into the trash, so a
+//! rollback can be undone the same way. Only the newest `MAX_POINTS` are
+//! kept.
+
+use crate::{assets, id_from_abs_path, is_visible_notes_entry, recovery, trash};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Where restore points were kept in the vault before they moved to app data.
+const LEGACY_DIR: &str = ".scratch/restore-points";
+const MANIFEST: &str = "restore-point.json";
+const MAX_POINTS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestorePoint {
+    #[serde(default)]
+    pub id: String, // archive file stem, passed back to rollback_to
+    pub name: String,
+    pub created_at: i64,
+    pub notes: usize,
+    #[serde(default)]
+    pub bytes: u64, // archive size on disk
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackReport {
+    pub restored: usize,            // notes rewritten or recreated from the point
+    pub trashed: Vec<String>,       // IDs of notes created since, moved to the trash
+    pub safety_point: RestorePoint, // taken just before rolling back
+    #[serde(skip)]
+    pub rewritten: Vec<Rewritten>, // for the audit log and version history
+}
+
+/// A note a rollback rewrote, with what it held before if it existed.
+#[derive(Debug, Clone)]
+pub struct Rewritten {
+    pub rel: String,
+    pub before: Option<Vec<u8>>,
+    pub after: Vec<u8>,
+}
+
+/// Move restore points left in the vault by older versions into `dir`.
+pub fn adopt_legacy(notes_root: &Path, dir: &Path) {
+    let legacy = notes_root.join(LEGACY_DIR);
+    let Ok(entries) = std::fs::read_dir(&legacy) else {
+        return;
+    };
+    if std::fs::create_dir_all(dir).is_err() {
+        return;
+    }
+    for entry in entries.flatten() {
+        let target = dir.join(entry.file_name());
+        if target.exists() {
+            continue;
+        }
+        // Copy when app data is on another volume
+        if std::fs::rename(entry.path(), &target).is_err()
+            && std::fs::copy(entry.path(), &target).is_ok()
+        {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+    let _ = std::fs::remove_dir(&legacy);
+}
+
+/// Every visible note file, as (vault-relative path, absolute path).
+fn note_files(notes_root: &Path) -> Vec<(String, PathBuf)> {
+    walkdir::WalkDir::new(notes_root)
+        .max_depth(10)
+        .into_iter()
+        .filter_entry(is_visible_notes_entry)
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let id = id_from_abs_path(notes_root, e.path())?;
+            Some((crate::rel_path_from_id(&id), e.into_path()))
+        })
+        .collect()
+}
+
+/// Snapshot every note into a new restore point named `name`, kept in `dir`.
+/// Blocking.
+pub fn create(notes_root: &Path, dir: &Path, name: &str) -> Result<RestorePoint, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let created_at = chrono::Utc::now().timestamp();
+    let stem = format!(
+        "{}-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        crate::sanitize_filename(name)
+            .chars()
+            .take(60)
+            .collect::<String>()
+    );
+    let file_name = assets::unique_name(dir, &format!("{}.zip", stem));
+    let path = dir.join(&file_name);
+    // Written under a temporary name so a half-written archive is never listed
+    let temp = dir.join(format!("{}.partial", file_name));
+
+    let write = || -> Result<usize, String> {
+        let file = std::fs::File::create(&temp).map_err(|e| e.to_string())?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        let files = note_files(notes_root);
+        for (rel, abs) in &files {
+            let mut source = std::fs::File::open(abs).map_err(|e| e.to_string())?;
+            zip.start_file(rel.as_str(), options)
+                .map_err(|e| e.to_string())?;
+            std::io::copy(&mut source, &mut zip).map_err(|e| e.to_string())?;
+        }
+        let manifest = RestorePoint {
+            id: String::new(),
+            name: name.to_string(),
+            created_at,
+            notes: files.len(),
+            bytes: 0,
+        };
+        zip.start_file(MANIFEST, options)
+            .map_err(|e| e.to_string())?;
+        let content = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+        zip.write_all(&content).map_err(|e| e.to_string())?;
+        zip.finish()
+            .map_err(|e| e.to_string())?
+            .sync_all()
+            .map_err(|e| e.to_string())?;
+        Ok(files.len())
+    };
+    let notes = write().map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        format!("Failed to create a restore point: {}", e)
+    })?;
+    std::fs::rename(&temp, &path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        format!("Failed to create a restore point: {}", e)
+    })?;
+
+    prune(dir);
+    Ok(RestorePoint {
+        id: file_name.trim_end_matches(".zip").to_string(),
+        name: name.to_string(),
+        created_at,
+        notes,
+        bytes: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+fn open(path: &Path) -> Result<zip::ZipArchive<std::fs::File>, String> {
+    let file = std::fs::File::open(path).map_err(|_| "Restore point not found".to_string())?;
+    zip::ZipArchive::new(file).map_err(|e| format!("Restore point is damaged: {}", e))
+}
+
+fn read_manifest(archive: &mut zip::ZipArchive<std::fs::File>) -> Option<RestorePoint> {
+    let mut content = String::new();
+    archive
+        .by_name(MANIFEST)
+        .ok()?
+        .read_to_string(&mut content)
+        .ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Restore points in `dir`, newest first. Archives that no longer open are
+/// left out.
+pub fn list(dir: &Path) -> Vec<RestorePoint> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut points: Vec<RestorePoint> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(".zip")?
+                .to_string();
+            let mut point = read_manifest(&mut open(&path).ok()?)?;
+            point.id = id;
+            point.bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            Some(point)
+        })
+        .collect();
+    points.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+    points
+}
+
+/// Delete all but the newest `MAX_POINTS` restore points.
+fn prune(dir: &Path) {
+    for point in list(dir).into_iter().skip(MAX_POINTS) {
+        let _ = std::fs::remove_file(dir.join(format!("{}.zip", point.id)));
+    }
+}
+
+/// Put the vault's notes back as they were in restore point `id`, one of
+/// the points in `dir`. Blocking; the caller holds the vault lock.
+pub fn rollback(notes_root: &Path, dir: &Path, id: &str) -> Result<RollbackReport, String> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+        return Err("Invalid restore point".to_string());
+    }
+    let mut archive = open(&dir.join(format!("{}.zip", id)))?;
+    let point = read_manifest(&mut archive).ok_or("Restore point is damaged")?;
+
+    // Read the whole point before touching the vault, so a damaged archive
+    // fails without changing anything
+    let mut files: Vec<(String, Vec<u8>)> = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Restore point is damaged: {}", e))?;
+        let Some(rel) = entry.enclosed_name() else {
+            continue;
+        };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        if rel == MANIFEST
+            || entry.is_dir()
+            || id_from_abs_path(notes_root, &notes_root.join(&rel)).is_none()
+        {
+            continue;
+        }
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Restore point is damaged: {}", e))?;
+        files.push((rel, content));
+    }
+
+    let safety_point = create(
+        notes_root,
+        dir,
+        &format!("Before rollback to {}", point.name),
+    )?;
+
+    let mut rewritten = Vec::new();
+    let kept: HashSet<&str> = files.iter().map(|(rel, _)| rel.as_str()).collect();
+    for (rel, content) in &files {
+        let path = notes_root.join(rel);
+        let before = std::fs::read(&path).ok();
+        if before.as_ref() == Some(content) {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to restore {}: {}", rel, e))?;
+        }
+        recovery::write_atomic_sync(&path, content).map_err(|e| e.to_string())?;
+        rewritten.push(Rewritten {
+            rel: rel.clone(),
+            before,
+            after: content.clone(),
+        });
+    }
+
+    let added: Vec<String> = note_files(notes_root)
+        .into_iter()
+        .map(|(rel, _)| rel)
+        .filter(|rel| !kept.contains(rel.as_str()))
+        .collect();
+    let trashed = trash::trash_assets(notes_root, &format!("Rollback to {}", point.name), &added)?
+        .iter()
+        .map(|rel| rel.trim_end_matches(".md").to_string())
+        .collect();
+
+    Ok(RollbackReport {
+        restored: rewritten.len(),
+        trashed,
+        safety_point,
+        rewritten,
+    })
+}
@@ -0,0 +1,227 @@
+//! Note expiry and retention.
+//!
+//! A note with `expires: <date>` frontmatter is due from that day on, and
+//! `expires: never` keeps it out of the folder rules. Folder rules in the
+//! `retention` setting make notes under a folder due once they are older
+//! than a number of days, counted from when the note was created; the first
+//! rule that covers a note applies. Due notes are archived, moved under the
+//! archive folder (`Archive` by default) with their folder path kept, or
+//! trashed. Expired notes are archived unless `expiredAction` says trash,
+//! and notes already in the archive folder are not archived again.
+//!
+//! A background task applies retention hourly. Runs that archived or
+//! trashed anything are kept in `.scratch/retention.json` as a report.
+
+use crate::{enforce_retention, frontmatter::Frontmatter, store, AppState, ScannedNote};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const STORE_FILE: &str = "retention.json";
+const DEFAULT_ARCHIVE_FOLDER: &str = "Archive";
+const REPORT_LEN: usize = 20; // runs kept in the report
+const TICK: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RetentionAction {
+    #[default]
+    Archive,
+    Trash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionRule {
+    pub folder: String,
+    pub older_than_days: u32,
+    #[serde(default)]
+    pub action: RetentionAction,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionSettings {
+    pub archive_folder: Option<String>,          // default "Archive"
+    pub expired_action: Option<RetentionAction>, // for `expires:`; default archive
+    #[serde(default)]
+    pub rules: Vec<RetentionRule>,
+}
+
+/// A note retention has made due, and why.
+#[derive(Debug, Clone)]
+pub struct Due {
+    pub id: String,
+    pub action: RetentionAction,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetainedNote {
+    pub id: String,
+    pub action: RetentionAction,
+    pub reason: String,
+    pub new_id: Option<String>, // where an archived note went
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionRun {
+    pub ran_at: i64,
+    pub dry_run: bool, // listed what is due without acting
+    pub notes: Vec<RetainedNote>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub runs: Vec<RetentionRun>, // newest first
+}
+
+impl RetentionReport {
+    pub fn load(notes_folder: &str) -> Self {
+        store::load(Path::new(notes_folder), STORE_FILE)
+    }
+
+    pub fn save(&self, notes_folder: &str) -> Result<()> {
+        store::save(Path::new(notes_folder), STORE_FILE, self)
+    }
+
+    pub fn record(&mut self, run: RetentionRun) {
+        self.runs.insert(0, run);
+        self.runs.truncate(REPORT_LEN);
+    }
+}
+
+/// The configured archive folder, without surrounding slashes.
+pub fn archive_folder(settings: &RetentionSettings) -> String {
+    match settings
+        .archive_folder
+        .as_deref()
+        .map(|f| f.trim().trim_matches('/'))
+    {
+        Some(f) if !f.is_empty() => f.to_string(),
+        _ => DEFAULT_ARCHIVE_FOLDER.to_string(),
+    }
+}
+
+fn in_folder(folder: &str, id: &str) -> bool {
+    id.strip_prefix(folder)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// The folder an archived note moves to: its own folder path under the
+/// archive folder.
+pub fn archive_target(archive_folder: &str, id: &str) -> String {
+    match id.rsplit_once('/') {
+        Some((parent, _)) => format!("{}/{}", archive_folder, parent),
+        None => archive_folder.to_string(),
+    }
+}
+
+enum Expiry {
+    Never,
+    On(NaiveDate),
+}
+
+fn expiry(content: &str) -> Option<Expiry> {
+    let value = Frontmatter::parse(content)
+        .get("expires")?
+        .trim()
+        .to_string();
+    if value.eq_ignore_ascii_case("never") {
+        return Some(Expiry::Never);
+    }
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d")
+        .ok()
+        .map(Expiry::On)
+}
+
+/// The notes due for archiving or trashing on `today`, in ID order.
+pub fn due(settings: &RetentionSettings, notes: &[ScannedNote], today: NaiveDate) -> Vec<Due> {
+    let archive_folder = archive_folder(settings);
+    let rules: Vec<&RetentionRule> = settings
+        .rules
+        .iter()
+        .filter(|rule| rule.enabled && !rule.folder.trim_matches('/').is_empty())
+        .collect();
+    let mut due: Vec<Due> = notes
+        .iter()
+        .filter_map(|note| {
+            let (action, reason) = match expiry(&note.content) {
+                Some(Expiry::Never) => return None,
+                Some(Expiry::On(date)) if date <= today => (
+                    settings.expired_action.unwrap_or_default(),
+                    format!("Expired on {}", date.format("%Y-%m-%d")),
+                ),
+                Some(Expiry::On(_)) => return None,
+                None => {
+                    let rule = rules
+                        .iter()
+                        .find(|rule| in_folder(rule.folder.trim_matches('/'), &note.id))?;
+                    let created = DateTime::from_timestamp(note.created, 0)?.date_naive();
+                    if (today - created).num_days() < rule.older_than_days as i64 {
+                        return None;
+                    }
+                    (
+                        rule.action,
+                        format!(
+                            "Older than {} days in {}",
+                            rule.older_than_days,
+                            rule.folder.trim_matches('/')
+                        ),
+                    )
+                }
+            };
+            if action == RetentionAction::Archive && in_folder(&archive_folder, &note.id) {
+                return None;
+            }
+            Some(Due {
+                id: note.id.clone(),
+                action,
+                reason,
+            })
+        })
+        .collect();
+    due.sort_by(|a, b| a.id.cmp(&b.id));
+    due
+}
+
+/// Apply retention hourly. Runs that acted are emitted as
+/// `retention-applied`.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let state = app.state::<AppState>();
+            if state.shutdown.started() {
+                return;
+            }
+            let has_folder = state
+                .app_config
+                .read()
+                .expect("app_config read lock")
+                .notes_folder
+                .is_some();
+            if has_folder {
+                match enforce_retention(&state, false).await {
+                    Ok(run) if !run.notes.is_empty() => {
+                        let _ = app.emit("retention-applied", &run);
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Retention failed: {}", e),
+                }
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
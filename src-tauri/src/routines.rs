@@ -0,0 +1,359 @@
+//! Recurring tasks (routines).
+//!
+//! Any task marked `@every(...)` is a routine. On each day its schedule falls
+//! on, a plain copy (`- [ ] text`) is added to that day's daily note, once.
+//! `.scratch/routines.json` records the days a copy was generated and the days
+//! it was checked off. Days the app was not running are skipped rather than
+//! backfilled.
+//!
+//! Schedules: `day`, `week`, `weekday`, `weekend`, weekday names (`monday`,
+//! `mon, thu`), `N days` / `N weeks` (counted from the last generated copy) and
+//! a day of the month (`1st`, `15`; clamped to the end of short months).
+
+use crate::{
+    abs_path_from_id, active_private_folders, daily, dates, scan_analyzable_notes, store, tasks,
+    AppState, ScannedNote,
+};
+use anyhow::Result;
+use chrono::{Datelike, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const STORE_FILE: &str = "routines.json";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const HISTORY_LEN: usize = 366; // generated and completed days kept per routine
+const TICK: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Schedule {
+    EveryDays(i64),
+    Weekdays(Vec<Weekday>),
+    MonthDay(u32),
+}
+
+fn parse_schedule(spec: &str) -> Option<Schedule> {
+    use Weekday::*;
+
+    let spec = spec.trim().to_lowercase();
+    let words: Vec<&str> = spec
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty() && *w != "and")
+        .collect();
+    let count = |n: &str| n.parse::<i64>().ok().filter(|n| (1..=366).contains(n));
+
+    match words.as_slice() {
+        ["day"] | ["daily"] => Some(Schedule::EveryDays(1)),
+        ["week"] | ["weekly"] => Some(Schedule::EveryDays(7)),
+        ["weekday"] | ["weekdays"] => Some(Schedule::Weekdays(vec![Mon, Tue, Wed, Thu, Fri])),
+        ["weekend"] | ["weekends"] => Some(Schedule::Weekdays(vec![Sat, Sun])),
+        [n, "day" | "days"] => count(n).map(Schedule::EveryDays),
+        [n, "week" | "weeks"] => count(n).map(|n| Schedule::EveryDays(n * 7)),
+        [day] if dates::day_of_month(day).is_some() => {
+            dates::day_of_month(day).map(Schedule::MonthDay)
+        }
+        names => names
+            .iter()
+            .map(|name| dates::weekday(name))
+            .collect::<Option<Vec<_>>>()
+            .filter(|days| !days.is_empty())
+            .map(Schedule::Weekdays),
+    }
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    date.with_day(1)
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+        .and_then(|next| next.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(31)
+}
+
+impl Schedule {
+    /// Whether a copy is due on `date`, given the last day one was generated.
+    fn due_on(&self, date: NaiveDate, last: Option<NaiveDate>) -> bool {
+        match self {
+            Schedule::EveryDays(n) => match last {
+                Some(last) => (date - last).num_days() >= *n,
+                None => true,
+            },
+            Schedule::Weekdays(days) => days.contains(&date.weekday()),
+            Schedule::MonthDay(day) => date.day() == (*day).min(days_in_month(date)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct History {
+    generated: Vec<String>, // YYYY-MM-DD, oldest first
+    completed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutineStore {
+    routines: HashMap<String, History>,
+}
+
+impl RoutineStore {
+    fn load(notes_root: &Path) -> Self {
+        store::load(notes_root, STORE_FILE)
+    }
+
+    fn save(&self, notes_root: &Path) -> Result<()> {
+        store::save(notes_root, STORE_FILE, self)
+    }
+}
+
+struct Routine {
+    key: String, // "<source note id>::<text>"
+    source_id: String,
+    text: String,
+    every: String,
+    schedule: Schedule,
+}
+
+fn discover(notes: &[ScannedNote]) -> Vec<Routine> {
+    let mut routines: Vec<Routine> = Vec::new();
+    for note in notes {
+        for task in tasks::extract_tasks(&note.content) {
+            let Some(every) = task.every else {
+                continue;
+            };
+            let Some(schedule) = parse_schedule(&every) else {
+                continue;
+            };
+            let key = format!("{}::{}", note.id, task.text);
+            if task.text.is_empty() || routines.iter().any(|r| r.key == key) {
+                continue;
+            }
+            routines.push(Routine {
+                key,
+                source_id: note.id.clone(),
+                text: task.text,
+                every,
+                schedule,
+            });
+        }
+    }
+    routines
+}
+
+fn push_day(days: &mut Vec<String>, day: &str) {
+    if !days.iter().any(|d| d == day) {
+        days.push(day.to_string());
+        days.sort();
+    }
+    if days.len() > HISTORY_LEN {
+        days.drain(..days.len() - HISTORY_LEN);
+    }
+}
+
+/// Record copies that have been checked off since the last look.
+fn sync_completions(
+    notes_root: &Path,
+    daily: &daily::Layout,
+    routines: &[Routine],
+    store: &mut RoutineStore,
+) {
+    let mut daily_tasks: HashMap<String, Vec<tasks::Task>> = HashMap::new();
+    for routine in routines {
+        let Some(history) = store.routines.get_mut(&routine.key) else {
+            continue;
+        };
+        let open: Vec<String> = history
+            .generated
+            .iter()
+            .filter(|day| !history.completed.contains(day))
+            .cloned()
+            .collect();
+        for day in open {
+            let day_tasks = daily_tasks.entry(day.clone()).or_insert_with(|| {
+                NaiveDate::parse_from_str(&day, DATE_FORMAT)
+                    .ok()
+                    .and_then(|date| abs_path_from_id(notes_root, &daily.note_id(date)).ok())
+                    .and_then(|path| std::fs::read_to_string(path).ok())
+                    .map(|content| tasks::extract_tasks(&content))
+                    .unwrap_or_default()
+            });
+            if day_tasks.iter().any(|t| t.done && t.text == routine.text) {
+                push_day(&mut history.completed, &day);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutineRun {
+    pub date: String,
+    pub daily_note_id: String,
+    pub created: Vec<String>, // task texts added to the daily note
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutineStatus {
+    pub source_id: String,
+    pub text: String,
+    pub every: String,
+    pub due_today: bool,
+    pub generated: Vec<String>,
+    pub completed: Vec<String>,
+}
+
+/// Add the copies due on `date` to its daily note. Blocking.
+pub fn materialize(
+    notes_root: &Path,
+    notes: &[ScannedNote],
+    daily: &daily::Layout,
+    date: NaiveDate,
+) -> Result<RoutineRun, String> {
+    let day = date.format(DATE_FORMAT).to_string();
+    let daily_note_id = daily.note_id(date);
+    let routines = discover(notes);
+    if routines.is_empty() {
+        return Ok(RoutineRun {
+            date: day,
+            daily_note_id,
+            created: Vec::new(),
+        });
+    }
+    let mut store = RoutineStore::load(notes_root);
+    sync_completions(notes_root, daily, &routines, &mut store);
+
+    let path = abs_path_from_id(notes_root, &daily_note_id)?;
+    let existing = std::fs::read_to_string(&path).ok();
+    let existing_texts: Vec<String> = existing
+        .as_deref()
+        .map(|content| {
+            tasks::extract_tasks(content)
+                .into_iter()
+                .map(|t| t.text)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut created: Vec<String> = Vec::new();
+    for routine in &routines {
+        let history = store.routines.entry(routine.key.clone()).or_default();
+        if history.generated.contains(&day) {
+            continue;
+        }
+        let last = history
+            .generated
+            .iter()
+            .rev()
+            .find(|d| **d < day)
+            .and_then(|d| NaiveDate::parse_from_str(d, DATE_FORMAT).ok());
+        if !routine.schedule.due_on(date, last) {
+            continue;
+        }
+        push_day(&mut history.generated, &day);
+        // Already on the page (added by hand, or the template lives there)
+        if !existing_texts.contains(&routine.text) && !created.contains(&routine.text) {
+            created.push(routine.text.clone());
+        }
+    }
+
+    if !created.is_empty() {
+        let mut content = existing.unwrap_or_else(|| daily::new_content(date));
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for text in &created {
+            content.push_str(&format!("- [ ] {}\n", text));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&path, content).map_err(|e| e.to_string())?;
+    }
+    store.save(notes_root).map_err(|e| e.to_string())?;
+
+    Ok(RoutineRun {
+        date: day,
+        daily_note_id,
+        created,
+    })
+}
+
+/// Every routine with its history, refreshed from the daily notes. Blocking.
+pub fn statuses(
+    notes_root: &Path,
+    notes: &[ScannedNote],
+    daily: &daily::Layout,
+    today: NaiveDate,
+) -> Vec<RoutineStatus> {
+    let routines = discover(notes);
+    let mut store = RoutineStore::load(notes_root);
+    sync_completions(notes_root, daily, &routines, &mut store);
+    let _ = store.save(notes_root);
+
+    let day = today.format(DATE_FORMAT).to_string();
+    routines
+        .into_iter()
+        .map(|routine| {
+            let history = store
+                .routines
+                .get(&routine.key)
+                .cloned()
+                .unwrap_or_default();
+            let last = history
+                .generated
+                .iter()
+                .rev()
+                .find(|d| **d < day)
+                .and_then(|d| NaiveDate::parse_from_str(d, DATE_FORMAT).ok());
+            RoutineStatus {
+                due_today: history.generated.contains(&day) || routine.schedule.due_on(today, last),
+                source_id: routine.source_id,
+                text: routine.text,
+                every: routine.every,
+                generated: history.generated,
+                completed: history.completed,
+            }
+        })
+        .collect()
+}
+
+/// Generate today's copies periodically, so routines appear without the user
+/// asking. Nothing is written unless a routine is due.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let state = app.state::<AppState>();
+            if state.shutdown.started() {
+                return;
+            }
+            let folder = state
+                .app_config
+                .read()
+                .expect("app_config read lock")
+                .notes_folder
+                .clone();
+            if let Some(folder) = folder {
+                let daily = {
+                    let settings = state.settings.read().expect("settings read lock");
+                    daily::Layout::from_settings(&settings)
+                };
+                let private_folders = active_private_folders(&state);
+                let run = tauri::async_runtime::spawn_blocking(move || {
+                    let root = Path::new(&folder);
+                    let notes = scan_analyzable_notes(root, private_folders.as_deref());
+                    let today = chrono::Local::now().date_naive();
+                    materialize(root, &notes, &daily, today)
+                })
+                .await;
+                if let Ok(Ok(run)) = run {
+                    if !run.created.is_empty() {
+                        let _ = app.emit("routines-materialized", &run);
+                    }
+                }
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
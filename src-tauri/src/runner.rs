@@ -0,0 +1,482 @@
+//! Running a note's fenced code blocks, for literate notes.
+//!
+//! Off unless the code-block settings list the languages that may run: `shell`
+//! (sh), `python` (python3) and `node`. They live in the app config on this
+//! machine, never in the vault, so a shared vault cannot switch running on. A
+//! block runs from a file in a fresh temporary directory, which is also its
+//! working directory and HOME, with no stdin and only PATH, HOME, TMPDIR and
+//! LANG in its environment. Where the platform allows, it is sandboxed without
+//! network access and without write access outside that directory:
+//! `sandbox-exec` on macOS, bubblewrap (`bwrap`) on Linux. Elsewhere it only
+//! runs if `allowUnsandboxed` is set.
+//!
+//! Output streams to the frontend as `code-block-output` events while the
+//! block runs, and the process (with anything it started) is killed at the
+//! timeout. Results can be written back under the block as an `output` fence,
+//! replacing the previous one. Blocks are counted from 0 in the order they
+//! appear, leaving out `output` blocks, so writing results never shifts them.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const MAX_TIMEOUT_SECS: u64 = 600;
+const MAX_OUTPUT_BYTES: usize = 256 * 1024;
+pub const OUTPUT_LANGUAGE: &str = "output";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeBlockSettings {
+    #[serde(default)]
+    pub languages: Vec<String>, // "shell", "python", "node"; empty turns running off
+    pub timeout_secs: Option<u64>, // default 30, at most 600
+    #[serde(default)]
+    pub allow_unsandboxed: bool, // run where no OS sandbox is available
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Shell,
+    Python,
+    Node,
+}
+
+impl Language {
+    /// The language a fence's info string names, if it can run.
+    fn from_info(info: &str) -> Option<Self> {
+        match info.to_ascii_lowercase().as_str() {
+            "sh" | "bash" | "shell" | "zsh" => Some(Language::Shell),
+            "python" | "py" | "python3" => Some(Language::Python),
+            "js" | "javascript" | "node" | "mjs" => Some(Language::Node),
+            _ => None,
+        }
+    }
+
+    fn setting_name(self) -> &'static str {
+        match self {
+            Language::Shell => "shell",
+            Language::Python => "python",
+            Language::Node => "node",
+        }
+    }
+
+    fn programs(self) -> &'static [&'static str] {
+        match self {
+            Language::Shell => &["sh"],
+            Language::Python => &["python3", "python"],
+            Language::Node => &["node"],
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Language::Shell => "block.sh",
+            Language::Python => "block.py",
+            Language::Node => "block.js",
+        }
+    }
+}
+
+/// A fenced code block and where it sits in the note.
+#[derive(Debug, Clone)]
+pub struct CodeBlock {
+    pub language: String, // first word of the info string, lowercased
+    pub code: String,
+    end: usize, // byte offset just past the closing fence line
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeRun {
+    pub output: String,         // stdout and stderr as they arrived
+    pub exit_code: Option<i32>, // None when killed
+    pub timed_out: bool,
+    pub truncated: bool, // output past 256 KB was dropped
+    pub sandboxed: bool,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputEvent<'a> {
+    id: &'a str,
+    block_index: usize,
+    stream: &'a str, // "stdout" or "stderr"
+    text: String,
+}
+
+/// An opening or closing fence: its character, length and info string.
+pub fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|x| *x == c).count();
+    (len >= 3).then(|| (c, len, trimmed[len..].trim()))
+}
+
+/// The fenced code blocks of a note, in order, `output` blocks included.
+fn all_blocks(content: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(char, usize, String, String)> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        offset += line.len();
+        match open.as_mut() {
+            None => {
+                if let Some((c, len, info)) = fence(line) {
+                    let language = info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_ascii_lowercase();
+                    open = Some((c, len, language, String::new()));
+                }
+            }
+            Some((c, len, _, code)) => {
+                let closes = fence(line)
+                    .is_some_and(|(fc, flen, info)| fc == *c && flen >= *len && info.is_empty());
+                if closes {
+                    let (_, _, language, code) = open.take().unwrap_or_default();
+                    blocks.push(CodeBlock {
+                        language,
+                        code,
+                        end: offset,
+                    });
+                } else {
+                    code.push_str(line);
+                }
+            }
+        }
+    }
+    // An unclosed fence runs to the end of the note
+    if let Some((_, _, language, code)) = open {
+        blocks.push(CodeBlock {
+            language,
+            code,
+            end: content.len(),
+        });
+    }
+    blocks
+}
+
+/// Block `index` of a note, counting every fenced block except `output` ones.
+pub fn code_block(content: &str, index: usize) -> Option<CodeBlock> {
+    all_blocks(content)
+        .into_iter()
+        .filter(|block| block.language != OUTPUT_LANGUAGE)
+        .nth(index)
+}
+
+/// `content` with `output` written as an `output` block right under block
+/// `index`, in place of the one already there.
+pub fn with_output(content: &str, index: usize, output: &str) -> Option<String> {
+    let block = code_block(content, index)?;
+    let blocks = all_blocks(content);
+    let position = blocks.iter().position(|b| b.end == block.end)?;
+
+    // An output block separated from the code only by blank lines is replaced
+    let mut replace_end = block.end;
+    if let Some(next) = blocks.get(position + 1) {
+        if next.language == OUTPUT_LANGUAGE {
+            let between = &content[block.end..next.end];
+            let opening = between.trim_start_matches(['\n', '\r', ' ', '\t']);
+            if fence(opening.lines().next().unwrap_or_default()).is_some() {
+                replace_end = next.end;
+            }
+        }
+    }
+
+    let longest_run = output
+        .lines()
+        .map(|line| line.trim_start().chars().take_while(|c| *c == '`').count())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let mut text = output.trim_end_matches('\n').to_string();
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    let mut before = content[..block.end].to_string();
+    if !before.ends_with('\n') {
+        before.push('\n');
+    }
+    let rest = &content[replace_end..];
+    let separator = if rest.is_empty() || rest.starts_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    Some(format!(
+        "{}\n{}{}\n{}{}\n{}{}",
+        before, fence, OUTPUT_LANGUAGE, text, fence, separator, rest
+    ))
+}
+
+/// How to start `program` on `file` under the platform's sandbox, if it has
+/// one this machine can use.
+fn sandboxed_command(
+    program: &str,
+    file: &Path,
+    dir: &Path,
+    path: &str,
+) -> Option<std::process::Command> {
+    if cfg!(target_os = "macos") {
+        let profile = format!(
+            "(version 1)(allow default)(deny network*)(deny file-write*)\
+(allow file-write* (subpath \"{}\") (literal \"/dev/null\") (literal \"/dev/tty\"))",
+            dir.display()
+        );
+        if !Path::new("/usr/bin/sandbox-exec").exists() {
+            return None;
+        }
+        let mut cmd = crate::no_window_cmd("/usr/bin/sandbox-exec");
+        cmd.arg("-p").arg(profile).arg(program).arg(file);
+        return Some(cmd);
+    }
+    if cfg!(target_os = "linux") && crate::check_cli_exists("bwrap", path).unwrap_or(false) {
+        let mut cmd = crate::no_window_cmd("bwrap");
+        cmd.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+            .arg("--bind")
+            .arg(dir)
+            .arg(dir)
+            .args(["--unshare-all", "--die-with-parent"])
+            .arg("--chdir")
+            .arg(dir)
+            .arg(program)
+            .arg(file);
+        return Some(cmd);
+    }
+    None
+}
+
+fn temp_dir() -> Result<PathBuf, String> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "scratch-run-{}-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_millis(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create a run directory: {}", e))?;
+    // sandbox-exec matches real paths; /var/folders is a symlink on macOS
+    dir.canonicalize().map_err(|e| e.to_string())
+}
+
+/// Kill the child and, on Unix, the process group it leads.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let _ = std::process::Command::new("kill")
+            .args(["-KILL", &format!("-{}", child.id())])
+            .status();
+    }
+    let _ = child.kill();
+}
+
+/// Read one of the child's pipes into `output`, emitting each chunk.
+fn pump(
+    mut pipe: impl Read + Send + 'static,
+    stream: &'static str,
+    output: Arc<Mutex<(String, bool)>>,
+    app: AppHandle,
+    id: String,
+    block_index: usize,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let n = match pipe.read(&mut buf) {
+                Ok(0) | Err(_) => 0,
+                Ok(n) => n,
+            };
+            pending.extend_from_slice(&buf[..n]);
+            // Hold back a UTF-8 sequence split across reads
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(_) => pending.len(),
+                Err(e) if n > 0 && e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => pending.len(),
+            };
+            if valid > 0 {
+                let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+                pending.drain(..valid);
+                {
+                    let mut output = output.lock().expect("run output mutex");
+                    let room = MAX_OUTPUT_BYTES.saturating_sub(output.0.len());
+                    if text.len() > room {
+                        let mut cut = room;
+                        while !text.is_char_boundary(cut) {
+                            cut -= 1;
+                        }
+                        output.0.push_str(&text[..cut]);
+                        output.1 = true;
+                    } else {
+                        output.0.push_str(&text);
+                    }
+                }
+                let _ = app.emit(
+                    "code-block-output",
+                    OutputEvent {
+                        id: &id,
+                        block_index,
+                        stream,
+                        text,
+                    },
+                );
+            }
+            if n == 0 {
+                return;
+            }
+        }
+    })
+}
+
+/// Run block `block_index` of note `id`, streaming its output. Blocking.
+pub fn run(
+    app: &AppHandle,
+    settings: &CodeBlockSettings,
+    id: &str,
+    block_index: usize,
+    block: &CodeBlock,
+) -> Result<CodeRun, String> {
+    let language = Language::from_info(&block.language)
+        .ok_or_else(|| format!("Code blocks in \"{}\" can't be run", block.language))?;
+    if !settings
+        .languages
+        .iter()
+        .any(|l| l.eq_ignore_ascii_case(language.setting_name()))
+    {
+        return Err(format!(
+            "Running {} blocks is not enabled on this machine",
+            language.setting_name()
+        ));
+    }
+    let path = crate::get_expanded_path();
+    let program = language
+        .programs()
+        .iter()
+        .find(|program| crate::check_cli_exists(program, &path).unwrap_or(false))
+        .ok_or_else(|| format!("{} not found", language.programs()[0]))?;
+
+    let dir = temp_dir()?;
+    let file = dir.join(language.file_name());
+    let result = std::fs::write(&file, &block.code)
+        .map_err(|e| format!("Failed to write the block: {}", e))
+        .and_then(|_| run_in(app, settings, id, block_index, program, &file, &dir, &path));
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_in(
+    app: &AppHandle,
+    settings: &CodeBlockSettings,
+    id: &str,
+    block_index: usize,
+    program: &str,
+    file: &Path,
+    dir: &Path,
+    path: &str,
+) -> Result<CodeRun, String> {
+    let (mut cmd, sandboxed) = match sandboxed_command(program, file, dir, path) {
+        Some(cmd) => (cmd, true),
+        None if settings.allow_unsandboxed => {
+            let mut cmd = crate::no_window_cmd(program);
+            cmd.arg(file);
+            (cmd, false)
+        }
+        None => return Err("No sandbox is available to run code blocks in".to_string()),
+    };
+    cmd.current_dir(dir)
+        .env_clear()
+        .env("PATH", path)
+        .env("HOME", dir)
+        .env("TMPDIR", dir)
+        .env("LANG", "C.UTF-8")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let timeout = Duration::from_secs(
+        settings
+            .timeout_secs
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .clamp(1, MAX_TIMEOUT_SECS),
+    );
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+    let output = Arc::new(Mutex::new((String::new(), false)));
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(pump(
+            stdout,
+            "stdout",
+            Arc::clone(&output),
+            app.clone(),
+            id.to_string(),
+            block_index,
+        ));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(pump(
+            stderr,
+            "stderr",
+            Arc::clone(&output),
+            app.clone(),
+            id.to_string(),
+            block_index,
+        ));
+    }
+
+    let mut timed_out = false;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if started.elapsed() >= timeout => {
+                timed_out = true;
+                kill(&mut child);
+                break child.wait().ok();
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(_) => {
+                kill(&mut child);
+                break None;
+            }
+        }
+    };
+    // Anything the block left running in the background goes too
+    kill(&mut child);
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let (output, truncated) = std::mem::take(&mut *output.lock().expect("run output mutex"));
+    Ok(CodeRun {
+        output,
+        exit_code: if timed_out {
+            None
+        } else {
+            status.and_then(|s| s.code())
+        },
+        timed_out,
+        truncated,
+        sandboxed,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
@@ -0,0 +1,171 @@
+//! Read-only safe mode, for inspecting a misbehaving vault or getting data
+//! out of it without changing anything.
+//!
+//! Safe mode is chosen at launch: the `--safe-mode` flag, `SCRATCH_SAFE_MODE=1`
+//! in the environment, or Shift held down while the app starts (macOS and
+//! Windows). It lasts until the app quits. The app then starts no file
+//! watcher, source watcher, automations or routines and takes no vault lock,
+//! and only the commands in `ALLOWED` run; every other command is refused
+//! with `permissionDenied` before it starts, so nothing reaches the vault or
+//! git.
+
+use crate::error::ScratchError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::Runtime;
+
+pub const FLAG: &str = "--safe-mode";
+const ENV_VAR: &str = "SCRATCH_SAFE_MODE";
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Commands that leave the vault, its git repository and the app config
+/// untouched. Anything missing here is refused in safe mode.
+const ALLOWED: &[&str] = &[
+    "get_notes_folder",
+    "resolve_vault_path",
+    "resolve_id",
+    "list_source_folders",
+    "list_notes",
+    "list_starred",
+    "read_note",
+    "get_backlinks",
+    "get_delete_impact",
+    "list_trash",
+    "list_restore_points",
+    "list_note_versions",
+    "read_note_version",
+    "get_history_storage_stats",
+    "list_folders",
+    "get_audit_log",
+    "get_settings",
+    "list_themes",
+    "get_theme",
+    "list_vault_templates",
+    "list_vaults",
+    "detect_importable_sources",
+    "find_orphan_notes",
+    "list_tags",
+    "list_notes_by_tag",
+    "parse_natural_date",
+    "get_routines",
+    "get_habit_data",
+    "get_goal_progress",
+    "get_project_dashboard",
+    "get_structured_content",
+    "get_reading_list",
+    "list_code_annotations",
+    "get_random_note",
+    "get_review_queue",
+    "get_due_cards",
+    "search_references",
+    "get_annotations",
+    "get_note_export_style",
+    "run_export_profile", // writes outside the vault only
+    "export_vault_zip",   // writes outside the vault only
+    "get_automation_status",
+    "get_retention_report",
+    "list_access_tokens",
+    "get_privacy_status",
+    "unlock_private_notes",
+    "lock_private_notes",
+    "preview_note_name",
+    "search_notes",
+    "start_file_watcher", // a no-op in safe mode
+    "stop_file_watcher",
+    "rebuild_search_index",
+    "get_vault_languages",
+    "get_instance_status",
+    "list_drafts",
+    "verify_vault", // without `repair`, see `allowed`
+    "get_disk_space",
+    "get_memory_usage",
+    "get_safe_mode",
+    "copy_to_clipboard",
+    "list_template_variables",
+    "list_assets",
+    "find_orphaned_assets",
+    "get_attachment_meta",
+    "open_folder_dialog",
+    "open_in_file_manager",
+    "open_url_safe",
+    "git_is_available",
+    "git_get_status",
+    "git_history",
+    "git_diff",
+    "ai_check_claude_cli",
+    "ai_check_codex_cli",
+    "ai_check_opencode_cli",
+    "ai_check_ollama_cli",
+    "read_file_direct",
+    "open_file_preview",
+    "get_cli_status",
+];
+
+/// Turn safe mode on if the launch asked for it. Call once, before the app
+/// is built.
+pub fn init(args: &[String]) {
+    let requested = args.iter().skip(1).any(|arg| arg == FLAG)
+        || std::env::var(ENV_VAR).is_ok_and(|value| value == "1")
+        || shift_held();
+    ACTIVE.store(requested, Ordering::Relaxed);
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+#[cfg(target_os = "macos")]
+fn shift_held() -> bool {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    }
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const SHIFT_MASK: u64 = 0x0002_0000;
+    // SAFETY: reads the current modifier flags; no pointers involved
+    unsafe { CGEventSourceFlagsState(COMBINED_SESSION_STATE) & SHIFT_MASK != 0 }
+}
+
+#[cfg(target_os = "windows")]
+fn shift_held() -> bool {
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetAsyncKeyState(key: i32) -> i16;
+    }
+    const VK_SHIFT: i32 = 0x10;
+    // SAFETY: reads the key state; no pointers involved
+    unsafe { GetAsyncKeyState(VK_SHIFT) < 0 }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn shift_held() -> bool {
+    false
+}
+
+fn allowed(command: &str, payload: &InvokeBody) -> bool {
+    if command == "verify_vault" {
+        return match payload {
+            InvokeBody::Json(args) => args.get("repair").and_then(|v| v.as_bool()) != Some(true),
+            InvokeBody::Raw(_) => false,
+        };
+    }
+    ALLOWED.contains(&command)
+}
+
+/// Wrap the app's command handler so that, in safe mode, commands outside
+/// `ALLOWED` are refused.
+pub fn guard<R: Runtime>(
+    commands: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if is_active() && !allowed(invoke.message.command(), invoke.message.payload()) {
+            invoke.resolver.reject(ScratchError::PermissionDenied {
+                path: None,
+                message: "Safe mode is on; changes are disabled".to_string(),
+            });
+            return true;
+        }
+        commands(invoke)
+    }
+}
@@ -0,0 +1,297 @@
+//! Starter structures for new vaults.
+//!
+//! `create_vault` lays out a new, empty folder from a template before opening
+//! it: folders, a few starter notes that explain the method (tagged, and
+//! linked to each other) and the vault settings that suit it. Zettelkasten
+//! and PARA turn folders on and pin a start note; Journal also sets the daily
+//! notes folder and a few habits and writes today's daily note; Blank only
+//! creates the vault. Nothing is written into a folder that already holds
+//! files.
+
+use crate::{abs_path_from_id, daily, frontmatter, Settings};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const JOURNAL_HABITS: &[&str] = &["Exercise", "Read", "Write"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VaultTemplate {
+    Zettelkasten,
+    Para,
+    Journal,
+    Blank,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultTemplateInfo {
+    pub template: VaultTemplate,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedVault {
+    pub path: String,
+    pub notes: Vec<String>,   // IDs of the starter notes
+    pub home: Option<String>, // the note to open first
+}
+
+struct StarterNote {
+    id: &'static str,
+    tags: &'static [&'static str],
+    body: &'static str, // starts with the title heading
+}
+
+const ZETTELKASTEN_FOLDERS: &[&str] = &["Fleeting", "Literature", "Permanent", "Index"];
+const ZETTELKASTEN_NOTES: &[StarterNote] = &[
+    StarterNote {
+        id: "Index/Start Here",
+        tags: &["index"],
+        body: "# Start Here
+
+This vault follows the Zettelkasten method: many small notes, each holding one idea, linked to each other.
+
+- **Fleeting** holds quick captures. Go through them often; turn the good ones into permanent notes and delete the rest. See [[Capture first, sort later]].
+- **Literature** holds notes on what you read, in your own words, with the source. See [[How to take literature notes]].
+- **Permanent** holds your own ideas, one per note, linked to related ones. See [[Write one idea per note]].
+- **Index** holds entry points like this one: lists of links into topics that grew large.
+",
+    },
+    StarterNote {
+        id: "Fleeting/Capture first, sort later",
+        tags: &["fleeting"],
+        body: "# Capture first, sort later
+
+A fleeting note is anything you want to keep for a moment: a thought, a quote, a question. Write it down without worrying about wording.
+
+Within a day or two, either rewrite it as a permanent note or delete it. Back to [[Start Here]].
+",
+    },
+    StarterNote {
+        id: "Literature/How to take literature notes",
+        tags: &["literature"],
+        body: "# How to take literature notes
+
+While reading, note what strikes you, briefly and in your own words, with where it came from (author, title, page).
+
+Afterwards, ask how each point relates to what you already know, and write that down as [[Write one idea per note|permanent notes]].
+",
+    },
+    StarterNote {
+        id: "Permanent/Write one idea per note",
+        tags: &["zettel"],
+        body: "# Write one idea per note
+
+A permanent note states one idea fully enough to understand it on its own, years later. Give it a title that says the idea.
+
+Link it to the notes it builds on, contradicts or explains; the links are where new ideas come from. Related: [[Capture first, sort later]].
+",
+    },
+];
+
+const PARA_FOLDERS: &[&str] = &["Projects", "Areas", "Resources", "Archive"];
+const PARA_NOTES: &[StarterNote] = &[
+    StarterNote {
+        id: "Start Here",
+        tags: &["para"],
+        body: "# Start Here
+
+This vault is organised with PARA, by how actionable things are:
+
+- **Projects**: work with a goal and a deadline, like [[Example Project]].
+- **Areas**: responsibilities you keep up over time, like [[Health]].
+- **Resources**: topics and material you may want later, like [[Reading List]].
+- **Archive**: anything from the other three that is done or no longer active.
+
+Move notes between folders as their status changes; finished projects go to the archive.
+",
+    },
+    StarterNote {
+        id: "Projects/Example Project",
+        tags: &["project"],
+        body: "# Example Project
+
+**Goal:** what done looks like.
+**Deadline:** when it should be done.
+
+## Tasks
+
+- [ ] Define the goal
+- [ ] List the first steps
+- [ ] Move this note to Archive when finished
+",
+    },
+    StarterNote {
+        id: "Areas/Health",
+        tags: &["area"],
+        body: "# Health
+
+An area has a standard to keep rather than an end date. Note the standard, and link the projects that serve it.
+
+- Standard:
+- Projects:
+",
+    },
+    StarterNote {
+        id: "Resources/Reading List",
+        tags: &["resource"],
+        body: "# Reading List
+
+- [ ] A book or article to read
+",
+    },
+];
+
+const JOURNAL_FOLDERS: &[&str] = &["Daily", "Reviews"];
+const JOURNAL_NOTES: &[StarterNote] = &[
+    StarterNote {
+        id: "Journal",
+        tags: &["journal"],
+        body: "# Journal
+
+Open today's daily note to write; each day gets its own note in **Daily**, with checkboxes for the habits you track.
+
+Prompts for when the page is blank:
+
+- What happened today that I want to remember?
+- What am I grateful for?
+- What is on my mind?
+- What would make tomorrow good?
+
+Once a week, look back in a note in **Reviews**, starting from [[Weekly Review]].
+",
+    },
+    StarterNote {
+        id: "Reviews/Weekly Review",
+        tags: &["review"],
+        body: "# Weekly Review
+
+- What went well this week?
+- What did not, and why?
+- What did I learn?
+- What do I want to focus on next week?
+",
+    },
+];
+
+pub fn templates() -> Vec<VaultTemplateInfo> {
+    vec![
+        VaultTemplateInfo {
+            template: VaultTemplate::Zettelkasten,
+            name: "Zettelkasten",
+            description: "Small linked notes, one idea each, from fleeting to permanent",
+        },
+        VaultTemplateInfo {
+            template: VaultTemplate::Para,
+            name: "PARA",
+            description: "Projects, areas, resources and an archive",
+        },
+        VaultTemplateInfo {
+            template: VaultTemplate::Journal,
+            name: "Journal",
+            description: "Daily notes with habits and weekly reviews",
+        },
+        VaultTemplateInfo {
+            template: VaultTemplate::Blank,
+            name: "Blank",
+            description: "An empty vault",
+        },
+    ]
+}
+
+fn layout(
+    template: VaultTemplate,
+) -> (
+    &'static [&'static str],
+    &'static [StarterNote],
+    Option<&'static str>,
+) {
+    match template {
+        VaultTemplate::Zettelkasten => (
+            ZETTELKASTEN_FOLDERS,
+            ZETTELKASTEN_NOTES,
+            Some("Index/Start Here"),
+        ),
+        VaultTemplate::Para => (PARA_FOLDERS, PARA_NOTES, Some("Start Here")),
+        VaultTemplate::Journal => (JOURNAL_FOLDERS, JOURNAL_NOTES, Some("Journal")),
+        VaultTemplate::Blank => (&[], &[], None),
+    }
+}
+
+/// Whether `path` can become a new vault: missing, or a folder with nothing
+/// in it but hidden files.
+pub fn check_empty(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+    if !path.is_dir() {
+        return Err("Choose a folder for the new vault".to_string());
+    }
+    let occupied = std::fs::read_dir(path)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .any(|entry| !entry.file_name().to_string_lossy().starts_with('.'));
+    if occupied {
+        return Err("The folder for a new vault must be empty".to_string());
+    }
+    Ok(())
+}
+
+/// Write a template's folders and starter notes into `notes_root`, and set
+/// the vault settings it comes with. Returns the starter note IDs and the
+/// note to open first. Blocking.
+pub fn scaffold(
+    notes_root: &Path,
+    template: VaultTemplate,
+    settings: &mut Settings,
+) -> Result<(Vec<String>, Option<String>), String> {
+    let (folders, notes, home) = layout(template);
+    for folder in folders {
+        std::fs::create_dir_all(notes_root.join(folder)).map_err(|e| e.to_string())?;
+    }
+
+    let mut ids = Vec::new();
+    let mut write = |id: &str, content: String| -> Result<(), String> {
+        let path = abs_path_from_id(notes_root, id)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", id, e))?;
+        ids.push(id.to_string());
+        Ok(())
+    };
+    for note in notes {
+        let tags = note.tags.iter().map(|t| t.to_string()).collect();
+        write(
+            note.id,
+            frontmatter::set(note.body, "tags", frontmatter::Value::List(tags)),
+        )?;
+    }
+
+    match template {
+        VaultTemplate::Zettelkasten | VaultTemplate::Para => {
+            settings.folders_enabled = Some(true);
+        }
+        VaultTemplate::Journal => {
+            settings.folders_enabled = Some(true);
+            settings.daily_notes_folder = Some(daily::folder(None));
+            settings.habits = Some(JOURNAL_HABITS.iter().map(|h| h.to_string()).collect());
+
+            let today = chrono::Local::now().date_naive();
+            let mut content = daily::new_content(today);
+            for habit in JOURNAL_HABITS {
+                content.push_str(&format!("- [ ] {}\n", habit));
+            }
+            write(&daily::Layout::default().note_id(today), content)?;
+        }
+        VaultTemplate::Blank => {}
+    }
+    if let Some(home) = home {
+        settings.pinned_note_ids = Some(vec![home.to_string()]);
+    }
+
+    Ok((ids, home.map(String::from)))
+}
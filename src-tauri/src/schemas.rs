@@ -0,0 +1,458 @@
+//! Structured content read from conventionally written notes.
+//!
+//! A content schema knows how some kind of note is usually laid out and turns
+//! it into typed JSON, for views and exports that need the parts (a shopping
+//! list from recipes, a reading list from book notes). Schemas are entries in
+//! `SCHEMAS`: a name, the tags that mark a note as one, and an extractor over
+//! the note's frontmatter, `key:: value` fields and heading sections; a new
+//! schema is a function and a line there. A note names its schema with
+//! `type:` frontmatter or one of those tags, or the caller asks for one.
+//!
+//! - `recipe`: `servings`, `prep`, `cook` and `total` times and `source`; an
+//!   "Ingredients" section, whose subheadings become groups and whose items
+//!   are split into quantity, unit and item; steps under "Instructions",
+//!   "Directions", "Method" or "Steps"; "Notes".
+//! - `book`: `author`, `isbn`, `year`, `pages`, `status`, `progress`, `rating`
+//!   (a number, `4/5` or stars), `started` and `finished`; "Summary", "Quotes"
+//!   or "Highlights", and "Notes" or "Takeaways" sections.
+
+use crate::frontmatter::{self, Frontmatter};
+use crate::tags;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+struct Schema {
+    name: &'static str,
+    tags: &'static [&'static str],
+    extract: fn(&Source) -> Option<StructuredContent>,
+}
+
+const SCHEMAS: &[Schema] = &[
+    Schema {
+        name: "recipe",
+        tags: &["recipe", "recipes"],
+        extract: recipe,
+    },
+    Schema {
+        name: "book",
+        tags: &["book", "books", "reading"],
+        extract: book,
+    },
+];
+
+// Whitespace separated, lowercase
+const UNITS: &str = "c cup cups tbsp tbs tablespoon tablespoons tsp teaspoon teaspoons \
+g gram grams kg kilogram kilograms mg ml milliliter milliliters millilitre millilitres \
+l liter liters litre litres dl cl oz ounce ounces lb lbs pound pounds pint pints pt \
+quart quarts qt gallon gallons pinch pinches dash dashes clove cloves can cans slice slices \
+piece pieces stick sticks bunch bunches handful handfuls sprig sprigs package packages pkg";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "schema", rename_all = "camelCase")]
+pub enum StructuredContent {
+    Recipe(Recipe),
+    Book(Book),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ingredient {
+    pub text: String,          // as written
+    pub quantity: Option<f64>, // upper end of a range
+    pub unit: Option<String>,  // lowercase, as written
+    pub item: String,          // the rest, e.g. "plain flour, sifted"
+    pub group: Option<String>, // subheading inside Ingredients
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recipe {
+    pub title: String,
+    pub servings: Option<String>,
+    pub prep_time: Option<String>,
+    pub cook_time: Option<String>,
+    pub total_time: Option<String>,
+    pub source: Option<String>,
+    pub ingredients: Vec<Ingredient>,
+    pub steps: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Book {
+    pub title: String,
+    pub author: Option<String>,
+    pub isbn: Option<String>,
+    pub year: Option<String>,
+    pub pages: Option<String>,
+    pub status: Option<String>, // as written, e.g. "to-read" or "finished"
+    pub progress: Option<String>, // as written, e.g. "120", "120/350" or "35%"
+    pub rating: Option<f64>,
+    pub started: Option<String>,
+    pub finished: Option<String>,
+    pub summary: Option<String>,
+    pub quotes: Vec<String>,
+    pub takeaways: Vec<String>,
+}
+
+/// A run of lines under one heading; the lines before the first heading have
+/// level 0 and no heading.
+struct Section<'a> {
+    heading: String,
+    level: usize,
+    lines: Vec<&'a str>,
+}
+
+struct Source<'a> {
+    title: String,
+    frontmatter: Frontmatter,
+    fields: Vec<(String, String)>, // `key:: value` lines, keys lowercased
+    sections: Vec<Section<'a>>,
+}
+
+impl Source<'_> {
+    /// The first of `keys` set in frontmatter or as a `key:: value` field.
+    fn field(&self, keys: &[&str]) -> Option<String> {
+        keys.iter().find_map(|key| {
+            self.frontmatter
+                .get(key)
+                .map(|v| v.trim().to_string())
+                .or_else(|| {
+                    self.fields
+                        .iter()
+                        .find(|(k, _)| k == key)
+                        .map(|(_, v)| v.clone())
+                })
+                .filter(|v| !v.is_empty())
+        })
+    }
+}
+
+fn inline_field_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*(?:[-*+]\s+)?([A-Za-z][\w ]*?)::\s+(.+?)\s*$").unwrap())
+}
+
+fn list_item_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*(?:[-*+]|\d+[.)])\s+(?:\[[ xX]\]\s+)?(.+?)\s*$").unwrap())
+}
+
+fn quantity_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    const NUMBER: &str = r"\d+\s+\d+/\d+|\d+/\d+|\d+(?:[.,]\d+)?[½⅓⅔¼¾⅛]?|[½⅓⅔¼¾⅛]";
+    RE.get_or_init(|| {
+        Regex::new(&format!(
+            r"^\s*({n})(?:\s*(?:-|–|to)\s*({n}))?\s*",
+            n = NUMBER
+        ))
+        .unwrap()
+    })
+}
+
+fn parse<'a>(title: &str, content: &'a str) -> Source<'a> {
+    let mut sections = vec![Section {
+        heading: String::new(),
+        level: 0,
+        lines: Vec::new(),
+    }];
+    let mut fields = Vec::new();
+    let mut in_fence = false;
+    for line in frontmatter::body(content).lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+                sections.push(Section {
+                    heading: trimmed[level..].trim().trim_end_matches(':').to_string(),
+                    level,
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+            if let Some(caps) = inline_field_re().captures(line) {
+                fields.push((caps[1].trim().to_lowercase(), caps[2].to_string()));
+            }
+        }
+        sections
+            .last_mut()
+            .expect("preamble section")
+            .lines
+            .push(line);
+    }
+    Source {
+        title: title.to_string(),
+        frontmatter: Frontmatter::parse(content),
+        fields,
+        sections,
+    }
+}
+
+/// Whether a heading starts with one of `names` ("Ingredients (serves 4)").
+fn heading_is(heading: &str, names: &[&str]) -> bool {
+    let heading = heading
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    names.iter().any(|name| heading.starts_with(name))
+}
+
+fn list_items(lines: &[&str]) -> Vec<String> {
+    lines
+        .iter()
+        .filter_map(|line| list_item_re().captures(line))
+        .map(|caps| caps[1].to_string())
+        .filter(|item| !inline_field_re().is_match(item))
+        .collect()
+}
+
+/// Blank-line separated paragraphs, each joined into one line.
+fn paragraphs(lines: &[&str]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in lines.iter().chain(std::iter::once(&"")) {
+        let line = line.trim();
+        if line.is_empty() {
+            if !current.is_empty() {
+                out.push(current.join(" "));
+                current.clear();
+            }
+        } else if !inline_field_re().is_match(line) {
+            current.push(line.trim_start_matches('>').trim());
+        }
+    }
+    out
+}
+
+fn items_or_paragraphs(lines: &[&str]) -> Vec<String> {
+    let items = list_items(lines);
+    if items.is_empty() {
+        paragraphs(lines)
+    } else {
+        items
+    }
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if let Some((whole, fraction)) = text.split_once(char::is_whitespace) {
+        return Some(parse_number(whole)? + parse_number(fraction)?);
+    }
+    if let Some((numerator, denominator)) = text.split_once('/') {
+        let numerator: f64 = numerator.parse().ok()?;
+        let denominator: f64 = denominator.parse().ok()?;
+        return (denominator != 0.0).then_some(numerator / denominator);
+    }
+    let vulgar = |c: char| match c {
+        '½' => Some(0.5),
+        '⅓' => Some(1.0 / 3.0),
+        '⅔' => Some(2.0 / 3.0),
+        '¼' => Some(0.25),
+        '¾' => Some(0.75),
+        '⅛' => Some(0.125),
+        _ => None,
+    };
+    match text.chars().last().and_then(vulgar) {
+        Some(fraction) => {
+            let whole = &text[..text.len() - text.chars().last()?.len_utf8()];
+            let whole = if whole.is_empty() {
+                0.0
+            } else {
+                whole.parse().ok()?
+            };
+            Some(whole + fraction)
+        }
+        None => text.replace(',', ".").parse().ok(),
+    }
+}
+
+fn parse_ingredient(text: &str, group: Option<&str>) -> Ingredient {
+    let mut rest = text;
+    let mut quantity = None;
+    if let Some(caps) = quantity_re().captures(text) {
+        let upper = caps.get(2).or_else(|| caps.get(1)).map(|m| m.as_str());
+        quantity = upper.and_then(parse_number);
+        if quantity.is_some() {
+            rest = &text[caps[0].len()..];
+        }
+    }
+    let mut unit = None;
+    if quantity.is_some() {
+        let word: String = rest.chars().take_while(|c| c.is_alphabetic()).collect();
+        let candidate = word.to_lowercase();
+        let after = &rest[word.len()..];
+        if UNITS.split_whitespace().any(|u| u == candidate)
+            && !after.starts_with(|c: char| c.is_alphanumeric())
+        {
+            unit = Some(candidate);
+            rest = after.strip_prefix('.').unwrap_or(after);
+        }
+    }
+    let rest = rest.trim();
+    let item = rest.strip_prefix("of ").unwrap_or(rest).trim();
+    Ingredient {
+        text: text.to_string(),
+        quantity,
+        unit,
+        item: item.to_string(),
+        group: group.map(String::from),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum RecipePart {
+    Ingredients,
+    Steps,
+    Notes,
+}
+
+fn recipe(source: &Source) -> Option<StructuredContent> {
+    let mut ingredients = Vec::new();
+    let mut steps = Vec::new();
+    let mut notes = Vec::new();
+    // The recipe section being read and its heading level
+    let mut current: Option<(RecipePart, usize)> = None;
+    for section in &source.sections {
+        let group = match current {
+            Some((_, level)) if section.level > level => Some(section.heading.as_str()),
+            _ => {
+                current = if heading_is(&section.heading, &["ingredient"]) {
+                    Some((RecipePart::Ingredients, section.level))
+                } else if heading_is(
+                    &section.heading,
+                    &["instruction", "direction", "method", "step", "preparation"],
+                ) {
+                    Some((RecipePart::Steps, section.level))
+                } else if heading_is(&section.heading, &["note", "tip"]) {
+                    Some((RecipePart::Notes, section.level))
+                } else {
+                    None
+                };
+                None
+            }
+        };
+        match current.map(|(part, _)| part) {
+            Some(RecipePart::Ingredients) => ingredients.extend(
+                list_items(&section.lines)
+                    .iter()
+                    .map(|item| parse_ingredient(item, group)),
+            ),
+            Some(RecipePart::Steps) => steps.extend(items_or_paragraphs(&section.lines)),
+            Some(RecipePart::Notes) => notes.extend(items_or_paragraphs(&section.lines)),
+            None => {}
+        }
+    }
+    if ingredients.is_empty() && steps.is_empty() {
+        return None;
+    }
+    Some(StructuredContent::Recipe(Recipe {
+        title: source.title.clone(),
+        servings: source.field(&["servings", "serves", "yield"]),
+        prep_time: source.field(&["prep_time", "prep", "preptime"]),
+        cook_time: source.field(&["cook_time", "cook", "cooktime"]),
+        total_time: source.field(&["total_time", "total", "totaltime"]),
+        source: source.field(&["source", "url"]),
+        ingredients,
+        steps,
+        notes,
+    }))
+}
+
+/// A rating as a number, `4/5`, or stars.
+fn parse_rating(text: &str) -> Option<f64> {
+    let stars = text.chars().filter(|c| matches!(c, '★' | '⭐')).count();
+    if stars > 0 {
+        return Some(stars as f64);
+    }
+    let value = text.split('/').next()?.trim();
+    value.replace(',', ".").parse().ok()
+}
+
+fn book(source: &Source) -> Option<StructuredContent> {
+    let mut summary = Vec::new();
+    let mut quotes = Vec::new();
+    let mut takeaways = Vec::new();
+    for section in &source.sections {
+        if heading_is(&section.heading, &["summary", "review"]) {
+            summary.extend(paragraphs(&section.lines));
+        } else if heading_is(&section.heading, &["quote", "highlight"]) {
+            quotes.extend(items_or_paragraphs(&section.lines));
+        } else if heading_is(
+            &section.heading,
+            &["note", "takeaway", "key idea", "lesson"],
+        ) {
+            takeaways.extend(items_or_paragraphs(&section.lines));
+        }
+    }
+    let author = source.field(&["author", "authors", "by"]);
+    let isbn = source.field(&["isbn"]);
+    if author.is_none() && isbn.is_none() && summary.is_empty() && quotes.is_empty() {
+        return None;
+    }
+    Some(StructuredContent::Book(Book {
+        title: source
+            .frontmatter
+            .get("title")
+            .map(String::from)
+            .unwrap_or_else(|| source.title.clone()),
+        author,
+        isbn,
+        year: source.field(&["year", "published"]),
+        pages: source.field(&["pages"]),
+        status: source.field(&["status"]),
+        progress: source.field(&["progress"]),
+        rating: source.field(&["rating"]).as_deref().and_then(parse_rating),
+        started: source.field(&["started", "start_date"]),
+        finished: source.field(&["finished", "finish_date", "date_read", "read"]),
+        summary: (!summary.is_empty()).then(|| summary.join("\n\n")),
+        quotes,
+        takeaways,
+    }))
+}
+
+pub fn names() -> Vec<&'static str> {
+    SCHEMAS.iter().map(|schema| schema.name).collect()
+}
+
+fn declared_schema(content: &str) -> Option<&'static Schema> {
+    let declared = Frontmatter::parse(content)
+        .get("type")
+        .map(|t| t.trim().to_lowercase());
+    let note_tags = tags::extract_tags(content);
+    SCHEMAS.iter().find(|s| {
+        declared.as_deref() == Some(s.name)
+            || s.tags.iter().any(|tag| note_tags.iter().any(|t| t == tag))
+    })
+}
+
+/// The schema a note names with `type:` or a tag.
+pub fn declared(content: &str) -> Option<&'static str> {
+    declared_schema(content).map(|schema| schema.name)
+}
+
+/// Read a note as `schema`, or as the schema it names with `type:` or a tag.
+pub fn extract(
+    title: &str,
+    content: &str,
+    schema: Option<&str>,
+) -> Result<StructuredContent, String> {
+    let chosen = match schema.map(str::trim) {
+        Some(name) => SCHEMAS
+            .iter()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown content schema \"{}\" (known: {})",
+                    name,
+                    names().join(", ")
+                )
+            })?,
+        None => declared_schema(content)
+            .ok_or("The note does not name a content schema; set `type:` or pass one")?,
+    };
+    (chosen.extract)(&parse(title, content))
+        .ok_or_else(|| format!("The note does not read as a {}", chosen.name))
+}
@@ -0,0 +1,144 @@
+//! Screenshots straight into a note.
+//!
+//! `capture` runs the platform's own screenshot tool and waits for the user
+//! to finish: `screencapture` on macOS; gnome-screenshot, spectacle, scrot or
+//! grim + slurp on Linux, whichever is installed; the Snipping Tool overlay on
+//! Windows, whose result is picked up from the clipboard. A cancelled capture
+//! leaves no file behind.
+
+use serde::Deserialize;
+use std::path::Path;
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CaptureMode {
+    Region,
+    Window,
+    Screen,
+}
+
+/// Capture into `target` (a PNG path). Returns false when the user cancelled.
+/// Blocking until the capture is done.
+pub fn capture(app: &AppHandle, mode: CaptureMode, target: &Path) -> Result<bool, String> {
+    capture_to(app, mode, target)?;
+    Ok(target.is_file() && std::fs::metadata(target).is_ok_and(|m| m.len() > 0))
+}
+
+#[cfg(target_os = "macos")]
+fn capture_to(_app: &AppHandle, mode: CaptureMode, target: &Path) -> Result<(), String> {
+    let mut cmd = std::process::Command::new("screencapture");
+    cmd.arg("-x"); // no sound
+    match mode {
+        CaptureMode::Region => cmd.args(["-i", "-s"]),
+        CaptureMode::Window => cmd.args(["-i", "-w"]),
+        CaptureMode::Screen => &mut cmd,
+    };
+    cmd.arg(target)
+        .status()
+        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn capture_to(_app: &AppHandle, mode: CaptureMode, target: &Path) -> Result<(), String> {
+    use std::process::Command;
+
+    let path = std::env::var("PATH").unwrap_or_default();
+    let has = |tool: &str| crate::check_cli_exists(tool, &path).unwrap_or(false);
+    let target_str = target.to_string_lossy().into_owned();
+
+    let status = if has("gnome-screenshot") {
+        let flag = match mode {
+            CaptureMode::Region => Some("-a"),
+            CaptureMode::Window => Some("-w"),
+            CaptureMode::Screen => None,
+        };
+        Command::new("gnome-screenshot")
+            .args(flag)
+            .args(["-f", &target_str])
+            .status()
+    } else if has("spectacle") {
+        let flag = match mode {
+            CaptureMode::Region => "-r",
+            CaptureMode::Window => "-a",
+            CaptureMode::Screen => "-f",
+        };
+        Command::new("spectacle")
+            .args(["-b", "-n", flag, "-o", &target_str])
+            .status()
+    } else if has("scrot") {
+        let flag = match mode {
+            CaptureMode::Region => Some("-s"),
+            CaptureMode::Window => Some("-u"),
+            CaptureMode::Screen => None,
+        };
+        Command::new("scrot").args(flag).arg(&target_str).status()
+    } else if has("grim") {
+        let geometry = match mode {
+            CaptureMode::Region if has("slurp") => {
+                let output = Command::new("slurp")
+                    .output()
+                    .map_err(|e| format!("Failed to run slurp: {}", e))?;
+                if !output.status.success() {
+                    return Ok(()); // selection cancelled
+                }
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+            CaptureMode::Screen => None,
+            _ => return Err("This capture mode needs slurp and a window-aware tool".to_string()),
+        };
+        let mut cmd = Command::new("grim");
+        if let Some(geometry) = geometry {
+            cmd.args(["-g", &geometry]);
+        }
+        cmd.arg(&target_str).status()
+    } else {
+        return Err(
+            "No screenshot tool found (install gnome-screenshot, spectacle, scrot or grim)"
+                .to_string(),
+        );
+    };
+    status.map_err(|e| format!("Failed to run the screenshot tool: {}", e))?;
+    Ok(())
+}
+
+/// The Snipping Tool overlay only copies its result, so wait for a new image
+/// on the clipboard.
+#[cfg(target_os = "windows")]
+fn capture_to(app: &AppHandle, _mode: CaptureMode, target: &Path) -> Result<(), String> {
+    use std::time::{Duration, Instant};
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    const WAIT: Duration = Duration::from_secs(120);
+    const POLL: Duration = Duration::from_millis(400);
+
+    let current = |app: &AppHandle| {
+        app.clipboard()
+            .read_image()
+            .ok()
+            .map(|image| (image.width(), image.height(), image.rgba().to_vec()))
+    };
+    let before = current(app);
+    crate::no_window_cmd("explorer")
+        .arg("ms-screenclip:")
+        .spawn()
+        .map_err(|e| format!("Failed to open the Snipping Tool: {}", e))?;
+
+    let started = Instant::now();
+    while started.elapsed() < WAIT {
+        std::thread::sleep(POLL);
+        if let Some((width, height, rgba)) =
+            current(app).filter(|image| Some(image) != before.as_ref())
+        {
+            let png = crate::clipboard::encode_png(width, height, &rgba)?;
+            return std::fs::write(target, png).map_err(|e| e.to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn capture_to(_app: &AppHandle, _mode: CaptureMode, _target: &Path) -> Result<(), String> {
+    Err("Unsupported platform".to_string())
+}
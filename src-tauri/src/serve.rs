@@ -0,0 +1,706 @@
+//! Serve the vault as rendered HTML over HTTP, on this machine or, when
+//! asked, the local network.
+//!
+//! A deliberately small HTTP/1.1 server: one request per connection, GET for
+//! pages and (when not read-only) POST to save or append to a note. Private
+//! notes (while privacy mode is active) and locked notes are never served.
+//! Notes can hold raw HTML, so pages go out with a Content-Security-Policy
+//! that runs no scripts.
+//!
+//! Requests may carry a `Bearer` access token (see `capabilities`), whose
+//! scope limits what they can do; otherwise the server's own mode and basic
+//! auth apply. Writing always takes a token or the basic auth credentials:
+//! a request with neither may only read. Every route is checked against the
+//! scope before it runs.
+//!
+//! `POST /clip` is the browser extension's endpoint: it takes a page or a
+//! selection as JSON, makes a note of it with the web clipper and answers
+//! with the note's ID and `scratch://` link. It only accepts tokens issued
+//! for the extension surface, which may only create notes, and a read-only
+//! server refuses it; from a browser, only extension pages may call it.
+
+use crate::capabilities::{self, Operation, Scope, Surface};
+use crate::{
+    abs_path_from_id, active_private_folders, audit, clipboard, collab, extract_title,
+    git_auto_commit, is_private_note, note_deep_link, note_exposure, rel_path_from_id, render,
+    scan_notes, snapshot_history_blocking, sort_captured_note, write_note_content_from, AppState,
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, Semaphore};
+
+const MAX_HEADER_LEN: usize = 64 * 1024;
+const MAX_BODY_LEN: usize = 10 * 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_CONNECTIONS: usize = 32;
+// Notes may hold raw HTML; pages run no scripts and load nothing from elsewhere
+const PAGE_POLICY: &str =
+    "default-src 'none'; style-src 'unsafe-inline'; img-src 'self'; form-action 'self'; frame-ancestors 'none'";
+const EXTENSION_ORIGINS: &[&str] = &[
+    "chrome-extension://",
+    "moz-extension://",
+    "safari-web-extension://",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServeInfo {
+    pub url: String,
+    pub readonly: bool,
+    pub auth: bool,
+    pub lan: bool,
+}
+
+#[derive(Clone)]
+pub struct ServeOptions {
+    pub readonly: bool,
+    pub credentials: Option<String>, // "user:password" for basic auth
+    pub lan: bool,                   // listen on all interfaces, not just this machine
+}
+
+/// A running server. Dropping it stops accepting connections.
+pub struct VaultServer {
+    pub info: ServeInfo,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Drop for VaultServer {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    extra_headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn html(body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            extra_headers: vec![("Content-Security-Policy", PAGE_POLICY.to_string())],
+            body: body.into_bytes(),
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            extra_headers: Vec::new(),
+            body: status.as_bytes().to_vec(),
+        }
+    }
+
+    fn json(status: &'static str, body: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            extra_headers: Vec::new(),
+            body: serde_json::to_vec(body).unwrap_or_default(),
+        }
+    }
+
+    fn no_content() -> Self {
+        Self {
+            status: "204 No Content",
+            content_type: "text/plain; charset=utf-8",
+            extra_headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn redirect(location: String) -> Self {
+        Self {
+            status: "303 See Other",
+            content_type: "text/plain; charset=utf-8",
+            extra_headers: vec![("Location", location)],
+            body: Vec::new(),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\nCache-Control: no-store\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        );
+        for (name, value) in &self.extra_headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let mut out = head.into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEADER_LEN {
+            return None;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_LEN {
+        return None;
+    }
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+fn authorized(request: &Request, credentials: &str) -> bool {
+    request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok())
+        .map(|decoded| decoded == credentials.as_bytes())
+        .unwrap_or(false)
+}
+
+fn bearer_token(request: &Request) -> Option<&str> {
+    request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// What a request may do: a bearer token's scope, or the server's default
+/// once basic auth passes. Without either a request may only read.
+fn request_scope(folder: &Path, options: &ServeOptions, request: &Request) -> Result<Scope, Response> {
+    if let Some(token) = bearer_token(request) {
+        return capabilities::scope_for(folder, Surface::Http, token)
+            .ok_or_else(|| Response::error("401 Unauthorized"));
+    }
+    let Some(ref credentials) = options.credentials else {
+        return Ok(Scope::ReadOnly);
+    };
+    if !authorized(request, credentials) {
+        let mut response = Response::error("401 Unauthorized");
+        response
+            .extra_headers
+            .push(("WWW-Authenticate", "Basic realm=\"Scratch\"".to_string()));
+        return Err(response);
+    }
+    Ok(if options.readonly { Scope::ReadOnly } else { Scope::Full })
+}
+
+enum Route {
+    Index,
+    View(String),
+    Edit(String),
+    Save(String),
+    Append(String),
+}
+
+impl Route {
+    fn parse(method: &str, path: &str) -> Result<Self, Response> {
+        let decode = |raw: &str| {
+            urlencoding::decode(raw)
+                .map(|id| id.into_owned())
+                .map_err(|_| Response::error("400 Bad Request"))
+        };
+        match (method, path) {
+            ("GET", "/") => Ok(Route::Index),
+            ("GET", path) if path.starts_with("/note/") => decode(&path["/note/".len()..]).map(Route::View),
+            ("GET", path) if path.starts_with("/edit/") => decode(&path["/edit/".len()..]).map(Route::Edit),
+            ("POST", path) if path.starts_with("/note/") => decode(&path["/note/".len()..]).map(Route::Save),
+            ("POST", path) if path.starts_with("/append/") => {
+                decode(&path["/append/".len()..]).map(Route::Append)
+            }
+            ("GET", _) | ("POST", _) => Err(Response::error("404 Not Found")),
+            _ => Err(Response::error("405 Method Not Allowed")),
+        }
+    }
+
+    fn operation(&self) -> Operation {
+        match self {
+            Route::Index | Route::View(_) => Operation::Read,
+            Route::Append(_) => Operation::Append,
+            Route::Edit(_) | Route::Save(_) => Operation::Write,
+        }
+    }
+}
+
+fn note_href(id: &str) -> String {
+    format!("/note/{}", urlencoding::encode(id))
+}
+
+/// Whether a note may be served, given the vault's privacy settings.
+fn servable(state: &AppState, private_folders: Option<&[String]>, id: &str, content: &str) -> bool {
+    if let Some(private) = private_folders {
+        if is_private_note(id, content, private) {
+            return false;
+        }
+    }
+    let policy = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.locked_notes.clone().unwrap_or_default()
+    };
+    !note_exposure(content, &policy).locked
+}
+
+fn index_page(state: &AppState, folder: &Path) -> Response {
+    let private_folders = active_private_folders(state);
+    let mut notes: Vec<_> = scan_notes(folder)
+        .into_iter()
+        .filter(|n| servable(state, private_folders.as_deref(), &n.id, &n.content))
+        .collect();
+    notes.sort_by_key(|n| Reverse(n.modified));
+
+    let mut list = String::from("<h1>Notes</h1>\n<ul>\n");
+    for note in &notes {
+        list.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            note_href(&note.id),
+            render::escape_html(&note.title)
+        ));
+    }
+    list.push_str("</ul>\n");
+
+    // Render the listing as the page body; it is already HTML
+    let page = render::render_page("Notes", "", &list, None, None);
+    Response::html(page)
+}
+
+fn note_page(state: &AppState, folder: &Path, id: &str, can_edit: bool, edit: bool) -> Response {
+    let Ok(path) = abs_path_from_id(folder, id) else {
+        return Response::error("400 Bad Request");
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Response::error("404 Not Found");
+    };
+    let private_folders = active_private_folders(state);
+    if !servable(state, private_folders.as_deref(), id, &content) {
+        return Response::error("404 Not Found");
+    }
+
+    let title = extract_title(&content);
+    let mut nav = String::from("<p><a href=\"/\">&larr; All notes</a>");
+    if can_edit && !edit {
+        nav.push_str(&format!(" &middot; <a href=\"/edit/{}\">Edit</a>", urlencoding::encode(id)));
+    }
+    nav.push_str("</p>\n");
+
+    if edit {
+        nav.push_str(&format!(
+            "<form method=\"post\" action=\"{}\">\n\
+<textarea name=\"content\" style=\"width:100%;height:70vh;font-family:ui-monospace,monospace\">{}</textarea>\n\
+<p><button type=\"submit\">Save</button></p>\n</form>\n",
+            note_href(id),
+            render::escape_html(&content)
+        ));
+        return Response::html(render::render_page(&title, "", &nav, None, None));
+    }
+    let class_styles = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings.export_class_styles.clone().unwrap_or_default()
+    };
+    Response::html(render::render_page(
+        &title,
+        &content,
+        &nav,
+        None,
+        Some(&class_styles),
+    ))
+}
+
+/// Reject cross-site form posts. Browsers send basic auth with any request
+/// to the server, so a write made with it must carry an `Origin` naming the
+/// server; bearer tokens are never sent on their own.
+fn same_origin(request: &Request) -> bool {
+    if bearer_token(request).is_some() {
+        return true;
+    }
+    match (request.header("origin"), request.header("host")) {
+        (Some(origin), Some(host)) => origin.split("://").nth(1) == Some(host),
+        _ => false,
+    }
+}
+
+/// Read a note that may be written through the server.
+fn writable_note(state: &AppState, folder: &Path, id: &str) -> Result<String, Response> {
+    let path = abs_path_from_id(folder, id).map_err(|_| Response::error("400 Bad Request"))?;
+    let existing = std::fs::read_to_string(&path).map_err(|_| Response::error("404 Not Found"))?;
+    let private_folders = active_private_folders(state);
+    if !servable(state, private_folders.as_deref(), id, &existing) {
+        return Err(Response::error("404 Not Found"));
+    }
+    Ok(existing)
+}
+
+/// Write a note the way the app saves one: atomically, audited, indexed,
+/// snapshotted in its history and auto-committed. Callers hold the note's
+/// lock.
+fn write_note(
+    app: &AppHandle,
+    folder: &Path,
+    id: &str,
+    action: audit::Action,
+    existing: &str,
+    content: String,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let note = tauri::async_runtime::block_on(write_note_content_from(
+        &state,
+        audit::Surface::Http,
+        Some(action),
+        folder,
+        id,
+        content,
+    ))?;
+    state
+        .notes_cache
+        .write()
+        .expect("cache write lock")
+        .remove(id);
+    snapshot_history_blocking(app, folder, id, Some(existing), &note.content);
+    git_auto_commit(
+        &state,
+        folder,
+        vec![rel_path_from_id(id)],
+        format!("Update {}", id),
+    );
+    Ok(())
+}
+
+fn form_field(request: &Request, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(&request.body)
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.replace("\r\n", "\n"))
+}
+
+fn save_note(
+    app: &AppHandle,
+    state: &AppState,
+    folder: &Path,
+    id: &str,
+    request: &Request,
+) -> Response {
+    if !same_origin(request) {
+        return Response::error("403 Forbidden");
+    }
+    let _guard = state.note_locks.blocking_lock(folder, &[id]);
+    let existing = match writable_note(state, folder, id) {
+        Ok(note) => note,
+        Err(response) => return response,
+    };
+    let Some(content) = form_field(request, "content") else {
+        return Response::error("400 Bad Request");
+    };
+    if write_note(app, folder, id, audit::Action::Save, &existing, content).is_err() {
+        return Response::error("500 Internal Server Error");
+    }
+    Response::redirect(note_href(id))
+}
+
+/// Add the `text` form field to the end of a note, on its own line.
+fn append_note(
+    app: &AppHandle,
+    state: &AppState,
+    folder: &Path,
+    id: &str,
+    request: &Request,
+) -> Response {
+    if !same_origin(request) {
+        return Response::error("403 Forbidden");
+    }
+    let _guard = state.note_locks.blocking_lock(folder, &[id]);
+    let existing = match writable_note(state, folder, id) {
+        Ok(note) => note,
+        Err(response) => return response,
+    };
+    let mut content = existing.clone();
+    let Some(text) = form_field(request, "text").filter(|t| !t.trim().is_empty()) else {
+        return Response::error("400 Bad Request");
+    };
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&text);
+    if !text.ends_with('\n') {
+        content.push('\n');
+    }
+    if write_note(app, folder, id, audit::Action::Append, &existing, content).is_err() {
+        return Response::error("500 Internal Server Error");
+    }
+    Response::no_content()
+}
+
+#[derive(Deserialize)]
+struct ClipRequest {
+    url: Option<String>,
+    title: Option<String>,
+    html: Option<String>,      // the whole page
+    selection: Option<String>, // HTML of the selection, clipped instead of the page
+    folder: Option<String>,    // vault root if absent
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipResponse {
+    id: String,
+    deep_link: String,
+}
+
+/// The `Origin` of a request to `/clip`: None from outside a browser, an
+/// error for anything but an extension page.
+fn clip_origin(request: &Request) -> Result<Option<String>, Response> {
+    let Some(origin) = request.header("origin") else {
+        return Ok(None);
+    };
+    if EXTENSION_ORIGINS
+        .iter()
+        .any(|scheme| origin.starts_with(scheme))
+    {
+        Ok(Some(origin.to_string()))
+    } else {
+        Err(Response::error("403 Forbidden"))
+    }
+}
+
+fn clip_note(app: &AppHandle, folder: &Path, request: &Request) -> Response {
+    let Some(scope) = bearer_token(request)
+        .and_then(|token| capabilities::scope_for(folder, Surface::Extension, token))
+    else {
+        return Response::error("401 Unauthorized");
+    };
+    if capabilities::authorize(scope, Operation::Create).is_err() {
+        return Response::error("403 Forbidden");
+    }
+    let Ok(clip) = serde_json::from_slice::<ClipRequest>(&request.body) else {
+        return Response::error("400 Bad Request");
+    };
+    let Some(html) = clip
+        .selection
+        .filter(|s| !s.trim().is_empty())
+        .or(clip.html)
+        .filter(|h| !h.trim().is_empty())
+    else {
+        return Response::error("400 Bad Request");
+    };
+    match clipboard::create_web_note(
+        app,
+        &html,
+        clip.url.as_deref(),
+        clip.title.as_deref(),
+        clip.folder.as_deref(),
+        audit::Surface::Http,
+    ) {
+        Ok(note) => {
+            let id = sort_captured_note(app, note.id);
+            Response::json(
+                "201 Created",
+                &ClipResponse {
+                    deep_link: note_deep_link(&id),
+                    id,
+                },
+            )
+        }
+        Err(_) => Response::error("500 Internal Server Error"),
+    }
+}
+
+/// `/clip`, with the CORS headers an extension page needs.
+fn clip(app: &AppHandle, folder: &Path, readonly: bool, request: &Request) -> Response {
+    let origin = match clip_origin(request) {
+        Ok(origin) => origin,
+        Err(response) => return response,
+    };
+    let mut response = match request.method.as_str() {
+        "OPTIONS" => {
+            let mut response = Response::no_content();
+            response.extra_headers.extend([
+                ("Access-Control-Allow-Methods", "POST".to_string()),
+                ("Access-Control-Allow-Headers", "Authorization, Content-Type".to_string()),
+                ("Access-Control-Max-Age", "600".to_string()),
+            ]);
+            response
+        }
+        "POST" if readonly => Response::error("403 Forbidden"),
+        "POST" => clip_note(app, folder, request),
+        _ => Response::error("405 Method Not Allowed"),
+    };
+    if let Some(origin) = origin {
+        response
+            .extra_headers
+            .push(("Access-Control-Allow-Origin", origin));
+        response.extra_headers.push(("Vary", "Origin".to_string()));
+    }
+    response
+}
+
+fn respond(app: &AppHandle, options: &ServeOptions, request: &Request) -> Response {
+    let state = app.state::<AppState>();
+    let Some(folder) = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone()
+    else {
+        return Response::error("503 Service Unavailable");
+    };
+    let folder = Path::new(&folder);
+
+    // Answers its own preflight and checks its own token, before basic auth
+    if request.path == "/clip" {
+        return clip(app, folder, options.readonly, request);
+    }
+
+    let scope = match request_scope(folder, options, request) {
+        Ok(scope) => scope,
+        Err(response) => return response,
+    };
+    let route = match Route::parse(&request.method, &request.path) {
+        Ok(route) => route,
+        Err(response) => return response,
+    };
+    // A read-only server stays read-only whatever the token allows
+    let operation = route.operation();
+    if capabilities::authorize(scope, operation).is_err()
+        || (options.readonly && operation != Operation::Read)
+    {
+        return Response::error("403 Forbidden");
+    }
+
+    let can_edit = !options.readonly && scope.allows(Operation::Write);
+    match route {
+        Route::Index => index_page(&state, folder),
+        Route::View(id) => note_page(&state, folder, &id, can_edit, false),
+        Route::Edit(id) => note_page(&state, folder, &id, can_edit, true),
+        Route::Save(id) => save_note(app, &state, folder, &id, request),
+        Route::Append(id) => append_note(app, &state, folder, &id, request),
+    }
+}
+
+async fn handle_connection(app: AppHandle, mut stream: TcpStream, options: ServeOptions) {
+    let Ok(Some(request)) = tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await
+    else {
+        return;
+    };
+    let response = tauri::async_runtime::spawn_blocking(move || respond(&app, &options, &request))
+        .await
+        .unwrap_or_else(|_| Response::error("500 Internal Server Error"));
+    let _ = stream.write_all(&response.into_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Start serving on `port` (0 picks a free port), on all interfaces with
+/// `lan` and on the loopback interface otherwise. A writable server needs
+/// basic auth credentials. Connections past `MAX_CONNECTIONS` are dropped,
+/// and one that has not sent its request within `READ_TIMEOUT` is closed.
+pub async fn start(app: AppHandle, port: u16, options: ServeOptions) -> Result<VaultServer, String> {
+    if !options.readonly && options.credentials.is_none() {
+        return Err("A writable server needs a username and password".to_string());
+    }
+    let host = if options.lan { "0.0.0.0" } else { "127.0.0.1" };
+    let listener = TcpListener::bind((host, port))
+        .await
+        .map_err(|e| format!("Failed to start server: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let url_host = if options.lan {
+        collab::local_ip().to_string()
+    } else {
+        host.to_string()
+    };
+    let info = ServeInfo {
+        url: format!("http://{}:{}/", url_host, port),
+        readonly: options.readonly,
+        auth: options.credentials.is_some(),
+        lan: options.lan,
+    };
+    let (shutdown, mut shutdown_rx) = watch::channel(false);
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let Ok((stream, _)) = accepted else {
+                        continue;
+                    };
+                    let Ok(permit) = connections.clone().try_acquire_owned() else {
+                        continue;
+                    };
+                    let (app, options) = (app.clone(), options.clone());
+                    tauri::async_runtime::spawn(async move {
+                        handle_connection(app, stream, options).await;
+                        drop(permit);
+                    });
+                }
+                _ = shutdown_rx.changed() => break,
+            }
+        }
+    });
+
+    Ok(VaultServer { info, shutdown })
+}
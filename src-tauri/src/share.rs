@@ -0,0 +1,188 @@
+//! Encrypted one-time note sharing.
+//!
+//! A shared note is rendered to HTML, encrypted with a fresh AES-256-GCM key and
+//! uploaded with `PUT {endpoint}/{share_id}`. The upload body is the base64 of
+//! `nonce || ciphertext`, and the requested lifetime is sent in the
+//! `X-Expires-In` header (seconds). The key never leaves this machine except in
+//! the URL fragment of the returned link, which browsers do not send to the
+//! server. Revoking a share issues `DELETE {endpoint}/{share_id}`. The
+//! endpoint's bearer token, if it needs one, is kept in the OS keychain
+//! (`set_share_token`), never in the vault.
+
+use crate::store;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::Aes256Gcm;
+use anyhow::Result;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const STORE_FILE: &str = "shares.json";
+const KEYCHAIN_SERVICE: &str = "Scratch";
+const KEYCHAIN_USER: &str = "share-token";
+pub const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+const MIN_TTL_SECS: u64 = 5 * 60;
+const MAX_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Link returned to the user. `url` already carries the key fragment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    pub url: String,
+    pub key: String,
+    pub expires_at: i64,
+}
+
+/// A share that has been uploaded and may still need revoking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareRecord {
+    pub note_id: String,
+    pub share_id: String,
+    pub upload_url: String,
+    pub created: i64,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShareStore {
+    pub shares: Vec<ShareRecord>,
+}
+
+impl ShareStore {
+    /// Load the store, dropping shares that have already expired.
+    pub fn load(notes_folder: &str) -> Self {
+        let mut store: Self = store::load(Path::new(notes_folder), STORE_FILE);
+        let now = chrono::Utc::now().timestamp();
+        store.shares.retain(|s| s.expires_at > now);
+        store
+    }
+
+    pub fn save(&self, notes_folder: &str) -> Result<()> {
+        store::save(Path::new(notes_folder), STORE_FILE, self)
+    }
+}
+
+fn keychain_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER)
+        .map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+/// Store the endpoint's bearer token, or remove it when `token` is None or
+/// empty. Blocking.
+pub fn set_token(token: Option<&str>) -> Result<(), String> {
+    let entry = keychain_entry()?;
+    match token.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(token) => entry
+            .set_password(token)
+            .map_err(|e| format!("Failed to store the token: {}", e)),
+        None => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to remove the token: {}", e)),
+        },
+    }
+}
+
+/// The stored bearer token, if any. Blocking.
+pub fn get_token() -> Result<Option<String>, String> {
+    match keychain_entry()?.get_password() {
+        Ok(token) => Ok(Some(token)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read the token: {}", e)),
+    }
+}
+
+pub fn clamp_ttl(ttl_secs: Option<u64>) -> u64 {
+    ttl_secs
+        .unwrap_or(DEFAULT_TTL_SECS)
+        .clamp(MIN_TTL_SECS, MAX_TTL_SECS)
+}
+
+fn validate_endpoint(endpoint: &str) -> Result<String, String> {
+    let parsed = url::Url::parse(endpoint.trim()).map_err(|_| "Invalid share endpoint URL".to_string())?;
+    match parsed.scheme() {
+        "https" => {}
+        "http" if matches!(parsed.host_str(), Some("localhost" | "127.0.0.1")) => {}
+        _ => return Err("Share endpoint must use https".to_string()),
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+/// Encrypt `html` and upload it. Returns the link for the recipient and the
+/// record needed to revoke it later.
+pub async fn create(
+    endpoint: &str,
+    token: Option<&str>,
+    note_id: &str,
+    html: &str,
+    ttl_secs: u64,
+) -> Result<(ShareLink, ShareRecord), String> {
+    let endpoint = validate_endpoint(endpoint)?;
+    let b64url = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    let key = Aes256Gcm::generate_key(OsRng);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(&nonce, html.as_bytes())
+        .map_err(|_| "Failed to encrypt note".to_string())?;
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+
+    let mut id_bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut id_bytes);
+    let share_id = b64url.encode(id_bytes);
+    let upload_url = format!("{}/{}", endpoint, share_id);
+
+    let mut request = reqwest::Client::new()
+        .put(&upload_url)
+        .header(reqwest::header::CONTENT_TYPE, "text/plain")
+        .header("X-Expires-In", ttl_secs.to_string())
+        .body(base64::engine::general_purpose::STANDARD.encode(&payload));
+    if let Some(token) = token.filter(|t| !t.is_empty()) {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to upload share: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Share endpoint returned {}", response.status()));
+    }
+
+    let key = b64url.encode(key);
+    let now = chrono::Utc::now().timestamp();
+    let expires_at = now + ttl_secs as i64;
+    Ok((
+        ShareLink {
+            url: format!("{}#{}", upload_url, key),
+            key,
+            expires_at,
+        },
+        ShareRecord {
+            note_id: note_id.to_string(),
+            share_id,
+            upload_url,
+            created: now,
+            expires_at,
+        },
+    ))
+}
+
+/// Delete an uploaded share. A share the server no longer has counts as revoked.
+pub async fn revoke(record: &ShareRecord, token: Option<&str>) -> Result<(), String> {
+    let mut request = reqwest::Client::new().delete(&record.upload_url);
+    if let Some(token) = token.filter(|t| !t.is_empty()) {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to revoke share: {}", e))?;
+    let status = response.status();
+    if status.is_success() || status == reqwest::StatusCode::NOT_FOUND {
+        Ok(())
+    } else {
+        Err(format!("Share endpoint returned {}", status))
+    }
+}
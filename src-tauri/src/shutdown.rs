@@ -0,0 +1,110 @@
+//! Graceful shutdown.
+//!
+//! Quitting is vetoed until pending autosaves are flushed, the search index
+//! is committed, the notes cache is written to app data, live sessions and
+//! the vault server are ended, watchers are stopped, app-quit automations
+//! have run (not in safe mode) and the vault lock is released; the app then
+//! exits for real.
+//! Closing a window with unsaved autosaves likewise waits for the flush.
+
+use crate::{autosave, automation, instance, safemode, save_notes_cache, AppState};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, CloseRequestApi, ExitRequestApi, Manager, Window};
+
+#[derive(Default)]
+pub struct Shutdown {
+    started: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl Shutdown {
+    /// Background jobs check this to stop between runs.
+    pub fn started(&self) -> bool {
+        self.started.load(Ordering::SeqCst)
+    }
+}
+
+async fn run_steps(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    autosave::flush_all(&state).await;
+
+    // Nothing should touch the vault from here on
+    state.file_watcher.lock().expect("file watcher mutex").take();
+    state.theme_watcher.lock().expect("theme watcher mutex").take();
+    state.source_watcher.lock().expect("source watcher mutex").take();
+    state.collab_session.lock().expect("collab session mutex").take();
+    state.vault_server.lock().expect("vault server mutex").take();
+
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.commit();
+        }
+    }
+    let _ = save_notes_cache(app, &state);
+
+    if !safemode::is_active() {
+        let quit_app = app.clone();
+        let _ = tauri::async_runtime::spawn_blocking(move || {
+            automation::run_for_trigger(&quit_app, &automation::Trigger::AppQuit)
+        })
+        .await;
+    }
+
+    // A clean exit leaves no lock behind, so the next launch skips recovery
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+    if let Some(folder) = folder {
+        instance::release(Path::new(&folder));
+    }
+}
+
+/// Veto an exit request and shut down first; exits once done.
+pub fn on_exit_requested(app: &AppHandle, code: Option<i32>, api: &ExitRequestApi) {
+    let state = app.state::<AppState>();
+    if state.shutdown.finished.load(Ordering::SeqCst) {
+        return;
+    }
+    api.prevent_exit();
+    if state.shutdown.started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_steps(&app).await;
+        app.state::<AppState>()
+            .shutdown
+            .finished
+            .store(true, Ordering::SeqCst);
+        app.exit(code.unwrap_or(0));
+    });
+}
+
+/// Last chance when the app exits without a vetoable request. Blocking.
+pub fn on_exit(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if state.shutdown.finished.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    state.shutdown.started.store(true, Ordering::SeqCst);
+    tauri::async_runtime::block_on(run_steps(app));
+}
+
+/// Keep a window open until its pending autosaves are on disk.
+pub fn on_close_requested(window: &Window, api: &CloseRequestApi) {
+    let state = window.state::<AppState>();
+    if state.autosave.pending_ids().is_empty() && !state.autosave.is_flushing() {
+        return;
+    }
+    api.prevent_close();
+    let window = window.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = window.state::<AppState>();
+        autosave::flush_all(&state).await;
+        // destroy() skips CloseRequested, so a failed flush cannot loop
+        let _ = window.destroy();
+    });
+}
@@ -0,0 +1,197 @@
+//! Metadata sidecars for attachments.
+//!
+//! A PDF, image or other non-markdown file in the vault can be described by a
+//! `<file>.meta.json` beside it (`assets/paper.pdf.meta.json`) holding a
+//! title, tags and free-form notes, so reference documents take part in
+//! search, tags and backlinks without being wrapped in a markdown note.
+//!
+//! A described attachment appears as a read-only pseudo-note with ID
+//! `.attachments/<vault-relative path>`, whose content is generated from the
+//! sidecar: the tags as frontmatter, the title as a heading, then the notes
+//! and the file's path. The search index, tag listings and note policies
+//! handle it like any other note; `abs_path_from_id` refuses these IDs, so
+//! nothing writes to them. An attachment with a sidecar is never treated as
+//! orphaned.
+
+use crate::{frontmatter, recovery, ScannedNote};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const ID_PREFIX: &str = ".attachments";
+pub const SUFFIX: &str = ".meta.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl AttachmentMeta {
+    /// Trim the fields and drop blank ones and duplicate tags.
+    fn normalized(self) -> Self {
+        let text = |s: Option<String>| s.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags {
+            let tag = tag.trim().trim_start_matches('#').to_string();
+            if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                tags.push(tag);
+            }
+        }
+        AttachmentMeta {
+            title: text(self.title),
+            tags,
+            notes: text(self.notes),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.title.is_none() && self.tags.is_empty() && self.notes.is_none()
+    }
+}
+
+pub fn is_attachment_id(id: &str) -> bool {
+    path_from_id(id).is_some()
+}
+
+pub fn note_id(path: &str) -> String {
+    format!("{}/{}", ID_PREFIX, path)
+}
+
+/// The vault-relative attachment path behind a pseudo-note ID.
+pub fn path_from_id(id: &str) -> Option<&str> {
+    id.strip_prefix(ID_PREFIX)?
+        .strip_prefix('/')
+        .filter(|path| !path.is_empty())
+}
+
+fn sidecar_path(notes_root: &Path, path: &str) -> PathBuf {
+    notes_root.join(format!("{}{}", path, SUFFIX))
+}
+
+pub fn has_sidecar(notes_root: &Path, path: &str) -> bool {
+    sidecar_path(notes_root, path).is_file()
+}
+
+/// The sidecar of attachment `path`, if it has one that parses.
+pub fn read(notes_root: &Path, path: &str) -> Option<AttachmentMeta> {
+    let content = std::fs::read_to_string(sidecar_path(notes_root, path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Write the sidecar of attachment `path`, or remove it when `meta` is empty.
+/// Returns what was stored. Blocking.
+pub fn write(
+    notes_root: &Path,
+    path: &str,
+    meta: AttachmentMeta,
+) -> Result<Option<AttachmentMeta>, String> {
+    let file = crate::assets::resolve(notes_root, path)?;
+    if file.extension().is_some_and(|ext| ext == "md") || path.ends_with(SUFFIX) {
+        return Err("Only attachments can have a metadata sidecar".to_string());
+    }
+    if !file.is_file() {
+        return Err("Attachment not found".to_string());
+    }
+    let sidecar = sidecar_path(notes_root, path);
+    let meta = meta.normalized();
+    if meta.is_empty() {
+        match std::fs::remove_file(&sidecar) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("Failed to remove the sidecar: {}", e))
+            }
+            _ => return Ok(None),
+        }
+    }
+    let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    recovery::write_atomic_sync(&sidecar, content.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(Some(meta))
+}
+
+/// Move attachment `old`'s sidecar along with it to `new`. Returns whether
+/// there was one.
+pub fn rename(notes_root: &Path, old: &str, new: &str) -> bool {
+    let from = sidecar_path(notes_root, old);
+    from.is_file() && std::fs::rename(&from, sidecar_path(notes_root, new)).is_ok()
+}
+
+/// The attachment a sidecar file describes, as a vault-relative path.
+pub fn attachment_for(notes_root: &Path, sidecar: &Path) -> Option<String> {
+    let rel = sidecar
+        .strip_prefix(notes_root)
+        .ok()?
+        .to_str()?
+        .replace(std::path::MAIN_SEPARATOR, "/");
+    let path = rel.strip_suffix(SUFFIX)?;
+    let is_note = Path::new(path).extension().is_some_and(|ext| ext == "md");
+    (!path.is_empty() && !is_note && crate::assets::resolve(notes_root, path).is_ok())
+        .then(|| path.to_string())
+}
+
+/// Note-shaped text for an attachment, as the index and tag listings see it.
+pub fn content(path: &str, meta: &AttachmentMeta) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let mut body = format!("# {}\n\n", meta.title.as_deref().unwrap_or(name));
+    if let Some(notes) = &meta.notes {
+        body.push_str(notes);
+        body.push_str("\n\n");
+    }
+    body.push_str(path);
+    body.push('\n');
+    if meta.tags.is_empty() {
+        body
+    } else {
+        frontmatter::set(&body, "tags", frontmatter::Value::List(meta.tags.clone()))
+    }
+}
+
+/// Attachment `path` as a pseudo-note, if it exists and has a sidecar.
+pub fn load(notes_root: &Path, path: &str) -> Option<ScannedNote> {
+    let file = notes_root.join(path);
+    let metadata = std::fs::metadata(&file).ok().filter(|m| m.is_file())?;
+    let meta = read(notes_root, path)?;
+    let modified = std::fs::metadata(sidecar_path(notes_root, path))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let content = content(path, &meta);
+    Some(ScannedNote {
+        id: note_id(path),
+        title: crate::extract_title(&content),
+        created: crate::note_created_secs(&content, Some(&metadata), modified),
+        content,
+        modified,
+        size: metadata.len(),
+    })
+}
+
+/// Every described attachment in the vault, as pseudo-notes. Hidden folders
+/// (the trash, history, `.scratch`) are skipped. Blocking.
+pub fn scan(notes_root: &Path) -> Vec<ScannedNote> {
+    walkdir::WalkDir::new(notes_root)
+        .max_depth(10)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| attachment_for(notes_root, e.path()))
+        .filter_map(|path| load(notes_root, &path))
+        .collect()
+}
+
+/// Lines of a note that reference attachment `path`, as 1-based line numbers
+/// and the line's text.
+pub fn lines_referencing<'a>(note_id: &str, content: &'a str, path: &str) -> Vec<(usize, &'a str)> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| crate::assets::references(note_id, line, path))
+        .map(|(i, line)| (i + 1, line))
+        .collect()
+}
@@ -0,0 +1,239 @@
+//! Slide decks from notes.
+//!
+//! `export_slides` writes a note as a Reveal.js deck. A line holding only
+//! `---` or an H2 heading starts a new slide (not inside code fences); the
+//! text before the first one is the opening slide and empty slides are
+//! dropped. Images the note embeds from the vault become data URLs, so the
+//! deck is a single file that can be mailed or put on a stick. Reveal.js is
+//! loaded from jsDelivr; offline, a small script in the deck still steps
+//! through the slides with the arrow keys.
+
+use crate::{assets, export, frontmatter, render};
+use base64::Engine;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const REVEAL: &str = "https://cdn.jsdelivr.net/npm/reveal.js@5.1.0/dist";
+const ASSET_URL_PREFIXES: &[&str] = &[
+    "asset://localhost/",
+    "http://asset.localhost/",
+    "https://asset.localhost/",
+];
+
+const OFFLINE_STYLE: &str = ".deck-offline{margin:0;font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif}\
+.deck-offline .slides>section{display:none;box-sizing:border-box;min-height:100vh;padding:6vh 8vw;font-size:1.6rem}\
+.deck-offline .slides>section.present{display:block}\
+.deck-offline img{max-width:100%;max-height:70vh}";
+
+const DECK_SCRIPT: &str = "if (window.Reveal) {\n\
+  Reveal.initialize({ hash: true });\n\
+} else {\n\
+  var slides = document.querySelectorAll('.slides > section'), current = 0;\n\
+  var show = function (i) {\n\
+    current = Math.max(0, Math.min(slides.length - 1, i));\n\
+    slides.forEach(function (s, j) { s.classList.toggle('present', j === current); });\n\
+  };\n\
+  document.body.classList.add('deck-offline');\n\
+  document.addEventListener('keydown', function (e) {\n\
+    if (['ArrowRight', 'ArrowDown', 'PageDown', ' '].indexOf(e.key) >= 0) show(current + 1);\n\
+    if (['ArrowLeft', 'ArrowUp', 'PageUp'].indexOf(e.key) >= 0) show(current - 1);\n\
+  });\n\
+  show(0);\n\
+}\n";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlideExport {
+    pub path: String,
+    pub slides: usize,
+    pub images: usize, // embedded from the vault
+}
+
+fn img_src_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"(<img\b[^>]*?\bsrc=")([^"]+)(")"#).unwrap())
+}
+
+/// Split a note body into the markdown of each slide.
+fn split(body: &str) -> Vec<String> {
+    let mut slides = vec![String::new()];
+    let mut fence: Option<&str> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if line.trim() == "---" {
+            slides.push(String::new());
+            continue;
+        } else if line.starts_with("## ") || line.trim_end() == "##" {
+            slides.push(String::new());
+        }
+        let slide = slides.last_mut().expect("at least one slide");
+        slide.push_str(line);
+        slide.push('\n');
+    }
+    slides.retain(|slide| !slide.trim().is_empty());
+    slides
+}
+
+fn image_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        "tif" | "tiff" => "image/tiff",
+        _ => return None,
+    })
+}
+
+/// `rel` joined to `dir`, with `.` and `..` resolved; None if it climbs out.
+fn join_relative(dir: &str, rel: &str) -> Option<String> {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in rel.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// The vault file an image `src` points at: an asset URL or absolute path
+/// inside the vault, or a path relative to the note or the vault.
+fn image_path(notes_root: &Path, note_id: &str, src: &str) -> Option<PathBuf> {
+    let src = src.replace("&amp;", "&");
+    let decoded = urlencoding::decode(&src).ok()?.into_owned();
+    let path = ASSET_URL_PREFIXES
+        .iter()
+        .find_map(|prefix| decoded.strip_prefix(prefix))
+        .unwrap_or(&decoded);
+    if Path::new(path).is_absolute() {
+        let rel = Path::new(path)
+            .strip_prefix(notes_root)
+            .ok()?
+            .to_str()?
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        return assets::resolve(notes_root, &rel).ok();
+    }
+    if path.contains(':') {
+        return None; // remote or data URL
+    }
+    let note_dir = note_id.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    [join_relative(note_dir, path), join_relative("", path)]
+        .into_iter()
+        .flatten()
+        .filter_map(|rel| assets::resolve(notes_root, &rel).ok())
+        .find(|file| file.is_file())
+}
+
+/// Replace the `src` of images found in the vault with data URLs. Returns
+/// the HTML and how many were embedded.
+fn embed_images(html: &str, notes_root: &Path, note_id: &str) -> (String, usize) {
+    let mut embedded = 0;
+    let html = img_src_re().replace_all(html, |caps: &Captures| {
+        let data_url = image_path(notes_root, note_id, &caps[2]).and_then(|path| {
+            let mime = image_mime(&path)?;
+            let bytes = std::fs::read(&path).ok()?;
+            Some(format!(
+                "data:{};base64,{}",
+                mime,
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            ))
+        });
+        match data_url {
+            Some(url) => {
+                embedded += 1;
+                format!("{}{}{}", &caps[1], url, &caps[3])
+            }
+            None => caps[0].to_string(),
+        }
+    });
+    (html.into_owned(), embedded)
+}
+
+/// Render a note as a deck. Returns the page, the slide count and how many
+/// images were embedded.
+fn render_deck(
+    title: &str,
+    content: &str,
+    note_id: &str,
+    notes_root: &Path,
+    class_styles: &BTreeMap<String, String>,
+) -> (String, usize, usize) {
+    let slides = split(frontmatter::body(content));
+    let mut sections = String::new();
+    for slide in &slides {
+        sections.push_str("<section>\n");
+        sections.push_str(&render::render_body(slide));
+        sections.push_str("</section>\n");
+    }
+    let (sections, images) = embed_images(&sections, notes_root, note_id);
+    let classes = render::note_classes(content);
+    let page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+<title>{title}</title>\n\
+<link rel=\"stylesheet\" href=\"{reveal}/reveal.css\">\n\
+<link rel=\"stylesheet\" href=\"{reveal}/theme/white.css\">\n\
+<style>{offline}{class_css}</style>\n</head>\n<body>\n\
+<div class=\"reveal{classes}\">\n<div class=\"slides\">\n{sections}</div>\n</div>\n\
+<script src=\"{reveal}/reveal.js\"></script>\n<script>\n{script}</script>\n</body>\n</html>\n",
+        title = render::escape_html(title),
+        reveal = REVEAL,
+        offline = OFFLINE_STYLE,
+        class_css = render::class_css(&classes, class_styles),
+        classes = classes
+            .iter()
+            .map(|c| format!(" {}", c))
+            .collect::<String>(),
+        script = DECK_SCRIPT,
+    );
+    (page, slides.len(), images)
+}
+
+/// Write a note as a deck to `path`, an absolute file path outside the vault;
+/// `.html` is added when it has no extension. Blocking.
+pub fn export(
+    title: &str,
+    content: &str,
+    note_id: &str,
+    path: &str,
+    notes_root: &Path,
+    class_styles: &BTreeMap<String, String>,
+) -> Result<SlideExport, String> {
+    let requested = PathBuf::from(path.trim());
+    let (Some(parent), Some(name)) = (requested.parent(), requested.file_name()) else {
+        return Err("Export path must be a file path".to_string());
+    };
+    let mut path = export::validate_output_dir(&parent.to_string_lossy(), notes_root)?.join(name);
+    if path.extension().is_none() {
+        path.set_extension("html");
+    }
+
+    let (page, slides, images) = render_deck(title, content, note_id, notes_root, class_styles);
+    if slides == 0 {
+        return Err("The note has nothing to put on a slide".to_string());
+    }
+    std::fs::write(&path, page).map_err(|e| format!("Failed to write slides: {}", e))?;
+    Ok(SlideExport {
+        path: path.to_string_lossy().into_owned(),
+        slides,
+        images,
+    })
+}
@@ -0,0 +1,209 @@
+//! Read-only source folders.
+//!
+//! Extra folders outside the vault (a shared team docs directory, say) can be
+//! registered by name. Their markdown files are indexed and searchable and can
+//! be opened and linked to, but Scratch never writes to them. A source note's
+//! ID is `.sources/<name>/<id within the folder>`; `.sources` is excluded from
+//! vault scans and `abs_path_from_id` refuses these IDs, so every write path
+//! fails for them. Registrations are machine-specific and live in the app
+//! config, next to the notes folder.
+
+use crate::{
+    extract_title, id_from_abs_path, is_visible_notes_entry, note_created_secs, sanitize_filename,
+    AppState, FileWatcherState, ScannedNote,
+};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const ID_PREFIX: &str = ".sources";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceFolder {
+    pub name: String,
+    pub path: String, // absolute
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceChangeEvent {
+    source: String,
+    changed_ids: Vec<String>,
+}
+
+pub fn is_source_id(id: &str) -> bool {
+    id.starts_with(&format!("{}/", ID_PREFIX))
+}
+
+fn note_id(name: &str, rel_id: &str) -> String {
+    format!("{}/{}/{}", ID_PREFIX, name, rel_id)
+}
+
+/// Absolute path of a source note, validated like a vault note ID.
+pub fn resolve(sources: &[SourceFolder], id: &str) -> Result<PathBuf, String> {
+    let rest = id
+        .strip_prefix(ID_PREFIX)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .ok_or("Not a source note")?;
+    let (name, rel_id) = rest.split_once('/').ok_or("Invalid source note ID")?;
+    let source = sources
+        .iter()
+        .find(|s| s.name == name)
+        .ok_or("Source folder not found")?;
+    crate::abs_path_from_id(Path::new(&source.path), rel_id)
+}
+
+/// Pick a unique, filename-safe name for a new source.
+pub fn unique_name(sources: &[SourceFolder], requested: &str) -> String {
+    let base = sanitize_filename(requested.trim());
+    let base = if base.is_empty() {
+        "source".to_string()
+    } else {
+        base
+    };
+    let mut name = base.clone();
+    let mut counter = 1;
+    while sources.iter().any(|s| s.name.eq_ignore_ascii_case(&name)) {
+        name = format!("{}-{}", base, counter);
+        counter += 1;
+    }
+    name
+}
+
+/// Every markdown note in the registered sources. Blocking.
+pub fn scan(sources: &[SourceFolder]) -> Vec<ScannedNote> {
+    use walkdir::WalkDir;
+
+    let mut notes = Vec::new();
+    for source in sources {
+        let root = Path::new(&source.path);
+        for entry in WalkDir::new(root)
+            .max_depth(10)
+            .into_iter()
+            .filter_entry(is_visible_notes_entry)
+            .flatten()
+        {
+            let file_path = entry.path();
+            if !file_path.is_file() {
+                continue;
+            }
+            let Some(rel_id) = id_from_abs_path(root, file_path) else {
+                continue;
+            };
+            let Ok(content) = std::fs::read_to_string(file_path) else {
+                continue;
+            };
+            let metadata = entry.metadata().ok();
+            let modified = metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            notes.push(ScannedNote {
+                id: note_id(&source.name, &rel_id),
+                title: extract_title(&content),
+                created: note_created_secs(&content, metadata.as_ref(), modified),
+                size: metadata.map(|m| m.len()).unwrap_or(content.len() as u64),
+                content,
+                modified,
+            });
+        }
+    }
+    notes
+}
+
+/// A source note matching a wikilink target by ID within its source, file
+/// name or title (case-insensitive). Blocking.
+pub fn find(sources: &[SourceFolder], target: &str) -> Option<String> {
+    let target = target.trim().trim_end_matches(".md").to_lowercase();
+    let notes = scan(sources);
+    let rel = |id: &str| id.splitn(3, '/').nth(2).unwrap_or_default().to_lowercase();
+    notes
+        .iter()
+        .find(|n| rel(&n.id) == target)
+        .or_else(|| {
+            notes
+                .iter()
+                .find(|n| rel(&n.id).rsplit('/').next() == Some(target.as_str()))
+        })
+        .or_else(|| notes.iter().find(|n| n.title.to_lowercase() == target))
+        .map(|n| n.id.clone())
+}
+
+/// Watch the sources and keep their notes in the search index up to date.
+/// Emits `source-change` with the affected IDs.
+pub fn watch(app: AppHandle, sources: Vec<SourceFolder>) -> Result<FileWatcherState, String> {
+    let watched = sources.clone();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| {
+            let Ok(event) = res else {
+                return;
+            };
+            for path in &event.paths {
+                let Some(source) = watched.iter().find(|s| path.starts_with(&s.path)) else {
+                    continue;
+                };
+                let Some(rel_id) = id_from_abs_path(Path::new(&source.path), path) else {
+                    continue;
+                };
+                let id = note_id(&source.name, &rel_id);
+                let state = app.state::<AppState>();
+                {
+                    let index = state.search_index.lock().expect("search index mutex");
+                    if let Some(ref search_index) = *index {
+                        match std::fs::read_to_string(path) {
+                            Ok(content) => {
+                                let modified = chrono::Utc::now().timestamp();
+                                let _ = search_index.index_note(
+                                    &id,
+                                    &extract_title(&content),
+                                    &content,
+                                    modified,
+                                );
+                            }
+                            Err(_) => {
+                                let _ = search_index.delete_note(&id);
+                            }
+                        }
+                    }
+                }
+                let _ = app.emit(
+                    "source-change",
+                    SourceChangeEvent {
+                        source: source.name.clone(),
+                        changed_ids: vec![id],
+                    },
+                );
+            }
+        },
+        Config::default(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    for source in &sources {
+        if Path::new(&source.path).is_dir() {
+            let _ = watcher.watch(Path::new(&source.path), RecursiveMode::Recursive);
+        }
+    }
+    Ok(FileWatcherState { watcher })
+}
+
+/// (Re)start watching the registered sources.
+pub fn restart_watcher(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let sources = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .source_folders
+        .clone();
+    let watcher = if sources.is_empty() {
+        None
+    } else {
+        watch(app.clone(), sources).ok()
+    };
+    *state.source_watcher.lock().expect("source watcher mutex") = watcher;
+}
@@ -0,0 +1,80 @@
+//! Starred notes, listed as favorites.
+//!
+//! Separate from pins, which only order the note list: stars are kept in
+//! `.scratch/stars.json` as note IDs with the time each was starred, and
+//! follow their note through renames, moves and folder renames. Deleting a
+//! note drops its star.
+
+use crate::store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const STORE_FILE: &str = "stars.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Star {
+    pub id: String,
+    pub starred: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StarStore {
+    pub stars: Vec<Star>, // in the order they were starred
+}
+
+impl StarStore {
+    pub fn load(notes_folder: &str) -> Self {
+        store::load(Path::new(notes_folder), STORE_FILE)
+    }
+
+    pub fn save(&self, notes_folder: &str) -> Result<()> {
+        store::save(Path::new(notes_folder), STORE_FILE, self)
+    }
+
+    /// Star a note. Returns false if it already was.
+    pub fn star(&mut self, note_id: &str) -> bool {
+        if self.stars.iter().any(|s| s.id == note_id) {
+            return false;
+        }
+        self.stars.push(Star {
+            id: note_id.to_string(),
+            starred: chrono::Utc::now().timestamp(),
+        });
+        true
+    }
+
+    pub fn unstar(&mut self, note_id: &str) -> bool {
+        let before = self.stars.len();
+        self.stars.retain(|s| s.id != note_id);
+        self.stars.len() != before
+    }
+
+    /// Carry a star over to the note's new ID after a rename or move.
+    pub fn rename_note(&mut self, old_id: &str, new_id: &str) -> bool {
+        let mut changed = false;
+        for star in self.stars.iter_mut().filter(|s| s.id == old_id) {
+            star.id = new_id.to_string();
+            changed = true;
+        }
+        changed
+    }
+
+    /// Carry the stars of notes under folder `old_path` over to `new_path`.
+    pub fn rename_folder(&mut self, old_path: &str, new_path: &str) -> bool {
+        let old_prefix = format!("{}/", old_path);
+        let mut changed = false;
+        for star in self.stars.iter_mut() {
+            if let Some(rest) = star.id.strip_prefix(&old_prefix) {
+                star.id = format!("{}/{}", new_path, rest);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    pub fn remove_note(&mut self, note_id: &str) -> bool {
+        self.unstar(note_id)
+    }
+}
@@ -0,0 +1,32 @@
+//! Per-vault JSON stores: state kept in `.scratch/<file>` rather than in
+//! notes, such as stars, annotations and access tokens.
+//!
+//! A store that is missing or no longer parses loads as its default. Saves
+//! go through `recovery::write_atomic_sync`, so a crash mid-save leaves the
+//! previous store rather than a truncated one.
+
+use crate::recovery;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Path of store `file` in the vault, creating `.scratch` if needed.
+pub fn path(notes_root: &Path, file: &str) -> PathBuf {
+    let scratch_dir = notes_root.join(".scratch");
+    std::fs::create_dir_all(&scratch_dir).ok();
+    scratch_dir.join(file)
+}
+
+pub fn load<T: DeserializeOwned + Default>(notes_root: &Path, file: &str) -> T {
+    std::fs::read_to_string(path(notes_root, file))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save<T: Serialize>(notes_root: &Path, file: &str, value: &T) -> Result<()> {
+    let content = serde_json::to_string_pretty(value)?;
+    recovery::write_atomic_sync(&path(notes_root, file), content.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Tag extraction from note content.
+//!
+//! Tags come from two places: the frontmatter `tags:` key and inline `#tag`
+//! tokens in the body. Inline tags inside code spans and fenced code blocks are
+//! ignored, as are headings (`# Title`) and purely numeric tokens (`#123`).
+
+use crate::frontmatter::{self, Frontmatter};
+use regex::Regex;
+use std::sync::OnceLock;
+
+fn inline_tag_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:^|[\s(\[,;])#([\p{L}\p{N}_][\p{L}\p{N}_/-]*)").unwrap())
+}
+
+/// Normalize a tag for comparison: strip a leading '#', trim trailing
+/// separators and lowercase.
+pub fn normalize(tag: &str) -> String {
+    tag.trim()
+        .trim_start_matches('#')
+        .trim_end_matches(['/', '-'])
+        .to_lowercase()
+}
+
+/// Remove inline code spans from a line so tags inside them are not picked up.
+fn strip_code_spans(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_code = false;
+    for c in line.chars() {
+        if c == '`' {
+            in_code = !in_code;
+            continue;
+        }
+        if !in_code {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Extract normalized, de-duplicated tags in order of first appearance.
+pub fn extract_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut push = |tag: String| {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    };
+
+    for tag in Frontmatter::parse(content).get_list("tags") {
+        push(normalize(&tag));
+    }
+
+    let mut in_fence = false;
+    for line in frontmatter::body(content).lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let text = strip_code_spans(line);
+        for cap in inline_tag_re().captures_iter(&text) {
+            let tag = &cap[1];
+            // Pure numbers are issue references or headings, not tags
+            if tag.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            push(normalize(tag));
+        }
+    }
+
+    tags
+}
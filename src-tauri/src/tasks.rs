@@ -0,0 +1,119 @@
+//! Markdown task parsing.
+//!
+//! A task is a list item with a checkbox (`- [ ] ...`, `* [x] ...`,
+//! `1. [ ] ...`). Inline markers add metadata: `@due(YYYY-MM-DD)`,
+//! `@every(...)` (see `routines`) and `@progress(3/5)` or `@progress(60%)`
+//! (see `goals`). Tasks inside fenced code blocks are ignored.
+
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    pub line: usize,  // 0-based line in the note
+    pub text: String, // without markers
+    pub done: bool,
+    pub due: Option<String>,   // YYYY-MM-DD
+    pub every: Option<String>, // recurrence spec as written
+    pub progress: Option<f64>, // 0.0..=1.0
+}
+
+fn task_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*(?:[-*+]|\d+[.)])\s+\[([ xX])\]\s+(.*)$").unwrap())
+}
+
+fn marker_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"@(due|every|progress)\(([^)]*)\)").unwrap())
+}
+
+/// Task text with markers removed and whitespace collapsed.
+fn strip_markers(text: &str) -> String {
+    marker_re()
+        .replace_all(text, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse a progress value: a fraction (`3/5`), a percentage (`60%`, `60`) or
+/// a ratio (`0.6`), clamped to 0..=1.
+pub fn parse_progress(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let ratio = if let Some((done, total)) = value.split_once('/') {
+        let total: f64 = total.trim().parse().ok()?;
+        if total <= 0.0 {
+            return None;
+        }
+        done.trim().parse::<f64>().ok()? / total
+    } else if let Some(percent) = value.strip_suffix('%') {
+        percent.trim().parse::<f64>().ok()? / 100.0
+    } else {
+        let n: f64 = value.parse().ok()?;
+        if n > 1.0 {
+            n / 100.0
+        } else {
+            n
+        }
+    };
+    ratio.is_finite().then(|| ratio.clamp(0.0, 1.0))
+}
+
+/// The value of the first `@progress(...)` marker in a line.
+pub fn progress_marker(line: &str) -> Option<f64> {
+    marker_re()
+        .captures_iter(line)
+        .find(|cap| &cap[1] == "progress")
+        .and_then(|cap| parse_progress(&cap[2]))
+}
+
+fn parse_line(line: &str) -> Option<(bool, &str)> {
+    let caps = task_re().captures(line)?;
+    let done = !caps.get(1)?.as_str().trim().is_empty();
+    Some((done, caps.get(2)?.as_str()))
+}
+
+pub fn extract_tasks(content: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut in_fence = false;
+    for (line_no, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let Some((done, raw)) = parse_line(line) else {
+            continue;
+        };
+
+        let mut due = None;
+        let mut every = None;
+        for cap in marker_re().captures_iter(raw) {
+            let value = cap[2].trim();
+            match &cap[1] {
+                "due" => {
+                    due = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .ok()
+                        .map(|d| d.format("%Y-%m-%d").to_string());
+                }
+                "every" if !value.is_empty() => every = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        tasks.push(Task {
+            line: line_no,
+            text: strip_markers(raw),
+            done,
+            due,
+            every,
+            progress: progress_marker(raw),
+        });
+    }
+    tasks
+}
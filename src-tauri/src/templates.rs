@@ -0,0 +1,150 @@
+//! Template variables.
+//!
+//! `{{name}}` placeholders in note templates are filled from a registry of
+//! variables. Built in: `date`, `time`, `year`, `month`, `day`, `weekday`,
+//! `timestamp`, `vault_name`, `clipboard`, `selection` and `weather` (the
+//! trimmed response of the `weatherUrl` setting, e.g. wttr.in). Other code can
+//! `register` computed variables and the frontend can register fixed values.
+//! Unknown placeholders are left as written.
+
+use chrono::{DateTime, Local};
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+const BUILTINS: &[&str] = &[
+    "date",
+    "time",
+    "year",
+    "month",
+    "day",
+    "weekday",
+    "timestamp",
+    "vault_name",
+    "clipboard",
+    "selection",
+    "weather",
+];
+const WEATHER_TIMEOUT: Duration = Duration::from_secs(5);
+const WEATHER_MAX_LEN: usize = 200;
+
+fn placeholder_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][\w.-]*)\s*\}\}").unwrap())
+}
+
+/// What the built-in variables draw on. Slow or external values are only
+/// gathered when the template uses them (see `uses`).
+pub struct TemplateContext {
+    pub now: DateTime<Local>,
+    pub vault_name: String,
+    pub selection: Option<String>,
+    pub clipboard: Option<String>,
+    pub weather: Option<String>,
+}
+
+type Provider = Box<dyn Fn(&TemplateContext) -> Option<String> + Send + Sync>;
+
+#[derive(Default)]
+pub struct Registry {
+    providers: RwLock<BTreeMap<String, Provider>>,
+}
+
+impl Registry {
+    /// Add or replace a computed variable. Built-ins cannot be replaced.
+    pub fn register(&self, name: &str, provider: Provider) -> Result<(), String> {
+        let name = name.trim();
+        if !placeholder_re().is_match(&format!("{{{{{}}}}}", name)) {
+            return Err("Variable names may use letters, digits, '_', '.' and '-'".to_string());
+        }
+        if BUILTINS.contains(&name) {
+            return Err(format!("'{}' is a built-in variable", name));
+        }
+        self.providers
+            .write()
+            .expect("template registry write lock")
+            .insert(name.to_string(), provider);
+        Ok(())
+    }
+
+    /// Add or replace a variable with a fixed value.
+    pub fn register_value(&self, name: &str, value: String) -> Result<(), String> {
+        self.register(name, Box::new(move |_| Some(value.clone())))
+    }
+
+    pub fn unregister(&self, name: &str) -> bool {
+        self.providers
+            .write()
+            .expect("template registry write lock")
+            .remove(name.trim())
+            .is_some()
+    }
+
+    /// Built-in and registered variable names.
+    pub fn names(&self) -> Vec<String> {
+        let providers = self.providers.read().expect("template registry read lock");
+        BUILTINS
+            .iter()
+            .map(|name| name.to_string())
+            .chain(providers.keys().cloned())
+            .collect()
+    }
+
+    fn value(&self, name: &str, ctx: &TemplateContext) -> Option<String> {
+        let builtin = match name {
+            "date" => ctx.now.format("%Y-%m-%d").to_string(),
+            "time" => ctx.now.format("%H:%M").to_string(),
+            "year" => ctx.now.format("%Y").to_string(),
+            "month" => ctx.now.format("%m").to_string(),
+            "day" => ctx.now.format("%d").to_string(),
+            "weekday" => ctx.now.format("%A").to_string(),
+            "timestamp" => ctx.now.timestamp().to_string(),
+            "vault_name" => ctx.vault_name.clone(),
+            "clipboard" => ctx.clipboard.clone().unwrap_or_default(),
+            "selection" => ctx.selection.clone().unwrap_or_default(),
+            "weather" => ctx.weather.clone().unwrap_or_default(),
+            _ => {
+                let providers = self.providers.read().expect("template registry read lock");
+                return providers.get(name).and_then(|provider| provider(ctx));
+            }
+        };
+        Some(builtin)
+    }
+
+    /// Fill every known `{{name}}` placeholder in `template`.
+    pub fn expand(&self, template: &str, ctx: &TemplateContext) -> String {
+        placeholder_re()
+            .replace_all(template, |cap: &Captures| {
+                self.value(&cap[1], ctx)
+                    .unwrap_or_else(|| cap[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+/// Whether `template` has a `{{name}}` placeholder.
+pub fn uses(template: &str, name: &str) -> bool {
+    placeholder_re()
+        .captures_iter(template)
+        .any(|cap| &cap[1] == name)
+}
+
+/// Fetch the weather line from the configured provider URL, with
+/// `{location}` in it replaced by `location` (or nothing, which lets wttr.in
+/// place the request itself).
+pub async fn fetch_weather(url: &str, location: Option<&str>) -> Option<String> {
+    let location = urlencoding::encode(location.unwrap_or_default().trim());
+    let response = reqwest::Client::new()
+        .get(url.replace("{location}", &location))
+        .timeout(WEATHER_TIMEOUT)
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let text = response.text().await.ok()?;
+    let line = text.lines().next().unwrap_or_default().trim();
+    Some(line.chars().take(WEATHER_MAX_LEN).collect())
+}
@@ -0,0 +1,358 @@
+//! Note translation through DeepL, LibreTranslate or an OpenAI-compatible
+//! chat API.
+//!
+//! Fenced code blocks are kept as written; the rest of the body goes out in
+//! chunks of whole paragraphs, with the whitespace around each chunk put back
+//! afterwards. API keys are kept in the OS keychain (`set_translation_key`),
+//! never in settings. The translated copy keeps the original's frontmatter and
+//! adds `translation_of: "[[<id>]]"` and `lang: <target>`.
+
+use crate::frontmatter::{self, Value};
+use serde::{Deserialize, Serialize};
+
+const KEYCHAIN_SERVICE: &str = "Scratch";
+const MAX_CHUNK_CHARS: usize = 4000;
+const DEEPL_BATCH: usize = 50; // texts per request
+const DEFAULT_LIBRE_TRANSLATE_URL: &str = "https://libretranslate.com";
+const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Provider {
+    Deepl,
+    LibreTranslate,
+    OpenAi,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationSettings {
+    pub libre_translate_url: Option<String>, // default https://libretranslate.com
+    pub openai_base_url: Option<String>,     // default https://api.openai.com/v1
+    pub openai_model: Option<String>,        // default gpt-4o-mini
+}
+
+impl Provider {
+    fn keychain_user(self) -> &'static str {
+        match self {
+            Provider::Deepl => "translate-deepl",
+            Provider::LibreTranslate => "translate-libretranslate",
+            Provider::OpenAi => "translate-openai",
+        }
+    }
+}
+
+fn keychain_entry(provider: Provider) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, provider.keychain_user())
+        .map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+/// Store the API key for `provider`, or remove it when `key` is None or
+/// empty. Blocking.
+pub fn set_key(provider: Provider, key: Option<&str>) -> Result<(), String> {
+    let entry = keychain_entry(provider)?;
+    match key.map(str::trim).filter(|k| !k.is_empty()) {
+        Some(key) => entry
+            .set_password(key)
+            .map_err(|e| format!("Failed to store the key: {}", e)),
+        None => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to remove the key: {}", e)),
+        },
+    }
+}
+
+/// The stored API key for `provider`, if any. Blocking.
+pub fn get_key(provider: Provider) -> Result<Option<String>, String> {
+    match keychain_entry(provider)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read the key: {}", e)),
+    }
+}
+
+/// A base URL from settings: https, or http on this machine.
+fn base_url(configured: Option<&str>, default: &str) -> Result<String, String> {
+    let url = configured
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .unwrap_or(default);
+    let parsed = url::Url::parse(url).map_err(|_| format!("Invalid URL: {}", url))?;
+    match parsed.scheme() {
+        "https" => {}
+        "http" if matches!(parsed.host_str(), Some("localhost" | "127.0.0.1")) => {}
+        _ => return Err("Translation URLs must use https".to_string()),
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+enum Segment {
+    Keep(String),
+    Translate(String),
+}
+
+/// Split a note body into code blocks and blank lines to keep and chunks of
+/// whole paragraphs to translate.
+fn segments(body: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut chunk = String::new();
+    let mut in_fence = false;
+    let flush = |chunk: &mut String, segments: &mut Vec<Segment>| {
+        if !chunk.is_empty() {
+            segments.push(Segment::Translate(std::mem::take(chunk)));
+        }
+    };
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        if in_fence || is_fence {
+            flush(&mut chunk, &mut segments);
+            segments.push(Segment::Keep(line.to_string()));
+            in_fence ^= is_fence;
+            continue;
+        }
+        if line.trim().is_empty() && chunk.len() >= MAX_CHUNK_CHARS {
+            flush(&mut chunk, &mut segments);
+        }
+        chunk.push_str(line);
+    }
+    flush(&mut chunk, &mut segments);
+    segments
+}
+
+async fn post_json(
+    url: &str,
+    auth: Option<(&str, String)>,
+    body: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let mut request = reqwest::Client::new()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    if let Some((header, value)) = auth {
+        request = request.header(header, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Translation request failed: {}", e))?;
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("Translation request failed: {}", e))?;
+    if !status.is_success() {
+        return Err(format!(
+            "Translation service returned {}: {}",
+            status,
+            text.trim()
+        ));
+    }
+    serde_json::from_str(&text)
+        .map_err(|_| "Unexpected response from the translation service".to_string())
+}
+
+fn strings_at(
+    value: &serde_json::Value,
+    pointer: &str,
+    count: usize,
+) -> Result<Vec<String>, String> {
+    let texts: Vec<String> = match value.pointer(pointer) {
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        Some(serde_json::Value::String(text)) => vec![text.clone()],
+        _ => Vec::new(),
+    };
+    if texts.len() != count {
+        return Err("Unexpected response from the translation service".to_string());
+    }
+    Ok(texts)
+}
+
+async fn translate_deepl(texts: &[&str], target: &str, key: &str) -> Result<Vec<String>, String> {
+    // Keys for the free API end in ":fx" and only work against its own host
+    let url = if key.ends_with(":fx") {
+        "https://api-free.deepl.com/v2/translate"
+    } else {
+        "https://api.deepl.com/v2/translate"
+    };
+    let mut out = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(DEEPL_BATCH) {
+        let body = serde_json::json!({
+            "text": batch,
+            "target_lang": target.to_uppercase(),
+            "preserve_formatting": true,
+        });
+        let auth = ("Authorization", format!("DeepL-Auth-Key {}", key));
+        let response = post_json(url, Some(auth), body).await?;
+        let translations = response
+            .get("translations")
+            .and_then(|t| t.as_array())
+            .ok_or("Unexpected response from DeepL")?;
+        out.extend(
+            translations
+                .iter()
+                .filter_map(|t| t.get("text")?.as_str().map(str::to_string)),
+        );
+    }
+    if out.len() != texts.len() {
+        return Err("Unexpected response from DeepL".to_string());
+    }
+    Ok(out)
+}
+
+async fn translate_libre(
+    texts: &[&str],
+    target: &str,
+    key: Option<&str>,
+    settings: &TranslationSettings,
+) -> Result<Vec<String>, String> {
+    let base = base_url(
+        settings.libre_translate_url.as_deref(),
+        DEFAULT_LIBRE_TRANSLATE_URL,
+    )?;
+    // LibreTranslate knows languages, not regions ("pt", not "pt-BR")
+    let target = target
+        .split(['-', '_'])
+        .next()
+        .unwrap_or(target)
+        .to_lowercase();
+    let mut body = serde_json::json!({
+        "q": texts,
+        "source": "auto",
+        "target": target,
+        "format": "text",
+    });
+    if let Some(key) = key {
+        body["api_key"] = serde_json::Value::String(key.to_string());
+    }
+    let response = post_json(&format!("{}/translate", base), None, body).await?;
+    strings_at(&response, "/translatedText", texts.len())
+}
+
+async fn translate_openai(
+    texts: &[&str],
+    target: &str,
+    key: Option<&str>,
+    settings: &TranslationSettings,
+) -> Result<Vec<String>, String> {
+    let base = base_url(settings.openai_base_url.as_deref(), DEFAULT_OPENAI_BASE_URL)?;
+    let model = settings
+        .openai_model
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .unwrap_or(DEFAULT_OPENAI_MODEL);
+    let instructions = format!(
+        "Translate the user's Markdown into the language with code \"{}\". Keep Markdown \
+syntax, URLs, [[wikilinks]], #tags and inline code unchanged. Reply with the translation only.",
+        target
+    );
+    let mut out = Vec::with_capacity(texts.len());
+    for text in texts {
+        let body = serde_json::json!({
+            "model": model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": instructions },
+                { "role": "user", "content": text },
+            ],
+        });
+        let auth = key.map(|key| ("Authorization", format!("Bearer {}", key)));
+        let response = post_json(&format!("{}/chat/completions", base), auth, body).await?;
+        out.extend(strings_at(&response, "/choices/0/message/content", 1)?);
+    }
+    Ok(out)
+}
+
+/// Translate a note's content into `target` (a language code such as "de" or
+/// "pt-BR"), returning the translated copy with its link back to `note_id`.
+pub async fn translate(
+    note_id: &str,
+    content: &str,
+    target: &str,
+    provider: Provider,
+    key: Option<String>,
+    settings: &TranslationSettings,
+) -> Result<String, String> {
+    let target = target.trim();
+    if target.is_empty()
+        || !target
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Invalid target language".to_string());
+    }
+    let key = key.filter(|k| !k.is_empty());
+    if key.is_none() && provider == Provider::Deepl {
+        return Err("No DeepL API key set".to_string());
+    }
+
+    let body = frontmatter::body(content);
+    let segments = segments(body);
+    // Only the text between the whitespace at either end is sent
+    let parts: Vec<(&str, &str, &str)> = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Translate(text) => {
+                let core = text.trim();
+                let start = text.len() - text.trim_start().len();
+                Some((&text[..start], core, &text[start + core.len()..]))
+            }
+            Segment::Keep(_) => None,
+        })
+        .collect();
+    let texts: Vec<&str> = parts
+        .iter()
+        .map(|(_, core, _)| *core)
+        .filter(|core| !core.is_empty())
+        .collect();
+
+    let translated = if texts.is_empty() {
+        Vec::new()
+    } else {
+        match provider {
+            Provider::Deepl => {
+                translate_deepl(&texts, target, key.as_deref().unwrap_or_default()).await?
+            }
+            Provider::LibreTranslate => {
+                translate_libre(&texts, target, key.as_deref(), settings).await?
+            }
+            Provider::OpenAi => translate_openai(&texts, target, key.as_deref(), settings).await?,
+        }
+    };
+
+    let mut translated = translated.into_iter();
+    let mut parts = parts.into_iter();
+    let mut out = String::with_capacity(body.len());
+    for segment in &segments {
+        match segment {
+            Segment::Keep(text) => out.push_str(text),
+            Segment::Translate(_) => {
+                let Some((lead, core, trail)) = parts.next() else {
+                    continue;
+                };
+                out.push_str(lead);
+                if !core.is_empty() {
+                    out.push_str(translated.next().as_deref().unwrap_or(core).trim());
+                }
+                out.push_str(trail);
+            }
+        }
+    }
+
+    let head = &content[..content.len() - body.len()];
+    let with_link = frontmatter::set(
+        &format!("{}{}", head, out),
+        "translation_of",
+        Value::Scalar(format!("[[{}]]", note_id)),
+    );
+    Ok(frontmatter::set(
+        &with_link,
+        "lang",
+        Value::Scalar(target.to_string()),
+    ))
+}
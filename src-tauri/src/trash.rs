@@ -0,0 +1,263 @@
+//! Vault trash (`.trash/` inside the notes folder).
+//!
+//! Deleting a note moves it here instead of removing it. Each deletion gets its
+//! own entry folder containing a `manifest.json` and the files moved out of the
+//! vault with it, laid out relative to the vault root (e.g. `Work/Plan.md`,
+//! `assets/diagram.png`), so everything from one deletion can be put back
+//! together. Entries stay until the trash is emptied.
+
+use crate::{assets, claim_note_file, sidecars, ScannedNote};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+pub const TRASH_DIR: &str = ".trash";
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashManifest {
+    pub note_id: String,
+    pub deleted_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>, // vault-relative path of the note file, if it is held here
+    #[serde(default)]
+    pub assets: Vec<String>, // vault-relative paths of assets moved with the note
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub entry: String, // entry folder name, passed back to restore
+    pub note_id: String,
+    pub deleted_at: i64,
+    pub has_note: bool, // false for entries holding only assets
+    pub assets: Vec<String>,
+}
+
+/// Assets referenced by the deleted note that no other note references and
+/// no sidecar describes.
+pub fn orphaned_assets(
+    notes_root: &Path,
+    deleted_id: &str,
+    deleted_content: &str,
+    notes: &[ScannedNote],
+    layout: assets::AssetLayout,
+) -> Vec<String> {
+    assets::referenced(notes_root, deleted_id, deleted_content, layout)
+        .into_iter()
+        .filter(|path| !sidecars::has_sidecar(notes_root, path))
+        .filter(|path| {
+            !notes
+                .iter()
+                .any(|n| n.id != deleted_id && assets::references(&n.id, &n.content, path))
+        })
+        .collect()
+}
+
+/// Create a new, uniquely named trash entry for a deleted note.
+pub fn create_entry(notes_root: &Path, note_id: &str, deleted_at: i64) -> std::io::Result<PathBuf> {
+    let leaf: String = note_id
+        .rsplit('/')
+        .next()
+        .unwrap_or(note_id)
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let trash = notes_root.join(TRASH_DIR);
+    let mut entry = trash.join(format!("{}-{}", deleted_at, leaf));
+    let mut counter = 1;
+    while entry.exists() {
+        entry = trash.join(format!("{}-{}-{}", deleted_at, leaf, counter));
+        counter += 1;
+    }
+    std::fs::create_dir_all(&entry)?;
+    Ok(entry)
+}
+
+/// Move one file (a vault-relative path) into a trash entry.
+pub fn move_into(notes_root: &Path, entry: &Path, rel: &str) -> std::io::Result<()> {
+    let target = entry.join(rel);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(notes_root.join(rel), target)
+}
+
+/// Move assets (vault-relative paths) into a trash entry. Returns the paths
+/// actually moved.
+pub fn move_assets(notes_root: &Path, entry: &Path, paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .filter(|rel| notes_root.join(rel).is_file())
+        .filter(|rel| move_into(notes_root, entry, rel).is_ok())
+        .cloned()
+        .collect()
+}
+
+/// Move assets (vault-relative paths) that belong to no deleted note into a
+/// new entry of their own under `label`. Returns the paths actually moved; an
+/// entry that ends up empty is removed.
+pub fn trash_assets(
+    notes_root: &Path,
+    label: &str,
+    paths: &[String],
+) -> Result<Vec<String>, String> {
+    let deleted_at = chrono::Utc::now().timestamp();
+    let entry = create_entry(notes_root, label, deleted_at).map_err(|e| e.to_string())?;
+    let moved = move_assets(notes_root, &entry, paths);
+    if moved.is_empty() {
+        let _ = std::fs::remove_dir_all(&entry);
+        return Ok(moved);
+    }
+    let manifest = TrashManifest {
+        note_id: label.to_string(),
+        deleted_at,
+        note: None,
+        assets: moved.clone(),
+    };
+    write_manifest(&entry, &manifest)
+        .map_err(|e| format!("Failed to write the trash manifest: {}", e))?;
+    Ok(moved)
+}
+
+pub fn write_manifest(entry: &Path, manifest: &TrashManifest) -> std::io::Result<()> {
+    let content = serde_json::to_string_pretty(manifest)?;
+    std::fs::write(entry.join(MANIFEST), content)
+}
+
+pub fn read_manifest(entry: &Path) -> Option<TrashManifest> {
+    let content = std::fs::read_to_string(entry.join(MANIFEST)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Files held in a trash entry, other than its manifest.
+pub fn entry_files(entry: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    walkdir::WalkDir::new(entry)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(move |path| path.as_path() != entry.join(MANIFEST))
+}
+
+/// The entry folder named `name`, which must be a single path component.
+fn entry_path(notes_root: &Path, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err("Invalid trash entry".to_string());
+    }
+    let entry = notes_root.join(TRASH_DIR).join(name);
+    if !entry.is_dir() {
+        return Err("Trash entry not found".to_string());
+    }
+    Ok(entry)
+}
+
+/// `rel`, a vault-relative path from a manifest, joined to `dir`. Absolute
+/// paths and `.` or `..` components are refused, so an edited manifest can't
+/// reach outside the entry or the vault.
+fn contained(dir: &Path, rel: &str) -> Option<PathBuf> {
+    let path = Path::new(rel);
+    if rel.is_empty() || rel.contains('\\') {
+        return None;
+    }
+    if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let joined = dir.join(path);
+    joined.starts_with(dir).then_some(joined)
+}
+
+/// Trash entries, most recently deleted first.
+pub fn list(notes_root: &Path) -> Vec<TrashItem> {
+    let Ok(entries) = std::fs::read_dir(notes_root.join(TRASH_DIR)) else {
+        return Vec::new();
+    };
+    let mut items: Vec<TrashItem> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let manifest = read_manifest(&path)?;
+            let has_note = manifest
+                .note
+                .as_deref()
+                .is_some_and(|rel| path.join(rel).is_file());
+            Some(TrashItem {
+                entry: entry.file_name().to_string_lossy().into_owned(),
+                note_id: manifest.note_id,
+                deleted_at: manifest.deleted_at,
+                has_note,
+                assets: manifest.assets,
+            })
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        b.deleted_at
+            .cmp(&a.deleted_at)
+            .then_with(|| a.entry.cmp(&b.entry))
+    });
+    items
+}
+
+/// Put an entry's files back. The note returns under its old ID, or with a
+/// `-n` suffix if that is taken; assets whose path is taken again stay in the
+/// trash. Returns the restored note's ID, if the entry held one.
+pub fn restore(notes_root: &Path, name: &str) -> Result<Option<String>, String> {
+    let entry = entry_path(notes_root, name)?;
+    let mut manifest = read_manifest(&entry).ok_or("Trash entry has no readable manifest")?;
+
+    let mut restored_id = None;
+    if let Some(rel) = manifest.note.take() {
+        let source = contained(&entry, &rel).ok_or("Trash entry has an invalid note path")?;
+        if source.is_file() {
+            let base = rel.strip_suffix(".md").unwrap_or(&rel).to_string();
+            let (id, target) = claim_note_file(notes_root, None, |attempt| match attempt {
+                0 => base.clone(),
+                n => format!("{}-{}", base, n),
+            })
+            .map_err(|e| e.to_string())?;
+            if let Err(e) = std::fs::rename(&source, &target) {
+                let _ = std::fs::remove_file(&target);
+                return Err(format!("Failed to restore note: {}", e));
+            }
+            restored_id = Some(id);
+        }
+    }
+
+    // Assets with invalid paths stay in the entry
+    manifest.assets.retain(|rel| {
+        let (Some(source), Some(target)) = (contained(&entry, rel), contained(notes_root, rel))
+        else {
+            return true;
+        };
+        if !source.is_file() {
+            return false;
+        }
+        if target.exists() {
+            return true;
+        }
+        if let Some(parent) = target.parent() {
+            if std::fs::create_dir_all(parent).is_err() {
+                return true;
+            }
+        }
+        std::fs::rename(&source, &target).is_err()
+    });
+
+    if manifest.assets.is_empty() {
+        let _ = std::fs::remove_dir_all(&entry);
+    } else {
+        let _ = write_manifest(&entry, &manifest);
+    }
+    Ok(restored_id)
+}
+
+/// Permanently delete every trash entry. Returns how many there were.
+pub fn empty(notes_root: &Path) -> Result<usize, String> {
+    let trash = notes_root.join(TRASH_DIR);
+    let Ok(entries) = std::fs::read_dir(&trash) else {
+        return Ok(0);
+    };
+    let count = entries.flatten().count();
+    std::fs::remove_dir_all(&trash).map_err(|e| format!("Failed to empty the trash: {}", e))?;
+    Ok(count)
+}
@@ -0,0 +1,214 @@
+//! Read-aloud export: a note's text synthesized to an audio file.
+//!
+//! `synthesize` hands the note's prose (no markup, code blocks or
+//! frontmatter) to the platform's speech engine: `say` on macOS (AIFF, or
+//! whatever the output extension names, e.g. `.m4a`), System.Speech through
+//! PowerShell on Windows (WAV), and espeak-ng or espeak on Linux (WAV). A
+//! speech command set on this machine (kept in the app config, not the vault)
+//! replaces the built-in engine with any command line, with `{input}` (a UTF-8
+//! text file), `{output}` and `{voice}` filled in.
+
+use crate::{export, frontmatter};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioExport {
+    pub path: String,
+    pub engine: String,    // program that did the synthesis
+    pub characters: usize, // length of the text spoken
+}
+
+/// The text of a note as it should be read out: paragraphs, headings and list
+/// items on their own lines, code blocks, HTML and link targets left out.
+pub fn speakable_text(content: &str) -> String {
+    let mut text = String::new();
+    let mut in_code_block = false;
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    for event in Parser::new_ext(frontmatter::body(content), options) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(t) | Event::Code(t) if !in_code_block => text.push_str(&t),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            // Headings get a full stop so the engine pauses after them
+            Event::End(TagEnd::Heading(_)) => {
+                if !text.trim_end().ends_with(['.', '!', '?', ':']) {
+                    text.push('.');
+                }
+                text.push('\n');
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Item | TagEnd::TableRow) => text.push('\n'),
+            _ => {}
+        }
+    }
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    lines.join("\n")
+}
+
+fn default_extension() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "aiff"
+    } else {
+        "wav"
+    }
+}
+
+/// Run `program` with `args`, failing with its stderr.
+fn run(program: &str, args: &[String], env: &[(&str, &OsStr)]) -> Result<(), String> {
+    let mut cmd = crate::no_window_cmd(program);
+    cmd.args(args);
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// A speech command line split on whitespace, with placeholders filled in.
+fn run_custom(
+    command: &str,
+    input: &Path,
+    output: &Path,
+    voice: Option<&str>,
+) -> Result<String, String> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or("Speech command is empty")?;
+    let args: Vec<String> = parts
+        .map(|part| {
+            part.replace("{input}", &input.to_string_lossy())
+                .replace("{output}", &output.to_string_lossy())
+                .replace("{voice}", voice.unwrap_or_default())
+        })
+        .collect();
+    run(program, &args, &[])?;
+    Ok(program.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn run_builtin(input: &Path, output: &Path, voice: Option<&str>) -> Result<String, String> {
+    let mut args = vec![
+        "-f".to_string(),
+        input.to_string_lossy().into_owned(),
+        "-o".to_string(),
+        output.to_string_lossy().into_owned(),
+    ];
+    if let Some(voice) = voice {
+        args.extend(["-v".to_string(), voice.to_string()]);
+    }
+    run("say", &args, &[])?;
+    Ok("say".to_string())
+}
+
+/// Paths and voice go through the environment so nothing needs quoting.
+#[cfg(target_os = "windows")]
+fn run_builtin(input: &Path, output: &Path, voice: Option<&str>) -> Result<String, String> {
+    const SCRIPT: &str = "Add-Type -AssemblyName System.Speech; \
+$s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+if ($env:SCRATCH_TTS_VOICE) { $s.SelectVoice($env:SCRATCH_TTS_VOICE) }; \
+$s.SetOutputToWaveFile($env:SCRATCH_TTS_OUTPUT); \
+$s.Speak([IO.File]::ReadAllText($env:SCRATCH_TTS_INPUT)); \
+$s.Dispose()";
+    let args = ["-NoProfile", "-NonInteractive", "-Command", SCRIPT].map(String::from);
+    let env = [
+        ("SCRATCH_TTS_INPUT", input.as_os_str()),
+        ("SCRATCH_TTS_OUTPUT", output.as_os_str()),
+        ("SCRATCH_TTS_VOICE", OsStr::new(voice.unwrap_or_default())),
+    ];
+    run("powershell", &args, &env)?;
+    Ok("System.Speech".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn run_builtin(input: &Path, output: &Path, voice: Option<&str>) -> Result<String, String> {
+    let path = std::env::var("PATH").unwrap_or_default();
+    let program = ["espeak-ng", "espeak"]
+        .into_iter()
+        .find(|tool| crate::check_cli_exists(tool, &path).unwrap_or(false))
+        .ok_or("No speech engine found (install espeak-ng, or set a speech command)")?;
+    let mut args = vec![
+        "-f".to_string(),
+        input.to_string_lossy().into_owned(),
+        "-w".to_string(),
+        output.to_string_lossy().into_owned(),
+    ];
+    if let Some(voice) = voice {
+        args.extend(["-v".to_string(), voice.to_string()]);
+    }
+    run(program, &args, &[])?;
+    Ok(program.to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn run_builtin(_input: &Path, _output: &Path, _voice: Option<&str>) -> Result<String, String> {
+    Err("No built-in speech engine on this platform; set a speech command".to_string())
+}
+
+/// Speak `content` into `path`, an absolute file path outside the vault. A
+/// missing extension becomes the engine's native format. Blocking; long notes
+/// take a while.
+pub fn synthesize(
+    content: &str,
+    path: &str,
+    voice: Option<&str>,
+    command: Option<&str>,
+    notes_root: &Path,
+) -> Result<AudioExport, String> {
+    let text = speakable_text(content);
+    if text.is_empty() {
+        return Err("The note has no text to read".to_string());
+    }
+    let voice = voice.map(str::trim).filter(|v| !v.is_empty());
+    if voice.is_some_and(|v| v.starts_with('-')) {
+        return Err("Invalid voice name".to_string());
+    }
+
+    let requested = PathBuf::from(path.trim());
+    let (Some(parent), Some(name)) = (requested.parent(), requested.file_name()) else {
+        return Err("Export path must be a file path".to_string());
+    };
+    let mut path = export::validate_output_dir(&parent.to_string_lossy(), notes_root)?.join(name);
+    if path.extension().is_none() {
+        path.set_extension(default_extension());
+    }
+
+    let input = std::env::temp_dir().join(format!(
+        "scratch-tts-{}-{}.txt",
+        std::process::id(),
+        chrono::Utc::now().timestamp_millis()
+    ));
+    std::fs::write(&input, &text).map_err(|e| format!("Failed to write text: {}", e))?;
+    let result = match command.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(command) => run_custom(command, &input, &path, voice),
+        None => run_builtin(&input, &path, voice),
+    };
+    let _ = std::fs::remove_file(&input);
+    let engine = result?;
+
+    if !path.is_file() {
+        return Err(format!("{} didn't write an audio file", engine));
+    }
+    Ok(AudioExport {
+        path: path.to_string_lossy().into_owned(),
+        engine,
+        characters: text.chars().count(),
+    })
+}
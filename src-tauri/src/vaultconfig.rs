@@ -0,0 +1,183 @@
+//! Vault options kept with the vault.
+//!
+//! `.scratch/config.json` holds settings that belong to the vault rather than
+//! to one machine's copy of it, so it can be committed and shared: sort
+//! order (`noteSort`), ignore patterns (`watcherIgnore`), and the daily notes
+//! folder, file name format and template. Only those keys are read; a shared
+//! vault must not be able to turn on code blocks, change the privacy
+//! settings or point the app at other programs and servers, so any other
+//! key is ignored with a warning. Its values are merged over
+//! `.scratch/settings.json` whenever settings load, objects key by key, and
+//! win over changes made in the app. `settings.json` keeps its own values
+//! for those keys, so taking a key out of the config brings the app's value
+//! back. The file watcher reloads the config when it changes on disk.
+
+use crate::{AppState, Settings};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+const FILE: &str = "config.json";
+const VAULT_OPTIONS: &[&str] = &[
+    "noteSort",
+    "watcherIgnore",
+    "dailyNotesFolder",
+    "dailyNoteFormat",
+    "dailyTemplate",
+];
+
+pub fn path(notes_root: &Path) -> PathBuf {
+    notes_root.join(".scratch").join(FILE)
+}
+
+pub fn is_config_file(notes_root: &Path, path: &Path) -> bool {
+    path == self::path(notes_root)
+}
+
+/// The config's top-level object, or None without a config file.
+fn read_file(notes_root: &Path) -> Result<Option<Map<String, Value>>, String> {
+    let content = match std::fs::read_to_string(path(notes_root)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", FILE, e)),
+    };
+    match serde_json::from_str(&content) {
+        Ok(Value::Object(config)) => Ok(Some(config)),
+        Ok(_) => Err(format!("{} must hold a JSON object", FILE)),
+        Err(e) => Err(format!("Invalid {}: {}", FILE, e)),
+    }
+}
+
+/// Drop the keys that are not vault options, returning them.
+fn retain_vault_options(config: &mut Map<String, Value>) -> Vec<String> {
+    let ignored: Vec<String> = config
+        .keys()
+        .filter(|key| !VAULT_OPTIONS.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    config.retain(|key, _| VAULT_OPTIONS.contains(&key.as_str()));
+    ignored
+}
+
+/// The config's vault options, or None without a config file.
+pub fn read(notes_root: &Path) -> Result<Option<Map<String, Value>>, String> {
+    Ok(read_file(notes_root)?.map(|mut config| {
+        retain_vault_options(&mut config);
+        config
+    }))
+}
+
+fn merge(base: &mut Map<String, Value>, overlay: &Map<String, Value>) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Object(inner)), Value::Object(over)) => merge(inner, over),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Merge the vault config over `settings`. An error leaves them as they
+/// were: the config does not parse or does not fit the settings.
+pub fn apply(notes_root: &Path, settings: &mut Settings) -> Result<(), String> {
+    let Some(mut config) = read_file(notes_root)? else {
+        return Ok(());
+    };
+    let ignored = retain_vault_options(&mut config);
+    if !ignored.is_empty() {
+        eprintln!(
+            "Ignoring {} in {}: not vault options",
+            ignored.join(", "),
+            FILE
+        );
+    }
+    let Ok(Value::Object(mut merged)) = serde_json::to_value(&*settings) else {
+        return Ok(());
+    };
+    merge(&mut merged, &config);
+    *settings = serde_json::from_value(Value::Object(merged))
+        .map_err(|e| format!("Invalid {}: {}", FILE, e))?;
+    Ok(())
+}
+
+/// Put back the values `settings.json` (`own`) has for the keys the config
+/// sets, so saving never copies the config into it. Keys it never had get
+/// their defaults.
+fn restore(
+    value: &mut Map<String, Value>,
+    config: &Map<String, Value>,
+    own: Option<&Map<String, Value>>,
+    defaults: Option<&Map<String, Value>>,
+) {
+    for (key, configured) in config {
+        let own_value = own.and_then(|own| own.get(key));
+        let default = defaults.and_then(|defaults| defaults.get(key));
+        match (value.get_mut(key), configured) {
+            (Some(Value::Object(inner)), Value::Object(configured)) => restore(
+                inner,
+                configured,
+                own_value.and_then(Value::as_object),
+                default.and_then(Value::as_object),
+            ),
+            (Some(slot), _) => {
+                *slot = own_value.or(default).cloned().unwrap_or(Value::Null);
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+/// The JSON to write to `settings.json` for `settings`: its own values for
+/// whatever the vault config sets, the rest as given.
+pub fn settings_file_value(
+    notes_root: &Path,
+    settings: &Settings,
+    settings_file: &Path,
+) -> Result<Value, serde_json::Error> {
+    let mut value = serde_json::to_value(settings)?;
+    let Ok(Some(config)) = read(notes_root) else {
+        return Ok(value);
+    };
+    let own: Option<Value> = std::fs::read_to_string(settings_file)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let defaults = serde_json::to_value(Settings::default())?;
+    if let Value::Object(map) = &mut value {
+        restore(
+            map,
+            &config,
+            own.as_ref().and_then(Value::as_object),
+            defaults.as_object(),
+        );
+    }
+    Ok(value)
+}
+
+/// Re-read the settings after the config changed on disk and apply them.
+/// Emits "vault-config-changed" with the settings, or "vault-config-error"
+/// with the reason when the config is broken; the settings in use then stay.
+pub fn reload(app: &AppHandle, notes_root: &Path) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let folder = notes_root.to_string_lossy();
+    let mut settings = crate::load_own_settings(&folder);
+    if let Err(e) = apply(notes_root, &mut settings) {
+        let _ = app.emit("vault-config-error", e);
+        return;
+    }
+    // Editors write in several steps; only a real change is applied
+    let unchanged = {
+        let current = state.settings.read().expect("settings read lock");
+        serde_json::to_value(&*current).ok() == serde_json::to_value(&settings).ok()
+    };
+    if unchanged {
+        return;
+    }
+    if let Err(e) = crate::apply_settings(app, &state, &folder, settings.clone()) {
+        let _ = app.emit("vault-config-error", e.to_string());
+        return;
+    }
+    let _ = app.emit("vault-config-changed", crate::settings_for_webview(&settings));
+}
@@ -0,0 +1,162 @@
+//! Known vaults and switching between them.
+//!
+//! The app config keeps the vaults this machine knows (a name and a path)
+//! beside the active `notes_folder`; every folder opened as the notes folder
+//! is added, and `add_vault` registers one without opening it. Before another
+//! vault is opened, the current one is closed: pending autosaves are written
+//! (a failed write stops the switch), the search index is committed, live
+//! sessions, the vault server and the file watcher end, and the notes cache,
+//! link index, autosave bookkeeping and privacy unlock are dropped, since
+//! note IDs mean different notes in another vault. Opening the new vault
+//! then loads its settings, index and watcher, and the vault lock follows.
+
+use crate::{autosave, sanitize_filename, AppConfig, AppState};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vault {
+    pub name: String,
+    pub path: String, // absolute
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultInfo {
+    pub name: String,
+    pub path: String,
+    pub active: bool,
+    pub available: bool, // false while the folder is missing, e.g. on an unmounted drive
+}
+
+/// Pick a unique, filename-safe name for a new vault.
+fn unique_name(vaults: &[Vault], requested: &str) -> String {
+    let base = sanitize_filename(requested.trim());
+    let base = if base.is_empty() {
+        "vault".to_string()
+    } else {
+        base
+    };
+    let mut name = base.clone();
+    let mut counter = 1;
+    while vaults.iter().any(|v| v.name.eq_ignore_ascii_case(&name)) {
+        name = format!("{}-{}", base, counter);
+        counter += 1;
+    }
+    name
+}
+
+fn folder_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+pub fn list(config: &AppConfig) -> Vec<VaultInfo> {
+    config
+        .vaults
+        .iter()
+        .map(|vault| VaultInfo {
+            name: vault.name.clone(),
+            path: vault.path.clone(),
+            active: config.notes_folder.as_deref() == Some(vault.path.as_str()),
+            available: Path::new(&vault.path).is_dir(),
+        })
+        .collect()
+}
+
+/// A known vault by name (case-insensitive) or path.
+pub fn find<'a>(config: &'a AppConfig, name_or_path: &str) -> Option<&'a Vault> {
+    config
+        .vaults
+        .iter()
+        .find(|v| v.name.eq_ignore_ascii_case(name_or_path))
+        .or_else(|| config.vaults.iter().find(|v| v.path == name_or_path))
+}
+
+/// Add the vault at `path` under `name` (default: the folder name).
+pub fn add(config: &mut AppConfig, path: &str, name: Option<&str>) -> Result<Vault, String> {
+    if config.vaults.iter().any(|v| v.path == path) {
+        return Err("This folder is already a vault".to_string());
+    }
+    let requested = name.map(String::from).unwrap_or_else(|| folder_name(path));
+    let vault = Vault {
+        name: unique_name(&config.vaults, &requested),
+        path: path.to_string(),
+    };
+    config.vaults.push(vault.clone());
+    Ok(vault)
+}
+
+/// Add the folder being opened as the notes folder, if it is new.
+pub fn remember(config: &mut AppConfig, path: &str) {
+    if !config.vaults.iter().any(|v| v.path == path) {
+        let _ = add(config, path, None);
+    }
+}
+
+/// Forget a vault. Its folder is left as it is; the open vault stays known.
+pub fn remove(config: &mut AppConfig, name: &str) -> Result<(), String> {
+    let Some(index) = config
+        .vaults
+        .iter()
+        .position(|v| v.name.eq_ignore_ascii_case(name))
+    else {
+        return Err("Vault not found".to_string());
+    };
+    if config.notes_folder.as_deref() == Some(config.vaults[index].path.as_str()) {
+        return Err("Switch to another vault before removing this one".to_string());
+    }
+    config.vaults.remove(index);
+    Ok(())
+}
+
+/// Close the open vault before another one is opened: write pending
+/// autosaves, then let go of everything tied to it.
+pub async fn close(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let failed = autosave::flush_all(&state).await;
+    if !failed.is_empty() {
+        return Err(format!(
+            "Failed to save before switching vaults: {}",
+            failed.join(", ")
+        ));
+    }
+    release(&state);
+    Ok(())
+}
+
+/// The synchronous part of `close`, once nothing is pending.
+fn release(state: &AppState) {
+    state
+        .file_watcher
+        .lock()
+        .expect("file watcher mutex")
+        .take();
+    state
+        .collab_session
+        .lock()
+        .expect("collab session mutex")
+        .take();
+    state
+        .vault_server
+        .lock()
+        .expect("vault server mutex")
+        .take();
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.commit();
+        }
+    }
+    state.notes_cache.write().expect("cache write lock").clear();
+    state.link_index.invalidate();
+    state.autosave.reset();
+    *state
+        .privacy_unlocked_until
+        .lock()
+        .expect("privacy unlock mutex") = None;
+}
@@ -28,6 +28,8 @@ import {
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import * as aiService from "./services/ai";
 import type { AiProvider } from "./services/ai";
+import { createNoteFromClipboard } from "./services/notes";
+import { errorMessage } from "./lib/errors";
 
 // Detect preview mode from URL search params
 function getWindowMode(): {
@@ -302,6 +304,19 @@ function AppContent() {
         return;
       }
 
+      // Cmd+Shift+N - New note from clipboard
+      if (
+        (e.metaKey || e.ctrlKey) &&
+        e.shiftKey &&
+        e.key.toLowerCase() === "n"
+      ) {
+        e.preventDefault();
+        createNoteFromClipboard().catch((err) =>
+          toast.error(errorMessage(err)),
+        );
+        return;
+      }
+
       // Cmd+N - New note
       if ((e.metaKey || e.ctrlKey) && e.key === "n") {
         e.preventDefault();
@@ -30,6 +30,7 @@ import {
 } from "../ui";
 import { cleanTitle } from "../../lib/utils";
 import { plainTextFromMarkdown } from "../../lib/plainText";
+import { errorMessage } from "../../lib/errors";
 import { duplicateNote } from "../../services/notes";
 import {
   CopyIcon,
@@ -89,6 +90,7 @@ export function CommandPalette({
     refreshNotes,
     pinNote,
     unpinNote,
+    reloadCurrentNote,
     notesFolder,
   } = useNotes();
   const { setTheme } = useTheme();
@@ -153,6 +155,20 @@ export function CommandPalette({
           onClose();
         },
       },
+      {
+        id: "new-note-from-clipboard",
+        label: "New Note from Clipboard",
+        shortcut: `${mod} ${shift} N`,
+        icon: <AddNoteIcon className="w-4.5 h-4.5 stroke-[1.5]" />,
+        action: () => {
+          onClose();
+          notesService
+            .createNoteFromClipboard()
+            .catch((err) =>
+              toast.error(errorMessage(err)),
+            );
+        },
+      },
       {
         id: "new-folder",
         label: "New Folder",
@@ -233,6 +249,22 @@ export function CommandPalette({
             }
           },
         },
+        {
+          id: "add-current-location",
+          label: "Add Current Location",
+          icon: <PinIcon className="w-5 h-5 stroke-[1.3]" />,
+          action: async () => {
+            onClose();
+            try {
+              const location = await notesService.getCurrentLocation();
+              await notesService.setNoteLocation(currentNote.id, location);
+              await reloadCurrentNote();
+              toast.success(`Location set to ${location}`);
+            } catch (error) {
+              toast.error(errorMessage(error));
+            }
+          },
+        },
         ...aiCommands,
         {
           id: "duplicate-note",
@@ -319,7 +351,7 @@ export function CommandPalette({
                 toast.error("Editor not available");
                 return;
               }
-              await downloadPdf(editorRef.current, currentNote.title);
+              await downloadPdf(editorRef.current, currentNote.title, currentNote.id);
               // Note: window.print() opens the print dialog but doesn't wait for user action
               // No success toast needed - the print dialog provides its own feedback
               onClose();
@@ -509,6 +541,7 @@ export function CommandPalette({
     settings,
     pinNote,
     unpinNote,
+    reloadCurrentNote,
     focusMode,
     onToggleFocusMode,
     notesFolder,
@@ -756,8 +789,8 @@ export function CommandPalette({
           <AlertDialogHeader>
             <AlertDialogTitle>Delete note?</AlertDialogTitle>
             <AlertDialogDescription>
-              This will permanently delete the note and all its content. This
-              action cannot be undone.
+              The note will be moved to the trash, where it can be restored
+              until the trash is emptied.
             </AlertDialogDescription>
           </AlertDialogHeader>
           <AlertDialogFooter>
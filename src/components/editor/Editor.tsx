@@ -456,6 +456,10 @@ export function Editor({
         }
       : null
     : (notesCtx?.currentNote ?? null);
+  // Note language from the listing, so spellcheck picks the right dictionary
+  const noteLanguage = currentNote
+    ? notesCtx?.notes.find((n) => n.id === currentNote.id)?.language
+    : undefined;
 
   const saveNote = previewMode
     ? async (content: string, _noteId?: string) => {
@@ -1066,10 +1070,11 @@ export function Editor({
 
               try {
                 // Save clipboard image
-                const relativePath = await invoke<string>(
-                  "save_clipboard_image",
-                  { base64Data: base64 },
+                const markdown = await notesService.saveClipboardImage(
+                  currentNoteIdRef.current,
+                  base64,
                 );
+                const relativePath = notesService.assetPathFromMarkdown(markdown);
 
                 // Get notes folder and construct absolute path using Tauri's join
                 const notesFolder = await invoke<string>("get_notes_folder");
@@ -1234,7 +1239,17 @@ export function Editor({
           if (note) {
             notesCtxRef.current?.selectNote(note.id);
           } else {
-            toast.info(`Note "${noteTitle}" does not exist yet`);
+            // The note may have been renamed or moved since the link was written
+            notesService
+              .resolveId(noteTitle)
+              .then((id) => {
+                if (id) {
+                  notesCtxRef.current?.selectNote(id);
+                } else {
+                  toast.info(`Note "${noteTitle}" does not exist yet`);
+                }
+              })
+              .catch(() => toast.info(`Note "${noteTitle}" does not exist yet`));
           }
         }
         return;
@@ -1572,9 +1587,10 @@ export function Editor({
     });
     if (selected) {
       try {
-        // Copy image to assets folder and get relative path (assets/filename.ext)
+        // Copy image to the note's asset folder and get its vault-relative path
         const relativePath = await invoke<string>("copy_image_to_assets", {
           sourcePath: selected as string,
+          noteId: currentNoteIdRef.current,
         });
 
         // Get notes folder and construct absolute path using Tauri's join
@@ -1735,7 +1751,7 @@ export function Editor({
   const handleDownloadPdf = useCallback(async () => {
     if (!editor || !currentNote) return;
     try {
-      await downloadPdf(editor, currentNote.title);
+      await downloadPdf(editor, currentNote.title, currentNote.id);
       // Note: window.print() opens the print dialog but doesn't wait for user action
       // No success toast needed - the print dialog provides its own feedback
     } catch (error) {
@@ -2108,6 +2124,7 @@ export function Editor({
           ref={scrollContainerRef}
           className="absolute inset-0 overflow-y-auto overflow-x-hidden"
           dir={textDirection}
+          lang={noteLanguage ?? undefined}
         >
           {sourceMode ? (
             /* Markdown source textarea */
@@ -360,8 +360,8 @@ export function NoteList() {
             <AlertDialogHeader>
               <AlertDialogTitle>Delete note?</AlertDialogTitle>
               <AlertDialogDescription>
-                This will permanently delete the note and all its content. This
-                action cannot be undone.
+                The note will be moved to the trash, where it can be restored
+                until the trash is emptied.
               </AlertDialogDescription>
             </AlertDialogHeader>
             <AlertDialogFooter>
@@ -409,8 +409,8 @@ export function NoteList() {
           <AlertDialogHeader>
             <AlertDialogTitle>Delete note?</AlertDialogTitle>
             <AlertDialogDescription>
-              This will permanently delete the note and all its content. This
-              action cannot be undone.
+              The note will be moved to the trash, where it can be restored
+              until the trash is emptied.
             </AlertDialogDescription>
           </AlertDialogHeader>
           <AlertDialogFooter>
@@ -4,6 +4,7 @@ import { getCurrentWindow } from "@tauri-apps/api/window";
 import { toast } from "sonner";
 import { Editor, type PreviewModeData } from "../editor/Editor";
 import * as filesService from "../../services/files";
+import { errorMessage } from "../../lib/errors";
 
 interface PreviewAppProps {
   filePath: string;
@@ -29,7 +30,7 @@ export function PreviewApp({ filePath }: PreviewAppProps) {
       })
       .catch((error) => {
         console.error("Failed to load file:", error);
-        toast.error(`Failed to load file: ${error}`);
+        toast.error(`Failed to load file: ${errorMessage(error)}`);
       });
   }, [filePath]);
 
@@ -64,7 +65,7 @@ export function PreviewApp({ filePath }: PreviewAppProps) {
         setHasExternalChanges(false);
       } catch (error) {
         console.error("Failed to save file:", error);
-        toast.error(`Failed to save: ${error}`);
+        toast.error(`Failed to save: ${errorMessage(error)}`);
       }
     },
     [filePath],
@@ -80,7 +81,7 @@ export function PreviewApp({ filePath }: PreviewAppProps) {
       setReloadVersion((v) => v + 1);
     } catch (error) {
       console.error("Failed to reload file:", error);
-      toast.error(`Failed to reload: ${error}`);
+      toast.error(`Failed to reload: ${errorMessage(error)}`);
     }
   }, [filePath]);
 
@@ -154,7 +155,7 @@ export function PreviewApp({ filePath }: PreviewAppProps) {
       await getCurrentWindow().close();
     } catch (error) {
       console.error("Failed to save to folder:", error);
-      toast.error(`Failed to save to folder: ${error}`);
+      toast.error(`Failed to save to folder: ${errorMessage(error)}`);
     } finally {
       savingRef.current = false;
       setIsSaving(false);
@@ -22,6 +22,11 @@ const shortcuts: Shortcut[] = [
     description: "Create new note",
     category: "Notes",
   },
+  {
+    keys: [mod, "Shift", "N"],
+    description: "New note from clipboard",
+    category: "Notes",
+  },
   {
     keys: [mod, "D"],
     description: "Duplicate current note",
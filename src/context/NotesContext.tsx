@@ -9,9 +9,30 @@ import {
   type ReactNode,
 } from "react";
 import { listen } from "@tauri-apps/api/event";
-import type { Note, NoteMetadata } from "../types/note";
+import type { Note, NoteMetadata, NotesDelta } from "../types/note";
 import * as notesService from "../services/notes";
-import type { SearchResult } from "../services/notes";
+import type { LinkRewrite, SearchResult } from "../services/notes";
+
+// Apply a `notes-updated` delta to the list, keeping list_notes' default
+// order: pinned notes first, then newest first
+function applyNotesDelta(
+  notes: NoteMetadata[],
+  delta: NotesDelta,
+  pinnedIds: Set<string>
+): NoteMetadata[] {
+  const touched = new Set([
+    ...delta.removed,
+    ...delta.upserted.map((note) => note.id),
+  ]);
+  const next = [
+    ...notes.filter((note) => !touched.has(note.id)),
+    ...delta.upserted,
+  ];
+  return next.sort((a, b) => {
+    const pinned = Number(pinnedIds.has(b.id)) - Number(pinnedIds.has(a.id));
+    return pinned !== 0 ? pinned : b.modified - a.modified;
+  });
+}
 
 // Separate contexts to prevent unnecessary re-renders
 // Data context: changes frequently, only subscribed by components that need the data
@@ -35,7 +56,7 @@ interface NotesActionsContextValue {
   createNote: () => Promise<void>;
   consumePendingNewNote: (id: string) => boolean;
   saveNote: (content: string, noteId?: string) => Promise<void>;
-  deleteNote: (id: string) => Promise<void>;
+  deleteNote: (id: string, rewriteLinks?: LinkRewrite) => Promise<void>;
   duplicateNote: (id: string) => Promise<void>;
   refreshNotes: () => Promise<void>;
   reloadCurrentNote: () => Promise<void>;
@@ -90,7 +111,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
   const refreshNotes = useCallback(async () => {
     if (!notesFolder) return;
     try {
-      const notesList = await notesService.listNotes();
+      const { notes: notesList } = await notesService.listNotes();
       setNotes(notesList);
     } catch (err) {
       setError(err instanceof Error ? err.message : "Failed to load notes");
@@ -252,9 +273,9 @@ export function NotesProvider({ children }: { children: ReactNode }) {
   );
 
   const deleteNote = useCallback(
-    async (id: string) => {
+    async (id: string, rewriteLinks?: LinkRewrite) => {
       try {
-        await notesService.deleteNote(id);
+        await notesService.deleteNote(id, rewriteLinks);
 
         // Clean up pinned status for deleted note
         const currentSettings = await notesService.getSettings();
@@ -310,11 +331,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
         const pinnedIds = currentSettings.pinnedNoteIds || [];
 
         if (!pinnedIds.includes(id)) {
-          const updatedSettings = {
-            ...currentSettings,
-            pinnedNoteIds: [...pinnedIds, id],
-          };
-          await notesService.updateSettings(updatedSettings);
+          await notesService.togglePin(id);
           await refreshNotes();
         }
       } catch (err) {
@@ -330,12 +347,10 @@ export function NotesProvider({ children }: { children: ReactNode }) {
         const currentSettings = await notesService.getSettings();
         const pinnedIds = currentSettings.pinnedNoteIds || [];
 
-        const updatedSettings = {
-          ...currentSettings,
-          pinnedNoteIds: pinnedIds.filter((pinId) => pinId !== id),
-        };
-        await notesService.updateSettings(updatedSettings);
-        await refreshNotes();
+        if (pinnedIds.includes(id)) {
+          await notesService.togglePin(id);
+          await refreshNotes();
+        }
       } catch (err) {
         setError(err instanceof Error ? err.message : "Failed to unpin note");
       }
@@ -505,10 +520,9 @@ export function NotesProvider({ children }: { children: ReactNode }) {
 
   const setNotesFolder = useCallback(async (path: string) => {
     try {
+      // The backend also moves the file watcher to the new folder
       await notesService.setNotesFolder(path);
       setNotesFolderState(path);
-      // Start file watcher after setting folder
-      await notesService.startFileWatcher();
     } catch (err) {
       setError(
         err instanceof Error ? err.message : "Failed to set notes folder"
@@ -523,9 +537,8 @@ export function NotesProvider({ children }: { children: ReactNode }) {
       setNotesFolderState(path);
       setSelectedNoteId(null);
       setCurrentNote(null);
-      const notesList = await notesService.listNotes();
+      const { notes: notesList } = await notesService.listNotes();
       setNotes(notesList);
-      await notesService.startFileWatcher();
     } catch (err) {
       setError(
         err instanceof Error ? err.message : "Failed to sync notes folder"
@@ -604,7 +617,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
         const folder = await notesService.getNotesFolder();
         setNotesFolderState(folder);
         if (folder) {
-          const notesList = await notesService.listNotes();
+          const { notes: notesList } = await notesService.listNotes();
           setNotes(notesList);
           // Start file watcher
           await notesService.startFileWatcher();
@@ -634,15 +647,11 @@ export function NotesProvider({ children }: { children: ReactNode }) {
         (id) => !recentlySavedRef.current.has(id)
       );
 
-      // Only refresh if there are external changes
-      if (externalChanges.length > 0) {
-        refreshNotes();
-
-        // If the currently selected note was changed externally, set flag (don't auto-reload)
-        const currentId = selectedNoteIdRef.current;
-        if (currentId && externalChanges.includes(currentId)) {
-          setHasExternalChanges(true);
-        }
+      // The note list itself is updated from `notes-updated`. If the
+      // currently selected note was changed externally, set flag (don't auto-reload)
+      const currentId = selectedNoteIdRef.current;
+      if (currentId && externalChanges.includes(currentId)) {
+        setHasExternalChanges(true);
       }
     }).then((fn) => {
       if (isCancelled) {
@@ -653,6 +662,56 @@ export function NotesProvider({ children }: { children: ReactNode }) {
       }
     });
 
+    return () => {
+      isCancelled = true;
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, []);
+
+  // Patch the note list with cache changes the file watcher sends
+  useEffect(() => {
+    let isCancelled = false;
+    let unlisten: (() => void) | undefined;
+
+    listen<NotesDelta>("notes-updated", async (event) => {
+      if (isCancelled) return;
+      const { pinnedNoteIds } = await notesService.getSettings();
+      const pinnedIds = new Set(pinnedNoteIds || []);
+      setNotes((prev) => applyNotesDelta(prev, event.payload, pinnedIds));
+    }).then((fn) => {
+      if (isCancelled) {
+        fn();
+      } else {
+        unlisten = fn;
+      }
+    });
+
+    return () => {
+      isCancelled = true;
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, []);
+
+  // Re-sort the note list when the vault config changes on disk
+  useEffect(() => {
+    let isCancelled = false;
+    let unlisten: (() => void) | undefined;
+
+    listen("vault-config-changed", () => {
+      if (isCancelled) return;
+      refreshNotes();
+    }).then((fn) => {
+      if (isCancelled) {
+        fn();
+      } else {
+        unlisten = fn;
+      }
+    });
+
     return () => {
       isCancelled = true;
       if (unlisten) {
@@ -0,0 +1,42 @@
+/**
+ * Errors rejected by Tauri commands (see src-tauri/src/error.rs).
+ */
+
+export type ScratchErrorCode =
+  | "folderNotSet"
+  | "noteNotFound"
+  | "notFound"
+  | "permissionDenied"
+  | "alreadyExists"
+  | "diskFull"
+  | "invalidInput"
+  | "io"
+  | "tryAgain" // may succeed if retried, e.g. new note names taken by racing saves
+  | "other";
+
+export interface ScratchError {
+  code: ScratchErrorCode;
+  message: string;
+  id?: string; // note ID, for noteNotFound
+  path?: string; // file involved, for I/O errors
+}
+
+/** Whether `err` is a command error, optionally with the given code. */
+export function isScratchError(
+  err: unknown,
+  code?: ScratchErrorCode,
+): err is ScratchError {
+  if (typeof err !== "object" || err === null) return false;
+  const candidate = err as Partial<ScratchError>;
+  if (typeof candidate.code !== "string") return false;
+  if (typeof candidate.message !== "string") return false;
+  return code === undefined || candidate.code === code;
+}
+
+/** Text to show for an error from a command, a thrown Error or a string. */
+export function errorMessage(err: unknown, fallback = "Unknown error"): string {
+  if (err instanceof Error) return err.message;
+  if (isScratchError(err)) return err.message;
+  if (typeof err === "string") return err;
+  return fallback;
+}
@@ -34,3 +34,89 @@ export async function importFileToFolder(
 ): Promise<ImportedNote> {
   return invoke("import_file_to_folder", { path });
 }
+
+export type CollisionPolicy = "skip" | "rename" | "overwrite";
+
+export interface ImportOptions {
+  collision?: CollisionPolicy;
+  dryRun?: boolean;
+  targetFolder?: string;
+}
+
+export interface PlannedNote {
+  source: string;
+  requestedId: string;
+  id: string;
+  action: "create" | "rename" | "overwrite" | "skip";
+}
+
+export interface ImportReport {
+  dryRun: boolean;
+  notes: PlannedNote[];
+  created: number;
+  overwritten: number;
+  skipped: number;
+  failed: { source: string; error: string }[];
+  warnings: ImportWarning[];
+}
+
+// Files the importLimits setting left out, and an oversized vault. A single
+// attachment that takes the vault past the warning size emits "import-warning"
+export type ImportWarning =
+  | { kind: "tooLarge"; source: string; bytes: number; limit: number }
+  | { kind: "extensionNotAllowed"; source: string; extension: string }
+  | { kind: "vaultSize"; bytes: number; limit: number };
+
+// Emitted as "import-progress" while an import runs
+export interface ImportProgress {
+  current: number;
+  total: number;
+  source: string;
+  id: string;
+}
+
+export async function importMarkdownFiles(
+  paths: string[],
+  options?: ImportOptions,
+): Promise<ImportReport> {
+  return invoke("import_markdown_files", { paths, options });
+}
+
+export async function importEnex(
+  path: string,
+  options?: ImportOptions,
+): Promise<ImportReport> {
+  return invoke("import_enex", { path, options });
+}
+
+// Plain text, org-mode, textile, RTF and markdown files, or folders of them
+export async function importFiles(
+  paths: string[],
+  options?: ImportOptions,
+): Promise<ImportReport> {
+  return invoke("import_files", { paths, options });
+}
+
+// A .jex archive or a RAW export folder
+export async function importJoplin(
+  path: string,
+  options?: ImportOptions,
+): Promise<ImportReport> {
+  return invoke("import_joplin", { path, options });
+}
+
+export interface ImportableSource {
+  app: "obsidian" | "appleNotes" | "evernote" | "joplin";
+  name: string;
+  path: string;
+  notes: number | null; // null when only the app can count them
+  // markdownFiles: importMarkdownFiles([path]); enex: importEnex(path);
+  // joplin: importJoplin(path); exportFirst: the app has to export first, see hint
+  importer: "markdownFiles" | "enex" | "joplin" | "exportFirst";
+  hint: string | null;
+}
+
+// Notes from other apps found on this computer, for the import flow
+export async function detectImportableSources(): Promise<ImportableSource[]> {
+  return invoke("detect_importable_sources");
+}
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { Note } from "../types/note";
 
 export interface GitStatus {
   isRepo: boolean;
@@ -18,6 +19,15 @@ export interface GitResult {
   error: string | null;
 }
 
+export interface GitCommit {
+  rev: string;
+  shortRev: string;
+  author: string;
+  timestamp: number; // unix seconds
+  message: string;
+  path: string; // the note's path in this commit
+}
+
 export async function isGitAvailable(): Promise<boolean> {
   return invoke("git_is_available");
 }
@@ -53,3 +63,16 @@ export async function addRemote(url: string): Promise<GitResult> {
 export async function pushWithUpstream(): Promise<GitResult> {
   return invoke("git_push_with_upstream");
 }
+
+export async function getGitHistory(id: string): Promise<GitCommit[]> {
+  return invoke("git_history", { id });
+}
+
+// The changes the commit made to the note, as a unified diff
+export async function getGitDiff(id: string, rev: string): Promise<string> {
+  return invoke("git_diff", { id, rev });
+}
+
+export async function gitRestore(id: string, rev: string): Promise<Note> {
+  return invoke("git_restore", { id, rev });
+}
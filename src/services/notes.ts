@@ -1,16 +1,136 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { Note, NoteMetadata, Settings } from "../types/note";
+import type {
+  AutomationTrigger,
+  CodeBlockSettings,
+  Note,
+  NoteMetadata,
+  NotePage,
+  NoteSort,
+  RetentionAction,
+  Settings,
+  ThemeFile,
+  ThemeInfo,
+} from "../types/note";
 
 export async function getNotesFolder(): Promise<string | null> {
   return invoke("get_notes_folder");
 }
 
+// Current ID for a note that may have been renamed, moved or redirected
+// since `id` was stored; also accepts a wikilink target
+export async function resolveId(id: string): Promise<string | null> {
+  return invoke("resolve_id", { id });
+}
+
+// Absolute path for a vault-relative path such as Note.path
+export async function resolveVaultPath(path: string): Promise<string> {
+  return invoke("resolve_vault_path", { path });
+}
+
 export async function setNotesFolder(path: string): Promise<void> {
   return invoke("set_notes_folder", { path });
 }
 
-export async function listNotes(): Promise<NoteMetadata[]> {
-  return invoke("list_notes");
+export interface VaultInfo {
+  name: string;
+  path: string;
+  active: boolean;
+  available: boolean; // false while the folder is missing, e.g. an unmounted drive
+}
+
+// Vaults known on this machine; every folder opened as the notes folder is added
+export async function listVaults(): Promise<VaultInfo[]> {
+  return invoke("list_vaults");
+}
+
+// Register a folder as a vault without opening it
+export async function addVault(path: string, name?: string): Promise<VaultInfo> {
+  return invoke("add_vault", { path, name: name ?? null });
+}
+
+export async function removeVault(name: string): Promise<void> {
+  return invoke("remove_vault", { name });
+}
+
+// Close the open vault (writing pending autosaves) and open another, by name
+// or path; windows also get "set-notes-folder"
+export async function switchVault(name: string): Promise<VaultInfo[]> {
+  return invoke("switch_vault", { name });
+}
+
+export type VaultTemplate = "zettelkasten" | "para" | "journal" | "blank";
+
+export interface VaultTemplateInfo {
+  template: VaultTemplate;
+  name: string;
+  description: string;
+}
+
+export interface CreatedVault {
+  path: string;
+  notes: string[]; // IDs of the starter notes
+  home: string | null; // the note to open first
+}
+
+export async function listVaultTemplates(): Promise<VaultTemplateInfo[]> {
+  return invoke("list_vault_templates");
+}
+
+// Lay out a new vault in a missing or empty folder and open it
+export async function createVault(
+  path: string,
+  template: VaultTemplate,
+): Promise<CreatedVault> {
+  return invoke("create_vault", { path, template });
+}
+
+// Read-only folders outside the vault; their notes get `.sources/<name>/` IDs
+export interface SourceFolder {
+  name: string;
+  path: string;
+}
+
+export async function listSourceFolders(): Promise<SourceFolder[]> {
+  return invoke("list_source_folders");
+}
+
+export async function addSourceFolder(
+  path: string,
+  name?: string
+): Promise<SourceFolder> {
+  return invoke("add_source_folder", { path, name });
+}
+
+export async function removeSourceFolder(name: string): Promise<void> {
+  return invoke("remove_source_folder", { name });
+}
+
+// Pinned notes come first; without a limit, the rest of the list is returned
+export async function listNotes(
+  sortBy?: NoteSort,
+  offset?: number,
+  limit?: number
+): Promise<NotePage> {
+  return invoke("list_notes", { sortBy, offset, limit });
+}
+
+// Pins or unpins a note; resolves to whether it is pinned now
+export async function togglePin(id: string): Promise<boolean> {
+  return invoke("toggle_pin", { id });
+}
+
+// Favorites, separate from pins; stars follow renames and moves
+export async function starNote(id: string): Promise<void> {
+  return invoke("star_note", { id });
+}
+
+export async function unstarNote(id: string): Promise<void> {
+  return invoke("unstar_note", { id });
+}
+
+// Most recently starred first
+export async function listStarred(): Promise<NoteMetadata[]> {
+  return invoke("list_starred");
 }
 
 export async function readNote(id: string): Promise<Note> {
@@ -21,14 +141,185 @@ export async function saveNote(id: string | null, content: string): Promise<Note
   return invoke("save_note", { id, content });
 }
 
-export async function deleteNote(id: string): Promise<void> {
-  return invoke("delete_note", { id });
+export interface SaveConflict {
+  id: string;
+  diskContent: string | null; // null if the file was deleted
+  diskModified: number | null;
+  diskHash: string | null; // pass to saveNoteChecked to overwrite it
+}
+
+export type SaveResult =
+  | ({ status: "saved" } & Note)
+  | ({ status: "conflict" } & SaveConflict);
+
+// SHA-256 of note content, hex-encoded, as saveNoteChecked expects
+export async function contentHash(content: string): Promise<string> {
+  const bytes = new TextEncoder().encode(content);
+  const digest = new Uint8Array(await crypto.subtle.digest("SHA-256", bytes));
+  return Array.from(digest, (b) => b.toString(16).padStart(2, "0")).join("");
+}
+
+// Saves only if the note on disk is still the one last loaded (`expectedHash`
+// is contentHash of that content); otherwise returns what is on disk so the
+// user can merge, overwrite or copy
+export async function saveNoteChecked(
+  id: string,
+  content: string,
+  expectedHash: string,
+): Promise<SaveResult> {
+  return invoke("save_note", { id, content, expectedHash });
+}
+
+export interface Backlink {
+  id: string;
+  title: string;
+  // Lines with the link; empty for locked notes whose content is hidden
+  lines: { line: number; text: string }[];
+}
+
+// Notes linking to a note, sorted by title
+export async function getBacklinks(id: string): Promise<Backlink[]> {
+  return invoke("get_backlinks", { id });
+}
+
+// What to do with links to a deleted note; leaving it out keeps them as-is
+export type LinkRewrite =
+  | { action: "strip" }
+  | { action: "redirect"; to: string };
+
+export interface DeleteImpact {
+  id: string;
+  linkCount: number;
+  notes: { id: string; title: string; links: number }[];
+}
+
+export async function getDeleteImpact(id: string): Promise<DeleteImpact> {
+  return invoke("get_delete_impact", { id });
+}
+
+export async function deleteNote(
+  id: string,
+  rewriteLinks?: LinkRewrite
+): Promise<void> {
+  return invoke("delete_note", { id, rewriteLinks });
+}
+
+export interface NoteRename {
+  note: Note;
+  updatedNotes: string[]; // notes whose links were rewritten
+}
+
+// Sets the title heading, renames the file to match (with a "-n" suffix if
+// taken) and points links in other notes at the new name
+export async function renameNote(id: string, newTitle: string): Promise<NoteRename> {
+  return invoke("rename_note", { id, newTitle });
+}
+
+export interface TrashItem {
+  entry: string; // pass to restoreNote
+  noteId: string;
+  deletedAt: number; // unix seconds
+  hasNote: boolean; // false for entries holding only assets
+  assets: string[];
+}
+
+// Most recently deleted first
+export async function listTrash(): Promise<TrashItem[]> {
+  return invoke("list_trash");
+}
+
+// Returns the restored note (under a "-n" name if its old one is taken), or
+// null for an entry holding only assets
+export async function restoreNote(entry: string): Promise<Note | null> {
+  return invoke("restore_note", { entry });
+}
+
+export async function emptyTrash(): Promise<number> {
+  return invoke("empty_trash");
+}
+
+export interface RestorePoint {
+  id: string; // pass to rollbackTo
+  name: string;
+  createdAt: number; // unix seconds
+  notes: number;
+  bytes: number;
+}
+
+export interface RollbackReport {
+  restored: number; // notes rewritten from the point
+  trashed: string[]; // IDs of notes created since, moved to the trash
+  safetyPoint: RestorePoint; // the state just before rolling back
+}
+
+export async function createRestorePoint(name: string): Promise<RestorePoint> {
+  return invoke("create_restore_point", { name });
+}
+
+// Newest first; imports and folder deletes take one automatically
+export async function listRestorePoints(): Promise<RestorePoint[]> {
+  return invoke("list_restore_points");
+}
+
+export async function rollbackTo(point: string): Promise<RollbackReport> {
+  return invoke("rollback_to", { point });
+}
+
+export interface NoteVersion {
+  ts: number; // unix milliseconds
+  size: number;
+}
+
+// Snapshots taken on each save, newest first
+export async function listNoteVersions(id: string): Promise<NoteVersion[]> {
+  return invoke("list_note_versions", { id });
+}
+
+export async function readNoteVersion(id: string, ts: number): Promise<string> {
+  return invoke("read_note_version", { id, ts });
+}
+
+export interface HistoryStats {
+  notes: number;
+  versions: number;
+  fullVersions: number; // the rest are stored as deltas
+  storedBytes: number;
+  contentBytes: number; // what the versions would take stored whole
+  drafts: number;
+  draftBytes: number;
+}
+
+export async function getHistoryStorageStats(): Promise<HistoryStats> {
+  return invoke("get_history_storage_stats");
 }
 
 export async function createNote(targetFolder?: string): Promise<Note> {
   return invoke("create_note", { targetFolder: targetFolder ?? null });
 }
 
+// The backend reads text, images and file lists itself; the webview passes
+// the HTML flavour when it can read it. The new note is selected via
+// "select-note".
+export async function createNoteFromClipboard(
+  targetFolder?: string,
+): Promise<Note> {
+  let html: string | null = null;
+  try {
+    for (const item of await navigator.clipboard.read()) {
+      if (item.types.includes("text/html")) {
+        html = await (await item.getType("text/html")).text();
+        break;
+      }
+    }
+  } catch {
+    // Clipboard access denied or unsupported; fall back to the backend
+  }
+  return invoke("create_note_from_clipboard", {
+    html,
+    targetFolder: targetFolder ?? null,
+  });
+}
+
 export async function listFolders(): Promise<string[]> {
   return invoke("list_folders");
 }
@@ -49,6 +340,33 @@ export async function moveNote(id: string, targetFolder: string): Promise<string
   return invoke("move_note", { id, targetFolder });
 }
 
+export type AuditSurface = "ui" | "cli" | "http" | "sync";
+
+export interface AuditEntry {
+  time: number; // unix seconds
+  surface: AuditSurface;
+  action: "create" | "save" | "rename" | "move" | "append" | "delete" | "restore";
+  vault: string;
+  noteId: string;
+  newId?: string; // after a rename or move
+  oldHash: string | null;
+  newHash: string | null;
+}
+
+export interface AuditFilter {
+  noteId?: string; // matches the old or new ID
+  surface?: AuditSurface;
+  vault?: string;
+  since?: number;
+  until?: number;
+  limit?: number; // default 500
+}
+
+// Recorded note changes, newest first
+export async function getAuditLog(filter?: AuditFilter): Promise<AuditEntry[]> {
+  return invoke("get_audit_log", { filter: filter ?? null });
+}
+
 export async function moveFolder(path: string, targetParent: string): Promise<void> {
   return invoke("move_folder", { path, targetParent });
 }
@@ -82,6 +400,59 @@ export async function updateGitEnabled(
   });
 }
 
+export async function exportSettings(path: string): Promise<void> {
+  return invoke("export_settings", { path });
+}
+
+// JSON dump of notes (title, preview, tags, links, tasks), tag counts, the
+// link graph and totals, for external tooling
+export async function exportVaultMetadata(path: string): Promise<void> {
+  return invoke("export_vault_metadata", { path });
+}
+
+// Seeds the notes cache from a dump; returns how many unchanged notes it held
+export async function importVaultMetadata(path: string): Promise<number> {
+  return invoke("import_vault_metadata", { path });
+}
+
+export interface VaultZipOptions {
+  excludeTrash?: boolean;
+  excludeHistory?: boolean;
+}
+
+export interface VaultZipProgress {
+  current: number;
+  total: number;
+  path: string; // vault-relative, the last file packed
+}
+
+export interface VaultZipReport {
+  destination: string;
+  files: number;
+  bytes: number;
+}
+
+// Packs every file in the vault into a zip; listen for "vault-export-progress"
+// (VaultZipProgress) to follow along
+export async function exportVaultZip(
+  destination: string,
+  options?: VaultZipOptions
+): Promise<VaultZipReport> {
+  return invoke("export_vault_zip", { destination, options });
+}
+
+export async function importSettings(path: string): Promise<Settings> {
+  return invoke("import_settings", { path });
+}
+
+export async function listThemes(): Promise<ThemeInfo[]> {
+  return invoke("list_themes");
+}
+
+export async function getTheme(name: string): Promise<ThemeFile> {
+  return invoke("get_theme", { name });
+}
+
 export interface SearchResult {
   id: string;
   title: string;
@@ -94,6 +465,1062 @@ export async function searchNotes(query: string): Promise<SearchResult[]> {
   return invoke("search_notes", { query });
 }
 
+export interface VaultLanguages {
+  dominant: string | null;
+  languages: { code: string; notes: number; share: number }[]; // most notes first
+  undetected: number;
+  searchLanguage: string | null; // stemmer the search index uses
+}
+
+export async function getVaultLanguages(): Promise<VaultLanguages> {
+  return invoke("get_vault_languages");
+}
+
+export async function generateVaultReport(): Promise<Note> {
+  return invoke("generate_vault_report");
+}
+
+export async function findOrphanNotes(): Promise<NoteMetadata[]> {
+  return invoke("find_orphan_notes");
+}
+
+export async function listTags(): Promise<{ tag: string; count: number }[]> {
+  return invoke("list_tags");
+}
+
+// Includes nested tags: "project" also matches "project/alpha"
+export async function listNotesByTag(tag: string): Promise<NoteMetadata[]> {
+  return invoke("list_notes_by_tag", { tag });
+}
+
+export interface RandomNoteFilter {
+  folder?: string;
+  tag?: string;
+  olderThanDays?: number;
+}
+
+export async function getRandomNote(
+  filter?: RandomNoteFilter
+): Promise<NoteMetadata | null> {
+  return invoke("get_random_note", { filter });
+}
+
+export interface ReviewItem {
+  id: string;
+  title: string;
+  lastReviewed: string | null;
+  intervalDays: number;
+  due: string;
+  overdueDays: number;
+}
+
+export async function getReviewQueue(): Promise<ReviewItem[]> {
+  return invoke("get_review_queue");
+}
+
+// Resolves e.g. "next tuesday" or "in 3 weeks" to YYYY-MM-DD; rejects
+// anything it does not understand
+export async function parseNaturalDate(
+  text: string,
+  reference?: string
+): Promise<string> {
+  return invoke("parse_natural_date", { text, reference });
+}
+
+// `location` ("lat, lon", see getCurrentLocation) is stamped into a new
+// note when the dailyContext setting asks for it
+export async function openDailyNote(
+  date?: string,
+  location?: string
+): Promise<Note> {
+  return invoke("open_daily_note", { date, location });
+}
+
+// Appends to this month's note of the log (`<log>-YYYY-MM`) and returns it;
+// the note `log` indexes the series
+export async function appendLogEntry(log: string, entry: string): Promise<Note> {
+  return invoke("append_log_entry", { log, entry });
+}
+
+export interface RoutineRun {
+  date: string;
+  dailyNoteId: string;
+  created: string[];
+}
+
+export interface RoutineStatus {
+  sourceId: string;
+  text: string;
+  every: string;
+  dueToday: boolean;
+  generated: string[]; // YYYY-MM-DD
+  completed: string[];
+}
+
+export async function materializeRoutines(date?: string): Promise<RoutineRun> {
+  return invoke("materialize_routines", { date });
+}
+
+export async function getRoutines(): Promise<RoutineStatus[]> {
+  return invoke("get_routines");
+}
+
+export interface HabitData {
+  habit: string;
+  from: string;
+  to: string;
+  days: { date: string; status: "done" | "missed" | "untracked" }[];
+  done: number;
+  missed: number;
+  currentStreak: number;
+  longestStreak: number;
+}
+
+// Range bounds may be YYYY-MM-DD or natural ("30 days ago"); defaults to the
+// last 30 days
+export async function getHabitData(
+  habit: string,
+  range?: { from: string; to: string }
+): Promise<HabitData> {
+  return invoke("get_habit_data", { habit, range });
+}
+
+export interface GoalProgress {
+  name: string;
+  area: string | null;
+  quarter: string | null;
+  progress: number; // 0..1
+  measurements: number;
+  notes: string[];
+}
+
+export interface GoalRollupGroup {
+  key: string;
+  goals: number;
+  progress: number;
+}
+
+export interface GoalRollup {
+  goals: GoalProgress[];
+  byQuarter: GoalRollupGroup[];
+  byArea: GoalRollupGroup[];
+  overall: number;
+}
+
+export async function getGoalProgress(): Promise<GoalRollup> {
+  return invoke("get_goal_progress");
+}
+
+export interface ProjectDashboard {
+  project: string;
+  kind: "folder" | "tag";
+  noteCount: number;
+  openTasks: {
+    noteId: string;
+    noteTitle: string;
+    line: number;
+    text: string;
+    due: string | null;
+  }[];
+  recentNotes: NoteMetadata[];
+  upcoming: { date: string; label: string; noteId: string }[];
+  people: { id: string; name: string; mentions: number }[];
+}
+
+// `project` is a tag ("#launch") or a folder path ("Projects/Launch")
+export async function getProjectDashboard(
+  project: string
+): Promise<ProjectDashboard> {
+  return invoke("get_project_dashboard", { project });
+}
+
+export interface Ingredient {
+  text: string;
+  quantity: number | null; // upper end of a range
+  unit: string | null;
+  item: string;
+  group: string | null; // subheading inside Ingredients
+}
+
+export interface Recipe {
+  schema: "recipe";
+  title: string;
+  servings: string | null;
+  prepTime: string | null;
+  cookTime: string | null;
+  totalTime: string | null;
+  source: string | null;
+  ingredients: Ingredient[];
+  steps: string[];
+  notes: string[];
+}
+
+export interface Book {
+  schema: "book";
+  title: string;
+  author: string | null;
+  isbn: string | null;
+  year: string | null;
+  pages: string | null;
+  status: string | null;
+  progress: string | null;
+  rating: number | null;
+  started: string | null;
+  finished: string | null;
+  summary: string | null;
+  quotes: string[];
+  takeaways: string[];
+}
+
+export type StructuredContent = Recipe | Book;
+
+// Without a schema, the note's `type:` frontmatter or tags pick one
+export async function getStructuredContent(
+  id: string,
+  schema?: StructuredContent["schema"]
+): Promise<StructuredContent> {
+  return invoke("get_structured_content", { id, schema });
+}
+
+export interface NoteLocation {
+  id: string;
+  title: string;
+  location: string; // as written
+  latitude: number | null;
+  longitude: number | null; // both null for a place not (yet) geocoded
+  geocoded: boolean; // from a place name rather than coordinates
+}
+
+export async function getNotesWithLocations(): Promise<NoteLocation[]> {
+  return invoke("get_notes_with_locations");
+}
+
+// `location` is "lat, lon" or a place name
+export async function setNoteLocation(
+  id: string,
+  location: string
+): Promise<Note> {
+  return invoke("set_note_location", { id, location });
+}
+
+// The device's position as "lat, lon", from the webview's geolocation API
+export function getCurrentLocation(): Promise<string> {
+  return new Promise((resolve, reject) => {
+    if (!navigator.geolocation) {
+      reject(new Error("Location is not available on this device"));
+      return;
+    }
+    navigator.geolocation.getCurrentPosition(
+      (position) =>
+        resolve(
+          `${position.coords.latitude.toFixed(5)}, ${position.coords.longitude.toFixed(5)}`
+        ),
+      (error) => reject(new Error(error.message || "Could not get the location")),
+      { enableHighAccuracy: true, timeout: 15000 }
+    );
+  });
+}
+
+export type ReadingStatus = "to-read" | "reading" | "finished" | "abandoned";
+
+export interface ReadingItem {
+  id: string;
+  title: string;
+  author: string | null;
+  status: ReadingStatus;
+  page: number | null;
+  pages: number | null;
+  percent: number | null; // 0-100
+  started: string | null;
+  finished: string | null;
+  modified: number;
+}
+
+export interface ReadingList {
+  reading: ReadingItem[];
+  toRead: ReadingItem[];
+  finished: ReadingItem[];
+  abandoned: ReadingItem[];
+  finishedThisYear: number;
+}
+
+export async function getReadingList(): Promise<ReadingList> {
+  return invoke("get_reading_list");
+}
+
+// `progress` is a page ("120"), a page of a total ("120/350") or "35%"
+export async function updateReadingProgress(
+  id: string,
+  progress: string
+): Promise<ReadingItem> {
+  return invoke("update_reading_progress", { id, progress });
+}
+
+export async function setReadingStatus(
+  id: string,
+  status: ReadingStatus
+): Promise<ReadingItem> {
+  return invoke("set_reading_status", { id, status });
+}
+
+export interface CodeAnnotation {
+  noteId: string;
+  noteTitle: string;
+  line: number; // 0-based
+  marker: "TODO" | "FIXME" | "HACK";
+  text: string;
+  language: string | null; // null for inline code or a block without one
+  inline: boolean;
+}
+
+export async function listCodeAnnotations(): Promise<CodeAnnotation[]> {
+  return invoke("list_code_annotations");
+}
+
+export async function markReviewed(id: string): Promise<Note> {
+  return invoke("mark_reviewed", { id });
+}
+
+export interface CardSchedule {
+  ease: number;
+  intervalDays: number;
+  repetitions: number;
+  due: string;
+  lastReviewed: string | null;
+}
+
+export interface DueCard {
+  id: string;
+  noteId: string;
+  question: string;
+  answer: string;
+  schedule: CardSchedule;
+}
+
+export async function getDueCards(): Promise<DueCard[]> {
+  return invoke("get_due_cards");
+}
+
+export async function gradeCard(id: string, grade: number): Promise<CardSchedule> {
+  return invoke("grade_card", { id, grade });
+}
+
+export interface AnkiScope {
+  folder?: string;
+  tag?: string;
+  noteIds?: string[];
+  deck?: string; // top-level deck, default "Scratch"
+}
+
+export interface AnkiExport {
+  path: string;
+  cards: number;
+  notes: number;
+  mediaDir: string | null; // copy its files into Anki's collection.media
+  mediaCopied: number;
+}
+
+// Writes an Anki text import (tab-separated, HTML fields) outside the vault
+export async function exportAnki(path: string, scope?: AnkiScope): Promise<AnkiExport> {
+  return invoke("export_anki", { path, scope });
+}
+
+export interface AudioExport {
+  path: string;
+  engine: string;
+  characters: number;
+}
+
+// Reads the note aloud into an audio file outside the vault (AIFF on macOS,
+// WAV elsewhere when the path has no extension)
+export async function exportNoteAudio(
+  id: string,
+  path: string,
+  voice?: string,
+): Promise<AudioExport> {
+  return invoke("export_note_audio", { id, path, voice });
+}
+
+// Replaces the built-in speech engine on this machine, e.g.
+// "espeak-ng -v {voice} -f {input} -w {output}"; kept out of the vault
+export async function getTtsCommand(): Promise<string | null> {
+  return invoke("get_tts_command");
+}
+
+export async function setTtsCommand(command: string | null): Promise<void> {
+  return invoke("set_tts_command", { command });
+}
+
+export interface SlideExport {
+  path: string;
+  slides: number;
+  images: number; // embedded from the vault
+}
+
+// Writes the note as a Reveal.js deck outside the vault; slides break at
+// `---` lines and H2 headings
+export async function exportSlides(id: string, path: string): Promise<SlideExport> {
+  return invoke("export_slides", { id, path });
+}
+
+export type TranslationProvider = "deepl" | "libreTranslate" | "openAi";
+
+export interface NoteTranslation {
+  content: string;
+  note: Note | null; // the saved copy, when save was set
+}
+
+export async function translateNote(
+  id: string,
+  targetLang: string,
+  provider: TranslationProvider,
+  save?: boolean,
+): Promise<NoteTranslation> {
+  return invoke("translate_note", { id, targetLang, provider, save });
+}
+
+// Stores the provider's API key in the OS keychain; an empty key removes it
+export async function setTranslationKey(
+  provider: TranslationProvider,
+  key: string | null,
+): Promise<void> {
+  return invoke("set_translation_key", { provider, key });
+}
+
+export interface ArchivedPage {
+  url: string;
+  path: string; // vault-relative .mhtml file in assets/archives/
+  title: string | null;
+  resources: number; // stylesheets and images saved with the page
+  bytes: number;
+}
+
+// Saves the page as MHTML; with a note ID, the note records it as `archive`
+export async function archiveUrl(
+  url: string,
+  noteId?: string,
+): Promise<ArchivedPage> {
+  return invoke("archive_url", { url, noteId });
+}
+
+export interface Reference {
+  key: string;
+  kind: string; // entry type: article, book, ...
+  title: string | null;
+  authors: string[]; // "First Last"
+  year: string | null;
+  container: string | null; // journal, book or proceedings
+  doi: string | null;
+  url: string | null;
+  literatureNote: string | null; // ID of the note for it, if there is one
+}
+
+export async function searchReferences(query: string): Promise<Reference[]> {
+  return invoke("search_references", { query });
+}
+
+// Citation text for the key, rendered with the citationFormat setting
+export async function insertCitation(key: string): Promise<string> {
+  return invoke("insert_citation", { key });
+}
+
+// Returns the existing literature note when the reference already has one
+export async function createLiteratureNote(key: string): Promise<Note> {
+  return invoke("create_literature_note", { key });
+}
+
+export interface CodeRun {
+  output: string; // stdout and stderr as they arrived
+  exitCode: number | null; // null when killed
+  timedOut: boolean;
+  truncated: boolean; // output past 256 KB was dropped
+  sandboxed: boolean;
+  durationMs: number;
+}
+
+// Payload of `code-block-output`, emitted while a block runs
+export interface CodeBlockOutput {
+  id: string;
+  blockIndex: number;
+  stream: "stdout" | "stderr";
+  text: string;
+}
+
+// Kept in the app config on this machine, not in the vault
+export async function getCodeBlockSettings(): Promise<CodeBlockSettings> {
+  return invoke("get_code_block_settings");
+}
+
+export async function setCodeBlockSettings(
+  settings: CodeBlockSettings,
+): Promise<void> {
+  return invoke("set_code_block_settings", { settings });
+}
+
+// Blocks are counted from 0, leaving out `output` blocks
+export async function runCodeBlock(
+  id: string,
+  blockIndex: number,
+  writeResult?: boolean,
+): Promise<CodeRun> {
+  return invoke("run_code_block", { id, blockIndex, writeResult });
+}
+
+export interface ReadingPosition {
+  offset: number;
+  scrollRatio: number;
+  updated: number;
+}
+
+export interface Annotation {
+  id: string;
+  start: number;
+  end: number;
+  quote: string;
+  comment: string | null;
+  created: number;
+}
+
+export interface NoteAnnotations {
+  readingPosition: ReadingPosition | null;
+  annotations: Annotation[];
+}
+
+export async function getAnnotations(id: string): Promise<NoteAnnotations> {
+  return invoke("get_annotations", { id });
+}
+
+export async function addAnnotation(
+  id: string,
+  start: number,
+  end: number,
+  comment?: string
+): Promise<Annotation> {
+  return invoke("add_annotation", { id, start, end, comment });
+}
+
+export async function removeAnnotation(
+  id: string,
+  annotationId: string
+): Promise<void> {
+  return invoke("remove_annotation", { id, annotationId });
+}
+
+export async function setReadingPosition(
+  id: string,
+  offset: number,
+  scrollRatio: number
+): Promise<void> {
+  return invoke("set_reading_position", { id, offset, scrollRatio });
+}
+
+export interface ExportResult {
+  profile: string;
+  outputDir: string;
+  exported: number;
+  assetsCopied: number;
+  finishedAt: number;
+}
+
+export async function runExportProfile(name: string): Promise<ExportResult> {
+  return invoke("run_export_profile", { name });
+}
+
+export interface AutomationRun {
+  startedAt: number;
+  finishedAt: number;
+  success: boolean;
+  message: string;
+}
+
+export interface AutomationStatus {
+  name: string;
+  trigger: AutomationTrigger;
+  enabled: boolean;
+  lastRun: AutomationRun | null;
+  nextRun: number | null;
+}
+
+export async function getAutomationStatus(): Promise<AutomationStatus[]> {
+  return invoke("get_automation_status");
+}
+
+export async function runAutomation(name: string): Promise<AutomationRun> {
+  return invoke("run_automation", { name });
+}
+
+export interface InboxOutcome {
+  id: string; // as it was in the inbox
+  rules: string[];
+  noteId: string | null; // null once merged
+  mergedInto: string | null;
+  error: string | null;
+}
+
+export interface InboxRun {
+  processed: InboxOutcome[];
+  unmatched: number;
+}
+
+export async function runInboxRules(): Promise<InboxRun> {
+  return invoke("run_inbox_rules");
+}
+
+export interface RetainedNote {
+  id: string;
+  action: RetentionAction;
+  reason: string;
+  newId: string | null; // where an archived note went
+  error: string | null;
+}
+
+// Emitted as "retention-applied" when the hourly run archives or trashes notes
+export interface RetentionRun {
+  ranAt: number;
+  dryRun: boolean;
+  notes: RetainedNote[];
+}
+
+export async function applyRetention(dryRun?: boolean): Promise<RetentionRun> {
+  return invoke("apply_retention", { dryRun });
+}
+
+export async function getRetentionReport(): Promise<RetentionRun[]> {
+  return invoke("get_retention_report");
+}
+
+export interface ShareLink {
+  url: string;
+  key: string;
+  expiresAt: number;
+}
+
+export async function shareNoteLink(id: string, ttl?: number): Promise<ShareLink> {
+  return invoke("share_note_link", { id, ttl });
+}
+
+// Stores the share endpoint's token in the OS keychain; an empty token
+// removes it
+export async function setShareToken(token: string | null): Promise<void> {
+  return invoke("set_share_token", { token });
+}
+
+export async function revokeShare(id: string): Promise<number> {
+  return invoke("revoke_share", { id });
+}
+
+// Live co-editing (experimental). Remote edits arrive as "collab-content"
+// events; a guest receives "collab-ended" when the host goes away.
+export interface SessionInfo {
+  code: string;
+  isHost: boolean;
+  noteId: string | null;
+  content: string;
+}
+
+export async function hostSession(noteId: string): Promise<SessionInfo> {
+  return invoke("host_session", { noteId });
+}
+
+export async function joinSession(code: string): Promise<SessionInfo> {
+  return invoke("join_session", { code });
+}
+
+export async function updateSessionContent(content: string): Promise<void> {
+  return invoke("update_session_content", { content });
+}
+
+export async function getSession(): Promise<SessionInfo | null> {
+  return invoke("get_session");
+}
+
+export async function leaveSession(): Promise<void> {
+  return invoke("leave_session");
+}
+
+export interface ServeInfo {
+  url: string;
+  readonly: boolean;
+  auth: boolean;
+  lan: boolean;
+}
+
+// Writable servers need a username and password; `lan` also listens on the
+// local network rather than only this machine
+export async function serveVault(
+  port: number,
+  options?: { readonly?: boolean; username?: string; password?: string; lan?: boolean }
+): Promise<ServeInfo> {
+  return invoke("serve_vault", { port, ...options });
+}
+
+export async function stopServingVault(): Promise<void> {
+  return invoke("stop_serving_vault");
+}
+
+export type AccessSurface = "http" | "extension";
+export type AccessScope = "readOnly" | "appendOnly" | "full";
+
+export interface AccessTokenInfo {
+  id: string;
+  label: string;
+  surface: AccessSurface;
+  scope: AccessScope;
+  createdAt: number;
+}
+
+export async function createAccessToken(
+  surface: AccessSurface,
+  scope: AccessScope,
+  label: string
+): Promise<{ token: string; info: AccessTokenInfo }> {
+  return invoke("create_access_token", { surface, scope, label });
+}
+
+export async function listAccessTokens(): Promise<AccessTokenInfo[]> {
+  return invoke("list_access_tokens");
+}
+
+export async function revokeAccessToken(id: string): Promise<void> {
+  return invoke("revoke_access_token", { id });
+}
+
+export interface AssetRename {
+  path: string;
+  updatedNotes: string[];
+}
+
+export async function renameAsset(
+  oldPath: string,
+  newName: string
+): Promise<AssetRename> {
+  return invoke("rename_asset", { oldPath, newName });
+}
+
+// Saves the bytes as an attachment; resolves to the markdown that embeds
+// (images) or links it
+export async function importAsset(
+  bytes: Uint8Array,
+  suggestedName: string,
+  noteId?: string
+): Promise<string> {
+  return invoke("import_asset", {
+    bytes: Array.from(bytes),
+    suggestedName,
+    noteId,
+  });
+}
+
+export interface AssetInfo {
+  path: string; // vault-relative
+  size: number;
+  modified: number;
+  image: boolean;
+  meta: AttachmentMeta | null; // from its metadata sidecar
+}
+
+export async function listAssets(): Promise<AssetInfo[]> {
+  return invoke("list_assets");
+}
+
+export async function findOrphanedAssets(): Promise<AssetInfo[]> {
+  return invoke("find_orphaned_assets");
+}
+
+// Moves those of `paths` still unreferenced into the trash; resolves to the
+// paths moved
+export async function deleteOrphanedAssets(paths: string[]): Promise<string[]> {
+  return invoke("delete_orphaned_assets", { paths });
+}
+
+// Kept in `<file>.meta.json` beside an attachment; described attachments show
+// up in search, tags and backlinks under `.attachments/<path>` IDs
+export interface AttachmentMeta {
+  title?: string;
+  tags?: string[];
+  notes?: string;
+}
+
+export const ATTACHMENT_ID_PREFIX = ".attachments/";
+
+// The vault-relative attachment path behind an `.attachments/` ID, if it is one
+export function attachmentPathFromId(id: string): string | null {
+  return id.startsWith(ATTACHMENT_ID_PREFIX)
+    ? id.slice(ATTACHMENT_ID_PREFIX.length) || null
+    : null;
+}
+
+export async function getAttachmentMeta(
+  path: string
+): Promise<AttachmentMeta | null> {
+  return invoke("get_attachment_meta", { path });
+}
+
+// Empty metadata removes the sidecar; resolves to what was stored
+export async function setAttachmentMeta(
+  path: string,
+  meta: AttachmentMeta
+): Promise<AttachmentMeta | null> {
+  return invoke("set_attachment_meta", { path, meta });
+}
+
+// Saves the clipboard image (or `base64Data`, when the paste event carried
+// one) as an attachment; resolves to the markdown image embed
+export async function saveClipboardImage(
+  noteId: string | null,
+  base64Data?: string
+): Promise<string> {
+  return invoke("save_clipboard_image", { noteId, base64Data });
+}
+
+// The vault-relative path in a markdown embed or link from the backend
+export function assetPathFromMarkdown(markdown: string): string {
+  const start = markdown.lastIndexOf("](");
+  return decodeURIComponent(markdown.slice(start + 2, -1));
+}
+
+export type CaptureMode = "region" | "window" | "screen";
+
+// Runs the OS screenshot tool; resolves to a markdown image embed, or null
+// when the capture was cancelled
+export async function captureScreenshot(
+  noteId: string | null,
+  mode: CaptureMode = "region",
+): Promise<string | null> {
+  return invoke("capture_screenshot", { noteId, mode });
+}
+
+export type VaultIssueKind =
+  | "unreadable"
+  | "emptyNote"
+  | "missingFromIndex"
+  | "staleIndexEntry"
+  | "indexUnavailable"
+  | "staleCacheEntry"
+  | "danglingTrashEntry"
+  | "brokenSidecar"
+  | "orphanedAnnotations";
+
+export interface VaultIssue {
+  kind: VaultIssueKind;
+  path: string;
+  detail: string;
+  repaired: boolean;
+}
+
+export interface VaultReport {
+  checkedNotes: number;
+  issues: VaultIssue[];
+  repaired: boolean;
+}
+
+export async function verifyVault(repair = false): Promise<VaultReport> {
+  return invoke("verify_vault", { repair });
+}
+
+export interface LatencyStats {
+  samples: number;
+  minMs: number;
+  medianMs: number;
+  p95Ms: number;
+  maxMs: number;
+}
+
+export interface BenchmarkReport {
+  noteCount: number;
+  totalBytes: number;
+  startupMs: number | null; // process start to end of app setup
+  scanMs: number;
+  indexBuildMs: number;
+  search: LatencyStats | null;
+  watcher: LatencyStats | null; // null if no watcher event arrived
+}
+
+// Takes a few seconds on large vaults; leaves the live index and notes alone
+export async function benchmarkVault(): Promise<BenchmarkReport> {
+  return invoke("benchmark_vault");
+}
+
+export interface VolumeSpace {
+  volume: "vault" | "appData";
+  path: string;
+  availableBytes: number;
+  level: "ok" | "low" | "critical";
+}
+
+export async function getDiskSpace(): Promise<VolumeSpace[]> {
+  return invoke("get_disk_space");
+}
+
+export interface CacheUsage {
+  entries: number;
+  bytes: number; // estimated
+  budgetBytes: number;
+}
+
+export interface MemoryUsage {
+  notesCache: CacheUsage;
+  linkIndex: CacheUsage; // empty when not built or over budget
+  debounceEntries: number;
+  debounceLimit: number;
+  residentBytes: number | null; // whole process; Linux only
+}
+
+export async function getMemoryUsage(): Promise<MemoryUsage> {
+  return invoke("get_memory_usage");
+}
+
+export interface AutosaveStatus {
+  id: string;
+  revision: number;
+  persistedRevision: number | null;
+  pending: boolean;
+}
+
+// With expectedHash (see contentHash), a note changed on disk meanwhile isn't
+// overwritten: the content stays pending and "autosave-conflict" carries a
+// SaveConflict with what is on disk
+export async function autosaveNote(
+  id: string,
+  content: string,
+  expectedHash?: string
+): Promise<AutosaveStatus> {
+  return invoke("autosave_note", {
+    id,
+    content,
+    expectedHash: expectedHash ?? null,
+  });
+}
+
+export async function flushAutosave(id?: string): Promise<AutosaveStatus[]> {
+  return invoke("flush_autosave", { id: id ?? null });
+}
+
+// Payload of the "note-stats" event sent after each autosave write
+export interface NoteStats {
+  id: string;
+  words: number; // body only, frontmatter aside
+  characters: number;
+  charactersNoSpaces: number;
+  lines: number;
+  paragraphs: number;
+  revision: number; // the revision these counts are for
+  pending: boolean; // newer edits are waiting to be written
+  savedAt: number; // unix seconds
+}
+
+export interface DraftInfo {
+  id: string;
+  title: string;
+  savedAt: number;
+}
+
+export interface RecoveryReport {
+  drafts: DraftInfo[];
+  tempFilesRemoved: number;
+  tempFilesRestored: string[];
+}
+
+export interface InstanceStatus {
+  notesFolder: string;
+  owned: boolean;
+  otherInstance: {
+    hostname: string;
+    pid: number;
+    startedAt: number;
+    heartbeat: number;
+  } | null;
+  remoteInstance: boolean;
+  recoveredFromCrash: boolean;
+  recovery: RecoveryReport | null;
+}
+
+export async function getInstanceStatus(): Promise<InstanceStatus | null> {
+  return invoke("get_instance_status");
+}
+
+// True when the app was launched in read-only safe mode (--safe-mode,
+// SCRATCH_SAFE_MODE=1 or Shift held at startup); writing commands then fail
+// with permissionDenied
+export async function getSafeMode(): Promise<boolean> {
+  return invoke("get_safe_mode");
+}
+
+export async function listDrafts(): Promise<DraftInfo[]> {
+  return invoke("list_drafts");
+}
+
+export async function restoreDraft(id: string): Promise<Note> {
+  return invoke("restore_draft", { id });
+}
+
+export async function discardDraft(id: string): Promise<void> {
+  return invoke("discard_draft", { id });
+}
+
+export interface PrivacyStatus {
+  hasPassphrase: boolean;
+  unlocked: boolean;
+  unlockedSecondsRemaining: number;
+}
+
+export async function getPrivacyStatus(): Promise<PrivacyStatus> {
+  return invoke("get_privacy_status");
+}
+
+// Sets a new passphrase, the private folders, or both (null keeps them);
+// `current` is required once a passphrase is set
+export async function setPrivacyPassphrase(
+  current: string | null,
+  passphrase: string | null,
+  privateFolders?: string[],
+): Promise<void> {
+  return invoke("set_privacy_passphrase", {
+    current,
+    passphrase,
+    privateFolders,
+  });
+}
+
+export async function unlockPrivateNotes(
+  passphrase: string,
+  minutes?: number,
+): Promise<void> {
+  return invoke("unlock_private_notes", { passphrase, minutes: minutes ?? null });
+}
+
+export async function lockPrivateNotes(): Promise<void> {
+  return invoke("lock_private_notes");
+}
+
+// Fills {{date}}, {{weekday}}, {{clipboard}}, {{selection}}, {{vault_name}},
+// {{weather}} and any registered variables; unknown ones are left as written
+export async function expandTemplate(
+  template: string,
+  selection?: string,
+): Promise<string> {
+  return invoke("expand_template", { template, selection: selection ?? null });
+}
+
+export async function registerTemplateVariable(
+  name: string,
+  value: string,
+): Promise<void> {
+  return invoke("register_template_variable", { name, value });
+}
+
+export async function unregisterTemplateVariable(name: string): Promise<boolean> {
+  return invoke("unregister_template_variable", { name });
+}
+
+export async function listTemplateVariables(): Promise<string[]> {
+  return invoke("list_template_variables");
+}
+
 export async function startFileWatcher(): Promise<void> {
   return invoke("start_file_watcher");
 }
+
+export async function stopFileWatcher(): Promise<void> {
+  return invoke("stop_file_watcher");
+}
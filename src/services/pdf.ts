@@ -2,20 +2,52 @@ import type { Editor } from "@tiptap/react";
 import { save } from "@tauri-apps/plugin-dialog";
 import { invoke } from "@tauri-apps/api/core";
 
+interface ExportStyle {
+  classes: string[];
+  css: string; // exportClassStyles snippets for those classes
+}
+
 /**
  * Triggers the native print dialog for the editor content.
  * Users can save as PDF or print to a physical printer.
  * Uses the browser's native print functionality which produces high-quality PDFs.
+ * The note's `cssclasses` frontmatter and their `exportClassStyles` apply
+ * while printing, as in HTML exports.
  *
  * @param editor - The TipTap editor instance
  * @param _noteTitle - The note title (currently unused, but kept for API consistency)
+ * @param noteId - The note's ID, to look up its export classes
  */
 export async function downloadPdf(
   editor: Editor,
-  _noteTitle: string
+  _noteTitle: string,
+  noteId?: string
 ): Promise<void> {
   if (!editor) throw new Error("Editor not available");
 
+  if (noteId) {
+    const style = await invoke<ExportStyle>("get_note_export_style", {
+      id: noteId,
+    }).catch(() => null);
+    if (style && (style.classes.length > 0 || style.css)) {
+      const root = editor.view.dom;
+      const added = style.classes.filter((c) => !root.classList.contains(c));
+      root.classList.add(...added);
+      const sheet = document.createElement("style");
+      sheet.media = "print";
+      sheet.textContent = style.css;
+      document.head.appendChild(sheet);
+      window.addEventListener(
+        "afterprint",
+        () => {
+          root.classList.remove(...added);
+          sheet.remove();
+        },
+        { once: true }
+      );
+    }
+  }
+
   // Trigger native print dialog
   // The user can choose "Save as PDF" in the print dialog
   window.print();
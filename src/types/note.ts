@@ -3,18 +3,61 @@ export interface NoteMetadata {
   title: string;
   preview: string;
   modified: number;
+  created?: number; // `created:` frontmatter, else the file's creation time, else modified
+  tags?: string[]; // inline #tags and frontmatter tags; empty for locked notes
+  language?: string | null; // `lang` frontmatter or detected ISO 639-1 code; null for locked notes
+  pinned?: boolean; // set by listNotes
+}
+
+export type NoteSort = "modified" | "title" | "created" | "size";
+
+export interface NotePage {
+  notes: NoteMetadata[];
+  total: number; // notes in the whole listing
+  offset: number;
+}
+
+// Payload of the `notes-updated` event: notes cache entries a file change touched
+export interface NotesDelta {
+  upserted: NoteMetadata[];
+  removed: string[]; // note IDs
 }
 
 export interface Note {
   id: string;
   title: string;
   content: string;
-  path: string;
+  path: string; // vault-relative, e.g. "Projects/plan.md"
   modified: number;
 }
 
 export interface ThemeSettings {
   mode: "light" | "dark" | "system";
+  customTheme?: string;
+}
+
+export interface ThemeColors {
+  bg?: string;
+  bgSecondary?: string;
+  bgMuted?: string;
+  bgEmphasis?: string;
+  text?: string;
+  textMuted?: string;
+  textInverse?: string;
+  border?: string;
+  accent?: string;
+}
+
+export interface ThemeInfo {
+  name: string;
+  format: "json" | "css";
+}
+
+export interface ThemeFile {
+  name: string;
+  lightColors?: ThemeColors;
+  darkColors?: ThemeColors;
+  css?: string;
 }
 
 export type FontFamily = "system-sans" | "serif" | "monospace";
@@ -28,19 +71,213 @@ export interface EditorFontSettings {
   lineHeight?: number; // default 1.6
 }
 
+// Exposure policy for notes marked `locked`/`encrypted` in frontmatter
+export interface LockedNotePolicy {
+  showTitles: boolean;
+  indexContent: boolean;
+}
+
+export interface TranslationSettings {
+  libreTranslateUrl?: string; // default https://libretranslate.com
+  openaiBaseUrl?: string; // default https://api.openai.com/v1
+  openaiModel?: string; // default gpt-4o-mini
+}
+
+// Frontmatter added to today's daily note when openDailyNote creates it
+export interface DailyContextStamp {
+  weather?: boolean; // from weatherUrl
+  device?: boolean;
+  location?: boolean; // the position passed to openDailyNote, else `place`
+  place?: string;
+  deviceName?: string; // instead of the machine's name
+}
+
+export interface GeocodingSettings {
+  provider?: "nominatim" | "photon"; // unset: place names stay off the map
+  url?: string; // default: the provider's public instance
+}
+
+export interface ReferenceSettings {
+  library?: string; // .bib or CSL .json file; absolute or vault-relative
+  citationFormat?: string; // `{key}` is replaced; default "[@{key}]"
+  literatureFolder?: string; // default "References"
+}
+
+export type CodeBlockLanguage = "shell" | "python" | "node";
+
+export interface CodeBlockSettings {
+  languages?: CodeBlockLanguage[]; // languages run_code_block may run; empty turns it off
+  timeoutSecs?: number; // default 30, at most 600
+  allowUnsandboxed?: boolean; // run where no OS sandbox is available
+}
+
+export type NoteRestriction = "export" | "sync" | "index";
+
+export interface NotePolicy {
+  target: string; // a folder ("inbox/") or a tag ("#private")
+  never: NoteRestriction[];
+}
+
 // Per-folder settings (stored in .scratch/settings.json)
 export interface Settings {
   theme: ThemeSettings;
   editorFont?: EditorFontSettings;
   gitEnabled?: boolean;
+  gitAutoCommit?: boolean; // commit each note save, rename and delete
   foldersEnabled?: boolean;
   pinnedNoteIds?: string[];
   textDirection?: TextDirection;
   editorWidth?: EditorWidth;
   customEditorWidthPx?: number;
   defaultNoteName?: string;
+  filenameScheme?: FilenameScheme; // default "titleSlug": the file name follows the title
+  slugStyle?: SlugStyle; // title-based file names; default "preserveCase"
+  transliterateFilenames?: boolean; // "Über μλ" -> "Uber ml"
   interfaceZoom?: number;
   ollamaModel?: string;
+  lockedNotes?: LockedNotePolicy;
+  privateFolders?: string[]; // read-only here; change with setPrivacyPassphrase
+  shareEndpoint?: string;
+  exportProfiles?: ExportProfile[];
+  exportClassStyles?: Record<string, string>; // CSS per `cssclasses` class in HTML/PDF exports
+  automations?: Automation[];
+  trashOrphanedAssets?: boolean;
+  assetLayout?: AssetLayout;
+  diskSpace?: DiskThresholds;
+  autosaveIntervalMs?: number;
+  // "memory" keeps the search index (and notes cache) out of app data,
+  // rebuilding it on each launch
+  searchIndexMode?: "disk" | "memory";
+  // ISO 639-1 code whose stemmer and stop words search uses; "auto" (default)
+  // follows the vault's dominant language, "none" turns both off
+  searchLanguage?: string;
+  collationLocale?: string; // BCP 47 tag, e.g. "de" or "sv-SE"
+  noteSort?: NoteSort; // note list order; default "modified"
+  dailyNotesFolder?: string; // default "Daily"
+  dailyNoteFormat?: string; // file name under the folder, e.g. "%Y/%m/%Y-%m-%d"; default "%Y-%m-%d"
+  dailyTemplate?: string; // note ID of the template new daily notes start from
+  habits?: string[]; // daily-note checkboxes shown on the habit dashboard
+  weatherUrl?: string; // plain-text provider for {{weather}}, e.g. https://wttr.in/{location}?format=3
+  dailyContext?: DailyContextStamp; // stamped into new daily notes
+  memoryBudgets?: MemoryBudgets;
+  translation?: TranslationSettings; // API keys are kept in the OS keychain
+  geocoding?: GeocodingSettings; // looks up `location:` place names; off by default
+  archiveLinks?: boolean; // archive the `url`/`source` page of notes when saved or clipped
+  references?: ReferenceSettings; // BibTeX or CSL JSON library for citations
+  watcherIgnore?: string[]; // directory names the file watcher skips ("*" wildcards)
+  notePolicies?: NotePolicy[]; // folders and tags never exported, synced or indexed
+  readLimits?: ReadLimits; // bytes of each note read for the note list and the index
+  importLimits?: ImportLimits; // size and type caps on imported notes and attachments
+  inbox?: InboxSettings; // rules for notes captured into the inbox folder
+  retention?: RetentionSettings; // folder rules that archive or trash old notes
+}
+
+// Cache limits (defaults: notes cache 32 MB, link index 64 MB, 100 debounced paths)
+export interface MemoryBudgets {
+  notesCacheMb?: number;
+  linkIndexMb?: number;
+  debounceEntries?: number;
+}
+
+// Bytes read from the start of each note, or "full" for whole files
+export type ReadLimit = number | "full";
+
+export interface ReadLimits {
+  listingBytes?: ReadLimit; // title, preview, tags and language (default 1 MB)
+  indexBytes?: ReadLimit; // search index content (default 10 MB)
+}
+
+// Caps on what importers bring into the vault
+export interface ImportLimits {
+  maxFileMb?: number; // per note or attachment (default 100)
+  allowedExtensions?: string[]; // attachment types; any when unset
+  vaultWarningMb?: number; // warn when an import takes the vault past this (default 2048)
+}
+
+// Free-space levels in MB (defaults: warn below 1024, critical below 200)
+export interface DiskThresholds {
+  warnMb?: number;
+  criticalMb?: number;
+}
+
+// flat: assets/, perNote: assets/<note-id>/, noteFolder: beside the note
+export type AssetLayout = "flat" | "perNote" | "noteFolder";
+
+// Timestamp and UUID names never change; a date-prefixed slug keeps its date
+export type FilenameScheme =
+  | "titleSlug"
+  | "timestamp"
+  | "uuid"
+  | "datePrefixSlug";
+
+export type SlugStyle = "preserveCase" | "kebab" | "snake";
+
+export interface ExportProfile {
+  name: string;
+  format?: "markdown" | "html";
+  folder?: string;
+  includeTags?: string[];
+  excludeTags?: string[];
+  assets?: "copy" | "skip";
+  css?: string;
+  outputDir: string;
+}
+
+export type AutomationTrigger =
+  | { type: "interval"; minutes: number }
+  | { type: "appStart" }
+  | { type: "appQuit" };
+
+export type AutomationAction =
+  | { type: "export"; profile: string }
+  | { type: "backup"; outputDir: string; keep?: number };
+
+export interface Automation {
+  name: string;
+  trigger: AutomationTrigger;
+  action: AutomationAction;
+  enabled?: boolean;
+}
+
+// Case-insensitive regular expressions; all that are given must match
+export interface InboxMatch {
+  title?: string;
+  content?: string;
+  source?: string; // the `source` or `url` frontmatter
+}
+
+export type InboxAction =
+  | { type: "tag"; tags: string[] }
+  | { type: "template"; template: string } // note ID; `{{content}}` takes the note's body
+  | { type: "move"; folder: string }
+  | { type: "merge"; target: string }; // note ID
+
+export interface InboxRule {
+  name: string;
+  match?: InboxMatch;
+  actions: InboxAction[];
+  enabled?: boolean;
+}
+
+export interface InboxSettings {
+  folder?: string; // default "inbox"
+  rules?: InboxRule[];
+}
+
+export type RetentionAction = "archive" | "trash";
+
+// Notes under `folder` created more than `olderThanDays` ago
+export interface RetentionRule {
+  folder: string;
+  olderThanDays: number;
+  action?: RetentionAction; // default "archive"
+  enabled?: boolean;
+}
+
+export interface RetentionSettings {
+  archiveFolder?: string; // default "Archive"
+  expiredAction?: RetentionAction; // for `expires:` frontmatter; default "archive"
+  rules?: RetentionRule[];
 }
 
 export interface FolderNode {